@@ -0,0 +1,51 @@
+// Display currency requested by the storefront
+//
+// Shoppers pick the currency prices are shown in with a `currency` query
+// parameter or an `X-Currency` header (the parameter wins). Without either,
+// prices are shown in the store currency.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use goseli_core::{ApiError, Currency};
+use serde::Deserialize;
+
+pub const CURRENCY_HEADER: &str = "x-currency";
+
+#[derive(Deserialize)]
+struct CurrencyParam {
+    currency: Option<String>,
+}
+
+/// The currency a request asked prices to be shown in, if any
+#[derive(Debug, Clone, Copy)]
+pub struct RequestedCurrency(pub Option<Currency>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestedCurrency
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let param = Query::<CurrencyParam>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(param)| param.currency);
+        let header = parts
+            .headers
+            .get(CURRENCY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let currency = param
+            .or(header)
+            .map(|code| code.trim().to_string())
+            .filter(|code| !code.is_empty())
+            .map(|code| Currency::new(&code))
+            .transpose()?;
+        Ok(RequestedCurrency(currency))
+    }
+}
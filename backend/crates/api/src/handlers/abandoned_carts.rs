@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{AbandonedCartStats, PaginatedResponse, PaginationMeta, PaginationParams},
+    models::AbandonedCart,
+    Result,
+};
+use goseli_db::abandoned_carts;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// GET /api/v1/admin/abandoned-carts - List abandoned carts (admin)
+async fn list_abandoned_carts(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<AbandonedCart>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let data = abandoned_carts::list_abandoned_carts(
+        &state.pool,
+        store_id,
+        params.limit(),
+        params.offset(),
+    )
+    .await?;
+    let total = abandoned_carts::count_abandoned_carts(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// GET /api/v1/admin/abandoned-carts/stats - Recovery metrics (admin)
+async fn get_stats(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<AbandonedCartStats>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let stats = abandoned_carts::get_stats(&state.pool, store_id).await?;
+
+    Ok(Json(stats))
+}
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Mount abandoned cart admin routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/admin/abandoned-carts", get(list_abandoned_carts))
+        .route("/api/v1/admin/abandoned-carts/stats", get(get_stats))
+}
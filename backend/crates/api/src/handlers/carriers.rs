@@ -0,0 +1,92 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{CreateCarrierRequest, UpdateCarrierRequest},
+    models::Carrier,
+    Result,
+};
+use goseli_db::fulfillments;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/carriers - List the store's carriers (admin)
+async fn list_carriers(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<Carrier>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let carriers = fulfillments::list_carriers(&state.pool, store_id).await?;
+    Ok(Json(carriers))
+}
+
+/// POST /api/v1/admin/carriers - Add a carrier and its tracking URL template (admin)
+async fn create_carrier(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<CreateCarrierRequest>,
+) -> Result<(StatusCode, Json<Carrier>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let carrier = fulfillments::create_carrier(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(carrier)))
+}
+
+/// PUT /api/v1/admin/carriers/:id - Update a carrier (admin)
+async fn update_carrier(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateCarrierRequest>,
+) -> Result<Json<Carrier>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let carrier = fulfillments::update_carrier(&state.pool, id, &req).await?;
+    Ok(Json(carrier))
+}
+
+/// DELETE /api/v1/admin/carriers/:id - Delete a carrier (admin)
+async fn delete_carrier(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    fulfillments::delete_carrier(&state.pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mount carrier routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/admin/carriers",
+            get(list_carriers).post(create_carrier),
+        )
+        .route(
+            "/api/v1/admin/carriers/:id",
+            put(update_carrier).delete(delete_carrier),
+        )
+}
@@ -1,21 +1,33 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     routing::{get, post, put},
     Json, Router,
 };
-use axum_extra::extract::CookieJar;
-use goseli_auth::AuthUser;
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use goseli_auth::{validate_cart_restore_token, AuthUser};
 use goseli_core::{
-    dto::{AddToCartRequest, CartResponse, UpdateCartItemRequest},
-    Result,
+    dto::{
+        AddToCartRequest, ApplyCouponRequest, BulkCartAction, BulkCartCsvParams,
+        BulkCartLineResult, BulkCartMode, BulkCartOperation, BulkCartRequest, BulkCartResponse,
+        BulkLineStatus, CartResponse, RestoreCartRequest, SetCartEmailRequest,
+        UpdateCartItemRequest,
+    },
+    models::AbandonedCartStatus,
+    ApiError, Currency, Result,
 };
-use goseli_db::cart;
+use goseli_db::{abandoned_carts, cart, currencies, products, stores};
+use serde::Deserialize;
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::currency::RequestedCurrency;
+
 const SESSION_COOKIE_NAME: &str = "goseli_session";
 
 /// Helper to get or create a session ID from cookies
@@ -25,6 +37,26 @@ fn get_or_create_session_id(jar: &CookieJar) -> String {
         .unwrap_or_else(|| Uuid::now_v7().to_string())
 }
 
+/// Helper to (re)set the guest session cookie; signed-in customers have no
+/// session ID and get the jar back unchanged
+fn with_session_cookie(jar: CookieJar, session_id: Option<String>) -> CookieJar {
+    match session_id {
+        Some(sid) => jar.add(
+            Cookie::build((SESSION_COOKIE_NAME, sid))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .max_age(time::Duration::days(30)),
+        ),
+        None => jar,
+    }
+}
+
+/// Helper to read the session ID from cookies without minting a new one
+fn existing_session_id(jar: &CookieJar) -> Option<String> {
+    jar.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string())
+}
+
 /// Helper to get default store ID (temporary until domain-based routing)
 async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
     let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
@@ -33,49 +65,47 @@ async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
     Ok(row.0)
 }
 
-/// GET /api/v1/cart - Get current cart
+/// GET /api/v1/cart - Get current cart, priced in the requested currency
+///
+/// Carts are created when the first item is added, so a plain GET never
+/// writes to the database (crawlers without cookies would otherwise create
+/// one cart row per request).
 async fn get_cart(
     State(state): State<Arc<crate::AppState>>,
     auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
     jar: CookieJar,
-) -> Result<(CookieJar, Json<CartResponse>)> {
+) -> Result<Json<CartResponse>> {
     let store_id = get_default_store_id(&state.pool).await?;
-
-    let (user_id, session_id) = if let Some(user) = auth_user {
-        (Some(user.user_id), None)
-    } else {
-        let sid = get_or_create_session_id(&jar);
-        (None, Some(sid))
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
+
+    let Some(cart) = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref()).await?
+    else {
+        let store = stores::get_store(&state.pool, store_id).await?;
+        let pricing = currencies::display_pricing(
+            &state.pool,
+            store_id,
+            store.currency,
+            requested,
+            None,
+            &[],
+        )
+        .await?;
+        return Ok(Json(CartResponse::empty(pricing.currency())));
     };
 
-    // Get or create cart
-    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id.clone()).await?;
-
     // Get cart with enriched items
-    let cart_response = cart::get_cart_with_items(&state.pool, cart.id).await?;
-
-    // Set session cookie if guest
-    let jar = if user_id.is_none() {
-        if let Some(sid) = session_id {
-            let cookie = format!(
-                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age=2592000",
-                SESSION_COOKIE_NAME, sid
-            );
-            jar.add(axum_extra::extract::cookie::Cookie::parse(cookie).unwrap())
-        } else {
-            jar
-        }
-    } else {
-        jar
-    };
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
 
-    Ok((jar, Json(cart_response)))
+    Ok(Json(cart_response))
 }
 
 /// POST /api/v1/cart/items - Add item to cart
 async fn add_to_cart(
     State(state): State<Arc<crate::AppState>>,
     auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
     jar: CookieJar,
     Json(req): Json<AddToCartRequest>,
 ) -> Result<(CookieJar, Json<CartResponse>)> {
@@ -95,32 +125,21 @@ async fn add_to_cart(
     let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id.clone()).await?;
 
     // Add item to cart
+    let mut conn = state.pool.acquire().await?;
     cart::add_item(
-        &state.pool,
+        &mut conn,
         cart.id,
         req.product_id,
         req.variant_id,
         req.quantity,
+        req.properties.as_ref().unwrap_or(&serde_json::Value::Null),
     )
     .await?;
 
     // Get updated cart with enriched items
-    let cart_response = cart::get_cart_with_items(&state.pool, cart.id).await?;
-
-    // Set session cookie if guest
-    let jar = if user_id.is_none() {
-        if let Some(sid) = session_id {
-            let cookie = format!(
-                "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age=2592000",
-                SESSION_COOKIE_NAME, sid
-            );
-            jar.add(axum_extra::extract::cookie::Cookie::parse(cookie).unwrap())
-        } else {
-            jar
-        }
-    } else {
-        jar
-    };
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
+
+    let jar = with_session_cookie(jar, session_id);
 
     Ok((jar, Json(cart_response)))
 }
@@ -129,6 +148,7 @@ async fn add_to_cart(
 async fn update_cart_item(
     State(state): State<Arc<crate::AppState>>,
     auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
     jar: CookieJar,
     Path(item_id): Path<Uuid>,
     Json(req): Json<UpdateCartItemRequest>,
@@ -138,21 +158,20 @@ async fn update_cart_item(
 
     let store_id = get_default_store_id(&state.pool).await?;
 
-    let (user_id, session_id) = if let Some(user) = auth_user {
-        (Some(user.user_id), None)
-    } else {
-        let sid = get_or_create_session_id(&jar);
-        (None, Some(sid))
-    };
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
 
     // Get cart
-    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id).await?;
+    let cart = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Cart item not found"))?;
 
     // Update item quantity
-    cart::update_item_quantity(&state.pool, item_id, cart.id, req.quantity).await?;
+    let mut conn = state.pool.acquire().await?;
+    cart::update_item_quantity(&mut conn, item_id, cart.id, req.quantity).await?;
 
     // Get updated cart with enriched items
-    let cart_response = cart::get_cart_with_items(&state.pool, cart.id).await?;
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
 
     Ok(Json(cart_response))
 }
@@ -166,18 +185,17 @@ async fn remove_cart_item(
 ) -> Result<StatusCode> {
     let store_id = get_default_store_id(&state.pool).await?;
 
-    let (user_id, session_id) = if let Some(user) = auth_user {
-        (Some(user.user_id), None)
-    } else {
-        let sid = get_or_create_session_id(&jar);
-        (None, Some(sid))
-    };
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
 
     // Get cart
-    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id).await?;
+    let cart = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref())
+        .await?
+        .ok_or_else(|| ApiError::not_found("Cart item not found"))?;
 
     // Remove item
-    cart::remove_item(&state.pool, item_id, cart.id).await?;
+    let mut conn = state.pool.acquire().await?;
+    cart::remove_item(&mut conn, item_id, cart.id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -190,6 +208,34 @@ async fn clear_cart(
 ) -> Result<StatusCode> {
     let store_id = get_default_store_id(&state.pool).await?;
 
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
+
+    // Nothing to clear if the visitor never created a cart
+    let Some(cart) = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref()).await?
+    else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    // Clear cart
+    cart::clear_cart(&state.pool, cart.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/v1/cart/email - Attach a contact email to the cart
+async fn set_cart_email(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+    Json(req): Json<SetCartEmailRequest>,
+) -> Result<(CookieJar, Json<CartResponse>)> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
     let (user_id, session_id) = if let Some(user) = auth_user {
         (Some(user.user_id), None)
     } else {
@@ -197,13 +243,272 @@ async fn clear_cart(
         (None, Some(sid))
     };
 
-    // Get cart
-    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id).await?;
+    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id.clone()).await?;
+    cart::set_cart_email(&state.pool, cart.id, &req.email).await?;
 
-    // Clear cart
-    cart::clear_cart(&state.pool, cart.id).await?;
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
 
-    Ok(StatusCode::NO_CONTENT)
+    let jar = with_session_cookie(jar, session_id);
+
+    Ok((jar, Json(cart_response)))
+}
+
+/// POST /api/v1/cart/restore - Rebuild an abandoned cart from a recovery link
+///
+/// Items are copied into the requester's current cart, so the link works on
+/// any device and for guests as well as signed-in customers.
+async fn restore_cart(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+    Json(req): Json<RestoreCartRequest>,
+) -> Result<(CookieJar, Json<CartResponse>)> {
+    let abandoned_cart_id = validate_cart_restore_token(&req.token)?;
+    let abandoned = abandoned_carts::get_abandoned_cart(&state.pool, abandoned_cart_id).await?;
+    let source_cart_id = abandoned
+        .cart_id
+        .ok_or_else(|| ApiError::not_found("This cart is no longer available"))?;
+
+    let (user_id, session_id) = if let Some(user) = auth_user {
+        (Some(user.user_id), None)
+    } else {
+        let sid = get_or_create_session_id(&jar);
+        (None, Some(sid))
+    };
+
+    let cart =
+        cart::get_or_create_cart(&state.pool, abandoned.store_id, user_id, session_id.clone())
+            .await?;
+
+    // Following the same link twice must not double the quantities
+    let already_restored = abandoned.status == AbandonedCartStatus::Recovered
+        && abandoned.recovered_cart_id == Some(cart.id);
+
+    if source_cart_id != cart.id && !already_restored {
+        cart::copy_items(&state.pool, source_cart_id, cart.id).await?;
+    } else {
+        cart::touch_cart(&state.pool, cart.id).await?;
+    }
+
+    if cart.email.is_none() && user_id.is_none() {
+        cart::set_cart_email(&state.pool, cart.id, &abandoned.email).await?;
+    }
+
+    abandoned_carts::mark_recovered(&state.pool, abandoned.id, cart.id).await?;
+
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
+
+    let jar = with_session_cookie(jar, session_id);
+
+    Ok((jar, Json(cart_response)))
+}
+
+/// POST /api/v1/cart/bulk - Quick order: apply many add/set/remove operations
+async fn bulk_update_cart(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+    Json(req): Json<BulkCartRequest>,
+) -> Result<(StatusCode, CookieJar, Json<BulkCartResponse>)> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    apply_bulk(&state, auth_user, jar, requested, req.mode, req.operations).await
+}
+
+/// One row of a quick order CSV upload
+#[derive(Debug, Deserialize)]
+struct BulkCartCsvRow {
+    op: Option<BulkCartAction>,
+    sku: Option<String>,
+    product_id: Option<Uuid>,
+    variant_id: Option<Uuid>,
+    quantity: Option<i32>,
+}
+
+/// Read quick order operations from CSV text with a header row
+fn parse_bulk_csv(body: &str) -> Result<Vec<BulkCartOperation>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+
+    let mut operations = Vec::new();
+    for (line, row) in reader.deserialize::<BulkCartCsvRow>().enumerate() {
+        // Line 1 is the header
+        let row = row.map_err(|e| ApiError::validation(format!("CSV line {}: {}", line + 2, e)))?;
+        operations.push(BulkCartOperation {
+            op: row.op.unwrap_or_default(),
+            sku: row.sku.filter(|sku| !sku.is_empty()),
+            product_id: row.product_id,
+            variant_id: row.variant_id,
+            quantity: row.quantity.unwrap_or(0),
+            properties: None,
+        });
+    }
+
+    Ok(operations)
+}
+
+/// POST /api/v1/cart/bulk/csv - Quick order from a CSV upload
+///
+/// Expects a header row. Columns: `sku` (or `product_id` and optional
+/// `variant_id`), `quantity`, and an optional `op` (add, set, remove).
+async fn bulk_update_cart_csv(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+    Query(params): Query<BulkCartCsvParams>,
+    body: String,
+) -> Result<(StatusCode, CookieJar, Json<BulkCartResponse>)> {
+    let operations = parse_bulk_csv(&body)?;
+    let req = BulkCartRequest {
+        mode: params.mode,
+        operations,
+    };
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    apply_bulk(&state, auth_user, jar, requested, req.mode, req.operations).await
+}
+
+/// Apply bulk operations to the requester's cart.
+///
+/// SKUs are resolved up front in one query, then the operations are applied
+/// in the requested mode (see `cart::apply_bulk`).
+async fn apply_bulk(
+    state: &crate::AppState,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    requested: Option<Currency>,
+    mode: BulkCartMode,
+    operations: Vec<BulkCartOperation>,
+) -> Result<(StatusCode, CookieJar, Json<BulkCartResponse>)> {
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let (user_id, session_id) = if let Some(user) = auth_user {
+        (Some(user.user_id), None)
+    } else {
+        let sid = get_or_create_session_id(&jar);
+        (None, Some(sid))
+    };
+
+    let cart = cart::get_or_create_cart(&state.pool, store_id, user_id, session_id.clone()).await?;
+
+    let skus: Vec<String> = operations.iter().filter_map(|op| op.sku.clone()).collect();
+    let resolved = products::resolve_skus(&state.pool, store_id, &skus).await?;
+
+    let mut results: Vec<BulkCartLineResult> = operations
+        .iter()
+        .enumerate()
+        .map(|(index, op)| {
+            let (product_id, variant_id) = match &op.sku {
+                Some(sku) => resolved
+                    .get(sku)
+                    .map(|(p, v)| (Some(*p), *v))
+                    .unwrap_or((None, None)),
+                None => (op.product_id, op.variant_id),
+            };
+            BulkCartLineResult {
+                index,
+                op: op.op,
+                sku: op.sku.clone(),
+                product_id,
+                variant_id,
+                quantity: op.quantity,
+                status: BulkLineStatus::Skipped,
+                error: None,
+            }
+        })
+        .collect();
+
+    let applied = cart::apply_bulk(&state.pool, cart.id, mode, &operations, &mut results).await?;
+
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
+    let status = if applied {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+
+    let jar = with_session_cookie(jar, session_id);
+
+    Ok((
+        status,
+        jar,
+        Json(BulkCartResponse {
+            mode,
+            applied,
+            results,
+            cart: cart_response,
+        }),
+    ))
+}
+
+/// POST /api/v1/cart/coupon - Enter a coupon code
+///
+/// The code is only kept when the coupon applies to the cart; otherwise the
+/// reason is returned (`coupon_expired`, `coupon_min_subtotal`, ...).
+async fn apply_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+    Json(req): Json<ApplyCouponRequest>,
+) -> Result<Json<CartResponse>> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
+
+    let cart = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref())
+        .await?
+        .ok_or_else(|| {
+            ApiError::rule(
+                "coupon_not_applicable",
+                "Add items to the cart before entering a coupon",
+            )
+        })?;
+    let cart_response = cart::apply_coupon(&state.pool, cart.id, &req.code, requested).await?;
+
+    Ok(Json(cart_response))
+}
+
+/// DELETE /api/v1/cart/coupon - Remove the coupon code
+async fn remove_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
+    jar: CookieJar,
+) -> Result<Json<CartResponse>> {
+    let store_id = get_default_store_id(&state.pool).await?;
+    let user_id = auth_user.map(|user| user.user_id);
+    let session_id = existing_session_id(&jar);
+
+    let Some(cart) = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref()).await?
+    else {
+        let store = stores::get_store(&state.pool, store_id).await?;
+        let pricing = currencies::display_pricing(
+            &state.pool,
+            store_id,
+            store.currency,
+            requested,
+            None,
+            &[],
+        )
+        .await?;
+        return Ok(Json(CartResponse::empty(pricing.currency())));
+    };
+    cart::set_coupon_code(&state.pool, cart.id, None).await?;
+
+    let cart_response = cart::get_cart_in_currency(&state.pool, cart.id, requested).await?;
+
+    Ok(Json(cart_response))
 }
 
 /// Mount cart routes
@@ -211,8 +516,64 @@ pub fn routes() -> Router<Arc<crate::AppState>> {
     Router::new()
         .route("/api/v1/cart", get(get_cart).delete(clear_cart))
         .route("/api/v1/cart/items", post(add_to_cart))
+        .route("/api/v1/cart/bulk", post(bulk_update_cart))
+        .route("/api/v1/cart/bulk/csv", post(bulk_update_cart_csv))
+        .route(
+            "/api/v1/cart/coupon",
+            post(apply_coupon).delete(remove_coupon),
+        )
+        .route("/api/v1/cart/email", put(set_cart_email))
+        .route("/api/v1/cart/restore", post(restore_cart))
         .route(
             "/api/v1/cart/items/:id",
             put(update_cart_item).delete(remove_cart_item),
         )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bulk_csv() {
+        let product_id = Uuid::now_v7();
+        let operations = parse_bulk_csv(&format!(
+            "sku,quantity,op,product_id\n\
+             MUG-1, 3 ,,\n\
+             \"SHIRT,L\",1,set,\n\
+             ,0,remove,{product_id}\n"
+        ))
+        .unwrap();
+
+        assert_eq!(operations.len(), 3);
+        assert_eq!(operations[0].sku.as_deref(), Some("MUG-1"));
+        assert_eq!(operations[0].quantity, 3);
+        assert_eq!(operations[0].op, BulkCartAction::Add);
+        // Quoted fields may contain commas
+        assert_eq!(operations[1].sku.as_deref(), Some("SHIRT,L"));
+        assert_eq!(operations[1].op, BulkCartAction::Set);
+        // An empty SKU falls back to the product ID
+        assert_eq!(operations[2].sku, None);
+        assert_eq!(operations[2].product_id, Some(product_id));
+        assert_eq!(operations[2].op, BulkCartAction::Remove);
+    }
+
+    #[test]
+    fn test_parse_bulk_csv_columns_in_any_order() {
+        let operations = parse_bulk_csv("quantity,sku\n2,MUG-1\n").unwrap();
+        assert_eq!(operations[0].sku.as_deref(), Some("MUG-1"));
+        assert_eq!(operations[0].quantity, 2);
+
+        assert!(parse_bulk_csv("sku,quantity\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_bulk_csv_reports_bad_rows() {
+        let error = parse_bulk_csv("sku,quantity\nMUG-1,2\nMUG-2,two\n").unwrap_err();
+        assert_eq!(error.code(), "validation_error");
+        assert!(error.to_string().contains("CSV line 3"), "{error}");
+
+        let error = parse_bulk_csv("sku,quantity,op\nMUG-1,1,buy\n").unwrap_err();
+        assert!(error.to_string().contains("CSV line 2"), "{error}");
+    }
+}
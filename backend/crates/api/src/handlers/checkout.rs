@@ -0,0 +1,438 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::CookieJar;
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CartStepRequest, CheckoutSessionResponse, CheckoutStepState, CompleteCheckoutRequest,
+        ContactStepRequest, OrderResponse, PaymentStepRequest, ShippingMethodStepRequest,
+        ShippingRate, ShippingStepRequest,
+    },
+    models::{
+        customization, Cart, CheckoutSession, CheckoutSessionStatus, CheckoutSettings,
+        CheckoutStep, CheckoutStepData, OrderActor,
+    },
+    ApiError, Result,
+};
+use goseli_db::{
+    abandoned_carts, cart, checkout, gift_cards,
+    orders::{self, NewOrder},
+    stores,
+};
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use validator::Validate;
+
+const SESSION_COOKIE_NAME: &str = "goseli_session";
+
+/// How long a checkout session stays open
+const CHECKOUT_SESSION_TTL: Duration = Duration::hours(24);
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Find the requester's cart (by account or session cookie)
+async fn current_cart(
+    pool: &PgPool,
+    auth_user: &Option<AuthUser>,
+    jar: &CookieJar,
+) -> Result<Option<Cart>> {
+    let store_id = get_default_store_id(pool).await?;
+    let user_id = auth_user.as_ref().map(|user| user.user_id);
+    let session_id = jar.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string());
+
+    cart::find_cart(pool, store_id, user_id, session_id.as_deref()).await
+}
+
+/// Load a checkout session that belongs to the requester's cart
+async fn load_owned_session(
+    pool: &PgPool,
+    id: Uuid,
+    auth_user: &Option<AuthUser>,
+    jar: &CookieJar,
+) -> Result<(CheckoutSession, Cart)> {
+    let session = checkout::get_session(pool, id).await?;
+    let cart = current_cart(pool, auth_user, jar)
+        .await?
+        .filter(|cart| cart.id == session.cart_id)
+        .ok_or_else(|| ApiError::not_found("Checkout session not found"))?;
+
+    Ok((session, cart))
+}
+
+/// Reject sessions that can no longer be changed
+fn ensure_open(session: &CheckoutSession) -> Result<()> {
+    if session.status != CheckoutSessionStatus::Open {
+        return Err(ApiError::conflict(
+            "This checkout session is already completed",
+        ));
+    }
+    if session.expires_at < OffsetDateTime::now_utc() {
+        return Err(ApiError::rule(
+            "checkout_session_expired",
+            "This checkout session has expired, please start again",
+        ));
+    }
+    Ok(())
+}
+
+async fn load_settings(pool: &PgPool, store_id: Uuid) -> Result<CheckoutSettings> {
+    let store = stores::get_store(pool, store_id).await?;
+    CheckoutSettings::from_store_config(&store.config)
+}
+
+/// Build the session response: flow progress plus the cart being checked out
+async fn session_response(
+    pool: &PgPool,
+    session: CheckoutSession,
+    settings: &CheckoutSettings,
+) -> Result<CheckoutSessionResponse> {
+    let completed = session.completed();
+    let steps = settings
+        .steps
+        .iter()
+        .map(|step| CheckoutStepState {
+            step: *step,
+            completed: completed.contains(step),
+            fields: settings.fields_for(*step),
+        })
+        .collect();
+    let current_step = settings.current_step(&completed);
+    let cart = cart::get_cart_with_items(pool, session.cart_id).await?;
+
+    Ok(CheckoutSessionResponse {
+        session,
+        steps,
+        current_step,
+        cart,
+    })
+}
+
+/// Rates for the session's cart and shipping address
+async fn session_rates(
+    state: &crate::AppState,
+    session: &CheckoutSession,
+    cart: &Cart,
+) -> Result<Vec<ShippingRate>> {
+    let address = session.shipping_address.as_ref().ok_or_else(|| {
+        ApiError::rule(
+            "shipping_address_required",
+            "Enter a shipping address before choosing a shipping method",
+        )
+    })?;
+    let dest = crate::shipping::destination_from_address(address)?;
+
+    crate::shipping::quote_cart(state, cart.store_id, cart.id, &dest).await
+}
+
+/// Parse and validate a step payload
+fn parse_step<T: DeserializeOwned + Validate>(payload: serde_json::Value) -> Result<T> {
+    let req: T = serde_json::from_value(payload)
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+    Ok(req)
+}
+
+fn address_json(address: &impl serde::Serialize) -> Result<serde_json::Value> {
+    serde_json::to_value(address).map_err(|e| ApiError::internal(e.to_string()))
+}
+
+/// Validate a step's payload and turn it into the data saved on the session
+fn step_data(
+    step: CheckoutStep,
+    payload: serde_json::Value,
+    settings: &CheckoutSettings,
+) -> Result<CheckoutStepData> {
+    let (mut data, fields) = match step {
+        CheckoutStep::Cart | CheckoutStep::Confirmation => {
+            let req: CartStepRequest = parse_step(payload)?;
+            (CheckoutStepData::default(), req.fields)
+        }
+        CheckoutStep::Contact => {
+            let req: ContactStepRequest = parse_step(payload)?;
+            let data = CheckoutStepData {
+                email: Some(req.email),
+                ..Default::default()
+            };
+            (data, req.fields)
+        }
+        CheckoutStep::Shipping => {
+            let req: ShippingStepRequest = parse_step(payload)?;
+            let data = CheckoutStepData {
+                email: req.email,
+                shipping_address: Some(address_json(&req.address)?),
+                billing_address: Some(address_json(
+                    req.billing_address.as_ref().unwrap_or(&req.address),
+                )?),
+                ..Default::default()
+            };
+            (data, req.fields)
+        }
+        CheckoutStep::ShippingMethod => {
+            let req: ShippingMethodStepRequest = parse_step(payload)?;
+            let data = CheckoutStepData {
+                shipping_method: Some(req.code),
+                ..Default::default()
+            };
+            (data, req.fields)
+        }
+        CheckoutStep::Payment => {
+            let req: PaymentStepRequest = parse_step(payload)?;
+            let data = CheckoutStepData {
+                payment_method: Some(req.method),
+                gift_card_codes: req.gift_cards,
+                ..Default::default()
+            };
+            (data, req.fields)
+        }
+    };
+
+    let validated = customization::validate_properties(
+        &settings.fields_for(step),
+        fields.as_ref().unwrap_or(&serde_json::Value::Null),
+    )?;
+    data.custom_fields = validated.properties;
+
+    Ok(data)
+}
+
+/// POST /api/v1/checkout - Start (or resume) checkout for the current cart
+async fn start_checkout(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+) -> Result<Json<CheckoutSessionResponse>> {
+    let cart = current_cart(&state.pool, &auth_user, &jar)
+        .await?
+        .ok_or_else(|| ApiError::bad_request("Cart is empty"))?;
+    let cart_response = cart::get_cart_with_items(&state.pool, cart.id).await?;
+    if cart_response.items.is_empty() {
+        return Err(ApiError::bad_request("Cart is empty"));
+    }
+
+    let settings = load_settings(&state.pool, cart.store_id).await?;
+    let session = checkout::start_session(
+        &state.pool,
+        cart.store_id,
+        cart.id,
+        auth_user.as_ref().map(|user| user.user_id),
+        OffsetDateTime::now_utc() + CHECKOUT_SESSION_TTL,
+    )
+    .await?;
+
+    let response = session_response(&state.pool, session, &settings).await?;
+
+    Ok(Json(response))
+}
+
+/// GET /api/v1/checkout/:id - Get checkout progress
+async fn get_checkout(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CheckoutSessionResponse>> {
+    let (session, cart) = load_owned_session(&state.pool, id, &auth_user, &jar).await?;
+    let settings = load_settings(&state.pool, cart.store_id).await?;
+
+    let response = session_response(&state.pool, session, &settings).await?;
+
+    Ok(Json(response))
+}
+
+/// PUT /api/v1/checkout/:id/steps/:step - Submit a checkout step
+async fn submit_step(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    Path((id, step)): Path<(Uuid, String)>,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<CheckoutSessionResponse>> {
+    let step = CheckoutStep::parse(&step)
+        .ok_or_else(|| ApiError::not_found(format!("Unknown checkout step: {}", step)))?;
+    if step == CheckoutStep::Confirmation {
+        return Err(ApiError::bad_request(
+            "Complete the checkout to confirm the order",
+        ));
+    }
+
+    let (session, cart) = load_owned_session(&state.pool, id, &auth_user, &jar).await?;
+    ensure_open(&session)?;
+
+    let settings = load_settings(&state.pool, cart.store_id).await?;
+    settings.ensure_can_submit(step, &session.completed())?;
+
+    let data = step_data(step, payload, &settings)?;
+    if step == CheckoutStep::Cart {
+        let cart_response = cart::get_cart_with_items(&state.pool, cart.id).await?;
+        if cart_response.items.is_empty() {
+            return Err(ApiError::bad_request("Cart is empty"));
+        }
+    }
+
+    if let Some(code) = data
+        .shipping_method
+        .as_deref()
+        .filter(|_| step == CheckoutStep::ShippingMethod)
+    {
+        if !session_rates(&state, &session, &cart)
+            .await?
+            .iter()
+            .any(|rate| rate.code == code)
+        {
+            return Err(ApiError::rule(
+                "shipping_method_unavailable",
+                format!(
+                    "Shipping method '{}' is not available for this address",
+                    code
+                ),
+            ));
+        }
+    }
+
+    // Checked again, and spent, when the order is placed
+    if let Some(codes) = data
+        .gift_card_codes
+        .as_ref()
+        .filter(|codes| !codes.is_empty())
+    {
+        let currency = stores::get_store(&state.pool, cart.store_id)
+            .await?
+            .currency;
+        let mut conn = state.pool.acquire().await?;
+        gift_cards::resolve_cards(&mut conn, cart.store_id, currency, codes, false).await?;
+    }
+
+    let session = checkout::save_step(&state.pool, session.id, step, &data).await?;
+
+    let response = session_response(&state.pool, session, &settings).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/checkout/:id/complete - Place the order
+async fn complete_checkout(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CompleteCheckoutRequest>,
+) -> Result<(StatusCode, Json<OrderResponse>)> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (session, cart) = load_owned_session(&state.pool, id, &auth_user, &jar).await?;
+    ensure_open(&session)?;
+
+    let settings = load_settings(&state.pool, cart.store_id).await?;
+    settings.ensure_can_submit(CheckoutStep::Confirmation, &session.completed())?;
+
+    // Fields collected at confirmation come with this request
+    let confirmation = step_data(
+        CheckoutStep::Confirmation,
+        serde_json::json!({ "fields": req.fields }),
+        &settings,
+    )?;
+    let mut custom_fields = session.custom_fields.clone();
+    if let (Some(all), Some(new)) = (
+        custom_fields.as_object_mut(),
+        confirmation.custom_fields.as_object(),
+    ) {
+        all.extend(new.clone());
+    }
+
+    let email = session
+        .email
+        .clone()
+        .or_else(|| auth_user.as_ref().map(|user| user.email.clone()))
+        .or_else(|| cart.email.clone())
+        .ok_or_else(|| ApiError::validation("An email address is required"))?;
+
+    // Re-priced now: the cart or address may have changed since the step
+    let shipping_total = match (&session.shipping_method, &session.shipping_address) {
+        (Some(code), Some(address)) => {
+            let dest = crate::shipping::destination_from_address(address)?;
+            let rate =
+                crate::shipping::select_rate(&state, cart.store_id, cart.id, &dest, code).await?;
+            rate.amount.amount()
+        }
+        _ => 0,
+    };
+
+    let new_order = NewOrder {
+        user_id: auth_user.as_ref().map(|user| user.user_id),
+        email,
+        shipping_address: session.shipping_address.clone(),
+        billing_address: session.billing_address.clone(),
+        notes: req.notes,
+        shipping_method: session.shipping_method.clone(),
+        shipping_total,
+        custom_fields,
+        checkout_session_id: Some(session.id),
+        gift_card_codes: session.gift_card_codes.clone(),
+    };
+
+    let order = orders::place_order(&state.pool, cart.id, &new_order).await?;
+
+    // Revenue attribution for abandoned cart recovery must not fail a placed order
+    if let Err(e) =
+        abandoned_carts::record_recovered_revenue(&state.pool, cart.id, order.id, order.total).await
+    {
+        tracing::warn!(
+            "Failed to attribute recovered revenue for order {}: {e}",
+            order.id
+        );
+    }
+
+    let items = orders::get_order_items(&state.pool, order.id).await?;
+    let allowed_actions = order.status.allowed_actions(OrderActor::Customer);
+
+    Ok((
+        StatusCode::CREATED,
+        Json(OrderResponse {
+            order,
+            items,
+            allowed_actions,
+        }),
+    ))
+}
+
+/// GET /api/v1/checkout/:id/shipping-rates - Shipping options for the session's address
+async fn get_shipping_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<ShippingRate>>> {
+    let (session, cart) = load_owned_session(&state.pool, id, &auth_user, &jar).await?;
+
+    let rates = session_rates(&state, &session, &cart).await?;
+
+    Ok(Json(rates))
+}
+
+/// Mount checkout routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/checkout", post(start_checkout))
+        .route("/api/v1/checkout/:id", get(get_checkout))
+        .route(
+            "/api/v1/checkout/:id/shipping-rates",
+            get(get_shipping_rates),
+        )
+        .route("/api/v1/checkout/:id/steps/:step", put(submit_step))
+        .route("/api/v1/checkout/:id/complete", post(complete_checkout))
+}
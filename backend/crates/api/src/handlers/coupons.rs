@@ -0,0 +1,189 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        is_valid_code, CouponRequest, GenerateCouponCodesRequest, PaginatedResponse,
+        PaginationMeta, PaginationParams,
+    },
+    models::{Coupon, CouponCode, CouponRedemption},
+    ApiError, Result,
+};
+use goseli_db::coupons;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/coupons - List coupons (admin)
+async fn list_coupons(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<Coupon>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let data =
+        coupons::list_coupons(&state.pool, store_id, params.limit(), params.offset()).await?;
+    let total = coupons::count_coupons(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/coupons - Create a coupon (admin)
+async fn create_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<CouponRequest>,
+) -> Result<(StatusCode, Json<Coupon>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let coupon = coupons::create_coupon(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(coupon)))
+}
+
+/// GET /api/v1/admin/coupons/:id - Get a coupon (admin)
+async fn get_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Coupon>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let coupon = coupons::get_coupon(&state.pool, store_id, id).await?;
+    Ok(Json(coupon))
+}
+
+/// PUT /api/v1/admin/coupons/:id - Replace a coupon's terms (admin)
+async fn update_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CouponRequest>,
+) -> Result<Json<Coupon>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let coupon = coupons::update_coupon(&state.pool, store_id, id, &req).await?;
+    Ok(Json(coupon))
+}
+
+/// DELETE /api/v1/admin/coupons/:id - Delete a coupon that was never used (admin)
+async fn delete_coupon(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    coupons::delete_coupon(&state.pool, store_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/admin/coupons/:id/codes - List a coupon's generated codes (admin)
+async fn list_codes(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<CouponCode>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let data =
+        coupons::list_codes(&state.pool, store_id, id, params.limit(), params.offset()).await?;
+    let total = coupons::count_codes(&state.pool, store_id, id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/coupons/:id/codes - Generate single-use codes (admin)
+async fn generate_codes(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<GenerateCouponCodesRequest>,
+) -> Result<(StatusCode, Json<Vec<CouponCode>>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+    if req.prefix.as_deref().is_some_and(|p| !is_valid_code(p)) {
+        return Err(ApiError::validation(
+            "prefix may only contain letters, digits, '-' and '_'",
+        ));
+    }
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let codes = coupons::generate_codes(
+        &state.pool,
+        store_id,
+        id,
+        req.count as usize,
+        req.prefix.as_deref(),
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(codes)))
+}
+
+/// GET /api/v1/admin/coupons/:id/redemptions - Orders a coupon was used on (admin)
+async fn list_redemptions(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<Vec<CouponRedemption>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let redemptions =
+        coupons::list_redemptions(&state.pool, store_id, id, params.limit(), params.offset())
+            .await?;
+    Ok(Json(redemptions))
+}
+
+/// Mount coupon routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/admin/coupons",
+            get(list_coupons).post(create_coupon),
+        )
+        .route(
+            "/api/v1/admin/coupons/:id",
+            get(get_coupon).put(update_coupon).delete(delete_coupon),
+        )
+        .route(
+            "/api/v1/admin/coupons/:id/codes",
+            get(list_codes).post(generate_codes),
+        )
+        .route(
+            "/api/v1/admin/coupons/:id/redemptions",
+            get(list_redemptions),
+        )
+}
@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CurrencyListResponse, EnableCurrencyRequest, SetExchangeRateRequest, SetPriceRequest,
+        UpdateCurrencyRequest,
+    },
+    models::{
+        currency::parse_rate_ppm, ExchangeRate, ExchangeRateSource, PriceListEntry, StoreCurrency,
+    },
+    ApiError, Currency, Result,
+};
+use goseli_db::{currencies, stores};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// Default store ID and its currency
+async fn get_default_store_currency(pool: &PgPool) -> Result<(Uuid, Currency)> {
+    let store_id = get_default_store_id(pool).await?;
+    let store = stores::get_store(pool, store_id).await?;
+    Ok((store_id, store.currency))
+}
+
+/// GET /api/v1/currencies - Currencies prices can be shown in
+async fn list_display_currencies(
+    State(state): State<Arc<crate::AppState>>,
+) -> Result<Json<CurrencyListResponse>> {
+    let (store_id, base) = get_default_store_currency(&state.pool).await?;
+    let mut currencies = vec![base];
+    currencies.extend(currencies::list_display_currencies(&state.pool, store_id).await?);
+
+    Ok(Json(CurrencyListResponse { base, currencies }))
+}
+
+/// GET /api/v1/admin/currencies - List enabled currencies (admin)
+async fn list_currencies(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<StoreCurrency>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let currencies = currencies::list_currencies(&state.pool, store_id).await?;
+    Ok(Json(currencies))
+}
+
+/// POST /api/v1/admin/currencies - Enable a currency (admin)
+async fn enable_currency(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<EnableCurrencyRequest>,
+) -> Result<(StatusCode, Json<StoreCurrency>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (store_id, base) = get_default_store_currency(&state.pool).await?;
+    let currency = currencies::enable_currency(&state.pool, store_id, base, &req).await?;
+    Ok((StatusCode::CREATED, Json(currency)))
+}
+
+/// PUT /api/v1/admin/currencies/:currency - Change how prices in a currency are rounded (admin)
+async fn update_currency(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+    Json(req): Json<UpdateCurrencyRequest>,
+) -> Result<Json<StoreCurrency>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let currency = currencies::update_currency(&state.pool, store_id, currency, &req).await?;
+    Ok(Json(currency))
+}
+
+/// DELETE /api/v1/admin/currencies/:currency - Disable a currency (admin)
+async fn disable_currency(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    currencies::disable_currency(&state.pool, store_id, currency).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/admin/exchange-rates - List exchange rates (admin)
+async fn list_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<ExchangeRate>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let rates = currencies::list_rates(&state.pool, store_id).await?;
+    Ok(Json(rates))
+}
+
+/// PUT /api/v1/admin/exchange-rates/:currency - Set a rate by hand (admin)
+async fn set_rate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+    Json(req): Json<SetExchangeRateRequest>,
+) -> Result<Json<ExchangeRate>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (store_id, base) = get_default_store_currency(&state.pool).await?;
+    let rate = currencies::set_rate(
+        &state.pool,
+        store_id,
+        base,
+        currency,
+        req.rate_ppm,
+        ExchangeRateSource::Manual,
+    )
+    .await?;
+    Ok(Json(rate))
+}
+
+/// DELETE /api/v1/admin/exchange-rates/:currency - Delete a rate (admin)
+async fn delete_rate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    currencies::delete_rate(&state.pool, store_id, currency).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// One row of an exchange rates file
+#[derive(Debug, Deserialize)]
+struct RateCsvRow {
+    currency: String,
+    rate: String,
+}
+
+/// POST /api/v1/admin/exchange-rates/import - Import rates from a CSV file (admin)
+///
+/// Expects a header row and `currency` and `rate` columns, the rate being
+/// units of the currency per unit of the store currency (`EUR,0.921345`).
+/// Either every row is imported or none is.
+async fn import_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    body: String,
+) -> Result<Json<Vec<ExchangeRate>>> {
+    auth_user.require_admin()?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+
+    let mut rates = Vec::new();
+    for (line, row) in reader.deserialize::<RateCsvRow>().enumerate() {
+        // Line 1 is the header
+        let line_error =
+            |msg: String| ApiError::validation(format!("CSV line {}: {}", line + 2, msg));
+        let row = row.map_err(|e| line_error(e.to_string()))?;
+        let currency = Currency::new(&row.currency).map_err(|e| line_error(e.to_string()))?;
+        let rate_ppm = parse_rate_ppm(&row.rate)
+            .ok_or_else(|| line_error(format!("'{}' is not a positive rate", row.rate)))?;
+        rates.push((currency, rate_ppm));
+    }
+    if rates.is_empty() {
+        return Err(ApiError::validation("The file has no rates"));
+    }
+
+    let (store_id, base) = get_default_store_currency(&state.pool).await?;
+    let rates = currencies::import_rates(&state.pool, store_id, base, &rates).await?;
+    Ok(Json(rates))
+}
+
+/// GET /api/v1/admin/price-lists/:currency - List fixed prices in a currency (admin)
+async fn list_prices(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+) -> Result<Json<Vec<PriceListEntry>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let prices = currencies::list_prices(&state.pool, store_id, currency).await?;
+    Ok(Json(prices))
+}
+
+/// PUT /api/v1/admin/price-lists/:currency - Set the fixed price of a product or variant (admin)
+async fn set_price(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(currency): Path<Currency>,
+    Json(req): Json<SetPriceRequest>,
+) -> Result<Json<PriceListEntry>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (store_id, base) = get_default_store_currency(&state.pool).await?;
+    let entry = currencies::set_price(&state.pool, store_id, base, currency, &req).await?;
+    Ok(Json(entry))
+}
+
+/// DELETE /api/v1/admin/price-lists/entries/:id - Remove a fixed price (admin)
+async fn delete_price(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    currencies::delete_price(&state.pool, store_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mount currency, exchange rate and price list routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/currencies", get(list_display_currencies))
+        .route(
+            "/api/v1/admin/currencies",
+            get(list_currencies).post(enable_currency),
+        )
+        .route(
+            "/api/v1/admin/currencies/:currency",
+            put(update_currency).delete(disable_currency),
+        )
+        .route("/api/v1/admin/exchange-rates", get(list_rates))
+        .route("/api/v1/admin/exchange-rates/import", post(import_rates))
+        .route(
+            "/api/v1/admin/exchange-rates/:currency",
+            put(set_rate).delete(delete_rate),
+        )
+        .route(
+            "/api/v1/admin/price-lists/entries/:id",
+            delete(delete_price),
+        )
+        .route(
+            "/api/v1/admin/price-lists/:currency",
+            get(list_prices).put(set_price),
+        )
+}
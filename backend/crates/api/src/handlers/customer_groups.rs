@@ -0,0 +1,158 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{CreateCustomerGroupRequest, SetGroupPriceRequest, UpdateCustomerGroupRequest},
+    models::{CustomerGroup, GroupPrice},
+    Result,
+};
+use goseli_db::customer_groups;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/customer-groups - List customer groups (admin)
+async fn list_groups(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<CustomerGroup>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let groups = customer_groups::list_groups(&state.pool, store_id).await?;
+    Ok(Json(groups))
+}
+
+/// POST /api/v1/admin/customer-groups - Add a customer group (admin)
+async fn create_group(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<CreateCustomerGroupRequest>,
+) -> Result<(StatusCode, Json<CustomerGroup>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let group = customer_groups::create_group(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// GET /api/v1/admin/customer-groups/:id - Get a customer group (admin)
+async fn get_group(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CustomerGroup>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let group = customer_groups::get_group(&state.pool, store_id, id).await?;
+    Ok(Json(group))
+}
+
+/// PUT /api/v1/admin/customer-groups/:id - Rename or describe a customer group (admin)
+async fn update_group(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateCustomerGroupRequest>,
+) -> Result<Json<CustomerGroup>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let group = customer_groups::update_group(&state.pool, store_id, id, &req).await?;
+    Ok(Json(group))
+}
+
+/// DELETE /api/v1/admin/customer-groups/:id - Delete a customer group and its prices (admin)
+async fn delete_group(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    customer_groups::delete_group(&state.pool, store_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/admin/customer-groups/:id/prices - List a group's prices (admin)
+async fn list_prices(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<GroupPrice>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let prices = customer_groups::list_prices(&state.pool, store_id, id).await?;
+    Ok(Json(prices))
+}
+
+/// PUT /api/v1/admin/customer-groups/:id/prices - Set a group's price for a
+/// product or variant from a quantity up (admin)
+async fn set_price(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SetGroupPriceRequest>,
+) -> Result<Json<GroupPrice>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let price = customer_groups::set_price(&state.pool, store_id, id, &req).await?;
+    Ok(Json(price))
+}
+
+/// DELETE /api/v1/admin/customer-groups/:id/prices/:price_id - Remove a group price (admin)
+async fn delete_price(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path((id, price_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    customer_groups::delete_price(&state.pool, store_id, id, price_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mount customer group routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/admin/customer-groups",
+            get(list_groups).post(create_group),
+        )
+        .route(
+            "/api/v1/admin/customer-groups/:id",
+            get(get_group).put(update_group).delete(delete_group),
+        )
+        .route(
+            "/api/v1/admin/customer-groups/:id/prices",
+            get(list_prices).put(set_price),
+        )
+        .route(
+            "/api/v1/admin/customer-groups/:id/prices/:price_id",
+            delete(delete_price),
+        )
+}
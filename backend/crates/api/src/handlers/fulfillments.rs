@@ -0,0 +1,357 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CreateFulfillmentRequest, FulfillmentResponse, LabelRatesRequest, OrderTrackingResponse,
+        PurchaseLabelRequest, ShipFulfillmentRequest,
+    },
+    models::{
+        Fulfillment, FulfillmentItem, FulfillmentStatus, Order, ShippingLabel, ShippingLabelStatus,
+    },
+    ApiError, Result,
+};
+use goseli_db::{fulfillments, notifications, orders, shipping_labels};
+use goseli_shipping::CarrierRate;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Group fulfillment lines and labels under their fulfillments
+fn with_items(
+    list: Vec<Fulfillment>,
+    mut items: Vec<FulfillmentItem>,
+    labels: Vec<ShippingLabel>,
+) -> Vec<FulfillmentResponse> {
+    list.into_iter()
+        .map(|fulfillment| {
+            let (mine, rest) = items
+                .drain(..)
+                .partition(|item| item.fulfillment_id == fulfillment.id);
+            items = rest;
+            FulfillmentResponse {
+                label: labels
+                    .iter()
+                    .find(|label| label.fulfillment_id == fulfillment.id)
+                    .cloned(),
+                fulfillment,
+                items: mine,
+            }
+        })
+        .collect()
+}
+
+/// A fulfillment with its lines and label, for staff
+async fn fulfillment_response(
+    pool: &PgPool,
+    fulfillment: Fulfillment,
+) -> Result<FulfillmentResponse> {
+    let items = fulfillments::get_fulfillment_items(pool, fulfillment.id).await?;
+    let label = shipping_labels::live_label(pool, fulfillment.id).await?;
+    Ok(FulfillmentResponse {
+        fulfillment,
+        items,
+        label,
+    })
+}
+
+/// Tell the customer a parcel left the warehouse or arrived
+pub(crate) async fn notify_customer(
+    pool: &PgPool,
+    order: &Order,
+    fulfillment: &Fulfillment,
+    items: &[FulfillmentItem],
+) -> Result<()> {
+    let template = match fulfillment.status {
+        FulfillmentStatus::Shipped => "order_shipped",
+        FulfillmentStatus::Delivered => "order_delivered",
+        _ => return Ok(()),
+    };
+    let payload = serde_json::json!({
+        "order_number": order.order_number,
+        "order_status": order.status,
+        "carrier": fulfillment.carrier,
+        "tracking_number": fulfillment.tracking_number,
+        "tracking_url": fulfillment.tracking_url,
+        "item_count": items.iter().map(|item| item.quantity).sum::<i32>(),
+    });
+
+    notifications::enqueue(
+        pool,
+        order.store_id,
+        &order.email,
+        template,
+        &payload,
+        OffsetDateTime::now_utc(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// GET /api/v1/orders/:id/tracking - Shipments and tracking links of an order (owner or admin)
+async fn track_order(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<OrderTrackingResponse>> {
+    let order = orders::get_order(&state.pool, id).await?;
+    if auth_user.require_admin().is_err() && order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+
+    // Parcels still being packed or cancelled are not shipments yet
+    let list = fulfillments::list_order_fulfillments(&state.pool, order.id)
+        .await?
+        .into_iter()
+        .filter(|f| {
+            matches!(
+                f.status,
+                FulfillmentStatus::Shipped | FulfillmentStatus::Delivered
+            )
+        })
+        .collect();
+    let items = fulfillments::list_order_fulfillment_items(&state.pool, order.id).await?;
+
+    Ok(Json(OrderTrackingResponse {
+        order_id: order.id,
+        order_number: order.order_number,
+        status: order.status,
+        shipments: with_items(list, items, vec![]),
+        updated_at: order.updated_at,
+    }))
+}
+
+/// GET /api/v1/admin/orders/:id/fulfillments - List an order's fulfillments (admin)
+async fn list_fulfillments(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<FulfillmentResponse>>> {
+    auth_user.require_admin()?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    let list = fulfillments::list_order_fulfillments(&state.pool, order.id).await?;
+    let items = fulfillments::list_order_fulfillment_items(&state.pool, order.id).await?;
+    let labels = shipping_labels::list_order_labels(&state.pool, order.id).await?;
+
+    Ok(Json(with_items(list, items, labels)))
+}
+
+/// POST /api/v1/admin/orders/:id/fulfillments - Pack order lines into a parcel (admin)
+async fn create_fulfillment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateFulfillmentRequest>,
+) -> Result<(StatusCode, Json<FulfillmentResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (fulfillment, items) =
+        fulfillments::create_fulfillment(&state.pool, id, &req, auth_user.user_id).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(FulfillmentResponse {
+            fulfillment,
+            items,
+            label: None,
+        }),
+    ))
+}
+
+/// POST /api/v1/admin/fulfillments/:id/ship - Hand a parcel to the carrier (admin)
+///
+/// The order moves to partially fulfilled or fulfilled depending on what
+/// has shipped so far.
+async fn ship_fulfillment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ShipFulfillmentRequest>,
+) -> Result<Json<FulfillmentResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (fulfillment, order) =
+        fulfillments::ship_fulfillment(&state.pool, id, &req, auth_user.user_id).await?;
+    let response = fulfillment_response(&state.pool, fulfillment).await?;
+    notify_customer(&state.pool, &order, &response.fulfillment, &response.items).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/fulfillments/:id/deliver - Record that a parcel arrived (admin)
+async fn deliver_fulfillment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FulfillmentResponse>> {
+    auth_user.require_admin()?;
+
+    let fulfillment = fulfillments::deliver_fulfillment(&state.pool, id).await?;
+    let order = orders::get_order(&state.pool, fulfillment.order_id).await?;
+    let response = fulfillment_response(&state.pool, fulfillment).await?;
+    notify_customer(&state.pool, &order, &response.fulfillment, &response.items).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/fulfillments/:id/cancel - Cancel a parcel before it ships (admin)
+///
+/// A purchased label is voided at the carrier first.
+async fn cancel_fulfillment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FulfillmentResponse>> {
+    auth_user.require_admin()?;
+
+    let current = fulfillments::get_fulfillment(&state.pool, id).await?;
+    current.status.transition(FulfillmentStatus::Cancelled)?;
+    if let Some(label) = shipping_labels::live_label(&state.pool, id).await? {
+        crate::shipping::void_label(&state, &label).await?;
+    }
+
+    let fulfillment = fulfillments::cancel_fulfillment(&state.pool, id).await?;
+    let response = fulfillment_response(&state.pool, fulfillment).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/fulfillments/:id/rates - Quote a parcel with a carrier (admin)
+async fn label_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<LabelRatesRequest>,
+) -> Result<Json<Vec<CarrierRate>>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let fulfillment = fulfillments::get_fulfillment(&state.pool, id).await?;
+    let rates = crate::shipping::label_rates(&state, &fulfillment, &req).await?;
+
+    Ok(Json(rates))
+}
+
+/// POST /api/v1/admin/fulfillments/:id/label - Buy a shipping label for a parcel (admin)
+///
+/// The parcel takes the label's carrier and tracking number.
+async fn purchase_label(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<PurchaseLabelRequest>,
+) -> Result<(StatusCode, Json<FulfillmentResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let (_, fulfillment) =
+        crate::shipping::purchase_label(&state, id, &req, auth_user.user_id).await?;
+    let response = fulfillment_response(&state.pool, fulfillment).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// GET /api/v1/admin/fulfillments/:id/label - Download the parcel's label (admin)
+async fn get_label(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    auth_user.require_admin()?;
+
+    let (label, key) = shipping_labels::live_label(&state.pool, id)
+        .await?
+        .and_then(|label| {
+            let key = label.storage_key.clone()?;
+            Some((label, key))
+        })
+        .ok_or_else(|| ApiError::not_found("This parcel has no label"))?;
+    let data = state.storage.get(&key).await?;
+    let format = crate::shipping::carrier_format(label.format);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"label-{}.{}\"",
+                    label.tracking_number.as_deref().unwrap_or("parcel"),
+                    format.extension()
+                ),
+            ),
+        ],
+        data,
+    ))
+}
+
+/// DELETE /api/v1/admin/fulfillments/:id/label - Void the parcel's label (admin)
+async fn void_label(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FulfillmentResponse>> {
+    auth_user.require_admin()?;
+
+    let label = shipping_labels::live_label(&state.pool, id)
+        .await?
+        .filter(|label| label.status == ShippingLabelStatus::Purchased)
+        .ok_or_else(|| ApiError::not_found("This parcel has no label"))?;
+    let fulfillment = fulfillments::get_fulfillment(&state.pool, id).await?;
+    if fulfillment.status != FulfillmentStatus::Pending {
+        return Err(ApiError::rule(
+            "fulfillment_not_pending",
+            format!(
+                "Labels can only be voided before the parcel ships; this one is {}",
+                fulfillment.status
+            ),
+        ));
+    }
+
+    let (_, fulfillment) = crate::shipping::void_label(&state, &label).await?;
+    let response = fulfillment_response(&state.pool, fulfillment).await?;
+
+    Ok(Json(response))
+}
+
+/// Mount fulfillment routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/orders/:id/tracking", get(track_order))
+        .route(
+            "/api/v1/admin/orders/:id/fulfillments",
+            get(list_fulfillments).post(create_fulfillment),
+        )
+        .route(
+            "/api/v1/admin/fulfillments/:id/ship",
+            post(ship_fulfillment),
+        )
+        .route(
+            "/api/v1/admin/fulfillments/:id/deliver",
+            post(deliver_fulfillment),
+        )
+        .route(
+            "/api/v1/admin/fulfillments/:id/cancel",
+            post(cancel_fulfillment),
+        )
+        .route("/api/v1/admin/fulfillments/:id/rates", post(label_rates))
+        .route(
+            "/api/v1/admin/fulfillments/:id/label",
+            get(get_label).post(purchase_label).delete(void_label),
+        )
+}
@@ -0,0 +1,176 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        AdjustGiftCardRequest, GiftCardBalanceRequest, GiftCardBalanceResponse, GiftCardResponse,
+        IssueGiftCardRequest, PaginatedResponse, PaginationMeta, PaginationParams,
+        UpdateGiftCardRequest,
+    },
+    models::GiftCard,
+    ApiError, Result,
+};
+use goseli_db::{gift_cards, stores};
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+async fn gift_card_response(pool: &PgPool, gift_card: GiftCard) -> Result<GiftCardResponse> {
+    let transactions = gift_cards::list_transactions(pool, gift_card.id).await?;
+    Ok(GiftCardResponse {
+        status: gift_card.status(OffsetDateTime::now_utc()),
+        gift_card,
+        transactions,
+    })
+}
+
+/// POST /api/v1/gift-cards/balance - Check what is left on a gift card
+async fn check_balance(
+    State(state): State<Arc<crate::AppState>>,
+    Json(req): Json<GiftCardBalanceRequest>,
+) -> Result<Json<GiftCardBalanceResponse>> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let card = gift_cards::find_by_code(&state.pool, store_id, &req.code)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Gift card not found"))?;
+
+    Ok(Json(GiftCardBalanceResponse {
+        status: card.status(OffsetDateTime::now_utc()),
+        balance: card.balance(),
+        expires_at: card.expires_at,
+        code: card.code,
+    }))
+}
+
+/// GET /api/v1/admin/gift-cards - List gift cards, newest first (admin)
+async fn list_gift_cards(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<GiftCard>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let data =
+        gift_cards::list_gift_cards(&state.pool, store_id, params.limit(), params.offset()).await?;
+    let total = gift_cards::count_gift_cards(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/gift-cards - Issue a gift card in the store currency (admin)
+async fn issue_gift_card(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<IssueGiftCardRequest>,
+) -> Result<(StatusCode, Json<GiftCardResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let currency = stores::get_store(&state.pool, store_id).await?.currency;
+    let card =
+        gift_cards::issue_gift_card(&state.pool, store_id, currency, &req, auth_user.user_id)
+            .await?;
+
+    let response = gift_card_response(&state.pool, card).await?;
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// GET /api/v1/admin/gift-cards/:id - Get a gift card with its ledger (admin)
+async fn get_gift_card(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<GiftCardResponse>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let card = gift_cards::get_gift_card(&state.pool, store_id, id).await?;
+
+    let response = gift_card_response(&state.pool, card).await?;
+    Ok(Json(response))
+}
+
+/// PUT /api/v1/admin/gift-cards/:id - Disable, re-enable or change a gift card's expiry (admin)
+async fn update_gift_card(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateGiftCardRequest>,
+) -> Result<Json<GiftCardResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let card = gift_cards::update_gift_card(&state.pool, store_id, id, &req).await?;
+
+    let response = gift_card_response(&state.pool, card).await?;
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/gift-cards/:id/adjust - Add to or take from a gift card's balance (admin)
+async fn adjust_gift_card(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<AdjustGiftCardRequest>,
+) -> Result<Json<GiftCardResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let card = gift_cards::adjust_balance(
+        &state.pool,
+        store_id,
+        id,
+        req.amount,
+        &req.note,
+        auth_user.user_id,
+    )
+    .await?;
+
+    let response = gift_card_response(&state.pool, card).await?;
+    Ok(Json(response))
+}
+
+/// Mount gift card routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/gift-cards/balance", post(check_balance))
+        .route(
+            "/api/v1/admin/gift-cards",
+            get(list_gift_cards).post(issue_gift_card),
+        )
+        .route(
+            "/api/v1/admin/gift-cards/:id",
+            get(get_gift_card).put(update_gift_card),
+        )
+        .route(
+            "/api/v1/admin/gift-cards/:id/adjust",
+            post(adjust_gift_card),
+        )
+}
@@ -1,5 +1,23 @@
 // API route handlers
+pub mod abandoned_carts;
 pub mod auth;
+pub mod carriers;
 pub mod cart;
 pub mod categories;
+pub mod checkout;
+pub mod coupons;
+pub mod currencies;
+pub mod customer_groups;
+pub mod fulfillments;
+pub mod gift_cards;
+pub mod orders;
+pub mod payments;
 pub mod products;
+pub mod promotions;
+pub mod refunds;
+pub mod returns;
+pub mod sales;
+pub mod shipping;
+pub mod tax_exemptions;
+pub mod taxes;
+pub mod webhooks;
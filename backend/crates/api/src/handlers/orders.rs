@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CancelOrderRequest, OrderResponse, OrderTransitionRequest, PaginatedResponse,
+        PaginationMeta, PaginationParams,
+    },
+    models::{Order, OrderAction, OrderActor, OrderEvent},
+    ApiError, Result,
+};
+use goseli_db::orders;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// How the requester relates to an order, or None if they may not see it
+fn viewer_actor(order: &Order, auth_user: &AuthUser) -> Option<OrderActor> {
+    if auth_user.require_admin().is_ok() {
+        Some(OrderActor::Admin)
+    } else if order.user_id == Some(auth_user.user_id) {
+        Some(OrderActor::Customer)
+    } else {
+        None
+    }
+}
+
+/// Load an order the requester may see; other customers' orders are
+/// reported as missing rather than forbidden
+async fn load_visible_order(
+    pool: &PgPool,
+    id: Uuid,
+    auth_user: &AuthUser,
+) -> Result<(Order, OrderActor)> {
+    let order = orders::get_order(pool, id).await?;
+    let actor =
+        viewer_actor(&order, auth_user).ok_or_else(|| ApiError::not_found("Order not found"))?;
+    Ok((order, actor))
+}
+
+/// Build the full order response for a requester acting as `actor`
+async fn order_response(pool: &PgPool, order: Order, actor: OrderActor) -> Result<OrderResponse> {
+    let items = orders::get_order_items(pool, order.id).await?;
+    let allowed_actions = order.status.allowed_actions(actor);
+
+    Ok(OrderResponse {
+        order,
+        items,
+        allowed_actions,
+    })
+}
+
+/// GET /api/v1/orders - List the current customer's orders
+async fn list_my_orders(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<Order>>> {
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let data = orders::list_user_orders(
+        &state.pool,
+        store_id,
+        auth_user.user_id,
+        params.limit(),
+        params.offset(),
+    )
+    .await?;
+    let total = orders::count_user_orders(&state.pool, store_id, auth_user.user_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// GET /api/v1/orders/:id - Get an order (owner or admin)
+async fn get_order(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<OrderResponse>> {
+    let (order, actor) = load_visible_order(&state.pool, id, &auth_user).await?;
+
+    let response = order_response(&state.pool, order, actor).await?;
+
+    Ok(Json(response))
+}
+
+/// GET /api/v1/orders/:id/events - Order status history (owner or admin)
+async fn get_order_events(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<OrderEvent>>> {
+    let (order, _) = load_visible_order(&state.pool, id, &auth_user).await?;
+
+    let events = orders::get_order_events(&state.pool, order.id).await?;
+
+    Ok(Json(events))
+}
+
+/// POST /api/v1/orders/:id/cancel - Cancel an unpaid order (owner)
+async fn cancel_order(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CancelOrderRequest>,
+) -> Result<Json<OrderResponse>> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    if order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+    crate::payments::void_for_cancel(&state, &order, OrderActor::Customer).await?;
+
+    let order = orders::transition_order(
+        &state.pool,
+        order.id,
+        OrderAction::Cancel,
+        OrderActor::Customer,
+        Some(auth_user.user_id),
+        req.reason.as_deref(),
+    )
+    .await?;
+
+    let response = order_response(&state.pool, order, OrderActor::Customer).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/orders/:id/transitions - Apply a lifecycle action (admin)
+async fn transition_order(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<OrderTransitionRequest>,
+) -> Result<Json<OrderResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    // Captured payments are refunded through the refunds endpoint instead
+    if req.action == OrderAction::Cancel {
+        let order = orders::get_order(&state.pool, id).await?;
+        crate::payments::void_for_cancel(&state, &order, OrderActor::Admin).await?;
+    }
+
+    let order = orders::transition_order(
+        &state.pool,
+        id,
+        req.action,
+        OrderActor::Admin,
+        Some(auth_user.user_id),
+        req.reason.as_deref(),
+    )
+    .await?;
+
+    let response = order_response(&state.pool, order, OrderActor::Admin).await?;
+
+    Ok(Json(response))
+}
+
+/// GET /api/v1/admin/orders - List all orders (admin)
+async fn list_orders(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<Order>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let data = orders::list_orders(&state.pool, store_id, params.limit(), params.offset()).await?;
+    let total = orders::count_orders(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// Mount order routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/orders", get(list_my_orders))
+        .route("/api/v1/orders/:id", get(get_order))
+        .route("/api/v1/orders/:id/events", get(get_order_events))
+        .route("/api/v1/orders/:id/cancel", post(cancel_order))
+        .route("/api/v1/admin/orders", get(list_orders))
+        .route(
+            "/api/v1/admin/orders/:id/transitions",
+            post(transition_order),
+        )
+}
@@ -0,0 +1,123 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{CreatePaymentRequest, PaymentResponse},
+    models::{Order, Payment, PaymentAttempt, PaymentState},
+    ApiError, Result,
+};
+use goseli_db::{orders, payments};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Load an order the requester may see (owner or admin)
+async fn load_visible_order(pool: &PgPool, id: Uuid, auth_user: &AuthUser) -> Result<Order> {
+    let order = orders::get_order(pool, id).await?;
+    if auth_user.require_admin().is_err() && order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+    Ok(order)
+}
+
+/// POST /api/v1/orders/:id/payments - Pay an order (owner)
+async fn create_payment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreatePaymentRequest>,
+) -> Result<(StatusCode, Json<PaymentResponse>)> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    if order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+
+    let (mut payment, client_secret) =
+        crate::payments::authorize_order(&state, &order, req.payment_method).await?;
+    if payment.status == PaymentState::Authorized && crate::payments::auto_capture() {
+        payment = crate::payments::capture_payment(&state, &payment).await?;
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PaymentResponse {
+            payment,
+            client_secret,
+        }),
+    ))
+}
+
+/// GET /api/v1/orders/:id/payments - List an order's payments (owner or admin)
+async fn list_order_payments(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Payment>>> {
+    let order = load_visible_order(&state.pool, id, &auth_user).await?;
+
+    let payments = payments::list_order_payments(&state.pool, order.id).await?;
+
+    Ok(Json(payments))
+}
+
+/// GET /api/v1/admin/payments/:id/attempts - Provider calls made for a payment (admin)
+async fn list_attempts(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<PaymentAttempt>>> {
+    auth_user.require_admin()?;
+
+    let payment = payments::get_payment(&state.pool, id).await?;
+    let attempts = payments::list_attempts(&state.pool, payment.id).await?;
+
+    Ok(Json(attempts))
+}
+
+/// POST /api/v1/admin/payments/:id/capture - Capture an authorized payment (admin)
+async fn capture_payment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Payment>> {
+    auth_user.require_admin()?;
+
+    let payment = payments::get_payment(&state.pool, id).await?;
+    let payment = crate::payments::capture_payment(&state, &payment).await?;
+
+    Ok(Json(payment))
+}
+
+/// POST /api/v1/admin/payments/:id/void - Release an uncaptured payment (admin)
+async fn void_payment(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Payment>> {
+    auth_user.require_admin()?;
+
+    let payment = payments::get_payment(&state.pool, id).await?;
+    let payment = crate::payments::void_payment(&state, &payment).await?;
+
+    Ok(Json(payment))
+}
+
+/// Mount payment routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/orders/:id/payments",
+            get(list_order_payments).post(create_payment),
+        )
+        .route("/api/v1/admin/payments/:id/attempts", get(list_attempts))
+        .route("/api/v1/admin/payments/:id/capture", post(capture_payment))
+        .route("/api/v1/admin/payments/:id/void", post(void_payment))
+}
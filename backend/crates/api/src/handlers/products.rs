@@ -1,25 +1,32 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    routing::get,
+    routing::{get, post, put},
     Json, Router,
 };
+use goseli_auth::AuthUser;
 use goseli_core::{
     dto::{
-        CreateProductRequest, PaginatedResponse, PaginationMeta, PaginationParams,
-        ProductListParams, ProductResponse, UpdateProductRequest,
+        CreateProductRequest, CreateVariantRequest, PaginatedResponse, PaginationMeta,
+        PaginationParams, ProductListParams, ProductResponse, ProductVariantResponse,
+        UpdateProductRequest, UpdateVariantRequest,
     },
     Result,
 };
-use goseli_db::products;
+use goseli_db::{currencies, products, promotions, stores};
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
 use validator::Validate;
 
-/// GET /api/v1/products - List products with pagination and filters
+use crate::currency::RequestedCurrency;
+
+/// GET /api/v1/products - List products with pagination and filters, priced
+/// in the requested currency for the customer's group
 async fn list_products(
     State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
     Query(params): Query<ProductListParams>,
 ) -> Result<Json<PaginatedResponse<ProductResponse>>> {
     let store_id = get_default_store_id(&state.pool).await?;
@@ -31,7 +38,27 @@ async fn list_products(
     let items = products::list_products(&state.pool, store_id, page, per_page, &params).await?;
     let total = products::count_products(&state.pool, store_id, &params).await?;
 
-    let data: Vec<ProductResponse> = items.into_iter().map(ProductResponse::from).collect();
+    let currency = stores::get_store(&state.pool, store_id).await?.currency;
+    let product_ids: Vec<Uuid> = items.iter().map(|product| product.id).collect();
+    let customer_group =
+        promotions::customer_group(&state.pool, auth_user.map(|user| user.user_id)).await?;
+    let pricing = currencies::display_pricing(
+        &state.pool,
+        store_id,
+        currency,
+        requested,
+        customer_group.as_deref(),
+        &product_ids,
+    )
+    .await?;
+    let data = items
+        .into_iter()
+        .map(|product| {
+            let mut response = ProductResponse::new(product);
+            response.reprice(&pricing)?;
+            Ok(response)
+        })
+        .collect::<Result<Vec<ProductResponse>>>()?;
     let response = PaginatedResponse {
         data,
         pagination: PaginationMeta::new(&pagination, total),
@@ -40,18 +67,39 @@ async fn list_products(
     Ok(Json(response))
 }
 
-/// GET /api/v1/products/:id - Get a single product with images and variants
+/// GET /api/v1/products/:id - Get a single product with images and variants,
+/// priced in the requested currency for the customer's group
 async fn get_product(
     State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    RequestedCurrency(requested): RequestedCurrency,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ProductResponse>> {
     let product = products::get_product_by_id(&state.pool, id).await?;
     let images = products::get_product_images(&state.pool, product.id).await?;
     let variants = products::get_product_variants(&state.pool, product.id).await?;
 
-    let mut response = ProductResponse::from(product);
+    let store_id = product.store_id;
+    let currency = stores::get_store(&state.pool, store_id).await?.currency;
+
+    let mut response = ProductResponse::new(product);
     response.images = images;
-    response.variants = variants;
+    response.variants = variants
+        .into_iter()
+        .map(ProductVariantResponse::new)
+        .collect();
+    let customer_group =
+        promotions::customer_group(&state.pool, auth_user.map(|user| user.user_id)).await?;
+    let pricing = currencies::display_pricing(
+        &state.pool,
+        store_id,
+        currency,
+        requested,
+        customer_group.as_deref(),
+        &[response.id],
+    )
+    .await?;
+    response.reprice(&pricing)?;
 
     Ok(Json(response))
 }
@@ -67,7 +115,7 @@ async fn create_product(
     let store_id = get_default_store_id(&state.pool).await?;
     let product = products::create_product(&state.pool, store_id, &req).await?;
 
-    Ok((StatusCode::CREATED, Json(ProductResponse::from(product))))
+    Ok((StatusCode::CREATED, Json(ProductResponse::new(product))))
 }
 
 /// PUT /api/v1/products/:id - Update a product
@@ -81,7 +129,7 @@ async fn update_product(
 
     let product = products::update_product(&state.pool, id, &req).await?;
 
-    Ok(Json(ProductResponse::from(product)))
+    Ok(Json(ProductResponse::new(product)))
 }
 
 /// DELETE /api/v1/products/:id - Soft delete a product (archive)
@@ -94,6 +142,43 @@ async fn delete_product(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// POST /api/v1/products/:id/variants - Add a variant to a product (admin)
+async fn create_variant(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(product_id): Path<Uuid>,
+    Json(req): Json<CreateVariantRequest>,
+) -> Result<(StatusCode, Json<ProductVariantResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let product = products::get_product_by_id(&state.pool, product_id).await?;
+    let variant = products::create_variant(&state.pool, product.id, &req).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ProductVariantResponse::new(variant)),
+    ))
+}
+
+/// PUT /api/v1/products/:id/variants/:variant_id - Update a variant (admin)
+async fn update_variant(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path((product_id, variant_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<UpdateVariantRequest>,
+) -> Result<Json<ProductVariantResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let product = products::get_product_by_id(&state.pool, product_id).await?;
+    let variant = products::update_variant(&state.pool, product.id, variant_id, &req).await?;
+
+    Ok(Json(ProductVariantResponse::new(variant)))
+}
+
 /// Helper to get default store ID (temporary until domain-based routing)
 async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
     let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
@@ -110,4 +195,9 @@ pub fn routes() -> Router<Arc<crate::AppState>> {
             "/api/v1/products/:id",
             get(get_product).put(update_product).delete(delete_product),
         )
+        .route("/api/v1/products/:id/variants", post(create_variant))
+        .route(
+            "/api/v1/products/:id/variants/:variant_id",
+            put(update_variant),
+        )
 }
@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        PaginatedResponse, PaginationMeta, PaginationParams, PromotionRequest,
+        SetCustomerGroupRequest,
+    },
+    models::Promotion,
+    Result,
+};
+use goseli_db::promotions;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/promotions - List promotions in the order they run (admin)
+async fn list_promotions(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<Promotion>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let data =
+        promotions::list_promotions(&state.pool, store_id, params.limit(), params.offset()).await?;
+    let total = promotions::count_promotions(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/promotions - Create a promotion (admin)
+async fn create_promotion(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<PromotionRequest>,
+) -> Result<(StatusCode, Json<Promotion>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let promotion = promotions::create_promotion(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(promotion)))
+}
+
+/// GET /api/v1/admin/promotions/:id - Get a promotion (admin)
+async fn get_promotion(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Promotion>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let promotion = promotions::get_promotion(&state.pool, store_id, id).await?;
+    Ok(Json(promotion))
+}
+
+/// PUT /api/v1/admin/promotions/:id - Replace a promotion's rules (admin)
+async fn update_promotion(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<PromotionRequest>,
+) -> Result<Json<Promotion>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let promotion = promotions::update_promotion(&state.pool, store_id, id, &req).await?;
+    Ok(Json(promotion))
+}
+
+/// DELETE /api/v1/admin/promotions/:id - Delete a promotion (admin)
+async fn delete_promotion(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    promotions::delete_promotion(&state.pool, store_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/v1/admin/customers/:user_id/group - Put a customer in a customer group (admin)
+async fn set_customer_group(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<SetCustomerGroupRequest>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    promotions::set_customer_group(
+        &state.pool,
+        store_id,
+        user_id,
+        req.customer_group
+            .as_deref()
+            .map(|group| group.trim().to_lowercase())
+            .filter(|group| !group.is_empty())
+            .as_deref(),
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mount promotion routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/admin/promotions",
+            get(list_promotions).post(create_promotion),
+        )
+        .route(
+            "/api/v1/admin/promotions/:id",
+            get(get_promotion)
+                .put(update_promotion)
+                .delete(delete_promotion),
+        )
+        .route(
+            "/api/v1/admin/customers/:user_id/group",
+            put(set_customer_group),
+        )
+}
@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{CreateRefundRequest, RefundResponse},
+    Result,
+};
+use goseli_db::{orders, refunds};
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+/// POST /api/v1/admin/orders/:id/refunds - Refund lines, shipping or an amount (admin)
+async fn create_refund(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateRefundRequest>,
+) -> Result<(StatusCode, Json<RefundResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    let (refund, items) =
+        crate::payments::refund_order(&state, &order, &req, auth_user.user_id).await?;
+
+    Ok((StatusCode::CREATED, Json(RefundResponse { refund, items })))
+}
+
+/// GET /api/v1/admin/orders/:id/refunds - List an order's refunds (admin)
+async fn list_refunds(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<RefundResponse>>> {
+    auth_user.require_admin()?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    let list = refunds::list_order_refunds(&state.pool, order.id).await?;
+    let items = refunds::list_order_refund_items(&state.pool, order.id).await?;
+
+    let response = list
+        .into_iter()
+        .map(|refund| RefundResponse {
+            items: items
+                .iter()
+                .filter(|item| item.refund_id == refund.id)
+                .cloned()
+                .collect(),
+            refund,
+        })
+        .collect();
+
+    Ok(Json(response))
+}
+
+/// Mount refund routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new().route(
+        "/api/v1/admin/orders/:id/refunds",
+        get(list_refunds).post(create_refund),
+    )
+}
@@ -0,0 +1,450 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CloseReturnRequest, CreateRefundRequest, CreateReturnRequest, PaginatedResponse,
+        PaginationMeta, PaginationParams, ReceiveReturnRequest, RefundLineRequest,
+        ReturnDecisionRequest, ReturnListFilter, ReturnResponse,
+    },
+    models::{Order, Return, ReturnPhoto, ReturnResolution, ReturnStatus},
+    ApiError, Result,
+};
+use goseli_db::{
+    gift_cards, notifications, orders, refunds,
+    returns::{self, ReturnClosing},
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Photos a customer may attach to one return
+const MAX_PHOTOS: i64 = 5;
+const MAX_PHOTO_BYTES: usize = 5 * 1024 * 1024;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// How long after purchase a return can be requested (`RETURN_WINDOW_DAYS`)
+fn return_window() -> Duration {
+    std::env::var("RETURN_WINDOW_DAYS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(Duration::days)
+        .unwrap_or(Duration::days(30))
+}
+
+/// Load a return the requester may see (the customer who opened it, or admin)
+async fn load_visible_return(pool: &PgPool, id: Uuid, auth_user: &AuthUser) -> Result<Return> {
+    let ret = returns::get_return(pool, id).await?;
+    if auth_user.require_admin().is_err() && ret.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Return not found"));
+    }
+    Ok(ret)
+}
+
+async fn return_response(pool: &PgPool, ret: Return) -> Result<ReturnResponse> {
+    let items = returns::get_return_items(pool, ret.id).await?;
+    let photos = returns::get_return_photos(pool, ret.id).await?;
+    Ok(ReturnResponse {
+        r#return: ret,
+        items,
+        photos,
+    })
+}
+
+/// Tell the customer a return moved to a new step
+async fn notify_customer(pool: &PgPool, order: &Order, ret: &Return) -> Result<()> {
+    let (template, note) = match ret.status {
+        ReturnStatus::Requested => ("return_requested", ret.comment.as_deref()),
+        ReturnStatus::Approved => ("return_approved", ret.decision_note.as_deref()),
+        ReturnStatus::Rejected => ("return_rejected", ret.decision_note.as_deref()),
+        ReturnStatus::Received => ("return_received", ret.condition_note.as_deref()),
+        ReturnStatus::Closed => ("return_closed", ret.resolution_note.as_deref()),
+    };
+    // Store credit is spent with the code of the card it was issued on
+    let gift_card_code = match ret.gift_card_id {
+        Some(id) => Some(
+            gift_cards::get_gift_card(pool, order.store_id, id)
+                .await?
+                .code,
+        ),
+        None => None,
+    };
+    let payload = serde_json::json!({
+        "rma_number": ret.rma_number,
+        "order_number": order.order_number,
+        "status": ret.status,
+        "reason": ret.reason,
+        "note": note,
+        "condition": ret.condition,
+        "resolution": ret.resolution,
+        "credit_amount": ret.credit_amount,
+        "currency": order.currency,
+        "gift_card_code": gift_card_code,
+    });
+
+    notifications::enqueue(
+        pool,
+        order.store_id,
+        &order.email,
+        template,
+        &payload,
+        OffsetDateTime::now_utc(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// POST /api/v1/orders/:id/returns - Request a return for order lines (owner)
+async fn create_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateReturnRequest>,
+) -> Result<(StatusCode, Json<ReturnResponse>)> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let order = orders::get_order(&state.pool, id).await?;
+    if order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+
+    let (ret, _) = returns::create_return(
+        &state.pool,
+        order.id,
+        Some(auth_user.user_id),
+        &req,
+        return_window(),
+    )
+    .await?;
+    notify_customer(&state.pool, &order, &ret).await?;
+
+    let response = return_response(&state.pool, ret).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// GET /api/v1/orders/:id/returns - List an order's returns (owner or admin)
+async fn list_order_returns(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Return>>> {
+    let order = orders::get_order(&state.pool, id).await?;
+    if auth_user.require_admin().is_err() && order.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Order not found"));
+    }
+
+    let list = returns::list_order_returns(&state.pool, order.id).await?;
+
+    Ok(Json(list))
+}
+
+/// GET /api/v1/returns/:id - Get a return with its lines and photos (owner or admin)
+async fn get_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReturnResponse>> {
+    let ret = load_visible_return(&state.pool, id, &auth_user).await?;
+
+    let response = return_response(&state.pool, ret).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/returns/:id/photos - Attach a photo (multipart field `photo`) (owner)
+async fn upload_photo(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<ReturnPhoto>)> {
+    let ret = returns::get_return(&state.pool, id).await?;
+    if ret.user_id != Some(auth_user.user_id) {
+        return Err(ApiError::not_found("Return not found"));
+    }
+    if !matches!(ret.status, ReturnStatus::Requested | ReturnStatus::Approved) {
+        return Err(ApiError::rule(
+            "return_closed_for_photos",
+            format!("Photos cannot be added to a return that is {}", ret.status),
+        ));
+    }
+    if returns::count_return_photos(&state.pool, ret.id).await? >= MAX_PHOTOS {
+        return Err(ApiError::rule(
+            "too_many_photos",
+            format!("A return can have at most {} photos", MAX_PHOTOS),
+        ));
+    }
+
+    let mut data = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+    {
+        if field.name() == Some("photo") {
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|e| ApiError::bad_request(e.to_string()))?;
+            data = Some(bytes);
+            break;
+        }
+    }
+    let data = data.ok_or_else(|| ApiError::validation("Missing 'photo' file"))?;
+    if data.is_empty() || data.len() > MAX_PHOTO_BYTES {
+        return Err(ApiError::validation(format!(
+            "Photos must be between 1 byte and {} MB",
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        )));
+    }
+    let content_type = goseli_storage::image_content_type(&data)
+        .ok_or_else(|| ApiError::validation("Photos must be JPEG, PNG or WebP images"))?;
+
+    let photo_id = Uuid::now_v7();
+    let key = format!(
+        "returns/{}/{}.{}",
+        ret.id,
+        photo_id,
+        goseli_storage::extension_for(content_type)
+    );
+    state.storage.put(&key, &data).await?;
+
+    let photo = returns::add_return_photo(
+        &state.pool,
+        photo_id,
+        ret.id,
+        &key,
+        content_type,
+        data.len() as i32,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(photo)))
+}
+
+/// GET /api/v1/returns/:id/photos/:photo_id - Download a return photo (owner or admin)
+async fn get_photo(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path((id, photo_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse> {
+    let ret = load_visible_return(&state.pool, id, &auth_user).await?;
+    let photo = returns::get_return_photo(&state.pool, ret.id, photo_id).await?;
+
+    let data = state.storage.get(&photo.storage_key).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, photo.content_type),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        data,
+    ))
+}
+
+/// GET /api/v1/admin/returns - List returns, optionally by status (admin)
+async fn list_returns(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+    Query(filter): Query<ReturnListFilter>,
+) -> Result<Json<PaginatedResponse<Return>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let data = returns::list_returns(
+        &state.pool,
+        store_id,
+        filter.status,
+        params.limit(),
+        params.offset(),
+    )
+    .await?;
+    let total = returns::count_returns(&state.pool, store_id, filter.status).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// Approve or reject a requested return and tell the customer
+async fn decide(
+    state: &crate::AppState,
+    auth_user: &AuthUser,
+    id: Uuid,
+    approve: bool,
+    req: ReturnDecisionRequest,
+) -> Result<Json<ReturnResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let ret = returns::decide_return(
+        &state.pool,
+        id,
+        approve,
+        req.note.as_deref(),
+        auth_user.user_id,
+    )
+    .await?;
+    let order = orders::get_order(&state.pool, ret.order_id).await?;
+    notify_customer(&state.pool, &order, &ret).await?;
+
+    let response = return_response(&state.pool, ret).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/returns/:id/approve - Approve a requested return (admin)
+async fn approve_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReturnDecisionRequest>,
+) -> Result<Json<ReturnResponse>> {
+    decide(&state, &auth_user, id, true, req).await
+}
+
+/// POST /api/v1/admin/returns/:id/reject - Reject a requested return (admin)
+async fn reject_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReturnDecisionRequest>,
+) -> Result<Json<ReturnResponse>> {
+    decide(&state, &auth_user, id, false, req).await
+}
+
+/// POST /api/v1/admin/returns/:id/receive - Record arrival and condition (admin)
+async fn receive_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<ReceiveReturnRequest>,
+) -> Result<Json<ReturnResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let ret = returns::receive_return(&state.pool, id, req.condition, req.note.as_deref()).await?;
+    let order = orders::get_order(&state.pool, ret.order_id).await?;
+    notify_customer(&state.pool, &order, &ret).await?;
+
+    let response = return_response(&state.pool, ret).await?;
+
+    Ok(Json(response))
+}
+
+/// POST /api/v1/admin/returns/:id/close - Resolve with a refund, exchange or store credit (admin)
+///
+/// A refund goes through the payment provider for the returned lines and
+/// restocks them itself; the other resolutions restock here if asked.
+async fn close_return(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CloseReturnRequest>,
+) -> Result<Json<ReturnResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let ret = returns::get_return(&state.pool, id).await?;
+    ret.status.transition(ReturnStatus::Closed)?;
+    let order = orders::get_order(&state.pool, ret.order_id).await?;
+
+    let mut closing = ReturnClosing {
+        resolution: req.resolution,
+        refund_id: None,
+        credit_amount: None,
+        restock: req.restock,
+        note: req.note.as_deref(),
+    };
+    match req.resolution {
+        ReturnResolution::Refund => {
+            let items = returns::get_return_items(&state.pool, ret.id).await?;
+            let refund = CreateRefundRequest {
+                full: false,
+                items: items
+                    .iter()
+                    .map(|item| RefundLineRequest {
+                        order_item_id: item.order_item_id,
+                        quantity: item.quantity,
+                    })
+                    .collect(),
+                shipping: false,
+                amount: None,
+                restock: req.restock,
+                reason: format!("Return RMA-{}: {}", ret.rma_number, ret.reason),
+            };
+            let (refund, items) = refunds::create_return_refund(
+                &state.pool,
+                ret.id,
+                &refund,
+                Some(auth_user.user_id),
+            )
+            .await?;
+            let (refund, _) =
+                crate::payments::send_refund(&state, refund, items, auth_user.user_id).await?;
+            closing.refund_id = Some(refund.id);
+            closing.restock = false;
+        }
+        ReturnResolution::StoreCredit => {
+            let amount = match req.credit_amount {
+                Some(amount) => amount,
+                None => returns::return_value(&state.pool, ret.id).await?,
+            };
+            if amount <= 0 {
+                return Err(ApiError::validation("Store credit must be positive"));
+            }
+            closing.credit_amount = Some(amount);
+        }
+        ReturnResolution::Exchange => {}
+    }
+
+    let ret = returns::close_return(&state.pool, ret.id, &closing).await?;
+    notify_customer(&state.pool, &order, &ret).await?;
+
+    let response = return_response(&state.pool, ret).await?;
+
+    Ok(Json(response))
+}
+
+/// Mount return routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route(
+            "/api/v1/orders/:id/returns",
+            get(list_order_returns).post(create_return),
+        )
+        .route("/api/v1/returns/:id", get(get_return))
+        .route(
+            "/api/v1/returns/:id/photos",
+            post(upload_photo).layer(DefaultBodyLimit::max(MAX_PHOTO_BYTES + 64 * 1024)),
+        )
+        .route("/api/v1/returns/:id/photos/:photo_id", get(get_photo))
+        .route("/api/v1/admin/returns", get(list_returns))
+        .route("/api/v1/admin/returns/:id/approve", post(approve_return))
+        .route("/api/v1/admin/returns/:id/reject", post(reject_return))
+        .route("/api/v1/admin/returns/:id/receive", post(receive_return))
+        .route("/api/v1/admin/returns/:id/close", post(close_return))
+}
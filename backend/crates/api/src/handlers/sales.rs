@@ -0,0 +1,203 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CatalogPreviewParams, PaginatedResponse, PaginationMeta, PaginationParams,
+        ProductListParams, ProductResponse, ProductVariantResponse, SaleRequest, SaleResponse,
+    },
+    models::Sale,
+    Result,
+};
+use goseli_db::{currencies, products, sales, stores};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::currency::RequestedCurrency;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/sales - List sales, latest start first (admin)
+async fn list_sales(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<PaginatedResponse<Sale>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let data = sales::list_sales(&state.pool, store_id, params.limit(), params.offset()).await?;
+    let total = sales::count_sales(&state.pool, store_id).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/sales - Schedule a sale (admin)
+async fn create_sale(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<SaleRequest>,
+) -> Result<(StatusCode, Json<SaleResponse>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let sale = sales::create_sale(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(sale)))
+}
+
+/// GET /api/v1/admin/sales/:id - Get a sale with its items (admin)
+async fn get_sale(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SaleResponse>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let sale = sales::get_sale(&state.pool, store_id, id).await?;
+    Ok(Json(sale))
+}
+
+/// PUT /api/v1/admin/sales/:id - Replace a sale and its items (admin)
+async fn update_sale(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<SaleRequest>,
+) -> Result<Json<SaleResponse>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let sale = sales::update_sale(&state.pool, store_id, id, &req).await?;
+    Ok(Json(sale))
+}
+
+/// DELETE /api/v1/admin/sales/:id - Delete a sale (admin)
+async fn delete_sale(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    sales::delete_sale(&state.pool, store_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/admin/catalog/preview - List products priced as of `at`, with
+/// the sales running then and optionally a customer group's prices (admin)
+async fn preview_products(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    RequestedCurrency(requested): RequestedCurrency,
+    Query(preview): Query<CatalogPreviewParams>,
+    Query(params): Query<ProductListParams>,
+) -> Result<Json<PaginatedResponse<ProductResponse>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let page = params.page.unwrap_or(1);
+    let per_page = params.per_page.unwrap_or(20);
+    let pagination = PaginationParams { page, per_page };
+
+    let items = products::list_products(&state.pool, store_id, page, per_page, &params).await?;
+    let total = products::count_products(&state.pool, store_id, &params).await?;
+
+    let currency = stores::get_store(&state.pool, store_id).await?.currency;
+    let product_ids: Vec<Uuid> = items.iter().map(|product| product.id).collect();
+    let pricing = currencies::display_pricing_at(
+        &state.pool,
+        store_id,
+        currency,
+        requested,
+        preview.customer_group.as_deref(),
+        &product_ids,
+        preview.at,
+    )
+    .await?;
+    let data = items
+        .into_iter()
+        .map(|product| {
+            let mut response = ProductResponse::new(product);
+            response.reprice(&pricing)?;
+            Ok(response)
+        })
+        .collect::<Result<Vec<ProductResponse>>>()?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&pagination, total),
+    }))
+}
+
+/// GET /api/v1/admin/catalog/preview/:id - Get a product with its variants
+/// priced as of `at`, optionally for a customer group (admin)
+async fn preview_product(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    RequestedCurrency(requested): RequestedCurrency,
+    Path(id): Path<Uuid>,
+    Query(preview): Query<CatalogPreviewParams>,
+) -> Result<Json<ProductResponse>> {
+    auth_user.require_admin()?;
+
+    let product = products::get_product_by_id(&state.pool, id).await?;
+    let images = products::get_product_images(&state.pool, product.id).await?;
+    let variants = products::get_product_variants(&state.pool, product.id).await?;
+
+    let store_id = product.store_id;
+    let currency = stores::get_store(&state.pool, store_id).await?.currency;
+
+    let mut response = ProductResponse::new(product);
+    response.images = images;
+    response.variants = variants
+        .into_iter()
+        .map(ProductVariantResponse::new)
+        .collect();
+    let pricing = currencies::display_pricing_at(
+        &state.pool,
+        store_id,
+        currency,
+        requested,
+        preview.customer_group.as_deref(),
+        &[response.id],
+        preview.at,
+    )
+    .await?;
+    response.reprice(&pricing)?;
+
+    Ok(Json(response))
+}
+
+/// Mount sale routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/admin/sales", get(list_sales).post(create_sale))
+        .route(
+            "/api/v1/admin/sales/:id",
+            get(get_sale).put(update_sale).delete(delete_sale),
+        )
+        .route("/api/v1/admin/catalog/preview", get(preview_products))
+        .route("/api/v1/admin/catalog/preview/:id", get(preview_product))
+}
@@ -0,0 +1,185 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use axum_extra::extract::CookieJar;
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        CreateShippingMethodRequest, CreateShippingZoneRequest, ShippingRate, ShippingRateRequest,
+        ShippingZoneResponse, UpdateShippingMethodRequest, UpdateShippingZoneRequest,
+    },
+    models::{ShippingMethod, ShippingZone},
+    ApiError, Result,
+};
+use goseli_db::{cart, shipping};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+const SESSION_COOKIE_NAME: &str = "goseli_session";
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// POST /api/v1/shipping/rates - Shipping options for the current cart and an address
+async fn quote_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: Option<AuthUser>,
+    jar: CookieJar,
+    Json(req): Json<ShippingRateRequest>,
+) -> Result<Json<Vec<ShippingRate>>> {
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let user_id = auth_user.as_ref().map(|user| user.user_id);
+    let session_id = jar.get(SESSION_COOKIE_NAME).map(|c| c.value().to_string());
+    let cart = cart::find_cart(&state.pool, store_id, user_id, session_id.as_deref())
+        .await?
+        .ok_or_else(|| ApiError::bad_request("Cart is empty"))?;
+
+    let rates = crate::shipping::quote_cart(&state, store_id, cart.id, &req.address.into()).await?;
+
+    Ok(Json(rates))
+}
+
+/// GET /api/v1/admin/shipping/zones - List zones with their methods (admin)
+async fn list_zones(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<ShippingZoneResponse>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let zones = shipping::list_zones(&state.pool, store_id, false).await?;
+    let methods = shipping::list_methods(&state.pool, store_id, false).await?;
+
+    let data = zones
+        .into_iter()
+        .map(|zone| ShippingZoneResponse {
+            methods: methods
+                .iter()
+                .filter(|method| method.zone_id == zone.id)
+                .cloned()
+                .collect(),
+            zone,
+        })
+        .collect();
+
+    Ok(Json(data))
+}
+
+/// POST /api/v1/admin/shipping/zones - Create a zone (admin)
+async fn create_zone(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<CreateShippingZoneRequest>,
+) -> Result<(StatusCode, Json<ShippingZone>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let zone = shipping::create_zone(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(zone)))
+}
+
+/// PUT /api/v1/admin/shipping/zones/:id - Update a zone (admin)
+async fn update_zone(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateShippingZoneRequest>,
+) -> Result<Json<ShippingZone>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let zone = shipping::update_zone(&state.pool, id, &req).await?;
+    Ok(Json(zone))
+}
+
+/// DELETE /api/v1/admin/shipping/zones/:id - Delete a zone and its methods (admin)
+async fn delete_zone(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    shipping::delete_zone(&state.pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/admin/shipping/zones/:id/methods - Add a method to a zone (admin)
+async fn create_method(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateShippingMethodRequest>,
+) -> Result<(StatusCode, Json<ShippingMethod>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let method = shipping::create_method(&state.pool, id, &req).await?;
+    Ok((StatusCode::CREATED, Json(method)))
+}
+
+/// PUT /api/v1/admin/shipping/methods/:id - Update a method (admin)
+async fn update_method(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateShippingMethodRequest>,
+) -> Result<Json<ShippingMethod>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let method = shipping::update_method(&state.pool, id, &req).await?;
+    Ok(Json(method))
+}
+
+/// DELETE /api/v1/admin/shipping/methods/:id - Delete a method (admin)
+async fn delete_method(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    shipping::delete_method(&state.pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mount shipping routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/shipping/rates", post(quote_rates))
+        .route(
+            "/api/v1/admin/shipping/zones",
+            get(list_zones).post(create_zone),
+        )
+        .route(
+            "/api/v1/admin/shipping/zones/:id",
+            put(update_zone).delete(delete_zone),
+        )
+        .route(
+            "/api/v1/admin/shipping/zones/:id/methods",
+            post(create_method),
+        )
+        .route(
+            "/api/v1/admin/shipping/methods/:id",
+            put(update_method).delete(delete_method),
+        )
+}
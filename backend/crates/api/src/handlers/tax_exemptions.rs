@@ -0,0 +1,327 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{
+        end_of_day, parse_date_param, PaginatedResponse, PaginationMeta, PaginationParams,
+        TaxExemptionDecisionRequest, TaxExemptionListFilter,
+    },
+    models::{TaxExemption, TaxExemptionStatus},
+    ApiError, Result,
+};
+use goseli_db::taxes::{self, NewTaxExemption};
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+const MAX_CERTIFICATE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// A certificate upload: the file, its number and its expiry date
+struct CertificateUpload {
+    data: Vec<u8>,
+    content_type: &'static str,
+    certificate_number: Option<String>,
+    expires_at: OffsetDateTime,
+}
+
+/// Read the `certificate` file, `certificate_number` and `expires_on` fields
+async fn read_certificate(mut multipart: Multipart) -> Result<CertificateUpload> {
+    let mut data = None;
+    let mut certificate_number = None;
+    let mut expires_on = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+    {
+        match field.name() {
+            Some("certificate") => {
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|e| ApiError::bad_request(e.to_string()))?;
+                data = Some(bytes.to_vec());
+            }
+            Some("certificate_number") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| ApiError::bad_request(e.to_string()))?;
+                certificate_number = Some(text.trim().to_string()).filter(|s| !s.is_empty());
+            }
+            Some("expires_on") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| ApiError::bad_request(e.to_string()))?;
+                expires_on = Some(text.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    let data = data.ok_or_else(|| ApiError::validation("Missing 'certificate' file"))?;
+    if data.is_empty() || data.len() > MAX_CERTIFICATE_BYTES {
+        return Err(ApiError::validation(format!(
+            "Certificates must be between 1 byte and {} MB",
+            MAX_CERTIFICATE_BYTES / (1024 * 1024)
+        )));
+    }
+    let content_type = goseli_storage::document_content_type(&data).ok_or_else(|| {
+        ApiError::validation("Certificates must be PDF documents or JPEG, PNG or WebP images")
+    })?;
+    if certificate_number.as_ref().is_some_and(|n| n.len() > 100) {
+        return Err(ApiError::validation(
+            "certificate_number must be at most 100 characters",
+        ));
+    }
+
+    let expires_on = expires_on.ok_or_else(|| ApiError::validation("Missing 'expires_on'"))?;
+    let expires_at = end_of_day(parse_date_param("expires_on", &expires_on)?);
+    if expires_at <= OffsetDateTime::now_utc() {
+        return Err(ApiError::validation("The certificate has already expired"));
+    }
+
+    Ok(CertificateUpload {
+        data,
+        content_type,
+        certificate_number,
+        expires_at,
+    })
+}
+
+/// Store the certificate file and record the exemption
+async fn save_certificate(
+    state: &crate::AppState,
+    user_id: Uuid,
+    upload: CertificateUpload,
+    approved_by: Option<Uuid>,
+) -> Result<TaxExemption> {
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let id = Uuid::now_v7();
+    let key = format!(
+        "tax-exemptions/{}/{}.{}",
+        user_id,
+        id,
+        goseli_storage::extension_for(upload.content_type)
+    );
+    state.storage.put(&key, &upload.data).await?;
+
+    let new = NewTaxExemption {
+        id,
+        store_id,
+        user_id,
+        certificate_number: upload.certificate_number,
+        storage_key: key.clone(),
+        content_type: upload.content_type.to_string(),
+        size_bytes: upload.data.len() as i32,
+        expires_at: upload.expires_at,
+    };
+    match taxes::create_exemption(&state.pool, &new, approved_by).await {
+        Ok(exemption) => Ok(exemption),
+        Err(e) => {
+            // Don't leave the file behind when the exemption is refused
+            if let Err(delete_err) = state.storage.delete(&key).await {
+                tracing::warn!("Failed to delete certificate {}: {}", key, delete_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// GET /api/v1/account/tax-exemptions - List my tax exemptions
+async fn list_my_exemptions(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<TaxExemption>>> {
+    let exemptions = taxes::list_user_exemptions(&state.pool, auth_user.user_id).await?;
+    Ok(Json(exemptions))
+}
+
+/// POST /api/v1/account/tax-exemptions - Upload an exemption certificate for review
+async fn upload_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<TaxExemption>)> {
+    let upload = read_certificate(multipart).await?;
+    let exemption = save_certificate(&state, auth_user.user_id, upload, None).await?;
+    Ok((StatusCode::CREATED, Json(exemption)))
+}
+
+/// GET /api/v1/tax-exemptions/:id/certificate - Download a certificate (owner or admin)
+async fn get_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse> {
+    let exemption = taxes::get_exemption(&state.pool, id).await?;
+    if auth_user.require_admin().is_err() && exemption.user_id != auth_user.user_id {
+        return Err(ApiError::not_found("Tax exemption not found"));
+    }
+
+    let data = state.storage.get(&exemption.storage_key).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, exemption.content_type),
+            (header::CACHE_CONTROL, "private, max-age=3600".to_string()),
+        ],
+        data,
+    ))
+}
+
+/// GET /api/v1/admin/tax-exemptions - List tax exemptions, optionally by status (admin)
+async fn list_exemptions(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<PaginationParams>,
+    Query(filter): Query<TaxExemptionListFilter>,
+) -> Result<Json<PaginatedResponse<TaxExemption>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+
+    let data = taxes::list_exemptions(
+        &state.pool,
+        store_id,
+        filter.status,
+        params.limit(),
+        params.offset(),
+    )
+    .await?;
+    let total = taxes::count_exemptions(&state.pool, store_id, filter.status).await?;
+
+    Ok(Json(PaginatedResponse {
+        data,
+        pagination: PaginationMeta::new(&params, total),
+    }))
+}
+
+/// POST /api/v1/admin/customers/:user_id/tax-exemptions - Upload a certificate for a customer, approved right away (admin)
+async fn upload_customer_certificate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<TaxExemption>)> {
+    auth_user.require_admin()?;
+
+    let exists: Option<(Uuid,)> = sqlx::query_as("SELECT id FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await?;
+    if exists.is_none() {
+        return Err(ApiError::not_found("Customer not found"));
+    }
+
+    let upload = read_certificate(multipart).await?;
+    let exemption = save_certificate(&state, user_id, upload, Some(auth_user.user_id)).await?;
+    Ok((StatusCode::CREATED, Json(exemption)))
+}
+
+async fn decide(
+    state: &crate::AppState,
+    auth_user: &AuthUser,
+    id: Uuid,
+    next: TaxExemptionStatus,
+    req: TaxExemptionDecisionRequest,
+) -> Result<Json<TaxExemption>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let expires_at = match next {
+        TaxExemptionStatus::Approved => req.expires_at()?,
+        _ => None,
+    };
+    let exemption = taxes::decide_exemption(
+        &state.pool,
+        id,
+        next,
+        expires_at,
+        req.note.as_deref(),
+        auth_user.user_id,
+    )
+    .await?;
+    Ok(Json(exemption))
+}
+
+/// POST /api/v1/admin/tax-exemptions/:id/approve - Approve a certificate (admin)
+async fn approve_exemption(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<TaxExemptionDecisionRequest>,
+) -> Result<Json<TaxExemption>> {
+    decide(&state, &auth_user, id, TaxExemptionStatus::Approved, req).await
+}
+
+/// POST /api/v1/admin/tax-exemptions/:id/reject - Reject a certificate (admin)
+async fn reject_exemption(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<TaxExemptionDecisionRequest>,
+) -> Result<Json<TaxExemption>> {
+    decide(&state, &auth_user, id, TaxExemptionStatus::Rejected, req).await
+}
+
+/// POST /api/v1/admin/tax-exemptions/:id/revoke - Revoke an approved exemption (admin)
+async fn revoke_exemption(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<TaxExemptionDecisionRequest>,
+) -> Result<Json<TaxExemption>> {
+    decide(&state, &auth_user, id, TaxExemptionStatus::Revoked, req).await
+}
+
+/// Mount tax exemption routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    let upload_limit = DefaultBodyLimit::max(MAX_CERTIFICATE_BYTES + 64 * 1024);
+    Router::new()
+        .route(
+            "/api/v1/account/tax-exemptions",
+            get(list_my_exemptions)
+                .post(upload_certificate)
+                .layer(upload_limit),
+        )
+        .route(
+            "/api/v1/tax-exemptions/:id/certificate",
+            get(get_certificate),
+        )
+        .route("/api/v1/admin/tax-exemptions", get(list_exemptions))
+        .route(
+            "/api/v1/admin/customers/:user_id/tax-exemptions",
+            post(upload_customer_certificate).layer(upload_limit),
+        )
+        .route(
+            "/api/v1/admin/tax-exemptions/:id/approve",
+            post(approve_exemption),
+        )
+        .route(
+            "/api/v1/admin/tax-exemptions/:id/reject",
+            post(reject_exemption),
+        )
+        .route(
+            "/api/v1/admin/tax-exemptions/:id/revoke",
+            post(revoke_exemption),
+        )
+}
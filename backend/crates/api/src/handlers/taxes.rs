@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use goseli_auth::AuthUser;
+use goseli_core::{
+    dto::{CreateTaxRateRequest, ReportFormat, TaxReport, TaxReportParams, UpdateTaxRateRequest},
+    models::TaxRate,
+    ApiError, Result,
+};
+use goseli_db::taxes;
+use sqlx::PgPool;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Helper to get default store ID (temporary until domain-based routing)
+async fn get_default_store_id(pool: &PgPool) -> Result<Uuid> {
+    let row: (Uuid,) = sqlx::query_as("SELECT id FROM stores LIMIT 1")
+        .fetch_one(pool)
+        .await?;
+    Ok(row.0)
+}
+
+/// GET /api/v1/admin/tax/rates - List tax rates (admin)
+async fn list_rates(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+) -> Result<Json<Vec<TaxRate>>> {
+    auth_user.require_admin()?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let rates = taxes::list_rates(&state.pool, store_id, false).await?;
+    Ok(Json(rates))
+}
+
+/// POST /api/v1/admin/tax/rates - Create a tax rate (admin)
+async fn create_rate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Json(req): Json<CreateTaxRateRequest>,
+) -> Result<(StatusCode, Json<TaxRate>)> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let store_id = get_default_store_id(&state.pool).await?;
+    let rate = taxes::create_rate(&state.pool, store_id, &req).await?;
+    Ok((StatusCode::CREATED, Json(rate)))
+}
+
+/// PUT /api/v1/admin/tax/rates/:id - Update a tax rate (admin)
+async fn update_rate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateTaxRateRequest>,
+) -> Result<Json<TaxRate>> {
+    auth_user.require_admin()?;
+    req.validate()
+        .map_err(|e| goseli_core::error::ApiError::validation(e.to_string()))?;
+
+    let rate = taxes::update_rate(&state.pool, id, &req).await?;
+    Ok(Json(rate))
+}
+
+/// DELETE /api/v1/admin/tax/rates/:id - Delete a tax rate (admin)
+async fn delete_rate(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    auth_user.require_admin()?;
+
+    taxes::delete_rate(&state.pool, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Render a tax report as CSV, one row per currency, jurisdiction and rate,
+/// followed by a row per currency for exempt sales
+fn report_csv(report: &TaxReport) -> Result<Vec<u8>> {
+    let csv_error = |e: csv::Error| ApiError::internal(format!("Failed to write CSV: {}", e));
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer
+        .write_record([
+            "currency",
+            "country",
+            "region",
+            "name",
+            "rate_ppm",
+            "order_count",
+            "taxable_amount",
+            "tax_amount",
+        ])
+        .map_err(csv_error)?;
+    for row in &report.rows {
+        writer
+            .write_record([
+                row.currency.clone(),
+                row.country.clone(),
+                row.region.clone().unwrap_or_default(),
+                row.name.clone(),
+                row.rate_ppm.to_string(),
+                row.order_count.to_string(),
+                row.taxable_amount.to_string(),
+                row.tax_amount.to_string(),
+            ])
+            .map_err(csv_error)?;
+    }
+    for row in &report.exempt_sales {
+        writer
+            .write_record([
+                row.currency.clone(),
+                String::new(),
+                String::new(),
+                "Tax exempt".to_string(),
+                "0".to_string(),
+                row.order_count.to_string(),
+                row.amount.to_string(),
+                "0".to_string(),
+            ])
+            .map_err(csv_error)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| ApiError::internal(format!("Failed to write CSV: {}", e)))
+}
+
+/// GET /api/v1/admin/reports/tax - Tax collected by jurisdiction and rate, as JSON or CSV (admin)
+async fn tax_report(
+    State(state): State<Arc<crate::AppState>>,
+    auth_user: AuthUser,
+    Query(params): Query<TaxReportParams>,
+) -> Result<Response> {
+    auth_user.require_admin()?;
+
+    let (from, to) = params.period(OffsetDateTime::now_utc().date())?;
+    let store_id = get_default_store_id(&state.pool).await?;
+    let report = taxes::tax_report(&state.pool, store_id, from, to).await?;
+
+    match params.format {
+        ReportFormat::Json => Ok(Json(report).into_response()),
+        ReportFormat::Csv => {
+            let filename = format!(
+                "tax-{}-{}.csv",
+                from.date(),
+                (to - time::Duration::days(1)).date()
+            );
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", filename),
+                    ),
+                ],
+                report_csv(&report)?,
+            )
+                .into_response())
+        }
+    }
+}
+
+/// Mount tax routes
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new()
+        .route("/api/v1/admin/tax/rates", get(list_rates).post(create_rate))
+        .route(
+            "/api/v1/admin/tax/rates/:id",
+            put(update_rate).delete(delete_rate),
+        )
+        .route("/api/v1/admin/reports/tax", get(tax_report))
+}
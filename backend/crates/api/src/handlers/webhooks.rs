@@ -0,0 +1,75 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use goseli_core::{ApiError, Result};
+use goseli_db::payment_webhooks;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// POST /api/v1/webhooks/payments/:provider - Receive a signed payment provider event
+///
+/// The event is verified and stored, then applied in the background so the
+/// provider gets a fast answer. Redelivered events are acknowledged again
+/// without being stored twice.
+async fn receive_payment_webhook(
+    State(state): State<Arc<crate::AppState>>,
+    Path(provider_name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode> {
+    let provider = state
+        .payments
+        .get(&provider_name)
+        .ok_or_else(|| ApiError::not_found("Unknown payment provider"))?;
+
+    let signature = headers
+        .get(provider.signature_header())
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| ApiError::unauthorized("Missing webhook signature"))?;
+    provider
+        .verify_webhook(&body, signature, OffsetDateTime::now_utc())
+        .map_err(crate::payments::provider_error)?;
+
+    let event = provider
+        .parse_webhook(&body)
+        .map_err(crate::payments::provider_error)?;
+    let payload = std::str::from_utf8(&body)
+        .map_err(|_| ApiError::bad_request("Webhook payload is not valid UTF-8"))?;
+
+    let inserted = payment_webhooks::record_event(
+        &state.pool,
+        provider.name(),
+        &event.id,
+        &event.event_type,
+        payload,
+    )
+    .await?;
+
+    if inserted {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::jobs::payment_webhooks::run(state).await {
+                tracing::error!("Payment webhook processing failed: {e}");
+            }
+        });
+    } else {
+        tracing::debug!(
+            "Duplicate {} webhook {} acknowledged",
+            provider.name(),
+            event.id
+        );
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn routes() -> Router<Arc<crate::AppState>> {
+    Router::new().route(
+        "/api/v1/webhooks/payments/:provider",
+        post(receive_payment_webhook),
+    )
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use goseli_auth::generate_cart_restore_token;
+use goseli_core::{
+    models::{AbandonedCart, AbandonedCartSettings, Store},
+    Result,
+};
+use goseli_db::{abandoned_carts, cart, notifications, stores};
+use time::{Duration, OffsetDateTime};
+
+use crate::AppState;
+
+const MESSAGE_BATCH_SIZE: i64 = 500;
+
+/// Detect abandoned carts and send due recovery messages for every store
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    for store in stores::list_active_stores(&state.pool).await? {
+        let settings = AbandonedCartSettings::from_store_config(&store.config);
+        if !settings.enabled {
+            continue;
+        }
+        process_store(&state, &store, &settings).await?;
+    }
+
+    Ok(())
+}
+
+async fn process_store(
+    state: &AppState,
+    store: &Store,
+    settings: &AbandonedCartSettings,
+) -> Result<()> {
+    let now = OffsetDateTime::now_utc();
+
+    let reactivated = abandoned_carts::close_reactivated(&state.pool, store.id).await?;
+    let newly_abandoned = abandoned_carts::mark_idle_carts_abandoned(
+        &state.pool,
+        store.id,
+        now - Duration::hours(settings.after_hours),
+    )
+    .await?;
+    let expired = abandoned_carts::expire_stale(
+        &state.pool,
+        store.id,
+        now - Duration::days(settings.expire_after_days),
+    )
+    .await?;
+
+    let mut messages_sent = 0;
+    for abandoned in abandoned_carts::list_open(&state.pool, store.id, MESSAGE_BATCH_SIZE).await? {
+        let due = settings.next_message_due(abandoned.abandoned_at, abandoned.messages_sent);
+        if due.is_some_and(|due| due <= now)
+            && send_recovery_message(state, store, &abandoned).await?
+        {
+            messages_sent += 1;
+        }
+    }
+
+    tracing::info!(
+        store = %store.slug,
+        newly_abandoned = newly_abandoned.len(),
+        reactivated,
+        expired,
+        messages_sent,
+        "Abandoned cart job finished"
+    );
+
+    Ok(())
+}
+
+/// Queue the next message in the recovery sequence with a signed restore link.
+///
+/// Every API instance runs this job, so the step is claimed before the
+/// message is queued, in one transaction; returns false when another
+/// instance got there first.
+async fn send_recovery_message(
+    state: &AppState,
+    store: &Store,
+    abandoned: &AbandonedCart,
+) -> Result<bool> {
+    let Some(cart_id) = abandoned.cart_id else {
+        return Ok(false);
+    };
+
+    let token = generate_cart_restore_token(abandoned.id)?;
+    let restore_url = format!("{}/cart/restore?token={}", storefront_url(), token);
+    let cart = cart::get_cart_with_items(&state.pool, cart_id).await?;
+
+    let payload = serde_json::json!({
+        "store_name": store.name,
+        "step": abandoned.messages_sent + 1,
+        "restore_url": restore_url,
+        "currency": store.currency,
+        "total": cart.total,
+        "items": cart.items.iter().map(|item| serde_json::json!({
+            "product_name": item.product_name,
+            "variant_name": item.variant_name,
+            "image_url": item.product_image_url,
+            "quantity": item.quantity,
+            "subtotal": item.subtotal,
+        })).collect::<Vec<_>>(),
+    });
+
+    let mut tx = state.pool.begin().await?;
+    if abandoned_carts::claim_next_message(&mut *tx, abandoned.id, abandoned.messages_sent)
+        .await?
+        .is_none()
+    {
+        return Ok(false);
+    }
+    notifications::enqueue(
+        &mut *tx,
+        store.id,
+        &abandoned.email,
+        "abandoned_cart_reminder",
+        &payload,
+        OffsetDateTime::now_utc(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+fn storefront_url() -> String {
+    std::env::var("STOREFRONT_URL").unwrap_or_else(|_| "http://localhost:3000".to_string())
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use goseli_core::{
+    models::{FulfillmentStatus, ShippingLabel, TrackingStatus},
+    Result,
+};
+use goseli_db::{fulfillments, orders, shipping_labels};
+use time::{Duration, OffsetDateTime};
+
+use crate::AppState;
+
+const BATCH_SIZE: i64 = 50;
+/// How long a parcel's status is trusted before the carrier is asked again
+const RECHECK_AFTER: Duration = Duration::minutes(30);
+
+/// Poll carriers for shipped parcels with a purchased label, marking
+/// delivered parcels as delivered
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    let due = OffsetDateTime::now_utc() - RECHECK_AFTER;
+    let labels = shipping_labels::labels_to_track(&state.pool, due, BATCH_SIZE).await?;
+
+    for label in labels {
+        if let Err(e) = track(&state, &label).await {
+            tracing::warn!(
+                label_id = %label.id,
+                carrier = %label.carrier,
+                "Tracking poll failed: {e}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn track(state: &AppState, label: &ShippingLabel) -> Result<()> {
+    let carrier = crate::shipping::carrier_for(state, &label.carrier)?;
+    let Some(tracking_number) = label.tracking_number.as_deref() else {
+        return Ok(());
+    };
+
+    let info = carrier
+        .track(tracking_number)
+        .await
+        .map_err(crate::shipping::carrier_error)?;
+    let status = crate::shipping::tracking_status(info.status);
+    let fulfillment =
+        shipping_labels::record_tracking(&state.pool, label.fulfillment_id, status).await?;
+
+    if status == TrackingStatus::Delivered && fulfillment.status == FulfillmentStatus::Shipped {
+        let fulfillment = fulfillments::deliver_fulfillment(&state.pool, fulfillment.id).await?;
+        let order = orders::get_order(&state.pool, fulfillment.order_id).await?;
+        let items = fulfillments::get_fulfillment_items(&state.pool, fulfillment.id).await?;
+        crate::handlers::fulfillments::notify_customer(&state.pool, &order, &fulfillment, &items)
+            .await?;
+    }
+
+    Ok(())
+}
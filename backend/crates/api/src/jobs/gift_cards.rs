@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use goseli_core::Result;
+use goseli_db::gift_cards;
+use time::OffsetDateTime;
+
+use crate::AppState;
+
+/// Cards cleared per statement
+const BATCH_SIZE: i64 = 500;
+
+/// Clear the balance of gift cards past their expiry date
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let mut expired = 0;
+
+    loop {
+        let cleared = gift_cards::expire_cards(&state.pool, now, BATCH_SIZE).await?;
+        expired += cleared;
+        if cleared < BATCH_SIZE as u64 {
+            break;
+        }
+    }
+
+    if expired > 0 {
+        tracing::info!(expired, "Expired gift card balances cleared");
+    }
+
+    Ok(())
+}
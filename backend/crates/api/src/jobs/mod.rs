@@ -0,0 +1,83 @@
+// Background jobs - periodic maintenance and customer messaging
+
+pub mod abandoned_carts;
+pub mod carrier_tracking;
+pub mod gift_cards;
+pub mod notifications;
+pub mod payment_webhooks;
+pub mod retention;
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use goseli_core::Result;
+
+use crate::AppState;
+
+/// Spawn all background jobs on the Tokio runtime
+pub fn spawn_all(state: Arc<AppState>) {
+    spawn_periodic(
+        "abandoned_carts",
+        interval_from_env("ABANDONED_CART_JOB_INTERVAL_SECS", 900),
+        state.clone(),
+        abandoned_carts::run,
+    );
+    spawn_periodic(
+        "carrier_tracking",
+        interval_from_env("CARRIER_TRACKING_JOB_INTERVAL_SECS", 300),
+        state.clone(),
+        carrier_tracking::run,
+    );
+    spawn_periodic(
+        "gift_cards",
+        interval_from_env("GIFT_CARD_JOB_INTERVAL_SECS", 3600),
+        state.clone(),
+        gift_cards::run,
+    );
+    spawn_periodic(
+        "notifications",
+        interval_from_env("NOTIFICATION_JOB_INTERVAL_SECS", 30),
+        state.clone(),
+        notifications::run,
+    );
+    spawn_periodic(
+        "payment_webhooks",
+        interval_from_env("PAYMENT_WEBHOOK_JOB_INTERVAL_SECS", 30),
+        state.clone(),
+        payment_webhooks::run,
+    );
+    spawn_periodic(
+        "retention",
+        interval_from_env("RETENTION_JOB_INTERVAL_SECS", 3600),
+        state,
+        retention::run,
+    );
+}
+
+/// Run `job` every `interval`, logging (not propagating) failures
+fn spawn_periodic<F, Fut>(name: &'static str, interval: Duration, state: Arc<AppState>, job: F)
+where
+    F: Fn(Arc<AppState>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            if let Err(e) = job(state.clone()).await {
+                tracing::error!(job = name, "Background job failed: {e}");
+            }
+        }
+    });
+}
+
+fn interval_from_env(key: &str, default_secs: u64) -> Duration {
+    let secs = std::env::var(key)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default_secs);
+    Duration::from_secs(secs.max(1))
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use goseli_core::{models::Notification, Result};
+use goseli_db::notifications;
+
+use crate::AppState;
+
+const BATCH_SIZE: i64 = 50;
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Drain due notifications from the outbox
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    let batch = notifications::claim_pending(&state.pool, BATCH_SIZE).await?;
+
+    for notification in batch {
+        match deliver(&notification).await {
+            Ok(()) => notifications::mark_sent(&state.pool, notification.id).await?,
+            Err(e) => {
+                tracing::warn!(id = %notification.id, "Notification delivery failed: {e}");
+                notifications::mark_failed(
+                    &state.pool,
+                    notification.id,
+                    &e.to_string(),
+                    MAX_ATTEMPTS,
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deliver a single notification.
+// TODO: Send through the transactional email provider once it is configured;
+// until then messages are written to the log.
+async fn deliver(notification: &Notification) -> Result<()> {
+    tracing::info!(
+        id = %notification.id,
+        recipient = %notification.recipient,
+        template = %notification.template,
+        payload = %notification.payload,
+        "Delivering notification"
+    );
+    Ok(())
+}
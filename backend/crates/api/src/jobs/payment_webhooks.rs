@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use goseli_core::{
+    models::{PaymentWebhookEvent, WebhookEventStatus},
+    ApiError, Result,
+};
+use goseli_db::payment_webhooks;
+use time::Duration;
+
+use crate::AppState;
+
+const BATCH_SIZE: i64 = 50;
+const MAX_ATTEMPTS: i32 = 8;
+/// How long a claimed event is hidden from other workers
+const LEASE: Duration = Duration::minutes(5);
+
+/// Apply received payment webhooks, retrying failed ones with backoff
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    let batch = payment_webhooks::claim_pending(&state.pool, BATCH_SIZE, LEASE).await?;
+
+    for event in batch {
+        match process(&state, &event).await {
+            Ok(status) => payment_webhooks::mark_done(&state.pool, event.id, status).await?,
+            Err(e) => {
+                tracing::warn!(
+                    id = %event.id,
+                    provider = %event.provider,
+                    event_id = %event.event_id,
+                    "Payment webhook processing failed: {e}"
+                );
+                payment_webhooks::mark_failed(&state.pool, event.id, &e.to_string(), MAX_ATTEMPTS)
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-parse a stored event and apply it
+async fn process(state: &AppState, event: &PaymentWebhookEvent) -> Result<WebhookEventStatus> {
+    let provider = state.payments.get(&event.provider).ok_or_else(|| {
+        ApiError::internal(format!(
+            "Payment provider '{}' is not configured",
+            event.provider
+        ))
+    })?;
+    let parsed = provider
+        .parse_webhook(event.payload.as_bytes())
+        .map_err(crate::payments::provider_error)?;
+
+    crate::payments::apply_webhook_event(state, provider.name(), &parsed).await
+}
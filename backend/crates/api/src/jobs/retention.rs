@@ -0,0 +1,128 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+use goseli_core::Result;
+use goseli_db::retention;
+use time::{Duration, OffsetDateTime};
+
+use crate::AppState;
+
+/// How long data is kept before the retention job purges it.
+/// Configured through environment variables, shared by all stores.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Carts with no items are deleted after this many hours of inactivity
+    pub empty_cart_hours: i64,
+    /// Guest carts are deleted after this many days of inactivity
+    pub guest_cart_days: i64,
+    /// Rows deleted per statement, to keep locks and WAL bursts short
+    pub batch_size: i64,
+}
+
+impl RetentionPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            empty_cart_hours: env_or("RETENTION_EMPTY_CART_HOURS", 24),
+            // Matches the 30-day guest session cookie
+            guest_cart_days: env_or("RETENTION_GUEST_CART_DAYS", 30),
+            batch_size: env_or("RETENTION_BATCH_SIZE", 1000).max(1),
+        }
+    }
+
+    /// Carts with no items idle since before this are purged
+    pub fn empty_carts_before(&self, now: OffsetDateTime) -> OffsetDateTime {
+        now - Duration::hours(self.empty_cart_hours)
+    }
+
+    /// Guest carts idle since before this are purged
+    pub fn guest_carts_before(&self, now: OffsetDateTime) -> OffsetDateTime {
+        now - Duration::days(self.guest_cart_days)
+    }
+}
+
+fn env_or(key: &str, default: i64) -> i64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Purge expired carts, refresh tokens and idempotency keys
+pub async fn run(state: Arc<AppState>) -> Result<()> {
+    let policy = RetentionPolicy::from_env();
+    let now = OffsetDateTime::now_utc();
+    let pool = &state.pool;
+
+    let empty_carts_before = policy.empty_carts_before(now);
+    purge_in_batches("empty_carts", policy.batch_size, || {
+        retention::purge_empty_carts(pool, empty_carts_before, policy.batch_size)
+    })
+    .await?;
+
+    let guest_carts_before = policy.guest_carts_before(now);
+    purge_in_batches("guest_carts", policy.batch_size, || {
+        retention::purge_idle_guest_carts(pool, guest_carts_before, policy.batch_size)
+    })
+    .await?;
+
+    purge_in_batches("refresh_tokens", policy.batch_size, || {
+        retention::purge_expired_refresh_tokens(pool, policy.batch_size)
+    })
+    .await?;
+
+    purge_in_batches("idempotency_keys", policy.batch_size, || {
+        retention::purge_expired_idempotency_keys(pool, policy.batch_size)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Run `purge` until a batch comes back short, then log how much was removed
+async fn purge_in_batches<F, Fut>(name: &'static str, batch_size: i64, purge: F) -> Result<u64>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<u64>>,
+{
+    let started = Instant::now();
+    let mut total = 0;
+    let mut batches = 0;
+
+    loop {
+        let deleted = purge().await?;
+        total += deleted;
+        batches += 1;
+        if deleted < batch_size as u64 {
+            break;
+        }
+    }
+
+    tracing::info!(
+        target = name,
+        deleted = total,
+        batches,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "Retention purge finished"
+    );
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cutoffs() {
+        let policy = RetentionPolicy {
+            empty_cart_hours: 24,
+            guest_cart_days: 30,
+            batch_size: 1000,
+        };
+        let now = OffsetDateTime::now_utc();
+
+        assert_eq!(now - policy.empty_carts_before(now), Duration::hours(24));
+        assert_eq!(now - policy.guest_carts_before(now), Duration::days(30));
+    }
+}
@@ -1,8 +1,16 @@
 // Goseli API - Axum routes, handlers, middleware
 
+pub mod currency;
 pub mod handlers;
+pub mod jobs;
+pub mod middleware;
+pub mod payments;
+pub mod shipping;
 
 use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use goseli_payments::PaymentProviders;
+use goseli_shipping::ShippingCarriers;
+use goseli_storage::StorageBackend;
 use redis::aio::ConnectionManager;
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -13,6 +21,9 @@ use tower_http::trace::TraceLayer;
 pub struct AppState {
     pub pool: PgPool,
     pub redis: ConnectionManager,
+    pub payments: PaymentProviders,
+    pub carriers: ShippingCarriers,
+    pub storage: Arc<dyn StorageBackend>,
 }
 
 #[derive(serde::Serialize)]
@@ -69,10 +80,32 @@ pub fn build_router(state: Arc<AppState>) -> Router {
 
     Router::new()
         .route("/health", get(health_check))
+        .merge(handlers::abandoned_carts::routes())
         .merge(handlers::auth::routes())
         .merge(handlers::cart::routes())
+        .merge(handlers::carriers::routes())
         .merge(handlers::products::routes())
         .merge(handlers::categories::routes())
+        .merge(handlers::checkout::routes())
+        .merge(handlers::coupons::routes())
+        .merge(handlers::currencies::routes())
+        .merge(handlers::customer_groups::routes())
+        .merge(handlers::fulfillments::routes())
+        .merge(handlers::gift_cards::routes())
+        .merge(handlers::orders::routes())
+        .merge(handlers::payments::routes())
+        .merge(handlers::promotions::routes())
+        .merge(handlers::refunds::routes())
+        .merge(handlers::returns::routes())
+        .merge(handlers::sales::routes())
+        .merge(handlers::shipping::routes())
+        .merge(handlers::tax_exemptions::routes())
+        .merge(handlers::taxes::routes())
+        .merge(handlers::webhooks::routes())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::idempotency::idempotency,
+        ))
         .layer(TraceLayer::new_for_http())
         .layer(cors)
         .with_state(state)
@@ -9,7 +9,10 @@ use redis::aio::ConnectionManager;
 use sqlx::postgres::PgPoolOptions;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use goseli_api::{build_router, AppState};
+use goseli_api::{build_router, jobs, AppState};
+use goseli_payments::PaymentProviders;
+use goseli_shipping::ShippingCarriers;
+use goseli_storage::LocalStorage;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -40,7 +43,20 @@ async fn main() -> anyhow::Result<()> {
     let redis_client = redis::Client::open(redis_url)?;
     let redis = ConnectionManager::new(redis_client).await?;
 
-    let state = Arc::new(AppState { pool, redis });
+    let payments = PaymentProviders::from_env().context("Failed to configure payment providers")?;
+
+    let carriers = ShippingCarriers::from_env().context("Failed to configure shipping carriers")?;
+
+    let storage = Arc::new(LocalStorage::from_env());
+
+    let state = Arc::new(AppState {
+        pool,
+        redis,
+        payments,
+        carriers,
+        storage,
+    });
+    jobs::spawn_all(state.clone());
     let app = build_router(state);
 
     let port = std::env::var("BACKEND_PORT")
@@ -0,0 +1,285 @@
+//! `Idempotency-Key` support for POST and PUT requests.
+//!
+//! The first request with a key runs normally and its response is stored
+//! for `IDEMPOTENCY_KEY_TTL_HOURS`. A retry with the same key and the same
+//! method, path and body gets the stored response back (marked with
+//! `Idempotent-Replayed: true`) without running the handler again.
+//!
+//! - Same key, different request: 422 `idempotency_key_reused`
+//! - Same key while the first request is still running: 409, until
+//!   `IDEMPOTENCY_LOCK_TIMEOUT_SECONDS` pass and a retry takes the key over
+//! - Server errors (5xx) are not stored, so the client can retry them
+//!
+//! Keys only apply to signed-in users and guest sessions: a response stored
+//! for a cookie-less client could be replayed to anyone sending the same
+//! key, so a key sent without a token or session cookie is a 400 rather
+//! than being silently ignored. `Set-Cookie` headers are never stored.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+use goseli_auth::validate_token;
+use goseli_core::{
+    models::{IdempotencyRecord, IdempotencyStatus},
+    ApiError, Result,
+};
+use goseli_db::idempotency::{self, IdempotencyClaim};
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
+
+use crate::AppState;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+const SESSION_COOKIE_NAME: &str = "goseli_session";
+const MAX_KEY_LENGTH: usize = 255;
+/// Upper bound on buffered request bodies (CSV quick orders are the largest)
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+fn key_ttl() -> Duration {
+    let hours = std::env::var("IDEMPOTENCY_KEY_TTL_HOURS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(24);
+    Duration::hours(hours)
+}
+
+fn lock_timeout() -> Duration {
+    let seconds = std::env::var("IDEMPOTENCY_LOCK_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(300);
+    Duration::seconds(seconds)
+}
+
+/// Middleware entry point, mounted with `axum::middleware::from_fn_with_state`
+pub async fn idempotency(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    match handle(&state, req, next).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn handle(state: &AppState, req: Request, next: Next) -> Result<Response> {
+    if !matches!(*req.method(), Method::POST | Method::PUT) {
+        return Ok(next.run(req).await);
+    }
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+    let key = key
+        .to_str()
+        .ok()
+        .filter(|k| !k.is_empty() && k.len() <= MAX_KEY_LENGTH)
+        .ok_or_else(|| ApiError::validation("Idempotency-Key must be 1-255 ASCII characters"))?
+        .to_string();
+
+    let scope = request_scope(req.headers()).ok_or_else(|| {
+        ApiError::bad_request("Idempotency-Key needs a signed-in user or a guest session cookie")
+    })?;
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::bad_request("Request body is too large"))?;
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let fingerprint = fingerprint(&parts.method, path, &body);
+
+    let now = OffsetDateTime::now_utc();
+    let claim = idempotency::claim(
+        &state.pool,
+        &scope,
+        &key,
+        &fingerprint,
+        now + key_ttl(),
+        now - lock_timeout(),
+    )
+    .await?;
+    match claim {
+        IdempotencyClaim::Existing(record) => {
+            if record.fingerprint != fingerprint {
+                return Err(ApiError::rule(
+                    "idempotency_key_reused",
+                    "This Idempotency-Key was already used for a different request",
+                ));
+            }
+            match record.status {
+                IdempotencyStatus::InProgress => Err(ApiError::conflict(
+                    "A request with this Idempotency-Key is still being processed",
+                )),
+                IdempotencyStatus::Completed => Ok(replay(record)),
+            }
+        }
+        IdempotencyClaim::Claimed(id) => {
+            let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+            Ok(store_response(state, id, response).await)
+        }
+    }
+}
+
+/// Keys are scoped to whoever sent them: the signed-in user or the guest
+/// session. None for cookie-less clients, whose keys are rejected
+fn request_scope(headers: &HeaderMap) -> Option<String> {
+    let user_id = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .and_then(|token| validate_token(token).ok())
+        .map(|claims| claims.sub);
+    if let Some(user_id) = user_id {
+        return Some(format!("user:{}", user_id));
+    }
+
+    CookieJar::from_headers(headers)
+        .get(SESSION_COOKIE_NAME)
+        .map(|cookie| format!("session:{}", cookie.value()))
+}
+
+/// SHA-256 over method, path and body; a retry must match it exactly
+fn fingerprint(method: &Method, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Record the handler's response under the claimed key and pass it on
+async fn store_response(state: &AppState, id: uuid::Uuid, response: Response) -> Response {
+    if response.status().is_server_error() {
+        release(state, id).await;
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to buffer response for idempotency key: {e}");
+            release(state, id).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if let Err(e) = idempotency::complete(
+        &state.pool,
+        id,
+        parts.status.as_u16() as i16,
+        &stored_headers(&parts.headers),
+        &body,
+    )
+    .await
+    {
+        tracing::error!("Failed to store idempotent response: {e}");
+        release(state, id).await;
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Response headers to replay as `[name, value]` pairs; cookies set for the
+/// first request are left out
+fn stored_headers(headers: &HeaderMap) -> serde_json::Value {
+    let headers: Vec<(String, String)> = headers
+        .iter()
+        .filter(|(name, _)| *name != header::SET_COOKIE)
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    serde_json::to_value(headers).unwrap_or_default()
+}
+
+/// Drop a claim so the client can retry; failures only delay retries until the TTL
+async fn release(state: &AppState, id: uuid::Uuid) {
+    if let Err(e) = idempotency::release(&state.pool, id).await {
+        tracing::error!("Failed to release idempotency key {id}: {e}");
+    }
+}
+
+/// Rebuild a stored response
+fn replay(record: IdempotencyRecord) -> Response {
+    let status = record
+        .response_status
+        .and_then(|s| StatusCode::from_u16(s as u16).ok())
+        .unwrap_or(StatusCode::OK);
+    let mut response = Response::new(Body::from(Bytes::from(
+        record.response_body.unwrap_or_default(),
+    )));
+    *response.status_mut() = status;
+
+    let headers: Vec<(String, String)> = record
+        .response_headers
+        .and_then(|h| serde_json::from_value(h).ok())
+        .unwrap_or_default();
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+        .headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_covers_method_path_and_body() {
+        let base = fingerprint(&Method::POST, "/api/v1/orders", b"{}");
+        assert_eq!(base, fingerprint(&Method::POST, "/api/v1/orders", b"{}"));
+        assert_ne!(base, fingerprint(&Method::PUT, "/api/v1/orders", b"{}"));
+        assert_ne!(
+            base,
+            fingerprint(&Method::POST, "/api/v1/cart/items", b"{}")
+        );
+        assert_ne!(
+            base,
+            fingerprint(&Method::POST, "/api/v1/orders", b"{\"a\":1}")
+        );
+    }
+
+    #[test]
+    fn test_cookieless_requests_have_no_scope() {
+        assert_eq!(request_scope(&HeaderMap::new()), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("goseli_session=abc"),
+        );
+        assert_eq!(request_scope(&headers).as_deref(), Some("session:abc"));
+    }
+
+    #[test]
+    fn test_stored_headers_drop_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        headers.insert(
+            header::SET_COOKIE,
+            HeaderValue::from_static("goseli_session=abc; Path=/"),
+        );
+
+        assert_eq!(
+            stored_headers(&headers),
+            serde_json::json!([["content-type", "application/json"]])
+        );
+    }
+}
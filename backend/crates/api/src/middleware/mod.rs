@@ -0,0 +1,2 @@
+// API middleware
+pub mod idempotency;
@@ -0,0 +1,535 @@
+// Payment orchestration - provider calls recorded as payment attempts
+//
+// Every call to a provider is recorded in `payment_attempts` before it is
+// made, and the attempt ID is sent as the provider idempotency key. The
+// provider's answer is then reconciled against the order before anything
+// is marked as paid.
+
+use goseli_core::{
+    dto::CreateRefundRequest,
+    models::{
+        payment::reconcile, Order, OrderAction, OrderActor, OrderStatus, Payment,
+        PaymentAttemptStatus, PaymentOperation, PaymentState, Refund, RefundItem, RefundStatus,
+        WebhookEventStatus,
+    },
+    ApiError, Currency, Money, Result,
+};
+use goseli_db::payments::{self, PaymentUpdate};
+use goseli_db::refunds;
+use goseli_payments::{
+    AuthorizeRequest, PaymentError, PaymentProvider, ProviderPayment, ProviderPaymentStatus,
+    ProviderRefundStatus, WebhookEvent, WebhookEventKind,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Whether authorized payments are captured straight away (`PAYMENT_CAPTURE`).
+///
+/// `automatic` (the default) charges at checkout; `manual` only holds the
+/// funds until staff capture the payment, e.g. when the order ships.
+pub fn auto_capture() -> bool {
+    std::env::var("PAYMENT_CAPTURE")
+        .map(|mode| mode != "manual")
+        .unwrap_or(true)
+}
+
+/// Map a provider failure to an API error
+pub fn provider_error(e: PaymentError) -> ApiError {
+    match e {
+        PaymentError::Declined(msg) => ApiError::rule("payment_declined", msg),
+        PaymentError::InvalidRequest(msg) => ApiError::rule("payment_rejected", msg),
+        PaymentError::InvalidSignature(msg) => {
+            ApiError::unauthorized(format!("Invalid webhook signature: {}", msg))
+        }
+        PaymentError::InvalidWebhook(msg) => ApiError::bad_request(msg),
+        PaymentError::Provider(_) | PaymentError::NotConfigured(_) => {
+            tracing::error!("{e}");
+            ApiError::internal(e.to_string())
+        }
+    }
+}
+
+fn payment_state(status: ProviderPaymentStatus) -> PaymentState {
+    match status {
+        ProviderPaymentStatus::RequiresAction => PaymentState::RequiresAction,
+        ProviderPaymentStatus::Authorized => PaymentState::Authorized,
+        ProviderPaymentStatus::Captured => PaymentState::Captured,
+        ProviderPaymentStatus::Voided => PaymentState::Voided,
+        ProviderPaymentStatus::Failed => PaymentState::Failed,
+    }
+}
+
+/// The provider's view of a payment, as stored on ours
+pub fn payment_update(remote: &ProviderPayment) -> PaymentUpdate {
+    PaymentUpdate {
+        status: payment_state(remote.status),
+        provider_payment_id: Some(remote.id.clone()),
+        amount_captured: remote.amount_captured,
+        failure_reason: remote.failure_reason.clone(),
+    }
+}
+
+/// Provider a payment was made with
+fn provider_for(state: &AppState, payment: &Payment) -> Result<Arc<dyn PaymentProvider>> {
+    state.payments.get(&payment.provider).ok_or_else(|| {
+        ApiError::internal(format!(
+            "Payment provider '{}' is not configured",
+            payment.provider
+        ))
+    })
+}
+
+fn provider_payment_id(payment: &Payment) -> Result<&str> {
+    payment.provider_payment_id.as_deref().ok_or_else(|| {
+        ApiError::rule(
+            "payment_not_started",
+            "This payment never reached the provider",
+        )
+    })
+}
+
+/// Record a failed provider call on its attempt
+async fn fail_attempt(state: &AppState, attempt_id: Uuid, e: &PaymentError) -> Result<()> {
+    payments::finish_attempt(
+        &state.pool,
+        attempt_id,
+        PaymentAttemptStatus::Failed,
+        None,
+        Some(&e.to_string()),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Create a payment for what is due on the order and authorize it with the default provider.
+///
+/// Returns the payment and the client secret the browser needs when the
+/// customer still has to confirm it.
+pub async fn authorize_order(
+    state: &AppState,
+    order: &Order,
+    payment_method: Option<String>,
+) -> Result<(Payment, Option<String>)> {
+    let provider = state.payments.default_provider();
+    let payment = payments::create_payment(&state.pool, order.id, provider.name()).await?;
+    let attempt = payments::start_attempt(
+        &state.pool,
+        payment.id,
+        PaymentOperation::Authorize,
+        payment.amount.amount(),
+    )
+    .await?;
+
+    let request = AuthorizeRequest {
+        amount: payment.amount.amount(),
+        currency: payment.currency.to_string(),
+        order_id: order.id,
+        payment_method,
+        idempotency_key: attempt.id.to_string(),
+    };
+    let remote = match provider.authorize(&request).await {
+        Ok(remote) => remote,
+        Err(e) => {
+            fail_attempt(state, attempt.id, &e).await?;
+            let update = PaymentUpdate {
+                status: PaymentState::Failed,
+                provider_payment_id: None,
+                amount_captured: 0,
+                failure_reason: Some(e.to_string()),
+            };
+            payments::record_provider_state(&state.pool, payment.id, &update).await?;
+            return Err(provider_error(e));
+        }
+    };
+
+    // A hold for the wrong amount is released rather than kept
+    let held = remote
+        .currency
+        .parse::<Currency>()
+        .map(|currency| Money::new(remote.amount, currency))
+        .map_err(ApiError::from);
+    if let Err(mismatch) = held.and_then(|amount| reconcile(order, amount)) {
+        let void_key = format!("{}-void", attempt.id);
+        if let Err(e) = provider.void(&remote.id, &void_key).await {
+            tracing::error!("Failed to void mismatched payment {}: {e}", remote.id);
+        }
+        payments::finish_attempt(
+            &state.pool,
+            attempt.id,
+            PaymentAttemptStatus::Failed,
+            Some(&remote.id),
+            Some(&mismatch.to_string()),
+        )
+        .await?;
+        let update = PaymentUpdate {
+            status: PaymentState::Failed,
+            failure_reason: Some(mismatch.to_string()),
+            ..payment_update(&remote)
+        };
+        payments::record_provider_state(&state.pool, payment.id, &update).await?;
+        return Err(mismatch);
+    }
+
+    payments::finish_attempt(
+        &state.pool,
+        attempt.id,
+        PaymentAttemptStatus::Succeeded,
+        Some(&remote.id),
+        None,
+    )
+    .await?;
+    let (payment, _) =
+        payments::record_provider_state(&state.pool, payment.id, &payment_update(&remote)).await?;
+
+    Ok((payment, remote.client_secret))
+}
+
+/// Capture an authorized payment in full; the order becomes paid
+pub async fn capture_payment(state: &AppState, payment: &Payment) -> Result<Payment> {
+    if payment.status != PaymentState::Authorized {
+        return Err(ApiError::rule(
+            "payment_not_capturable",
+            format!("Cannot capture a payment that is {}", payment.status),
+        ));
+    }
+    let order = goseli_db::orders::get_order(&state.pool, payment.order_id).await?;
+    if order.status != OrderStatus::Pending {
+        return Err(ApiError::rule(
+            "order_not_payable",
+            format!(
+                "Order {} is {} and cannot be paid",
+                order.order_number, order.status
+            ),
+        ));
+    }
+
+    let provider = provider_for(state, payment)?;
+    let provider_payment_id = provider_payment_id(payment)?;
+    let attempt = payments::start_attempt(
+        &state.pool,
+        payment.id,
+        PaymentOperation::Capture,
+        payment.amount.amount(),
+    )
+    .await?;
+
+    let remote = match provider
+        .capture(
+            provider_payment_id,
+            payment.amount.amount(),
+            &attempt.id.to_string(),
+        )
+        .await
+    {
+        Ok(remote) => remote,
+        Err(e) => {
+            fail_attempt(state, attempt.id, &e).await?;
+            return Err(provider_error(e));
+        }
+    };
+
+    payments::finish_attempt(
+        &state.pool,
+        attempt.id,
+        PaymentAttemptStatus::Succeeded,
+        Some(&remote.id),
+        None,
+    )
+    .await?;
+    let (payment, order) =
+        payments::record_provider_state(&state.pool, payment.id, &payment_update(&remote)).await?;
+
+    // Recorded either way (the money moved), but a mismatch leaves the order unpaid
+    reconcile(&order, payment.amount_captured)?;
+
+    Ok(payment)
+}
+
+/// Release an uncaptured payment
+pub async fn void_payment(state: &AppState, payment: &Payment) -> Result<Payment> {
+    if !matches!(
+        payment.status,
+        PaymentState::RequiresAction | PaymentState::Authorized
+    ) {
+        return Err(ApiError::rule(
+            "payment_not_voidable",
+            format!("Cannot void a payment that is {}", payment.status),
+        ));
+    }
+
+    let provider = provider_for(state, payment)?;
+    let provider_payment_id = provider_payment_id(payment)?;
+    let attempt =
+        payments::start_attempt(&state.pool, payment.id, PaymentOperation::Void, 0).await?;
+
+    let remote = match provider
+        .void(provider_payment_id, &attempt.id.to_string())
+        .await
+    {
+        Ok(remote) => remote,
+        Err(e) => {
+            fail_attempt(state, attempt.id, &e).await?;
+            return Err(provider_error(e));
+        }
+    };
+
+    payments::finish_attempt(
+        &state.pool,
+        attempt.id,
+        PaymentAttemptStatus::Succeeded,
+        Some(&remote.id),
+        None,
+    )
+    .await?;
+    let (payment, _) =
+        payments::record_provider_state(&state.pool, payment.id, &payment_update(&remote)).await?;
+
+    Ok(payment)
+}
+
+/// Void an order's uncaptured payments before it is cancelled.
+///
+/// Does nothing unless the order may be cancelled by `actor`, so a refused
+/// cancellation never releases the customer's payment.
+pub async fn void_for_cancel(state: &AppState, order: &Order, actor: OrderActor) -> Result<()> {
+    order.status.transition(OrderAction::Cancel, actor)?;
+
+    for payment in payments::list_order_payments(&state.pool, order.id).await? {
+        if matches!(
+            payment.status,
+            PaymentState::RequiresAction | PaymentState::Authorized
+        ) {
+            void_payment(state, &payment).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Refund part or all of an order's captured payment (staff).
+///
+/// The refund is recorded before the provider is called, so its amount is
+/// reserved against the captured amount while the call is in flight. A
+/// provider that answers `pending` confirms the refund later by webhook.
+/// Only the payment's share is sent to the provider; the gift card share
+/// goes back on the cards once the refund succeeds.
+pub async fn refund_order(
+    state: &AppState,
+    order: &Order,
+    req: &CreateRefundRequest,
+    actor_id: Uuid,
+) -> Result<(Refund, Vec<RefundItem>)> {
+    let (refund, items) =
+        refunds::create_refund(&state.pool, order.id, req, Some(actor_id)).await?;
+    send_refund(state, refund, items, actor_id).await
+}
+
+/// Send a recorded refund to the provider and record its answer
+pub async fn send_refund(
+    state: &AppState,
+    refund: Refund,
+    items: Vec<RefundItem>,
+    actor_id: Uuid,
+) -> Result<(Refund, Vec<RefundItem>)> {
+    // A refund that only goes back to gift cards has nothing to send
+    if refund.amount.is_zero() {
+        let (refund, _) = refunds::record_refund_result(
+            &state.pool,
+            refund.id,
+            RefundStatus::Succeeded,
+            None,
+            None,
+            OrderActor::Admin,
+            Some(actor_id),
+        )
+        .await?;
+        return Ok((refund, items));
+    }
+    let payment = payments::get_payment(&state.pool, refund.payment_id).await?;
+
+    let provider = provider_for(state, &payment)?;
+    let provider_payment_id = provider_payment_id(&payment)?;
+    let attempt = payments::start_attempt(
+        &state.pool,
+        payment.id,
+        PaymentOperation::Refund,
+        refund.amount.amount(),
+    )
+    .await?;
+
+    let remote = match provider
+        .refund(
+            provider_payment_id,
+            refund.amount.amount(),
+            &attempt.id.to_string(),
+        )
+        .await
+    {
+        Ok(remote) => remote,
+        Err(e) => {
+            fail_attempt(state, attempt.id, &e).await?;
+            refunds::record_refund_result(
+                &state.pool,
+                refund.id,
+                RefundStatus::Failed,
+                None,
+                Some(&e.to_string()),
+                OrderActor::Admin,
+                Some(actor_id),
+            )
+            .await?;
+            return Err(provider_error(e));
+        }
+    };
+
+    let status = match remote.status {
+        ProviderRefundStatus::Pending => RefundStatus::Pending,
+        ProviderRefundStatus::Succeeded => RefundStatus::Succeeded,
+        ProviderRefundStatus::Failed => RefundStatus::Failed,
+    };
+    payments::finish_attempt(
+        &state.pool,
+        attempt.id,
+        match status {
+            RefundStatus::Failed => PaymentAttemptStatus::Failed,
+            _ => PaymentAttemptStatus::Succeeded,
+        },
+        Some(&remote.id),
+        None,
+    )
+    .await?;
+    let (refund, _) = refunds::record_refund_result(
+        &state.pool,
+        refund.id,
+        status,
+        Some(&remote.id),
+        (status == RefundStatus::Failed).then_some("Refused by the payment provider"),
+        OrderActor::Admin,
+        Some(actor_id),
+    )
+    .await?;
+
+    if refund.status == RefundStatus::Failed {
+        return Err(ApiError::rule(
+            "refund_failed",
+            "The payment provider refused the refund",
+        ));
+    }
+
+    Ok((refund, items))
+}
+
+/// Settle pending refunds covered by a provider's refunded total.
+///
+/// `refunded` is everything the provider has refunded on the payment so
+/// far; pending refunds are confirmed oldest first while they fit in it.
+async fn settle_refunds(state: &AppState, payment: &Payment, refunded: i64) -> Result<()> {
+    let mut settled = payment.amount_refunded.amount();
+    for refund in refunds::list_pending_for_payment(&state.pool, payment.id).await? {
+        if settled + refund.amount.amount() > refunded {
+            break;
+        }
+        refunds::record_refund_result(
+            &state.pool,
+            refund.id,
+            RefundStatus::Succeeded,
+            None,
+            None,
+            OrderActor::System,
+            None,
+        )
+        .await?;
+        settled += refund.amount.amount();
+    }
+    Ok(())
+}
+
+/// Apply a verified webhook event to the payment it is about.
+///
+/// Returns `Ignored` for events we do not act on. Failures are retried by
+/// the webhook job, so applying the same event twice must be harmless: the
+/// payment state only moves forward and the order is only paid once.
+pub async fn apply_webhook_event(
+    state: &AppState,
+    provider: &str,
+    event: &WebhookEvent,
+) -> Result<WebhookEventStatus> {
+    // None for refunds, which are settled without changing the payment state
+    let target = match event.kind {
+        WebhookEventKind::PaymentAuthorized => Some(PaymentState::Authorized),
+        WebhookEventKind::PaymentCaptured => Some(PaymentState::Captured),
+        WebhookEventKind::PaymentFailed => Some(PaymentState::Failed),
+        WebhookEventKind::PaymentVoided => Some(PaymentState::Voided),
+        WebhookEventKind::PaymentRefunded => None,
+        WebhookEventKind::Other => return Ok(WebhookEventStatus::Ignored),
+    };
+    let Some(provider_payment_id) = event.payment_id.as_deref() else {
+        return Ok(WebhookEventStatus::Ignored);
+    };
+
+    // An event can arrive before the call that created the payment has
+    // returned; failing here retries it once the payment is recorded
+    let payment = payments::find_by_provider_payment_id(&state.pool, provider, provider_payment_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "No payment found for {} payment {}",
+                provider, provider_payment_id
+            ))
+        })?;
+
+    let Some(target) = target else {
+        let Some(refunded) = event.amount else {
+            return Ok(WebhookEventStatus::Ignored);
+        };
+        settle_refunds(state, &payment, refunded).await?;
+        return Ok(WebhookEventStatus::Processed);
+    };
+
+    // A hold for the wrong amount is released rather than captured
+    if target == PaymentState::Authorized {
+        if let Some(amount) = event
+            .amount
+            .filter(|amount| *amount != payment.amount.amount())
+        {
+            tracing::error!(
+                "Payment {} authorized for {} instead of {}, voiding",
+                payment.id,
+                amount,
+                payment.amount
+            );
+            void_payment(state, &payment).await?;
+            return Ok(WebhookEventStatus::Processed);
+        }
+    }
+
+    let update = PaymentUpdate {
+        status: target,
+        provider_payment_id: None,
+        amount_captured: match target {
+            PaymentState::Captured => event.amount.unwrap_or(payment.amount.amount()),
+            _ => payment.amount_captured.amount(),
+        },
+        failure_reason: match target {
+            PaymentState::Failed => Some(format!("Provider reported {}", event.event_type)),
+            _ => payment.failure_reason.clone(),
+        },
+    };
+    let (payment, order) =
+        payments::record_provider_state(&state.pool, payment.id, &update).await?;
+
+    if payment.status == PaymentState::Captured {
+        if let Err(e) = reconcile(&order, payment.amount_captured) {
+            tracing::error!("Webhook {} captured an unreconciled payment: {e}", event.id);
+        }
+    }
+
+    // Confirmed in the browser: charge now when capture is automatic
+    if payment.status == PaymentState::Authorized && auto_capture() {
+        capture_payment(state, &payment).await?;
+    }
+
+    Ok(WebhookEventStatus::Processed)
+}
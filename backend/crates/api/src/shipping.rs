@@ -0,0 +1,442 @@
+// Shipping rate engine - zones and methods priced against a cart
+//
+// The first active zone matching the destination (by priority) decides
+// which methods are offered; each method then prices the cart's value and
+// weight, and methods that cannot carry the parcel are left out. Carrier
+// methods are quoted live by their carrier adapter, once per carrier.
+//
+// Labels for parcels are bought through the same adapters. Every purchase
+// is recorded in `shipping_labels` before the carrier is called, and the
+// label ID is sent as the carrier idempotency key.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use goseli_core::{
+    dto::{Address, LabelRatesRequest, PurchaseLabelRequest, ShippingRate},
+    models::{
+        Destination, Fulfillment, LabelFormat, PackageDimensions, Parcel, ShippingLabel,
+        ShippingMethod, ShippingMethodKind, Store, TrackingStatus,
+    },
+    ApiError, Money, Result,
+};
+use goseli_db::{
+    fulfillments, orders, shipping,
+    shipping_labels::{self, LabelPurchase},
+    stores,
+};
+use goseli_shipping::{
+    CarrierError, CarrierRate, LabelRequest, Package, RateRequest, ShippingCarrier,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Map a carrier failure to an API error
+pub fn carrier_error(e: CarrierError) -> ApiError {
+    match e {
+        CarrierError::InvalidRequest(msg) => ApiError::rule("carrier_rejected", msg),
+        CarrierError::Provider(_) | CarrierError::NotConfigured(_) => {
+            tracing::error!("{e}");
+            ApiError::internal(e.to_string())
+        }
+    }
+}
+
+/// Carrier adapter by code
+pub fn carrier_for(state: &AppState, code: &str) -> Result<Arc<dyn ShippingCarrier>> {
+    state.carriers.get(code).ok_or_else(|| {
+        ApiError::rule(
+            "carrier_unavailable",
+            format!("Carrier '{}' is not configured", code),
+        )
+    })
+}
+
+fn parse_address(address: &serde_json::Value) -> Result<Address> {
+    serde_json::from_value(address.clone())
+        .map_err(|e| ApiError::validation(format!("Invalid shipping address: {}", e)))
+}
+
+/// Destination of a shipping address saved on a checkout session or order
+pub fn destination_from_address(address: &serde_json::Value) -> Result<Destination> {
+    let address = parse_address(address)?;
+
+    Ok(Destination {
+        country: address.country,
+        region: address.region,
+        postal_code: Some(address.postal_code),
+    })
+}
+
+fn carrier_address(address: Address) -> goseli_shipping::Address {
+    goseli_shipping::Address {
+        name: Some(address.name),
+        company: address.company,
+        line1: Some(address.line1),
+        line2: address.line2,
+        city: Some(address.city),
+        region: address.region,
+        postal_code: Some(address.postal_code),
+        country: address.country,
+        phone: address.phone,
+    }
+}
+
+/// Where a store's parcels leave from: the `ship_from` address in its config
+fn ship_from(store: &Store) -> Option<goseli_shipping::Address> {
+    let address = store.config.get("ship_from")?;
+    match parse_address(address) {
+        Ok(address) => Some(carrier_address(address)),
+        Err(e) => {
+            tracing::warn!(store_id = %store.id, "Ignoring ship_from in store config: {e:?}");
+            None
+        }
+    }
+}
+
+/// Services a carrier quotes for a parcel; empty when it cannot be asked
+async fn carrier_rates(
+    state: &AppState,
+    store: &Store,
+    code: &str,
+    parcel: &Parcel,
+    dest: &Destination,
+) -> Vec<CarrierRate> {
+    let Some(carrier) = state.carriers.get(code) else {
+        tracing::warn!(
+            carrier = code,
+            "Shipping method uses an unconfigured carrier"
+        );
+        return vec![];
+    };
+    let Some(from) = ship_from(store) else {
+        tracing::warn!(store_id = %store.id, "Carrier rates need a ship_from address");
+        return vec![];
+    };
+
+    let req = RateRequest {
+        from,
+        to: goseli_shipping::Address {
+            country: dest.country.clone(),
+            region: dest.region.clone(),
+            postal_code: dest.postal_code.clone(),
+            ..Default::default()
+        },
+        package: Package {
+            weight_grams: parcel.weight_grams,
+            ..Default::default()
+        },
+        currency: store.currency.to_string(),
+    };
+    // A carrier outage hides its methods instead of failing checkout
+    carrier.rates(&req).await.unwrap_or_else(|e| {
+        tracing::warn!(carrier = code, "Carrier rate quote failed: {e}");
+        vec![]
+    })
+}
+
+/// Price of a carrier method from its carrier's quotes, plus the handling fee
+fn carrier_quote(method: &ShippingMethod, quotes: &[CarrierRate], currency: &str) -> Option<i64> {
+    let quote = quotes
+        .iter()
+        .find(|quote| Some(quote.service_code.as_str()) == method.service_code.as_deref())?;
+    if !quote.currency.eq_ignore_ascii_case(currency) {
+        tracing::warn!(
+            method = %method.code,
+            "Carrier quoted in {} but the store sells in {}",
+            quote.currency,
+            currency
+        );
+        return None;
+    }
+    quote.amount.checked_add(method.rate)
+}
+
+/// Rates for shipping a parcel to `dest`, in the store's method order
+pub async fn quote_parcel(
+    state: &AppState,
+    store_id: Uuid,
+    parcel: &Parcel,
+    dest: &Destination,
+) -> Result<Vec<ShippingRate>> {
+    let zones = shipping::list_zones(&state.pool, store_id, true).await?;
+    let Some(zone) = zones.into_iter().find(|zone| zone.matches(dest)) else {
+        return Ok(vec![]);
+    };
+    let store = stores::get_store(&state.pool, store_id).await?;
+
+    let methods: Vec<ShippingMethod> = shipping::list_methods(&state.pool, store_id, true)
+        .await?
+        .into_iter()
+        .filter(|method| method.zone_id == zone.id)
+        .collect();
+
+    let mut live: HashMap<&str, Vec<CarrierRate>> = HashMap::new();
+    for method in &methods {
+        if let Some(code) = method.carrier_code.as_deref() {
+            if !live.contains_key(code) {
+                let quotes = carrier_rates(state, &store, code, parcel, dest).await;
+                live.insert(code, quotes);
+            }
+        }
+    }
+
+    let rates = methods
+        .iter()
+        .filter_map(|method| {
+            let amount = match (method.kind, method.carrier_code.as_deref()) {
+                (ShippingMethodKind::Carrier, Some(code)) => {
+                    carrier_quote(method, &live[code], store.currency.code())?
+                }
+                _ => method.quote(parcel)?,
+            };
+            Some(ShippingRate {
+                code: method.code.clone(),
+                name: method.name.clone(),
+                description: method.description.clone(),
+                kind: method.kind,
+                amount: Money::new(amount, store.currency),
+                zone_id: zone.id,
+            })
+        })
+        .collect();
+
+    Ok(rates)
+}
+
+/// Rates for shipping a cart's contents to `dest`
+pub async fn quote_cart(
+    state: &AppState,
+    store_id: Uuid,
+    cart_id: Uuid,
+    dest: &Destination,
+) -> Result<Vec<ShippingRate>> {
+    let parcel = shipping::cart_parcel(&state.pool, cart_id).await?;
+    quote_parcel(state, store_id, &parcel, dest).await
+}
+
+/// Price of the method `code` for a cart, failing if it is not offered
+pub async fn select_rate(
+    state: &AppState,
+    store_id: Uuid,
+    cart_id: Uuid,
+    dest: &Destination,
+    code: &str,
+) -> Result<ShippingRate> {
+    quote_cart(state, store_id, cart_id, dest)
+        .await?
+        .into_iter()
+        .find(|rate| rate.code == code)
+        .ok_or_else(|| {
+            ApiError::rule(
+                "shipping_method_unavailable",
+                format!(
+                    "Shipping method '{}' is not available for this address",
+                    code
+                ),
+            )
+        })
+}
+
+/// A parcel as a carrier needs to see it
+struct Shipment {
+    from: goseli_shipping::Address,
+    to: goseli_shipping::Address,
+    package: Package,
+    currency: String,
+    order_number: i64,
+}
+
+/// Addresses and package of a parcel; `package` overrides the catalog weight
+async fn shipment(
+    state: &AppState,
+    fulfillment: &Fulfillment,
+    package: Option<PackageDimensions>,
+) -> Result<Shipment> {
+    let order = orders::get_order(&state.pool, fulfillment.order_id).await?;
+    let store = stores::get_store(&state.pool, order.store_id).await?;
+    let from = ship_from(&store).ok_or_else(|| {
+        ApiError::rule(
+            "ship_from_missing",
+            "Set a ship_from address in the store config to use carriers",
+        )
+    })?;
+    let to = order
+        .shipping_address
+        .as_ref()
+        .map(parse_address)
+        .transpose()?
+        .ok_or_else(|| {
+            ApiError::rule(
+                "shipping_address_required",
+                "The order has no shipping address",
+            )
+        })?;
+
+    let package = package.unwrap_or_default();
+    if [package.length_mm, package.width_mm, package.height_mm]
+        .iter()
+        .chain([&package.weight_grams])
+        .any(|value| value.is_some_and(|v| v <= 0))
+    {
+        return Err(ApiError::validation(
+            "Package weight and sizes must be positive",
+        ));
+    }
+    let weight_grams = match package.weight_grams {
+        Some(weight) => i64::from(weight),
+        None => shipping_labels::fulfillment_weight(&state.pool, fulfillment.id).await?,
+    };
+
+    Ok(Shipment {
+        from,
+        to: carrier_address(to),
+        package: Package {
+            weight_grams,
+            length_mm: package.length_mm,
+            width_mm: package.width_mm,
+            height_mm: package.height_mm,
+        },
+        currency: store.currency.to_string(),
+        order_number: order.order_number,
+    })
+}
+
+/// Services a carrier offers for a parcel (admin, before buying a label)
+pub async fn label_rates(
+    state: &AppState,
+    fulfillment: &Fulfillment,
+    req: &LabelRatesRequest,
+) -> Result<Vec<CarrierRate>> {
+    let carrier = carrier_for(state, &req.carrier.to_lowercase())?;
+    let shipment = shipment(state, fulfillment, req.package).await?;
+
+    carrier
+        .rates(&RateRequest {
+            from: shipment.from,
+            to: shipment.to,
+            package: shipment.package,
+            currency: shipment.currency,
+        })
+        .await
+        .map_err(carrier_error)
+}
+
+/// Label format as carrier adapters name it
+pub fn carrier_format(format: LabelFormat) -> goseli_shipping::LabelFormat {
+    match format {
+        LabelFormat::Pdf => goseli_shipping::LabelFormat::Pdf,
+        LabelFormat::Zpl => goseli_shipping::LabelFormat::Zpl,
+    }
+}
+
+/// Buy a label for a parcel that has not shipped and store the file.
+///
+/// The parcel takes the label's carrier and tracking number.
+pub async fn purchase_label(
+    state: &AppState,
+    fulfillment_id: Uuid,
+    req: &PurchaseLabelRequest,
+    actor_id: Uuid,
+) -> Result<(ShippingLabel, Fulfillment)> {
+    let code = req.carrier.to_lowercase();
+    let carrier = carrier_for(state, &code)?;
+    let fulfillment = fulfillments::get_fulfillment(&state.pool, fulfillment_id).await?;
+    let shipment = shipment(state, &fulfillment, req.package).await?;
+
+    let format = req.format.unwrap_or(LabelFormat::Pdf);
+    let label = shipping_labels::begin_label(
+        &state.pool,
+        fulfillment_id,
+        &code,
+        &req.service_code,
+        format,
+        actor_id,
+    )
+    .await?;
+
+    let request = LabelRequest {
+        from: shipment.from,
+        to: shipment.to,
+        package: shipment.package,
+        service_code: req.service_code.clone(),
+        format: carrier_format(format),
+        reference: shipment.order_number.to_string(),
+        idempotency_key: label.id.to_string(),
+    };
+    let bought = match carrier.purchase_label(&request).await {
+        Ok(bought) => bought,
+        Err(e) => {
+            shipping_labels::fail_label(&state.pool, label.id, &e.to_string()).await?;
+            return Err(carrier_error(e));
+        }
+    };
+
+    let key = format!(
+        "labels/{}/{}.{}",
+        fulfillment_id,
+        label.id,
+        bought.format.extension()
+    );
+    if let Err(e) = state.storage.put(&key, &bought.data).await {
+        // Do not pay for a label nobody can print
+        if let Err(void) = carrier.void_label(&bought.shipment_id).await {
+            tracing::error!(label_id = %label.id, "Could not void unstored label: {void}");
+        }
+        shipping_labels::fail_label(&state.pool, label.id, &e.to_string()).await?;
+        return Err(e.into());
+    }
+
+    shipping_labels::complete_label(
+        &state.pool,
+        label.id,
+        &LabelPurchase {
+            carrier_shipment_id: bought.shipment_id,
+            tracking_number: bought.tracking_number,
+            tracking_url: bought.tracking_url,
+            storage_key: key,
+            amount: bought.amount,
+            currency: bought.currency,
+        },
+    )
+    .await
+}
+
+/// Void a purchased label at its carrier and remove the file
+pub async fn void_label(
+    state: &AppState,
+    label: &ShippingLabel,
+) -> Result<(ShippingLabel, Fulfillment)> {
+    let carrier = carrier_for(state, &label.carrier)?;
+    let shipment_id = label
+        .carrier_shipment_id
+        .as_deref()
+        .ok_or_else(|| ApiError::conflict("Label purchase has not completed"))?;
+    carrier
+        .void_label(shipment_id)
+        .await
+        .map_err(carrier_error)?;
+
+    let voided = shipping_labels::void_label(&state.pool, label.id).await?;
+    if let Some(key) = &label.storage_key {
+        state.storage.delete(key).await?;
+    }
+
+    Ok(voided)
+}
+
+/// Tracking status as stored on fulfillments
+pub fn tracking_status(status: goseli_shipping::TrackingStatus) -> TrackingStatus {
+    use goseli_shipping::TrackingStatus as Carrier;
+
+    match status {
+        Carrier::PreTransit => TrackingStatus::PreTransit,
+        Carrier::InTransit => TrackingStatus::InTransit,
+        Carrier::OutForDelivery => TrackingStatus::OutForDelivery,
+        Carrier::Delivered => TrackingStatus::Delivered,
+        Carrier::Exception => TrackingStatus::Exception,
+        Carrier::ReturnedToSender => TrackingStatus::ReturnedToSender,
+        Carrier::Unknown => TrackingStatus::Unknown,
+    }
+}
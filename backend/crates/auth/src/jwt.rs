@@ -14,6 +14,17 @@ pub struct Claims {
     pub iat: i64, // issued at (Unix timestamp)
 }
 
+/// Claims for the signed link in abandoned cart recovery emails
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CartRestoreClaims {
+    pub sub: Uuid, // abandoned_cart id
+    pub purpose: String,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+const CART_RESTORE_PURPOSE: &str = "cart_restore";
+
 fn get_jwt_secret() -> String {
     std::env::var("JWT_SECRET")
         .unwrap_or_else(|_| "change-me-in-production-use-64-char-random-string".to_string())
@@ -33,6 +44,13 @@ fn get_refresh_token_expiry() -> i64 {
         .unwrap_or(604800) // 7 days
 }
 
+fn get_cart_restore_token_expiry() -> i64 {
+    std::env::var("CART_RESTORE_TOKEN_EXPIRY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1_209_600) // 14 days
+}
+
 /// Generate an access token (short-lived, 15 min)
 pub fn generate_access_token(
     user_id: Uuid,
@@ -108,6 +126,46 @@ pub fn validate_token(token: &str) -> Result<Claims, ApiError> {
     Ok(token_data.claims)
 }
 
+/// Generate a signed cart restore token for an abandoned cart
+pub fn generate_cart_restore_token(abandoned_cart_id: Uuid) -> Result<String, ApiError> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+
+    let claims = CartRestoreClaims {
+        sub: abandoned_cart_id,
+        purpose: CART_RESTORE_PURPOSE.to_string(),
+        exp: now + get_cart_restore_token_expiry(),
+        iat: now,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(get_jwt_secret().as_bytes()),
+    )
+    .map_err(|e| ApiError::internal(format!("Token generation failed: {}", e)))
+}
+
+/// Validate a cart restore token and return the abandoned cart ID
+pub fn validate_cart_restore_token(token: &str) -> Result<Uuid, ApiError> {
+    let token_data = decode::<CartRestoreClaims>(
+        token,
+        &DecodingKey::from_secret(get_jwt_secret().as_bytes()),
+        &Validation::default(),
+    )
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+            ApiError::bad_request("Restore link has expired")
+        }
+        _ => ApiError::bad_request("Invalid restore link"),
+    })?;
+
+    if token_data.claims.purpose != CART_RESTORE_PURPOSE {
+        return Err(ApiError::bad_request("Invalid restore link"));
+    }
+
+    Ok(token_data.claims.sub)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +186,26 @@ mod tests {
         assert_eq!(claims.store_id, store_id);
     }
 
+    #[test]
+    fn test_cart_restore_token_roundtrip() {
+        let abandoned_cart_id = Uuid::now_v7();
+        let token = generate_cart_restore_token(abandoned_cart_id).unwrap();
+        assert_eq!(
+            validate_cart_restore_token(&token).unwrap(),
+            abandoned_cart_id
+        );
+
+        // Access tokens must not be accepted as restore links
+        let access = generate_access_token(
+            Uuid::now_v7(),
+            "a@b.co".into(),
+            UserRole::Customer,
+            Uuid::now_v7(),
+        )
+        .unwrap();
+        assert!(validate_cart_restore_token(&access).is_err());
+    }
+
     #[test]
     fn test_invalid_token() {
         let result = validate_token("invalid.token.here");
@@ -2,6 +2,9 @@ pub mod jwt;
 pub mod middleware;
 pub mod password;
 
-pub use jwt::{generate_access_token, generate_refresh_token, validate_token, Claims};
+pub use jwt::{
+    generate_access_token, generate_cart_restore_token, generate_refresh_token,
+    validate_cart_restore_token, validate_token, CartRestoreClaims, Claims,
+};
 pub use middleware::AuthUser;
 pub use password::{hash_password, verify_password};
@@ -3,6 +3,7 @@ use axum::{
     extract::FromRequestParts,
     http::{header, request::Parts, StatusCode},
 };
+use goseli_core::{error::ApiError, models::user::UserRole};
 use uuid::Uuid;
 
 use crate::jwt::{validate_token, Claims};
@@ -27,6 +28,18 @@ impl From<Claims> for AuthUser {
     }
 }
 
+impl AuthUser {
+    /// Require a store admin or staff role for admin endpoints
+    pub fn require_admin(&self) -> Result<(), ApiError> {
+        let allowed = [UserRole::SuperAdmin, UserRole::StoreAdmin, UserRole::Staff];
+        if allowed.iter().any(|r| r.to_string() == self.role) {
+            Ok(())
+        } else {
+            Err(ApiError::forbidden("Admin access required"))
+        }
+    }
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
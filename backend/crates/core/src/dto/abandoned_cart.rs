@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Restore an abandoned cart from the signed link sent in recovery emails
+#[derive(Debug, Deserialize)]
+pub struct RestoreCartRequest {
+    pub token: String,
+}
+
+/// Abandoned cart recovery metrics for the store admin
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct AbandonedCartStats {
+    pub abandoned_count: i64,
+    pub recovered_count: i64,
+    pub expired_count: i64,
+    pub messages_sent: i64,
+    pub abandoned_value: i64,
+    pub recovered_revenue: i64,
+}
@@ -2,15 +2,62 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::dto::AppliedCoupon;
+use crate::models::{AppliedPromotion, FreeItem, TaxLine};
+use crate::money::{Currency, Money};
+
 /// Cart response with enriched items
 #[derive(Debug, Clone, Serialize)]
 pub struct CartResponse {
-    pub id: Uuid,
+    /// None until the first item is added (carts are created lazily)
+    pub id: Option<Uuid>,
+    pub email: Option<String>,
     pub items: Vec<CartItemResponse>,
-    pub total: i32,
+    /// Sum of the line subtotals
+    pub subtotal: Money,
+    /// Taken off the lines by promotions and the coupon, already subtracted
+    /// from `total`
+    pub discount_total: Money,
+    /// Automatic promotions the cart qualifies for, each with an explanation
+    pub promotions: Vec<AppliedPromotion>,
+    /// Gifts added by promotions when the order is placed
+    pub free_items: Vec<FreeItem>,
+    pub coupon: Option<AppliedCoupon>,
+    /// Estimated for the checkout address, or the store's own location before
+    /// one is entered; shipping and its tax are added when the order is placed
+    pub tax_total: Money,
+    /// Tax per rate
+    pub tax_lines: Vec<TaxLine>,
+    /// Whether prices already contain their tax; if not, it is added to `total`
+    pub prices_include_tax: bool,
+    /// The customer has an approved tax exemption, so no tax is charged
+    pub tax_exempt: bool,
+    pub total: Money,
     pub item_count: i32,
 }
 
+impl CartResponse {
+    /// Response for a visitor who has not added anything yet
+    pub fn empty(currency: Currency) -> Self {
+        Self {
+            id: None,
+            email: None,
+            items: vec![],
+            subtotal: Money::zero(currency),
+            discount_total: Money::zero(currency),
+            promotions: vec![],
+            free_items: vec![],
+            coupon: None,
+            tax_total: Money::zero(currency),
+            tax_lines: vec![],
+            prices_include_tax: false,
+            tax_exempt: false,
+            total: Money::zero(currency),
+            item_count: 0,
+        }
+    }
+}
+
 /// Enriched cart item with product details
 #[derive(Debug, Clone, Serialize)]
 pub struct CartItemResponse {
@@ -21,9 +68,23 @@ pub struct CartItemResponse {
     pub product_slug: String,
     pub product_image_url: Option<String>,
     pub variant_name: Option<String>,
-    pub price: i32,
+    /// Customer customizations for this line (engraving, gift message, ...)
+    pub properties: serde_json::Value,
+    /// Unit price including customization price modifiers
+    pub price: Money,
+    /// Regular unit price while `price` is a sale price
+    pub compare_at_price: Option<Money>,
+    /// Units at the sale price while a sale runs; when a sale's cap leaves
+    /// fewer than `quantity`, the rest are at `compare_at_price`
+    pub sale_quantity: Option<i32>,
     pub quantity: i32,
-    pub subtotal: i32,
+    /// Price of all units, sale and regular
+    pub subtotal: Money,
+    /// Taken off this line by promotions and the coupon; tax is charged on
+    /// what is left
+    pub discount_total: Money,
+    pub tax_total: Money,
+    pub tax_lines: Vec<TaxLine>,
 }
 
 /// Add item to cart request
@@ -33,6 +94,8 @@ pub struct AddToCartRequest {
     pub variant_id: Option<Uuid>,
     #[validate(range(min = 1))]
     pub quantity: i32,
+    /// Line customizations, validated against the product's options
+    pub properties: Option<serde_json::Value>,
 }
 
 /// Update cart item quantity request
@@ -41,3 +104,103 @@ pub struct UpdateCartItemRequest {
     #[validate(range(min = 1))]
     pub quantity: i32,
 }
+
+/// Attach a contact email to a guest cart (used for abandoned cart recovery)
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetCartEmailRequest {
+    #[validate(email, length(max = 255))]
+    pub email: String,
+}
+
+/// How a bulk cart request is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkCartMode {
+    /// All operations succeed or none are applied
+    #[default]
+    Atomic,
+    /// Apply every operation that succeeds and report the rest
+    BestEffort,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkCartAction {
+    /// Add quantity to the line (creating it if needed)
+    #[default]
+    Add,
+    /// Set the line to exactly this quantity (0 removes it)
+    Set,
+    /// Remove the line
+    Remove,
+}
+
+/// One line of a quick order: identified by SKU or by product/variant ID
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BulkCartOperation {
+    #[serde(default)]
+    pub op: BulkCartAction,
+    #[validate(length(min = 1, max = 100))]
+    pub sku: Option<String>,
+    pub product_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub quantity: i32,
+    pub properties: Option<serde_json::Value>,
+}
+
+/// POST /api/v1/cart/bulk request
+#[derive(Debug, Deserialize, Validate)]
+pub struct BulkCartRequest {
+    #[serde(default)]
+    pub mode: BulkCartMode,
+    #[validate(length(min = 1, max = 500), nested)]
+    pub operations: Vec<BulkCartOperation>,
+}
+
+/// Query parameters for the CSV quick order upload
+#[derive(Debug, Deserialize)]
+pub struct BulkCartCsvParams {
+    #[serde(default)]
+    pub mode: BulkCartMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkLineStatus {
+    Applied,
+    Failed,
+    /// Not attempted, or rolled back because another line failed (atomic mode)
+    Skipped,
+}
+
+/// Machine-readable reason a bulk line failed
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkLineError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Outcome of one bulk operation, in request order
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkCartLineResult {
+    pub index: usize,
+    pub op: BulkCartAction,
+    pub sku: Option<String>,
+    pub product_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub status: BulkLineStatus,
+    pub error: Option<BulkLineError>,
+}
+
+/// Result of a bulk cart request with the updated cart
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkCartResponse {
+    pub mode: BulkCartMode,
+    /// False when an atomic request was rolled back
+    pub applied: bool,
+    pub results: Vec<BulkCartLineResult>,
+    pub cart: CartResponse,
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use super::{cart::CartResponse, order::Address};
+use crate::models::{CheckoutSession, CheckoutStep, CustomizationOption};
+
+/// State of one step in a checkout session
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutStepState {
+    pub step: CheckoutStep,
+    pub completed: bool,
+    /// Store-defined fields collected at this step
+    pub fields: Vec<CustomizationOption>,
+}
+
+/// Checkout session with the store's flow and the cart being checked out
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutSessionResponse {
+    #[serde(flatten)]
+    pub session: CheckoutSession,
+    pub steps: Vec<CheckoutStepState>,
+    pub current_step: CheckoutStep,
+    pub cart: CartResponse,
+}
+
+/// Payload of the `cart` step
+#[derive(Debug, Deserialize, Validate)]
+pub struct CartStepRequest {
+    /// Values for the store's custom fields collected at this step
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Payload of the `contact` step
+#[derive(Debug, Deserialize, Validate)]
+pub struct ContactStepRequest {
+    #[validate(email, length(max = 255))]
+    pub email: String,
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Payload of the `shipping` step
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShippingStepRequest {
+    /// For flows without a `contact` step
+    #[validate(email, length(max = 255))]
+    pub email: Option<String>,
+    #[validate(nested)]
+    pub address: Address,
+    /// Defaults to the shipping address
+    #[validate(nested)]
+    pub billing_address: Option<Address>,
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Payload of the `shipping_method` step
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShippingMethodStepRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub code: String,
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Payload of the `payment` step
+#[derive(Debug, Deserialize, Validate)]
+pub struct PaymentStepRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub method: String,
+    /// Gift card codes to spend first, in order; the payment covers the
+    /// rest. Replaces codes given before; an empty list removes them.
+    #[validate(length(max = 5))]
+    pub gift_cards: Option<Vec<String>>,
+    pub fields: Option<serde_json::Value>,
+}
+
+/// Complete a checkout session and place the order
+#[derive(Debug, Deserialize, Validate)]
+pub struct CompleteCheckoutRequest {
+    /// Values for custom fields collected at confirmation
+    pub fields: Option<serde_json::Value>,
+    #[validate(length(max = 2000))]
+    pub notes: Option<String>,
+}
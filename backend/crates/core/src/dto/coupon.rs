@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::ApiError;
+use crate::models::CouponKind;
+use crate::money::Money;
+
+/// Create a coupon or replace its terms (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CouponRequest {
+    /// Shared code; leave out to only use generated codes
+    #[validate(length(min = 3, max = 50))]
+    pub code: Option<String>,
+    #[validate(length(max = 255))]
+    pub description: Option<String>,
+    pub kind: CouponKind,
+    /// Required for percentage coupons (10% = 100000)
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub percentage_ppm: Option<i32>,
+    /// Required for fixed amount coupons, in minor units of the store currency
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+    #[validate(range(min = 0))]
+    pub min_subtotal: Option<i64>,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub product_ids: Vec<Uuid>,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub category_ids: Vec<Uuid>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    #[validate(range(min = 1))]
+    pub usage_limit: Option<i32>,
+    #[validate(range(min = 1))]
+    pub usage_limit_per_customer: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+impl CouponRequest {
+    /// Checks that span fields: the value the kind needs and the validity window
+    pub fn validate_terms(&self) -> Result<(), ApiError> {
+        match self.kind {
+            CouponKind::Percentage if self.percentage_ppm.is_none() => Err(ApiError::validation(
+                "percentage_ppm is required for percentage coupons",
+            )),
+            CouponKind::FixedAmount if self.amount.is_none() => Err(ApiError::validation(
+                "amount is required for fixed_amount coupons",
+            )),
+            _ => Ok(()),
+        }?;
+        if let (Some(starts_at), Some(ends_at)) = (self.starts_at, self.ends_at) {
+            if ends_at <= starts_at {
+                return Err(ApiError::validation("ends_at must be after starts_at"));
+            }
+        }
+        if self
+            .code
+            .as_deref()
+            .is_some_and(|code| !is_valid_code(code))
+        {
+            return Err(ApiError::validation(
+                "code may only contain letters, digits, '-' and '_'",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Codes are typed in by customers: letters, digits, dashes and underscores
+pub fn is_valid_code(code: &str) -> bool {
+    code.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Generate single-use codes for a coupon (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct GenerateCouponCodesRequest {
+    #[validate(range(min = 1, max = 10_000))]
+    pub count: i32,
+    /// Put in front of every code, e.g. `SPRING` for `SPRING-7KQ2-MXW9`
+    #[validate(length(min = 1, max = 20))]
+    pub prefix: Option<String>,
+}
+
+/// Enter a coupon code on the cart
+#[derive(Debug, Deserialize, Validate)]
+pub struct ApplyCouponRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub code: String,
+}
+
+/// The coupon entered on a cart and what it takes off
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedCoupon {
+    pub code: String,
+    pub description: Option<String>,
+    /// False when the coupon no longer applies to the cart (it expired,
+    /// items were removed, ...); `message` says why
+    pub applied: bool,
+    pub message: Option<String>,
+    /// What the coupon does, e.g. "10% off your order"; None when not applied
+    pub explanation: Option<String>,
+    pub discount_total: Money,
+    /// Shipping is waived when the order is placed
+    pub free_shipping: bool,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::money::{Currency, RoundingMode};
+
+/// Currencies a storefront can show prices in
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrencyListResponse {
+    /// The store currency, in which orders are placed
+    pub base: Currency,
+    /// The store currency first, then the enabled currencies that have an
+    /// exchange rate
+    pub currencies: Vec<Currency>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct EnableCurrencyRequest {
+    pub currency: Currency,
+    pub rounding_mode: Option<RoundingMode>,
+    /// Minor units; defaults to 1 (no rounding beyond the minor unit)
+    #[validate(range(min = 1))]
+    pub rounding_increment: Option<i64>,
+    /// Minor units below each increment, e.g. 99 for .99 prices
+    #[validate(range(min = 0))]
+    pub price_ending: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCurrencyRequest {
+    pub rounding_mode: Option<RoundingMode>,
+    #[validate(range(min = 1))]
+    pub rounding_increment: Option<i64>,
+    /// 0 removes the ending
+    #[validate(range(min = 0))]
+    pub price_ending: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetExchangeRateRequest {
+    /// Millionths of a unit per unit of the store currency (0.92 = 920000)
+    #[validate(range(min = 1))]
+    pub rate_ppm: i64,
+}
+
+/// Set the fixed price of a product or variant in a currency
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetPriceRequest {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    /// Minor units of the price list currency
+    #[validate(range(min = 0))]
+    pub price: i64,
+    #[validate(range(min = 0))]
+    pub compare_at_price: Option<i64>,
+}
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::ApiError;
+
+/// Create a customer group (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCustomerGroupRequest {
+    /// E.g. "wholesale"; what customers and promotion conditions refer to,
+    /// so it cannot be changed later
+    #[validate(length(min = 1, max = 50))]
+    pub code: String,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+}
+
+/// Rename or describe a customer group (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCustomerGroupRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    #[validate(length(max = 2000))]
+    pub description: Option<String>,
+}
+
+/// Set a group's price for a product or variant from a quantity up (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetGroupPriceRequest {
+    pub product_id: Uuid,
+    /// Leave out for the product and all its variants
+    pub variant_id: Option<Uuid>,
+    /// First quantity the price applies to (default 1)
+    #[validate(range(min = 1))]
+    pub min_quantity: Option<i32>,
+    /// Fixed unit price in minor units of the store currency
+    #[validate(range(min = 0))]
+    pub price: Option<i64>,
+    /// Taken off the regular price (10% = 100000)
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub percentage_ppm: Option<i32>,
+}
+
+impl SetGroupPriceRequest {
+    /// Checks that exactly one of `price` and `percentage_ppm` is given
+    pub fn validate_terms(&self) -> Result<(), ApiError> {
+        if self.price.is_some() == self.percentage_ppm.is_some() {
+            return Err(ApiError::validation(
+                "Give either a price or a percentage_ppm",
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{
+    Fulfillment, FulfillmentItem, LabelFormat, OrderStatus, PackageDimensions, ShippingLabel,
+};
+
+/// An order line to put in a parcel
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct FulfillmentLineRequest {
+    pub order_item_id: Uuid,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+/// Create a parcel for order lines (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFulfillmentRequest {
+    /// Lines to ship; everything not yet in a parcel when empty
+    #[serde(default)]
+    #[validate(length(max = 100), nested)]
+    pub items: Vec<FulfillmentLineRequest>,
+    #[validate(length(min = 1, max = 50))]
+    pub carrier: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub tracking_number: Option<String>,
+    /// Overrides the carrier's tracking URL template
+    #[validate(length(min = 1, max = 1000))]
+    pub tracking_url: Option<String>,
+}
+
+/// Hand a parcel to the carrier (admin); tracking details may be added now
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct ShipFulfillmentRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub carrier: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub tracking_number: Option<String>,
+    #[validate(length(min = 1, max = 1000))]
+    pub tracking_url: Option<String>,
+}
+
+/// A parcel with its lines
+#[derive(Debug, Clone, Serialize)]
+pub struct FulfillmentResponse {
+    #[serde(flatten)]
+    pub fulfillment: Fulfillment,
+    pub items: Vec<FulfillmentItem>,
+    /// Label in use, for staff
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<ShippingLabel>,
+}
+
+/// Quote a parcel with a carrier adapter (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct LabelRatesRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub carrier: String,
+    /// Overrides the weight worked out from the catalog and adds box sizes
+    pub package: Option<PackageDimensions>,
+}
+
+/// Buy a label for a parcel from a carrier adapter (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct PurchaseLabelRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub carrier: String,
+    #[validate(length(min = 1, max = 64))]
+    pub service_code: String,
+    /// PDF unless given
+    pub format: Option<LabelFormat>,
+    /// Overrides the weight worked out from the catalog and adds box sizes
+    pub package: Option<PackageDimensions>,
+}
+
+/// What a customer sees when tracking an order
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderTrackingResponse {
+    pub order_id: Uuid,
+    pub order_number: i64,
+    pub status: OrderStatus,
+    pub shipments: Vec<FulfillmentResponse>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCarrierRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub code: String,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// Tracking page URL with a `{tracking_number}` placeholder
+    #[validate(length(min = 1, max = 500))]
+    pub tracking_url_template: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCarrierRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 500))]
+    pub tracking_url_template: Option<String>,
+    pub is_active: Option<bool>,
+}
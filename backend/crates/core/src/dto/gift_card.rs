@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use validator::Validate;
+
+use crate::error::ApiError;
+use crate::models::{GiftCard, GiftCardStatus, GiftCardTransaction};
+use crate::money::Money;
+
+use super::coupon::is_valid_code;
+
+/// A gift card with its ledger (admin)
+#[derive(Debug, Clone, Serialize)]
+pub struct GiftCardResponse {
+    #[serde(flatten)]
+    pub gift_card: GiftCard,
+    pub status: GiftCardStatus,
+    /// Oldest first
+    pub transactions: Vec<GiftCardTransaction>,
+}
+
+/// Issue a gift card (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct IssueGiftCardRequest {
+    /// Initial balance in minor units of the store currency
+    #[validate(range(min = 1))]
+    pub amount: i64,
+    /// Leave out to generate one
+    #[validate(length(min = 6, max = 50))]
+    pub code: Option<String>,
+    /// Leave out for a card that never expires
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[validate(email, length(max = 255))]
+    pub recipient_email: Option<String>,
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+impl IssueGiftCardRequest {
+    /// Checks that a chosen code can be typed in
+    pub fn validate_code(&self) -> Result<(), ApiError> {
+        if self
+            .code
+            .as_deref()
+            .is_some_and(|code| !is_valid_code(code.trim()))
+        {
+            return Err(ApiError::validation(
+                "code may only contain letters, digits, '-' and '_'",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Disable or re-enable a gift card, or replace its expiry, recipient and
+/// note (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateGiftCardRequest {
+    pub is_active: Option<bool>,
+    /// Leave out for a card that never expires
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[validate(email, length(max = 255))]
+    pub recipient_email: Option<String>,
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+/// Add to or take from a gift card's balance (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdjustGiftCardRequest {
+    /// Signed, in minor units of the card currency
+    pub amount: i64,
+    /// Why, kept in the card's ledger
+    #[validate(length(min = 1, max = 500))]
+    pub note: String,
+}
+
+/// Look up a gift card's balance
+#[derive(Debug, Deserialize, Validate)]
+pub struct GiftCardBalanceRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub code: String,
+}
+
+/// What is left on a gift card
+#[derive(Debug, Clone, Serialize)]
+pub struct GiftCardBalanceResponse {
+    pub code: String,
+    pub balance: Money,
+    pub status: GiftCardStatus,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
@@ -1,11 +1,54 @@
+pub mod abandoned_cart;
 pub mod auth;
 pub mod cart;
 pub mod category;
+pub mod checkout;
+pub mod coupon;
+pub mod currency;
+pub mod customer_group;
+pub mod fulfillment;
+pub mod gift_card;
+pub mod order;
 pub mod pagination;
+pub mod payment;
 pub mod product;
+pub mod promotion;
+pub mod refund;
+pub mod returns;
+pub mod sale;
+pub mod shipping;
+pub mod tax;
 
+pub use abandoned_cart::*;
 pub use auth::*;
 pub use cart::*;
 pub use category::*;
+pub use checkout::*;
+pub use coupon::*;
+pub use currency::*;
+pub use customer_group::*;
+pub use fulfillment::*;
+pub use gift_card::*;
+pub use order::*;
 pub use pagination::{PaginatedResponse, PaginationMeta, PaginationParams};
+pub use payment::*;
 pub use product::*;
+pub use promotion::*;
+pub use refund::*;
+pub use returns::*;
+pub use sale::*;
+pub use shipping::*;
+pub use tax::*;
+
+use serde::{Deserialize, Deserializer};
+
+/// Deserialize an update field that can be left out (`None`), cleared with
+/// `null` (`Some(None)`) or set (`Some(Some(value))`); use with
+/// `#[serde(default, deserialize_with = "double_option")]`
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::{Order, OrderAction, OrderItem};
+
+/// Postal address, stored on the order as JSONB
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct Address {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(length(max = 255))]
+    pub company: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    pub line1: String,
+    #[validate(length(max = 255))]
+    pub line2: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub city: String,
+    #[validate(length(max = 100))]
+    pub region: Option<String>,
+    #[validate(length(min = 1, max = 20))]
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    #[validate(length(equal = 2))]
+    pub country: String,
+    #[validate(length(max = 50))]
+    pub phone: Option<String>,
+}
+
+/// Order with its line items
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderResponse {
+    #[serde(flatten)]
+    pub order: Order,
+    pub items: Vec<OrderItem>,
+    /// Lifecycle actions the requester may take next
+    pub allowed_actions: Vec<OrderAction>,
+}
+
+/// Cancel an order (customer)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CancelOrderRequest {
+    #[validate(length(max = 1000))]
+    pub reason: Option<String>,
+}
+
+/// Apply a lifecycle action to an order (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct OrderTransitionRequest {
+    pub action: OrderAction,
+    #[validate(length(max = 1000))]
+    pub reason: Option<String>,
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::Payment;
+
+/// Pay an order (customer)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePaymentRequest {
+    /// Provider payment method token collected by the browser (e.g. a Stripe
+    /// `pm_...` ID). Without one the payment waits for client-side confirmation.
+    #[validate(length(min = 1, max = 255))]
+    pub payment_method: Option<String>,
+}
+
+/// A payment, plus what the browser needs to finish it
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentResponse {
+    #[serde(flatten)]
+    pub payment: Payment,
+    /// Only returned when the payment is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
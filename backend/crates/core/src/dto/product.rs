@@ -4,8 +4,16 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::models::category::CategorySummary;
+use crate::models::currency::DisplayPricing;
+use crate::models::customer_group::PriceTier;
 use crate::models::product::{ProductImage, ProductStatus, ProductVariant};
+use crate::models::quantity_rules::QuantityRules;
+use crate::models::sale::SaleSummary;
+use crate::models::shipping::PackageDimensions;
+use crate::models::tax::TaxClass;
+use crate::money::{Money, MoneyError};
 
+use super::double_option;
 use super::pagination::PaginatedResponse;
 
 /// Product as returned by the API (enriched with category, images, variants).
@@ -16,24 +24,37 @@ pub struct ProductResponse {
     pub slug: String,
     pub description: Option<String>,
     pub short_description: Option<String>,
-    pub price: i32,
-    pub compare_at_price: Option<i32>,
+    pub price: Money,
+    pub compare_at_price: Option<Money>,
+    /// The sale `price` comes from, while one runs
+    pub sale: Option<SaleSummary>,
+    /// Unit prices by quantity for the customer's group; empty when the
+    /// group has no prices for the product
+    pub price_tiers: Vec<PriceTier>,
     pub status: ProductStatus,
     pub is_featured: bool,
     pub sku: Option<String>,
     pub stock_quantity: i32,
     pub attributes: serde_json::Value,
+    pub quantity_rules: QuantityRules,
+    pub dimensions: PackageDimensions,
+    pub tax_class: TaxClass,
+    pub is_gift_card: bool,
+    pub gift_card_validity_days: Option<i32>,
     pub category: Option<CategorySummary>,
     pub images: Vec<ProductImage>,
-    pub variants: Vec<ProductVariant>,
+    pub variants: Vec<ProductVariantResponse>,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
 }
 
-impl From<crate::models::product::Product> for ProductResponse {
-    fn from(p: crate::models::product::Product) -> Self {
+impl ProductResponse {
+    /// Response for a product at its catalog prices
+    pub fn new(p: crate::models::product::Product) -> Self {
+        let quantity_rules = p.quantity_rules();
+        let dimensions = p.dimensions();
         Self {
             id: p.id,
             name: p.name,
@@ -42,11 +63,18 @@ impl From<crate::models::product::Product> for ProductResponse {
             short_description: p.short_description,
             price: p.price,
             compare_at_price: p.compare_at_price,
+            sale: None,
+            price_tiers: vec![],
             status: p.status,
             is_featured: p.is_featured,
             sku: p.sku,
             stock_quantity: p.stock_quantity,
             attributes: p.attributes,
+            quantity_rules,
+            dimensions,
+            tax_class: p.tax_class,
+            is_gift_card: p.is_gift_card,
+            gift_card_validity_days: p.gift_card_validity_days,
             category: None,
             images: vec![],
             variants: vec![],
@@ -54,6 +82,95 @@ impl From<crate::models::product::Product> for ProductResponse {
             updated_at: p.updated_at,
         }
     }
+
+    /// Show the product and its variants in the pricing's currency, at
+    /// the customer group's prices and sale prices where a sale runs
+    pub fn reprice(&mut self, pricing: &DisplayPricing) -> Result<(), MoneyError> {
+        self.price_tiers = pricing.price_tiers(self.id, None, self.price)?;
+        (self.price, self.compare_at_price, self.sale) =
+            sale_price(pricing, self.id, None, self.price, self.compare_at_price)?;
+        for variant in &mut self.variants {
+            variant.price_tiers = pricing.price_tiers(self.id, Some(variant.id), variant.price)?;
+            (variant.price, variant.compare_at_price, variant.sale) = sale_price(
+                pricing,
+                self.id,
+                Some(variant.id),
+                variant.price,
+                variant.compare_at_price,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Catalog price and compare-at price for one unit, and the sale that
+/// lowered them if any
+fn sale_price(
+    pricing: &DisplayPricing,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    price: Money,
+    compare_at_price: Option<Money>,
+) -> Result<(Money, Option<Money>, Option<SaleSummary>), MoneyError> {
+    let (regular, _) = pricing.regular_price(product_id, variant_id, price, compare_at_price)?;
+    let group_price = pricing.group_price(product_id, variant_id, 1, regular)?;
+    let (price, compare_at_price) =
+        pricing.catalog_price(product_id, variant_id, price, compare_at_price)?;
+    let sale = pricing
+        .sale(product_id, variant_id)
+        .filter(|_| price.amount() < group_price.amount())
+        .map(SaleSummary::from);
+    Ok((price, compare_at_price, sale))
+}
+
+/// Variant as returned by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductVariantResponse {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub name: String,
+    pub sku: Option<String>,
+    pub price: Money,
+    pub compare_at_price: Option<Money>,
+    pub sale: Option<SaleSummary>,
+    /// The customer group's prices for this variant, or the product's
+    pub price_tiers: Vec<PriceTier>,
+    pub stock_quantity: i32,
+    pub attributes: serde_json::Value,
+    pub sort_order: i32,
+    pub is_active: bool,
+    pub quantity_rules: QuantityRules,
+    pub dimensions: PackageDimensions,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl ProductVariantResponse {
+    /// Response for a variant at its catalog prices
+    pub fn new(v: ProductVariant) -> Self {
+        let quantity_rules = v.quantity_rules();
+        let dimensions = v.dimensions();
+        Self {
+            id: v.id,
+            product_id: v.product_id,
+            name: v.name,
+            sku: v.sku,
+            price: v.price,
+            compare_at_price: v.compare_at_price,
+            sale: None,
+            price_tiers: vec![],
+            stock_quantity: v.stock_quantity,
+            attributes: v.attributes,
+            sort_order: v.sort_order,
+            is_active: v.is_active,
+            quantity_rules,
+            dimensions,
+            created_at: v.created_at,
+            updated_at: v.updated_at,
+        }
+    }
 }
 
 pub type ProductListResponse = PaginatedResponse<ProductResponse>;
@@ -66,11 +183,11 @@ pub struct CreateProductRequest {
     #[validate(length(max = 500))]
     pub short_description: Option<String>,
     #[validate(range(min = 0))]
-    pub price: i32,
+    pub price: i64,
     #[validate(range(min = 0))]
-    pub compare_at_price: Option<i32>,
+    pub compare_at_price: Option<i64>,
     #[validate(range(min = 0))]
-    pub cost_price: Option<i32>,
+    pub cost_price: Option<i64>,
     #[validate(length(max = 100))]
     pub sku: Option<String>,
     pub category_id: Option<Uuid>,
@@ -79,6 +196,27 @@ pub struct CreateProductRequest {
     pub attributes: Option<serde_json::Value>,
     pub status: Option<ProductStatus>,
     pub is_featured: Option<bool>,
+    #[validate(range(min = 1))]
+    pub min_quantity: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_quantity: Option<i32>,
+    #[validate(range(min = 1))]
+    pub quantity_step: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_per_customer: Option<i32>,
+    #[validate(range(min = 0))]
+    pub weight_grams: Option<i32>,
+    #[validate(range(min = 1))]
+    pub length_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub width_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub height_mm: Option<i32>,
+    pub tax_class: Option<TaxClass>,
+    /// Sell the product as a gift card
+    pub is_gift_card: Option<bool>,
+    #[validate(range(min = 1))]
+    pub gift_card_validity_days: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -89,11 +227,11 @@ pub struct UpdateProductRequest {
     #[validate(length(max = 500))]
     pub short_description: Option<String>,
     #[validate(range(min = 0))]
-    pub price: Option<i32>,
+    pub price: Option<i64>,
     #[validate(range(min = 0))]
-    pub compare_at_price: Option<i32>,
+    pub compare_at_price: Option<i64>,
     #[validate(range(min = 0))]
-    pub cost_price: Option<i32>,
+    pub cost_price: Option<i64>,
     #[validate(length(max = 100))]
     pub sku: Option<String>,
     pub category_id: Option<Uuid>,
@@ -102,6 +240,32 @@ pub struct UpdateProductRequest {
     pub attributes: Option<serde_json::Value>,
     pub status: Option<ProductStatus>,
     pub is_featured: Option<bool>,
+    /// Quantity rules: leave out to keep, `null` to remove
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub min_quantity: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub max_quantity: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub quantity_step: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub max_per_customer: Option<Option<i32>>,
+    #[validate(range(min = 0))]
+    pub weight_grams: Option<i32>,
+    #[validate(range(min = 1))]
+    pub length_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub width_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub height_mm: Option<i32>,
+    pub tax_class: Option<TaxClass>,
+    /// Sell the product as a gift card
+    pub is_gift_card: Option<bool>,
+    #[validate(range(min = 1))]
+    pub gift_card_validity_days: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -111,13 +275,29 @@ pub struct CreateVariantRequest {
     #[validate(length(max = 100))]
     pub sku: Option<String>,
     #[validate(range(min = 0))]
-    pub price: i32,
+    pub price: i64,
     #[validate(range(min = 0))]
-    pub compare_at_price: Option<i32>,
+    pub compare_at_price: Option<i64>,
     #[validate(range(min = 0))]
     pub stock_quantity: Option<i32>,
     pub attributes: Option<serde_json::Value>,
     pub sort_order: Option<i32>,
+    #[validate(range(min = 1))]
+    pub min_quantity: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_quantity: Option<i32>,
+    #[validate(range(min = 1))]
+    pub quantity_step: Option<i32>,
+    #[validate(range(min = 1))]
+    pub max_per_customer: Option<i32>,
+    #[validate(range(min = 0))]
+    pub weight_grams: Option<i32>,
+    #[validate(range(min = 1))]
+    pub length_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub width_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub height_mm: Option<i32>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -127,14 +307,35 @@ pub struct UpdateVariantRequest {
     #[validate(length(max = 100))]
     pub sku: Option<String>,
     #[validate(range(min = 0))]
-    pub price: Option<i32>,
+    pub price: Option<i64>,
     #[validate(range(min = 0))]
-    pub compare_at_price: Option<i32>,
+    pub compare_at_price: Option<i64>,
     #[validate(range(min = 0))]
     pub stock_quantity: Option<i32>,
     pub attributes: Option<serde_json::Value>,
     pub sort_order: Option<i32>,
     pub is_active: Option<bool>,
+    /// Quantity rules: leave out to keep, `null` to remove
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub min_quantity: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub max_quantity: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub quantity_step: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1))]
+    pub max_per_customer: Option<Option<i32>>,
+    #[validate(range(min = 0))]
+    pub weight_grams: Option<i32>,
+    #[validate(range(min = 1))]
+    pub length_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub width_mm: Option<i32>,
+    #[validate(range(min = 1))]
+    pub height_mm: Option<i32>,
 }
 
 /// Query parameters for product listing.
@@ -156,3 +357,23 @@ pub enum ProductSort {
     CreatedAtDesc,
     NameAsc,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_update_quantity_rules_tell_null_from_missing() {
+        let req: UpdateVariantRequest =
+            serde_json::from_value(json!({ "min_quantity": null, "max_quantity": 4 })).unwrap();
+        assert_eq!(req.min_quantity, Some(None));
+        assert_eq!(req.max_quantity, Some(Some(4)));
+        assert_eq!(req.quantity_step, None);
+        assert!(req.validate().is_ok());
+
+        let req: UpdateProductRequest =
+            serde_json::from_value(json!({ "quantity_step": 0 })).unwrap();
+        assert!(req.validate().is_err());
+    }
+}
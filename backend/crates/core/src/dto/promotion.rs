@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use time::OffsetDateTime;
+use validator::Validate;
+
+use crate::error::ApiError;
+use crate::models::{PromotionAction, PromotionConditions};
+
+/// Create a promotion or replace its rules (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct PromotionRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// Higher runs first (default 0)
+    pub priority: Option<i32>,
+    /// Apply alone, never together with another promotion (default false)
+    pub exclusive: Option<bool>,
+    /// Still apply when the customer uses a coupon (default true)
+    pub combines_with_coupons: Option<bool>,
+    #[serde(default)]
+    pub conditions: PromotionConditions,
+    pub action: PromotionAction,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub is_active: Option<bool>,
+}
+
+impl PromotionRequest {
+    /// Checks the conditions, the action and the validity window
+    pub fn validate_rules(&self) -> Result<(), ApiError> {
+        self.conditions.validate()?;
+        self.action.validate()?;
+        if self.conditions.product_ids.len() > 500 || self.conditions.category_ids.len() > 500 {
+            return Err(ApiError::validation(
+                "Give at most 500 products and 500 categories",
+            ));
+        }
+        if let (Some(starts_at), Some(ends_at)) = (self.starts_at, self.ends_at) {
+            if ends_at <= starts_at {
+                return Err(ApiError::validation("ends_at must be after starts_at"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Put a customer in a customer group, or take them out of it (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetCustomerGroupRequest {
+    /// Code of an existing group, e.g. "wholesale"; null for none
+    #[validate(length(min = 1, max = 50))]
+    pub customer_group: Option<String>,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{Refund, RefundItem};
+
+/// An order line to refund
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefundLineRequest {
+    pub order_item_id: Uuid,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+/// Refund part or all of an order (admin).
+///
+/// The refund is the sum of the listed lines, the shipping if `shipping` is
+/// set, and `amount` on top. `full` refunds whatever is left instead.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRefundRequest {
+    #[serde(default)]
+    pub full: bool,
+    #[serde(default)]
+    #[validate(nested)]
+    pub items: Vec<RefundLineRequest>,
+    /// Refund the shipping charged on the order
+    #[serde(default)]
+    pub shipping: bool,
+    /// Extra amount not tied to a line (e.g. a goodwill gesture)
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+    /// Put the refunded line quantities back in stock
+    #[serde(default)]
+    pub restock: bool,
+    #[validate(length(min = 1, max = 1000))]
+    pub reason: String,
+}
+
+/// A refund with the lines it covers
+#[derive(Debug, Clone, Serialize)]
+pub struct RefundResponse {
+    #[serde(flatten)]
+    pub refund: Refund,
+    pub items: Vec<RefundItem>,
+}
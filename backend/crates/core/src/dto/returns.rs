@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{
+    ItemCondition, Return, ReturnItem, ReturnPhoto, ReturnReason, ReturnResolution, ReturnStatus,
+};
+
+/// An order line to send back
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ReturnLineRequest {
+    pub order_item_id: Uuid,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+/// Ask to return lines of an order (customer)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateReturnRequest {
+    #[validate(length(min = 1, max = 100), nested)]
+    pub items: Vec<ReturnLineRequest>,
+    pub reason: ReturnReason,
+    #[validate(length(max = 2000))]
+    pub comment: Option<String>,
+}
+
+/// Approve or reject a return (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReturnDecisionRequest {
+    /// Shown to the customer
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+/// Record a return's arrival (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReceiveReturnRequest {
+    pub condition: ItemCondition,
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+/// Resolve a received return (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct CloseReturnRequest {
+    pub resolution: ReturnResolution,
+    /// Put the returned quantities back in stock
+    #[serde(default)]
+    pub restock: bool,
+    /// Store credit to grant; defaults to the value of the returned lines
+    #[validate(range(min = 1))]
+    pub credit_amount: Option<i64>,
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReturnListFilter {
+    pub status: Option<ReturnStatus>,
+}
+
+/// A return with its lines and photos
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnResponse {
+    #[serde(flatten)]
+    pub r#return: Return,
+    pub items: Vec<ReturnItem>,
+    pub photos: Vec<ReturnPhoto>,
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::ApiError;
+use crate::models::{Sale, SaleItem};
+
+/// Create a sale or replace it and its items (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaleRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// Taken off items without a price or percentage of their own
+    /// (10% = 100000)
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub percentage_ppm: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub starts_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub ends_at: OffsetDateTime,
+    pub is_active: Option<bool>,
+    #[validate(length(min = 1, max = 1000), nested)]
+    pub items: Vec<SaleItemRequest>,
+}
+
+/// A product, or one of its variants, in a sale
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct SaleItemRequest {
+    pub product_id: Uuid,
+    /// Leave out for the product and all its variants
+    pub variant_id: Option<Uuid>,
+    /// Fixed sale price in minor units of the store currency
+    #[validate(range(min = 0))]
+    pub sale_price: Option<i64>,
+    #[validate(range(min = 1, max = 1_000_000))]
+    pub percentage_ppm: Option<i32>,
+    /// Units that can be sold at the sale price
+    #[validate(range(min = 1))]
+    pub quantity_limit: Option<i32>,
+}
+
+impl SaleRequest {
+    /// Checks that span fields: the window, and a price for every item
+    pub fn validate_terms(&self) -> Result<(), ApiError> {
+        if self.ends_at <= self.starts_at {
+            return Err(ApiError::validation("ends_at must be after starts_at"));
+        }
+        let mut seen = std::collections::HashSet::new();
+        for item in &self.items {
+            if item.sale_price.is_none()
+                && item.percentage_ppm.is_none()
+                && self.percentage_ppm.is_none()
+            {
+                return Err(ApiError::validation(
+                    "Each item needs a sale_price or percentage_ppm, or the sale a percentage_ppm",
+                ));
+            }
+            if !seen.insert((item.product_id, item.variant_id)) {
+                return Err(ApiError::validation(
+                    "A product or variant can only be in a sale once",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A sale with its items
+#[derive(Debug, Clone, Serialize)]
+pub struct SaleResponse {
+    #[serde(flatten)]
+    pub sale: Sale,
+    pub items: Vec<SaleItem>,
+}
+
+/// The moment to show the catalog as of (admin preview)
+#[derive(Debug, Deserialize)]
+pub struct CatalogPreviewParams {
+    #[serde(with = "time::serde::rfc3339")]
+    pub at: OffsetDateTime,
+    /// Show the prices this customer group gets
+    pub customer_group: Option<String>,
+}
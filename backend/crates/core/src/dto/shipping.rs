@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::models::{Destination, RateTier, ShippingMethod, ShippingMethodKind, ShippingZone};
+use crate::money::Money;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateShippingZoneRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// ISO 3166-1 alpha-2 codes; empty for the rest of the world
+    #[serde(default)]
+    #[validate(length(max = 250))]
+    pub countries: Vec<String>,
+    #[serde(default)]
+    #[validate(length(max = 250))]
+    pub regions: Vec<String>,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub postcode_patterns: Vec<String>,
+    pub priority: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateShippingZoneRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 250))]
+    pub countries: Option<Vec<String>>,
+    #[validate(length(max = 250))]
+    pub regions: Option<Vec<String>>,
+    #[validate(length(max = 500))]
+    pub postcode_patterns: Option<Vec<String>>,
+    pub priority: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateShippingMethodRequest {
+    #[validate(length(min = 1, max = 64))]
+    pub code: String,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub kind: ShippingMethodKind,
+    #[validate(range(min = 0))]
+    pub rate: Option<i64>,
+    #[serde(default)]
+    #[validate(length(max = 50))]
+    pub tiers: Vec<RateTier>,
+    #[validate(range(min = 0))]
+    pub min_subtotal: Option<i64>,
+    pub sort_order: Option<i32>,
+    pub is_active: Option<bool>,
+    /// Carrier adapter code, for `carrier` methods
+    #[validate(length(min = 1, max = 64))]
+    pub carrier_code: Option<String>,
+    /// The carrier's service quoted by this method (e.g. `ground`)
+    #[validate(length(min = 1, max = 64))]
+    pub service_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateShippingMethodRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub kind: Option<ShippingMethodKind>,
+    #[validate(range(min = 0))]
+    pub rate: Option<i64>,
+    #[validate(length(max = 50))]
+    pub tiers: Option<Vec<RateTier>>,
+    #[validate(range(min = 0))]
+    pub min_subtotal: Option<i64>,
+    pub sort_order: Option<i32>,
+    pub is_active: Option<bool>,
+    /// Carrier adapter code, for `carrier` methods
+    #[validate(length(min = 1, max = 64))]
+    pub carrier_code: Option<String>,
+    /// The carrier's service quoted by this method (e.g. `ground`)
+    #[validate(length(min = 1, max = 64))]
+    pub service_code: Option<String>,
+}
+
+/// A zone with its methods
+#[derive(Debug, Clone, Serialize)]
+pub struct ShippingZoneResponse {
+    #[serde(flatten)]
+    pub zone: ShippingZone,
+    pub methods: Vec<ShippingMethod>,
+}
+
+/// The parts of an address shipping rates depend on
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ShippingDestination {
+    /// ISO 3166-1 alpha-2 country code
+    #[validate(length(equal = 2))]
+    pub country: String,
+    #[validate(length(max = 100))]
+    pub region: Option<String>,
+    #[validate(length(max = 20))]
+    pub postal_code: Option<String>,
+}
+
+impl From<ShippingDestination> for Destination {
+    fn from(d: ShippingDestination) -> Self {
+        Self {
+            country: d.country,
+            region: d.region,
+            postal_code: d.postal_code,
+        }
+    }
+}
+
+/// Quote shipping for the current cart
+#[derive(Debug, Deserialize, Validate)]
+pub struct ShippingRateRequest {
+    #[validate(nested)]
+    pub address: ShippingDestination,
+}
+
+/// A shipping option the customer can choose
+#[derive(Debug, Clone, Serialize)]
+pub struct ShippingRate {
+    /// Submitted as `code` at the `shipping_method` checkout step
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: ShippingMethodKind,
+    pub amount: Money,
+    pub zone_id: Uuid,
+}
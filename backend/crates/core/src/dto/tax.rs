@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+use validator::Validate;
+
+use crate::error::ApiError;
+use crate::models::{customization::parse_date, TaxClass, TaxExemptionStatus};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTaxRateRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    /// ISO 3166-1 alpha-2 code
+    #[validate(length(equal = 2))]
+    pub country: String,
+    #[validate(length(min = 1, max = 100))]
+    pub region: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 500))]
+    pub postcode_patterns: Vec<String>,
+    pub tax_class: Option<TaxClass>,
+    /// Parts per million of the taxed amount (21% = 210000)
+    #[validate(range(min = 0, max = 1_000_000))]
+    pub rate_ppm: i32,
+    pub priority: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTaxRateRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: Option<String>,
+    #[validate(length(equal = 2))]
+    pub country: Option<String>,
+    /// An empty string clears the region
+    #[validate(length(max = 100))]
+    pub region: Option<String>,
+    #[validate(length(max = 500))]
+    pub postcode_patterns: Option<Vec<String>>,
+    pub tax_class: Option<TaxClass>,
+    #[validate(range(min = 0, max = 1_000_000))]
+    pub rate_ppm: Option<i32>,
+    pub priority: Option<i32>,
+    pub is_active: Option<bool>,
+}
+
+/// Start of the day after `date`, in UTC: the end of `date`
+pub fn end_of_day(date: Date) -> OffsetDateTime {
+    date.next_day().unwrap_or(Date::MAX).midnight().assume_utc()
+}
+
+/// Parse a `YYYY-MM-DD` request parameter
+pub fn parse_date_param(name: &str, value: &str) -> Result<Date, ApiError> {
+    parse_date(value)
+        .ok_or_else(|| ApiError::validation(format!("{} must be a YYYY-MM-DD date", name)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxExemptionListFilter {
+    pub status: Option<TaxExemptionStatus>,
+}
+
+/// Approve, reject or revoke a tax exemption (admin)
+#[derive(Debug, Deserialize, Validate)]
+pub struct TaxExemptionDecisionRequest {
+    /// `YYYY-MM-DD`; on approval, replaces the expiry date the customer gave
+    pub expires_on: Option<String>,
+    /// Shown to the customer
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+}
+
+impl TaxExemptionDecisionRequest {
+    pub fn expires_at(&self) -> Result<Option<OffsetDateTime>, ApiError> {
+        self.expires_on
+            .as_deref()
+            .map(|date| parse_date_param("expires_on", date).map(end_of_day))
+            .transpose()
+    }
+}
+
+/// How a report is returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+/// Query parameters of the tax report
+#[derive(Debug, Deserialize)]
+pub struct TaxReportParams {
+    /// First day, `YYYY-MM-DD`; defaults to the start of the current month
+    pub from: Option<String>,
+    /// Last day, included; defaults to today
+    pub to: Option<String>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+impl TaxReportParams {
+    /// The period as a half-open UTC range
+    pub fn period(&self, today: Date) -> Result<(OffsetDateTime, OffsetDateTime), ApiError> {
+        let from = match &self.from {
+            Some(from) => parse_date_param("from", from)?,
+            None => today.replace_day(1).unwrap_or(today),
+        };
+        let to = match &self.to {
+            Some(to) => parse_date_param("to", to)?,
+            None => today,
+        };
+        if to < from {
+            return Err(ApiError::validation(
+                "The report period ends before it starts",
+            ));
+        }
+        Ok((from.midnight().assume_utc(), end_of_day(to)))
+    }
+}
+
+/// Tax collected at one rate in one jurisdiction
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TaxReportRow {
+    pub currency: String,
+    pub country: String,
+    pub region: Option<String>,
+    pub name: String,
+    pub rate_ppm: i32,
+    pub order_count: i64,
+    pub taxable_amount: i64,
+    pub tax_amount: i64,
+}
+
+/// Sales made without tax to exempt customers
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ExemptSalesRow {
+    pub currency: String,
+    pub order_count: i64,
+    pub amount: i64,
+}
+
+/// Tax collected over a period, from the tax snapshots of paid orders
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxReport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub from: OffsetDateTime,
+    /// Excluded
+    #[serde(with = "time::serde::rfc3339")]
+    pub to: OffsetDateTime,
+    pub rows: Vec<TaxReportRow>,
+    pub exempt_sales: Vec<ExemptSalesRow>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    fn date(year: i32, month: Month, day: u8) -> Date {
+        Date::from_calendar_date(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_report_period() {
+        let params = |from: Option<&str>, to: Option<&str>| TaxReportParams {
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            format: ReportFormat::Json,
+        };
+        let today = date(2026, Month::March, 18);
+
+        let (from, to) = params(None, None).period(today).unwrap();
+        assert_eq!(from.date(), date(2026, Month::March, 1));
+        assert_eq!(to.date(), date(2026, Month::March, 19));
+
+        let (from, to) = params(Some("2025-01-01"), Some("2025-12-31"))
+            .period(today)
+            .unwrap();
+        assert_eq!(from.date(), date(2025, Month::January, 1));
+        assert_eq!(to.date(), date(2026, Month::January, 1));
+
+        assert!(params(Some("2025-02-30"), None).period(today).is_err());
+        assert!(params(Some("2026-03-10"), Some("2026-03-09"))
+            .period(today)
+            .is_err());
+    }
+}
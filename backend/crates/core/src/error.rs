@@ -24,6 +24,10 @@ pub enum ApiError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// A business rule was violated; carries its own machine-readable code
+    #[error("Rule violation ({0}): {1}")]
+    Rule(&'static str, String),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -54,6 +58,39 @@ impl ApiError {
     pub fn validation(msg: impl Into<String>) -> Self {
         Self::Validation(msg.into())
     }
+    pub fn rule(code: &'static str, msg: impl Into<String>) -> Self {
+        Self::Rule(code, msg.into())
+    }
+
+    /// Machine-readable error code, as sent in `error.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Validation(_) => "validation_error",
+            ApiError::Rule(code, _) => code,
+            ApiError::Internal(_) | ApiError::Database(_) => "internal_error",
+        }
+    }
+
+    /// Message that is safe to show to clients (internal details are hidden)
+    pub fn public_message(&self) -> String {
+        match self {
+            ApiError::NotFound(msg)
+            | ApiError::BadRequest(msg)
+            | ApiError::Unauthorized(msg)
+            | ApiError::Forbidden(msg)
+            | ApiError::Conflict(msg)
+            | ApiError::Validation(msg)
+            | ApiError::Rule(_, msg) => msg.clone(),
+            ApiError::Internal(_) | ApiError::Database(_) => {
+                "An internal error occurred".to_string()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -77,39 +114,27 @@ pub struct ValidationDetail {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let (status, code, message) = match &self {
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg.clone()),
-            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "unauthorized", msg.clone()),
-            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg.clone()),
-            ApiError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
-            ApiError::Validation(msg) => (
-                StatusCode::UNPROCESSABLE_ENTITY,
-                "validation_error",
-                msg.clone(),
-            ),
+        let status = match &self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Validation(_) | ApiError::Rule(..) => StatusCode::UNPROCESSABLE_ENTITY,
             ApiError::Internal(msg) => {
                 tracing::error!("Internal error: {msg}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "internal_error",
-                    "An internal error occurred".to_string(),
-                )
+                StatusCode::INTERNAL_SERVER_ERROR
             }
             ApiError::Database(err) => {
                 tracing::error!("Database error: {err}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "internal_error",
-                    "An internal error occurred".to_string(),
-                )
+                StatusCode::INTERNAL_SERVER_ERROR
             }
         };
 
         let body = ErrorResponse {
             error: ErrorBody {
-                code: code.to_string(),
-                message,
+                code: self.code().to_string(),
+                message: self.public_message(),
                 details: None,
             },
         };
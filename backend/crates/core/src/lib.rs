@@ -4,8 +4,10 @@
 pub mod dto;
 pub mod error;
 pub mod models;
+pub mod money;
 
 pub use error::{ApiError, ErrorResponse};
+pub use money::{Currency, Money};
 
 /// Convenience Result alias using ApiError
 pub type Result<T> = std::result::Result<T, ApiError>;
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum AbandonedCartStatus {
+    Abandoned,
+    Recovered,
+    Expired,
+}
+
+impl std::fmt::Display for AbandonedCartStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbandonedCartStatus::Abandoned => write!(f, "abandoned"),
+            AbandonedCartStatus::Recovered => write!(f, "recovered"),
+            AbandonedCartStatus::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AbandonedCart {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub cart_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    pub cart_total: i64,
+    pub item_count: i32,
+    pub status: AbandonedCartStatus,
+    pub messages_sent: i32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_message_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub abandoned_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub recovered_at: Option<OffsetDateTime>,
+    pub recovered_cart_id: Option<Uuid>,
+    /// First order placed from the recovered cart
+    pub recovered_order_id: Option<Uuid>,
+    pub recovered_revenue: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Per-store abandoned cart settings, read from `stores.config.abandoned_cart`.
+///
+/// ```json
+/// { "abandoned_cart": { "enabled": true, "after_hours": 4, "message_delays_hours": [1, 24, 72] } }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbandonedCartSettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Hours without cart activity before the cart counts as abandoned
+    #[serde(default = "default_after_hours")]
+    pub after_hours: i64,
+    /// Hours after abandonment at which each recovery message is sent
+    #[serde(default = "default_message_delays_hours")]
+    pub message_delays_hours: Vec<i64>,
+    /// Days after abandonment when an unrecovered cart is given up on
+    #[serde(default = "default_expire_after_days")]
+    pub expire_after_days: i64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_after_hours() -> i64 {
+    4
+}
+
+fn default_message_delays_hours() -> Vec<i64> {
+    vec![1, 24, 72]
+}
+
+fn default_expire_after_days() -> i64 {
+    14
+}
+
+impl Default for AbandonedCartSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            after_hours: default_after_hours(),
+            message_delays_hours: default_message_delays_hours(),
+            expire_after_days: default_expire_after_days(),
+        }
+    }
+}
+
+impl AbandonedCartSettings {
+    /// Extract settings from a store's config JSON, falling back to defaults
+    pub fn from_store_config(config: &serde_json::Value) -> Self {
+        config
+            .get("abandoned_cart")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// When the next recovery message is due, or None if the sequence is complete
+    pub fn next_message_due(
+        &self,
+        abandoned_at: OffsetDateTime,
+        messages_sent: i32,
+    ) -> Option<OffsetDateTime> {
+        let step = usize::try_from(messages_sent).ok()?;
+        self.message_delays_hours
+            .get(step)
+            .map(|hours| abandoned_at + Duration::hours(*hours))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_fall_back_to_defaults() {
+        let settings = AbandonedCartSettings::from_store_config(&serde_json::json!({}));
+        assert!(settings.enabled);
+        assert_eq!(settings.after_hours, 4);
+        assert_eq!(settings.message_delays_hours, vec![1, 24, 72]);
+
+        let settings = AbandonedCartSettings::from_store_config(&serde_json::json!({
+            "abandoned_cart": { "after_hours": 12 }
+        }));
+        assert_eq!(settings.after_hours, 12);
+        assert_eq!(settings.expire_after_days, 14);
+    }
+
+    #[test]
+    fn test_next_message_due() {
+        let settings = AbandonedCartSettings::default();
+        let abandoned_at = OffsetDateTime::UNIX_EPOCH;
+
+        assert_eq!(
+            settings.next_message_due(abandoned_at, 0),
+            Some(abandoned_at + Duration::hours(1))
+        );
+        assert_eq!(
+            settings.next_message_due(abandoned_at, 2),
+            Some(abandoned_at + Duration::hours(72))
+        );
+        assert_eq!(settings.next_message_due(abandoned_at, 3), None);
+    }
+}
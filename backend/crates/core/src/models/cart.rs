@@ -8,6 +8,11 @@ pub struct Cart {
     pub store_id: Uuid,
     pub user_id: Option<Uuid>,
     pub session_id: Option<String>,
+    pub email: Option<String>,
+    /// Coupon entered on the cart, checked again when the order is placed
+    pub coupon_code: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_activity_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
@@ -21,6 +26,9 @@ pub struct CartItem {
     pub product_id: Uuid,
     pub variant_id: Option<Uuid>,
     pub quantity: i32,
+    pub properties: serde_json::Value,
+    pub properties_hash: String,
+    pub price_modifier: i64,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
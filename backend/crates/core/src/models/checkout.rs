@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::customization::CustomizationOption;
+use crate::error::ApiError;
+
+/// A step of the checkout flow, as listed in `StoreConfig.checkout_flow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutStep {
+    /// Review the cart; no payload
+    Cart,
+    /// Contact email
+    Contact,
+    /// Shipping (and billing) address
+    Shipping,
+    /// Delivery option chosen by the customer
+    ShippingMethod,
+    /// Payment method
+    Payment,
+    /// Final review; completing the session places the order
+    Confirmation,
+}
+
+impl CheckoutStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckoutStep::Cart => "cart",
+            CheckoutStep::Contact => "contact",
+            CheckoutStep::Shipping => "shipping",
+            CheckoutStep::ShippingMethod => "shipping_method",
+            CheckoutStep::Payment => "payment",
+            CheckoutStep::Confirmation => "confirmation",
+        }
+    }
+
+    pub fn parse(step: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(step.to_string())).ok()
+    }
+}
+
+impl std::fmt::Display for CheckoutStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A store-defined field collected during checkout (e.g. an installation date).
+///
+/// Uses the same definition format as product customizations, plus the step
+/// that collects it:
+///
+/// ```json
+/// { "checkout_fields": [
+///     { "key": "installation_date", "type": "date", "required": true, "step": "shipping" }
+/// ] }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckoutField {
+    #[serde(flatten)]
+    pub field: CustomizationOption,
+    #[serde(default = "default_field_step")]
+    pub step: CheckoutStep,
+}
+
+fn default_field_step() -> CheckoutStep {
+    CheckoutStep::Contact
+}
+
+/// Per-store checkout settings, read from `stores.config`
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckoutSettings {
+    pub steps: Vec<CheckoutStep>,
+    pub fields: Vec<CheckoutField>,
+}
+
+impl Default for CheckoutSettings {
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                CheckoutStep::Cart,
+                CheckoutStep::Contact,
+                CheckoutStep::Shipping,
+                CheckoutStep::Payment,
+                CheckoutStep::Confirmation,
+            ],
+            fields: vec![],
+        }
+    }
+}
+
+impl CheckoutSettings {
+    /// Extract settings from a store's config JSON.
+    ///
+    /// Unknown step names are ignored. `confirmation` is always the last
+    /// step, even when the store did not list it.
+    pub fn from_store_config(config: &serde_json::Value) -> Result<Self, ApiError> {
+        let mut settings = Self::default();
+
+        if let Some(flow) = config.get("checkout_flow").and_then(|v| v.as_array()) {
+            let mut steps: Vec<CheckoutStep> = vec![];
+            for step in flow.iter().filter_map(|v| v.as_str()) {
+                match CheckoutStep::parse(step) {
+                    Some(step) if !steps.contains(&step) => steps.push(step),
+                    Some(_) => {}
+                    None => tracing::warn!("Ignoring unknown checkout step '{}'", step),
+                }
+            }
+            steps.retain(|step| *step != CheckoutStep::Confirmation);
+            steps.push(CheckoutStep::Confirmation);
+            settings.steps = steps;
+        }
+
+        if let Some(fields) = config.get("checkout_fields") {
+            settings.fields = serde_json::from_value(fields.clone())
+                .map_err(|e| ApiError::internal(format!("Invalid checkout fields: {}", e)))?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Custom fields collected by `step`; fields attached to a step the store
+    /// did not enable are collected at confirmation instead
+    pub fn fields_for(&self, step: CheckoutStep) -> Vec<CustomizationOption> {
+        self.fields
+            .iter()
+            .filter(|f| {
+                let collected_at = if self.steps.contains(&f.step) {
+                    f.step
+                } else {
+                    CheckoutStep::Confirmation
+                };
+                collected_at == step
+            })
+            .map(|f| f.field.clone())
+            .collect()
+    }
+
+    /// First enabled step not yet completed
+    pub fn current_step(&self, completed: &[CheckoutStep]) -> CheckoutStep {
+        self.steps
+            .iter()
+            .copied()
+            .find(|step| !completed.contains(step))
+            .unwrap_or(CheckoutStep::Confirmation)
+    }
+
+    /// Check that `step` is enabled and every step before it is completed.
+    ///
+    /// Completed steps can be resubmitted to change their data.
+    pub fn ensure_can_submit(
+        &self,
+        step: CheckoutStep,
+        completed: &[CheckoutStep],
+    ) -> Result<(), ApiError> {
+        let position = self.steps.iter().position(|s| *s == step).ok_or_else(|| {
+            ApiError::rule(
+                "checkout_step_disabled",
+                format!("This store does not use the {} step", step),
+            )
+        })?;
+
+        if let Some(missing) = self.steps[..position]
+            .iter()
+            .find(|s| !completed.contains(s))
+        {
+            return Err(ApiError::rule(
+                "checkout_step_incomplete",
+                format!("Complete the {} step first", missing),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum CheckoutSessionStatus {
+    Open,
+    Completed,
+}
+
+impl std::fmt::Display for CheckoutSessionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckoutSessionStatus::Open => write!(f, "open"),
+            CheckoutSessionStatus::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+/// Server-side progress through the checkout flow for one cart
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CheckoutSession {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub cart_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub status: CheckoutSessionStatus,
+    /// Names of the steps submitted so far
+    pub completed_steps: Vec<String>,
+    pub email: Option<String>,
+    pub shipping_address: Option<serde_json::Value>,
+    pub billing_address: Option<serde_json::Value>,
+    pub shipping_method: Option<String>,
+    pub payment_method: Option<String>,
+    /// Gift card codes to redeem when the order is placed
+    pub gift_card_codes: Vec<String>,
+    /// Values of the store's custom checkout fields
+    pub custom_fields: serde_json::Value,
+    pub order_id: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl CheckoutSession {
+    pub fn completed(&self) -> Vec<CheckoutStep> {
+        self.completed_steps
+            .iter()
+            .filter_map(|step| CheckoutStep::parse(step))
+            .collect()
+    }
+}
+
+/// Validated data from one submitted step, saved onto the session
+#[derive(Debug, Clone, Default)]
+pub struct CheckoutStepData {
+    pub email: Option<String>,
+    pub shipping_address: Option<serde_json::Value>,
+    pub billing_address: Option<serde_json::Value>,
+    pub shipping_method: Option<String>,
+    pub payment_method: Option<String>,
+    /// Replaces the session's gift card codes when given
+    pub gift_card_codes: Option<Vec<String>>,
+    /// Custom field values, merged into the session's `custom_fields`
+    pub custom_fields: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_settings_from_store_config() {
+        let settings = CheckoutSettings::from_store_config(&json!({
+            "checkout_flow": ["cart", "shipping", "teleport", "payment"],
+            "checkout_fields": [
+                { "key": "installation_date", "type": "date", "required": true, "step": "shipping" },
+                { "key": "referral", "type": "text", "step": "contact" }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            settings.steps,
+            vec![
+                CheckoutStep::Cart,
+                CheckoutStep::Shipping,
+                CheckoutStep::Payment,
+                CheckoutStep::Confirmation
+            ]
+        );
+        assert_eq!(settings.fields_for(CheckoutStep::Shipping).len(), 1);
+        // The contact step is disabled, so its field moves to confirmation
+        assert_eq!(settings.fields_for(CheckoutStep::Confirmation).len(), 1);
+
+        let defaults = CheckoutSettings::from_store_config(&json!({})).unwrap();
+        assert_eq!(defaults.steps.len(), 5);
+    }
+
+    #[test]
+    fn test_steps_are_submitted_in_order() {
+        let settings = CheckoutSettings::default();
+        let completed = vec![CheckoutStep::Cart];
+
+        assert_eq!(settings.current_step(&completed), CheckoutStep::Contact);
+        assert!(settings
+            .ensure_can_submit(CheckoutStep::Contact, &completed)
+            .is_ok());
+        assert!(settings
+            .ensure_can_submit(CheckoutStep::Cart, &completed)
+            .is_ok());
+        assert_eq!(
+            settings
+                .ensure_can_submit(CheckoutStep::Payment, &completed)
+                .unwrap_err()
+                .code(),
+            "checkout_step_incomplete"
+        );
+        assert_eq!(
+            settings
+                .ensure_can_submit(CheckoutStep::ShippingMethod, &completed)
+                .unwrap_err()
+                .code(),
+            "checkout_step_disabled"
+        );
+    }
+}
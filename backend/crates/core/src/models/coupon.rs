@@ -0,0 +1,368 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::models::currency::DisplayPricing;
+use crate::models::promotion::format_percentage;
+use crate::money::{Currency, Money, RoundingMode};
+
+/// What a coupon takes off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CouponKind {
+    /// A share of the eligible lines
+    Percentage,
+    /// A fixed amount, spread over the eligible lines
+    FixedAmount,
+    /// The shipping charge
+    FreeShipping,
+}
+
+impl std::fmt::Display for CouponKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CouponKind::Percentage => write!(f, "percentage"),
+            CouponKind::FixedAmount => write!(f, "fixed_amount"),
+            CouponKind::FreeShipping => write!(f, "free_shipping"),
+        }
+    }
+}
+
+/// A discount customers unlock with a code
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Coupon {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// Shared code; None when only generated single-use codes work
+    pub code: Option<String>,
+    /// Shown to customers next to the discount
+    pub description: Option<String>,
+    pub kind: CouponKind,
+    /// Parts per million of the eligible lines (10% = 100000)
+    pub percentage_ppm: Option<i32>,
+    /// Minor units of the store currency
+    pub amount: Option<i64>,
+    /// Cart subtotal needed, in minor units of the store currency
+    pub min_subtotal: Option<i64>,
+    /// Products and categories the discount applies to; both empty for the
+    /// whole cart
+    pub product_ids: Vec<Uuid>,
+    pub category_ids: Vec<Uuid>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    /// Redemptions allowed in total; None for no limit
+    pub usage_limit: Option<i32>,
+    /// Redemptions allowed per customer (account or email); None for no limit
+    pub usage_limit_per_customer: Option<i32>,
+    pub times_used: i32,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A generated code that redeems its coupon once
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CouponCode {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub coupon_id: Uuid,
+    pub code: String,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub used_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A coupon used on an order
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CouponRedemption {
+    pub id: Uuid,
+    pub coupon_id: Uuid,
+    pub coupon_code_id: Option<Uuid>,
+    pub order_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    pub code: String,
+    /// Taken off the lines plus the shipping waived, in minor units of `currency`
+    pub amount: i64,
+    pub currency: Currency,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A cart or order line a coupon may apply to
+#[derive(Debug, Clone)]
+pub struct DiscountableLine {
+    pub product_id: Uuid,
+    pub category_id: Option<Uuid>,
+    /// Line subtotal before discounts
+    pub amount: Money,
+}
+
+/// What a coupon takes off a cart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CouponDiscount {
+    /// Per line, in line order
+    pub lines: Vec<Money>,
+    pub total: Money,
+    pub free_shipping: bool,
+    /// E.g. "10% off Game, Tube" or "Free shipping"
+    pub explanation: String,
+}
+
+impl Coupon {
+    /// Whether the coupon can be used at `now`, before looking at the cart
+    /// or the customer
+    pub fn check_available(&self, now: OffsetDateTime) -> Result<(), ApiError> {
+        if !self.is_active {
+            return Err(ApiError::rule(
+                "coupon_inactive",
+                "This coupon is no longer available",
+            ));
+        }
+        if self.starts_at.is_some_and(|starts_at| now < starts_at) {
+            return Err(ApiError::rule(
+                "coupon_not_started",
+                "This coupon is not valid yet",
+            ));
+        }
+        if self.ends_at.is_some_and(|ends_at| now >= ends_at) {
+            return Err(ApiError::rule("coupon_expired", "This coupon has expired"));
+        }
+        if self
+            .usage_limit
+            .is_some_and(|limit| self.times_used >= limit)
+        {
+            return Err(ApiError::rule(
+                "coupon_used_up",
+                "This coupon has been fully redeemed",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the discount applies to a line
+    pub fn applies_to(&self, line: &DiscountableLine) -> bool {
+        (self.product_ids.is_empty() && self.category_ids.is_empty())
+            || self.product_ids.contains(&line.product_id)
+            || line
+                .category_id
+                .is_some_and(|category_id| self.category_ids.contains(&category_id))
+    }
+
+    /// The discount on a cart whose lines are priced in `pricing`'s
+    /// currency; store-currency amounts are converted at its rate
+    pub fn discount(
+        &self,
+        lines: &[DiscountableLine],
+        pricing: &DisplayPricing,
+    ) -> Result<CouponDiscount, ApiError> {
+        let currency = pricing.currency();
+        let store_amount = |amount: i64| pricing.convert(Money::new(amount, pricing.base()));
+
+        let subtotal = Money::sum(currency, lines.iter().map(|line| line.amount))?;
+        if let Some(min_subtotal) = self.min_subtotal {
+            let min_subtotal = store_amount(min_subtotal)?;
+            if subtotal.amount() < min_subtotal.amount() {
+                return Err(ApiError::rule(
+                    "coupon_min_subtotal",
+                    format!("This coupon needs a subtotal of at least {}", min_subtotal),
+                ));
+            }
+        }
+
+        let weights: Vec<i64> = lines
+            .iter()
+            .map(|line| {
+                if self.applies_to(line) {
+                    line.amount.amount()
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let eligible: i64 = weights.iter().sum();
+        if eligible <= 0 {
+            return Err(ApiError::rule(
+                "coupon_not_applicable",
+                "This coupon does not apply to any item in the cart",
+            ));
+        }
+        let eligible = Money::new(eligible, currency);
+        let scope = if self.product_ids.is_empty() && self.category_ids.is_empty() {
+            "your order".to_string()
+        } else {
+            "eligible items".to_string()
+        };
+
+        let (total, explanation) = match self.kind {
+            CouponKind::Percentage => {
+                let ppm = self.percentage_ppm.unwrap_or(0);
+                let total = eligible.mul_ratio(i64::from(ppm), 1_000_000, RoundingMode::HalfUp)?;
+                (total, format!("{} off {}", format_percentage(ppm), scope))
+            }
+            CouponKind::FixedAmount => {
+                let amount = store_amount(self.amount.unwrap_or(0))?;
+                let total = if amount.amount() > eligible.amount() {
+                    eligible
+                } else {
+                    amount
+                };
+                (total, format!("{} off {}", amount, scope))
+            }
+            CouponKind::FreeShipping => (Money::zero(currency), "Free shipping".to_string()),
+        };
+
+        Ok(CouponDiscount {
+            lines: total.allocate(&weights)?,
+            total,
+            free_shipping: self.kind == CouponKind::FreeShipping,
+            explanation,
+        })
+    }
+}
+
+/// Characters of generated codes, without look-alikes (0/O, 1/I/L)
+pub(crate) const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+/// A random single-use code like `SPRING-7KQ2-MXW9`
+pub fn generate_code(prefix: Option<&str>) -> String {
+    let mut rng = rand::thread_rng();
+    let mut group = || {
+        (0..4)
+            .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+            .collect::<String>()
+    };
+    let random = format!("{}-{}", group(), group());
+    match prefix {
+        Some(prefix) => format!("{}-{}", prefix.to_uppercase(), random),
+        None => random,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coupon(kind: CouponKind) -> Coupon {
+        let now = OffsetDateTime::now_utc();
+        Coupon {
+            id: Uuid::now_v7(),
+            store_id: Uuid::now_v7(),
+            code: Some("SAVE".into()),
+            description: None,
+            kind,
+            percentage_ppm: None,
+            amount: None,
+            min_subtotal: None,
+            product_ids: vec![],
+            category_ids: vec![],
+            starts_at: None,
+            ends_at: None,
+            usage_limit: None,
+            usage_limit_per_customer: None,
+            times_used: 0,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn line(product_id: Uuid, amount: i64) -> DiscountableLine {
+        DiscountableLine {
+            product_id,
+            category_id: None,
+            amount: Money::new(amount, Currency::USD),
+        }
+    }
+
+    fn usd(amounts: &[i64]) -> Vec<Money> {
+        amounts
+            .iter()
+            .map(|amount| Money::new(*amount, Currency::USD))
+            .collect()
+    }
+
+    #[test]
+    fn test_coupon_availability() {
+        let now = OffsetDateTime::now_utc();
+        let mut c = coupon(CouponKind::FreeShipping);
+        assert!(c.check_available(now).is_ok());
+
+        c.starts_at = Some(now + time::Duration::hours(1));
+        assert_eq!(
+            c.check_available(now).unwrap_err().code(),
+            "coupon_not_started"
+        );
+        c.starts_at = None;
+        c.ends_at = Some(now);
+        assert_eq!(c.check_available(now).unwrap_err().code(), "coupon_expired");
+        c.ends_at = None;
+        c.usage_limit = Some(3);
+        c.times_used = 3;
+        assert_eq!(c.check_available(now).unwrap_err().code(), "coupon_used_up");
+        c.usage_limit = None;
+        c.is_active = false;
+        assert_eq!(
+            c.check_available(now).unwrap_err().code(),
+            "coupon_inactive"
+        );
+    }
+
+    #[test]
+    fn test_coupon_discounts() {
+        let pricing = DisplayPricing::store(Currency::USD);
+        let (shoes, socks) = (Uuid::now_v7(), Uuid::now_v7());
+        let lines = [line(shoes, 4999), line(socks, 1001)];
+
+        // 15% of 60.00, spread over the lines by value
+        let mut c = coupon(CouponKind::Percentage);
+        c.percentage_ppm = Some(150_000);
+        let discount = c.discount(&lines, &pricing).unwrap();
+        assert_eq!(discount.total, Money::new(900, Currency::USD));
+        assert_eq!(discount.lines, usd(&[750, 150]));
+
+        // Only the eligible product
+        c.product_ids = vec![socks];
+        let discount = c.discount(&lines, &pricing).unwrap();
+        assert_eq!(discount.lines, usd(&[0, 150]));
+        c.product_ids = vec![Uuid::now_v7()];
+        assert_eq!(
+            c.discount(&lines, &pricing).unwrap_err().code(),
+            "coupon_not_applicable"
+        );
+
+        // Fixed amounts never exceed the eligible lines
+        let mut c = coupon(CouponKind::FixedAmount);
+        c.amount = Some(2000);
+        c.product_ids = vec![socks];
+        let discount = c.discount(&lines, &pricing).unwrap();
+        assert_eq!(discount.lines, usd(&[0, 1001]));
+
+        let mut c = coupon(CouponKind::FreeShipping);
+        c.min_subtotal = Some(10_000);
+        assert_eq!(
+            c.discount(&lines, &pricing).unwrap_err().code(),
+            "coupon_min_subtotal"
+        );
+        c.min_subtotal = Some(6_000);
+        let discount = c.discount(&lines, &pricing).unwrap();
+        assert!(discount.free_shipping);
+        assert_eq!(discount.lines, usd(&[0, 0]));
+    }
+
+    #[test]
+    fn test_generated_codes() {
+        let code = generate_code(Some("spring"));
+        assert!(code.starts_with("SPRING-"));
+        assert_eq!(code.len(), "SPRING-XXXX-XXXX".len());
+        assert_ne!(generate_code(None), generate_code(None));
+    }
+}
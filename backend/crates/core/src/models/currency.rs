@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::customer_group::{tier_for, GroupPrice, PriceTier};
+use crate::models::sale::SalePrice;
+use crate::money::{Currency, Money, MoneyError, RoundingMode};
+
+/// A currency a store shows prices in besides its own.
+///
+/// Converted prices are rounded to `rounding_increment` minor units and
+/// then given `price_ending`: an increment of 100 with an ending of 99 turns
+/// 12.34 into 11.99 or 12.99, depending on the rounding mode.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreCurrency {
+    pub store_id: Uuid,
+    pub currency: Currency,
+    pub rounding_mode: RoundingMode,
+    /// Minor units; 1 keeps converted prices as they are
+    pub rounding_increment: i64,
+    /// Minor units below each increment, e.g. 99 for .99 prices
+    pub price_ending: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl StoreCurrency {
+    pub fn rounding(&self) -> PriceRounding {
+        PriceRounding {
+            mode: self.rounding_mode,
+            increment: self.rounding_increment,
+            ending: self.price_ending.unwrap_or(0),
+        }
+    }
+}
+
+/// Where an exchange rate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeRateSource {
+    /// Entered by staff
+    Manual,
+    /// Loaded from a rates file
+    Import,
+}
+
+impl std::fmt::Display for ExchangeRateSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExchangeRateSource::Manual => write!(f, "manual"),
+            ExchangeRateSource::Import => write!(f, "import"),
+        }
+    }
+}
+
+/// Units of a currency per unit of the store currency
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExchangeRate {
+    pub store_id: Uuid,
+    pub currency: Currency,
+    /// Millionths of a unit per unit of the store currency (0.92 = 920000)
+    pub rate_ppm: i64,
+    pub source: ExchangeRateSource,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A fixed price in a currency for a product, or one of its variants, used
+/// instead of converting the store price
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PriceListEntry {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub currency: Currency,
+    pub product_id: Uuid,
+    /// None for the product's own price
+    pub variant_id: Option<Uuid>,
+    /// Minor units of `currency`
+    pub price: i64,
+    pub compare_at_price: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// How converted prices are rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceRounding {
+    pub mode: RoundingMode,
+    pub increment: i64,
+    pub ending: i64,
+}
+
+impl Default for PriceRounding {
+    fn default() -> Self {
+        Self {
+            mode: RoundingMode::HalfUp,
+            increment: 1,
+            ending: 0,
+        }
+    }
+}
+
+impl PriceRounding {
+    pub fn apply(&self, price: Money) -> Result<Money, MoneyError> {
+        if self.increment == 1 || price.is_zero() {
+            return Ok(price);
+        }
+        let ending = Money::new(self.ending, price.currency());
+        let rounded = price
+            .checked_sub(ending)?
+            .round_to(self.increment, self.mode)?
+            .checked_add(ending)?;
+        // Never round a price down to nothing or below
+        if rounded.amount() <= 0 {
+            let lowest = if self.ending > 0 {
+                self.ending
+            } else {
+                self.increment
+            };
+            return Ok(Money::new(lowest, price.currency()));
+        }
+        Ok(rounded)
+    }
+}
+
+/// Parse a decimal exchange rate such as `0.921345` or `151.23` into
+/// millionths; more than six decimals are rounded half up
+pub fn parse_rate_ppm(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let mut micros: i64 = format!("{:0<6}", &fraction[..fraction.len().min(6)])
+        .parse()
+        .ok()?;
+    if fraction
+        .as_bytes()
+        .get(6)
+        .is_some_and(|digit| *digit >= b'5')
+    {
+        micros += 1;
+    }
+    let rate = whole.checked_mul(1_000_000)?.checked_add(micros)?;
+    (rate > 0).then_some(rate)
+}
+
+/// How catalog prices, kept in the store currency, are shown in the
+/// currency a shopper asked for, with the sales running at the time.
+/// Orders are placed and charged in the store currency.
+#[derive(Debug, Clone)]
+pub struct DisplayPricing {
+    base: Currency,
+    currency: Currency,
+    rate_ppm: i64,
+    rounding: PriceRounding,
+    /// Fixed prices by product and variant
+    fixed: HashMap<(Uuid, Option<Uuid>), (i64, Option<i64>)>,
+    /// Sale prices by product and variant; a None variant covers them all
+    sales: HashMap<(Uuid, Option<Uuid>), SalePrice>,
+    /// The customer group's price rules by product and variant, sorted by
+    /// minimum quantity
+    group_prices: HashMap<(Uuid, Option<Uuid>), Vec<GroupPrice>>,
+}
+
+impl DisplayPricing {
+    /// Prices as they are, in the store currency
+    pub fn store(base: Currency) -> Self {
+        Self {
+            base,
+            currency: base,
+            rate_ppm: 1_000_000,
+            rounding: PriceRounding::default(),
+            fixed: HashMap::new(),
+            sales: HashMap::new(),
+            group_prices: HashMap::new(),
+        }
+    }
+
+    /// Prices converted from `base` at `rate`, with fixed prices from a
+    /// price list taking precedence
+    pub fn converted(
+        base: Currency,
+        currency: &StoreCurrency,
+        rate: &ExchangeRate,
+        price_list: Vec<PriceListEntry>,
+    ) -> Self {
+        Self {
+            base,
+            currency: currency.currency,
+            rate_ppm: rate.rate_ppm,
+            rounding: currency.rounding(),
+            fixed: price_list
+                .into_iter()
+                .map(|entry| {
+                    (
+                        (entry.product_id, entry.variant_id),
+                        (entry.price, entry.compare_at_price),
+                    )
+                })
+                .collect(),
+            sales: HashMap::new(),
+            group_prices: HashMap::new(),
+        }
+    }
+
+    /// The same pricing with sale prices applied; when two sales cover the
+    /// same product or variant, the first one given wins
+    pub fn with_sales(mut self, sales: Vec<SalePrice>) -> Self {
+        for sale in sales {
+            self.sales
+                .entry((sale.product_id, sale.variant_id))
+                .or_insert(sale);
+        }
+        self
+    }
+
+    /// The sale price in effect for a product or variant, unless its units
+    /// at the sale price are sold out
+    pub fn sale(&self, product_id: Uuid, variant_id: Option<Uuid>) -> Option<&SalePrice> {
+        variant_id
+            .and_then(|variant_id| self.sales.get(&(product_id, Some(variant_id))))
+            .or_else(|| self.sales.get(&(product_id, None)))
+            .filter(|sale| sale.remaining.is_none_or(|remaining| remaining > 0))
+    }
+
+    /// The same pricing with a customer group's price rules applied
+    pub fn with_group_prices(mut self, rules: Vec<GroupPrice>) -> Self {
+        for rule in rules {
+            self.group_prices
+                .entry((rule.product_id, rule.variant_id))
+                .or_default()
+                .push(rule);
+        }
+        for rules in self.group_prices.values_mut() {
+            rules.sort_by_key(|rule| rule.min_quantity);
+        }
+        self
+    }
+
+    /// The group's rules for a variant, or for its product when the variant
+    /// has none of its own
+    fn group_rules(&self, product_id: Uuid, variant_id: Option<Uuid>) -> &[GroupPrice] {
+        variant_id
+            .and_then(|variant_id| self.group_prices.get(&(product_id, Some(variant_id))))
+            .or_else(|| self.group_prices.get(&(product_id, None)))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The store currency prices are converted from
+    pub fn base(&self) -> Currency {
+        self.base
+    }
+
+    /// The currency prices are shown in
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Convert a store-currency amount at the exchange rate, to the nearest
+    /// minor unit
+    pub fn convert(&self, amount: Money) -> Result<Money, MoneyError> {
+        if amount.currency() != self.base {
+            return Err(MoneyError::CurrencyMismatch(self.base, amount.currency()));
+        }
+        if self.currency == self.base {
+            return Ok(amount);
+        }
+        amount.convert(self.currency, self.rate_ppm, RoundingMode::HalfUp)
+    }
+
+    /// Convert a price tag, applying the currency's rounding rule
+    pub fn price(&self, amount: Money) -> Result<Money, MoneyError> {
+        self.rounding.apply(self.convert(amount)?)
+    }
+
+    /// Price and compare-at price of a product, or of one of its variants,
+    /// for a single unit
+    pub fn catalog_price(
+        &self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        price: Money,
+        compare_at_price: Option<Money>,
+    ) -> Result<(Money, Option<Money>), MoneyError> {
+        self.quantity_price(product_id, variant_id, 1, price, compare_at_price)
+    }
+
+    /// Unit price and compare-at price when buying `quantity` units: the
+    /// customer group's price for that quantity, or a running sale's price
+    /// when it is lower. The regular price is shown as the compare-at price
+    /// whenever either lowers it.
+    pub fn quantity_price(
+        &self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        quantity: i32,
+        price: Money,
+        compare_at_price: Option<Money>,
+    ) -> Result<(Money, Option<Money>), MoneyError> {
+        let (regular, compare_at_price) =
+            self.regular_price(product_id, variant_id, price, compare_at_price)?;
+        let mut price = self.group_price(product_id, variant_id, quantity, regular)?;
+        if let Some(sale) = self.sale(product_id, variant_id) {
+            let sale_price = self.reduce(regular, sale.sale_price, sale.percentage_ppm)?;
+            if sale_price.amount() < price.amount() {
+                price = sale_price;
+            }
+        }
+        if price.amount() >= regular.amount() {
+            return Ok((regular, compare_at_price));
+        }
+        let compare_at_price = match compare_at_price {
+            Some(compare_at) if compare_at.amount() > regular.amount() => compare_at,
+            _ => regular,
+        };
+        Ok((price, Some(compare_at_price)))
+    }
+
+    /// The customer group's unit price when buying `quantity` units whose
+    /// regular price (in the display currency) is `regular`
+    pub fn group_price(
+        &self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        quantity: i32,
+        regular: Money,
+    ) -> Result<Money, MoneyError> {
+        match tier_for(self.group_rules(product_id, variant_id), quantity) {
+            Some(rule) => self.reduce(regular, rule.price, rule.percentage_ppm),
+            None => Ok(regular),
+        }
+    }
+
+    /// The customer group's price table for a product or variant, without
+    /// sales; empty when the group has no prices for it
+    pub fn price_tiers(
+        &self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        price: Money,
+    ) -> Result<Vec<PriceTier>, MoneyError> {
+        let rules = self.group_rules(product_id, variant_id);
+        let Some(first) = rules.first() else {
+            return Ok(vec![]);
+        };
+        let (regular, _) = self.regular_price(product_id, variant_id, price, None)?;
+        // Below the first rule's quantity the regular price applies
+        let starts: Vec<i32> = (first.min_quantity > 1)
+            .then_some(1)
+            .into_iter()
+            .chain(rules.iter().map(|rule| rule.min_quantity))
+            .collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(index, &min_quantity)| {
+                Ok(PriceTier {
+                    min_quantity,
+                    max_quantity: starts.get(index + 1).map(|next| next - 1),
+                    price: self.group_price(product_id, variant_id, min_quantity, regular)?,
+                })
+            })
+            .collect()
+    }
+
+    /// `price` lowered to a fixed store-currency price or by a percentage;
+    /// never raised
+    fn reduce(
+        &self,
+        price: Money,
+        fixed: Option<i64>,
+        percentage_ppm: Option<i32>,
+    ) -> Result<Money, MoneyError> {
+        let reduced = match (fixed, percentage_ppm) {
+            (Some(amount), _) => self.price(Money::new(amount, self.base))?,
+            (None, Some(ppm)) => price.checked_sub(price.mul_ratio(
+                i64::from(ppm),
+                1_000_000,
+                RoundingMode::HalfUp,
+            )?)?,
+            (None, None) => price,
+        };
+        Ok(if reduced.amount() < price.amount() {
+            reduced
+        } else {
+            price
+        })
+    }
+
+    /// Price and compare-at price without sales or group prices: the fixed
+    /// prices from the price list if there are any, converted prices
+    /// otherwise
+    pub fn regular_price(
+        &self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        price: Money,
+        compare_at_price: Option<Money>,
+    ) -> Result<(Money, Option<Money>), MoneyError> {
+        if let Some((price, compare_at_price)) = self.fixed.get(&(product_id, variant_id)) {
+            return Ok((
+                Money::new(*price, self.currency),
+                compare_at_price.map(|amount| Money::new(amount, self.currency)),
+            ));
+        }
+        Ok((
+            self.price(price)?,
+            compare_at_price
+                .map(|amount| self.price(amount))
+                .transpose()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eur(amount: i64) -> Money {
+        Money::new(amount, Currency::EUR)
+    }
+
+    #[test]
+    fn test_price_rounding() {
+        let charm = PriceRounding {
+            mode: RoundingMode::Up,
+            increment: 100,
+            ending: 99,
+        };
+        assert_eq!(charm.apply(eur(1234)).unwrap(), eur(1299));
+        assert_eq!(charm.apply(eur(1299)).unwrap(), eur(1299));
+        assert_eq!(charm.apply(eur(1300)).unwrap(), eur(1399));
+        assert_eq!(charm.apply(eur(40)).unwrap(), eur(99));
+        assert_eq!(charm.apply(eur(0)).unwrap(), eur(0));
+
+        let nearest = PriceRounding {
+            mode: RoundingMode::HalfUp,
+            increment: 100,
+            ending: 99,
+        };
+        assert_eq!(nearest.apply(eur(1234)).unwrap(), eur(1199));
+        assert_eq!(nearest.apply(eur(1260)).unwrap(), eur(1299));
+
+        let whole = PriceRounding {
+            mode: RoundingMode::HalfUp,
+            increment: 100,
+            ending: 0,
+        };
+        assert_eq!(whole.apply(eur(1250)).unwrap(), eur(1300));
+        assert_eq!(whole.apply(eur(20)).unwrap(), eur(100));
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate_ppm("0.921345"), Some(921_345));
+        assert_eq!(parse_rate_ppm("151.23"), Some(151_230_000));
+        assert_eq!(parse_rate_ppm(" 1 "), Some(1_000_000));
+        assert_eq!(parse_rate_ppm(".5"), Some(500_000));
+        assert_eq!(parse_rate_ppm("0.0066125"), Some(6_613));
+        assert_eq!(parse_rate_ppm("0"), None);
+        assert_eq!(parse_rate_ppm("-1.5"), None);
+        assert_eq!(parse_rate_ppm("1,5"), None);
+        assert_eq!(parse_rate_ppm("."), None);
+    }
+
+    #[test]
+    fn test_display_pricing() {
+        let now = OffsetDateTime::now_utc();
+        let store_id = Uuid::now_v7();
+        let product_id = Uuid::now_v7();
+        let variant_id = Uuid::now_v7();
+        let currency = StoreCurrency {
+            store_id,
+            currency: Currency::EUR,
+            rounding_mode: RoundingMode::Up,
+            rounding_increment: 100,
+            price_ending: Some(99),
+            created_at: now,
+            updated_at: now,
+        };
+        let rate = ExchangeRate {
+            store_id,
+            currency: Currency::EUR,
+            rate_ppm: 920_000,
+            source: ExchangeRateSource::Manual,
+            created_at: now,
+            updated_at: now,
+        };
+        let fixed = PriceListEntry {
+            id: Uuid::now_v7(),
+            store_id,
+            currency: Currency::EUR,
+            product_id,
+            variant_id: Some(variant_id),
+            price: 1500,
+            compare_at_price: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let pricing = DisplayPricing::converted(Currency::USD, &currency, &rate, vec![fixed]);
+        let usd = |amount| Money::new(amount, Currency::USD);
+
+        // 19.99 USD is 18.39 EUR, shown as 18.99
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, None, usd(1999), Some(usd(2500)))
+                .unwrap(),
+            (eur(1899), Some(eur(2399)))
+        );
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, Some(variant_id), usd(1999), Some(usd(2500)))
+                .unwrap(),
+            (eur(1500), None)
+        );
+        // Plain conversion for amounts that are not price tags
+        assert_eq!(pricing.convert(usd(250)).unwrap(), eur(230));
+        assert!(pricing.convert(eur(250)).is_err());
+
+        let store = DisplayPricing::store(Currency::USD);
+        assert_eq!(store.price(usd(1999)).unwrap(), usd(1999));
+    }
+}
@@ -0,0 +1,174 @@
+//! Customer groups and their prices.
+//!
+//! Customers are put in a group (retail, wholesale, VIP, ...) and each group
+//! can have its own price rules per product or variant: a fixed price or a
+//! percentage off the regular price, from a minimum quantity up. A single
+//! rule from 1 unit is a group price; several make quantity breaks. A
+//! variant's own rules replace the product's, and quantities count every
+//! unit of the product or variant in the cart.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::money::Money;
+
+/// A group customers can be put in, e.g. wholesale
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CustomerGroup {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// What customers and promotion conditions refer to, e.g. "wholesale"
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A group's price for a product or variant from a quantity up
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GroupPrice {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub customer_group_id: Uuid,
+    pub product_id: Uuid,
+    /// None for the product and all its variants
+    pub variant_id: Option<Uuid>,
+    pub min_quantity: i32,
+    /// Fixed unit price in minor units of the store currency
+    pub price: Option<i64>,
+    /// Taken off the regular price when there is no fixed price
+    /// (10% = 100000)
+    pub percentage_ppm: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A row of a product's price table: the unit price when buying between
+/// `min_quantity` and `max_quantity` units
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PriceTier {
+    pub min_quantity: i32,
+    /// None for no upper bound
+    pub max_quantity: Option<i32>,
+    pub price: Money,
+}
+
+/// The rule that applies when buying `quantity` units, given rules sorted
+/// by minimum quantity
+pub fn tier_for(rules: &[GroupPrice], quantity: i32) -> Option<&GroupPrice> {
+    rules
+        .iter()
+        .take_while(|rule| rule.min_quantity <= quantity)
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::currency::DisplayPricing;
+    use crate::money::Currency;
+
+    fn rule(
+        product_id: Uuid,
+        min_quantity: i32,
+        price: Option<i64>,
+        ppm: Option<i32>,
+    ) -> GroupPrice {
+        let now = OffsetDateTime::now_utc();
+        GroupPrice {
+            id: Uuid::now_v7(),
+            store_id: Uuid::now_v7(),
+            customer_group_id: Uuid::now_v7(),
+            product_id,
+            variant_id: None,
+            min_quantity,
+            price,
+            percentage_ppm: ppm,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn usd(amount: i64) -> Money {
+        Money::new(amount, Currency::USD)
+    }
+
+    #[test]
+    fn test_quantity_breaks() {
+        let product_id = Uuid::now_v7();
+        let pricing = DisplayPricing::store(Currency::USD).with_group_prices(vec![
+            rule(product_id, 50, None, Some(200_000)),
+            rule(product_id, 10, Some(450), None),
+        ]);
+
+        // 1-9 at the regular price, 10-49 at 4.50, 50+ at 20% off
+        assert_eq!(
+            pricing.price_tiers(product_id, None, usd(500)).unwrap(),
+            vec![
+                PriceTier {
+                    min_quantity: 1,
+                    max_quantity: Some(9),
+                    price: usd(500),
+                },
+                PriceTier {
+                    min_quantity: 10,
+                    max_quantity: Some(49),
+                    price: usd(450),
+                },
+                PriceTier {
+                    min_quantity: 50,
+                    max_quantity: None,
+                    price: usd(400),
+                },
+            ]
+        );
+        assert_eq!(
+            pricing
+                .quantity_price(product_id, None, 12, usd(500), None)
+                .unwrap(),
+            (usd(450), Some(usd(500)))
+        );
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, None, usd(500), None)
+                .unwrap(),
+            (usd(500), None)
+        );
+        // Other products and customers without a group pay regular prices
+        assert!(pricing
+            .price_tiers(Uuid::now_v7(), None, usd(500))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_group_price_never_raises() {
+        let product_id = Uuid::now_v7();
+        let variant_id = Uuid::now_v7();
+        let mut variant_rule = rule(product_id, 1, Some(900), None);
+        variant_rule.variant_id = Some(variant_id);
+        let pricing = DisplayPricing::store(Currency::USD)
+            .with_group_prices(vec![rule(product_id, 1, None, Some(100_000)), variant_rule]);
+
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, None, usd(1000), None)
+                .unwrap(),
+            (usd(900), Some(usd(1000)))
+        );
+        // The variant's own rule replaces the product's, but cannot raise
+        // the price above the regular one
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, Some(variant_id), usd(800), None)
+                .unwrap(),
+            (usd(800), None)
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::ApiError;
+use crate::money::Money;
+
+/// Kind of value a customer may enter for a line-item customization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomizationType {
+    Text,
+    Number,
+    Boolean,
+    Enum,
+    /// Calendar date as `YYYY-MM-DD`, e.g. a requested installation date
+    Date,
+    /// Free-form structured data, e.g. an irrigation zone layout
+    Json,
+}
+
+/// One selectable value of an `enum` customization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomizationChoice {
+    pub value: String,
+    #[serde(default)]
+    pub price_modifier: i64,
+}
+
+/// A per-line option a product accepts (engraving text, gift message, ...).
+///
+/// Defined under `customizations` in the product's attributes, or in the
+/// store's `product_schema` for options shared by every product:
+///
+/// ```json
+/// { "customizations": [
+///     { "key": "engraving", "type": "text", "max_length": 30, "price_modifier": 500 },
+///     { "key": "gift_wrap", "type": "boolean", "price_modifier": 300 }
+/// ] }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomizationOption {
+    pub key: String,
+    pub label: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: CustomizationType,
+    #[serde(default)]
+    pub required: bool,
+    pub max_length: Option<usize>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub options: Vec<CustomizationChoice>,
+    /// Added to the unit price when the customization is filled in
+    #[serde(default)]
+    pub price_modifier: i64,
+}
+
+/// Properties that passed validation, ready to be stored on a cart line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedProperties {
+    pub properties: Value,
+    /// Total amount added to the unit price by the chosen customizations
+    pub price_modifier: i64,
+}
+
+/// Read customization options from product attributes, falling back to the store schema
+pub fn customization_options(
+    product_attributes: &Value,
+    product_schema: Option<&Value>,
+) -> Result<Vec<CustomizationOption>, ApiError> {
+    let defined = product_attributes
+        .get("customizations")
+        .or_else(|| product_schema.and_then(|schema| schema.get("customizations")));
+
+    match defined {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| ApiError::internal(format!("Invalid customization definition: {}", e))),
+        None => Ok(vec![]),
+    }
+}
+
+/// Validate customer-supplied properties against the product's options.
+///
+/// Null values are treated as "not provided" and dropped.
+pub fn validate_properties(
+    options: &[CustomizationOption],
+    properties: &Value,
+) -> Result<ValidatedProperties, ApiError> {
+    let provided = match properties {
+        Value::Null => Map::new(),
+        Value::Object(map) => map.clone(),
+        _ => return Err(ApiError::validation("properties must be an object")),
+    };
+
+    let mut normalized = Map::new();
+    let mut price_modifier: i64 = 0;
+
+    for (key, value) in provided {
+        if value.is_null() {
+            continue;
+        }
+        let option = options
+            .iter()
+            .find(|o| o.key == key)
+            .ok_or_else(|| ApiError::validation(format!("Unknown customization: {}", key)))?;
+
+        let modifier = validate_value(option, &value)?;
+        price_modifier = price_modifier
+            .checked_add(modifier)
+            .ok_or_else(|| ApiError::validation("Customization price is too large"))?;
+        normalized.insert(key, value);
+    }
+
+    if let Some(missing) = options
+        .iter()
+        .find(|o| o.required && !normalized.contains_key(&o.key))
+    {
+        return Err(ApiError::validation(format!(
+            "Customization '{}' is required",
+            missing.label.as_deref().unwrap_or(&missing.key)
+        )));
+    }
+
+    Ok(ValidatedProperties {
+        properties: Value::Object(normalized),
+        price_modifier,
+    })
+}
+
+/// Check a single value and return the price modifier it contributes
+fn validate_value(option: &CustomizationOption, value: &Value) -> Result<i64, ApiError> {
+    let invalid =
+        |reason: &str| ApiError::validation(format!("Customization '{}' {}", option.key, reason));
+
+    match option.kind {
+        CustomizationType::Text => {
+            let text = value.as_str().ok_or_else(|| invalid("must be text"))?;
+            if let Some(max) = option.max_length {
+                if text.chars().count() > max {
+                    return Err(invalid(&format!("must be at most {} characters", max)));
+                }
+            }
+            Ok(if text.trim().is_empty() {
+                0
+            } else {
+                option.price_modifier
+            })
+        }
+        CustomizationType::Number => {
+            let number = value.as_f64().ok_or_else(|| invalid("must be a number"))?;
+            if option.min.is_some_and(|min| number < min)
+                || option.max.is_some_and(|max| number > max)
+            {
+                return Err(invalid("is out of range"));
+            }
+            Ok(option.price_modifier)
+        }
+        CustomizationType::Boolean => {
+            let checked = value
+                .as_bool()
+                .ok_or_else(|| invalid("must be true or false"))?;
+            Ok(if checked { option.price_modifier } else { 0 })
+        }
+        CustomizationType::Enum => {
+            let chosen = value.as_str().ok_or_else(|| invalid("must be text"))?;
+            let choice = option
+                .options
+                .iter()
+                .find(|c| c.value == chosen)
+                .ok_or_else(|| invalid("has an unsupported value"))?;
+            option
+                .price_modifier
+                .checked_add(choice.price_modifier)
+                .ok_or_else(|| invalid("has an invalid price"))
+        }
+        CustomizationType::Date => {
+            let text = value.as_str().ok_or_else(|| invalid("must be a date"))?;
+            parse_date(text).ok_or_else(|| invalid("must be a date (YYYY-MM-DD)"))?;
+            Ok(option.price_modifier)
+        }
+        CustomizationType::Json => {
+            if !(value.is_object() || value.is_array()) {
+                return Err(invalid("must be an object or array"));
+            }
+            if let Some(max) = option.max_length {
+                if value.to_string().len() > max {
+                    return Err(invalid(&format!("must be at most {} bytes", max)));
+                }
+            }
+            Ok(option.price_modifier)
+        }
+    }
+}
+
+/// Check that customizations (which may be discounts) leave a line's unit
+/// price at zero or more
+pub fn check_unit_price(product_name: &str, unit_price: Money) -> Result<(), ApiError> {
+    if unit_price.is_negative() {
+        return Err(ApiError::rule(
+            "customization_price_negative",
+            format!(
+                "The chosen customizations take the price of {} below zero",
+                product_name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Parse a `YYYY-MM-DD` date
+pub fn parse_date(text: &str) -> Option<time::Date> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month: u8 = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    time::Date::from_calendar_date(year, month.try_into().ok()?, day).ok()
+}
+
+/// Serialize JSON with object keys sorted, so equal properties always hash the same
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{}:{}", Value::String(k.clone()), canonical_json(&map[k])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn engraving_options() -> Vec<CustomizationOption> {
+        customization_options(
+            &json!({ "customizations": [
+                { "key": "engraving", "type": "text", "max_length": 10, "price_modifier": 500 },
+                { "key": "gift_wrap", "type": "boolean", "price_modifier": 300 },
+                { "key": "finish", "type": "enum", "required": true, "options": [
+                    { "value": "matte" },
+                    { "value": "gold", "price_modifier": 1000 }
+                ] }
+            ] }),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_properties_sums_modifiers() {
+        let validated = validate_properties(
+            &engraving_options(),
+            &json!({ "engraving": "Anna", "gift_wrap": true, "finish": "gold" }),
+        )
+        .unwrap();
+        assert_eq!(validated.price_modifier, 1800);
+
+        let validated = validate_properties(
+            &engraving_options(),
+            &json!({ "gift_wrap": false, "finish": "matte", "engraving": null }),
+        )
+        .unwrap();
+        assert_eq!(validated.price_modifier, 0);
+        assert_eq!(
+            validated.properties,
+            json!({ "gift_wrap": false, "finish": "matte" })
+        );
+    }
+
+    #[test]
+    fn test_validate_properties_rejects_invalid_input() {
+        let options = engraving_options();
+        assert!(validate_properties(&options, &json!({ "finish": "chrome" })).is_err());
+        assert!(validate_properties(&options, &json!({ "engraving": "Hi" })).is_err());
+        assert!(validate_properties(
+            &options,
+            &json!({ "finish": "matte", "engraving": "far too long text" })
+        )
+        .is_err());
+        assert!(
+            validate_properties(&options, &json!({ "finish": "matte", "color": "red" })).is_err()
+        );
+        assert!(validate_properties(&[], &json!({ "note": "hi" })).is_err());
+
+        let dates = customization_options(
+            &json!({ "customizations": [{ "key": "install_on", "type": "date" }] }),
+            None,
+        )
+        .unwrap();
+        assert!(validate_properties(&dates, &json!({ "install_on": "2026-04-30" })).is_ok());
+        assert!(validate_properties(&dates, &json!({ "install_on": "2026-02-30" })).is_err());
+        assert!(validate_properties(&[], &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_check_unit_price() {
+        use crate::money::Currency;
+
+        assert!(check_unit_price("Mug", Money::new(0, Currency::USD)).is_ok());
+        assert_eq!(
+            check_unit_price("Mug", Money::new(-200, Currency::USD))
+                .unwrap_err()
+                .code(),
+            "customization_price_negative"
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_sorts_keys() {
+        let a = json!({ "b": 1, "a": { "y": [1, 2], "x": "z" } });
+        let b = json!({ "a": { "x": "z", "y": [1, 2] }, "b": 1 });
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_eq!(canonical_json(&a), r#"{"a":{"x":"z","y":[1,2]},"b":1}"#);
+    }
+}
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::models::OrderAction;
+
+/// Where a parcel stands.
+///
+/// `pending → shipped → delivered`, or `pending → cancelled`. Only shipped
+/// parcels count towards the order's fulfillment status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum FulfillmentStatus {
+    /// Being packed; lines are reserved but nothing has left the warehouse
+    Pending,
+    Shipped,
+    Delivered,
+    Cancelled,
+}
+
+impl std::fmt::Display for FulfillmentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FulfillmentStatus::Pending => write!(f, "pending"),
+            FulfillmentStatus::Shipped => write!(f, "shipped"),
+            FulfillmentStatus::Delivered => write!(f, "delivered"),
+            FulfillmentStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl FulfillmentStatus {
+    /// Validate moving a fulfillment from this status to `next`
+    pub fn transition(self, next: FulfillmentStatus) -> Result<FulfillmentStatus, ApiError> {
+        use FulfillmentStatus as S;
+
+        match (self, next) {
+            (S::Pending, S::Shipped | S::Cancelled) | (S::Shipped, S::Delivered) => Ok(next),
+            _ => Err(ApiError::rule(
+                "invalid_fulfillment_transition",
+                format!("Cannot move a fulfillment that is {} to {}", self, next),
+            )),
+        }
+    }
+}
+
+/// Where a parcel stands according to the carrier, for parcels with a label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum TrackingStatus {
+    PreTransit,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    Exception,
+    ReturnedToSender,
+    Unknown,
+}
+
+impl std::fmt::Display for TrackingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrackingStatus::PreTransit => write!(f, "pre_transit"),
+            TrackingStatus::InTransit => write!(f, "in_transit"),
+            TrackingStatus::OutForDelivery => write!(f, "out_for_delivery"),
+            TrackingStatus::Delivered => write!(f, "delivered"),
+            TrackingStatus::Exception => write!(f, "exception"),
+            TrackingStatus::ReturnedToSender => write!(f, "returned_to_sender"),
+            TrackingStatus::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A shipping carrier configured for a store
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Carrier {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub code: String,
+    pub name: String,
+    /// Tracking page URL with a `{tracking_number}` placeholder
+    pub tracking_url_template: Option<String>,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl Carrier {
+    /// Tracking page for a parcel, if the carrier has a template
+    pub fn tracking_url(&self, tracking_number: &str) -> Option<String> {
+        self.tracking_url_template.as_deref().map(|template| {
+            template.replace(
+                "{tracking_number}",
+                &encode_tracking_number(tracking_number),
+            )
+        })
+    }
+}
+
+/// Percent-encode everything but unreserved URL characters
+fn encode_tracking_number(number: &str) -> String {
+    let mut encoded = String::with_capacity(number.len());
+    for byte in number.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// A parcel shipping some of an order's lines
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Fulfillment {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub status: FulfillmentStatus,
+    /// Carrier code, matching a configured carrier when there is one
+    pub carrier: Option<String>,
+    pub tracking_number: Option<String>,
+    pub tracking_url: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub shipped_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub delivered_at: Option<OffsetDateTime>,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    /// Last status polled from the carrier that sold the label
+    pub tracking_status: Option<TrackingStatus>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub tracking_checked_at: Option<OffsetDateTime>,
+}
+
+/// An order line (and how many of it) in a parcel
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FulfillmentItem {
+    pub id: Uuid,
+    pub fulfillment_id: Uuid,
+    pub order_item_id: Uuid,
+    pub quantity: i32,
+}
+
+/// File format of a shipping label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum LabelFormat {
+    Pdf,
+    Zpl,
+}
+
+impl std::fmt::Display for LabelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LabelFormat::Pdf => write!(f, "pdf"),
+            LabelFormat::Zpl => write!(f, "zpl"),
+        }
+    }
+}
+
+/// Where a label purchase stands.
+///
+/// A label is `pending` while the carrier is being called; a parcel has at
+/// most one pending or purchased label.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ShippingLabelStatus {
+    Pending,
+    Purchased,
+    Failed,
+    Voided,
+}
+
+impl std::fmt::Display for ShippingLabelStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShippingLabelStatus::Pending => write!(f, "pending"),
+            ShippingLabelStatus::Purchased => write!(f, "purchased"),
+            ShippingLabelStatus::Failed => write!(f, "failed"),
+            ShippingLabelStatus::Voided => write!(f, "voided"),
+        }
+    }
+}
+
+/// A label bought from a carrier adapter for a parcel
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShippingLabel {
+    pub id: Uuid,
+    pub fulfillment_id: Uuid,
+    /// Carrier adapter code
+    pub carrier: String,
+    pub service_code: String,
+    pub carrier_shipment_id: Option<String>,
+    pub tracking_number: Option<String>,
+    pub status: ShippingLabelStatus,
+    pub format: LabelFormat,
+    /// Storage key; labels are served through the API, never directly
+    #[serde(skip_serializing)]
+    pub storage_key: Option<String>,
+    pub amount: Option<i64>,
+    pub currency: Option<String>,
+    pub error: Option<String>,
+    pub created_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub voided_at: Option<OffsetDateTime>,
+}
+
+/// The order action implied by shipped quantities, given `(ordered, shipped)`
+/// per order line: nothing until something ships, then a partial fulfillment
+/// until every line has shipped in full.
+pub fn fulfillment_action(lines: &[(i32, i64)]) -> Option<OrderAction> {
+    let shipped_any = lines.iter().any(|&(_, shipped)| shipped > 0);
+    let shipped_all = lines
+        .iter()
+        .all(|&(ordered, shipped)| shipped >= i64::from(ordered));
+
+    match (shipped_any, shipped_all) {
+        (false, _) => None,
+        (true, true) => Some(OrderAction::Fulfill),
+        (true, false) => Some(OrderAction::PartiallyFulfill),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fulfillment_rollup_and_tracking_url() {
+        assert_eq!(fulfillment_action(&[(2, 0), (1, 0)]), None);
+        assert_eq!(
+            fulfillment_action(&[(2, 2), (1, 0)]),
+            Some(OrderAction::PartiallyFulfill)
+        );
+        assert_eq!(
+            fulfillment_action(&[(2, 1), (1, 1)]),
+            Some(OrderAction::PartiallyFulfill)
+        );
+        assert_eq!(
+            fulfillment_action(&[(2, 2), (1, 1)]),
+            Some(OrderAction::Fulfill)
+        );
+
+        let now = OffsetDateTime::now_utc();
+        let carrier = Carrier {
+            id: Uuid::nil(),
+            store_id: Uuid::nil(),
+            code: "ups".into(),
+            name: "UPS".into(),
+            tracking_url_template: Some(
+                "https://www.ups.com/track?tracknum={tracking_number}".into(),
+            ),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        assert_eq!(
+            carrier.tracking_url("1Z 999/AA1").as_deref(),
+            Some("https://www.ups.com/track?tracknum=1Z%20999%2FAA1")
+        );
+    }
+}
@@ -0,0 +1,283 @@
+//! Gift cards and their balance ledger.
+//!
+//! A card is a code with a balance in the store currency. Customers spend
+//! it at checkout, alone or next to a card payment, and whatever is left
+//! stays on the card for next time. Every change to the balance is a
+//! transaction, so the ledger always adds up to the card's balance.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::models::coupon::CODE_ALPHABET;
+use crate::money::{Currency, Money, MoneyError};
+
+/// How a gift card came to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum GiftCardSource {
+    /// Bought as a gift card product
+    Purchase,
+    /// Issued by staff
+    Admin,
+    /// Given for a return resolved with store credit
+    StoreCredit,
+}
+
+/// What a ledger entry did to a card's balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum GiftCardTransactionKind {
+    /// The card's initial value
+    Issue,
+    /// Spent on an order
+    Redeem,
+    /// Given back from a cancelled or refunded order, or by a partial refund
+    Release,
+    /// Changed by staff
+    Adjust,
+    /// Cleared when the card expired
+    Expire,
+}
+
+impl std::fmt::Display for GiftCardTransactionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GiftCardTransactionKind::Issue => write!(f, "issue"),
+            GiftCardTransactionKind::Redeem => write!(f, "redeem"),
+            GiftCardTransactionKind::Release => write!(f, "release"),
+            GiftCardTransactionKind::Adjust => write!(f, "adjust"),
+            GiftCardTransactionKind::Expire => write!(f, "expire"),
+        }
+    }
+}
+
+/// Whether a card can be spent, as shown by the balance check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GiftCardStatus {
+    Active,
+    /// Disabled by staff
+    Disabled,
+    Expired,
+    /// Nothing left on it
+    Spent,
+}
+
+/// A code with a balance, spent at checkout
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GiftCard {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub code: String,
+    pub currency: Currency,
+    /// Minor units of `currency`
+    pub initial_amount: i64,
+    pub balance: i64,
+    /// None for cards that never expire
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    pub is_active: bool,
+    pub source: GiftCardSource,
+    /// Order (and line) the card was bought with
+    pub order_id: Option<Uuid>,
+    pub order_item_id: Option<Uuid>,
+    /// Return the card was given for as store credit
+    pub return_id: Option<Uuid>,
+    /// Who the card was sent to
+    pub recipient_email: Option<String>,
+    pub note: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A change to a card's balance
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GiftCardTransaction {
+    pub id: Uuid,
+    pub gift_card_id: Uuid,
+    pub kind: GiftCardTransactionKind,
+    /// Signed, in minor units of the card currency: positive adds to the balance
+    pub amount: i64,
+    pub balance_after: i64,
+    /// Order the card was spent on or given back from
+    pub order_id: Option<Uuid>,
+    pub note: Option<String>,
+    /// Staff member behind an adjustment or issue
+    pub created_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl GiftCard {
+    pub fn balance(&self) -> Money {
+        Money::new(self.balance, self.currency)
+    }
+
+    pub fn status(&self, now: OffsetDateTime) -> GiftCardStatus {
+        if !self.is_active {
+            GiftCardStatus::Disabled
+        } else if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            GiftCardStatus::Expired
+        } else if self.balance <= 0 {
+            GiftCardStatus::Spent
+        } else {
+            GiftCardStatus::Active
+        }
+    }
+
+    /// Check the card can pay for an order in `currency` now
+    pub fn check_redeemable(
+        &self,
+        currency: Currency,
+        now: OffsetDateTime,
+    ) -> Result<(), ApiError> {
+        let (code, message) = match self.status(now) {
+            GiftCardStatus::Active if self.currency == currency => return Ok(()),
+            GiftCardStatus::Active => (
+                "gift_card_currency_mismatch",
+                format!(
+                    "Gift card {} is in {} but the order is in {}",
+                    self.code, self.currency, currency
+                ),
+            ),
+            GiftCardStatus::Disabled => (
+                "gift_card_disabled",
+                format!("Gift card {} has been disabled", self.code),
+            ),
+            GiftCardStatus::Expired => (
+                "gift_card_expired",
+                format!("Gift card {} has expired", self.code),
+            ),
+            GiftCardStatus::Spent => (
+                "gift_card_spent",
+                format!("Gift card {} has no balance left", self.code),
+            ),
+        };
+        Err(ApiError::rule(code, message))
+    }
+}
+
+/// What each card pays towards `due`, in card order: every card is spent
+/// in full until the rest fits on one, which keeps its remainder
+pub fn allocate(balances: &[Money], due: Money) -> Result<Vec<Money>, MoneyError> {
+    let mut left = due;
+    balances
+        .iter()
+        .map(|&balance| {
+            let taken = if balance.amount() < left.amount() {
+                balance
+            } else {
+                left
+            };
+            left = left.checked_sub(taken)?;
+            Ok(taken)
+        })
+        .collect()
+}
+
+/// A code as customers type it: surrounding spaces and case do not matter
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// A random card code like `7KQ2-MXW9-B4TD-PZ3H`
+pub fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    let groups: Vec<String> = (0..4)
+        .map(|_| {
+            (0..4)
+                .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+                .collect()
+        })
+        .collect();
+    groups.join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Duration;
+
+    fn usd(amount: i64) -> Money {
+        Money::new(amount, Currency::USD)
+    }
+
+    fn card(balance: i64) -> GiftCard {
+        let now = OffsetDateTime::now_utc();
+        GiftCard {
+            id: Uuid::now_v7(),
+            store_id: Uuid::now_v7(),
+            code: generate_code(),
+            currency: Currency::USD,
+            initial_amount: 5000,
+            balance,
+            expires_at: None,
+            is_active: true,
+            source: GiftCardSource::Admin,
+            order_id: None,
+            order_item_id: None,
+            return_id: None,
+            recipient_email: None,
+            note: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_allocate_spends_cards_in_order() {
+        // The first card is used up, the second covers the rest and keeps 10.00
+        assert_eq!(
+            allocate(&[usd(1500), usd(5000), usd(2000)], usd(5500)).unwrap(),
+            vec![usd(1500), usd(4000), usd(0)]
+        );
+        // Cards worth less than the total leave the rest to the payment
+        assert_eq!(
+            allocate(&[usd(1500), usd(1000)], usd(5500)).unwrap(),
+            vec![usd(1500), usd(1000)]
+        );
+    }
+
+    #[test]
+    fn test_redeemable_cards() {
+        let now = OffsetDateTime::now_utc();
+
+        assert!(card(100).check_redeemable(Currency::USD, now).is_ok());
+        assert_eq!(
+            card(100)
+                .check_redeemable(Currency::EUR, now)
+                .unwrap_err()
+                .code(),
+            "gift_card_currency_mismatch"
+        );
+        assert_eq!(card(0).status(now), GiftCardStatus::Spent);
+
+        let mut expired = card(100);
+        expired.expires_at = Some(now - Duration::days(1));
+        assert_eq!(expired.status(now), GiftCardStatus::Expired);
+
+        let mut disabled = expired.clone();
+        disabled.is_active = false;
+        assert_eq!(
+            disabled
+                .check_redeemable(Currency::USD, now)
+                .unwrap_err()
+                .code(),
+            "gift_card_disabled"
+        );
+    }
+
+    #[test]
+    fn test_generated_codes() {
+        let code = generate_code();
+        assert_eq!(code.len(), 19);
+        assert_eq!(normalize_code(&format!(" {} ", code.to_lowercase())), code);
+    }
+}
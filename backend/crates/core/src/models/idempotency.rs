@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum IdempotencyStatus {
+    /// The first request with this key is still running
+    InProgress,
+    Completed,
+}
+
+/// A stored response for a request sent with an `Idempotency-Key` header
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct IdempotencyRecord {
+    pub id: Uuid,
+    pub scope: String,
+    pub key: String,
+    pub fingerprint: String,
+    pub status: IdempotencyStatus,
+    pub response_status: Option<i16>,
+    /// Response headers as `[name, value]` pairs
+    pub response_headers: Option<serde_json::Value>,
+    pub response_body: Option<Vec<u8>>,
+    pub expires_at: OffsetDateTime,
+    /// When the request holding the key started running
+    pub locked_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
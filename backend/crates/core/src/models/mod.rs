@@ -1,11 +1,82 @@
+pub mod abandoned_cart;
 pub mod cart;
 pub mod category;
+pub mod checkout;
+pub mod coupon;
+pub mod currency;
+pub mod customer_group;
+pub mod customization;
+pub mod fulfillment;
+pub mod gift_card;
+pub mod idempotency;
+pub mod notification;
+pub mod order;
+pub mod payment;
 pub mod product;
+pub mod promotion;
+pub mod quantity_rules;
+pub mod refund;
+pub mod returns;
+pub mod sale;
+pub mod shipping;
 pub mod store;
+pub mod tax;
 pub mod user;
 
+pub use abandoned_cart::{AbandonedCart, AbandonedCartSettings, AbandonedCartStatus};
 pub use cart::{Cart, CartItem};
 pub use category::Category;
+pub use checkout::{
+    CheckoutField, CheckoutSession, CheckoutSessionStatus, CheckoutSettings, CheckoutStep,
+    CheckoutStepData,
+};
+pub use coupon::{
+    Coupon, CouponCode, CouponDiscount, CouponKind, CouponRedemption, DiscountableLine,
+};
+pub use currency::{
+    DisplayPricing, ExchangeRate, ExchangeRateSource, PriceListEntry, PriceRounding, StoreCurrency,
+};
+pub use customer_group::{CustomerGroup, GroupPrice, PriceTier};
+pub use customization::{
+    CustomizationChoice, CustomizationOption, CustomizationType, ValidatedProperties,
+};
+pub use fulfillment::{
+    Carrier, Fulfillment, FulfillmentItem, FulfillmentStatus, LabelFormat, ShippingLabel,
+    ShippingLabelStatus, TrackingStatus,
+};
+pub use gift_card::{
+    GiftCard, GiftCardSource, GiftCardStatus, GiftCardTransaction, GiftCardTransactionKind,
+};
+pub use idempotency::{IdempotencyRecord, IdempotencyStatus};
+pub use notification::{Notification, NotificationStatus};
+pub use order::{
+    Order, OrderAction, OrderActor, OrderEvent, OrderItem, OrderStatus, PaymentStatus,
+};
+pub use payment::{
+    Payment, PaymentAttempt, PaymentAttemptStatus, PaymentOperation, PaymentState,
+    PaymentWebhookEvent, WebhookEventStatus,
+};
 pub use product::{Product, ProductImage, ProductStatus, ProductVariant};
+pub use promotion::{
+    AppliedPromotion, CartContext, CartDiscounts, FreeItem, GiftProduct, Promotion,
+    PromotionAction, PromotionConditions, PromotionLine, SpendTier,
+};
+pub use quantity_rules::QuantityRules;
+pub use refund::{Refund, RefundItem, RefundStatus};
+pub use returns::{
+    ItemCondition, Return, ReturnItem, ReturnPhoto, ReturnReason, ReturnResolution, ReturnStatus,
+};
+pub use sale::{
+    price_lines, LinePart, LinePrice, LineToPrice, PricedLines, Sale, SaleItem, SalePrice,
+    SaleSummary, SaleUnits,
+};
+pub use shipping::{
+    Destination, PackageDimensions, Parcel, RateTier, ShippingMethod, ShippingMethodKind,
+    ShippingZone,
+};
 pub use store::{Store, StoreConfig};
+pub use tax::{
+    LineTax, TaxBreakdown, TaxClass, TaxExemption, TaxExemptionStatus, TaxLine, TaxRate,
+    TaxRounding, TaxSettings, TaxableLine,
+};
 pub use user::{User, UserRole};
@@ -0,0 +1,430 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::types::Json;
+use sqlx::{FromRow, Row, Type};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::promotion::AppliedPromotion;
+use super::tax::{TaxClass, TaxLine};
+use crate::error::ApiError;
+use crate::money::{Currency, Money, MoneyError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum OrderStatus {
+    Pending,
+    Paid,
+    /// Some, but not all, lines have shipped
+    PartiallyFulfilled,
+    Fulfilled,
+    Completed,
+    Cancelled,
+    Refunded,
+}
+
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderStatus::Pending => write!(f, "pending"),
+            OrderStatus::Paid => write!(f, "paid"),
+            OrderStatus::PartiallyFulfilled => write!(f, "partially_fulfilled"),
+            OrderStatus::Fulfilled => write!(f, "fulfilled"),
+            OrderStatus::Completed => write!(f, "completed"),
+            OrderStatus::Cancelled => write!(f, "cancelled"),
+            OrderStatus::Refunded => write!(f, "refunded"),
+        }
+    }
+}
+
+/// Something that moves an order from one status to another.
+///
+/// The lifecycle is `pending → paid → (partially_fulfilled →) fulfilled →
+/// completed`, with `cancelled` reachable before fulfillment and `refunded`
+/// after payment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum OrderAction {
+    /// The order was created; only ever recorded, never applied
+    Place,
+    Pay,
+    /// Shipments cover some of the order; derived from fulfillments
+    PartiallyFulfill,
+    Fulfill,
+    Complete,
+    Cancel,
+    Refund,
+}
+
+impl std::fmt::Display for OrderAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderAction::Place => write!(f, "place"),
+            OrderAction::Pay => write!(f, "pay"),
+            OrderAction::PartiallyFulfill => write!(f, "partially_fulfill"),
+            OrderAction::Fulfill => write!(f, "fulfill"),
+            OrderAction::Complete => write!(f, "complete"),
+            OrderAction::Cancel => write!(f, "cancel"),
+            OrderAction::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+/// Who triggered an order status change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum OrderActor {
+    Customer,
+    Admin,
+    /// Background jobs and payment provider webhooks
+    System,
+}
+
+impl std::fmt::Display for OrderActor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderActor::Customer => write!(f, "customer"),
+            OrderActor::Admin => write!(f, "admin"),
+            OrderActor::System => write!(f, "system"),
+        }
+    }
+}
+
+impl OrderAction {
+    /// Actions that can be applied to an order (everything except `Place`)
+    pub const TRANSITIONS: [OrderAction; 6] = [
+        OrderAction::Pay,
+        OrderAction::PartiallyFulfill,
+        OrderAction::Fulfill,
+        OrderAction::Complete,
+        OrderAction::Cancel,
+        OrderAction::Refund,
+    ];
+
+    /// Whether `actor` may trigger this action at all.
+    ///
+    /// Customers can only cancel, and only while the order is unpaid (see
+    /// [`OrderStatus::allowed_actions`]). Partial fulfillment follows from
+    /// shipped fulfillments, so only the system applies it.
+    pub fn permitted_for(self, actor: OrderActor) -> bool {
+        match actor {
+            OrderActor::System => self != OrderAction::Place,
+            OrderActor::Admin => {
+                !matches!(self, OrderAction::Place | OrderAction::PartiallyFulfill)
+            }
+            OrderActor::Customer => self == OrderAction::Cancel,
+        }
+    }
+}
+
+impl OrderStatus {
+    /// Status reached by applying `action`, or None if the transition is not allowed
+    pub fn next(self, action: OrderAction) -> Option<OrderStatus> {
+        use OrderAction as A;
+        use OrderStatus as S;
+
+        match (self, action) {
+            (S::Pending, A::Pay) => Some(S::Paid),
+            (S::Pending | S::Paid, A::Cancel) => Some(S::Cancelled),
+            (S::Paid, A::PartiallyFulfill) => Some(S::PartiallyFulfilled),
+            (S::Paid | S::PartiallyFulfilled, A::Fulfill) => Some(S::Fulfilled),
+            (S::Fulfilled, A::Complete) => Some(S::Completed),
+            (S::Paid | S::PartiallyFulfilled | S::Fulfilled | S::Completed, A::Refund) => {
+                Some(S::Refunded)
+            }
+            _ => None,
+        }
+    }
+
+    /// Validate a transition requested by `actor`
+    pub fn transition(
+        self,
+        action: OrderAction,
+        actor: OrderActor,
+    ) -> Result<OrderStatus, ApiError> {
+        match self.next(action) {
+            Some(next) if self.allowed_actions(actor).contains(&action) => Ok(next),
+            _ => Err(ApiError::rule(
+                "invalid_order_transition",
+                format!("Cannot {} an order that is {}", action, self),
+            )),
+        }
+    }
+
+    /// Next actions `actor` may take from this status
+    pub fn allowed_actions(self, actor: OrderActor) -> Vec<OrderAction> {
+        OrderAction::TRANSITIONS
+            .into_iter()
+            .filter(|action| action.permitted_for(actor) && self.next(*action).is_some())
+            // A paid order is cancelled through a refund by staff, not by the customer
+            .filter(|_| actor != OrderActor::Customer || self == OrderStatus::Pending)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum PaymentStatus {
+    Unpaid,
+    /// A payment is authorized but not yet captured
+    Authorized,
+    Paid,
+    /// Some of the captured amount went back to the customer
+    PartiallyRefunded,
+    Refunded,
+}
+
+impl std::fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentStatus::Unpaid => write!(f, "unpaid"),
+            PaymentStatus::Authorized => write!(f, "authorized"),
+            PaymentStatus::Paid => write!(f, "paid"),
+            PaymentStatus::PartiallyRefunded => write!(f, "partially_refunded"),
+            PaymentStatus::Refunded => write!(f, "refunded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub cart_id: Option<Uuid>,
+    pub order_number: i64,
+    pub status: OrderStatus,
+    pub payment_status: PaymentStatus,
+    pub email: String,
+    /// Store currency at the time of purchase; all amounts are in it
+    pub currency: Currency,
+    /// Sum of the line prices, before discounts
+    pub subtotal: Money,
+    /// Taken off the lines by promotions and a coupon; shipping waived by a
+    /// free shipping coupon is not included, it shows as a zero
+    /// `shipping_total`
+    pub discount_total: Money,
+    /// Coupon the order was placed with
+    pub coupon_code: Option<String>,
+    /// Automatic promotions applied, as explained to the customer
+    pub promotions: Vec<AppliedPromotion>,
+    /// Shipping charged, included in `total`
+    pub shipping_total: Money,
+    pub total: Money,
+    /// Part of `total` paid with gift cards; the rest is due from the payment
+    pub gift_card_total: Money,
+    /// Whether line and shipping amounts already contain their tax; if not,
+    /// `tax_total` was added to `total`
+    pub prices_include_tax: bool,
+    pub tax_total: Money,
+    /// Part of `tax_total` charged on shipping
+    pub shipping_tax: Money,
+    /// Tax per rate, shipping included
+    pub tax_lines: Vec<TaxLine>,
+    /// Exemption the customer bought under, if any
+    pub tax_exemption_id: Option<Uuid>,
+    pub item_count: i32,
+    pub shipping_address: Option<serde_json::Value>,
+    pub billing_address: Option<serde_json::Value>,
+    pub notes: Option<String>,
+    pub shipping_method: Option<String>,
+    /// Values of the store's custom checkout fields (e.g. installation date)
+    pub custom_fields: serde_json::Value,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl<'r> FromRow<'r, PgRow> for Order {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        let money = |column: &str| Money::from_row(row, column, currency);
+        Ok(Self {
+            id: row.try_get("id")?,
+            store_id: row.try_get("store_id")?,
+            user_id: row.try_get("user_id")?,
+            cart_id: row.try_get("cart_id")?,
+            order_number: row.try_get("order_number")?,
+            status: row.try_get("status")?,
+            payment_status: row.try_get("payment_status")?,
+            email: row.try_get("email")?,
+            currency,
+            subtotal: money("subtotal")?,
+            discount_total: money("discount_total")?,
+            coupon_code: row.try_get("coupon_code")?,
+            promotions: row
+                .try_get::<Json<Vec<AppliedPromotion>>, _>("promotions")?
+                .0,
+            shipping_total: money("shipping_total")?,
+            total: money("total")?,
+            gift_card_total: money("gift_card_total")?,
+            prices_include_tax: row.try_get("prices_include_tax")?,
+            tax_total: money("tax_total")?,
+            shipping_tax: money("shipping_tax")?,
+            tax_lines: row.try_get::<Json<Vec<TaxLine>>, _>("tax_lines")?.0,
+            tax_exemption_id: row.try_get("tax_exemption_id")?,
+            item_count: row.try_get("item_count")?,
+            shipping_address: row.try_get("shipping_address")?,
+            billing_address: row.try_get("billing_address")?,
+            notes: row.try_get("notes")?,
+            shipping_method: row.try_get("shipping_method")?,
+            custom_fields: row.try_get("custom_fields")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// A purchased line, snapshotted from the catalog when the order was placed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderItem {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    /// None once the product has been deleted from the catalog
+    pub product_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+    pub product_name: String,
+    pub variant_name: Option<String>,
+    pub sku: Option<String>,
+    pub properties: serde_json::Value,
+    /// Unit price including customization price modifiers
+    pub unit_price: Money,
+    pub price_modifier: Money,
+    pub quantity: i32,
+    /// Charged for the line: unit price times quantity, less `discount_total`
+    pub total: Money,
+    /// Taken off the line by promotions and a coupon; for a free gift, its
+    /// whole price (gifts are not part of the order subtotal)
+    pub discount_total: Money,
+    pub tax_class: TaxClass,
+    pub tax_total: Money,
+    pub tax_lines: Vec<TaxLine>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl<'r> FromRow<'r, PgRow> for OrderItem {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        let money = |column: &str| Money::from_row(row, column, currency);
+        Ok(Self {
+            id: row.try_get("id")?,
+            order_id: row.try_get("order_id")?,
+            product_id: row.try_get("product_id")?,
+            variant_id: row.try_get("variant_id")?,
+            product_name: row.try_get("product_name")?,
+            variant_name: row.try_get("variant_name")?,
+            sku: row.try_get("sku")?,
+            properties: row.try_get("properties")?,
+            unit_price: money("unit_price")?,
+            price_modifier: money("price_modifier")?,
+            quantity: row.try_get("quantity")?,
+            total: money("total")?,
+            discount_total: money("discount_total")?,
+            tax_class: row.try_get("tax_class")?,
+            tax_total: money("tax_total")?,
+            tax_lines: row.try_get::<Json<Vec<TaxLine>>, _>("tax_lines")?.0,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+impl Order {
+    /// What is left to pay once gift cards are taken off the total
+    pub fn amount_due(&self) -> Result<Money, MoneyError> {
+        self.total.checked_sub(self.gift_card_total)
+    }
+
+    /// Shipping the customer paid, with its tax
+    pub fn shipping_charged(&self) -> Result<Money, MoneyError> {
+        if self.prices_include_tax {
+            Ok(self.shipping_total)
+        } else {
+            self.shipping_total.checked_add(self.shipping_tax)
+        }
+    }
+}
+
+impl OrderItem {
+    /// What the customer paid for the line, with its tax
+    pub fn charged(&self, prices_include_tax: bool) -> Result<Money, MoneyError> {
+        if prices_include_tax {
+            Ok(self.total)
+        } else {
+            self.total.checked_add(self.tax_total)
+        }
+    }
+}
+
+/// One entry in an order's status history
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderEvent {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    /// None for the event recording order placement
+    pub from_status: Option<OrderStatus>,
+    pub to_status: OrderStatus,
+    pub action: OrderAction,
+    pub actor: OrderActor,
+    pub actor_id: Option<Uuid>,
+    pub reason: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_lifecycle_transitions() {
+        let admin = OrderActor::Admin;
+        let status = OrderStatus::Pending;
+
+        let status = status.transition(OrderAction::Pay, admin).unwrap();
+        assert_eq!(status, OrderStatus::Paid);
+        let status = status.transition(OrderAction::Fulfill, admin).unwrap();
+        assert_eq!(status, OrderStatus::Fulfilled);
+        assert!(status.transition(OrderAction::Cancel, admin).is_err());
+        let status = status.transition(OrderAction::Complete, admin).unwrap();
+        assert_eq!(status, OrderStatus::Completed);
+        assert_eq!(status.allowed_actions(admin), vec![OrderAction::Refund]);
+
+        let refunded = status.transition(OrderAction::Refund, admin).unwrap();
+        assert!(refunded.allowed_actions(admin).is_empty());
+        assert_eq!(
+            refunded
+                .transition(OrderAction::Pay, OrderActor::System)
+                .unwrap_err()
+                .code(),
+            "invalid_order_transition"
+        );
+    }
+
+    #[test]
+    fn test_customer_can_only_cancel_unpaid_orders() {
+        let customer = OrderActor::Customer;
+
+        assert_eq!(
+            OrderStatus::Pending.allowed_actions(customer),
+            vec![OrderAction::Cancel]
+        );
+        assert!(OrderStatus::Paid.allowed_actions(customer).is_empty());
+        assert!(OrderStatus::Pending
+            .transition(OrderAction::Pay, customer)
+            .is_err());
+        assert_eq!(
+            OrderStatus::Paid.allowed_actions(OrderActor::Admin),
+            vec![
+                OrderAction::Fulfill,
+                OrderAction::Cancel,
+                OrderAction::Refund
+            ]
+        );
+    }
+}
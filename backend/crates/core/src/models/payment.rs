@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Row, Type};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::order::Order;
+use crate::error::ApiError;
+use crate::money::{Currency, Money};
+
+/// Where a payment stands, mirrored from the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum PaymentState {
+    /// Created locally; the provider has not been called yet
+    Pending,
+    /// Waiting for the customer to confirm (3-D Secure, client-side confirmation)
+    RequiresAction,
+    /// Funds are held and can be captured or voided
+    Authorized,
+    Captured,
+    Voided,
+    Failed,
+}
+
+impl PaymentState {
+    /// Whether the payment still holds or has collected money
+    pub fn is_live(self) -> bool {
+        matches!(
+            self,
+            PaymentState::Pending
+                | PaymentState::RequiresAction
+                | PaymentState::Authorized
+                | PaymentState::Captured
+        )
+    }
+
+    /// Whether a provider update may move a payment from this state to `next`.
+    ///
+    /// Webhooks can arrive late or out of order, so a payment never moves
+    /// backwards (a delayed "authorized" event must not undo a capture). A
+    /// failed payment can still succeed when the customer retries it.
+    pub fn can_become(self, next: PaymentState) -> bool {
+        use PaymentState as S;
+
+        match (self, next) {
+            (S::Pending, _) => true,
+            (S::RequiresAction, S::Pending) => false,
+            (S::RequiresAction, _) => true,
+            (S::Authorized, S::Captured | S::Voided | S::Failed) => true,
+            (S::Failed, S::RequiresAction | S::Authorized | S::Captured) => true,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for PaymentState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentState::Pending => write!(f, "pending"),
+            PaymentState::RequiresAction => write!(f, "requires_action"),
+            PaymentState::Authorized => write!(f, "authorized"),
+            PaymentState::Captured => write!(f, "captured"),
+            PaymentState::Voided => write!(f, "voided"),
+            PaymentState::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A call made to the payment provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum PaymentOperation {
+    Authorize,
+    Capture,
+    Void,
+    Refund,
+}
+
+impl std::fmt::Display for PaymentOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaymentOperation::Authorize => write!(f, "authorize"),
+            PaymentOperation::Capture => write!(f, "capture"),
+            PaymentOperation::Void => write!(f, "void"),
+            PaymentOperation::Refund => write!(f, "refund"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum PaymentAttemptStatus {
+    /// Sent to the provider, no answer recorded yet
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// Money collected (or being collected) for an order through a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub provider: String,
+    /// Provider's ID for the payment, set once the provider has been called
+    pub provider_payment_id: Option<String>,
+    pub status: PaymentState,
+    pub currency: Currency,
+    /// The order total at the time of payment
+    pub amount: Money,
+    pub amount_captured: Money,
+    /// Part of the captured amount returned by succeeded refunds
+    pub amount_refunded: Money,
+    pub failure_reason: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl<'r> FromRow<'r, PgRow> for Payment {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        let money = |column: &str| Money::from_row(row, column, currency);
+        Ok(Self {
+            id: row.try_get("id")?,
+            order_id: row.try_get("order_id")?,
+            provider: row.try_get("provider")?,
+            provider_payment_id: row.try_get("provider_payment_id")?,
+            status: row.try_get("status")?,
+            currency,
+            amount: money("amount")?,
+            amount_captured: money("amount_captured")?,
+            amount_refunded: money("amount_refunded")?,
+            failure_reason: row.try_get("failure_reason")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// One call to the provider for a payment; its ID doubles as the provider
+/// idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PaymentAttempt {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+    pub operation: PaymentOperation,
+    pub amount: i64,
+    pub status: PaymentAttemptStatus,
+    pub provider_reference: Option<String>,
+    pub error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum WebhookEventStatus {
+    /// Waiting to be applied (or retried)
+    Pending,
+    Processed,
+    /// Verified but not relevant (unknown event type, someone else's payment)
+    Ignored,
+    /// Gave up after repeated failures
+    Failed,
+}
+
+/// A verified payment provider webhook, stored as received
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PaymentWebhookEvent {
+    pub id: Uuid,
+    pub provider: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub status: WebhookEventStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub process_after: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub processed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub received_at: OffsetDateTime,
+}
+
+/// Check an amount reported by the payment provider against the order.
+///
+/// The order is the only source of truth for what is owed (its total, less
+/// what gift cards paid): a payment is never trusted for a different amount
+/// or currency.
+pub fn reconcile(order: &Order, amount: Money) -> Result<(), ApiError> {
+    if amount.currency() != order.currency {
+        return Err(ApiError::rule(
+            "payment_amount_mismatch",
+            format!(
+                "Payment is in {} but order {} is in {}",
+                amount.currency(),
+                order.order_number,
+                order.currency
+            ),
+        ));
+    }
+    let due = order.amount_due()?;
+    if amount != due {
+        return Err(ApiError::rule(
+            "payment_amount_mismatch",
+            format!(
+                "Payment of {} does not match the {} due on order {}",
+                amount, due, order.order_number
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OrderStatus, PaymentStatus};
+
+    fn order(total: i64) -> Order {
+        let usd = |amount| Money::new(amount, Currency::USD);
+        Order {
+            id: Uuid::nil(),
+            store_id: Uuid::nil(),
+            user_id: None,
+            cart_id: None,
+            order_number: 1001,
+            status: OrderStatus::Pending,
+            payment_status: PaymentStatus::Unpaid,
+            email: "a@example.com".to_string(),
+            currency: Currency::USD,
+            subtotal: usd(total),
+            discount_total: usd(0),
+            coupon_code: None,
+            promotions: vec![],
+            shipping_total: usd(0),
+            total: usd(total),
+            gift_card_total: usd(0),
+            prices_include_tax: false,
+            tax_total: usd(0),
+            shipping_tax: usd(0),
+            tax_lines: vec![],
+            tax_exemption_id: None,
+            item_count: 1,
+            shipping_address: None,
+            billing_address: None,
+            notes: None,
+            shipping_method: None,
+            custom_fields: serde_json::json!({}),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            updated_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_payment_state_never_moves_backwards() {
+        use PaymentState as S;
+
+        assert!(S::RequiresAction.can_become(S::Authorized));
+        assert!(S::Authorized.can_become(S::Captured));
+        assert!(!S::Captured.can_become(S::Authorized));
+        assert!(!S::Voided.can_become(S::Captured));
+        assert!(S::Failed.can_become(S::Captured));
+    }
+
+    #[test]
+    fn test_reconcile_against_order_total() {
+        let order = order(4500);
+        let usd = |amount| Money::new(amount, Currency::USD);
+
+        assert!(reconcile(&order, usd(4500)).is_ok());
+        assert_eq!(
+            reconcile(&order, usd(4400)).unwrap_err().code(),
+            "payment_amount_mismatch"
+        );
+        assert_eq!(
+            reconcile(&order, Money::new(4500, Currency::EUR))
+                .unwrap_err()
+                .code(),
+            "payment_amount_mismatch"
+        );
+
+        // Gift cards paid part of it; the payment covers the rest
+        let mut order = order;
+        order.gift_card_total = usd(1500);
+        assert!(reconcile(&order, usd(3000)).is_ok());
+        assert!(reconcile(&order, usd(4500)).is_err());
+    }
+}
@@ -1,8 +1,14 @@
 use serde::{Deserialize, Serialize};
-use sqlx::Type;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Row, Type};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use super::quantity_rules::QuantityRules;
+use super::shipping::PackageDimensions;
+use super::tax::TaxClass;
+use crate::money::{Currency, Money};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
 #[serde(rename_all = "snake_case")]
 #[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
@@ -22,7 +28,8 @@ impl std::fmt::Display for ProductStatus {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+/// A catalog product; rows carry the store's `currency` alongside
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
     pub id: Uuid,
     pub store_id: Uuid,
@@ -31,14 +38,28 @@ pub struct Product {
     pub slug: String,
     pub description: Option<String>,
     pub short_description: Option<String>,
-    pub price: i32,
-    pub compare_at_price: Option<i32>,
-    pub cost_price: Option<i32>,
+    /// In the store currency, like all catalog amounts
+    pub price: Money,
+    pub compare_at_price: Option<Money>,
+    pub cost_price: Option<Money>,
     pub sku: Option<String>,
     pub stock_quantity: i32,
     pub attributes: serde_json::Value,
     pub status: ProductStatus,
     pub is_featured: bool,
+    pub min_quantity: Option<i32>,
+    pub max_quantity: Option<i32>,
+    pub quantity_step: Option<i32>,
+    pub max_per_customer: Option<i32>,
+    pub weight_grams: Option<i32>,
+    pub length_mm: Option<i32>,
+    pub width_mm: Option<i32>,
+    pub height_mm: Option<i32>,
+    pub tax_class: TaxClass,
+    /// Each unit bought issues a gift card worth its price
+    pub is_gift_card: bool,
+    /// Days a purchased gift card stays valid; None for no expiry
+    pub gift_card_validity_days: Option<i32>,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
@@ -57,20 +78,133 @@ pub struct ProductImage {
     pub created_at: OffsetDateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+/// A variant of a product; rows carry the store's `currency` alongside
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProductVariant {
     pub id: Uuid,
     pub product_id: Uuid,
     pub name: String,
     pub sku: Option<String>,
-    pub price: i32,
-    pub compare_at_price: Option<i32>,
+    pub price: Money,
+    pub compare_at_price: Option<Money>,
     pub stock_quantity: i32,
     pub attributes: serde_json::Value,
     pub sort_order: i32,
     pub is_active: bool,
+    pub min_quantity: Option<i32>,
+    pub max_quantity: Option<i32>,
+    pub quantity_step: Option<i32>,
+    pub max_per_customer: Option<i32>,
+    pub weight_grams: Option<i32>,
+    pub length_mm: Option<i32>,
+    pub width_mm: Option<i32>,
+    pub height_mm: Option<i32>,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
     #[serde(with = "time::serde::rfc3339")]
     pub updated_at: OffsetDateTime,
 }
+
+impl<'r> FromRow<'r, PgRow> for Product {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            store_id: row.try_get("store_id")?,
+            category_id: row.try_get("category_id")?,
+            name: row.try_get("name")?,
+            slug: row.try_get("slug")?,
+            description: row.try_get("description")?,
+            short_description: row.try_get("short_description")?,
+            price: Money::from_row(row, "price", currency)?,
+            compare_at_price: Money::from_row_optional(row, "compare_at_price", currency)?,
+            cost_price: Money::from_row_optional(row, "cost_price", currency)?,
+            sku: row.try_get("sku")?,
+            stock_quantity: row.try_get("stock_quantity")?,
+            attributes: row.try_get("attributes")?,
+            status: row.try_get("status")?,
+            is_featured: row.try_get("is_featured")?,
+            min_quantity: row.try_get("min_quantity")?,
+            max_quantity: row.try_get("max_quantity")?,
+            quantity_step: row.try_get("quantity_step")?,
+            max_per_customer: row.try_get("max_per_customer")?,
+            weight_grams: row.try_get("weight_grams")?,
+            length_mm: row.try_get("length_mm")?,
+            width_mm: row.try_get("width_mm")?,
+            height_mm: row.try_get("height_mm")?,
+            tax_class: row.try_get("tax_class")?,
+            is_gift_card: row.try_get("is_gift_card")?,
+            gift_card_validity_days: row.try_get("gift_card_validity_days")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+impl<'r> FromRow<'r, PgRow> for ProductVariant {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        Ok(Self {
+            id: row.try_get("id")?,
+            product_id: row.try_get("product_id")?,
+            name: row.try_get("name")?,
+            sku: row.try_get("sku")?,
+            price: Money::from_row(row, "price", currency)?,
+            compare_at_price: Money::from_row_optional(row, "compare_at_price", currency)?,
+            stock_quantity: row.try_get("stock_quantity")?,
+            attributes: row.try_get("attributes")?,
+            sort_order: row.try_get("sort_order")?,
+            is_active: row.try_get("is_active")?,
+            min_quantity: row.try_get("min_quantity")?,
+            max_quantity: row.try_get("max_quantity")?,
+            quantity_step: row.try_get("quantity_step")?,
+            max_per_customer: row.try_get("max_per_customer")?,
+            weight_grams: row.try_get("weight_grams")?,
+            length_mm: row.try_get("length_mm")?,
+            width_mm: row.try_get("width_mm")?,
+            height_mm: row.try_get("height_mm")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+impl Product {
+    pub fn quantity_rules(&self) -> QuantityRules {
+        QuantityRules {
+            min_quantity: self.min_quantity,
+            max_quantity: self.max_quantity,
+            quantity_step: self.quantity_step,
+            max_per_customer: self.max_per_customer,
+        }
+    }
+
+    pub fn dimensions(&self) -> PackageDimensions {
+        PackageDimensions {
+            weight_grams: self.weight_grams,
+            length_mm: self.length_mm,
+            width_mm: self.width_mm,
+            height_mm: self.height_mm,
+        }
+    }
+}
+
+impl ProductVariant {
+    pub fn quantity_rules(&self) -> QuantityRules {
+        QuantityRules {
+            min_quantity: self.min_quantity,
+            max_quantity: self.max_quantity,
+            quantity_step: self.quantity_step,
+            max_per_customer: self.max_per_customer,
+        }
+    }
+
+    pub fn dimensions(&self) -> PackageDimensions {
+        PackageDimensions {
+            weight_grams: self.weight_grams,
+            length_mm: self.length_mm,
+            width_mm: self.width_mm,
+            height_mm: self.height_mm,
+        }
+    }
+}
@@ -0,0 +1,1018 @@
+//! Automatic promotions.
+//!
+//! A promotion applies without a code when the cart meets its conditions
+//! (products or categories in the cart, how much of them, the customer's
+//! group, its dates) and then either takes money off lines or adds a free
+//! item. Several promotions can apply to one cart:
+//!
+//! - They run from the highest `priority` down (oldest first on ties), each
+//!   on what is left of the lines after the ones before it, so conditions
+//!   and discounts look at discounted amounts and a line never goes below
+//!   zero.
+//! - An `exclusive` promotion applies alone: it is skipped once another
+//!   promotion has applied, and none runs after it.
+//! - While a coupon applies to the cart, promotions that do not
+//!   `combines_with_coupons` are left out. The coupon runs last, on what the
+//!   promotions left.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::models::coupon::{Coupon, CouponDiscount, DiscountableLine};
+use crate::models::currency::DisplayPricing;
+use crate::money::{Money, MoneyError, RoundingMode};
+
+/// A whole line off
+const FULL_PPM: i32 = 1_000_000;
+
+fn full_ppm() -> i32 {
+    FULL_PPM
+}
+
+fn one() -> i32 {
+    1
+}
+
+/// `10%`, `12.5%`
+pub fn format_percentage(ppm: i32) -> String {
+    let whole = ppm / 10_000;
+    let fraction = ppm % 10_000;
+    if fraction == 0 {
+        return format!("{}%", whole);
+    }
+    let decimals = format!("{:04}", fraction);
+    format!("{}.{}%", whole, decimals.trim_end_matches('0'))
+}
+
+/// What a cart must contain, and who must be buying, for a promotion to
+/// apply; every condition given has to hold
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromotionConditions {
+    /// Lines the promotion looks at and discounts; both empty for the
+    /// whole cart
+    #[serde(default)]
+    pub product_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub category_ids: Vec<Uuid>,
+    /// Least subtotal of those lines, in minor units of the store currency
+    pub min_subtotal: Option<i64>,
+    /// Least number of units of those lines
+    pub min_quantity: Option<i32>,
+    /// Customer groups the promotion is for; empty for everyone, guests
+    /// included
+    #[serde(default)]
+    pub customer_groups: Vec<String>,
+}
+
+impl PromotionConditions {
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.min_subtotal.is_some_and(|min| min < 0) {
+            return Err(ApiError::validation("min_subtotal must not be negative"));
+        }
+        if self.min_quantity.is_some_and(|min| min < 1) {
+            return Err(ApiError::validation("min_quantity must be at least 1"));
+        }
+        if self
+            .customer_groups
+            .iter()
+            .any(|group| group.trim().is_empty())
+        {
+            return Err(ApiError::validation("customer_groups must not be blank"));
+        }
+        Ok(())
+    }
+
+    /// Whether the promotion looks at a line
+    pub fn targets(&self, line: &PromotionLine) -> bool {
+        self.targets_everything()
+            || self.product_ids.contains(&line.product_id)
+            || line
+                .category_id
+                .is_some_and(|category_id| self.category_ids.contains(&category_id))
+    }
+
+    fn targets_everything(&self) -> bool {
+        self.product_ids.is_empty() && self.category_ids.is_empty()
+    }
+
+    fn admits(&self, customer_group: Option<&str>) -> bool {
+        self.customer_groups.is_empty()
+            || customer_group.is_some_and(|group| {
+                self.customer_groups
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(group))
+            })
+    }
+}
+
+/// One step of a spend tiers promotion: spending `min_subtotal` or more
+/// takes a percentage or an amount off
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendTier {
+    /// Minor units of the store currency
+    pub min_subtotal: i64,
+    pub percentage_ppm: Option<i32>,
+    /// Minor units of the store currency
+    pub amount: Option<i64>,
+}
+
+/// What a promotion does once its conditions hold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PromotionAction {
+    /// A percentage off the targeted lines (10% = 100000)
+    PercentOff { percentage_ppm: i32 },
+    /// An amount off the targeted lines, spread over them by value
+    AmountOff { amount: i64 },
+    /// For every `buy` + `get` units of the targeted lines, the `get`
+    /// cheapest are free (or `percentage_ppm` off)
+    BuyXGetY {
+        buy: i32,
+        get: i32,
+        #[serde(default = "full_ppm")]
+        percentage_ppm: i32,
+    },
+    /// The highest tier the targeted lines reach applies
+    SpendTiers { tiers: Vec<SpendTier> },
+    /// A product added to the order for free, while stock lasts
+    FreeGift {
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        #[serde(default = "one")]
+        quantity: i32,
+    },
+}
+
+impl PromotionAction {
+    /// The product and variant a free gift promotion hands out
+    pub fn gift(&self) -> Option<(Uuid, Option<Uuid>)> {
+        match self {
+            PromotionAction::FreeGift {
+                product_id,
+                variant_id,
+                ..
+            } => Some((*product_id, *variant_id)),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let percentage = |ppm: i32| {
+            if (1..=FULL_PPM).contains(&ppm) {
+                Ok(())
+            } else {
+                Err(ApiError::validation(
+                    "percentage_ppm must be between 1 and 1000000",
+                ))
+            }
+        };
+        match self {
+            PromotionAction::PercentOff { percentage_ppm } => percentage(*percentage_ppm),
+            PromotionAction::AmountOff { amount } if *amount < 1 => {
+                Err(ApiError::validation("amount must be at least 1"))
+            }
+            PromotionAction::AmountOff { .. } => Ok(()),
+            PromotionAction::BuyXGetY { buy, get, .. } if *buy < 1 || *get < 1 => {
+                Err(ApiError::validation("buy and get must be at least 1"))
+            }
+            PromotionAction::BuyXGetY { percentage_ppm, .. } => percentage(*percentage_ppm),
+            PromotionAction::SpendTiers { tiers } => {
+                if tiers.is_empty() || tiers.len() > 20 {
+                    return Err(ApiError::validation("Give between 1 and 20 tiers"));
+                }
+                for tier in tiers {
+                    if tier.min_subtotal < 0 {
+                        return Err(ApiError::validation(
+                            "Tier min_subtotal must not be negative",
+                        ));
+                    }
+                    match (tier.percentage_ppm, tier.amount) {
+                        (Some(ppm), None) => percentage(ppm)?,
+                        (None, Some(amount)) if amount >= 1 => {}
+                        _ => {
+                            return Err(ApiError::validation(
+                                "Each tier needs either percentage_ppm or a positive amount",
+                            ))
+                        }
+                    }
+                }
+                Ok(())
+            }
+            PromotionAction::FreeGift { quantity, .. } if *quantity < 1 => {
+                Err(ApiError::validation("quantity must be at least 1"))
+            }
+            PromotionAction::FreeGift { .. } => Ok(()),
+        }
+    }
+}
+
+/// A discount that applies by itself when a cart qualifies
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Promotion {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// Shown to customers with the discount, e.g. "Summer sale"
+    pub name: String,
+    /// Higher runs first
+    pub priority: i32,
+    /// Applies alone, never together with another promotion
+    pub exclusive: bool,
+    /// Still applies when the customer uses a coupon
+    pub combines_with_coupons: bool,
+    #[sqlx(json)]
+    pub conditions: PromotionConditions,
+    #[sqlx(json)]
+    pub action: PromotionAction,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A cart line as promotions see it
+#[derive(Debug, Clone)]
+pub struct PromotionLine {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    /// Product name, used in explanations
+    pub name: String,
+    pub quantity: i32,
+    /// Line subtotal before discounts
+    pub amount: Money,
+}
+
+/// A product a free gift promotion hands out, priced for display
+#[derive(Debug, Clone)]
+pub struct GiftProduct {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub name: String,
+    pub price: Money,
+    /// Units that can be given away: stock less what the cart itself holds
+    pub available: i32,
+}
+
+/// What promotions look at besides the promotions themselves
+#[derive(Debug, Clone)]
+pub struct CartContext<'a> {
+    pub lines: &'a [PromotionLine],
+    pub customer_group: Option<&'a str>,
+    pub gifts: &'a [GiftProduct],
+    pub now: OffsetDateTime,
+}
+
+/// A gift a promotion adds to the cart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreeItem {
+    pub promotion_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub name: String,
+    pub quantity: i32,
+    /// What the gift would have cost
+    pub value: Money,
+}
+
+/// A promotion applied to a cart, explained for the customer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedPromotion {
+    pub promotion_id: Uuid,
+    pub name: String,
+    /// E.g. "Buy 2, get 1 free: 1 × Tube free"
+    pub explanation: String,
+    /// Taken off the lines; zero for free gifts
+    pub discount_total: Money,
+}
+
+/// Everything taken off a cart
+#[derive(Debug)]
+pub struct CartDiscounts {
+    /// Per line, in line order: promotions and coupon together
+    pub lines: Vec<Money>,
+    /// Taken off the lines by promotions alone
+    pub promotion_total: Money,
+    pub promotions: Vec<AppliedPromotion>,
+    pub free_items: Vec<FreeItem>,
+    /// The coupon's part, or why it does not apply; None without a coupon
+    pub coupon: Option<Result<CouponDiscount, ApiError>>,
+}
+
+impl CartDiscounts {
+    /// Taken off the lines by promotions and coupon together
+    pub fn total(&self) -> Result<Money, MoneyError> {
+        match &self.coupon {
+            Some(Ok(coupon)) => self.promotion_total.checked_add(coupon.total),
+            _ => Ok(self.promotion_total),
+        }
+    }
+}
+
+/// What one promotion does to a cart
+struct PromotionEffect {
+    lines: Vec<Money>,
+    explanation: String,
+    free_items: Vec<FreeItem>,
+}
+
+impl Promotion {
+    /// Whether the promotion runs at `now`
+    pub fn is_live(&self, now: OffsetDateTime) -> bool {
+        self.is_active
+            && self.starts_at.is_none_or(|starts_at| now >= starts_at)
+            && self.ends_at.is_none_or(|ends_at| now < ends_at)
+    }
+
+    /// The effect on a cart whose lines have `remaining` left to pay, or
+    /// None if the cart does not qualify. `given` are the gifts promotions
+    /// that ran before handed out.
+    fn evaluate(
+        &self,
+        ctx: &CartContext,
+        remaining: &[Money],
+        given: &[FreeItem],
+        pricing: &DisplayPricing,
+    ) -> Result<Option<PromotionEffect>, ApiError> {
+        let currency = pricing.currency();
+        let store_amount = |amount: i64| pricing.convert(Money::new(amount, pricing.base()));
+        let conditions = &self.conditions;
+        if !conditions.admits(ctx.customer_group) {
+            return Ok(None);
+        }
+
+        let targeted: Vec<bool> = ctx.lines.iter().map(|l| conditions.targets(l)).collect();
+        let weights: Vec<i64> = remaining
+            .iter()
+            .zip(&targeted)
+            .map(|(amount, targeted)| if *targeted { amount.amount() } else { 0 })
+            .collect();
+        let subtotal = Money::new(weights.iter().sum(), currency);
+        let quantity: i64 = ctx
+            .lines
+            .iter()
+            .zip(&targeted)
+            .filter(|(_, targeted)| **targeted)
+            .map(|(line, _)| i64::from(line.quantity))
+            .sum();
+        if quantity == 0 {
+            return Ok(None);
+        }
+        let min_subtotal = conditions.min_subtotal.map(store_amount).transpose()?;
+        if min_subtotal.is_some_and(|min| subtotal.amount() < min.amount()) {
+            return Ok(None);
+        }
+        if conditions
+            .min_quantity
+            .is_some_and(|min| quantity < i64::from(min))
+        {
+            return Ok(None);
+        }
+
+        // "your order" or the names of the lines the discount is on
+        let scope = if conditions.targets_everything() {
+            "your order".to_string()
+        } else {
+            let mut names: Vec<&str> = Vec::new();
+            for (line, _) in ctx.lines.iter().zip(&targeted).filter(|(_, t)| **t) {
+                if !names.contains(&line.name.as_str()) {
+                    names.push(&line.name);
+                }
+            }
+            names.join(", ")
+        };
+        let threshold = match min_subtotal {
+            Some(min) if min.amount() > 0 => format!(" when you spend {} or more", min),
+            _ => String::new(),
+        };
+        let spread = |total: Money| -> Result<Vec<Money>, ApiError> {
+            if subtotal.is_zero() {
+                return Ok(vec![Money::zero(currency); remaining.len()]);
+            }
+            Ok(total.allocate(&weights)?)
+        };
+        let capped = |amount: Money| {
+            if amount.amount() > subtotal.amount() {
+                subtotal
+            } else {
+                amount
+            }
+        };
+
+        let effect = match &self.action {
+            PromotionAction::PercentOff { percentage_ppm } => {
+                let total = subtotal.mul_ratio(
+                    i64::from(*percentage_ppm),
+                    1_000_000,
+                    RoundingMode::HalfUp,
+                )?;
+                PromotionEffect {
+                    lines: spread(total)?,
+                    explanation: format!(
+                        "{} off {}{}",
+                        format_percentage(*percentage_ppm),
+                        scope,
+                        threshold
+                    ),
+                    free_items: vec![],
+                }
+            }
+            PromotionAction::AmountOff { amount } => {
+                let amount = store_amount(*amount)?;
+                PromotionEffect {
+                    lines: spread(capped(amount))?,
+                    explanation: format!("{} off {}{}", amount, scope, threshold),
+                    free_items: vec![],
+                }
+            }
+            PromotionAction::BuyXGetY {
+                buy,
+                get,
+                percentage_ppm,
+            } => {
+                let free = quantity / i64::from(buy + get) * i64::from(*get);
+                if free == 0 {
+                    return Ok(None);
+                }
+                // The cheapest units go first
+                let mut order: Vec<usize> = (0..ctx.lines.len()).filter(|i| targeted[*i]).collect();
+                order.sort_by_key(|i| {
+                    ctx.lines[*i].amount.amount() / i64::from(ctx.lines[*i].quantity.max(1))
+                });
+                let mut lines = vec![Money::zero(currency); remaining.len()];
+                let mut left = free;
+                let mut freed: Vec<String> = Vec::new();
+                for i in order {
+                    if left == 0 {
+                        break;
+                    }
+                    let line = &ctx.lines[i];
+                    let units = left.min(i64::from(line.quantity));
+                    left -= units;
+                    let unit_price = line.amount.amount() / i64::from(line.quantity.max(1));
+                    let off = Money::new(unit_price, currency)
+                        .checked_mul(units)?
+                        .mul_ratio(i64::from(*percentage_ppm), 1_000_000, RoundingMode::HalfUp)?;
+                    lines[i] = if off.amount() > remaining[i].amount() {
+                        remaining[i]
+                    } else {
+                        off
+                    };
+                    freed.push(format!("{} × {}", units, line.name));
+                }
+                let deal = if *percentage_ppm == FULL_PPM {
+                    "free".to_string()
+                } else {
+                    format!("{} off", format_percentage(*percentage_ppm))
+                };
+                PromotionEffect {
+                    lines,
+                    explanation: format!(
+                        "Buy {}, get {} {}: {} {}",
+                        buy,
+                        get,
+                        deal,
+                        freed.join(", "),
+                        deal
+                    ),
+                    free_items: vec![],
+                }
+            }
+            PromotionAction::SpendTiers { tiers } => {
+                let mut best: Option<(Money, &SpendTier)> = None;
+                for tier in tiers {
+                    let min = store_amount(tier.min_subtotal)?;
+                    if subtotal.amount() >= min.amount()
+                        && best.is_none_or(|(best_min, _)| min.amount() > best_min.amount())
+                    {
+                        best = Some((min, tier));
+                    }
+                }
+                let Some((min, tier)) = best else {
+                    return Ok(None);
+                };
+                let (total, deal) = match (tier.percentage_ppm, tier.amount) {
+                    (Some(ppm), _) => (
+                        subtotal.mul_ratio(i64::from(ppm), 1_000_000, RoundingMode::HalfUp)?,
+                        format_percentage(ppm),
+                    ),
+                    (None, amount) => {
+                        let amount = store_amount(amount.unwrap_or(0))?;
+                        (capped(amount), amount.to_string())
+                    }
+                };
+                PromotionEffect {
+                    lines: spread(total)?,
+                    explanation: format!("Spend {} or more: {} off {}", min, deal, scope),
+                    free_items: vec![],
+                }
+            }
+            PromotionAction::FreeGift {
+                product_id,
+                variant_id,
+                quantity,
+            } => {
+                let Some(gift) = ctx
+                    .gifts
+                    .iter()
+                    .find(|gift| gift.product_id == *product_id && gift.variant_id == *variant_id)
+                else {
+                    return Ok(None);
+                };
+                // Gifts already handed out come out of the same stock
+                let already_given: i64 = given
+                    .iter()
+                    .filter(|item| {
+                        item.product_id == gift.product_id && item.variant_id == gift.variant_id
+                    })
+                    .map(|item| i64::from(item.quantity))
+                    .sum();
+                if i64::from(gift.available) - already_given < i64::from(*quantity) {
+                    return Ok(None);
+                }
+                PromotionEffect {
+                    lines: vec![Money::zero(currency); remaining.len()],
+                    explanation: format!("Free gift{}: {} × {}", threshold, quantity, gift.name),
+                    free_items: vec![FreeItem {
+                        promotion_id: self.id,
+                        product_id: gift.product_id,
+                        variant_id: gift.variant_id,
+                        name: gift.name.clone(),
+                        quantity: *quantity,
+                        value: gift.price.checked_mul(i64::from(*quantity))?,
+                    }],
+                }
+            }
+        };
+
+        if effect.free_items.is_empty() && effect.lines.iter().all(Money::is_zero) {
+            return Ok(None);
+        }
+        Ok(Some(effect))
+    }
+}
+
+struct PromotionOutcome {
+    lines: Vec<Money>,
+    total: Money,
+    applied: Vec<AppliedPromotion>,
+    free_items: Vec<FreeItem>,
+}
+
+/// Run the live promotions over a cart in priority order
+fn apply_promotions(
+    promotions: &[Promotion],
+    with_coupon: bool,
+    ctx: &CartContext,
+    pricing: &DisplayPricing,
+) -> Result<PromotionOutcome, ApiError> {
+    let currency = pricing.currency();
+    let mut running: Vec<&Promotion> = promotions
+        .iter()
+        .filter(|p| p.is_live(ctx.now) && (!with_coupon || p.combines_with_coupons))
+        .collect();
+    running.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.created_at.cmp(&b.created_at))
+    });
+
+    let mut outcome = PromotionOutcome {
+        lines: vec![Money::zero(currency); ctx.lines.len()],
+        total: Money::zero(currency),
+        applied: vec![],
+        free_items: vec![],
+    };
+    for promotion in running {
+        if promotion.exclusive && !outcome.applied.is_empty() {
+            continue;
+        }
+        let remaining = ctx
+            .lines
+            .iter()
+            .zip(&outcome.lines)
+            .map(|(line, discount)| line.amount.checked_sub(*discount))
+            .collect::<Result<Vec<Money>, _>>()?;
+        let Some(effect) = promotion.evaluate(ctx, &remaining, &outcome.free_items, pricing)?
+        else {
+            continue;
+        };
+
+        let discount_total = Money::sum(currency, effect.lines.iter().copied())?;
+        outcome.total = outcome.total.checked_add(discount_total)?;
+        for (line, discount) in outcome.lines.iter_mut().zip(&effect.lines) {
+            *line = line.checked_add(*discount)?;
+        }
+        outcome.applied.push(AppliedPromotion {
+            promotion_id: promotion.id,
+            name: promotion.name.clone(),
+            explanation: effect.explanation,
+            discount_total,
+        });
+        outcome.free_items.extend(effect.free_items);
+        if promotion.exclusive {
+            break;
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Everything taken off a cart: the promotions that apply, then the
+/// coupon, if one was entered and can be used. A coupon that does not fit
+/// the cart is reported in `coupon` and does not keep promotions out.
+pub fn discount_cart(
+    promotions: &[Promotion],
+    coupon: Option<&Coupon>,
+    ctx: &CartContext,
+    pricing: &DisplayPricing,
+) -> Result<CartDiscounts, ApiError> {
+    let Some(coupon) = coupon else {
+        let outcome = apply_promotions(promotions, false, ctx, pricing)?;
+        return Ok(CartDiscounts {
+            lines: outcome.lines,
+            promotion_total: outcome.total,
+            promotions: outcome.applied,
+            free_items: outcome.free_items,
+            coupon: None,
+        });
+    };
+
+    let outcome = apply_promotions(promotions, true, ctx, pricing)?;
+    let remaining = ctx
+        .lines
+        .iter()
+        .zip(&outcome.lines)
+        .map(|(line, discount)| {
+            Ok(DiscountableLine {
+                product_id: line.product_id,
+                category_id: line.category_id,
+                amount: line.amount.checked_sub(*discount)?,
+            })
+        })
+        .collect::<Result<Vec<DiscountableLine>, MoneyError>>()?;
+    match coupon.discount(&remaining, pricing) {
+        Ok(discount) => {
+            let lines = outcome
+                .lines
+                .iter()
+                .zip(&discount.lines)
+                .map(|(promotions, coupon)| promotions.checked_add(*coupon))
+                .collect::<Result<Vec<Money>, _>>()?;
+            Ok(CartDiscounts {
+                lines,
+                promotion_total: outcome.total,
+                promotions: outcome.applied,
+                free_items: outcome.free_items,
+                coupon: Some(Ok(discount)),
+            })
+        }
+        Err(e @ ApiError::Rule(..)) => {
+            let outcome = apply_promotions(promotions, false, ctx, pricing)?;
+            Ok(CartDiscounts {
+                lines: outcome.lines,
+                promotion_total: outcome.total,
+                promotions: outcome.applied,
+                free_items: outcome.free_items,
+                coupon: Some(Err(e)),
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CouponKind;
+    use crate::money::Currency;
+
+    fn promotion(name: &str, action: PromotionAction) -> Promotion {
+        let now = OffsetDateTime::now_utc();
+        Promotion {
+            id: Uuid::now_v7(),
+            store_id: Uuid::now_v7(),
+            name: name.into(),
+            priority: 0,
+            exclusive: false,
+            combines_with_coupons: true,
+            conditions: PromotionConditions::default(),
+            action,
+            starts_at: None,
+            ends_at: None,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn line(name: &str, quantity: i32, unit_price: i64) -> PromotionLine {
+        PromotionLine {
+            product_id: Uuid::now_v7(),
+            variant_id: None,
+            category_id: None,
+            name: name.into(),
+            quantity,
+            amount: Money::new(unit_price * i64::from(quantity), Currency::USD),
+        }
+    }
+
+    fn context<'a>(lines: &'a [PromotionLine], gifts: &'a [GiftProduct]) -> CartContext<'a> {
+        CartContext {
+            lines,
+            customer_group: None,
+            gifts,
+            now: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn amounts(lines: &[Money]) -> Vec<i64> {
+        lines.iter().map(Money::amount).collect()
+    }
+
+    #[test]
+    fn test_format_percentage() {
+        assert_eq!(format_percentage(100_000), "10%");
+        assert_eq!(format_percentage(125_000), "12.5%");
+        assert_eq!(format_percentage(1_000_000), "100%");
+    }
+
+    #[test]
+    fn test_buy_x_get_y_frees_cheapest_units() {
+        let lines = [line("Game", 1, 4500), line("Tube", 2, 500)];
+        let promo = promotion(
+            "3 for 2",
+            PromotionAction::BuyXGetY {
+                buy: 2,
+                get: 1,
+                percentage_ppm: FULL_PPM,
+            },
+        );
+        let pricing = DisplayPricing::store(Currency::USD);
+        let result = discount_cart(&[promo], None, &context(&lines, &[]), &pricing).unwrap();
+
+        assert_eq!(amounts(&result.lines), vec![0, 500]);
+        assert_eq!(result.promotion_total.amount(), 500);
+        assert_eq!(
+            result.promotions[0].explanation,
+            "Buy 2, get 1 free: 1 × Tube free"
+        );
+    }
+
+    #[test]
+    fn test_category_threshold_and_customer_group() {
+        let category_id = Uuid::now_v7();
+        let mut game = line("Game", 3, 4500);
+        game.category_id = Some(category_id);
+        let lines = [game, line("Tube", 2, 500)];
+        let mut promo = promotion(
+            "Games week",
+            PromotionAction::PercentOff {
+                percentage_ppm: 100_000,
+            },
+        );
+        promo.conditions.category_ids = vec![category_id];
+        promo.conditions.min_subtotal = Some(10_000);
+        let pricing = DisplayPricing::store(Currency::USD);
+
+        let result =
+            discount_cart(&[promo.clone()], None, &context(&lines, &[]), &pricing).unwrap();
+        assert_eq!(amounts(&result.lines), vec![1350, 0]);
+        assert_eq!(
+            result.promotions[0].explanation,
+            "10% off Game when you spend 100.00 USD or more"
+        );
+
+        promo.conditions.customer_groups = vec!["wholesale".into()];
+        let result =
+            discount_cart(&[promo.clone()], None, &context(&lines, &[]), &pricing).unwrap();
+        assert!(result.promotions.is_empty());
+        let mut ctx = context(&lines, &[]);
+        ctx.customer_group = Some("Wholesale");
+        let result = discount_cart(&[promo], None, &ctx, &pricing).unwrap();
+        assert_eq!(result.promotion_total.amount(), 1350);
+    }
+
+    #[test]
+    fn test_spend_tiers_pick_highest_reached() {
+        let lines = [line("Game", 3, 4500)];
+        let promo = promotion(
+            "Spend more, save more",
+            PromotionAction::SpendTiers {
+                tiers: vec![
+                    SpendTier {
+                        min_subtotal: 5000,
+                        percentage_ppm: None,
+                        amount: Some(500),
+                    },
+                    SpendTier {
+                        min_subtotal: 10_000,
+                        percentage_ppm: None,
+                        amount: Some(1500),
+                    },
+                    SpendTier {
+                        min_subtotal: 20_000,
+                        percentage_ppm: None,
+                        amount: Some(5000),
+                    },
+                ],
+            },
+        );
+        let pricing = DisplayPricing::store(Currency::USD);
+        let result = discount_cart(&[promo], None, &context(&lines, &[]), &pricing).unwrap();
+        assert_eq!(result.promotion_total.amount(), 1500);
+        assert_eq!(
+            result.promotions[0].explanation,
+            "Spend 100.00 USD or more: 15.00 USD off your order"
+        );
+    }
+
+    #[test]
+    fn test_priority_exclusive_and_coupon_stacking() {
+        let lines = [line("Game", 2, 5000)];
+        let pricing = DisplayPricing::store(Currency::USD);
+        let mut first = promotion("Ten off", PromotionAction::AmountOff { amount: 1000 });
+        first.priority = 10;
+        let second = promotion(
+            "Half price",
+            PromotionAction::PercentOff {
+                percentage_ppm: 500_000,
+            },
+        );
+        let result = discount_cart(
+            &[second.clone(), first.clone()],
+            None,
+            &context(&lines, &[]),
+            &pricing,
+        )
+        .unwrap();
+        // 10.00 off first, then half of the 90.00 left
+        assert_eq!(result.promotion_total.amount(), 1000 + 4500);
+
+        let mut exclusive = second.clone();
+        exclusive.exclusive = true;
+        let result = discount_cart(
+            &[exclusive, first.clone()],
+            None,
+            &context(&lines, &[]),
+            &pricing,
+        )
+        .unwrap();
+        assert_eq!(result.promotions.len(), 1);
+        assert_eq!(result.promotion_total.amount(), 1000);
+
+        // A coupon keeps out promotions that do not combine with coupons,
+        // and runs on what the others left
+        let now = OffsetDateTime::now_utc();
+        let coupon = Coupon {
+            id: Uuid::now_v7(),
+            store_id: Uuid::now_v7(),
+            code: Some("SAVE".into()),
+            description: None,
+            kind: CouponKind::Percentage,
+            percentage_ppm: Some(100_000),
+            amount: None,
+            min_subtotal: None,
+            product_ids: vec![],
+            category_ids: vec![],
+            starts_at: None,
+            ends_at: None,
+            usage_limit: None,
+            usage_limit_per_customer: None,
+            times_used: 0,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let mut solo = second;
+        solo.combines_with_coupons = false;
+        let result = discount_cart(
+            &[first.clone(), solo.clone()],
+            Some(&coupon),
+            &context(&lines, &[]),
+            &pricing,
+        )
+        .unwrap();
+        assert_eq!(result.promotions.len(), 1);
+        assert_eq!(
+            result
+                .coupon
+                .as_ref()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .total
+                .amount(),
+            900
+        );
+        assert_eq!(result.total().unwrap().amount(), 1900);
+
+        // A coupon that does not fit the cart does not keep them out
+        let mut out_of_reach = coupon;
+        out_of_reach.min_subtotal = Some(50_000);
+        let result = discount_cart(
+            &[first, solo],
+            Some(&out_of_reach),
+            &context(&lines, &[]),
+            &pricing,
+        )
+        .unwrap();
+        assert_eq!(result.promotions.len(), 2);
+        assert!(result.coupon.unwrap().is_err());
+    }
+
+    #[test]
+    fn test_free_gift_needs_stock() {
+        let lines = [line("Game", 1, 4500)];
+        let gift_id = Uuid::now_v7();
+        let mut gifts = [GiftProduct {
+            product_id: gift_id,
+            variant_id: None,
+            name: "Tote".into(),
+            price: Money::new(1200, Currency::USD),
+            available: 1,
+        }];
+        let promo = promotion(
+            "Free tote",
+            PromotionAction::FreeGift {
+                product_id: gift_id,
+                variant_id: None,
+                quantity: 1,
+            },
+        );
+        let pricing = DisplayPricing::store(Currency::USD);
+        let result = discount_cart(
+            std::slice::from_ref(&promo),
+            None,
+            &context(&lines, &gifts),
+            &pricing,
+        )
+        .unwrap();
+        assert_eq!(result.free_items.len(), 1);
+        assert_eq!(result.free_items[0].value.amount(), 1200);
+        assert!(result.promotion_total.is_zero());
+        assert_eq!(result.promotions[0].explanation, "Free gift: 1 × Tote");
+
+        gifts[0].available = 0;
+        let result = discount_cart(&[promo], None, &context(&lines, &gifts), &pricing).unwrap();
+        assert!(result.free_items.is_empty());
+    }
+
+    #[test]
+    fn test_free_gifts_share_stock() {
+        let lines = [line("Game", 1, 4500)];
+        let gift_id = Uuid::now_v7();
+        let mut gifts = [GiftProduct {
+            product_id: gift_id,
+            variant_id: None,
+            name: "Tote".into(),
+            price: Money::new(1200, Currency::USD),
+            available: 3,
+        }];
+        let gift = |name: &str, quantity: i32| {
+            promotion(
+                name,
+                PromotionAction::FreeGift {
+                    product_id: gift_id,
+                    variant_id: None,
+                    quantity,
+                },
+            )
+        };
+        let mut first = gift("Two totes", 2);
+        first.priority = 1;
+        let promos = [
+            first,
+            gift("Another two totes", 2),
+            gift("One more tote", 1),
+        ];
+        let pricing = DisplayPricing::store(Currency::USD);
+
+        // The second promotion would take the totes past the three left
+        let result = discount_cart(&promos, None, &context(&lines, &gifts), &pricing).unwrap();
+        let given: Vec<(&str, i32)> = result
+            .promotions
+            .iter()
+            .zip(&result.free_items)
+            .map(|(applied, item)| (applied.name.as_str(), item.quantity))
+            .collect();
+        assert_eq!(given, vec![("Two totes", 2), ("One more tote", 1)]);
+
+        gifts[0].available = 4;
+        let result = discount_cart(&promos, None, &context(&lines, &gifts), &pricing).unwrap();
+        assert_eq!(
+            result
+                .free_items
+                .iter()
+                .map(|item| item.quantity)
+                .sum::<i32>(),
+            4
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+/// Purchase quantity limits for a product or variant.
+///
+/// Unset fields impose no limit. Variant rules override the product's
+/// field by field (see [`QuantityRules::merged_with`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuantityRules {
+    pub min_quantity: Option<i32>,
+    pub max_quantity: Option<i32>,
+    /// Sold in multiples of this (e.g. packs of 10 m of tubing)
+    pub quantity_step: Option<i32>,
+    /// Lifetime limit per customer, across past orders and the current cart
+    pub max_per_customer: Option<i32>,
+}
+
+impl QuantityRules {
+    /// Overlay variant rules onto product rules
+    pub fn merged_with(self, variant: QuantityRules) -> Self {
+        Self {
+            min_quantity: variant.min_quantity.or(self.min_quantity),
+            max_quantity: variant.max_quantity.or(self.max_quantity),
+            quantity_step: variant.quantity_step.or(self.quantity_step),
+            max_per_customer: variant.max_per_customer.or(self.max_per_customer),
+        }
+    }
+
+    /// Check a quantity for one order/cart.
+    ///
+    /// `previously_purchased` is what the customer already bought in past
+    /// orders; it only counts toward `max_per_customer`.
+    pub fn check(
+        &self,
+        product_name: &str,
+        quantity: i64,
+        previously_purchased: i64,
+    ) -> Result<(), ApiError> {
+        if let Some(min) = self.min_quantity {
+            if quantity < i64::from(min) {
+                return Err(ApiError::rule(
+                    "quantity_below_minimum",
+                    format!("{} requires a minimum quantity of {}", product_name, min),
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_quantity {
+            if quantity > i64::from(max) {
+                return Err(ApiError::rule(
+                    "quantity_above_maximum",
+                    format!("{} is limited to {} per order", product_name, max),
+                ));
+            }
+        }
+
+        if let Some(step) = self.quantity_step.filter(|step| *step > 1) {
+            if quantity % i64::from(step) != 0 {
+                return Err(ApiError::rule(
+                    "quantity_step_mismatch",
+                    format!("{} is sold in multiples of {}", product_name, step),
+                ));
+            }
+        }
+
+        if let Some(limit) = self.max_per_customer {
+            if previously_purchased + quantity > i64::from(limit) {
+                let remaining = (i64::from(limit) - previously_purchased).max(0);
+                return Err(ApiError::rule(
+                    "customer_limit_exceeded",
+                    format!(
+                        "{} is limited to {} per customer ({} remaining)",
+                        product_name, limit, remaining
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(result: Result<(), ApiError>) -> &'static str {
+        result.map(|_| "ok").unwrap_or_else(|e| e.code())
+    }
+
+    #[test]
+    fn test_check_quantity_rules() {
+        let rules = QuantityRules {
+            min_quantity: Some(20),
+            max_quantity: Some(100),
+            quantity_step: Some(10),
+            max_per_customer: Some(150),
+        };
+
+        assert_eq!(code(rules.check("Tubing", 30, 0)), "ok");
+        assert_eq!(code(rules.check("Tubing", 10, 0)), "quantity_below_minimum");
+        assert_eq!(
+            code(rules.check("Tubing", 110, 0)),
+            "quantity_above_maximum"
+        );
+        assert_eq!(code(rules.check("Tubing", 35, 0)), "quantity_step_mismatch");
+        assert_eq!(
+            code(rules.check("Tubing", 60, 100)),
+            "customer_limit_exceeded"
+        );
+        assert_eq!(code(QuantityRules::default().check("Game", 999, 0)), "ok");
+    }
+
+    #[test]
+    fn test_variant_rules_override_product_rules() {
+        let product = QuantityRules {
+            min_quantity: Some(2),
+            max_quantity: Some(10),
+            ..Default::default()
+        };
+        let variant = QuantityRules {
+            max_quantity: Some(1),
+            ..Default::default()
+        };
+
+        let merged = product.merged_with(variant);
+        assert_eq!(merged.min_quantity, Some(2));
+        assert_eq!(merged.max_quantity, Some(1));
+    }
+}
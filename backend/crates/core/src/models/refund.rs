@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Row, Type};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::order::{OrderItem, PaymentStatus};
+use crate::error::ApiError;
+use crate::money::{Currency, Money, MoneyError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum RefundStatus {
+    /// Sent to the provider, which has not confirmed it yet
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl std::fmt::Display for RefundStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefundStatus::Pending => write!(f, "pending"),
+            RefundStatus::Succeeded => write!(f, "succeeded"),
+            RefundStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Money returned to the customer for an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub payment_id: Uuid,
+    pub status: RefundStatus,
+    pub currency: Currency,
+    /// Refunded through the payment provider: lines, shipping and any
+    /// extra amount, less the gift card share
+    pub amount: Money,
+    /// Given back to the gift cards the order was partly paid with
+    pub gift_card_amount: Money,
+    pub shipping_amount: Money,
+    pub reason: String,
+    /// Whether refunded line quantities go back in stock
+    pub restock: bool,
+    pub provider_refund_id: Option<String>,
+    pub failure_reason: Option<String>,
+    /// Staff member who issued the refund
+    pub created_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl<'r> FromRow<'r, PgRow> for Refund {
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        let currency: Currency = row.try_get("currency")?;
+        let money = |column: &str| Money::from_row(row, column, currency);
+        Ok(Self {
+            id: row.try_get("id")?,
+            order_id: row.try_get("order_id")?,
+            payment_id: row.try_get("payment_id")?,
+            status: row.try_get("status")?,
+            currency,
+            amount: money("amount")?,
+            gift_card_amount: money("gift_card_amount")?,
+            shipping_amount: money("shipping_amount")?,
+            reason: row.try_get("reason")?,
+            restock: row.try_get("restock")?,
+            provider_refund_id: row.try_get("provider_refund_id")?,
+            failure_reason: row.try_get("failure_reason")?,
+            created_by: row.try_get("created_by")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+/// An order line covered by a refund
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RefundItem {
+    pub id: Uuid,
+    pub refund_id: Uuid,
+    pub order_item_id: Uuid,
+    pub quantity: i32,
+    pub amount: i64,
+}
+
+/// Amount to refund for `quantity` more units of a line.
+///
+/// Each unit is worth its share of what was charged for the line, tax
+/// included, with rounding settled so that refunding every unit, in any
+/// number of steps, returns exactly that amount.
+pub fn line_refund_amount(
+    item: &OrderItem,
+    prices_include_tax: bool,
+    already_refunded: i32,
+    quantity: i32,
+) -> Result<i64, ApiError> {
+    let remaining = item.quantity - already_refunded;
+    if quantity > remaining {
+        return Err(ApiError::rule(
+            "refund_quantity_exceeded",
+            format!(
+                "Only {} of {} can still be refunded",
+                remaining.max(0),
+                item.product_name
+            ),
+        ));
+    }
+
+    let charged = i128::from(item.charged(prices_include_tax)?.amount());
+    let share = |units: i32| charged * i128::from(units) / i128::from(item.quantity);
+    i64::try_from(share(already_refunded + quantity) - share(already_refunded))
+        .map_err(|_| MoneyError::Overflow.into())
+}
+
+/// Order payment status once `refunded` of `captured` has gone back
+pub fn refunded_payment_status(captured: Money, refunded: Money) -> PaymentStatus {
+    match refunded.amount() {
+        0 => PaymentStatus::Paid,
+        r if r >= captured.amount() => PaymentStatus::Refunded,
+        _ => PaymentStatus::PartiallyRefunded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaxClass;
+
+    #[test]
+    fn test_line_refunds_add_up_to_line_total() {
+        let item = OrderItem {
+            id: Uuid::nil(),
+            order_id: Uuid::nil(),
+            product_id: None,
+            variant_id: None,
+            product_name: "Tube".to_string(),
+            variant_name: None,
+            sku: None,
+            properties: serde_json::json!({}),
+            unit_price: Money::new(333, Currency::USD),
+            price_modifier: Money::zero(Currency::USD),
+            quantity: 3,
+            total: Money::new(1000, Currency::USD),
+            discount_total: Money::zero(Currency::USD),
+            tax_class: TaxClass::Standard,
+            tax_total: Money::new(210, Currency::USD),
+            tax_lines: vec![],
+            created_at: OffsetDateTime::UNIX_EPOCH,
+        };
+
+        let first = line_refund_amount(&item, true, 0, 1).unwrap();
+        let rest = line_refund_amount(&item, true, 1, 2).unwrap();
+        assert_eq!(first, 333);
+        assert_eq!(first + rest, 1000);
+        // Tax added on top is refunded with the line
+        assert_eq!(line_refund_amount(&item, false, 0, 3).unwrap(), 1210);
+        assert_eq!(
+            line_refund_amount(&item, true, 2, 2).unwrap_err().code(),
+            "refund_quantity_exceeded"
+        );
+
+        let usd = |amount| Money::new(amount, Currency::USD);
+        assert_eq!(
+            refunded_payment_status(usd(1000), usd(333)),
+            PaymentStatus::PartiallyRefunded
+        );
+        assert_eq!(
+            refunded_payment_status(usd(1000), usd(1000)),
+            PaymentStatus::Refunded
+        );
+    }
+}
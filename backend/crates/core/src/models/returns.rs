@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// Where a return stands.
+///
+/// `requested → approved → received → closed`, or `requested → rejected`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ReturnStatus {
+    Requested,
+    Approved,
+    Rejected,
+    /// The parcel arrived and its condition was recorded
+    Received,
+    /// Resolved with a refund, exchange or store credit
+    Closed,
+}
+
+impl std::fmt::Display for ReturnStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReturnStatus::Requested => write!(f, "requested"),
+            ReturnStatus::Approved => write!(f, "approved"),
+            ReturnStatus::Rejected => write!(f, "rejected"),
+            ReturnStatus::Received => write!(f, "received"),
+            ReturnStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl ReturnStatus {
+    /// Validate moving a return from this status to `next`
+    pub fn transition(self, next: ReturnStatus) -> Result<ReturnStatus, ApiError> {
+        use ReturnStatus as S;
+
+        match (self, next) {
+            (S::Requested, S::Approved | S::Rejected)
+            | (S::Approved, S::Received)
+            | (S::Received, S::Closed) => Ok(next),
+            _ => Err(ApiError::rule(
+                "invalid_return_transition",
+                format!("Cannot move a return that is {} to {}", self, next),
+            )),
+        }
+    }
+
+    /// Whether the return still holds on to its order lines
+    pub fn is_open(self) -> bool {
+        self != ReturnStatus::Rejected
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ReturnReason {
+    Damaged,
+    Defective,
+    /// Pieces, cards or tokens missing from the box
+    MissingParts,
+    WrongItem,
+    NotAsDescribed,
+    ChangedMind,
+    Other,
+}
+
+impl std::fmt::Display for ReturnReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReturnReason::Damaged => write!(f, "damaged"),
+            ReturnReason::Defective => write!(f, "defective"),
+            ReturnReason::MissingParts => write!(f, "missing_parts"),
+            ReturnReason::WrongItem => write!(f, "wrong_item"),
+            ReturnReason::NotAsDescribed => write!(f, "not_as_described"),
+            ReturnReason::ChangedMind => write!(f, "changed_mind"),
+            ReturnReason::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// Condition of returned goods on arrival
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ItemCondition {
+    /// Still shrink-wrapped
+    Unopened,
+    LikeNew,
+    Used,
+    Damaged,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ReturnResolution {
+    Refund,
+    /// A replacement is sent; no money moves
+    Exchange,
+    StoreCredit,
+}
+
+impl std::fmt::Display for ReturnResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReturnResolution::Refund => write!(f, "refund"),
+            ReturnResolution::Exchange => write!(f, "exchange"),
+            ReturnResolution::StoreCredit => write!(f, "store_credit"),
+        }
+    }
+}
+
+/// A return merchandise authorization (RMA) for some of an order's lines
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Return {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub order_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub rma_number: i64,
+    pub status: ReturnStatus,
+    pub reason: ReturnReason,
+    pub comment: Option<String>,
+    pub decision_note: Option<String>,
+    pub decided_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub decided_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub received_at: Option<OffsetDateTime>,
+    pub condition: Option<ItemCondition>,
+    pub condition_note: Option<String>,
+    pub resolution: Option<ReturnResolution>,
+    /// Refund issued when the return closed with a refund
+    pub refund_id: Option<Uuid>,
+    /// Store credit granted when the return closed with store credit
+    pub credit_amount: Option<i64>,
+    /// Gift card the store credit was issued on
+    pub gift_card_id: Option<Uuid>,
+    pub resolution_note: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub closed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// An order line (and how many of it) being returned
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReturnItem {
+    pub id: Uuid,
+    pub return_id: Uuid,
+    pub order_item_id: Uuid,
+    pub quantity: i32,
+}
+
+/// A photo attached to a return
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReturnPhoto {
+    pub id: Uuid,
+    pub return_id: Uuid,
+    /// Storage key; photos are served through the API, never directly
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_return_transitions() {
+        use ReturnStatus as S;
+
+        assert!(S::Requested.transition(S::Approved).is_ok());
+        assert!(S::Requested.transition(S::Rejected).is_ok());
+        assert!(S::Approved.transition(S::Received).is_ok());
+        assert!(S::Received.transition(S::Closed).is_ok());
+        assert_eq!(
+            S::Requested.transition(S::Closed).unwrap_err().code(),
+            "invalid_return_transition"
+        );
+        assert!(S::Rejected.transition(S::Approved).is_err());
+        assert!(S::Closed.transition(S::Received).is_err());
+    }
+}
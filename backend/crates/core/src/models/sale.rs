@@ -0,0 +1,438 @@
+//! Scheduled sales.
+//!
+//! A sale lowers the price of a set of products or variants between two
+//! exact timestamps. Nothing is written to the catalog: prices are looked up
+//! with the sales running at the time they are shown (or previewed), so a
+//! sale starts and ends on the second and the regular price comes back by
+//! itself. While a sale runs, the regular price is shown as the compare-at
+//! price. A sale item can cap the units sold at its price; units beyond the
+//! cap are charged the regular price.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::models::currency::DisplayPricing;
+use crate::money::{Money, MoneyError};
+
+/// A scheduled price reduction on a set of products or variants
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Sale {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// Shown to customers, e.g. "Black Friday"
+    pub name: String,
+    /// Taken off items that have no price or percentage of their own
+    /// (10% = 100000)
+    pub percentage_ppm: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub starts_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub ends_at: OffsetDateTime,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// A product, or one variant of it, in a sale
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SaleItem {
+    pub id: Uuid,
+    pub sale_id: Uuid,
+    pub product_id: Uuid,
+    /// None for the product and all its variants
+    pub variant_id: Option<Uuid>,
+    /// Fixed sale price in minor units of the store currency
+    pub sale_price: Option<i64>,
+    /// Taken off the regular price when there is no fixed sale price
+    pub percentage_ppm: Option<i32>,
+    /// Units that can be sold at the sale price; None for no cap
+    pub quantity_limit: Option<i32>,
+    /// Units sold at the sale price so far
+    pub sold_quantity: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// The sale price in effect for a product or variant, as pricing needs it
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SalePrice {
+    pub sale_item_id: Uuid,
+    pub sale_id: Uuid,
+    pub name: String,
+    pub ends_at: OffsetDateTime,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    /// Minor units of the store currency
+    pub sale_price: Option<i64>,
+    /// The item's percentage, or the sale's
+    pub percentage_ppm: Option<i32>,
+    /// Units left at the sale price; None for no cap
+    pub remaining: Option<i32>,
+}
+
+/// A running sale as shown on a product
+#[derive(Debug, Clone, Serialize)]
+pub struct SaleSummary {
+    pub sale_id: Uuid,
+    pub name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub ends_at: OffsetDateTime,
+    /// Units left at the sale price, when capped
+    pub remaining: Option<i32>,
+}
+
+impl From<&SalePrice> for SaleSummary {
+    fn from(sale: &SalePrice) -> Self {
+        Self {
+            sale_id: sale.sale_id,
+            name: sale.name.clone(),
+            ends_at: sale.ends_at,
+            remaining: sale.remaining,
+        }
+    }
+}
+
+/// How a cart or order line is priced while a sale may run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePrice {
+    /// Unit price of the first `sale_units` units; the regular price when
+    /// there are none
+    pub unit_price: Money,
+    /// Units at the sale price; the rest are at `regular_price`
+    pub sale_units: i32,
+    /// Unit price without the sale, after group prices
+    pub regular_price: Money,
+    /// Sale item the sale units count against
+    pub sale_item_id: Option<Uuid>,
+}
+
+impl LinePrice {
+    pub fn subtotal(&self, quantity: i32) -> Result<Money, MoneyError> {
+        let at_sale = self.unit_price.checked_mul(i64::from(self.sale_units))?;
+        let at_regular = self
+            .regular_price
+            .checked_mul(i64::from(quantity - self.sale_units))?;
+        at_sale.checked_add(at_regular)
+    }
+
+    /// The units of line `line` of `quantity` by unit price: those at the
+    /// sale price, then any beyond the sale's cap at the regular price
+    pub fn parts(&self, line: usize, quantity: i32) -> Vec<LinePart> {
+        let part = |unit_price, quantity, sale_item_id| LinePart {
+            line,
+            unit_price,
+            quantity,
+            sale_item_id,
+        };
+        if self.sale_units > 0 && self.sale_units < quantity {
+            vec![
+                part(self.unit_price, self.sale_units, self.sale_item_id),
+                part(self.regular_price, quantity - self.sale_units, None),
+            ]
+        } else {
+            vec![part(self.unit_price, quantity, self.sale_item_id)]
+        }
+    }
+}
+
+/// A cart or order line to price
+#[derive(Debug, Clone, Copy)]
+pub struct LineToPrice {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    /// Store price of the product or variant
+    pub price: Money,
+    /// Customization surcharge, in the pricing currency
+    pub surcharge: Money,
+}
+
+/// Units of a line charged at one unit price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePart {
+    /// Index of the line it belongs to
+    pub line: usize,
+    pub unit_price: Money,
+    pub quantity: i32,
+    /// Sale item the units were sold under, when at a sale price
+    pub sale_item_id: Option<Uuid>,
+}
+
+impl LinePart {
+    pub fn total(&self) -> Result<Money, MoneyError> {
+        self.unit_price.checked_mul(i64::from(self.quantity))
+    }
+}
+
+/// Lines priced together, as a cart shows them and an order charges them
+#[derive(Debug, Clone)]
+pub struct PricedLines {
+    /// Same order as the lines priced
+    pub prices: Vec<LinePrice>,
+    /// Each line split at its sale's cap; promotions and tax apply per part
+    pub parts: Vec<LinePart>,
+    /// Units taken per sale item
+    pub sold: HashMap<Uuid, i32>,
+}
+
+/// Price the lines of a cart or order in order, sharing capped sale units
+/// between them. Group quantity breaks count every unit of a product or
+/// variant across lines.
+pub fn price_lines(
+    pricing: &DisplayPricing,
+    lines: &[LineToPrice],
+) -> Result<PricedLines, MoneyError> {
+    let mut in_cart: HashMap<(Uuid, Option<Uuid>), i64> = HashMap::new();
+    for line in lines {
+        *in_cart
+            .entry((line.product_id, line.variant_id))
+            .or_insert(0) += i64::from(line.quantity);
+    }
+
+    let mut sale_units = SaleUnits::new(pricing);
+    let mut prices = Vec::with_capacity(lines.len());
+    let mut parts = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        let tier_quantity = in_cart[&(line.product_id, line.variant_id)];
+        let price = sale_units.price_line(
+            line.product_id,
+            line.variant_id,
+            line.quantity,
+            i32::try_from(tier_quantity).unwrap_or(i32::MAX),
+            line.price,
+            line.surcharge,
+        )?;
+        parts.extend(price.parts(index, line.quantity));
+        prices.push(price);
+    }
+
+    Ok(PricedLines {
+        prices,
+        parts,
+        sold: sale_units.taken,
+    })
+}
+
+/// Hands out the units left at sale prices while the lines of a cart or
+/// order are priced one by one, so lines sharing a capped sale item do not
+/// each get the full allowance
+pub struct SaleUnits<'a> {
+    pricing: &'a DisplayPricing,
+    taken: HashMap<Uuid, i32>,
+}
+
+impl<'a> SaleUnits<'a> {
+    pub fn new(pricing: &'a DisplayPricing) -> Self {
+        Self {
+            pricing,
+            taken: HashMap::new(),
+        }
+    }
+
+    /// Price a line of `quantity` units of a product or variant whose store
+    /// price is `price`, plus a `surcharge` (customizations) in the pricing
+    /// currency. Group quantity breaks go by `tier_quantity`, the units of
+    /// the product or variant in the whole cart.
+    pub fn price_line(
+        &mut self,
+        product_id: Uuid,
+        variant_id: Option<Uuid>,
+        quantity: i32,
+        tier_quantity: i32,
+        price: Money,
+        surcharge: Money,
+    ) -> Result<LinePrice, MoneyError> {
+        let (regular, _) = self
+            .pricing
+            .regular_price(product_id, variant_id, price, None)?;
+        let group_price =
+            self.pricing
+                .group_price(product_id, variant_id, tier_quantity, regular)?;
+        let (sale_price, _) =
+            self.pricing
+                .quantity_price(product_id, variant_id, tier_quantity, price, None)?;
+        let sale_units = if sale_price.amount() < group_price.amount() {
+            self.take(product_id, variant_id, quantity)
+        } else {
+            0
+        };
+        let regular_price = group_price.checked_add(surcharge)?;
+        let unit_price = if sale_units > 0 {
+            sale_price.checked_add(surcharge)?
+        } else {
+            regular_price
+        };
+        Ok(LinePrice {
+            unit_price,
+            sale_units,
+            regular_price,
+            sale_item_id: self
+                .pricing
+                .sale(product_id, variant_id)
+                .map(|sale| sale.sale_item_id)
+                .filter(|_| sale_units > 0),
+        })
+    }
+
+    /// How many of `quantity` units of a product or variant are sold at its
+    /// sale price; the rest are at the regular price
+    pub fn take(&mut self, product_id: Uuid, variant_id: Option<Uuid>, quantity: i32) -> i32 {
+        let Some(sale) = self.pricing.sale(product_id, variant_id) else {
+            return 0;
+        };
+        let taken = self.taken.entry(sale.sale_item_id).or_insert(0);
+        let units = match sale.remaining {
+            Some(remaining) => quantity.min(remaining - *taken).max(0),
+            None => quantity,
+        };
+        *taken += units;
+        units
+    }
+
+    /// Units taken per sale item
+    pub fn taken(&self) -> &HashMap<Uuid, i32> {
+        &self.taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::{Currency, Money};
+
+    fn sale_price(product_id: Uuid, variant_id: Option<Uuid>) -> SalePrice {
+        SalePrice {
+            sale_item_id: Uuid::now_v7(),
+            sale_id: Uuid::now_v7(),
+            name: "Flash sale".into(),
+            ends_at: OffsetDateTime::now_utc() + time::Duration::hours(2),
+            product_id,
+            variant_id,
+            sale_price: None,
+            percentage_ppm: Some(200_000),
+            remaining: None,
+        }
+    }
+
+    fn usd(amount: i64) -> Money {
+        Money::new(amount, Currency::USD)
+    }
+
+    #[test]
+    fn test_sale_sets_price_and_compare_at() {
+        let product_id = Uuid::now_v7();
+        let variant_id = Uuid::now_v7();
+        let mut fixed = sale_price(product_id, Some(variant_id));
+        fixed.sale_price = Some(3000);
+        let pricing = DisplayPricing::store(Currency::USD)
+            .with_sales(vec![sale_price(product_id, None), fixed]);
+
+        // 20% off the product, the regular price shown as compare-at
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, None, usd(5000), None)
+                .unwrap(),
+            (usd(4000), Some(usd(5000)))
+        );
+        // A higher compare-at price set by hand is kept
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, None, usd(5000), Some(usd(6000)))
+                .unwrap(),
+            (usd(4000), Some(usd(6000)))
+        );
+        // The variant has its own fixed price
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, Some(variant_id), usd(4500), None)
+                .unwrap(),
+            (usd(3000), Some(usd(4500)))
+        );
+        // Other variants fall back to the product-wide item
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, Some(Uuid::now_v7()), usd(4500), None)
+                .unwrap()
+                .0,
+            usd(3600)
+        );
+        // A sale never raises a price
+        assert_eq!(
+            pricing
+                .catalog_price(product_id, Some(variant_id), usd(2000), None)
+                .unwrap(),
+            (usd(2000), None)
+        );
+    }
+
+    #[test]
+    fn test_sale_units_respect_cap() {
+        let product_id = Uuid::now_v7();
+        let mut capped = sale_price(product_id, None);
+        capped.remaining = Some(3);
+        let sold_out = {
+            let mut sale = sale_price(Uuid::now_v7(), None);
+            sale.remaining = Some(0);
+            sale
+        };
+        let sold_out_product = sold_out.product_id;
+        let pricing = DisplayPricing::store(Currency::USD).with_sales(vec![capped, sold_out]);
+
+        let mut units = SaleUnits::new(&pricing);
+        let line = units
+            .price_line(product_id, None, 2, 2, usd(5000), usd(100))
+            .unwrap();
+        assert_eq!((line.unit_price, line.sale_units), (usd(4100), 2));
+        // One unit left at the sale price, the other at the regular price
+        let line = units
+            .price_line(product_id, Some(Uuid::now_v7()), 2, 2, usd(5000), usd(0))
+            .unwrap();
+        assert_eq!(line.sale_units, 1);
+        assert_eq!(line.subtotal(2).unwrap(), usd(4000 + 5000));
+        let line = units
+            .price_line(product_id, None, 1, 1, usd(5000), usd(0))
+            .unwrap();
+        assert_eq!((line.unit_price, line.sale_units), (usd(5000), 0));
+        assert_eq!(
+            units.taken()[&pricing.sale(product_id, None).unwrap().sale_item_id],
+            3
+        );
+        assert_eq!(units.take(Uuid::now_v7(), None, 5), 0);
+
+        // Lines priced together split at the cap, in line order
+        let line = |quantity| LineToPrice {
+            product_id,
+            variant_id: None,
+            quantity,
+            price: usd(5000),
+            surcharge: usd(0),
+        };
+        let priced = price_lines(&pricing, &[line(2), line(2)]).unwrap();
+        assert_eq!(priced.prices[1].subtotal(2).unwrap(), usd(4000 + 5000));
+        assert_eq!(
+            priced
+                .parts
+                .iter()
+                .map(|part| (part.line, part.unit_price, part.quantity))
+                .collect::<Vec<_>>(),
+            vec![(0, usd(4000), 2), (1, usd(4000), 1), (1, usd(5000), 1)]
+        );
+        assert!(priced.parts[1].sale_item_id.is_some());
+        assert!(priced.parts[2].sale_item_id.is_none());
+        assert_eq!(priced.sold.values().sum::<i32>(), 3);
+
+        // Once the cap is reached the regular price shows again
+        assert!(pricing.sale(sold_out_product, None).is_none());
+        assert_eq!(
+            pricing
+                .catalog_price(sold_out_product, None, usd(5000), None)
+                .unwrap(),
+            (usd(5000), None)
+        );
+    }
+}
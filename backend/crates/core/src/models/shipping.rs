@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+
+/// How a shipping method prices a parcel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum ShippingMethodKind {
+    /// Same `rate` for every parcel
+    FlatRate,
+    /// Rate from the first tier whose `up_to` covers the parcel weight (grams)
+    WeightTiers,
+    /// Rate from the first tier whose `up_to` covers the cart subtotal
+    PriceTiers,
+    /// Free once the subtotal reaches `min_subtotal`
+    Free,
+    /// Collected at the store; `rate` is usually zero
+    LocalPickup,
+    /// Quoted live by a carrier adapter for one of its services; `rate` is
+    /// added as a handling fee
+    Carrier,
+}
+
+impl std::fmt::Display for ShippingMethodKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShippingMethodKind::FlatRate => write!(f, "flat_rate"),
+            ShippingMethodKind::WeightTiers => write!(f, "weight_tiers"),
+            ShippingMethodKind::PriceTiers => write!(f, "price_tiers"),
+            ShippingMethodKind::Free => write!(f, "free"),
+            ShippingMethodKind::LocalPickup => write!(f, "local_pickup"),
+            ShippingMethodKind::Carrier => write!(f, "carrier"),
+        }
+    }
+}
+
+/// Weight and size of a product or variant, as stored in the catalog.
+///
+/// Unset fields are unknown; variant values override the product's field by
+/// field (see [`PackageDimensions::merged_with`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PackageDimensions {
+    pub weight_grams: Option<i32>,
+    pub length_mm: Option<i32>,
+    pub width_mm: Option<i32>,
+    pub height_mm: Option<i32>,
+}
+
+impl PackageDimensions {
+    /// Overlay variant dimensions onto product dimensions
+    pub fn merged_with(self, variant: PackageDimensions) -> Self {
+        Self {
+            weight_grams: variant.weight_grams.or(self.weight_grams),
+            length_mm: variant.length_mm.or(self.length_mm),
+            width_mm: variant.width_mm.or(self.width_mm),
+            height_mm: variant.height_mm.or(self.height_mm),
+        }
+    }
+}
+
+/// One step of a tiered rate table; `up_to: None` covers everything above
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateTier {
+    pub up_to: Option<i64>,
+    pub rate: i64,
+}
+
+/// A region of the world a store ships to.
+///
+/// Empty lists match everything, so a zone without countries is the
+/// "rest of the world" zone. Zones are tried by `priority`, lowest first.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShippingZone {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub name: String,
+    /// ISO 3166-1 alpha-2 codes
+    pub countries: Vec<String>,
+    /// Region names or codes, compared case-insensitively
+    pub regions: Vec<String>,
+    /// `*` wildcards (`SW1*`) or numeric ranges (`1000-1999`)
+    pub postcode_patterns: Vec<String>,
+    pub priority: i32,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Where a parcel is going; only the parts zones match on
+#[derive(Debug, Clone, Default)]
+pub struct Destination {
+    pub country: String,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+}
+
+impl ShippingZone {
+    pub fn matches(&self, dest: &Destination) -> bool {
+        let country = self.countries.is_empty()
+            || self
+                .countries
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&dest.country));
+        let region = self.regions.is_empty()
+            || dest.region.as_deref().is_some_and(|region| {
+                self.regions
+                    .iter()
+                    .any(|r| r.trim().eq_ignore_ascii_case(region.trim()))
+            });
+        let postcode = self.postcode_patterns.is_empty()
+            || dest.postal_code.as_deref().is_some_and(|code| {
+                self.postcode_patterns
+                    .iter()
+                    .any(|pattern| postcode_matches(pattern, code))
+            });
+
+        country && region && postcode
+    }
+}
+
+/// Normalize a postcode for matching: uppercase, no spaces
+fn normalize_postcode(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Match a postcode against a `*` wildcard pattern or an inclusive numeric range
+pub fn postcode_matches(pattern: &str, postcode: &str) -> bool {
+    let pattern = normalize_postcode(pattern);
+    let postcode = normalize_postcode(postcode);
+
+    if let Some((low, high)) = pattern.split_once('-') {
+        if let (Ok(low), Ok(high)) = (low.parse::<u64>(), high.parse::<u64>()) {
+            // Ranges compare the leading digits, so "1012 AB" and "90210-1234" work
+            let digits: String = postcode.chars().take_while(char::is_ascii_digit).collect();
+            return digits
+                .parse::<u64>()
+                .is_ok_and(|code| (low..=high).contains(&code));
+        }
+    }
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = postcode.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// A way to ship within a zone (e.g. "Standard", "Express", "Pickup")
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShippingMethod {
+    pub id: Uuid,
+    pub zone_id: Uuid,
+    /// Chosen by the customer at the `shipping_method` checkout step
+    pub code: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub kind: ShippingMethodKind,
+    /// Flat rate and local pickup price; handling fee for carrier methods
+    pub rate: i64,
+    #[sqlx(json)]
+    pub tiers: Vec<RateTier>,
+    /// Threshold for `free`
+    pub min_subtotal: Option<i64>,
+    pub sort_order: i32,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+    /// Carrier adapter and its service, for `carrier` methods
+    pub carrier_code: Option<String>,
+    pub service_code: Option<String>,
+}
+
+/// What is being shipped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Parcel {
+    pub subtotal: i64,
+    pub weight_grams: i64,
+}
+
+fn tier_rate(tiers: &[RateTier], value: i64) -> Option<i64> {
+    tiers
+        .iter()
+        .find(|tier| tier.up_to.is_none_or(|up_to| value <= up_to))
+        .map(|tier| tier.rate)
+}
+
+impl ShippingMethod {
+    /// Price of shipping `parcel`, or None if this method cannot carry it.
+    ///
+    /// Carrier methods are never priced here; their price comes from the
+    /// carrier.
+    pub fn quote(&self, parcel: &Parcel) -> Option<i64> {
+        match self.kind {
+            ShippingMethodKind::Carrier => None,
+            ShippingMethodKind::FlatRate | ShippingMethodKind::LocalPickup => Some(self.rate),
+            ShippingMethodKind::WeightTiers => tier_rate(&self.tiers, parcel.weight_grams),
+            ShippingMethodKind::PriceTiers => tier_rate(&self.tiers, parcel.subtotal),
+            ShippingMethodKind::Free => {
+                let threshold = self.min_subtotal.unwrap_or(0);
+                (parcel.subtotal >= threshold).then_some(0)
+            }
+        }
+    }
+}
+
+/// Check that a method's settings fit its kind
+pub fn validate_method_config(
+    kind: ShippingMethodKind,
+    tiers: &[RateTier],
+    min_subtotal: Option<i64>,
+    carrier_code: Option<&str>,
+    service_code: Option<&str>,
+) -> Result<(), ApiError> {
+    let has_carrier = carrier_code.is_some() || service_code.is_some();
+    if kind == ShippingMethodKind::Carrier && (carrier_code.is_none() || service_code.is_none()) {
+        return Err(ApiError::validation(
+            "A carrier method needs a carrier_code and a service_code",
+        ));
+    }
+    if kind != ShippingMethodKind::Carrier && has_carrier {
+        return Err(ApiError::validation(format!(
+            "A {} method does not use a carrier",
+            kind
+        )));
+    }
+    let tiered = matches!(
+        kind,
+        ShippingMethodKind::WeightTiers | ShippingMethodKind::PriceTiers
+    );
+    if tiered && tiers.is_empty() {
+        return Err(ApiError::validation(format!(
+            "A {} method needs at least one tier",
+            kind
+        )));
+    }
+    if !tiered && !tiers.is_empty() {
+        return Err(ApiError::validation(format!(
+            "A {} method does not use tiers",
+            kind
+        )));
+    }
+    if kind == ShippingMethodKind::Free && min_subtotal.is_none() {
+        return Err(ApiError::validation(
+            "A free method needs a min_subtotal threshold",
+        ));
+    }
+    if tiers.iter().any(|tier| tier.rate < 0) {
+        return Err(ApiError::validation("Tier rates cannot be negative"));
+    }
+    for pair in tiers.windows(2) {
+        match (pair[0].up_to, pair[1].up_to) {
+            (None, _) => {
+                return Err(ApiError::validation("Only the last tier can be open-ended"));
+            }
+            (Some(low), Some(high)) if high <= low => {
+                return Err(ApiError::validation("Tiers must be in ascending order"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(kind: ShippingMethodKind, tiers: Vec<RateTier>) -> ShippingMethod {
+        let now = OffsetDateTime::now_utc();
+        ShippingMethod {
+            id: Uuid::nil(),
+            zone_id: Uuid::nil(),
+            code: "standard".into(),
+            name: "Standard".into(),
+            description: None,
+            kind,
+            rate: 495,
+            tiers,
+            min_subtotal: Some(5000),
+            sort_order: 0,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            carrier_code: None,
+            service_code: None,
+        }
+    }
+
+    #[test]
+    fn test_postcode_patterns() {
+        assert!(postcode_matches("SW1*", "sw1a 1aa"));
+        assert!(!postcode_matches("SW1*", "SE1 7PB"));
+        assert!(postcode_matches("1000-1099", "1012 AB"));
+        assert!(postcode_matches("90000-90299", "90210-1234"));
+        assert!(!postcode_matches("1000-1099", "1100"));
+        assert!(postcode_matches("9*0", "9410"));
+        assert!(postcode_matches("90210", "90210"));
+        assert!(!postcode_matches("90210", "902101"));
+    }
+
+    #[test]
+    fn test_method_quotes() {
+        let tiers = vec![
+            RateTier {
+                up_to: Some(1000),
+                rate: 500,
+            },
+            RateTier {
+                up_to: Some(5000),
+                rate: 900,
+            },
+        ];
+        let weight = method(ShippingMethodKind::WeightTiers, tiers.clone());
+        let light = Parcel {
+            subtotal: 2000,
+            weight_grams: 1000,
+        };
+        let heavy = Parcel {
+            subtotal: 6000,
+            weight_grams: 7000,
+        };
+        assert_eq!(weight.quote(&light), Some(500));
+        assert_eq!(weight.quote(&heavy), None);
+
+        let free = method(ShippingMethodKind::Free, vec![]);
+        assert_eq!(free.quote(&light), None);
+        assert_eq!(free.quote(&heavy), Some(0));
+        assert_eq!(
+            method(ShippingMethodKind::FlatRate, vec![]).quote(&heavy),
+            Some(495)
+        );
+
+        assert_eq!(
+            method(ShippingMethodKind::Carrier, vec![]).quote(&light),
+            None
+        );
+
+        let check = |kind, tiers: &[RateTier], carrier| {
+            validate_method_config(kind, tiers, None, carrier, carrier.map(|_| "ground"))
+        };
+        assert!(check(ShippingMethodKind::WeightTiers, &tiers, None).is_ok());
+        let unordered = [tiers[1], tiers[0]];
+        assert!(check(ShippingMethodKind::PriceTiers, &unordered, None).is_err());
+        assert!(check(ShippingMethodKind::Free, &[], None).is_err());
+        assert!(check(ShippingMethodKind::Carrier, &[], Some("ups")).is_ok());
+        assert!(check(ShippingMethodKind::Carrier, &[], None).is_err());
+        assert!(check(ShippingMethodKind::FlatRate, &[], Some("ups")).is_err());
+    }
+}
@@ -2,7 +2,9 @@ use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::money::Currency;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Store {
     pub id: Uuid,
     pub slug: String,
@@ -10,7 +12,7 @@ pub struct Store {
     pub description: Option<String>,
     pub config: serde_json::Value,
     pub theme: String,
-    pub currency: String,
+    pub currency: Currency,
     pub domain: Option<String>,
     pub is_active: bool,
     #[serde(with = "time::serde::rfc3339")]
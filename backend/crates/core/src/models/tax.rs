@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::Type;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::shipping::{postcode_matches, Destination};
+use crate::money::MoneyError;
+
+/// Which rates apply to a product
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum TaxClass {
+    #[default]
+    Standard,
+    /// Lower rates for e.g. food or books
+    Reduced,
+    /// Never taxed
+    Exempt,
+}
+
+impl std::fmt::Display for TaxClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaxClass::Standard => write!(f, "standard"),
+            TaxClass::Reduced => write!(f, "reduced"),
+            TaxClass::Exempt => write!(f, "exempt"),
+        }
+    }
+}
+
+/// When tax amounts are rounded to whole minor units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxRounding {
+    /// Every line's tax is rounded on its own
+    #[default]
+    Line,
+    /// Each rate is rounded once over the whole order; lines get their share
+    Invoice,
+}
+
+/// Per-store tax settings, read from `stores.config`:
+///
+/// ```json
+/// { "tax": { "prices_include_tax": true, "shipping_taxable": true, "rounding": "invoice" } }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxSettings {
+    /// Catalog prices already contain tax, which is extracted rather than added
+    #[serde(default)]
+    pub prices_include_tax: bool,
+    #[serde(default = "default_shipping_taxable")]
+    pub shipping_taxable: bool,
+    /// Rates applied to shipping charges
+    #[serde(default)]
+    pub shipping_tax_class: TaxClass,
+    #[serde(default)]
+    pub rounding: TaxRounding,
+}
+
+fn default_shipping_taxable() -> bool {
+    true
+}
+
+impl Default for TaxSettings {
+    fn default() -> Self {
+        Self {
+            prices_include_tax: false,
+            shipping_taxable: default_shipping_taxable(),
+            shipping_tax_class: TaxClass::Standard,
+            rounding: TaxRounding::Line,
+        }
+    }
+}
+
+impl TaxSettings {
+    /// Extract settings from a store's config JSON, falling back to defaults
+    pub fn from_store_config(config: &serde_json::Value) -> Self {
+        config
+            .get("tax")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// A tax a store charges in a jurisdiction, for one tax class.
+///
+/// Rates are grouped by `priority`: within a group only the most specific
+/// match applies (postcode over region over country), and the groups stack,
+/// so a state rate and a city rate can both be charged.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxRate {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// Shown to customers and on reports (e.g. "VAT", "CA State Tax")
+    pub name: String,
+    /// ISO 3166-1 alpha-2 code
+    pub country: String,
+    /// Region name or code, compared case-insensitively; None for the whole country
+    pub region: Option<String>,
+    /// `*` wildcards (`SW1*`) or numeric ranges (`1000-1999`); empty for any
+    pub postcode_patterns: Vec<String>,
+    pub tax_class: TaxClass,
+    /// Parts per million of the taxed amount (21% = 210000)
+    pub rate_ppm: i32,
+    pub priority: i32,
+    pub is_active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl TaxRate {
+    pub fn matches(&self, dest: &Destination) -> bool {
+        let region = self.region.as_deref().is_none_or(|region| {
+            dest.region
+                .as_deref()
+                .is_some_and(|r| r.trim().eq_ignore_ascii_case(region.trim()))
+        });
+        let postcode = self.postcode_patterns.is_empty()
+            || dest.postal_code.as_deref().is_some_and(|code| {
+                self.postcode_patterns
+                    .iter()
+                    .any(|pattern| postcode_matches(pattern, code))
+            });
+
+        self.country.eq_ignore_ascii_case(&dest.country) && region && postcode
+    }
+
+    /// How narrowly the rate targets a place; higher wins within a priority
+    fn specificity(&self) -> u8 {
+        u8::from(self.region.is_some()) + 2 * u8::from(!self.postcode_patterns.is_empty())
+    }
+}
+
+/// Rates charged on `class` at `dest`, in priority order
+pub fn applicable_rates<'a>(
+    rates: &'a [TaxRate],
+    class: TaxClass,
+    dest: &Destination,
+) -> Vec<&'a TaxRate> {
+    if class == TaxClass::Exempt {
+        return vec![];
+    }
+
+    let mut best: Vec<&TaxRate> = vec![];
+    for rate in rates
+        .iter()
+        .filter(|rate| rate.is_active && rate.tax_class == class && rate.matches(dest))
+    {
+        match best.iter_mut().find(|b| b.priority == rate.priority) {
+            Some(current) if rate.specificity() > current.specificity() => *current = rate,
+            Some(_) => {}
+            None => best.push(rate),
+        }
+    }
+    best.sort_by_key(|rate| rate.priority);
+    best
+}
+
+/// Where tax is charged: the shipping address, or the store's own
+/// `ship_from` address when there is none (pickup, or a cart without checkout)
+pub fn tax_destination(
+    shipping_address: Option<&serde_json::Value>,
+    store_config: &serde_json::Value,
+) -> Option<Destination> {
+    #[derive(Deserialize)]
+    struct Place {
+        country: String,
+        region: Option<String>,
+        postal_code: Option<String>,
+    }
+
+    shipping_address
+        .or_else(|| store_config.get("ship_from"))
+        .and_then(|address| serde_json::from_value::<Place>(address.clone()).ok())
+        .filter(|place| !place.country.trim().is_empty())
+        .map(|place| Destination {
+            country: place.country.trim().to_uppercase(),
+            region: place.region,
+            postal_code: place.postal_code,
+        })
+}
+
+/// One rate applied to an amount; snapshotted on orders
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxLine {
+    pub rate_id: Uuid,
+    pub name: String,
+    pub country: String,
+    pub region: Option<String>,
+    pub rate_ppm: i32,
+    /// Amount the rate was charged on, without tax
+    pub taxable_amount: i64,
+    pub amount: i64,
+}
+
+/// Something to tax: a cart or order line, or shipping
+#[derive(Debug, Clone, Copy)]
+pub struct TaxableLine {
+    /// Line total as priced, with or without tax per the store settings
+    pub amount: i64,
+    pub tax_class: TaxClass,
+}
+
+/// Tax on one line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineTax {
+    pub amount: i64,
+    pub lines: Vec<TaxLine>,
+}
+
+/// Tax on a whole cart or order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaxBreakdown {
+    /// Same order as the lines taxed
+    pub lines: Vec<LineTax>,
+    pub shipping: LineTax,
+    /// Totals per rate, shipping included
+    pub summary: Vec<TaxLine>,
+    pub total: i64,
+    pub prices_include_tax: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+pub enum TaxExemptionStatus {
+    /// Waiting for staff to check the certificate
+    Pending,
+    Approved,
+    Rejected,
+    /// Withdrawn by staff, or replaced by a newer certificate
+    Revoked,
+}
+
+impl std::fmt::Display for TaxExemptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaxExemptionStatus::Pending => write!(f, "pending"),
+            TaxExemptionStatus::Approved => write!(f, "approved"),
+            TaxExemptionStatus::Rejected => write!(f, "rejected"),
+            TaxExemptionStatus::Revoked => write!(f, "revoked"),
+        }
+    }
+}
+
+impl TaxExemptionStatus {
+    /// Pending certificates are decided once; approved ones can be revoked
+    pub fn can_become(self, next: TaxExemptionStatus) -> bool {
+        use TaxExemptionStatus as S;
+        matches!(
+            (self, next),
+            (S::Pending, S::Approved | S::Rejected) | (S::Approved, S::Revoked)
+        )
+    }
+}
+
+/// A customer's claim to buy without tax, backed by a certificate.
+///
+/// Only approved exemptions that have not expired take effect; the customer
+/// then pays no tax on anything. With tax-inclusive prices the prices stay
+/// as listed.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxExemption {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub user_id: Uuid,
+    pub status: TaxExemptionStatus,
+    pub certificate_number: Option<String>,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+    pub note: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub reviewed_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Exact tax amounts are kept in millionths of a minor unit until rounded
+const EXACT_SCALE: i128 = 1_000_000;
+const PPM: i128 = 1_000_000;
+
+/// Round a non-negative exact amount half up to whole minor units
+fn round_exact(exact: i128) -> Result<i64, MoneyError> {
+    whole_units((exact + EXACT_SCALE / 2) / EXACT_SCALE)
+}
+
+fn whole_units(amount: i128) -> Result<i64, MoneyError> {
+    i64::try_from(amount).map_err(|_| MoneyError::Overflow)
+}
+
+fn checked_sum(amounts: impl IntoIterator<Item = i64>) -> Result<i64, MoneyError> {
+    amounts
+        .into_iter()
+        .try_fold(0i64, |sum, amount| sum.checked_add(amount))
+        .ok_or(MoneyError::Overflow)
+}
+
+/// Tax `lines` and `shipping` with `rates` for `dest`.
+///
+/// Without a destination nothing is taxed. With tax-inclusive prices the
+/// amounts stay the same and the tax is the part of them that is tax;
+/// otherwise tax comes on top. Fails if an amount does not fit in minor units.
+pub fn calculate_tax(
+    settings: &TaxSettings,
+    rates: &[TaxRate],
+    dest: Option<&Destination>,
+    lines: &[TaxableLine],
+    shipping: i64,
+) -> Result<TaxBreakdown, MoneyError> {
+    let shipping_line = TaxableLine {
+        amount: shipping,
+        tax_class: if settings.shipping_taxable {
+            settings.shipping_tax_class
+        } else {
+            TaxClass::Exempt
+        },
+    };
+    let all: Vec<TaxableLine> = lines
+        .iter()
+        .copied()
+        .chain(std::iter::once(shipping_line))
+        .collect();
+
+    // Exact tax per line and applied rate
+    let exact: Vec<Vec<(&TaxRate, i128)>> = all
+        .iter()
+        .map(|line| {
+            let Some(dest) = dest else {
+                return vec![];
+            };
+            let applied = applicable_rates(rates, line.tax_class, dest);
+            let combined: i128 = applied.iter().map(|r| i128::from(r.rate_ppm)).sum();
+            let base = if settings.prices_include_tax {
+                PPM + combined
+            } else {
+                PPM
+            };
+            applied
+                .into_iter()
+                .map(|rate| {
+                    let numerator =
+                        i128::from(line.amount.max(0)) * i128::from(rate.rate_ppm) * EXACT_SCALE;
+                    (rate, (numerator + base / 2) / base)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut rounded: Vec<Vec<i64>> = exact
+        .iter()
+        .map(|applied| applied.iter().map(|(_, e)| round_exact(*e)).collect())
+        .collect::<Result<_, _>>()?;
+
+    if settings.rounding == TaxRounding::Invoice {
+        // Round each rate once over the order, then hand out the whole units
+        // to the lines with the largest remainders
+        let mut by_rate: HashMap<Uuid, Vec<(usize, usize, i128)>> = HashMap::new();
+        for (l, applied) in exact.iter().enumerate() {
+            for (a, (rate, e)) in applied.iter().enumerate() {
+                by_rate.entry(rate.id).or_default().push((l, a, *e));
+            }
+        }
+        for shares in by_rate.values_mut() {
+            let target = round_exact(shares.iter().map(|(_, _, e)| e).sum())?;
+            let mut given = 0i64;
+            for &(l, a, e) in shares.iter() {
+                rounded[l][a] = whole_units(e / EXACT_SCALE)?;
+                given = given
+                    .checked_add(rounded[l][a])
+                    .ok_or(MoneyError::Overflow)?;
+            }
+            shares.sort_by_key(|(_, _, e)| std::cmp::Reverse(e % EXACT_SCALE));
+            let left = usize::try_from(target.saturating_sub(given)).unwrap_or(0);
+            for &(l, a, _) in shares.iter().take(left) {
+                rounded[l][a] += 1;
+            }
+        }
+    }
+
+    let mut taxed: Vec<LineTax> = all
+        .iter()
+        .zip(exact.iter().zip(&rounded))
+        .map(|(line, (applied, amounts))| {
+            let amount = checked_sum(amounts.iter().copied())?;
+            let taxable_amount = if settings.prices_include_tax {
+                line.amount
+                    .checked_sub(amount)
+                    .ok_or(MoneyError::Overflow)?
+            } else {
+                line.amount
+            };
+            Ok(LineTax {
+                amount,
+                lines: applied
+                    .iter()
+                    .zip(amounts)
+                    .map(|((rate, _), amount)| TaxLine {
+                        rate_id: rate.id,
+                        name: rate.name.clone(),
+                        country: rate.country.clone(),
+                        region: rate.region.clone(),
+                        rate_ppm: rate.rate_ppm,
+                        taxable_amount,
+                        amount: *amount,
+                    })
+                    .collect(),
+            })
+        })
+        .collect::<Result<_, MoneyError>>()?;
+    let shipping = taxed.pop().unwrap_or_default();
+
+    let mut summary: Vec<TaxLine> = vec![];
+    for line in taxed.iter().chain(std::iter::once(&shipping)) {
+        for tax in &line.lines {
+            match summary.iter_mut().find(|s| s.rate_id == tax.rate_id) {
+                Some(total) => {
+                    total.taxable_amount = checked_sum([total.taxable_amount, tax.taxable_amount])?;
+                    total.amount = checked_sum([total.amount, tax.amount])?;
+                }
+                None => summary.push(tax.clone()),
+            }
+        }
+    }
+
+    Ok(TaxBreakdown {
+        total: checked_sum(summary.iter().map(|s| s.amount))?,
+        lines: taxed,
+        shipping,
+        summary,
+        prices_include_tax: settings.prices_include_tax,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(
+        name: &str,
+        country: &str,
+        region: Option<&str>,
+        class: TaxClass,
+        rate_ppm: i32,
+        priority: i32,
+    ) -> TaxRate {
+        TaxRate {
+            id: Uuid::now_v7(),
+            store_id: Uuid::nil(),
+            name: name.to_string(),
+            country: country.to_string(),
+            region: region.map(str::to_string),
+            postcode_patterns: vec![],
+            tax_class: class,
+            rate_ppm,
+            priority,
+            is_active: true,
+            created_at: OffsetDateTime::UNIX_EPOCH,
+            updated_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    fn dest(country: &str, region: Option<&str>) -> Destination {
+        Destination {
+            country: country.to_string(),
+            region: region.map(str::to_string),
+            postal_code: Some("10001".to_string()),
+        }
+    }
+
+    fn line(amount: i64, tax_class: TaxClass) -> TaxableLine {
+        TaxableLine { amount, tax_class }
+    }
+
+    #[test]
+    fn test_rate_selection() {
+        let mut city = rate("NYC", "US", Some("NY"), TaxClass::Standard, 45_000, 1);
+        city.postcode_patterns = vec!["100*".to_string()];
+        let rates = vec![
+            rate("US", "US", None, TaxClass::Standard, 10_000, 0),
+            rate("NY", "US", Some("ny"), TaxClass::Standard, 40_000, 0),
+            city,
+            rate("NL", "NL", None, TaxClass::Standard, 210_000, 0),
+            rate("NL low", "NL", None, TaxClass::Reduced, 90_000, 0),
+        ];
+
+        let names = |class, dest: &Destination| {
+            applicable_rates(&rates, class, dest)
+                .iter()
+                .map(|r| r.name.clone())
+                .collect::<Vec<_>>()
+        };
+        // The region beats the country; the city rate stacks on top
+        assert_eq!(
+            names(TaxClass::Standard, &dest("US", Some("NY"))),
+            vec!["NY", "NYC"]
+        );
+        assert_eq!(
+            names(TaxClass::Standard, &dest("US", Some("CA"))),
+            vec!["US"]
+        );
+        assert_eq!(names(TaxClass::Reduced, &dest("nl", None)), vec!["NL low"]);
+        assert!(names(TaxClass::Exempt, &dest("NL", None)).is_empty());
+        assert!(names(TaxClass::Standard, &dest("DE", None)).is_empty());
+    }
+
+    #[test]
+    fn test_exclusive_tax_rounding() {
+        let rates = vec![rate("VAT", "NL", None, TaxClass::Standard, 210_000, 0)];
+        let nl = dest("NL", None);
+        let lines = [
+            line(333, TaxClass::Standard),
+            line(333, TaxClass::Standard),
+            line(333, TaxClass::Standard),
+            line(500, TaxClass::Exempt),
+        ];
+
+        // 69.93 rounds to 70 on every line
+        let settings = TaxSettings::default();
+        let tax = calculate_tax(&settings, &rates, Some(&nl), &lines, 0).unwrap();
+        assert_eq!(tax.total, 210);
+        assert_eq!(tax.lines[0].amount, 70);
+        assert_eq!(tax.lines[3], LineTax::default());
+
+        // 209.79 rounds to 210 once; the lines share it
+        let settings = TaxSettings {
+            rounding: TaxRounding::Invoice,
+            ..TaxSettings::default()
+        };
+        let tax = calculate_tax(&settings, &rates, Some(&nl), &lines, 1000).unwrap();
+        assert_eq!(tax.total, 210 + 210);
+        assert_eq!(tax.shipping.amount, 210);
+        assert_eq!(tax.summary.len(), 1);
+        assert_eq!(tax.summary[0].taxable_amount, 999 + 1000);
+        let line_total: i64 = tax.lines.iter().map(|l| l.amount).sum();
+        assert_eq!(line_total, 210);
+
+        let untaxed = calculate_tax(&settings, &rates, None, &lines, 1000).unwrap();
+        assert_eq!(untaxed.total, 0);
+        assert!(untaxed.lines.iter().all(|l| l.lines.is_empty()));
+    }
+
+    #[test]
+    fn test_inclusive_tax_is_extracted() {
+        let rates = vec![rate("VAT", "NL", None, TaxClass::Standard, 210_000, 0)];
+        let settings = TaxSettings {
+            prices_include_tax: true,
+            shipping_taxable: false,
+            ..TaxSettings::default()
+        };
+
+        let tax = calculate_tax(
+            &settings,
+            &rates,
+            Some(&dest("NL", None)),
+            &[line(1210, TaxClass::Standard)],
+            500,
+        )
+        .unwrap();
+        assert_eq!(tax.total, 210);
+        assert_eq!(tax.lines[0].lines[0].taxable_amount, 1000);
+        assert_eq!(tax.shipping, LineTax::default());
+        assert!(tax.prices_include_tax);
+    }
+
+    #[test]
+    fn test_destination_falls_back_to_store() {
+        let config = serde_json::json!({
+            "ship_from": { "country": "nl", "postal_code": "1012 AB" }
+        });
+        let address = serde_json::json!({ "country": "DE", "region": "BE" });
+
+        assert_eq!(
+            tax_destination(Some(&address), &config).unwrap().country,
+            "DE"
+        );
+        assert_eq!(tax_destination(None, &config).unwrap().country, "NL");
+        assert!(tax_destination(None, &serde_json::json!({})).is_none());
+    }
+
+    #[test]
+    fn test_exemption_transitions() {
+        use TaxExemptionStatus as S;
+        assert!(S::Pending.can_become(S::Approved));
+        assert!(S::Pending.can_become(S::Rejected));
+        assert!(S::Approved.can_become(S::Revoked));
+        assert!(!S::Pending.can_become(S::Revoked));
+        assert!(!S::Rejected.can_become(S::Approved));
+        assert!(!S::Revoked.can_become(S::Approved));
+    }
+
+    #[test]
+    fn test_tax_too_large_for_minor_units_is_an_error() {
+        let rates = vec![rate("VAT", "NL", None, TaxClass::Standard, 1_000_000, 0)];
+        let settings = TaxSettings::default();
+        let nl = dest("NL", None);
+
+        // Each line's tax fits, their sum does not
+        let result = calculate_tax(
+            &settings,
+            &rates,
+            Some(&nl),
+            &[line(i64::MAX / 2 + 1, TaxClass::Standard); 2],
+            0,
+        );
+        assert!(matches!(result, Err(MoneyError::Overflow)));
+    }
+}
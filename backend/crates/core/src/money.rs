@@ -0,0 +1,565 @@
+//! Amounts of money: whole minor units (cents, pence, yen) of an ISO 4217
+//! currency.
+//!
+//! Arithmetic is checked: adding amounts in different currencies or
+//! overflowing `i64` is an error rather than a wrong total. Amounts are
+//! stored in BIGINT columns next to a `currency` column.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgRow, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Row, Type};
+use thiserror::Error;
+
+use crate::error::ApiError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MoneyError {
+    #[error("'{0}' is not an ISO 4217 currency code")]
+    InvalidCurrency(String),
+
+    #[error("Cannot combine {0} and {1} amounts")]
+    CurrencyMismatch(Currency, Currency),
+
+    #[error("Amount is too large")]
+    Overflow,
+
+    #[error("Invalid {0}")]
+    InvalidRate(&'static str),
+
+    #[error("Cannot allocate by weights that add up to zero")]
+    NoWeights,
+}
+
+impl From<MoneyError> for ApiError {
+    fn from(err: MoneyError) -> Self {
+        match err {
+            MoneyError::InvalidCurrency(_) | MoneyError::Overflow | MoneyError::InvalidRate(_) => {
+                ApiError::validation(err.to_string())
+            }
+            MoneyError::CurrencyMismatch(..) | MoneyError::NoWeights => {
+                ApiError::internal(err.to_string())
+            }
+        }
+    }
+}
+
+/// An ISO 4217 currency code, always upper case
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+/// Currencies without minor units
+const ZERO_DECIMALS: [&str; 17] = [
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND",
+    "VUV", "XAF", "XOF", "XPF",
+];
+/// Currencies with thousandths
+const THREE_DECIMALS: [&str; 7] = ["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const JPY: Currency = Currency(*b"JPY");
+
+    /// Parse a three-letter code, in any case
+    pub fn new(code: &str) -> Result<Self, MoneyError> {
+        match code.as_bytes() {
+            [a, b, c] if code.bytes().all(|b| b.is_ascii_alphabetic()) => Ok(Self([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+                c.to_ascii_uppercase(),
+            ])),
+            _ => Err(MoneyError::InvalidCurrency(code.to_string())),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        // Only ever built from ASCII letters
+        std::str::from_utf8(&self.0).unwrap_or("XXX")
+    }
+
+    /// Number of decimals of the minor unit: 2 for USD, 0 for JPY, 3 for KWD
+    pub fn exponent(&self) -> u32 {
+        let code = self.code();
+        if ZERO_DECIMALS.contains(&code) {
+            0
+        } else if THREE_DECIMALS.contains(&code) {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Minor units in one major unit: 100 for USD, 1 for JPY
+    pub fn minor_per_major(&self) -> i64 {
+        10_i64.pow(self.exponent())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Currency({})", self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Self::new(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Type<Postgres> for Currency {
+    fn type_info() -> PgTypeInfo {
+        <&str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <&str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for Currency {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <&str as Encode<Postgres>>::encode_by_ref(&self.code(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Currency {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let code = <&str as Decode<Postgres>>::decode(value)?;
+        Ok(Self::new(code)?)
+    }
+}
+
+/// How to round amounts that fall between two minor units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "VARCHAR", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Halves away from zero
+    #[default]
+    HalfUp,
+    /// Halves to the even neighbour (banker's rounding)
+    HalfEven,
+    /// Toward zero
+    Down,
+    /// Away from zero
+    Up,
+}
+
+impl RoundingMode {
+    /// `numerator / denominator`, rounded; the denominator must be positive
+    pub fn divide(self, numerator: i128, denominator: i128) -> i128 {
+        let quotient = numerator / denominator;
+        let remainder = numerator % denominator;
+        if remainder == 0 {
+            return quotient;
+        }
+        let away = if numerator < 0 { -1 } else { 1 };
+        let twice = remainder.abs() * 2;
+        let round_away = match self {
+            RoundingMode::Down => false,
+            RoundingMode::Up => true,
+            RoundingMode::HalfUp => twice >= denominator,
+            RoundingMode::HalfEven => {
+                twice > denominator || (twice == denominator && quotient % 2 != 0)
+            }
+        };
+        if round_away {
+            quotient + away
+        } else {
+            quotient
+        }
+    }
+}
+
+/// An amount in minor units of a currency.
+///
+/// Serialized as `{"amount": 1999, "currency": "USD"}`. Bound in queries as
+/// its amount alone; the currency lives in its own column.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    amount: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: i64, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Minor units
+    pub fn amount(&self) -> i64 {
+        self.amount
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount < 0
+    }
+
+    /// This amount, or zero in the same currency if it is negative
+    pub fn clamp_to_zero(self) -> Money {
+        if self.is_negative() {
+            Money::zero(self.currency)
+        } else {
+            self
+        }
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
+        }
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self
+            .amount
+            .checked_add(other.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Money, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self
+            .amount
+            .checked_sub(other.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Multiply by a whole number, e.g. a unit price by a quantity
+    pub fn checked_mul(self, factor: i64) -> Result<Money, MoneyError> {
+        let amount = self
+            .amount
+            .checked_mul(factor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Sum amounts in `currency`; zero when there are none
+    pub fn sum<I>(currency: Currency, amounts: I) -> Result<Money, MoneyError>
+    where
+        I: IntoIterator<Item = Money>,
+    {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(currency), Money::checked_add)
+    }
+
+    /// Multiply by `numerator / denominator`, e.g. a price by an exchange rate
+    /// or a percentage, rounding the result to a minor unit
+    pub fn mul_ratio(
+        self,
+        numerator: i64,
+        denominator: i64,
+        mode: RoundingMode,
+    ) -> Result<Money, MoneyError> {
+        if denominator == 0 {
+            return Err(MoneyError::InvalidRate("denominator"));
+        }
+        let (numerator, denominator) = if denominator < 0 {
+            (-i128::from(numerator), -i128::from(denominator))
+        } else {
+            (i128::from(numerator), i128::from(denominator))
+        };
+        let exact = i128::from(self.amount)
+            .checked_mul(numerator)
+            .ok_or(MoneyError::Overflow)?;
+        let amount =
+            i64::try_from(mode.divide(exact, denominator)).map_err(|_| MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Convert to another currency at `rate_ppm` millionths of a unit of
+    /// `to` per unit of this currency (1 USD = 0.92 EUR is 920000), taking
+    /// the exponents of both currencies into account
+    pub fn convert(
+        self,
+        to: Currency,
+        rate_ppm: i64,
+        mode: RoundingMode,
+    ) -> Result<Money, MoneyError> {
+        if rate_ppm <= 0 {
+            return Err(MoneyError::InvalidRate("exchange rate"));
+        }
+        let exact = i128::from(self.amount)
+            .checked_mul(i128::from(rate_ppm) * i128::from(to.minor_per_major()))
+            .ok_or(MoneyError::Overflow)?;
+        let denominator = 1_000_000 * i128::from(self.currency.minor_per_major());
+        let amount =
+            i64::try_from(mode.divide(exact, denominator)).map_err(|_| MoneyError::Overflow)?;
+        Ok(Self::new(amount, to))
+    }
+
+    /// Round to a multiple of `increment` minor units, e.g. 5 for cash
+    /// payments in CHF or 100 for whole dollars
+    pub fn round_to(self, increment: i64, mode: RoundingMode) -> Result<Money, MoneyError> {
+        if increment <= 0 {
+            return Err(MoneyError::InvalidRate("rounding increment"));
+        }
+        let units = mode.divide(i128::from(self.amount), i128::from(increment));
+        let amount =
+            i64::try_from(units * i128::from(increment)).map_err(|_| MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Split into parts proportional to `weights` that add up to exactly this
+    /// amount; left-over minor units go to the parts with the largest
+    /// remainders, earlier parts first on ties
+    pub fn allocate(self, weights: &[i64]) -> Result<Vec<Money>, MoneyError> {
+        if weights.iter().any(|weight| *weight < 0) {
+            return Err(MoneyError::NoWeights);
+        }
+        let total: i128 = weights.iter().map(|weight| i128::from(*weight)).sum();
+        if total == 0 {
+            return Err(MoneyError::NoWeights);
+        }
+
+        let amount = i128::from(self.amount);
+        let mut parts: Vec<i128> = Vec::with_capacity(weights.len());
+        let mut remainders: Vec<(i128, usize)> = Vec::with_capacity(weights.len());
+        for (i, weight) in weights.iter().enumerate() {
+            let exact = amount * i128::from(*weight);
+            parts.push(exact.div_euclid(total));
+            remainders.push((exact.rem_euclid(total), i));
+        }
+
+        let mut left = amount - parts.iter().sum::<i128>();
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, i) in remainders {
+            if left == 0 {
+                break;
+            }
+            parts[i] += 1;
+            left -= 1;
+        }
+
+        Ok(parts
+            .into_iter()
+            // Each part is at most the whole amount in size
+            .map(|part| Self::new(part as i64, self.currency))
+            .collect())
+    }
+
+    /// Split into `n` parts as equal as possible
+    pub fn split(self, n: usize) -> Result<Vec<Money>, MoneyError> {
+        self.allocate(&vec![1; n])
+    }
+
+    /// Decode an amount column of a row whose currency is known
+    pub fn from_row(row: &PgRow, column: &str, currency: Currency) -> sqlx::Result<Money> {
+        Ok(Self::new(row.try_get(column)?, currency))
+    }
+
+    /// Decode a nullable amount column of a row whose currency is known
+    pub fn from_row_optional(
+        row: &PgRow,
+        column: &str,
+        currency: Currency,
+    ) -> sqlx::Result<Option<Money>> {
+        let amount: Option<i64> = row.try_get(column)?;
+        Ok(amount.map(|amount| Self::new(amount, currency)))
+    }
+}
+
+/// `12.34 USD`, `1234 JPY`
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let exponent = self.currency.exponent();
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let units = self.amount.unsigned_abs();
+        if exponent == 0 {
+            return write!(f, "{}{} {}", sign, units, self.currency);
+        }
+        let scale = 10_u64.pow(exponent);
+        write!(
+            f,
+            "{}{}.{:0width$} {}",
+            sign,
+            units / scale,
+            units % scale,
+            self.currency,
+            width = exponent as usize
+        )
+    }
+}
+
+impl fmt::Debug for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Money({})", self)
+    }
+}
+
+impl Type<Postgres> for Money {
+    fn type_info() -> PgTypeInfo {
+        <i64 as Type<Postgres>>::type_info()
+    }
+}
+
+impl Encode<'_, Postgres> for Money {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <i64 as Encode<Postgres>>::encode_by_ref(&self.amount, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(amount: i64) -> Money {
+        Money::new(amount, Currency::USD)
+    }
+
+    #[test]
+    fn test_currency_codes_and_exponents() {
+        assert_eq!(Currency::new("usd").unwrap(), Currency::USD);
+        assert!(Currency::new("US").is_err());
+        assert!(Currency::new("U$D").is_err());
+        assert_eq!(Currency::USD.exponent(), 2);
+        assert_eq!(Currency::JPY.exponent(), 0);
+        assert_eq!(Currency::new("KWD").unwrap().exponent(), 3);
+
+        assert_eq!(usd(1999).to_string(), "19.99 USD");
+        assert_eq!(usd(-5).to_string(), "-0.05 USD");
+        assert_eq!(Money::new(1999, Currency::JPY).to_string(), "1999 JPY");
+
+        let json = serde_json::to_value(usd(1999)).unwrap();
+        assert_eq!(json, serde_json::json!({"amount": 1999, "currency": "USD"}));
+        assert_eq!(serde_json::from_value::<Money>(json).unwrap(), usd(1999));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(usd(150).checked_add(usd(50)).unwrap(), usd(200));
+        assert_eq!(usd(150).checked_sub(usd(200)).unwrap(), usd(-50));
+        assert_eq!(
+            usd(100).checked_add(Money::new(100, Currency::EUR)),
+            Err(MoneyError::CurrencyMismatch(Currency::USD, Currency::EUR))
+        );
+        assert_eq!(usd(i64::MAX).checked_add(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(
+            usd(100).checked_sub(usd(150)).unwrap().clamp_to_zero(),
+            usd(0)
+        );
+        assert_eq!(usd(100).clamp_to_zero(), usd(100));
+        assert_eq!(usd(i64::MAX / 2).checked_mul(3), Err(MoneyError::Overflow));
+        // Large B2B lines no longer overflow i32
+        let line = usd(2_500_000).checked_mul(1_000).unwrap();
+        assert_eq!(line.amount(), 2_500_000_000);
+
+        assert_eq!(
+            Money::sum(Currency::USD, [usd(1), usd(2), usd(3)]).unwrap(),
+            usd(6)
+        );
+        assert_eq!(Money::sum(Currency::USD, []).unwrap(), usd(0));
+    }
+
+    #[test]
+    fn test_rounding() {
+        // 10% of 1.25 is 0.125
+        assert_eq!(
+            usd(125).mul_ratio(1, 10, RoundingMode::HalfUp).unwrap(),
+            usd(13)
+        );
+        assert_eq!(
+            usd(125).mul_ratio(1, 10, RoundingMode::HalfEven).unwrap(),
+            usd(12)
+        );
+        assert_eq!(
+            usd(125).mul_ratio(1, 10, RoundingMode::Down).unwrap(),
+            usd(12)
+        );
+        assert_eq!(
+            usd(121).mul_ratio(1, 10, RoundingMode::Up).unwrap(),
+            usd(13)
+        );
+        assert_eq!(
+            usd(-125).mul_ratio(1, 10, RoundingMode::HalfUp).unwrap(),
+            usd(-13)
+        );
+
+        assert_eq!(
+            usd(1234).round_to(5, RoundingMode::HalfUp).unwrap(),
+            usd(1235)
+        );
+        assert_eq!(
+            usd(1249).round_to(100, RoundingMode::Down).unwrap(),
+            usd(1200)
+        );
+    }
+
+    #[test]
+    fn test_conversion() {
+        // 1 USD = 0.921345 EUR
+        let eur = usd(1999).convert(Currency::EUR, 921_345, RoundingMode::HalfUp);
+        assert_eq!(eur.unwrap(), Money::new(1842, Currency::EUR));
+        // 1 USD = 151.23 JPY: cents to whole yen
+        let jpy = usd(1999).convert(Currency::JPY, 151_230_000, RoundingMode::HalfUp);
+        assert_eq!(jpy.unwrap(), Money::new(3023, Currency::JPY));
+        // And back: yen to cents
+        let back =
+            Money::new(3023, Currency::JPY).convert(Currency::USD, 6_612, RoundingMode::HalfUp);
+        assert_eq!(back.unwrap(), usd(1999));
+
+        assert_eq!(
+            usd(100).convert(Currency::EUR, 0, RoundingMode::HalfUp),
+            Err(MoneyError::InvalidRate("exchange rate"))
+        );
+    }
+
+    #[test]
+    fn test_allocation() {
+        let parts = usd(100).split(3).unwrap();
+        assert_eq!(parts, vec![usd(34), usd(33), usd(33)]);
+
+        // Proportional to line totals, and always adding up
+        let parts = usd(1000).allocate(&[4500, 1000, 0]).unwrap();
+        assert_eq!(parts, vec![usd(818), usd(182), usd(0)]);
+        let parts = usd(-100).split(3).unwrap();
+        assert_eq!(parts.iter().map(Money::amount).sum::<i64>(), -100);
+
+        assert_eq!(usd(100).allocate(&[0, 0]), Err(MoneyError::NoWeights));
+    }
+}
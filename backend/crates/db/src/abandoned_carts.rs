@@ -0,0 +1,321 @@
+use goseli_core::{dto::AbandonedCartStats, models::AbandonedCart, ApiError, Money, Result};
+use sqlx::{Executor, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Mark idle carts as abandoned.
+///
+/// A cart qualifies when it has items, no activity since `idle_before`, a
+/// reachable email (the user's account email or one left by a guest), and
+/// has not already been recorded for the current idle period.
+pub async fn mark_idle_carts_abandoned(
+    pool: &PgPool,
+    store_id: Uuid,
+    idle_before: OffsetDateTime,
+) -> Result<Vec<AbandonedCart>> {
+    let abandoned = sqlx::query_as::<_, AbandonedCart>(
+        r#"
+        INSERT INTO abandoned_carts (store_id, cart_id, user_id, email, cart_total, item_count)
+        SELECT
+            c.store_id,
+            c.id,
+            c.user_id,
+            COALESCE(u.email, c.email),
+            totals.total,
+            totals.item_count
+        FROM carts c
+        LEFT JOIN users u ON c.user_id = u.id
+        CROSS JOIN LATERAL (
+            SELECT
+                COALESCE(SUM(COALESCE(pv.price, p.price) * ci.quantity), 0)::BIGINT as total,
+                COALESCE(SUM(ci.quantity), 0)::INTEGER as item_count
+            FROM cart_items ci
+            INNER JOIN products p ON ci.product_id = p.id
+            LEFT JOIN product_variants pv ON ci.variant_id = pv.id
+            WHERE ci.cart_id = c.id
+        ) totals
+        WHERE c.store_id = $1
+          AND c.last_activity_at < $2
+          AND COALESCE(u.email, c.email) IS NOT NULL
+          AND totals.item_count > 0
+          AND NOT EXISTS (
+              SELECT 1 FROM abandoned_carts a
+              WHERE a.cart_id = c.id
+                AND (a.status = 'abandoned' OR a.abandoned_at >= c.last_activity_at)
+          )
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(idle_before)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(abandoned)
+}
+
+/// Close abandonments whose cart saw new activity (the customer came back on their own)
+pub async fn close_reactivated(pool: &PgPool, store_id: Uuid) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE abandoned_carts a SET
+            status = 'recovered',
+            recovered_at = NOW(),
+            recovered_cart_id = a.cart_id
+        FROM carts c
+        WHERE a.cart_id = c.id
+          AND a.store_id = $1
+          AND a.status = 'abandoned'
+          AND c.last_activity_at > a.abandoned_at
+        "#,
+    )
+    .bind(store_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Give up on abandonments older than `abandoned_before`
+pub async fn expire_stale(
+    pool: &PgPool,
+    store_id: Uuid,
+    abandoned_before: OffsetDateTime,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE abandoned_carts SET status = 'expired'
+        WHERE store_id = $1 AND status = 'abandoned' AND abandoned_at < $2
+        "#,
+    )
+    .bind(store_id)
+    .bind(abandoned_before)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// List open abandonments for a store, oldest first
+pub async fn list_open(pool: &PgPool, store_id: Uuid, limit: i64) -> Result<Vec<AbandonedCart>> {
+    let abandoned = sqlx::query_as::<_, AbandonedCart>(
+        r#"
+        SELECT * FROM abandoned_carts
+        WHERE store_id = $1 AND status = 'abandoned'
+        ORDER BY abandoned_at ASC
+        LIMIT $2
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(abandoned)
+}
+
+/// List abandonments for a store (admin view), newest first
+pub async fn list_abandoned_carts(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<AbandonedCart>> {
+    let abandoned = sqlx::query_as::<_, AbandonedCart>(
+        r#"
+        SELECT * FROM abandoned_carts
+        WHERE store_id = $1
+        ORDER BY abandoned_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(abandoned)
+}
+
+/// Count abandonments for a store
+pub async fn count_abandoned_carts(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM abandoned_carts WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count.0)
+}
+
+/// Get an abandonment record by ID
+pub async fn get_abandoned_cart(pool: &PgPool, id: Uuid) -> Result<AbandonedCart> {
+    let abandoned =
+        sqlx::query_as::<_, AbandonedCart>("SELECT * FROM abandoned_carts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Abandoned cart not found"))?;
+
+    Ok(abandoned)
+}
+
+/// Claim the next recovery message in the sequence.
+///
+/// Only succeeds while the abandonment is still open and has sent exactly
+/// `messages_sent` messages, so when several job runners race for the same
+/// step only one of them gets it. Returns None when the step was already
+/// taken; inside a transaction the claim is undone if the send is rolled back.
+pub async fn claim_next_message<'e, E>(
+    executor: E,
+    id: Uuid,
+    messages_sent: i32,
+) -> Result<Option<AbandonedCart>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let abandoned = sqlx::query_as::<_, AbandonedCart>(
+        r#"
+        UPDATE abandoned_carts SET
+            messages_sent = messages_sent + 1,
+            last_message_at = NOW()
+        WHERE id = $1 AND status = 'abandoned' AND messages_sent = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(messages_sent)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(abandoned)
+}
+
+/// Mark an abandonment as recovered into `recovered_cart_id`
+pub async fn mark_recovered(pool: &PgPool, id: Uuid, recovered_cart_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE abandoned_carts SET
+            status = 'recovered',
+            recovered_at = COALESCE(recovered_at, NOW()),
+            recovered_cart_id = $2
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(recovered_cart_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Attribute an order's revenue to the recovery that produced `cart_id`.
+///
+/// Called when an order is placed; does nothing if the cart was not
+/// recovered. Only the first order after the recovery is credited.
+pub async fn record_recovered_revenue(
+    pool: &PgPool,
+    cart_id: Uuid,
+    order_id: Uuid,
+    amount: Money,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE abandoned_carts SET
+            recovered_order_id = $2,
+            recovered_revenue = COALESCE(recovered_revenue, 0) + $3
+        WHERE recovered_cart_id = $1 AND status = 'recovered' AND recovered_order_id IS NULL
+        "#,
+    )
+    .bind(cart_id)
+    .bind(order_id)
+    .bind(amount)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Aggregate recovery metrics for a store
+pub async fn get_stats(pool: &PgPool, store_id: Uuid) -> Result<AbandonedCartStats> {
+    let stats = sqlx::query_as::<_, AbandonedCartStats>(
+        r#"
+        SELECT
+            COUNT(*) as abandoned_count,
+            COUNT(*) FILTER (WHERE status = 'recovered') as recovered_count,
+            COUNT(*) FILTER (WHERE status = 'expired') as expired_count,
+            COALESCE(SUM(messages_sent), 0)::BIGINT as messages_sent,
+            COALESCE(SUM(cart_total), 0)::BIGINT as abandoned_value,
+            COALESCE(SUM(recovered_revenue), 0)::BIGINT as recovered_revenue
+        FROM abandoned_carts
+        WHERE store_id = $1
+        "#,
+    )
+    .bind(store_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{self, NewOrder};
+    use crate::test_support;
+    use time::Duration;
+
+    async fn order_from(pool: &PgPool, cart_id: Uuid, product_id: Uuid, quantity: i32) {
+        test_support::cart_item(pool, cart_id, product_id, quantity).await;
+        let order = orders::place_order(
+            pool,
+            cart_id,
+            &NewOrder {
+                email: "ada@example.com".into(),
+                ..NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+        record_recovered_revenue(pool, cart_id, order.id, order.total)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_only_the_first_order_counts_as_recovered(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 2000, 10).await;
+        let now = OffsetDateTime::now_utc();
+        let cart_id = test_support::cart(&pool, store_id, None, now - Duration::days(1)).await;
+        test_support::cart_item(&pool, cart_id, product_id, 1).await;
+        sqlx::query("UPDATE carts SET email = 'ada@example.com' WHERE id = $1")
+            .bind(cart_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let abandoned = mark_idle_carts_abandoned(&pool, store_id, now - Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(abandoned.len(), 1);
+        mark_recovered(&pool, abandoned[0].id, cart_id)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM cart_items WHERE cart_id = $1")
+            .bind(cart_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        order_from(&pool, cart_id, product_id, 2).await;
+        order_from(&pool, cart_id, product_id, 3).await;
+
+        let stats = get_stats(&pool, store_id).await.unwrap();
+        assert_eq!(stats.recovered_count, 1);
+        assert_eq!(stats.recovered_revenue, 4000);
+        let recovered = get_abandoned_cart(&pool, abandoned[0].id).await.unwrap();
+        assert!(recovered.recovered_order_id.is_some());
+    }
+}
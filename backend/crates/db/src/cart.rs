@@ -1,9 +1,21 @@
+use std::collections::HashMap;
+
 use goseli_core::{
-    dto::{CartItemResponse, CartResponse},
-    models::{Cart, CartItem},
-    ApiError, Result,
+    dto::{
+        AppliedCoupon, BulkCartAction, BulkCartLineResult, BulkCartMode, BulkCartOperation,
+        BulkLineError, BulkLineStatus, CartItemResponse, CartResponse,
+    },
+    models::{
+        customization, price_lines,
+        promotion::discount_cart,
+        tax::{calculate_tax, tax_destination, LineTax},
+        Cart, CartContext, CartItem, GiftProduct, LinePart, LineToPrice, PromotionLine,
+        QuantityRules, TaxClass, TaxSettings, TaxableLine,
+    },
+    ApiError, Currency, Money, Result,
 };
-use sqlx::PgPool;
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 /// Helper struct for querying enriched cart items from the database
@@ -16,20 +28,22 @@ struct CartItemRow {
     product_slug: String,
     product_image_url: Option<String>,
     variant_name: Option<String>,
-    price: i32,
+    properties: serde_json::Value,
+    price: i64,
+    price_modifier: i64,
     quantity: i32,
-    subtotal: i32,
+    tax_class: TaxClass,
+    category_id: Option<Uuid>,
 }
 
-/// Get or create a cart for a user or session
-pub async fn get_or_create_cart(
+/// Find the existing cart for a user or session, without creating one
+pub async fn find_cart(
     pool: &PgPool,
     store_id: Uuid,
     user_id: Option<Uuid>,
-    session_id: Option<String>,
-) -> Result<Cart> {
-    // Try to find existing cart
-    let existing_cart = if let Some(uid) = user_id {
+    session_id: Option<&str>,
+) -> Result<Option<Cart>> {
+    let cart = if let Some(uid) = user_id {
         sqlx::query_as::<_, Cart>(
             "SELECT * FROM carts WHERE store_id = $1 AND user_id = $2 LIMIT 1",
         )
@@ -37,7 +51,7 @@ pub async fn get_or_create_cart(
         .bind(uid)
         .fetch_optional(pool)
         .await?
-    } else if let Some(ref sid) = session_id {
+    } else if let Some(sid) = session_id {
         sqlx::query_as::<_, Cart>(
             "SELECT * FROM carts WHERE store_id = $1 AND session_id = $2 LIMIT 1",
         )
@@ -49,6 +63,19 @@ pub async fn get_or_create_cart(
         None
     };
 
+    Ok(cart)
+}
+
+/// Get or create a cart for a user or session
+pub async fn get_or_create_cart(
+    pool: &PgPool,
+    store_id: Uuid,
+    user_id: Option<Uuid>,
+    session_id: Option<String>,
+) -> Result<Cart> {
+    // Try to find existing cart
+    let existing_cart = find_cart(pool, store_id, user_id, session_id.as_deref()).await?;
+
     if let Some(cart) = existing_cart {
         return Ok(cart);
     }
@@ -74,6 +101,80 @@ pub async fn get_or_create_cart(
 
 /// Get cart with enriched items (joined with product data)
 pub async fn get_cart_with_items(pool: &PgPool, cart_id: Uuid) -> Result<CartResponse> {
+    get_cart_in_currency(pool, cart_id, None).await
+}
+
+/// Get cart with enriched items, priced in `currency` (the store currency
+/// when None)
+pub async fn get_cart_in_currency(
+    pool: &PgPool,
+    cart_id: Uuid,
+    currency: Option<Currency>,
+) -> Result<CartResponse> {
+    let (cart, _) = build_cart(pool, cart_id, currency).await?;
+    Ok(cart)
+}
+
+/// Enter a coupon code on a cart. The code is only kept if the coupon
+/// applies to the cart as it is; otherwise the reason is returned and the
+/// previous code, if any, stays.
+pub async fn apply_coupon(
+    pool: &PgPool,
+    cart_id: Uuid,
+    code: &str,
+    currency: Option<Currency>,
+) -> Result<CartResponse> {
+    let cart = sqlx::query_as::<_, Cart>("SELECT * FROM carts WHERE id = $1")
+        .bind(cart_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Cart not found"))?;
+
+    let mut conn = pool.acquire().await?;
+    let (coupon, generated) = crate::coupons::resolve_coupon(
+        &mut conn,
+        cart.store_id,
+        code,
+        cart.user_id,
+        cart.email.as_deref(),
+        false,
+    )
+    .await?;
+    drop(conn);
+
+    set_coupon_code(
+        pool,
+        cart_id,
+        Some(&crate::coupons::redeemed_code(&coupon, generated.as_ref())),
+    )
+    .await?;
+    let (response, coupon_error) = build_cart(pool, cart_id, currency).await?;
+    if let Some(error) = coupon_error {
+        set_coupon_code(pool, cart_id, cart.coupon_code.as_deref()).await?;
+        return Err(error);
+    }
+
+    Ok(response)
+}
+
+/// Set or clear the coupon code on a cart
+pub async fn set_coupon_code(pool: &PgPool, cart_id: Uuid, code: Option<&str>) -> Result<()> {
+    sqlx::query("UPDATE carts SET coupon_code = $1, last_activity_at = NOW() WHERE id = $2")
+        .bind(code)
+        .bind(cart_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// The cart response, and why its coupon does not apply if it has one that
+/// does not
+async fn build_cart(
+    pool: &PgPool,
+    cart_id: Uuid,
+    currency: Option<Currency>,
+) -> Result<(CartResponse, Option<ApiError>)> {
     // Get the cart
     let cart = sqlx::query_as::<_, Cart>("SELECT * FROM carts WHERE id = $1")
         .bind(cart_id)
@@ -92,9 +193,12 @@ pub async fn get_cart_with_items(pool: &PgPool, cart_id: Uuid) -> Result<CartRes
             p.slug as product_slug,
             (SELECT url FROM product_images WHERE product_id = p.id AND is_primary = true LIMIT 1) as product_image_url,
             pv.name as variant_name,
+            ci.properties,
             COALESCE(pv.price, p.price) as price,
+            ci.price_modifier,
             ci.quantity,
-            (COALESCE(pv.price, p.price) * ci.quantity) as subtotal
+            p.tax_class,
+            p.category_id
         FROM cart_items ci
         INNER JOIN products p ON ci.product_id = p.id
         LEFT JOIN product_variants pv ON ci.variant_id = pv.id
@@ -106,92 +210,434 @@ pub async fn get_cart_with_items(pool: &PgPool, cart_id: Uuid) -> Result<CartRes
     .fetch_all(pool)
     .await?;
 
+    let (base, config): (Currency, serde_json::Value) =
+        sqlx::query_as("SELECT currency, config FROM stores WHERE id = $1")
+            .bind(cart.store_id)
+            .fetch_one(pool)
+            .await?;
+    let now = OffsetDateTime::now_utc();
+    let promotions = crate::promotions::list_live_promotions(pool, cart.store_id, now).await?;
+    let gift_rows = crate::promotions::load_gifts(pool, &promotions).await?;
+    let product_ids: Vec<Uuid> = rows
+        .iter()
+        .map(|row| row.product_id)
+        .chain(gift_rows.iter().map(|gift| gift.product_id))
+        .collect();
+    let customer_group = crate::promotions::customer_group(pool, cart.user_id).await?;
+    let pricing = crate::currencies::display_pricing(
+        pool,
+        cart.store_id,
+        base,
+        currency,
+        customer_group.as_deref(),
+        &product_ids,
+    )
+    .await?;
+    let currency = pricing.currency();
+    // The address entered at checkout, if the customer got that far
+    let shipping_address: Option<serde_json::Value> = sqlx::query_scalar(
+        r#"
+        SELECT shipping_address FROM checkout_sessions
+        WHERE cart_id = $1 AND status = 'open' AND shipping_address IS NOT NULL
+        ORDER BY updated_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(cart_id)
+    .fetch_optional(pool)
+    .await?;
+    let exemption = match cart.user_id {
+        Some(user_id) => crate::taxes::effective_exemption(pool, user_id, now).await?,
+        None => None,
+    };
+    let tax_rates = match exemption {
+        Some(_) => vec![],
+        None => crate::taxes::list_rates(pool, cart.store_id, true).await?,
+    };
+    // Stock counts every unit of a product or variant, across differently
+    // customized lines
+    let mut in_cart: HashMap<(Uuid, Option<Uuid>), i64> = HashMap::new();
+    for row in &rows {
+        *in_cart.entry((row.product_id, row.variant_id)).or_insert(0) += i64::from(row.quantity);
+    }
+
+    // Customization surcharges are converted but not rounded like price
+    // tags. Lines are split at a sale's cap the way the order will be, and
+    // promotions and tax apply to the parts.
+    let to_price = rows
+        .iter()
+        .map(|row| {
+            Ok(LineToPrice {
+                product_id: row.product_id,
+                variant_id: row.variant_id,
+                quantity: row.quantity,
+                price: Money::new(row.price, base),
+                surcharge: pricing.convert(Money::new(row.price_modifier, base))?,
+            })
+        })
+        .collect::<Result<Vec<LineToPrice>>>()?;
+    let priced = price_lines(&pricing, &to_price)?;
+    let subtotals = rows
+        .iter()
+        .zip(&priced.prices)
+        .map(|(row, price)| price.subtotal(row.quantity))
+        .collect::<std::result::Result<Vec<Money>, _>>()?;
+    let part_totals = priced
+        .parts
+        .iter()
+        .map(LinePart::total)
+        .collect::<std::result::Result<Vec<Money>, _>>()?;
+
+    // Gifts come out of the stock the cart leaves
+    let gifts = gift_rows
+        .iter()
+        .map(|gift| gift.gift(&pricing, &in_cart))
+        .collect::<Result<Vec<GiftProduct>>>()?;
+    let lines: Vec<PromotionLine> = priced
+        .parts
+        .iter()
+        .zip(&part_totals)
+        .map(|(part, total)| {
+            let row = &rows[part.line];
+            PromotionLine {
+                product_id: row.product_id,
+                variant_id: row.variant_id,
+                category_id: row.category_id,
+                name: row.product_name.clone(),
+                quantity: part.quantity,
+                amount: *total,
+            }
+        })
+        .collect();
+
+    // A coupon that no longer applies stays on the cart, shown as not applied
+    let mut coupon_error = None;
+    let found = match &cart.coupon_code {
+        Some(code) => {
+            let mut conn = pool.acquire().await?;
+            match crate::coupons::resolve_coupon(
+                &mut conn,
+                cart.store_id,
+                code,
+                cart.user_id,
+                cart.email.as_deref(),
+                false,
+            )
+            .await
+            {
+                Ok((found, _)) => Some(found),
+                Err(e @ ApiError::Rule(..)) => {
+                    coupon_error = Some(e);
+                    None
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        None => None,
+    };
+    let ctx = CartContext {
+        lines: &lines,
+        customer_group: customer_group.as_deref(),
+        gifts: &gifts,
+        now,
+    };
+    let mut discounts = discount_cart(&promotions, found.as_ref(), &ctx, &pricing)?;
+    let discount_total = discounts.total()?;
+    let coupon = match (&cart.coupon_code, discounts.coupon.take()) {
+        (Some(code), Some(Ok(discount))) => Some(AppliedCoupon {
+            code: code.clone(),
+            description: found.and_then(|found| found.description),
+            applied: true,
+            message: None,
+            explanation: Some(discount.explanation),
+            discount_total: discount.total,
+            free_shipping: discount.free_shipping,
+        }),
+        (Some(code), result) => {
+            if let Some(Err(e)) = result {
+                coupon_error = Some(e);
+            }
+            Some(AppliedCoupon {
+                code: code.clone(),
+                description: None,
+                applied: false,
+                message: coupon_error.as_ref().map(ApiError::public_message),
+                explanation: None,
+                discount_total: Money::zero(currency),
+                free_shipping: false,
+            })
+        }
+        (None, _) => None,
+    };
+
+    // Tax is charged on what is left after the discount
+    let taxable = priced
+        .parts
+        .iter()
+        .zip(&part_totals)
+        .zip(&discounts.lines)
+        .map(|((part, total), discount)| {
+            Ok(TaxableLine {
+                amount: total.checked_sub(*discount)?.clamp_to_zero().amount(),
+                tax_class: rows[part.line].tax_class,
+            })
+        })
+        .collect::<Result<Vec<TaxableLine>>>()?;
+    let tax = calculate_tax(
+        &TaxSettings::from_store_config(&config),
+        &tax_rates,
+        tax_destination(shipping_address.as_ref(), &config).as_ref(),
+        &taxable,
+        0,
+    )?;
+
+    // Parts add back up to their cart line
+    let mut discounts_by_line = vec![Money::zero(currency); rows.len()];
+    let mut tax_by_line = vec![LineTax::default(); rows.len()];
+    for ((part, discount), part_tax) in priced.parts.iter().zip(&discounts.lines).zip(tax.lines) {
+        discounts_by_line[part.line] = discounts_by_line[part.line].checked_add(*discount)?;
+        let line_tax = &mut tax_by_line[part.line];
+        line_tax.amount += part_tax.amount;
+        for tax_line in part_tax.lines {
+            match line_tax
+                .lines
+                .iter_mut()
+                .find(|total| total.rate_id == tax_line.rate_id)
+            {
+                Some(total) => {
+                    total.taxable_amount += tax_line.taxable_amount;
+                    total.amount += tax_line.amount;
+                }
+                None => line_tax.lines.push(tax_line),
+            }
+        }
+    }
+
     // Convert rows to CartItemResponse
     let items: Vec<CartItemResponse> = rows
         .into_iter()
-        .map(|row| CartItemResponse {
-            id: row.id,
-            product_id: row.product_id,
-            variant_id: row.variant_id,
-            product_name: row.product_name,
-            product_slug: row.product_slug,
-            product_image_url: row.product_image_url,
-            variant_name: row.variant_name,
-            price: row.price,
-            quantity: row.quantity,
-            subtotal: row.subtotal,
-        })
+        .zip(priced.prices)
+        .zip(subtotals)
+        .zip(discounts_by_line)
+        .zip(tax_by_line)
+        .map(
+            |((((row, price), subtotal), discount), line_tax)| CartItemResponse {
+                id: row.id,
+                product_id: row.product_id,
+                variant_id: row.variant_id,
+                product_name: row.product_name,
+                product_slug: row.product_slug,
+                product_image_url: row.product_image_url,
+                variant_name: row.variant_name,
+                properties: row.properties,
+                price: price.unit_price,
+                compare_at_price: (price.sale_units > 0).then_some(price.regular_price),
+                sale_quantity: (price.sale_units > 0).then_some(price.sale_units),
+                quantity: row.quantity,
+                subtotal,
+                discount_total: discount,
+                tax_total: Money::new(line_tax.amount, currency),
+                tax_lines: line_tax.lines,
+            },
+        )
         .collect();
 
-    let total: i32 = items.iter().map(|item| item.subtotal).sum();
-    let item_count: i32 = items.iter().map(|item| item.quantity).sum();
+    let subtotal = Money::sum(currency, items.iter().map(|item| item.subtotal))?;
+    let tax_total = Money::new(tax.total, currency);
+    let discounted = subtotal.checked_sub(discount_total)?;
+    let total = if tax.prices_include_tax {
+        discounted
+    } else {
+        discounted.checked_add(tax_total)?
+    };
+    let item_count = count_items(items.iter().map(|item| item.quantity))?;
 
-    Ok(CartResponse {
-        id: cart.id,
+    let response = CartResponse {
+        id: Some(cart.id),
+        email: cart.email,
         items,
+        subtotal,
+        discount_total,
+        promotions: discounts.promotions,
+        free_items: discounts.free_items,
+        coupon,
+        tax_total,
+        tax_lines: tax.summary,
+        prices_include_tax: tax.prices_include_tax,
+        tax_exempt: exemption.is_some(),
         total,
         item_count,
-    })
+    };
+    Ok((response, coupon_error))
 }
 
-/// Add item to cart (UPSERT: if same product+variant exists, add quantity)
-pub async fn add_item(
-    pool: &PgPool,
-    cart_id: Uuid,
+/// Stock and purchase limits that apply to a product+variant
+#[derive(sqlx::FromRow)]
+struct LineLimits {
+    product_name: String,
+    stock_quantity: i32,
+    #[sqlx(flatten)]
+    rules: QuantityRules,
+}
+
+/// Load stock and quantity rules for a product, or for a variant (whose rules
+/// override the product's field by field)
+async fn load_line_limits(
+    conn: &mut PgConnection,
     product_id: Uuid,
     variant_id: Option<Uuid>,
-    quantity: i32,
-) -> Result<CartItem> {
-    // First check stock availability
-    let (available_stock, product_name) = if let Some(vid) = variant_id {
-        sqlx::query_as::<_, (i32, String)>(
-            "SELECT pv.stock_quantity, p.name FROM product_variants pv
+) -> Result<LineLimits> {
+    if let Some(vid) = variant_id {
+        sqlx::query_as::<_, LineLimits>(
+            "SELECT p.name as product_name, pv.stock_quantity,
+                    COALESCE(pv.min_quantity, p.min_quantity) as min_quantity,
+                    COALESCE(pv.max_quantity, p.max_quantity) as max_quantity,
+                    COALESCE(pv.quantity_step, p.quantity_step) as quantity_step,
+                    COALESCE(pv.max_per_customer, p.max_per_customer) as max_per_customer
+             FROM product_variants pv
              INNER JOIN products p ON pv.product_id = p.id
              WHERE pv.id = $1",
         )
         .bind(vid)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?
-        .ok_or_else(|| ApiError::not_found("Product variant not found"))?
+        .ok_or_else(|| ApiError::not_found("Product variant not found"))
     } else {
-        sqlx::query_as::<_, (i32, String)>(
-            "SELECT stock_quantity, name FROM products WHERE id = $1",
+        sqlx::query_as::<_, LineLimits>(
+            "SELECT name as product_name, stock_quantity,
+                    min_quantity, max_quantity, quantity_step, max_per_customer
+             FROM products WHERE id = $1",
         )
         .bind(product_id)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?
-        .ok_or_else(|| ApiError::not_found("Product not found"))?
-    };
+        .ok_or_else(|| ApiError::not_found("Product not found"))
+    }
+}
 
-    // Check if item already exists in cart
-    let existing_quantity: Option<i32> = sqlx::query_scalar(
-        "SELECT quantity FROM cart_items
+/// What the cart's owner bought in past orders; only looked up when a
+/// per-customer limit applies
+async fn previously_purchased(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    line: &LineLimits,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<i64> {
+    if line.rules.max_per_customer.is_none() {
+        return Ok(0);
+    }
+
+    let cart = sqlx::query_as::<_, Cart>("SELECT * FROM carts WHERE id = $1")
+        .bind(cart_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    crate::orders::purchased_quantity(
+        conn,
+        cart.store_id,
+        cart.user_id,
+        cart.email.as_deref(),
+        product_id,
+        variant_id,
+    )
+    .await
+}
+
+/// Units across cart or order lines
+pub(crate) fn count_items(quantities: impl IntoIterator<Item = i32>) -> Result<i32> {
+    quantities
+        .into_iter()
+        .try_fold(0i32, |count, quantity| count.checked_add(quantity))
+        .ok_or_else(|| ApiError::validation("Too many items to count in one cart"))
+}
+
+/// Hash of a line's properties, part of the cart line uniqueness key
+fn properties_hash(properties: &serde_json::Value) -> String {
+    use sha2::{Digest, Sha256};
+
+    if properties.as_object().is_some_and(|map| map.is_empty()) {
+        return String::new();
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(customization::canonical_json(properties).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Add item to cart (UPSERT: if the same product+variant+properties line exists, add quantity)
+pub async fn add_item(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i32,
+    properties: &serde_json::Value,
+) -> Result<CartItem> {
+    // Validate customizations against the product's (or store's) options
+    let (attributes, product_schema, price, currency) =
+        sqlx::query_as::<_, (serde_json::Value, Option<serde_json::Value>, i64, Currency)>(
+            "SELECT p.attributes, s.config->'product_schema',
+                COALESCE(pv.price, p.price), s.currency
+         FROM products p
+         INNER JOIN stores s ON p.store_id = s.id
+         LEFT JOIN product_variants pv ON pv.id = $2 AND pv.product_id = p.id
+         WHERE p.id = $1",
+        )
+        .bind(product_id)
+        .bind(variant_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Product not found"))?;
+
+    let options = customization::customization_options(&attributes, product_schema.as_ref())?;
+    let validated = customization::validate_properties(&options, properties)?;
+    let hash = properties_hash(&validated.properties);
+
+    // First check stock availability
+    let line = load_line_limits(&mut *conn, product_id, variant_id).await?;
+    customization::check_unit_price(
+        &line.product_name,
+        Money::new(price, currency).checked_add(Money::new(validated.price_modifier, currency))?,
+    )?;
+
+    // Stock is shared by every line of this product+variant, however customized
+    let existing_quantity: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(quantity), 0) FROM cart_items
          WHERE cart_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3",
     )
     .bind(cart_id)
     .bind(product_id)
     .bind(variant_id)
-    .fetch_optional(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
-    let new_quantity = existing_quantity.unwrap_or(0) + quantity;
+    let new_quantity = existing_quantity + i64::from(quantity);
 
-    if new_quantity > available_stock {
+    if new_quantity > i64::from(line.stock_quantity) {
         return Err(ApiError::bad_request(format!(
             "Not enough stock for {}. Available: {}, Requested: {}",
-            product_name, available_stock, new_quantity
+            line.product_name, line.stock_quantity, new_quantity
         )));
     }
 
+    let purchased =
+        previously_purchased(&mut *conn, cart_id, &line, product_id, variant_id).await?;
+    line.rules
+        .check(&line.product_name, new_quantity, purchased)?;
+
     // UPSERT: insert or update quantity
     let item_id = Uuid::now_v7();
     let item = sqlx::query_as::<_, CartItem>(
         r#"
-        INSERT INTO cart_items (id, cart_id, product_id, variant_id, quantity)
-        VALUES ($1, $2, $3, $4, $5)
-        ON CONFLICT (cart_id, product_id, variant_id)
+        INSERT INTO cart_items (
+            id, cart_id, product_id, variant_id, quantity,
+            properties, properties_hash, price_modifier
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (cart_id, product_id, variant_id, properties_hash)
         DO UPDATE SET
             quantity = cart_items.quantity + EXCLUDED.quantity,
+            price_modifier = EXCLUDED.price_modifier,
             updated_at = NOW()
         RETURNING *
         "#,
@@ -201,15 +647,20 @@ pub async fn add_item(
     .bind(product_id)
     .bind(variant_id)
     .bind(quantity)
-    .fetch_one(pool)
+    .bind(&validated.properties)
+    .bind(&hash)
+    .bind(validated.price_modifier)
+    .fetch_one(&mut *conn)
     .await?;
 
+    touch_cart(&mut *conn, cart_id).await?;
+
     Ok(item)
 }
 
 /// Update cart item quantity
 pub async fn update_item_quantity(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     item_id: Uuid,
     cart_id: Uuid,
     quantity: i32,
@@ -219,36 +670,39 @@ pub async fn update_item_quantity(
         sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE id = $1 AND cart_id = $2")
             .bind(item_id)
             .bind(cart_id)
-            .fetch_optional(pool)
+            .fetch_optional(&mut *conn)
             .await?
             .ok_or_else(|| ApiError::not_found("Cart item not found"))?;
 
     // Check stock availability
-    let (available_stock, product_name) = if let Some(vid) = item.variant_id {
-        sqlx::query_as::<_, (i32, String)>(
-            "SELECT pv.stock_quantity, p.name FROM product_variants pv
-             INNER JOIN products p ON pv.product_id = p.id
-             WHERE pv.id = $1",
-        )
-        .bind(vid)
-        .fetch_one(pool)
-        .await?
-    } else {
-        sqlx::query_as::<_, (i32, String)>(
-            "SELECT stock_quantity, name FROM products WHERE id = $1",
-        )
-        .bind(item.product_id)
-        .fetch_one(pool)
-        .await?
-    };
+    let line = load_line_limits(&mut *conn, item.product_id, item.variant_id).await?;
 
-    if quantity > available_stock {
+    // Other lines of the same product+variant (different customizations) share the stock
+    let other_lines_quantity: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(quantity), 0) FROM cart_items
+         WHERE cart_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3
+           AND id <> $4",
+    )
+    .bind(cart_id)
+    .bind(item.product_id)
+    .bind(item.variant_id)
+    .bind(item_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let requested = other_lines_quantity + i64::from(quantity);
+
+    if requested > i64::from(line.stock_quantity) {
         return Err(ApiError::bad_request(format!(
             "Not enough stock for {}. Available: {}, Requested: {}",
-            product_name, available_stock, quantity
+            line.product_name, line.stock_quantity, requested
         )));
     }
 
+    let purchased =
+        previously_purchased(&mut *conn, cart_id, &line, item.product_id, item.variant_id).await?;
+    line.rules.check(&line.product_name, requested, purchased)?;
+
     // Update quantity
     let updated_item = sqlx::query_as::<_, CartItem>(
         "UPDATE cart_items SET quantity = $1, updated_at = NOW()
@@ -258,27 +712,242 @@ pub async fn update_item_quantity(
     .bind(quantity)
     .bind(item_id)
     .bind(cart_id)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
+    touch_cart(&mut *conn, cart_id).await?;
+
     Ok(updated_item)
 }
 
 /// Remove item from cart
-pub async fn remove_item(pool: &PgPool, item_id: Uuid, cart_id: Uuid) -> Result<()> {
+pub async fn remove_item(conn: &mut PgConnection, item_id: Uuid, cart_id: Uuid) -> Result<()> {
     let result = sqlx::query("DELETE FROM cart_items WHERE id = $1 AND cart_id = $2")
         .bind(item_id)
         .bind(cart_id)
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
 
     if result.rows_affected() == 0 {
         return Err(ApiError::not_found("Cart item not found"));
     }
 
+    touch_cart(&mut *conn, cart_id).await?;
+
+    Ok(())
+}
+
+/// Set the quantity of the line matching product+variant+properties.
+///
+/// Creates the line if missing; a quantity of 0 removes it.
+pub async fn set_line_quantity(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i32,
+    properties: &serde_json::Value,
+) -> Result<()> {
+    let existing: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM cart_items
+         WHERE cart_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3
+           AND properties_hash = $4",
+    )
+    .bind(cart_id)
+    .bind(product_id)
+    .bind(variant_id)
+    .bind(properties_hash(&normalize_properties(properties)))
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match (existing, quantity) {
+        (Some(item_id), 0) => remove_item(conn, item_id, cart_id).await,
+        (Some(item_id), _) => update_item_quantity(conn, item_id, cart_id, quantity)
+            .await
+            .map(|_| ()),
+        (None, 0) => Ok(()),
+        (None, _) => add_item(conn, cart_id, product_id, variant_id, quantity, properties)
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// Remove lines of a product+variant: only the line with these properties if
+/// given, otherwise every customization of it
+pub async fn remove_line(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    properties: Option<&serde_json::Value>,
+) -> Result<()> {
+    let hash = properties.map(|p| properties_hash(&normalize_properties(p)));
+
+    let result = sqlx::query(
+        "DELETE FROM cart_items
+         WHERE cart_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3
+           AND ($4::VARCHAR IS NULL OR properties_hash = $4)",
+    )
+    .bind(cart_id)
+    .bind(product_id)
+    .bind(variant_id)
+    .bind(hash)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Cart item not found"));
+    }
+
+    touch_cart(&mut *conn, cart_id).await?;
+
     Ok(())
 }
 
+/// Drop null values so lookups hash properties the same way `add_item` stores them
+fn normalize_properties(properties: &serde_json::Value) -> serde_json::Value {
+    match properties {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        ),
+        _ => serde_json::json!({}),
+    }
+}
+
+/// Apply quick order operations to a cart, recording each line's outcome in
+/// `results` (which carries the resolved product and variant).
+///
+/// Atomic mode runs every operation in a single transaction and rolls back
+/// on the first failure; best-effort mode gives each operation its own
+/// transaction. Returns whether the operations were applied (always true in
+/// best-effort mode).
+pub async fn apply_bulk(
+    pool: &PgPool,
+    cart_id: Uuid,
+    mode: BulkCartMode,
+    operations: &[BulkCartOperation],
+    results: &mut [BulkCartLineResult],
+) -> Result<bool> {
+    match mode {
+        BulkCartMode::Atomic => {
+            let mut tx = pool.begin().await?;
+            let mut failed = false;
+
+            for (op, result) in operations.iter().zip(results.iter_mut()) {
+                match apply_bulk_operation(&mut tx, cart_id, result, op).await {
+                    Ok(()) => result.status = BulkLineStatus::Applied,
+                    Err(e) => {
+                        record_line_failure(result, e);
+                        failed = true;
+                        break;
+                    }
+                }
+            }
+
+            if failed {
+                tx.rollback().await?;
+                for result in results.iter_mut() {
+                    if result.status == BulkLineStatus::Applied {
+                        result.status = BulkLineStatus::Skipped;
+                    }
+                }
+            } else {
+                tx.commit().await?;
+            }
+            Ok(!failed)
+        }
+        BulkCartMode::BestEffort => {
+            for (op, result) in operations.iter().zip(results.iter_mut()) {
+                let mut tx = pool.begin().await?;
+                match apply_bulk_operation(&mut tx, cart_id, result, op).await {
+                    Ok(()) => {
+                        tx.commit().await?;
+                        result.status = BulkLineStatus::Applied;
+                    }
+                    Err(e) => {
+                        tx.rollback().await?;
+                        record_line_failure(result, e);
+                    }
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// Apply a single bulk operation to the cart
+async fn apply_bulk_operation(
+    conn: &mut PgConnection,
+    cart_id: Uuid,
+    line: &BulkCartLineResult,
+    op: &BulkCartOperation,
+) -> Result<()> {
+    let product_id = match (line.product_id, &op.sku) {
+        (Some(id), _) => id,
+        (None, Some(sku)) => return Err(ApiError::not_found(format!("Unknown SKU: {}", sku))),
+        (None, None) => {
+            return Err(ApiError::validation(
+                "Each operation needs a sku or product_id",
+            ))
+        }
+    };
+    let properties = op.properties.as_ref().unwrap_or(&serde_json::Value::Null);
+
+    match op.op {
+        BulkCartAction::Add => {
+            if op.quantity < 1 {
+                return Err(ApiError::validation("quantity must be at least 1"));
+            }
+            add_item(
+                conn,
+                cart_id,
+                product_id,
+                line.variant_id,
+                op.quantity,
+                properties,
+            )
+            .await?;
+        }
+        BulkCartAction::Set => {
+            set_line_quantity(
+                conn,
+                cart_id,
+                product_id,
+                line.variant_id,
+                op.quantity,
+                properties,
+            )
+            .await?;
+        }
+        BulkCartAction::Remove => {
+            remove_line(
+                conn,
+                cart_id,
+                product_id,
+                line.variant_id,
+                op.properties.as_ref(),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn record_line_failure(result: &mut BulkCartLineResult, error: ApiError) {
+    if matches!(error, ApiError::Internal(_) | ApiError::Database(_)) {
+        tracing::error!("Bulk cart operation failed: {error}");
+    }
+    result.status = BulkLineStatus::Failed;
+    result.error = Some(BulkLineError {
+        code: error.code().to_string(),
+        message: error.public_message(),
+    });
+}
+
 /// Clear all items from cart
 pub async fn clear_cart(pool: &PgPool, cart_id: Uuid) -> Result<()> {
     sqlx::query("DELETE FROM cart_items WHERE cart_id = $1")
@@ -286,39 +955,78 @@ pub async fn clear_cart(pool: &PgPool, cart_id: Uuid) -> Result<()> {
         .execute(pool)
         .await?;
 
+    touch_cart(pool, cart_id).await?;
+
     Ok(())
 }
 
-/// Merge guest cart into user cart on login
-pub async fn merge_carts(pool: &PgPool, guest_cart_id: Uuid, user_cart_id: Uuid) -> Result<()> {
-    // Get all items from guest cart
-    let guest_items = sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE cart_id = $1")
-        .bind(guest_cart_id)
+/// Record customer activity on a cart (resets the abandonment clock)
+pub async fn touch_cart<'e, E>(executor: E, cart_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query("UPDATE carts SET last_activity_at = NOW() WHERE id = $1")
+        .bind(cart_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Set the contact email on a cart (guests opt in to recovery emails this way)
+pub async fn set_cart_email(pool: &PgPool, cart_id: Uuid, email: &str) -> Result<()> {
+    sqlx::query("UPDATE carts SET email = $1, last_activity_at = NOW() WHERE id = $2")
+        .bind(email)
+        .bind(cart_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Copy all items from one cart into another (UPSERT adds quantities)
+pub async fn copy_items(pool: &PgPool, from_cart_id: Uuid, to_cart_id: Uuid) -> Result<()> {
+    let items = sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE cart_id = $1")
+        .bind(from_cart_id)
         .fetch_all(pool)
         .await?;
 
-    // For each guest item, add to user cart (UPSERT will handle duplicates)
-    for item in guest_items {
+    for item in items {
         let item_id = Uuid::now_v7();
         sqlx::query(
             r#"
-            INSERT INTO cart_items (id, cart_id, product_id, variant_id, quantity)
-            VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (cart_id, product_id, variant_id)
+            INSERT INTO cart_items (
+                id, cart_id, product_id, variant_id, quantity,
+                properties, properties_hash, price_modifier
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (cart_id, product_id, variant_id, properties_hash)
             DO UPDATE SET
                 quantity = cart_items.quantity + EXCLUDED.quantity,
                 updated_at = NOW()
             "#,
         )
         .bind(item_id)
-        .bind(user_cart_id)
+        .bind(to_cart_id)
         .bind(item.product_id)
         .bind(item.variant_id)
         .bind(item.quantity)
+        .bind(&item.properties)
+        .bind(&item.properties_hash)
+        .bind(item.price_modifier)
         .execute(pool)
         .await?;
     }
 
+    touch_cart(pool, to_cart_id).await?;
+
+    Ok(())
+}
+
+/// Merge guest cart into user cart on login
+pub async fn merge_carts(pool: &PgPool, guest_cart_id: Uuid, user_cart_id: Uuid) -> Result<()> {
+    copy_items(pool, guest_cart_id, user_cart_id).await?;
+
     // Delete guest cart
     sqlx::query("DELETE FROM carts WHERE id = $1")
         .bind(guest_cart_id)
@@ -327,3 +1035,164 @@ pub async fn merge_carts(pool: &PgPool, guest_cart_id: Uuid, user_cart_id: Uuid)
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn add(product_id: Uuid, quantity: i32) -> BulkCartOperation {
+        BulkCartOperation {
+            op: BulkCartAction::Add,
+            sku: None,
+            product_id: Some(product_id),
+            variant_id: None,
+            quantity,
+            properties: None,
+        }
+    }
+
+    fn pending(operations: &[BulkCartOperation]) -> Vec<BulkCartLineResult> {
+        operations
+            .iter()
+            .enumerate()
+            .map(|(index, op)| BulkCartLineResult {
+                index,
+                op: op.op,
+                sku: None,
+                product_id: op.product_id,
+                variant_id: None,
+                quantity: op.quantity,
+                status: BulkLineStatus::Skipped,
+                error: None,
+            })
+            .collect()
+    }
+
+    async fn cart_quantities(pool: &PgPool, cart_id: Uuid) -> Vec<(Uuid, i32)> {
+        sqlx::query_as(
+            "SELECT product_id, quantity FROM cart_items WHERE cart_id = $1 ORDER BY created_at",
+        )
+        .bind(cart_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_atomic_bulk_rolls_back_on_first_failure(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let lamp = test_support::product(&pool, store_id, 5000, 1).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+
+        let operations = [add(mug, 2), add(lamp, 3), add(mug, 1)];
+        let mut results = pending(&operations);
+        let applied = apply_bulk(
+            &pool,
+            cart_id,
+            BulkCartMode::Atomic,
+            &operations,
+            &mut results,
+        )
+        .await
+        .unwrap();
+
+        assert!(!applied);
+        let statuses: Vec<_> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                BulkLineStatus::Skipped,
+                BulkLineStatus::Failed,
+                BulkLineStatus::Skipped
+            ]
+        );
+        assert!(results[1].error.is_some());
+        assert!(cart_quantities(&pool, cart_id).await.is_empty());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_best_effort_bulk_keeps_successful_lines(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let lamp = test_support::product(&pool, store_id, 5000, 1).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+
+        let operations = [add(mug, 2), add(lamp, 3), add(mug, 1)];
+        let mut results = pending(&operations);
+        let applied = apply_bulk(
+            &pool,
+            cart_id,
+            BulkCartMode::BestEffort,
+            &operations,
+            &mut results,
+        )
+        .await
+        .unwrap();
+
+        assert!(applied);
+        let statuses: Vec<_> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                BulkLineStatus::Applied,
+                BulkLineStatus::Failed,
+                BulkLineStatus::Applied
+            ]
+        );
+        assert_eq!(cart_quantities(&pool, cart_id).await, vec![(mug, 3)]);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cart_charges_what_the_order_will_past_a_sale_cap(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        test_support::tax_rate(&pool, store_id, "US", 100_000).await;
+        let kettle = test_support::product(&pool, store_id, 15, 10).await;
+        test_support::sale_item(&pool, store_id, kettle, 5, Some(1)).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(&pool, cart_id, kettle, 2).await;
+
+        // One unit at 5 and one at 15, each taxed and rounded on its own
+        let cart = get_cart_with_items(&pool, cart_id).await.unwrap();
+        assert_eq!(cart.items[0].sale_quantity, Some(1));
+        assert_eq!(cart.tax_total.amount(), 1 + 2);
+
+        let order = crate::orders::place_order(
+            &pool,
+            cart_id,
+            &crate::orders::NewOrder {
+                email: "buyer@example.com".to_string(),
+                ..crate::orders::NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(cart.tax_total, order.tax_total);
+        assert_eq!(cart.total, order.total);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_item_count_past_i32_is_rejected(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+        for _ in 0..2 {
+            let product_id = test_support::product(&pool, store_id, 1, i32::MAX).await;
+            test_support::cart_item(&pool, cart_id, product_id, i32::MAX / 2 + 1).await;
+        }
+
+        let error = get_cart_with_items(&pool, cart_id).await.unwrap_err();
+        assert_eq!(error.code(), "validation_error");
+        let error = crate::orders::place_order(
+            &pool,
+            cart_id,
+            &crate::orders::NewOrder {
+                email: "buyer@example.com".to_string(),
+                ..crate::orders::NewOrder::default()
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), "validation_error");
+    }
+}
@@ -0,0 +1,110 @@
+use goseli_core::{
+    models::{CheckoutSession, CheckoutStep, CheckoutStepData},
+    ApiError, Result,
+};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Get the open checkout session for a cart, or start one.
+///
+/// An expired open session is discarded and replaced by a fresh one.
+pub async fn start_session(
+    pool: &PgPool,
+    store_id: Uuid,
+    cart_id: Uuid,
+    user_id: Option<Uuid>,
+    expires_at: OffsetDateTime,
+) -> Result<CheckoutSession> {
+    sqlx::query(
+        "DELETE FROM checkout_sessions
+         WHERE cart_id = $1 AND status = 'open' AND expires_at < NOW()",
+    )
+    .bind(cart_id)
+    .execute(pool)
+    .await?;
+
+    // ON CONFLICT covers two tabs starting checkout at the same time
+    sqlx::query(
+        r#"
+        INSERT INTO checkout_sessions (id, store_id, cart_id, user_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (cart_id) WHERE status = 'open' DO NOTHING
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(cart_id)
+    .bind(user_id)
+    .bind(expires_at)
+    .execute(pool)
+    .await?;
+
+    let session = sqlx::query_as::<_, CheckoutSession>(
+        "SELECT * FROM checkout_sessions WHERE cart_id = $1 AND status = 'open'",
+    )
+    .bind(cart_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(session)
+}
+
+/// Get a checkout session by ID
+pub async fn get_session(pool: &PgPool, id: Uuid) -> Result<CheckoutSession> {
+    let session =
+        sqlx::query_as::<_, CheckoutSession>("SELECT * FROM checkout_sessions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Checkout session not found"))?;
+
+    Ok(session)
+}
+
+/// Save a submitted step: store its data and mark it completed.
+///
+/// Fields the step does not set are left unchanged; custom field values
+/// are merged into those collected at earlier steps.
+pub async fn save_step(
+    pool: &PgPool,
+    id: Uuid,
+    step: CheckoutStep,
+    data: &CheckoutStepData,
+) -> Result<CheckoutSession> {
+    let session = sqlx::query_as::<_, CheckoutSession>(
+        r#"
+        UPDATE checkout_sessions SET
+            completed_steps = CASE
+                WHEN $2 = ANY(completed_steps) THEN completed_steps
+                ELSE array_append(completed_steps, $2)
+            END,
+            email = COALESCE($3, email),
+            shipping_address = COALESCE($4, shipping_address),
+            billing_address = COALESCE($5, billing_address),
+            shipping_method = COALESCE($6, shipping_method),
+            payment_method = COALESCE($7, payment_method),
+            custom_fields = CASE
+                WHEN jsonb_typeof($8) = 'object' THEN custom_fields || $8
+                ELSE custom_fields
+            END,
+            gift_card_codes = COALESCE($9, gift_card_codes)
+        WHERE id = $1 AND status = 'open'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(step.as_str())
+    .bind(&data.email)
+    .bind(&data.shipping_address)
+    .bind(&data.billing_address)
+    .bind(&data.shipping_method)
+    .bind(&data.payment_method)
+    .bind(&data.custom_fields)
+    .bind(&data.gift_card_codes)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::conflict("This checkout session is already completed"))?;
+
+    Ok(session)
+}
@@ -0,0 +1,698 @@
+use goseli_core::{
+    dto::CouponRequest,
+    models::{coupon::generate_code, Coupon, CouponCode, CouponRedemption},
+    ApiError, Money, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Generated codes are retried this many times when they collide with
+/// existing ones
+const GENERATE_ATTEMPTS: usize = 5;
+
+/// Products and categories a coupon is limited to must belong to the store
+async fn validate_targets(pool: &PgPool, store_id: Uuid, req: &CouponRequest) -> Result<()> {
+    let products: i64 = sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT id) FROM products WHERE store_id = $1 AND id = ANY($2)",
+    )
+    .bind(store_id)
+    .bind(&req.product_ids)
+    .fetch_one(pool)
+    .await?;
+    let mut product_ids = req.product_ids.clone();
+    product_ids.sort();
+    product_ids.dedup();
+    if products != product_ids.len() as i64 {
+        return Err(ApiError::validation(
+            "product_ids contains unknown products",
+        ));
+    }
+
+    let categories: i64 = sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT id) FROM categories WHERE store_id = $1 AND id = ANY($2)",
+    )
+    .bind(store_id)
+    .bind(&req.category_ids)
+    .fetch_one(pool)
+    .await?;
+    let mut category_ids = req.category_ids.clone();
+    category_ids.sort();
+    category_ids.dedup();
+    if categories != category_ids.len() as i64 {
+        return Err(ApiError::validation(
+            "category_ids contains unknown categories",
+        ));
+    }
+
+    Ok(())
+}
+
+/// A shared code must not clash with another coupon or a generated code
+async fn ensure_code_free(
+    pool: &PgPool,
+    store_id: Uuid,
+    code: &str,
+    coupon_id: Option<Uuid>,
+) -> Result<()> {
+    let taken: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM coupons
+            WHERE store_id = $1 AND upper(code) = upper($2)
+              AND id IS DISTINCT FROM $3
+        ) OR EXISTS (
+            SELECT 1 FROM coupon_codes WHERE store_id = $1 AND upper(code) = upper($2)
+        )
+        "#,
+    )
+    .bind(store_id)
+    .bind(code)
+    .bind(coupon_id)
+    .fetch_one(pool)
+    .await?;
+    if taken {
+        return Err(ApiError::conflict(format!(
+            "Code {} is already in use",
+            code
+        )));
+    }
+
+    Ok(())
+}
+
+/// List a store's coupons, newest first
+pub async fn list_coupons(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Coupon>> {
+    let coupons = sqlx::query_as::<_, Coupon>(
+        r#"
+        SELECT * FROM coupons
+        WHERE store_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(coupons)
+}
+
+/// Count a store's coupons
+pub async fn count_coupons(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM coupons WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Get a coupon
+pub async fn get_coupon(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<Coupon> {
+    sqlx::query_as::<_, Coupon>("SELECT * FROM coupons WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Coupon not found"))
+}
+
+/// Create a coupon
+pub async fn create_coupon(pool: &PgPool, store_id: Uuid, req: &CouponRequest) -> Result<Coupon> {
+    req.validate_terms()?;
+    validate_targets(pool, store_id, req).await?;
+    if let Some(code) = &req.code {
+        ensure_code_free(pool, store_id, code, None).await?;
+    }
+
+    let coupon = sqlx::query_as::<_, Coupon>(
+        r#"
+        INSERT INTO coupons (
+            id, store_id, code, description, kind, percentage_ppm, amount, min_subtotal,
+            product_ids, category_ids, starts_at, ends_at,
+            usage_limit, usage_limit_per_customer, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&req.code)
+    .bind(&req.description)
+    .bind(req.kind)
+    .bind(req.percentage_ppm)
+    .bind(req.amount)
+    .bind(req.min_subtotal)
+    .bind(&req.product_ids)
+    .bind(&req.category_ids)
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.usage_limit)
+    .bind(req.usage_limit_per_customer)
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(coupon)
+}
+
+/// Replace a coupon's terms; its usage count is kept
+pub async fn update_coupon(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    req: &CouponRequest,
+) -> Result<Coupon> {
+    req.validate_terms()?;
+    get_coupon(pool, store_id, id).await?;
+    validate_targets(pool, store_id, req).await?;
+    if let Some(code) = &req.code {
+        ensure_code_free(pool, store_id, code, Some(id)).await?;
+    }
+
+    let coupon = sqlx::query_as::<_, Coupon>(
+        r#"
+        UPDATE coupons SET
+            code = $3,
+            description = $4,
+            kind = $5,
+            percentage_ppm = $6,
+            amount = $7,
+            min_subtotal = $8,
+            product_ids = $9,
+            category_ids = $10,
+            starts_at = $11,
+            ends_at = $12,
+            usage_limit = $13,
+            usage_limit_per_customer = $14,
+            is_active = COALESCE($15, is_active)
+        WHERE id = $1 AND store_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(store_id)
+    .bind(&req.code)
+    .bind(&req.description)
+    .bind(req.kind)
+    .bind(req.percentage_ppm)
+    .bind(req.amount)
+    .bind(req.min_subtotal)
+    .bind(&req.product_ids)
+    .bind(&req.category_ids)
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.usage_limit)
+    .bind(req.usage_limit_per_customer)
+    .bind(req.is_active)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(coupon)
+}
+
+/// Delete a coupon that was never redeemed; redeemed coupons are
+/// deactivated instead so their history stays
+pub async fn delete_coupon(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<()> {
+    let coupon = get_coupon(pool, store_id, id).await?;
+    if coupon.times_used > 0 {
+        return Err(ApiError::conflict(
+            "This coupon has been redeemed; deactivate it instead",
+        ));
+    }
+
+    sqlx::query("DELETE FROM coupons WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Generate `count` single-use codes for a coupon
+pub async fn generate_codes(
+    pool: &PgPool,
+    store_id: Uuid,
+    coupon_id: Uuid,
+    count: usize,
+    prefix: Option<&str>,
+) -> Result<Vec<CouponCode>> {
+    get_coupon(pool, store_id, coupon_id).await?;
+
+    let mut tx = pool.begin().await?;
+    let mut generated: Vec<CouponCode> = Vec::with_capacity(count);
+    for _ in 0..GENERATE_ATTEMPTS {
+        let missing = count - generated.len();
+        if missing == 0 {
+            break;
+        }
+        let codes: Vec<String> = (0..missing).map(|_| generate_code(prefix)).collect();
+        let ids: Vec<Uuid> = (0..missing).map(|_| Uuid::now_v7()).collect();
+        // Codes that collide with existing ones are skipped and drawn again;
+        // shared coupon codes are checked too
+        let inserted = sqlx::query_as::<_, CouponCode>(
+            r#"
+            INSERT INTO coupon_codes (id, store_id, coupon_id, code)
+            SELECT new.id, $1, $2, new.code
+            FROM UNNEST($3::UUID[], $4::VARCHAR[]) AS new (id, code)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM coupons c
+                WHERE c.store_id = $1 AND upper(c.code) = upper(new.code)
+            )
+            ON CONFLICT (store_id, upper(code)) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(coupon_id)
+        .bind(&ids)
+        .bind(&codes)
+        .fetch_all(&mut *tx)
+        .await?;
+        generated.extend(inserted);
+    }
+    if generated.len() < count {
+        return Err(ApiError::conflict(
+            "Could not generate enough unique codes; try a different prefix",
+        ));
+    }
+    tx.commit().await?;
+
+    generated.sort_by_key(|code| code.id);
+    Ok(generated)
+}
+
+/// List a coupon's generated codes, oldest first
+pub async fn list_codes(
+    pool: &PgPool,
+    store_id: Uuid,
+    coupon_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CouponCode>> {
+    let codes = sqlx::query_as::<_, CouponCode>(
+        r#"
+        SELECT * FROM coupon_codes
+        WHERE coupon_id = $1 AND store_id = $2
+        ORDER BY created_at ASC, id ASC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(coupon_id)
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(codes)
+}
+
+/// Count a coupon's generated codes
+pub async fn count_codes(pool: &PgPool, store_id: Uuid, coupon_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM coupon_codes WHERE coupon_id = $1 AND store_id = $2",
+    )
+    .bind(coupon_id)
+    .bind(store_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// List the orders a coupon was used on, newest first
+pub async fn list_redemptions(
+    pool: &PgPool,
+    store_id: Uuid,
+    coupon_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CouponRedemption>> {
+    let redemptions = sqlx::query_as::<_, CouponRedemption>(
+        r#"
+        SELECT r.* FROM coupon_redemptions r
+        INNER JOIN coupons c ON r.coupon_id = c.id
+        WHERE r.coupon_id = $1 AND c.store_id = $2
+        ORDER BY r.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(coupon_id)
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(redemptions)
+}
+
+/// The coupon a code unlocks: its shared code, or one of its generated
+/// codes. With `lock`, the rows stay locked until the transaction ends.
+async fn find_by_code(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    code: &str,
+    lock: bool,
+) -> Result<Option<(Coupon, Option<CouponCode>)>> {
+    let lock_clause = if lock { " FOR UPDATE" } else { "" };
+
+    let shared = sqlx::query_as::<_, Coupon>(&format!(
+        "SELECT * FROM coupons WHERE store_id = $1 AND upper(code) = upper($2){}",
+        lock_clause
+    ))
+    .bind(store_id)
+    .bind(code)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(coupon) = shared {
+        return Ok(Some((coupon, None)));
+    }
+
+    let generated = sqlx::query_as::<_, CouponCode>(&format!(
+        "SELECT * FROM coupon_codes WHERE store_id = $1 AND upper(code) = upper($2){}",
+        lock_clause
+    ))
+    .bind(store_id)
+    .bind(code)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(generated) = generated else {
+        return Ok(None);
+    };
+    let coupon = sqlx::query_as::<_, Coupon>(&format!(
+        "SELECT * FROM coupons WHERE id = $1{}",
+        lock_clause
+    ))
+    .bind(generated.coupon_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(Some((coupon, Some(generated))))
+}
+
+/// Look up a code entered by a customer and check it can be used now:
+/// the coupon is active and within its dates, it has uses left, a
+/// generated code is unused and the customer (account or email) is under
+/// the per-customer limit. Says nothing yet about the cart it is used on.
+pub async fn resolve_coupon(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    code: &str,
+    user_id: Option<Uuid>,
+    email: Option<&str>,
+    lock: bool,
+) -> Result<(Coupon, Option<CouponCode>)> {
+    let (coupon, generated) = find_by_code(&mut *conn, store_id, code.trim(), lock)
+        .await?
+        .ok_or_else(|| {
+            ApiError::rule(
+                "coupon_not_found",
+                format!("{} is not a valid coupon code", code.trim()),
+            )
+        })?;
+
+    coupon.check_available(OffsetDateTime::now_utc())?;
+    if generated
+        .as_ref()
+        .is_some_and(|code| code.used_at.is_some())
+    {
+        return Err(ApiError::rule(
+            "coupon_code_used",
+            "This coupon code has already been used",
+        ));
+    }
+
+    if let Some(limit) = coupon.usage_limit_per_customer {
+        if user_id.is_some() || email.is_some() {
+            let used: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM coupon_redemptions
+                WHERE coupon_id = $1 AND (user_id = $2 OR lower(email) = lower($3))
+                "#,
+            )
+            .bind(coupon.id)
+            .bind(user_id)
+            .bind(email)
+            .fetch_one(&mut *conn)
+            .await?;
+            if used >= i64::from(limit) {
+                return Err(ApiError::rule(
+                    "coupon_customer_limit",
+                    "You have already used this coupon the maximum number of times",
+                ));
+            }
+        }
+    }
+
+    Ok((coupon, generated))
+}
+
+/// The code a resolved coupon is known by: the generated code, or the
+/// shared one
+pub fn redeemed_code(coupon: &Coupon, generated: Option<&CouponCode>) -> String {
+    generated
+        .map(|code| code.code.clone())
+        .or_else(|| coupon.code.clone())
+        .unwrap_or_default()
+}
+
+/// A coupon used on an order, recorded in the order's transaction
+pub struct NewRedemption<'a> {
+    pub coupon: &'a Coupon,
+    pub code: Option<&'a CouponCode>,
+    pub order_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub email: &'a str,
+    /// Taken off the lines plus the shipping waived
+    pub amount: Money,
+}
+
+/// Record a redemption: count the use and spend a generated code
+pub async fn record_redemption(
+    conn: &mut PgConnection,
+    redemption: &NewRedemption<'_>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO coupon_redemptions (
+            id, coupon_id, coupon_code_id, order_id, user_id, email, code, amount, currency
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(redemption.coupon.id)
+    .bind(redemption.code.map(|code| code.id))
+    .bind(redemption.order_id)
+    .bind(redemption.user_id)
+    .bind(redemption.email)
+    .bind(redeemed_code(redemption.coupon, redemption.code))
+    .bind(redemption.amount)
+    .bind(redemption.amount.currency())
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query("UPDATE coupons SET times_used = times_used + 1 WHERE id = $1")
+        .bind(redemption.coupon.id)
+        .execute(&mut *conn)
+        .await?;
+
+    if let Some(code) = redemption.code {
+        sqlx::query("UPDATE coupon_codes SET used_at = NOW() WHERE id = $1")
+            .bind(code.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Undo the redemption recorded for a cancelled or refunded order: its use
+/// no longer counts against the coupon's limits and a generated code can be
+/// used again
+pub async fn release_for_order(conn: &mut PgConnection, order_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        WITH released AS (
+            DELETE FROM coupon_redemptions WHERE order_id = $1
+            RETURNING coupon_id, coupon_code_id
+        ), codes AS (
+            UPDATE coupon_codes SET used_at = NULL
+            WHERE id IN (SELECT coupon_code_id FROM released)
+        )
+        UPDATE coupons SET times_used = GREATEST(times_used - 1, 0)
+        WHERE id IN (SELECT coupon_id FROM released)
+        "#,
+    )
+    .bind(order_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{self, NewOrder};
+    use crate::test_support;
+    use goseli_core::models::{CouponKind, Order, OrderAction, OrderActor};
+
+    fn percentage_coupon(code: &str, per_customer: Option<i32>) -> CouponRequest {
+        CouponRequest {
+            code: Some(code.into()),
+            description: None,
+            kind: CouponKind::Percentage,
+            percentage_ppm: Some(100_000),
+            amount: None,
+            min_subtotal: None,
+            product_ids: vec![],
+            category_ids: vec![],
+            starts_at: None,
+            ends_at: None,
+            usage_limit: None,
+            usage_limit_per_customer: per_customer,
+            is_active: None,
+        }
+    }
+
+    async fn checkout_with_coupon(
+        pool: &PgPool,
+        store_id: Uuid,
+        product_id: Uuid,
+        code: &str,
+        email: &str,
+    ) -> Result<Order> {
+        let cart_id = test_support::cart(pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(pool, cart_id, product_id, 2).await;
+        sqlx::query("UPDATE carts SET coupon_code = $2 WHERE id = $1")
+            .bind(cart_id)
+            .bind(code)
+            .execute(pool)
+            .await
+            .unwrap();
+        orders::place_order(
+            pool,
+            cart_id,
+            &NewOrder {
+                email: email.into(),
+                ..NewOrder::default()
+            },
+        )
+        .await
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_orders_record_the_coupon_they_used(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let coupon = create_coupon(&pool, store_id, &percentage_coupon("SAVE10", None))
+            .await
+            .unwrap();
+
+        let order = checkout_with_coupon(&pool, store_id, product_id, "save10", "ada@example.com")
+            .await
+            .unwrap();
+
+        assert_eq!(order.discount_total.amount(), 1000);
+        assert_eq!(order.total.amount(), 9000);
+        let coupon = get_coupon(&pool, store_id, coupon.id).await.unwrap();
+        assert_eq!(coupon.times_used, 1);
+        let redemptions = list_redemptions(&pool, store_id, coupon.id, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(redemptions.len(), 1);
+        assert_eq!(redemptions[0].order_id, order.id);
+        assert_eq!(redemptions[0].amount, 1000);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_customer_limit_counts_guest_orders_by_email(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        create_coupon(&pool, store_id, &percentage_coupon("ONCE", Some(1)))
+            .await
+            .unwrap();
+        checkout_with_coupon(&pool, store_id, product_id, "ONCE", "ada@example.com")
+            .await
+            .unwrap();
+
+        let error = checkout_with_coupon(&pool, store_id, product_id, "ONCE", "Ada@Example.com")
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "coupon_customer_limit");
+
+        checkout_with_coupon(&pool, store_id, product_id, "ONCE", "grace@example.com")
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cancelled_orders_give_the_coupon_back(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let coupon = create_coupon(&pool, store_id, &percentage_coupon("ONCE", Some(1)))
+            .await
+            .unwrap();
+        let generated = generate_codes(&pool, store_id, coupon.id, 1, None)
+            .await
+            .unwrap()
+            .remove(0);
+        let shared = checkout_with_coupon(&pool, store_id, product_id, "ONCE", "ada@example.com")
+            .await
+            .unwrap();
+        let single = checkout_with_coupon(
+            &pool,
+            store_id,
+            product_id,
+            &generated.code,
+            "grace@example.com",
+        )
+        .await
+        .unwrap();
+
+        for order in [&shared, &single] {
+            orders::transition_order(
+                &pool,
+                order.id,
+                OrderAction::Cancel,
+                OrderActor::System,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            get_coupon(&pool, store_id, coupon.id)
+                .await
+                .unwrap()
+                .times_used,
+            0
+        );
+        checkout_with_coupon(&pool, store_id, product_id, "ONCE", "ada@example.com")
+            .await
+            .unwrap();
+        checkout_with_coupon(
+            &pool,
+            store_id,
+            product_id,
+            &generated.code,
+            "grace@example.com",
+        )
+        .await
+        .unwrap();
+    }
+}
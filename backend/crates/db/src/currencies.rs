@@ -0,0 +1,410 @@
+use goseli_core::{
+    dto::{EnableCurrencyRequest, SetPriceRequest, UpdateCurrencyRequest},
+    models::{DisplayPricing, ExchangeRate, ExchangeRateSource, PriceListEntry, StoreCurrency},
+    ApiError, Currency, Result,
+};
+use sqlx::{Executor, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// The price ending has to fall within one rounding increment
+fn validate_rounding(increment: i64, ending: Option<i64>) -> Result<()> {
+    if ending.is_some_and(|ending| ending >= increment) {
+        return Err(ApiError::validation(
+            "price_ending must be smaller than rounding_increment",
+        ));
+    }
+    Ok(())
+}
+
+/// The store currency needs no rate, rounding or price list
+fn ensure_foreign(base: Currency, currency: Currency) -> Result<()> {
+    if currency == base {
+        return Err(ApiError::validation(format!(
+            "{} is the store currency",
+            currency
+        )));
+    }
+    Ok(())
+}
+
+/// List the currencies a store has enabled
+pub async fn list_currencies(pool: &PgPool, store_id: Uuid) -> Result<Vec<StoreCurrency>> {
+    let currencies = sqlx::query_as::<_, StoreCurrency>(
+        "SELECT * FROM store_currencies WHERE store_id = $1 ORDER BY currency ASC",
+    )
+    .bind(store_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(currencies)
+}
+
+/// Enabled currencies that have an exchange rate, so prices can be shown in them
+pub async fn list_display_currencies(pool: &PgPool, store_id: Uuid) -> Result<Vec<Currency>> {
+    let currencies = sqlx::query_scalar::<_, Currency>(
+        r#"
+        SELECT sc.currency FROM store_currencies sc
+        JOIN exchange_rates er ON er.store_id = sc.store_id AND er.currency = sc.currency
+        WHERE sc.store_id = $1
+        ORDER BY sc.currency ASC
+        "#,
+    )
+    .bind(store_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(currencies)
+}
+
+async fn find_currency(
+    pool: &PgPool,
+    store_id: Uuid,
+    currency: Currency,
+) -> Result<Option<StoreCurrency>> {
+    let currency = sqlx::query_as::<_, StoreCurrency>(
+        "SELECT * FROM store_currencies WHERE store_id = $1 AND currency = $2",
+    )
+    .bind(store_id)
+    .bind(currency)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(currency)
+}
+
+/// Enable a currency for a store
+pub async fn enable_currency(
+    pool: &PgPool,
+    store_id: Uuid,
+    base: Currency,
+    req: &EnableCurrencyRequest,
+) -> Result<StoreCurrency> {
+    ensure_foreign(base, req.currency)?;
+    let increment = req.rounding_increment.unwrap_or(1);
+    validate_rounding(increment, req.price_ending)?;
+
+    let currency = sqlx::query_as::<_, StoreCurrency>(
+        r#"
+        INSERT INTO store_currencies (
+            store_id, currency, rounding_mode, rounding_increment, price_ending
+        )
+        VALUES ($1, $2, $3, $4, NULLIF($5, 0))
+        ON CONFLICT (store_id, currency) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(req.currency)
+    .bind(req.rounding_mode.unwrap_or_default())
+    .bind(increment)
+    .bind(req.price_ending)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::conflict(format!("{} is already enabled", req.currency)))?;
+
+    Ok(currency)
+}
+
+/// Change how prices in a currency are rounded
+pub async fn update_currency(
+    pool: &PgPool,
+    store_id: Uuid,
+    currency: Currency,
+    req: &UpdateCurrencyRequest,
+) -> Result<StoreCurrency> {
+    let current = find_currency(pool, store_id, currency)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("{} is not enabled", currency)))?;
+    let ending = req.price_ending.or(current.price_ending).filter(|e| *e > 0);
+    validate_rounding(
+        req.rounding_increment.unwrap_or(current.rounding_increment),
+        ending,
+    )?;
+
+    let currency = sqlx::query_as::<_, StoreCurrency>(
+        r#"
+        UPDATE store_currencies SET
+            rounding_mode = COALESCE($3, rounding_mode),
+            rounding_increment = COALESCE($4, rounding_increment),
+            price_ending = $5
+        WHERE store_id = $1 AND currency = $2
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(currency)
+    .bind(req.rounding_mode)
+    .bind(req.rounding_increment)
+    .bind(ending)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(currency)
+}
+
+/// Stop showing prices in a currency; its rate and price list are kept
+pub async fn disable_currency(pool: &PgPool, store_id: Uuid, currency: Currency) -> Result<()> {
+    let result = sqlx::query("DELETE FROM store_currencies WHERE store_id = $1 AND currency = $2")
+        .bind(store_id)
+        .bind(currency)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found(format!("{} is not enabled", currency)));
+    }
+
+    Ok(())
+}
+
+/// List a store's exchange rates
+pub async fn list_rates(pool: &PgPool, store_id: Uuid) -> Result<Vec<ExchangeRate>> {
+    let rates = sqlx::query_as::<_, ExchangeRate>(
+        "SELECT * FROM exchange_rates WHERE store_id = $1 ORDER BY currency ASC",
+    )
+    .bind(store_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rates)
+}
+
+/// Set the rate of a currency against the store currency
+pub async fn set_rate<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    base: Currency,
+    currency: Currency,
+    rate_ppm: i64,
+    source: ExchangeRateSource,
+) -> Result<ExchangeRate>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    ensure_foreign(base, currency)?;
+
+    let rate = sqlx::query_as::<_, ExchangeRate>(
+        r#"
+        INSERT INTO exchange_rates (store_id, currency, rate_ppm, source)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (store_id, currency) DO UPDATE SET
+            rate_ppm = EXCLUDED.rate_ppm,
+            source = EXCLUDED.source
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(currency)
+    .bind(rate_ppm)
+    .bind(source)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(rate)
+}
+
+/// Set many rates at once, all or none
+pub async fn import_rates(
+    pool: &PgPool,
+    store_id: Uuid,
+    base: Currency,
+    rates: &[(Currency, i64)],
+) -> Result<Vec<ExchangeRate>> {
+    let mut tx = pool.begin().await?;
+    let mut imported = Vec::with_capacity(rates.len());
+    for (currency, rate_ppm) in rates {
+        imported.push(
+            set_rate(
+                &mut *tx,
+                store_id,
+                base,
+                *currency,
+                *rate_ppm,
+                ExchangeRateSource::Import,
+            )
+            .await?,
+        );
+    }
+    tx.commit().await?;
+
+    Ok(imported)
+}
+
+/// Delete the rate of a currency; prices can no longer be shown in it
+pub async fn delete_rate(pool: &PgPool, store_id: Uuid, currency: Currency) -> Result<()> {
+    sqlx::query("DELETE FROM exchange_rates WHERE store_id = $1 AND currency = $2")
+        .bind(store_id)
+        .bind(currency)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// List the fixed prices in a currency
+pub async fn list_prices(
+    pool: &PgPool,
+    store_id: Uuid,
+    currency: Currency,
+) -> Result<Vec<PriceListEntry>> {
+    let prices = sqlx::query_as::<_, PriceListEntry>(
+        r#"
+        SELECT * FROM price_list_entries
+        WHERE store_id = $1 AND currency = $2
+        ORDER BY product_id ASC, variant_id ASC NULLS FIRST
+        "#,
+    )
+    .bind(store_id)
+    .bind(currency)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(prices)
+}
+
+/// Set the fixed price of a product or variant in a currency
+pub async fn set_price(
+    pool: &PgPool,
+    store_id: Uuid,
+    base: Currency,
+    currency: Currency,
+    req: &SetPriceRequest,
+) -> Result<PriceListEntry> {
+    ensure_foreign(base, currency)?;
+
+    let exists: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT p.id FROM products p
+        LEFT JOIN product_variants pv ON pv.id = $3 AND pv.product_id = p.id
+        WHERE p.id = $1 AND p.store_id = $2 AND ($3::UUID IS NULL OR pv.id IS NOT NULL)
+        "#,
+    )
+    .bind(req.product_id)
+    .bind(store_id)
+    .bind(req.variant_id)
+    .fetch_optional(pool)
+    .await?;
+    if exists.is_none() {
+        return Err(ApiError::not_found("Product or variant not found"));
+    }
+
+    let entry = sqlx::query_as::<_, PriceListEntry>(
+        r#"
+        INSERT INTO price_list_entries (
+            id, store_id, currency, product_id, variant_id, price, compare_at_price
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (
+            store_id, currency, product_id,
+            COALESCE(variant_id, '00000000-0000-0000-0000-000000000000')
+        ) DO UPDATE SET
+            price = EXCLUDED.price,
+            compare_at_price = EXCLUDED.compare_at_price
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(currency)
+    .bind(req.product_id)
+    .bind(req.variant_id)
+    .bind(req.price)
+    .bind(req.compare_at_price)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(entry)
+}
+
+/// Remove a fixed price; the converted price applies again
+pub async fn delete_price(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<()> {
+    let result = sqlx::query("DELETE FROM price_list_entries WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Price not found"));
+    }
+
+    Ok(())
+}
+
+/// Pricing for showing the given products in `currency` now: store prices
+/// when no currency or the store currency is asked for, converted and fixed
+/// prices otherwise, with the customer group's prices and running sales
+/// applied
+pub async fn display_pricing(
+    pool: &PgPool,
+    store_id: Uuid,
+    base: Currency,
+    currency: Option<Currency>,
+    customer_group: Option<&str>,
+    product_ids: &[Uuid],
+) -> Result<DisplayPricing> {
+    display_pricing_at(
+        pool,
+        store_id,
+        base,
+        currency,
+        customer_group,
+        product_ids,
+        OffsetDateTime::now_utc(),
+    )
+    .await
+}
+
+/// Pricing as it will be, or was, at `at`: the sales running then apply
+pub async fn display_pricing_at(
+    pool: &PgPool,
+    store_id: Uuid,
+    base: Currency,
+    currency: Option<Currency>,
+    customer_group: Option<&str>,
+    product_ids: &[Uuid],
+    at: OffsetDateTime,
+) -> Result<DisplayPricing> {
+    let sales = crate::sales::sale_prices(pool, store_id, product_ids, at, false).await?;
+    let group_prices =
+        crate::customer_groups::group_prices(pool, store_id, customer_group, product_ids).await?;
+    let Some(currency) = currency.filter(|currency| *currency != base) else {
+        return Ok(DisplayPricing::store(base)
+            .with_sales(sales)
+            .with_group_prices(group_prices));
+    };
+
+    let unsupported = || {
+        ApiError::rule(
+            "currency_not_supported",
+            format!("Prices are not available in {}", currency),
+        )
+    };
+    let store_currency = find_currency(pool, store_id, currency)
+        .await?
+        .ok_or_else(unsupported)?;
+    let rate = sqlx::query_as::<_, ExchangeRate>(
+        "SELECT * FROM exchange_rates WHERE store_id = $1 AND currency = $2",
+    )
+    .bind(store_id)
+    .bind(currency)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(unsupported)?;
+    let price_list = sqlx::query_as::<_, PriceListEntry>(
+        r#"
+        SELECT * FROM price_list_entries
+        WHERE store_id = $1 AND currency = $2 AND product_id = ANY($3)
+        "#,
+    )
+    .bind(store_id)
+    .bind(currency)
+    .bind(product_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(
+        DisplayPricing::converted(base, &store_currency, &rate, price_list)
+            .with_sales(sales)
+            .with_group_prices(group_prices),
+    )
+}
@@ -0,0 +1,241 @@
+use goseli_core::{
+    dto::{CreateCustomerGroupRequest, SetGroupPriceRequest, UpdateCustomerGroupRequest},
+    models::{CustomerGroup, GroupPrice},
+    ApiError, Result,
+};
+use sqlx::{Executor, PgPool, Postgres};
+use uuid::Uuid;
+
+/// List a store's customer groups
+pub async fn list_groups(pool: &PgPool, store_id: Uuid) -> Result<Vec<CustomerGroup>> {
+    let groups = sqlx::query_as::<_, CustomerGroup>(
+        "SELECT * FROM customer_groups WHERE store_id = $1 ORDER BY code ASC",
+    )
+    .bind(store_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(groups)
+}
+
+/// Get a customer group
+pub async fn get_group(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<CustomerGroup> {
+    let group = sqlx::query_as::<_, CustomerGroup>(
+        "SELECT * FROM customer_groups WHERE id = $1 AND store_id = $2",
+    )
+    .bind(id)
+    .bind(store_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Customer group not found"))?;
+
+    Ok(group)
+}
+
+/// Add a customer group; codes are unique per store
+pub async fn create_group(
+    pool: &PgPool,
+    store_id: Uuid,
+    req: &CreateCustomerGroupRequest,
+) -> Result<CustomerGroup> {
+    let code = req.code.trim().to_lowercase();
+    if code.is_empty() {
+        return Err(ApiError::validation("code must not be blank"));
+    }
+
+    let group = sqlx::query_as::<_, CustomerGroup>(
+        r#"
+        INSERT INTO customer_groups (id, store_id, code, name, description)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (store_id, code) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&code)
+    .bind(&req.name)
+    .bind(&req.description)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::conflict(format!("Customer group '{}' already exists", code)))?;
+
+    Ok(group)
+}
+
+/// Update a customer group's name or description
+pub async fn update_group(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    req: &UpdateCustomerGroupRequest,
+) -> Result<CustomerGroup> {
+    let group = sqlx::query_as::<_, CustomerGroup>(
+        r#"
+        UPDATE customer_groups SET
+            name = COALESCE($3, name),
+            description = COALESCE($4, description)
+        WHERE id = $1 AND store_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(&req.description)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Customer group not found"))?;
+
+    Ok(group)
+}
+
+/// Delete a customer group with its prices; its customers are left without
+/// a group
+pub async fn delete_group(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<()> {
+    let result = sqlx::query("DELETE FROM customer_groups WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Customer group not found"));
+    }
+
+    Ok(())
+}
+
+/// Whether a store has a customer group with this code
+pub async fn group_exists(pool: &PgPool, store_id: Uuid, code: &str) -> Result<bool> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM customer_groups WHERE store_id = $1 AND code = $2)",
+    )
+    .bind(store_id)
+    .bind(code)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+/// List a group's prices
+pub async fn list_prices(pool: &PgPool, store_id: Uuid, group_id: Uuid) -> Result<Vec<GroupPrice>> {
+    get_group(pool, store_id, group_id).await?;
+
+    let prices = sqlx::query_as::<_, GroupPrice>(
+        r#"
+        SELECT * FROM group_prices
+        WHERE customer_group_id = $1
+        ORDER BY product_id ASC, variant_id ASC NULLS FIRST, min_quantity ASC
+        "#,
+    )
+    .bind(group_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(prices)
+}
+
+/// Set a group's price for a product or variant from a quantity up,
+/// replacing the one it had from that quantity
+pub async fn set_price(
+    pool: &PgPool,
+    store_id: Uuid,
+    group_id: Uuid,
+    req: &SetGroupPriceRequest,
+) -> Result<GroupPrice> {
+    req.validate_terms()?;
+    get_group(pool, store_id, group_id).await?;
+
+    let exists: Option<(Uuid,)> = sqlx::query_as(
+        r#"
+        SELECT p.id FROM products p
+        LEFT JOIN product_variants pv ON pv.id = $3 AND pv.product_id = p.id
+        WHERE p.id = $1 AND p.store_id = $2 AND ($3::UUID IS NULL OR pv.id IS NOT NULL)
+        "#,
+    )
+    .bind(req.product_id)
+    .bind(store_id)
+    .bind(req.variant_id)
+    .fetch_optional(pool)
+    .await?;
+    if exists.is_none() {
+        return Err(ApiError::not_found("Product or variant not found"));
+    }
+
+    let price = sqlx::query_as::<_, GroupPrice>(
+        r#"
+        INSERT INTO group_prices (
+            id, store_id, customer_group_id, product_id, variant_id, min_quantity,
+            price, percentage_ppm
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (customer_group_id, product_id, variant_id, min_quantity) DO UPDATE SET
+            price = EXCLUDED.price,
+            percentage_ppm = EXCLUDED.percentage_ppm
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(group_id)
+    .bind(req.product_id)
+    .bind(req.variant_id)
+    .bind(req.min_quantity.unwrap_or(1))
+    .bind(req.price)
+    .bind(req.percentage_ppm)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(price)
+}
+
+/// Remove one of a group's prices
+pub async fn delete_price(pool: &PgPool, store_id: Uuid, group_id: Uuid, id: Uuid) -> Result<()> {
+    let result = sqlx::query(
+        "DELETE FROM group_prices WHERE id = $1 AND customer_group_id = $2 AND store_id = $3",
+    )
+    .bind(id)
+    .bind(group_id)
+    .bind(store_id)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Price not found"));
+    }
+
+    Ok(())
+}
+
+/// The prices a customer group has for the given products
+pub async fn group_prices<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    customer_group: Option<&str>,
+    product_ids: &[Uuid],
+) -> Result<Vec<GroupPrice>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let Some(customer_group) = customer_group else {
+        return Ok(vec![]);
+    };
+    if product_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let prices = sqlx::query_as::<_, GroupPrice>(
+        r#"
+        SELECT gp.* FROM group_prices gp
+        INNER JOIN customer_groups cg ON cg.id = gp.customer_group_id
+        WHERE cg.store_id = $1 AND cg.code = $2 AND gp.product_id = ANY($3)
+        "#,
+    )
+    .bind(store_id)
+    .bind(customer_group)
+    .bind(product_ids)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(prices)
+}
@@ -0,0 +1,590 @@
+use std::collections::{HashMap, HashSet};
+
+use goseli_core::{
+    dto::{
+        CreateCarrierRequest, CreateFulfillmentRequest, ShipFulfillmentRequest,
+        UpdateCarrierRequest,
+    },
+    models::{
+        fulfillment::fulfillment_action, Carrier, Fulfillment, FulfillmentItem, FulfillmentStatus,
+        Order, OrderAction, OrderActor, OrderItem, OrderStatus,
+    },
+    ApiError, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::orders::apply_transition;
+use crate::payments::lock_order;
+
+const TRACKING_PLACEHOLDER: &str = "{tracking_number}";
+
+fn check_template(template: Option<&str>) -> Result<()> {
+    match template {
+        Some(template) if !template.contains(TRACKING_PLACEHOLDER) => {
+            Err(ApiError::validation(format!(
+                "Tracking URL template must contain {}",
+                TRACKING_PLACEHOLDER
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// List a store's carriers
+pub async fn list_carriers(pool: &PgPool, store_id: Uuid) -> Result<Vec<Carrier>> {
+    let carriers =
+        sqlx::query_as::<_, Carrier>("SELECT * FROM carriers WHERE store_id = $1 ORDER BY name")
+            .bind(store_id)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(carriers)
+}
+
+/// Add a carrier; codes are unique per store
+pub async fn create_carrier(
+    pool: &PgPool,
+    store_id: Uuid,
+    req: &CreateCarrierRequest,
+) -> Result<Carrier> {
+    check_template(req.tracking_url_template.as_deref())?;
+    let code = req.code.trim().to_lowercase();
+
+    let carrier = sqlx::query_as::<_, Carrier>(
+        r#"
+        INSERT INTO carriers (id, store_id, code, name, tracking_url_template, is_active)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (store_id, code) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&code)
+    .bind(&req.name)
+    .bind(&req.tracking_url_template)
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::conflict(format!("Carrier '{}' already exists", code)))?;
+
+    Ok(carrier)
+}
+
+/// Update a carrier
+pub async fn update_carrier(
+    pool: &PgPool,
+    id: Uuid,
+    req: &UpdateCarrierRequest,
+) -> Result<Carrier> {
+    check_template(req.tracking_url_template.as_deref())?;
+
+    let carrier = sqlx::query_as::<_, Carrier>(
+        r#"
+        UPDATE carriers SET
+            name = COALESCE($2, name),
+            tracking_url_template = COALESCE($3, tracking_url_template),
+            is_active = COALESCE($4, is_active)
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&req.tracking_url_template)
+    .bind(req.is_active)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Carrier not found"))?;
+
+    Ok(carrier)
+}
+
+/// Delete a carrier; past fulfillments keep their code and tracking URL
+pub async fn delete_carrier(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM carriers WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Tracking details for a parcel: an explicit URL wins, otherwise the
+/// carrier's template is rendered with the tracking number
+pub(crate) async fn tracking_details(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    carrier: Option<&str>,
+    tracking_number: Option<&str>,
+    tracking_url: Option<&str>,
+) -> Result<(Option<String>, Option<String>)> {
+    let code = carrier.map(|code| code.trim().to_lowercase());
+    if let Some(url) = tracking_url {
+        return Ok((code, Some(url.to_string())));
+    }
+    let (Some(code_ref), Some(number)) = (code.as_deref(), tracking_number) else {
+        return Ok((code, None));
+    };
+
+    let configured = sqlx::query_as::<_, Carrier>(
+        "SELECT * FROM carriers WHERE store_id = $1 AND code = $2 AND is_active",
+    )
+    .bind(store_id)
+    .bind(code_ref)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let url = configured.and_then(|carrier| carrier.tracking_url(number));
+
+    Ok((code, url))
+}
+
+/// Create a parcel for lines of a paid order.
+///
+/// Each line can go in parcels up to its ordered quantity, across all
+/// parcels that were not cancelled. Without explicit lines, everything that
+/// is not in a parcel yet is packed.
+pub async fn create_fulfillment(
+    pool: &PgPool,
+    order_id: Uuid,
+    req: &CreateFulfillmentRequest,
+    created_by: Uuid,
+) -> Result<(Fulfillment, Vec<FulfillmentItem>)> {
+    let mut tx = pool.begin().await?;
+
+    let order = lock_order(&mut tx, order_id).await?;
+    if !matches!(
+        order.status,
+        OrderStatus::Paid | OrderStatus::PartiallyFulfilled
+    ) {
+        return Err(ApiError::rule(
+            "order_not_fulfillable",
+            format!(
+                "Order {} is {} and cannot be fulfilled",
+                order.order_number, order.status
+            ),
+        ));
+    }
+
+    let items = sqlx::query_as::<_, OrderItem>("SELECT * FROM order_items WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let packed: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+        r#"
+        SELECT fi.order_item_id, SUM(fi.quantity)::BIGINT
+        FROM fulfillment_items fi
+        JOIN fulfillments f ON f.id = fi.fulfillment_id
+        WHERE f.order_id = $1 AND f.status <> 'cancelled'
+        GROUP BY fi.order_item_id
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+    let remaining =
+        |item: &OrderItem| i64::from(item.quantity) - packed.get(&item.id).copied().unwrap_or(0);
+
+    let lines: Vec<(Uuid, i32)> = if req.items.is_empty() {
+        items
+            .iter()
+            .filter(|item| remaining(item) > 0)
+            .map(|item| (item.id, remaining(item) as i32))
+            .collect()
+    } else {
+        let mut seen = HashSet::new();
+        for line in &req.items {
+            if !seen.insert(line.order_item_id) {
+                return Err(ApiError::validation("An order line is listed twice"));
+            }
+            let item = items
+                .iter()
+                .find(|item| item.id == line.order_item_id)
+                .ok_or_else(|| ApiError::validation("Order line not found on this order"))?;
+            if i64::from(line.quantity) > remaining(item) {
+                return Err(ApiError::rule(
+                    "fulfillment_quantity_exceeded",
+                    format!(
+                        "Only {} of {} still need to ship",
+                        remaining(item).max(0),
+                        item.product_name
+                    ),
+                ));
+            }
+        }
+        req.items
+            .iter()
+            .map(|line| (line.order_item_id, line.quantity))
+            .collect()
+    };
+    if lines.is_empty() {
+        return Err(ApiError::rule(
+            "nothing_to_fulfill",
+            format!(
+                "Every line of order {} is already packed",
+                order.order_number
+            ),
+        ));
+    }
+
+    let (carrier, tracking_url) = tracking_details(
+        &mut tx,
+        order.store_id,
+        req.carrier.as_deref(),
+        req.tracking_number.as_deref(),
+        req.tracking_url.as_deref(),
+    )
+    .await?;
+
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        r#"
+        INSERT INTO fulfillments (id, order_id, carrier, tracking_number, tracking_url, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(order_id)
+    .bind(&carrier)
+    .bind(&req.tracking_number)
+    .bind(&tracking_url)
+    .bind(created_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut fulfillment_items = Vec::with_capacity(lines.len());
+    for (order_item_id, quantity) in lines {
+        let item = sqlx::query_as::<_, FulfillmentItem>(
+            r#"
+            INSERT INTO fulfillment_items (id, fulfillment_id, order_item_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(fulfillment.id)
+        .bind(order_item_id)
+        .bind(quantity)
+        .fetch_one(&mut *tx)
+        .await?;
+        fulfillment_items.push(item);
+    }
+
+    tx.commit().await?;
+
+    Ok((fulfillment, fulfillment_items))
+}
+
+/// Lock a fulfillment and check it may move to `next`
+async fn lock_fulfillment(
+    conn: &mut PgConnection,
+    id: Uuid,
+    next: FulfillmentStatus,
+) -> Result<Fulfillment> {
+    let fulfillment =
+        sqlx::query_as::<_, Fulfillment>("SELECT * FROM fulfillments WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Fulfillment not found"))?;
+    fulfillment.status.transition(next)?;
+    Ok(fulfillment)
+}
+
+/// Move the order to partially fulfilled or fulfilled from what has shipped
+async fn roll_up_order(conn: &mut PgConnection, order: &Order, actor_id: Uuid) -> Result<Order> {
+    let lines: Vec<(i32, i64)> = sqlx::query_as(
+        r#"
+        SELECT oi.quantity, COALESCE(SUM(fi.quantity), 0)::BIGINT
+        FROM order_items oi
+        LEFT JOIN fulfillment_items fi ON fi.order_item_id = oi.id
+            AND fi.fulfillment_id IN (
+                SELECT id FROM fulfillments
+                WHERE order_id = $1 AND status IN ('shipped', 'delivered')
+            )
+        WHERE oi.order_id = $1
+        GROUP BY oi.id, oi.quantity
+        "#,
+    )
+    .bind(order.id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let action = match fulfillment_action(&lines) {
+        Some(action) if order.status.next(action).is_some() => action,
+        _ => return Ok(order.clone()),
+    };
+    let reason = match action {
+        OrderAction::Fulfill => "All lines shipped",
+        _ => "Some lines shipped",
+    };
+
+    apply_transition(
+        conn,
+        order.id,
+        action,
+        OrderActor::System,
+        Some(actor_id),
+        Some(reason),
+    )
+    .await
+}
+
+/// Hand a parcel to the carrier and roll the order status up.
+///
+/// Tracking details given here replace the ones set when the parcel was
+/// created.
+pub async fn ship_fulfillment(
+    pool: &PgPool,
+    id: Uuid,
+    req: &ShipFulfillmentRequest,
+    actor_id: Uuid,
+) -> Result<(Fulfillment, Order)> {
+    let mut tx = pool.begin().await?;
+
+    let current = lock_fulfillment(&mut tx, id, FulfillmentStatus::Shipped).await?;
+    let order = lock_order(&mut tx, current.order_id).await?;
+
+    let carrier = req.carrier.as_deref().or(current.carrier.as_deref());
+    let tracking_number = req
+        .tracking_number
+        .as_deref()
+        .or(current.tracking_number.as_deref());
+    // A new carrier or number invalidates a previously rendered URL
+    let tracking_url = match (&req.tracking_url, &req.carrier, &req.tracking_number) {
+        (Some(url), _, _) => Some(url.as_str()),
+        (None, None, None) => current.tracking_url.as_deref(),
+        _ => None,
+    };
+    let (carrier, tracking_url) = tracking_details(
+        &mut tx,
+        order.store_id,
+        carrier,
+        tracking_number,
+        tracking_url,
+    )
+    .await?;
+
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        r#"
+        UPDATE fulfillments SET
+            status = 'shipped', carrier = $2, tracking_number = $3, tracking_url = $4,
+            shipped_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&carrier)
+    .bind(tracking_number)
+    .bind(&tracking_url)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let order = roll_up_order(&mut tx, &order, actor_id).await?;
+
+    tx.commit().await?;
+
+    Ok((fulfillment, order))
+}
+
+/// Record that a shipped parcel arrived
+pub async fn deliver_fulfillment(pool: &PgPool, id: Uuid) -> Result<Fulfillment> {
+    let mut tx = pool.begin().await?;
+    lock_fulfillment(&mut tx, id, FulfillmentStatus::Delivered).await?;
+
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        "UPDATE fulfillments SET status = 'delivered', delivered_at = NOW() WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(fulfillment)
+}
+
+/// Cancel a parcel that has not shipped; its lines can be packed again
+pub async fn cancel_fulfillment(pool: &PgPool, id: Uuid) -> Result<Fulfillment> {
+    let mut tx = pool.begin().await?;
+    lock_fulfillment(&mut tx, id, FulfillmentStatus::Cancelled).await?;
+
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        "UPDATE fulfillments SET status = 'cancelled' WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(fulfillment)
+}
+
+/// Get a fulfillment by ID
+pub async fn get_fulfillment(pool: &PgPool, id: Uuid) -> Result<Fulfillment> {
+    let fulfillment = sqlx::query_as::<_, Fulfillment>("SELECT * FROM fulfillments WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Fulfillment not found"))?;
+
+    Ok(fulfillment)
+}
+
+/// Get the lines of a fulfillment
+pub async fn get_fulfillment_items(
+    pool: &PgPool,
+    fulfillment_id: Uuid,
+) -> Result<Vec<FulfillmentItem>> {
+    let items = sqlx::query_as::<_, FulfillmentItem>(
+        "SELECT * FROM fulfillment_items WHERE fulfillment_id = $1 ORDER BY id ASC",
+    )
+    .bind(fulfillment_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// List an order's fulfillments, oldest first
+pub async fn list_order_fulfillments(pool: &PgPool, order_id: Uuid) -> Result<Vec<Fulfillment>> {
+    let fulfillments = sqlx::query_as::<_, Fulfillment>(
+        "SELECT * FROM fulfillments WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(fulfillments)
+}
+
+/// Get the lines of all of an order's fulfillments
+pub async fn list_order_fulfillment_items(
+    pool: &PgPool,
+    order_id: Uuid,
+) -> Result<Vec<FulfillmentItem>> {
+    let items = sqlx::query_as::<_, FulfillmentItem>(
+        r#"
+        SELECT fi.* FROM fulfillment_items fi
+        JOIN fulfillments f ON f.id = fi.fulfillment_id
+        WHERE f.order_id = $1
+        ORDER BY fi.id ASC
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use goseli_core::dto::FulfillmentLineRequest;
+
+    fn parcel(lines: &[(Uuid, i32)]) -> CreateFulfillmentRequest {
+        CreateFulfillmentRequest {
+            items: lines
+                .iter()
+                .map(|&(order_item_id, quantity)| FulfillmentLineRequest {
+                    order_item_id,
+                    quantity,
+                })
+                .collect(),
+            carrier: None,
+            tracking_number: None,
+            tracking_url: None,
+        }
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_shipping_parcels_rolls_the_order_up(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 3).await;
+        let item_id = crate::orders::get_order_items(&pool, order.id)
+            .await
+            .unwrap()[0]
+            .id;
+        create_carrier(
+            &pool,
+            store_id,
+            &CreateCarrierRequest {
+                code: "UPS".into(),
+                name: "UPS".into(),
+                tracking_url_template: Some("https://ups.test/{tracking_number}".into()),
+                is_active: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut first = parcel(&[(item_id, 1)]);
+        first.carrier = Some(" ups ".into());
+        first.tracking_number = Some("1Z".into());
+        let (first, items) = create_fulfillment(&pool, order.id, &first, staff_id)
+            .await
+            .unwrap();
+        assert_eq!(items[0].quantity, 1);
+        assert_eq!(first.carrier.as_deref(), Some("ups"));
+        assert_eq!(first.tracking_url.as_deref(), Some("https://ups.test/1Z"));
+        let (_, order_after) = ship_fulfillment(
+            &pool,
+            first.id,
+            &ShipFulfillmentRequest::default(),
+            staff_id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(order_after.status, OrderStatus::PartiallyFulfilled);
+
+        // No lines packs whatever is left
+        let (rest, items) = create_fulfillment(&pool, order.id, &parcel(&[]), staff_id)
+            .await
+            .unwrap();
+        assert_eq!(items[0].quantity, 2);
+        let (_, order_after) =
+            ship_fulfillment(&pool, rest.id, &ShipFulfillmentRequest::default(), staff_id)
+                .await
+                .unwrap();
+        assert_eq!(order_after.status, OrderStatus::Fulfilled);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_lines_cannot_be_packed_twice(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 3).await;
+        let item_id = crate::orders::get_order_items(&pool, order.id)
+            .await
+            .unwrap()[0]
+            .id;
+        let (first, _) = create_fulfillment(&pool, order.id, &parcel(&[(item_id, 3)]), staff_id)
+            .await
+            .unwrap();
+
+        let error = create_fulfillment(&pool, order.id, &parcel(&[(item_id, 1)]), staff_id)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "fulfillment_quantity_exceeded");
+        let error = create_fulfillment(&pool, order.id, &parcel(&[]), staff_id)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "nothing_to_fulfill");
+
+        // A cancelled parcel frees its lines again
+        cancel_fulfillment(&pool, first.id).await.unwrap();
+        create_fulfillment(&pool, order.id, &parcel(&[(item_id, 3)]), staff_id)
+            .await
+            .unwrap();
+    }
+}
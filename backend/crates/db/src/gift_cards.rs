@@ -0,0 +1,722 @@
+use goseli_core::{
+    dto::{IssueGiftCardRequest, UpdateGiftCardRequest},
+    models::{
+        gift_card::{generate_code, normalize_code},
+        GiftCard, GiftCardSource, GiftCardTransaction, GiftCardTransactionKind, Order,
+    },
+    ApiError, Currency, Money, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use time::{format_description::well_known::Rfc3339, Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// Generated codes are retried this many times when they collide with
+/// existing ones
+const GENERATE_ATTEMPTS: usize = 5;
+
+/// List a store's gift cards, newest first
+pub async fn list_gift_cards(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<GiftCard>> {
+    let cards = sqlx::query_as::<_, GiftCard>(
+        r#"
+        SELECT * FROM gift_cards
+        WHERE store_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(cards)
+}
+
+/// Count a store's gift cards
+pub async fn count_gift_cards(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM gift_cards WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Get a gift card
+pub async fn get_gift_card(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<GiftCard> {
+    sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_cards WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Gift card not found"))
+}
+
+/// Find a gift card by the code a customer typed
+pub async fn find_by_code(pool: &PgPool, store_id: Uuid, code: &str) -> Result<Option<GiftCard>> {
+    let card =
+        sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_cards WHERE store_id = $1 AND code = $2")
+            .bind(store_id)
+            .bind(normalize_code(code))
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(card)
+}
+
+/// A gift card's ledger, oldest first
+pub async fn list_transactions(
+    pool: &PgPool,
+    gift_card_id: Uuid,
+) -> Result<Vec<GiftCardTransaction>> {
+    let transactions = sqlx::query_as::<_, GiftCardTransaction>(
+        r#"
+        SELECT * FROM gift_card_transactions
+        WHERE gift_card_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(gift_card_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(transactions)
+}
+
+/// A gift card to issue
+pub struct NewGiftCard<'a> {
+    pub store_id: Uuid,
+    pub amount: Money,
+    /// Generated when None
+    pub code: Option<&'a str>,
+    pub expires_at: Option<OffsetDateTime>,
+    pub source: GiftCardSource,
+    pub order_id: Option<Uuid>,
+    pub order_item_id: Option<Uuid>,
+    pub return_id: Option<Uuid>,
+    pub recipient_email: Option<&'a str>,
+    pub note: Option<&'a str>,
+    /// Staff member issuing the card
+    pub created_by: Option<Uuid>,
+}
+
+/// Issue a gift card with its opening ledger entry
+pub async fn issue(conn: &mut PgConnection, new_card: &NewGiftCard<'_>) -> Result<GiftCard> {
+    let attempts = if new_card.code.is_some() {
+        1
+    } else {
+        GENERATE_ATTEMPTS
+    };
+
+    let mut issued = None;
+    for _ in 0..attempts {
+        let code = new_card
+            .code
+            .map(normalize_code)
+            .unwrap_or_else(generate_code);
+        issued = sqlx::query_as::<_, GiftCard>(
+            r#"
+            INSERT INTO gift_cards (
+                id, store_id, code, currency, initial_amount, balance, expires_at, source,
+                order_id, order_item_id, return_id, recipient_email, note
+            )
+            VALUES ($1, $2, $3, $4, $5, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (store_id, code) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(new_card.store_id)
+        .bind(&code)
+        .bind(new_card.amount.currency())
+        .bind(new_card.amount.amount())
+        .bind(new_card.expires_at)
+        .bind(new_card.source)
+        .bind(new_card.order_id)
+        .bind(new_card.order_item_id)
+        .bind(new_card.return_id)
+        .bind(new_card.recipient_email)
+        .bind(new_card.note)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if issued.is_some() {
+            break;
+        }
+    }
+    let card = issued.ok_or_else(|| match new_card.code {
+        Some(code) => ApiError::conflict(format!(
+            "Gift card code {} is already in use",
+            normalize_code(code)
+        )),
+        None => ApiError::conflict("Could not generate a unique gift card code"),
+    })?;
+
+    record(
+        conn,
+        &card,
+        GiftCardTransactionKind::Issue,
+        card.initial_amount,
+        None,
+        None,
+        new_card.created_by,
+    )
+    .await?;
+
+    Ok(card)
+}
+
+/// Issue a gift card from the admin
+pub async fn issue_gift_card(
+    pool: &PgPool,
+    store_id: Uuid,
+    currency: Currency,
+    req: &IssueGiftCardRequest,
+    actor_id: Uuid,
+) -> Result<GiftCard> {
+    req.validate_code()?;
+
+    let mut tx = pool.begin().await?;
+    let card = issue(
+        &mut tx,
+        &NewGiftCard {
+            store_id,
+            amount: Money::new(req.amount, currency),
+            code: req.code.as_deref(),
+            expires_at: req.expires_at,
+            source: GiftCardSource::Admin,
+            order_id: None,
+            order_item_id: None,
+            return_id: None,
+            recipient_email: req.recipient_email.as_deref(),
+            note: req.note.as_deref(),
+            created_by: Some(actor_id),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(card)
+}
+
+/// Disable or re-enable a card, or replace its expiry, recipient and note
+pub async fn update_gift_card(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    req: &UpdateGiftCardRequest,
+) -> Result<GiftCard> {
+    sqlx::query_as::<_, GiftCard>(
+        r#"
+        UPDATE gift_cards SET
+            is_active = COALESCE($3, is_active),
+            expires_at = $4,
+            recipient_email = $5,
+            note = $6
+        WHERE id = $1 AND store_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(store_id)
+    .bind(req.is_active)
+    .bind(req.expires_at)
+    .bind(&req.recipient_email)
+    .bind(&req.note)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Gift card not found"))
+}
+
+/// Add to (positive `amount`) or take from a card's balance; it cannot go
+/// below zero
+pub async fn adjust_balance(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    amount: i64,
+    note: &str,
+    actor_id: Uuid,
+) -> Result<GiftCard> {
+    if amount == 0 {
+        return Err(ApiError::validation("amount must not be zero"));
+    }
+
+    let mut tx = pool.begin().await?;
+    let card = sqlx::query_as::<_, GiftCard>(
+        "SELECT * FROM gift_cards WHERE id = $1 AND store_id = $2 FOR UPDATE",
+    )
+    .bind(id)
+    .bind(store_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Gift card not found"))?;
+    if card
+        .balance()
+        .checked_add(Money::new(amount, card.currency))?
+        .is_negative()
+    {
+        return Err(ApiError::rule(
+            "gift_card_balance_negative",
+            format!("Gift card {} only has {} left", card.code, card.balance()),
+        ));
+    }
+
+    let card = change_balance(
+        &mut tx,
+        &card,
+        GiftCardTransactionKind::Adjust,
+        amount,
+        None,
+        Some(note),
+        Some(actor_id),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(card)
+}
+
+/// The cards behind codes entered at checkout, in the order given, checked
+/// to be able to pay for an order in `currency` now. With `lock`, the rows
+/// stay locked until the transaction ends.
+pub async fn resolve_cards(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    currency: Currency,
+    codes: &[String],
+    lock: bool,
+) -> Result<Vec<GiftCard>> {
+    let mut wanted: Vec<String> = Vec::with_capacity(codes.len());
+    for code in codes.iter().map(|code| normalize_code(code)) {
+        if !wanted.contains(&code) {
+            wanted.push(code);
+        }
+    }
+    if wanted.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Locked in id order, so checkouts sharing cards cannot deadlock
+    let lock_clause = if lock { " FOR UPDATE" } else { "" };
+    let found = sqlx::query_as::<_, GiftCard>(&format!(
+        "SELECT * FROM gift_cards WHERE store_id = $1 AND code = ANY($2) ORDER BY id{}",
+        lock_clause
+    ))
+    .bind(store_id)
+    .bind(&wanted)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let now = OffsetDateTime::now_utc();
+    wanted
+        .iter()
+        .map(|code| {
+            let card = found
+                .iter()
+                .find(|card| &card.code == code)
+                .ok_or_else(|| {
+                    ApiError::rule(
+                        "gift_card_not_found",
+                        format!("{} is not a valid gift card code", code),
+                    )
+                })?;
+            card.check_redeemable(currency, now)?;
+            Ok(card.clone())
+        })
+        .collect()
+}
+
+/// Spend cards locked by [`resolve_cards`] on an order
+pub async fn redeem(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    redemptions: &[(GiftCard, Money)],
+) -> Result<()> {
+    for (card, amount) in redemptions {
+        if amount.is_zero() {
+            continue;
+        }
+        change_balance(
+            conn,
+            card,
+            GiftCardTransactionKind::Redeem,
+            -amount.amount(),
+            Some(order_id),
+            None,
+            None,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Issue a card for every unit of the gift card products on a paid order,
+/// worth what the customer paid for the unit, and send each to the customer.
+/// Free gift lines, which were paid for by no one, issue nothing.
+pub async fn issue_for_order(conn: &mut PgConnection, order: &Order) -> Result<Vec<GiftCard>> {
+    let lines: Vec<(Uuid, i64, i32, Option<i32>)> = sqlx::query_as(
+        r#"
+        SELECT oi.id, oi.total, oi.quantity, p.gift_card_validity_days
+        FROM order_items oi
+        INNER JOIN products p ON p.id = oi.product_id
+        WHERE oi.order_id = $1 AND p.is_gift_card AND oi.total > 0
+        ORDER BY oi.id
+        "#,
+    )
+    .bind(order.id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let now = OffsetDateTime::now_utc();
+    let mut cards = Vec::new();
+    for (order_item_id, total, quantity, validity_days) in lines {
+        // Discounts are spread over the units, a minor unit more on the first
+        // ones when they do not divide evenly
+        let amounts = Money::new(total, order.currency).split(quantity.max(0) as usize)?;
+        for amount in amounts {
+            let card = issue(
+                conn,
+                &NewGiftCard {
+                    store_id: order.store_id,
+                    amount,
+                    code: None,
+                    expires_at: validity_days.map(|days| now + Duration::days(i64::from(days))),
+                    source: GiftCardSource::Purchase,
+                    order_id: Some(order.id),
+                    order_item_id: Some(order_item_id),
+                    return_id: None,
+                    recipient_email: Some(&order.email),
+                    note: None,
+                    created_by: None,
+                },
+            )
+            .await?;
+            let payload = serde_json::json!({
+                "code": card.code,
+                "balance": card.balance(),
+                "expires_at": card
+                    .expires_at
+                    .map(|at| at.format(&Rfc3339))
+                    .transpose()
+                    .map_err(|e| ApiError::internal(e.to_string()))?,
+                "order_number": order.order_number,
+            });
+            crate::notifications::enqueue(
+                &mut *conn,
+                order.store_id,
+                &order.email,
+                "gift_card_issued",
+                &payload,
+                now,
+            )
+            .await?;
+            cards.push(card);
+        }
+    }
+
+    Ok(cards)
+}
+
+/// Give back what an order took from gift cards and disable the cards it
+/// bought, when it is cancelled or refunded in full
+pub async fn release_for_order(conn: &mut PgConnection, order_id: Uuid) -> Result<()> {
+    release_spent(conn, order_id, i64::MAX).await?;
+
+    sqlx::query(
+        "UPDATE gift_cards SET is_active = FALSE WHERE order_id = $1 AND source = 'purchase'",
+    )
+    .bind(order_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Give a refund's gift card share back to the cards the order was paid with
+pub async fn release_for_refund(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    amount: Money,
+) -> Result<()> {
+    release_spent(conn, order_id, amount.amount()).await
+}
+
+/// Give back up to `limit` of what an order took from gift cards, card by
+/// card; whatever was already given back is not given again
+async fn release_spent(conn: &mut PgConnection, order_id: Uuid, limit: i64) -> Result<()> {
+    let spent: Vec<(Uuid, i64)> = sqlx::query_as(
+        r#"
+        SELECT gift_card_id, -SUM(amount)::BIGINT
+        FROM gift_card_transactions
+        WHERE order_id = $1 AND kind IN ('redeem', 'release')
+        GROUP BY gift_card_id
+        HAVING SUM(amount) < 0
+        ORDER BY gift_card_id
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut left = limit;
+    for (gift_card_id, amount) in spent {
+        let amount = amount.min(left);
+        if amount <= 0 {
+            break;
+        }
+        let card =
+            sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_cards WHERE id = $1 FOR UPDATE")
+                .bind(gift_card_id)
+                .fetch_one(&mut *conn)
+                .await?;
+        change_balance(
+            conn,
+            &card,
+            GiftCardTransactionKind::Release,
+            amount,
+            Some(order_id),
+            None,
+            None,
+        )
+        .await?;
+        left -= amount;
+    }
+
+    Ok(())
+}
+
+/// Clear the balance of up to `limit` cards that expired by `now`, with an
+/// `expire` entry in each ledger. Returns how many were cleared.
+pub async fn expire_cards(pool: &PgPool, now: OffsetDateTime, limit: i64) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        WITH expired AS (
+            SELECT id, balance FROM gift_cards
+            WHERE expires_at <= $1 AND balance > 0
+            ORDER BY expires_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        ),
+        ledger AS (
+            INSERT INTO gift_card_transactions (gift_card_id, kind, amount, balance_after)
+            SELECT id, 'expire', -balance, 0 FROM expired
+        )
+        UPDATE gift_cards g SET balance = 0
+        FROM expired
+        WHERE g.id = expired.id
+        "#,
+    )
+    .bind(now)
+    .bind(limit)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Move a locked card's balance by `amount` and record it in the ledger
+async fn change_balance(
+    conn: &mut PgConnection,
+    card: &GiftCard,
+    kind: GiftCardTransactionKind,
+    amount: i64,
+    order_id: Option<Uuid>,
+    note: Option<&str>,
+    created_by: Option<Uuid>,
+) -> Result<GiftCard> {
+    let card = sqlx::query_as::<_, GiftCard>(
+        "UPDATE gift_cards SET balance = balance + $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(card.id)
+    .bind(amount)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    record(conn, &card, kind, amount, order_id, note, created_by).await?;
+
+    Ok(card)
+}
+
+/// Append a ledger entry for a card whose balance is already updated
+async fn record(
+    conn: &mut PgConnection,
+    card: &GiftCard,
+    kind: GiftCardTransactionKind,
+    amount: i64,
+    order_id: Option<Uuid>,
+    note: Option<&str>,
+    created_by: Option<Uuid>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO gift_card_transactions (
+            id, gift_card_id, kind, amount, balance_after, order_id, note, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(card.id)
+    .bind(kind)
+    .bind(amount)
+    .bind(card.balance)
+    .bind(order_id)
+    .bind(note)
+    .bind(created_by)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{self, NewOrder};
+    use crate::test_support;
+    use goseli_core::models::{OrderAction, OrderActor};
+
+    async fn gift_card_product(pool: &PgPool, store_id: Uuid, price: i64) -> Uuid {
+        let product_id = test_support::product(pool, store_id, price, 10).await;
+        sqlx::query("UPDATE products SET is_gift_card = TRUE WHERE id = $1")
+            .bind(product_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        product_id
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cards_are_issued_at_what_was_paid(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let card_product = gift_card_product(&pool, store_id, 5000).await;
+        test_support::promotion(
+            &pool,
+            store_id,
+            serde_json::json!({ "type": "amount_off", "amount": 100 }),
+        )
+        .await;
+        test_support::promotion(
+            &pool,
+            store_id,
+            serde_json::json!({ "type": "free_gift", "product_id": card_product }),
+        )
+        .await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(&pool, cart_id, card_product, 3).await;
+
+        let order = orders::place_order(
+            &pool,
+            cart_id,
+            &NewOrder {
+                email: "ada@example.com".into(),
+                ..NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(order.total.amount(), 14_900);
+        orders::transition_order(
+            &pool,
+            order.id,
+            OrderAction::Pay,
+            OrderActor::System,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let items = orders::get_order_items(&pool, order.id).await.unwrap();
+        assert!(items
+            .iter()
+            .any(|item| item.quantity == 1 && item.total.is_zero()));
+
+        // Three bought units share the discount; the free one issues nothing
+        let amounts: Vec<i64> = sqlx::query_scalar(
+            "SELECT initial_amount FROM gift_cards WHERE order_id = $1 ORDER BY initial_amount",
+        )
+        .bind(order.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(amounts, vec![4966, 4967, 4967]);
+    }
+
+    async fn checkout_with_cards(
+        pool: &PgPool,
+        store_id: Uuid,
+        product_id: Uuid,
+        codes: &[&str],
+    ) -> Result<Order> {
+        let cart_id = test_support::cart(pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(pool, cart_id, product_id, 1).await;
+        orders::place_order(
+            pool,
+            cart_id,
+            &NewOrder {
+                email: "ada@example.com".into(),
+                gift_card_codes: codes.iter().map(|code| code.to_string()).collect(),
+                ..NewOrder::default()
+            },
+        )
+        .await
+    }
+
+    async fn balance(pool: &PgPool, store_id: Uuid, code: &str) -> i64 {
+        find_by_code(pool, store_id, code)
+            .await
+            .unwrap()
+            .unwrap()
+            .balance
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cards_are_spent_in_the_order_given(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let small = test_support::gift_card(&pool, store_id, 3000).await;
+        let large = test_support::gift_card(&pool, store_id, 10_000).await;
+
+        let order = checkout_with_cards(&pool, store_id, product_id, &[&small, &large])
+            .await
+            .unwrap();
+
+        assert_eq!(order.gift_card_total.amount(), 5000);
+        assert_eq!(balance(&pool, store_id, &small).await, 0);
+        assert_eq!(balance(&pool, store_id, &large).await, 8000);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cards_cannot_be_spent_past_their_balance(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let code = test_support::gift_card(&pool, store_id, 5000).await;
+        checkout_with_cards(&pool, store_id, product_id, &[&code])
+            .await
+            .unwrap();
+
+        let error = checkout_with_cards(&pool, store_id, product_id, &[&code])
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "gift_card_spent");
+
+        let card = find_by_code(&pool, store_id, &code).await.unwrap().unwrap();
+        adjust_balance(&pool, store_id, card.id, 1000, "Goodwill", staff_id)
+            .await
+            .unwrap();
+        let error = adjust_balance(&pool, store_id, card.id, -1001, "Typo", staff_id)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "gift_card_balance_negative");
+        assert_eq!(balance(&pool, store_id, &code).await, 1000);
+    }
+}
@@ -0,0 +1,187 @@
+use goseli_core::{models::IdempotencyRecord, Result};
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Outcome of trying to claim an idempotency key
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// First use of the key: the request should run, then be recorded under this ID
+    Claimed(Uuid),
+    /// The key was used before (the request may still be running)
+    Existing(IdempotencyRecord),
+}
+
+/// Claim `key` for a request, or return the record of its earlier use.
+///
+/// Expired records are discarded first, so a key can be reused after its TTL.
+/// A key still in progress since before `stale_before` was held by a request
+/// that never finished, so the same request can take it over. The takeover
+/// gets a new ID, so the stale request can no longer complete or release it.
+pub async fn claim(
+    pool: &PgPool,
+    scope: &str,
+    key: &str,
+    fingerprint: &str,
+    expires_at: OffsetDateTime,
+    stale_before: OffsetDateTime,
+) -> Result<IdempotencyClaim> {
+    sqlx::query(
+        "DELETE FROM idempotency_keys WHERE scope = $1 AND key = $2 AND expires_at < NOW()",
+    )
+    .bind(scope)
+    .bind(key)
+    .execute(pool)
+    .await?;
+
+    let claimed: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO idempotency_keys (scope, key, fingerprint, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (scope, key) DO UPDATE SET
+            id = uuid_generate_v7(),
+            locked_at = NOW(),
+            expires_at = EXCLUDED.expires_at
+        WHERE idempotency_keys.status = 'in_progress'
+          AND idempotency_keys.fingerprint = EXCLUDED.fingerprint
+          AND idempotency_keys.locked_at < $5
+        RETURNING id
+        "#,
+    )
+    .bind(scope)
+    .bind(key)
+    .bind(fingerprint)
+    .bind(expires_at)
+    .bind(stale_before)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(id) = claimed {
+        return Ok(IdempotencyClaim::Claimed(id));
+    }
+
+    let existing = sqlx::query_as::<_, IdempotencyRecord>(
+        "SELECT * FROM idempotency_keys WHERE scope = $1 AND key = $2",
+    )
+    .bind(scope)
+    .bind(key)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(IdempotencyClaim::Existing(existing))
+}
+
+/// Store the response of a claimed request for replay
+pub async fn complete(
+    pool: &PgPool,
+    id: Uuid,
+    status: i16,
+    headers: &serde_json::Value,
+    body: &[u8],
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE idempotency_keys SET
+            status = 'completed',
+            response_status = $2,
+            response_headers = $3,
+            response_body = $4
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(headers)
+    .bind(body)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Drop a claim so the request can be retried (used when it failed server-side)
+pub async fn release(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goseli_core::models::IdempotencyStatus;
+    use time::Duration;
+
+    async fn claim_at(
+        pool: &PgPool,
+        fingerprint: &str,
+        stale_before: OffsetDateTime,
+    ) -> IdempotencyClaim {
+        let expires_at = OffsetDateTime::now_utc() + Duration::hours(24);
+        claim(
+            pool,
+            "user:1",
+            "key-1",
+            fingerprint,
+            expires_at,
+            stale_before,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_claim_waits_for_a_running_request(pool: PgPool) {
+        let long_ago = OffsetDateTime::now_utc() - Duration::minutes(5);
+
+        let IdempotencyClaim::Claimed(id) = claim_at(&pool, "abc", long_ago).await else {
+            panic!("first use should claim the key");
+        };
+        let IdempotencyClaim::Existing(record) = claim_at(&pool, "abc", long_ago).await else {
+            panic!("a running request keeps its key");
+        };
+        assert_eq!(record.id, id);
+        assert_eq!(record.status, IdempotencyStatus::InProgress);
+
+        complete(&pool, id, 201, &serde_json::json!([]), b"{}")
+            .await
+            .unwrap();
+        let IdempotencyClaim::Existing(record) = claim_at(&pool, "abc", long_ago).await else {
+            panic!("a completed request is replayed");
+        };
+        assert_eq!(record.status, IdempotencyStatus::Completed);
+        assert_eq!(record.response_status, Some(201));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_stale_claim_is_taken_over(pool: PgPool) {
+        let IdempotencyClaim::Claimed(stale_id) =
+            claim_at(&pool, "abc", OffsetDateTime::now_utc()).await
+        else {
+            panic!("first use should claim the key");
+        };
+
+        // A different request never takes over the key
+        let later = OffsetDateTime::now_utc() + Duration::seconds(1);
+        assert!(matches!(
+            claim_at(&pool, "def", later).await,
+            IdempotencyClaim::Existing(_)
+        ));
+
+        let IdempotencyClaim::Claimed(id) = claim_at(&pool, "abc", later).await else {
+            panic!("a stale claim should be taken over");
+        };
+        assert_ne!(id, stale_id);
+
+        // The request that died can no longer touch the key
+        release(&pool, stale_id).await.unwrap();
+        let just_now = OffsetDateTime::now_utc() - Duration::minutes(1);
+        let IdempotencyClaim::Existing(record) = claim_at(&pool, "abc", just_now).await else {
+            panic!("the new claim holds the key");
+        };
+        assert_eq!(record.id, id);
+    }
+}
@@ -1,7 +1,31 @@
 // Goseli DB - SQLx queries, connection pool, migrations
 
+pub mod abandoned_carts;
 pub mod cart;
 pub mod categories;
+pub mod checkout;
+pub mod coupons;
+pub mod currencies;
+pub mod customer_groups;
+pub mod fulfillments;
+pub mod gift_cards;
+pub mod idempotency;
+pub mod notifications;
+pub mod orders;
+pub mod payment_webhooks;
+pub mod payments;
 pub mod products;
+pub mod promotions;
+pub mod refunds;
+pub mod retention;
+pub mod returns;
+pub mod sales;
+pub mod shipping;
+pub mod shipping_labels;
+pub mod stores;
+pub mod taxes;
 pub mod tokens;
 pub mod users;
+
+#[cfg(test)]
+mod test_support;
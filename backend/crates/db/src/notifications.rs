@@ -0,0 +1,89 @@
+use goseli_core::{models::Notification, Result};
+use sqlx::{Executor, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Queue a notification for delivery at or after `send_after`; inside a
+/// transaction, it is only sent if the transaction commits
+pub async fn enqueue<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    recipient: &str,
+    template: &str,
+    payload: &serde_json::Value,
+    send_after: OffsetDateTime,
+) -> Result<Notification>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let notification = sqlx::query_as::<_, Notification>(
+        r#"
+        INSERT INTO notifications (store_id, recipient, template, payload, send_after)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(recipient)
+    .bind(template)
+    .bind(payload)
+    .bind(send_after)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(notification)
+}
+
+/// Claim up to `limit` due notifications for delivery.
+///
+/// Uses `SKIP LOCKED` so several workers can drain the outbox concurrently.
+pub async fn claim_pending(pool: &PgPool, limit: i64) -> Result<Vec<Notification>> {
+    let notifications = sqlx::query_as::<_, Notification>(
+        r#"
+        UPDATE notifications SET attempts = attempts + 1
+        WHERE id IN (
+            SELECT id FROM notifications
+            WHERE status = 'pending' AND send_after <= NOW()
+            ORDER BY send_after
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notifications)
+}
+
+/// Mark a notification as delivered
+pub async fn mark_sent(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("UPDATE notifications SET status = 'sent', sent_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Record a delivery failure; gives up after `max_attempts`
+pub async fn mark_failed(pool: &PgPool, id: Uuid, error: &str, max_attempts: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE notifications SET
+            status = CASE WHEN attempts >= $3 THEN 'failed' ELSE 'pending' END,
+            last_error = $2,
+            send_after = NOW() + (attempts * INTERVAL '5 minutes')
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(max_attempts)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,1244 @@
+use std::collections::HashMap;
+
+use goseli_core::{
+    models::{
+        customization, price_lines,
+        promotion::discount_cart,
+        tax::{calculate_tax, tax_destination},
+        CartContext, DisplayPricing, GiftCard, GiftProduct, LineToPrice, Order, OrderAction,
+        OrderActor, OrderEvent, OrderItem, OrderStatus, PaymentStatus, PromotionLine,
+        QuantityRules, TaxClass, TaxSettings, TaxableLine,
+    },
+    ApiError, Currency, Money, Result,
+};
+use sqlx::{types::Json, PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A cart line joined with the live catalog data it will be snapshotted from
+#[derive(Clone, sqlx::FromRow)]
+struct CheckoutLine {
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    product_name: String,
+    variant_name: Option<String>,
+    sku: Option<String>,
+    properties: serde_json::Value,
+    unit_price: i64,
+    price_modifier: i64,
+    quantity: i32,
+    tax_class: TaxClass,
+    category_id: Option<Uuid>,
+    available: bool,
+    stock_quantity: i32,
+    #[sqlx(flatten)]
+    rules: QuantityRules,
+}
+
+/// Details copied onto a new order from the checkout session that places it
+#[derive(Debug, Clone, Default)]
+pub struct NewOrder {
+    pub user_id: Option<Uuid>,
+    pub email: String,
+    pub shipping_address: Option<serde_json::Value>,
+    pub billing_address: Option<serde_json::Value>,
+    pub notes: Option<String>,
+    pub shipping_method: Option<String>,
+    /// Price of the chosen shipping method in minor units of the store
+    /// currency, added to the order total
+    pub shipping_total: i64,
+    pub custom_fields: serde_json::Value,
+    /// Checkout session completed by this order
+    pub checkout_session_id: Option<Uuid>,
+    /// Gift cards to pay with first, in order
+    pub gift_card_codes: Vec<String>,
+}
+
+/// Quantity of a product+variant a customer bought in past orders.
+///
+/// Matches on the account or, for guest checkouts, the email address.
+/// Cancelled and refunded orders do not count.
+pub async fn purchased_quantity(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    user_id: Option<Uuid>,
+    email: Option<&str>,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<i64> {
+    if user_id.is_none() && email.is_none() {
+        return Ok(0);
+    }
+
+    let quantity: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(oi.quantity), 0)::BIGINT
+        FROM order_items oi
+        INNER JOIN orders o ON oi.order_id = o.id
+        WHERE o.store_id = $1
+          AND o.status NOT IN ('cancelled', 'refunded')
+          AND (o.user_id = $2 OR lower(o.email) = lower($3))
+          AND oi.product_id = $4
+          AND oi.variant_id IS NOT DISTINCT FROM $5
+        "#,
+    )
+    .bind(store_id)
+    .bind(user_id)
+    .bind(email)
+    .bind(product_id)
+    .bind(variant_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(quantity)
+}
+
+/// Turn a cart into an order atomically.
+///
+/// Runs in one transaction: the cart and every product/variant it references
+/// are locked, stock and quantity rules are re-checked against the locked
+/// rows, line items are snapshotted (name, SKU, variant, unit price,
+/// customizations), promotions are applied and the cart's coupon is
+/// re-checked and redeemed, free gifts are added, gift cards are spent
+/// towards the total (paying the order if they cover it), stock is
+/// decremented, the cart is emptied and the checkout session (if any) is
+/// completed. Either all of it happens or none of it does.
+pub async fn place_order(pool: &PgPool, cart_id: Uuid, new_order: &NewOrder) -> Result<Order> {
+    let user_id = new_order.user_id;
+    let email = new_order.email.as_str();
+    let mut tx = pool.begin().await?;
+
+    // Serializes concurrent checkouts of the same cart; the loser finds it empty
+    let (store_id, currency, config, coupon_code): (
+        Uuid,
+        Currency,
+        serde_json::Value,
+        Option<String>,
+    ) = sqlx::query_as(
+        "SELECT c.store_id, s.currency, s.config, c.coupon_code FROM carts c
+         INNER JOIN stores s ON c.store_id = s.id
+         WHERE c.id = $1
+         FOR UPDATE OF c",
+    )
+    .bind(cart_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Cart not found"))?;
+
+    let now = OffsetDateTime::now_utc();
+    let promotions = crate::promotions::list_live_promotions(&mut *tx, store_id, now).await?;
+    let (gift_product_ids, gift_variant_ids): (Vec<Uuid>, Vec<Option<Uuid>>) = promotions
+        .iter()
+        .filter_map(|promotion| promotion.action.gift())
+        .unzip();
+    let gift_variant_ids: Vec<Uuid> = gift_variant_ids.into_iter().flatten().collect();
+
+    // Lock stock rows, gifts included, in a stable order so concurrent
+    // checkouts cannot deadlock
+    sqlx::query(
+        "SELECT id FROM products
+         WHERE id IN (SELECT product_id FROM cart_items WHERE cart_id = $1) OR id = ANY($2)
+         ORDER BY id
+         FOR UPDATE",
+    )
+    .bind(cart_id)
+    .bind(&gift_product_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "SELECT id FROM product_variants
+         WHERE id IN (SELECT variant_id FROM cart_items WHERE cart_id = $1) OR id = ANY($2)
+         ORDER BY id
+         FOR UPDATE",
+    )
+    .bind(cart_id)
+    .bind(&gift_variant_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    let lines = sqlx::query_as::<_, CheckoutLine>(
+        r#"
+        SELECT
+            ci.product_id,
+            ci.variant_id,
+            p.name as product_name,
+            pv.name as variant_name,
+            COALESCE(pv.sku, p.sku) as sku,
+            ci.properties,
+            (COALESCE(pv.price, p.price) + ci.price_modifier) as unit_price,
+            ci.price_modifier,
+            ci.quantity,
+            p.tax_class,
+            p.category_id,
+            (p.status = 'active' AND COALESCE(pv.is_active, true)) as available,
+            COALESCE(pv.stock_quantity, p.stock_quantity) as stock_quantity,
+            COALESCE(pv.min_quantity, p.min_quantity) as min_quantity,
+            COALESCE(pv.max_quantity, p.max_quantity) as max_quantity,
+            COALESCE(pv.quantity_step, p.quantity_step) as quantity_step,
+            COALESCE(pv.max_per_customer, p.max_per_customer) as max_per_customer
+        FROM cart_items ci
+        INNER JOIN products p ON ci.product_id = p.id
+        LEFT JOIN product_variants pv ON ci.variant_id = pv.id
+        WHERE ci.cart_id = $1
+        ORDER BY ci.created_at ASC
+        "#,
+    )
+    .bind(cart_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if lines.is_empty() {
+        return Err(ApiError::bad_request("Cart is empty"));
+    }
+
+    // Stock and limits apply to the product+variant, across differently customized lines
+    let mut totals: HashMap<(Uuid, Option<Uuid>), (i64, &CheckoutLine)> = HashMap::new();
+    for line in &lines {
+        if !line.available {
+            return Err(ApiError::rule(
+                "product_unavailable",
+                format!("{} is no longer available", line.product_name),
+            ));
+        }
+        totals
+            .entry((line.product_id, line.variant_id))
+            .or_insert((0, line))
+            .0 += i64::from(line.quantity);
+    }
+
+    for (&(product_id, variant_id), &(quantity, line)) in &totals {
+        if quantity > i64::from(line.stock_quantity) {
+            return Err(ApiError::rule(
+                "insufficient_stock",
+                format!(
+                    "Not enough stock for {}. Available: {}, Requested: {}",
+                    line.product_name, line.stock_quantity, quantity
+                ),
+            ));
+        }
+
+        let previously_purchased = if line.rules.max_per_customer.is_some() {
+            purchased_quantity(
+                &mut tx,
+                store_id,
+                user_id,
+                Some(email),
+                product_id,
+                variant_id,
+            )
+            .await?
+        } else {
+            0
+        };
+        line.rules
+            .check(&line.product_name, quantity, previously_purchased)?;
+    }
+
+    let too_large = || ApiError::validation("Order total is too large");
+    let in_cart: HashMap<(Uuid, Option<Uuid>), i64> = totals
+        .iter()
+        .map(|(key, (quantity, _))| (*key, *quantity))
+        .collect();
+
+    // Sale items stay locked until the order is committed, so their caps
+    // cannot be overrun by concurrent checkouts. Group quantity breaks count
+    // every unit of a product or variant.
+    let product_ids: Vec<Uuid> = in_cart
+        .keys()
+        .map(|(product_id, _)| *product_id)
+        .chain(gift_product_ids.iter().copied())
+        .collect();
+    let sales = crate::sales::sale_prices(&mut *tx, store_id, &product_ids, now, true).await?;
+    let customer_group = crate::promotions::customer_group(&mut *tx, user_id).await?;
+    let group_prices = crate::customer_groups::group_prices(
+        &mut *tx,
+        store_id,
+        customer_group.as_deref(),
+        &product_ids,
+    )
+    .await?;
+    let pricing = DisplayPricing::store(currency)
+        .with_sales(sales)
+        .with_group_prices(group_prices);
+    let to_price: Vec<LineToPrice> = lines
+        .iter()
+        .map(|line| LineToPrice {
+            product_id: line.product_id,
+            variant_id: line.variant_id,
+            quantity: line.quantity,
+            price: Money::new(line.unit_price - line.price_modifier, currency),
+            surcharge: Money::new(line.price_modifier, currency),
+        })
+        .collect();
+    let priced = price_lines(&pricing, &to_price)?;
+    for (line, price) in lines.iter().zip(&priced.prices) {
+        customization::check_unit_price(&line.product_name, price.unit_price)?;
+        customization::check_unit_price(&line.product_name, price.regular_price)?;
+    }
+    // Units beyond a sale's cap go on a line of their own at the regular price
+    let lines: Vec<CheckoutLine> = priced
+        .parts
+        .iter()
+        .map(|part| CheckoutLine {
+            unit_price: part.unit_price.amount(),
+            quantity: part.quantity,
+            ..lines[part.line].clone()
+        })
+        .collect();
+
+    let line_totals = lines
+        .iter()
+        .map(|line| Money::new(line.unit_price, currency).checked_mul(i64::from(line.quantity)))
+        .collect::<std::result::Result<Vec<Money>, _>>()?;
+    let subtotal = Money::sum(currency, line_totals.iter().copied())?;
+    let mut shipping_total = Money::new(new_order.shipping_total, currency);
+
+    // The coupon row (and generated code) stay locked until the order is
+    // committed, so its limits cannot be overrun by concurrent checkouts
+    let coupon = match &coupon_code {
+        Some(code) => Some(
+            crate::coupons::resolve_coupon(&mut tx, store_id, code, user_id, Some(email), true)
+                .await?,
+        ),
+        None => None,
+    };
+    let gift_rows = crate::promotions::load_gifts(&mut *tx, &promotions).await?;
+    let gifts = gift_rows
+        .iter()
+        .map(|gift| gift.gift(&pricing, &in_cart))
+        .collect::<Result<Vec<GiftProduct>>>()?;
+    let promotion_lines: Vec<PromotionLine> = lines
+        .iter()
+        .zip(&line_totals)
+        .map(|(line, line_total)| PromotionLine {
+            product_id: line.product_id,
+            variant_id: line.variant_id,
+            category_id: line.category_id,
+            name: line.product_name.clone(),
+            quantity: line.quantity,
+            amount: *line_total,
+        })
+        .collect();
+    let ctx = CartContext {
+        lines: &promotion_lines,
+        customer_group: customer_group.as_deref(),
+        gifts: &gifts,
+        now,
+    };
+    let mut discounts = discount_cart(
+        &promotions,
+        coupon.as_ref().map(|(coupon, _)| coupon),
+        &ctx,
+        &pricing,
+    )?;
+    let discount_total = discounts.total()?;
+    let coupon_discount = match discounts.coupon.take() {
+        Some(result) => Some(result?),
+        None => None,
+    };
+    let waived_shipping = match &coupon_discount {
+        Some(discount) if discount.free_shipping => shipping_total,
+        _ => Money::zero(currency),
+    };
+    shipping_total = shipping_total.checked_sub(waived_shipping)?;
+    // Lines are charged, and taxed, after their discount
+    let line_totals = line_totals
+        .iter()
+        .zip(&discounts.lines)
+        .map(|(line_total, discount)| Ok(line_total.checked_sub(*discount)?.clamp_to_zero()))
+        .collect::<Result<Vec<Money>>>()?;
+
+    let tax_settings = TaxSettings::from_store_config(&config);
+    // Exempt customers are charged no tax at all
+    let exemption = match user_id {
+        Some(user_id) => crate::taxes::effective_exemption(&mut *tx, user_id, now).await?,
+        None => None,
+    };
+    let tax_rates = match exemption {
+        Some(_) => vec![],
+        None => crate::taxes::list_rates(&mut *tx, store_id, true).await?,
+    };
+    let taxable: Vec<TaxableLine> = lines
+        .iter()
+        .zip(&line_totals)
+        .map(|(line, line_total)| TaxableLine {
+            amount: line_total.amount(),
+            tax_class: line.tax_class,
+        })
+        .collect();
+    let tax = calculate_tax(
+        &tax_settings,
+        &tax_rates,
+        tax_destination(new_order.shipping_address.as_ref(), &config).as_ref(),
+        &taxable,
+        shipping_total.amount(),
+    )?;
+    let tax_total = Money::new(tax.total, currency);
+    let added_tax = if tax.prices_include_tax {
+        Money::zero(currency)
+    } else {
+        tax_total
+    };
+
+    let total = subtotal
+        .checked_sub(discount_total)?
+        .checked_add(shipping_total)?
+        .checked_add(added_tax)?;
+
+    // Cards stay locked until the order is committed, so a balance cannot
+    // be spent twice by concurrent checkouts
+    let cards = crate::gift_cards::resolve_cards(
+        &mut tx,
+        store_id,
+        currency,
+        &new_order.gift_card_codes,
+        true,
+    )
+    .await?;
+    let balances: Vec<Money> = cards.iter().map(|card| card.balance()).collect();
+    let gift_card_amounts = goseli_core::models::gift_card::allocate(&balances, total)?;
+    let gift_card_total = Money::sum(currency, gift_card_amounts.iter().copied())?;
+    let item_count = crate::cart::count_items(
+        lines
+            .iter()
+            .map(|line| line.quantity)
+            .chain(discounts.free_items.iter().map(|item| item.quantity)),
+    )?;
+
+    let billing_address = new_order
+        .billing_address
+        .clone()
+        .or_else(|| new_order.shipping_address.clone());
+    let custom_fields = match &new_order.custom_fields {
+        serde_json::Value::Null => serde_json::json!({}),
+        fields => fields.clone(),
+    };
+
+    let order_id = Uuid::now_v7();
+    let order = sqlx::query_as::<_, Order>(
+        r#"
+        INSERT INTO orders (
+            id, store_id, user_id, cart_id, email, currency,
+            subtotal, shipping_total, total, item_count, shipping_address, billing_address,
+            notes, shipping_method, custom_fields,
+            prices_include_tax, tax_total, shipping_tax, tax_lines, tax_exemption_id,
+            discount_total, coupon_code, promotions, gift_card_total
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+                $16, $17, $18, $19, $20, $21, $22, $23, $24)
+        RETURNING *
+        "#,
+    )
+    .bind(order_id)
+    .bind(store_id)
+    .bind(user_id)
+    .bind(cart_id)
+    .bind(email)
+    .bind(currency)
+    .bind(subtotal)
+    .bind(shipping_total)
+    .bind(total)
+    .bind(item_count)
+    .bind(&new_order.shipping_address)
+    .bind(&billing_address)
+    .bind(&new_order.notes)
+    .bind(&new_order.shipping_method)
+    .bind(&custom_fields)
+    .bind(tax.prices_include_tax)
+    .bind(tax_total)
+    .bind(tax.shipping.amount)
+    .bind(Json(&tax.summary))
+    .bind(exemption.map(|exemption| exemption.id))
+    .bind(discount_total)
+    .bind(
+        coupon
+            .as_ref()
+            .map(|(coupon, generated)| crate::coupons::redeemed_code(coupon, generated.as_ref())),
+    )
+    .bind(Json(&discounts.promotions))
+    .bind(gift_card_total)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for ((((line, part), line_total), discount), line_tax) in lines
+        .iter()
+        .zip(&priced.parts)
+        .zip(&line_totals)
+        .zip(&discounts.lines)
+        .zip(&tax.lines)
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO order_items (
+                id, order_id, product_id, variant_id, product_name, variant_name, sku,
+                properties, unit_price, price_modifier, quantity, total,
+                tax_class, tax_total, tax_lines, currency, discount_total, sale_item_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                    $17, $18)
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(order_id)
+        .bind(line.product_id)
+        .bind(line.variant_id)
+        .bind(&line.product_name)
+        .bind(&line.variant_name)
+        .bind(&line.sku)
+        .bind(&line.properties)
+        .bind(line.unit_price)
+        .bind(line.price_modifier)
+        .bind(line.quantity)
+        .bind(line_total)
+        .bind(line.tax_class)
+        .bind(line_tax.amount)
+        .bind(Json(&line_tax.lines))
+        .bind(currency)
+        .bind(discount)
+        .bind(part.sale_item_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    crate::sales::record_sold(&mut tx, &priced.sold).await?;
+
+    // Gifts are lines of their own, charged nothing
+    let mut stock_used: HashMap<(Uuid, Option<Uuid>), i64> = in_cart;
+    for item in &discounts.free_items {
+        let Some(gift) = gift_rows
+            .iter()
+            .find(|gift| gift.product_id == item.product_id && gift.variant_id == item.variant_id)
+        else {
+            continue;
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO order_items (
+                id, order_id, product_id, variant_id, product_name, variant_name, sku,
+                properties, unit_price, price_modifier, quantity, total,
+                tax_class, tax_total, tax_lines, currency, discount_total
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, '{}', $8, 0, $9, 0, $10, 0, '[]', $11, $12)
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(order_id)
+        .bind(gift.product_id)
+        .bind(gift.variant_id)
+        .bind(&gift.product_name)
+        .bind(&gift.variant_name)
+        .bind(&gift.sku)
+        .bind(gift.price)
+        .bind(item.quantity)
+        .bind(gift.tax_class)
+        .bind(currency)
+        .bind(item.value)
+        .execute(&mut *tx)
+        .await?;
+        *stock_used
+            .entry((item.product_id, item.variant_id))
+            .or_insert(0) += i64::from(item.quantity);
+    }
+
+    if let (Some((coupon, generated)), Some(discount)) = (&coupon, &coupon_discount) {
+        crate::coupons::record_redemption(
+            &mut tx,
+            &crate::coupons::NewRedemption {
+                coupon,
+                code: generated.as_ref(),
+                order_id,
+                user_id,
+                email,
+                amount: discount.total.checked_add(waived_shipping)?,
+            },
+        )
+        .await?;
+    }
+
+    // Gifts come on top of the cart's own units, so stock is checked again
+    for (&(product_id, variant_id), &quantity) in &stock_used {
+        let quantity = i32::try_from(quantity).map_err(|_| too_large())?;
+        let result = if let Some(vid) = variant_id {
+            sqlx::query(
+                "UPDATE product_variants SET stock_quantity = stock_quantity - $2
+                 WHERE id = $1 AND stock_quantity >= $2",
+            )
+            .bind(vid)
+            .bind(quantity)
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query(
+                "UPDATE products SET stock_quantity = stock_quantity - $2
+                 WHERE id = $1 AND stock_quantity >= $2",
+            )
+            .bind(product_id)
+            .bind(quantity)
+            .execute(&mut *tx)
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            let name = totals
+                .get(&(product_id, variant_id))
+                .map(|(_, line)| line.product_name.as_str())
+                .or_else(|| {
+                    gift_rows
+                        .iter()
+                        .find(|gift| gift.product_id == product_id && gift.variant_id == variant_id)
+                        .map(|gift| gift.product_name.as_str())
+                })
+                .unwrap_or("a product");
+            return Err(ApiError::rule(
+                "insufficient_stock",
+                format!("Not enough stock for {}", name),
+            ));
+        }
+    }
+
+    insert_event(
+        &mut tx,
+        order_id,
+        None,
+        OrderStatus::Pending,
+        OrderAction::Place,
+        OrderActor::Customer,
+        user_id,
+        None,
+    )
+    .await?;
+
+    let redemptions: Vec<(GiftCard, Money)> = cards.into_iter().zip(gift_card_amounts).collect();
+    crate::gift_cards::redeem(&mut tx, order_id, &redemptions).await?;
+    // Gift cards covering the whole total leave nothing for a payment
+    let order = if !gift_card_total.is_zero() && order.amount_due()?.is_zero() {
+        apply_transition(
+            &mut tx,
+            order_id,
+            OrderAction::Pay,
+            OrderActor::System,
+            None,
+            Some("Paid with gift cards"),
+        )
+        .await?
+    } else {
+        order
+    };
+
+    sqlx::query("DELETE FROM cart_items WHERE cart_id = $1")
+        .bind(cart_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE carts SET coupon_code = NULL WHERE id = $1")
+        .bind(cart_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if let Some(session_id) = new_order.checkout_session_id {
+        sqlx::query(
+            "UPDATE checkout_sessions SET status = 'completed', order_id = $2
+             WHERE id = $1 AND status = 'open'",
+        )
+        .bind(session_id)
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(order)
+}
+
+/// Apply a lifecycle action to an order.
+///
+/// The order row is locked while the transition is validated against the
+/// state machine, so concurrent requests (a customer cancelling while a
+/// payment webhook arrives) are applied one after the other. The change is
+/// recorded in `order_events` and its hooks run in the same transaction.
+pub async fn transition_order(
+    pool: &PgPool,
+    order_id: Uuid,
+    action: OrderAction,
+    actor: OrderActor,
+    actor_id: Option<Uuid>,
+    reason: Option<&str>,
+) -> Result<Order> {
+    let mut tx = pool.begin().await?;
+
+    let order = apply_transition(&mut tx, order_id, action, actor, actor_id, reason).await?;
+
+    tx.commit().await?;
+
+    Ok(order)
+}
+
+/// [`transition_order`] inside a caller's transaction
+pub async fn apply_transition(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    action: OrderAction,
+    actor: OrderActor,
+    actor_id: Option<Uuid>,
+    reason: Option<&str>,
+) -> Result<Order> {
+    let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+
+    let to_status = order.status.transition(action, actor)?;
+    if matches!(action, OrderAction::Cancel | OrderAction::Refund) {
+        check_no_payment_held(conn, order_id).await?;
+    }
+    let payment_status = match action {
+        OrderAction::Pay => PaymentStatus::Paid,
+        // Nothing is held by a payment any more, so whatever was paid (with
+        // gift cards) goes back
+        OrderAction::Cancel | OrderAction::Refund
+            if order.payment_status == PaymentStatus::Paid =>
+        {
+            PaymentStatus::Refunded
+        }
+        _ => order.payment_status,
+    };
+
+    let updated = sqlx::query_as::<_, Order>(
+        "UPDATE orders SET status = $2, payment_status = $3 WHERE id = $1 RETURNING *",
+    )
+    .bind(order_id)
+    .bind(to_status)
+    .bind(payment_status)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    insert_event(
+        conn,
+        order_id,
+        Some(order.status),
+        to_status,
+        action,
+        actor,
+        actor_id,
+        reason,
+    )
+    .await?;
+
+    run_transition_hooks(conn, &updated, action).await?;
+
+    Ok(updated)
+}
+
+/// An order can only be cancelled or refunded once no payment holds its
+/// customer's money: authorizations are voided and captured payments
+/// refunded through the payment provider first
+async fn check_no_payment_held(conn: &mut PgConnection, order_id: Uuid) -> Result<()> {
+    let (authorized, captured): (bool, bool) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(BOOL_OR(status IN ('requires_action', 'authorized')), false),
+            COALESCE(BOOL_OR(status = 'captured' AND amount_captured > amount_refunded), false)
+        FROM payments
+        WHERE order_id = $1
+        "#,
+    )
+    .bind(order_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if authorized {
+        return Err(ApiError::rule(
+            "order_payment_authorized",
+            "Void the order's payment before cancelling it",
+        ));
+    }
+    if captured {
+        return Err(ApiError::rule(
+            "order_payment_captured",
+            "Refund the order's payment through its refunds first",
+        ));
+    }
+    Ok(())
+}
+
+/// Side effects of a status change, run inside the transition's transaction
+async fn run_transition_hooks(
+    conn: &mut PgConnection,
+    order: &Order,
+    action: OrderAction,
+) -> Result<()> {
+    match action {
+        OrderAction::Pay => {
+            crate::gift_cards::issue_for_order(conn, order).await?;
+            Ok(())
+        }
+        // Cancellation happens before fulfillment, so everything goes back on the shelf
+        OrderAction::Cancel => {
+            restock_order(conn, order.id).await?;
+            crate::coupons::release_for_order(conn, order.id).await?;
+            crate::gift_cards::release_for_order(conn, order.id).await
+        }
+        OrderAction::Refund => {
+            crate::coupons::release_for_order(conn, order.id).await?;
+            crate::gift_cards::release_for_order(conn, order.id).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Return an order's quantities to product and variant stock
+async fn restock_order(conn: &mut PgConnection, order_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE product_variants pv SET stock_quantity = pv.stock_quantity + t.quantity
+        FROM (
+            SELECT variant_id, SUM(quantity)::INTEGER as quantity
+            FROM order_items
+            WHERE order_id = $1 AND variant_id IS NOT NULL
+            GROUP BY variant_id
+        ) t
+        WHERE pv.id = t.variant_id
+        "#,
+    )
+    .bind(order_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE products p SET stock_quantity = p.stock_quantity + t.quantity
+        FROM (
+            SELECT product_id, SUM(quantity)::INTEGER as quantity
+            FROM order_items
+            WHERE order_id = $1 AND variant_id IS NULL AND product_id IS NOT NULL
+            GROUP BY product_id
+        ) t
+        WHERE p.id = t.product_id
+        "#,
+    )
+    .bind(order_id)
+    .execute(&mut *conn)
+    .await?;
+
+    // Units sold at a sale price count against its cap again
+    let lines: Vec<(Uuid, i32)> =
+        sqlx::query_as("SELECT id, quantity FROM order_items WHERE order_id = $1")
+            .bind(order_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    crate::sales::release_sold(conn, &lines).await?;
+
+    Ok(())
+}
+
+/// Append an entry to an order's status history
+#[allow(clippy::too_many_arguments)]
+async fn insert_event(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    from_status: Option<OrderStatus>,
+    to_status: OrderStatus,
+    action: OrderAction,
+    actor: OrderActor,
+    actor_id: Option<Uuid>,
+    reason: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO order_events (order_id, from_status, to_status, action, actor, actor_id, reason)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(order_id)
+    .bind(from_status)
+    .bind(to_status)
+    .bind(action)
+    .bind(actor)
+    .bind(actor_id)
+    .bind(reason)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Get an order's status history, oldest first
+pub async fn get_order_events(pool: &PgPool, order_id: Uuid) -> Result<Vec<OrderEvent>> {
+    let events = sqlx::query_as::<_, OrderEvent>(
+        "SELECT * FROM order_events WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
+
+/// Get an order by ID
+pub async fn get_order(pool: &PgPool, id: Uuid) -> Result<Order> {
+    let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))?;
+
+    Ok(order)
+}
+
+/// Get the line items of an order
+pub async fn get_order_items(pool: &PgPool, order_id: Uuid) -> Result<Vec<OrderItem>> {
+    let items = sqlx::query_as::<_, OrderItem>(
+        "SELECT * FROM order_items WHERE order_id = $1 ORDER BY id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// List orders for a store (admin view), newest first
+pub async fn list_orders(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Order>> {
+    let orders = sqlx::query_as::<_, Order>(
+        r#"
+        SELECT * FROM orders
+        WHERE store_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(orders)
+}
+
+/// Count orders for a store
+pub async fn count_orders(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM orders WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count.0)
+}
+
+/// List a customer's orders, newest first
+pub async fn list_user_orders(
+    pool: &PgPool,
+    store_id: Uuid,
+    user_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Order>> {
+    let orders = sqlx::query_as::<_, Order>(
+        r#"
+        SELECT * FROM orders
+        WHERE store_id = $1 AND user_id = $2
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(store_id)
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(orders)
+}
+
+/// Count a customer's orders
+pub async fn count_user_orders(pool: &PgPool, store_id: Uuid, user_id: Uuid) -> Result<i64> {
+    let count: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM orders WHERE store_id = $1 AND user_id = $2")
+            .bind(store_id)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(count.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn guest_order(email: &str) -> NewOrder {
+        NewOrder {
+            email: email.to_string(),
+            ..NewOrder::default()
+        }
+    }
+
+    async fn stock(pool: &PgPool, product_id: Uuid) -> i32 {
+        sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1")
+            .bind(product_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn cart_item_count(pool: &PgPool, cart_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM cart_items WHERE cart_id = $1")
+            .bind(cart_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn order_count(pool: &PgPool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn filled_cart(pool: &PgPool, store_id: Uuid, lines: &[(Uuid, i32)]) -> Uuid {
+        let cart_id = test_support::cart(pool, store_id, None, OffsetDateTime::now_utc()).await;
+        for &(product_id, quantity) in lines {
+            test_support::cart_item(pool, cart_id, product_id, quantity).await;
+        }
+        cart_id
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_place_order_snapshots_lines_and_takes_stock(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1250, 10).await;
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 3)]).await;
+
+        let order = place_order(&pool, cart_id, &guest_order("ada@example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(order.status, OrderStatus::Pending);
+        assert_eq!(order.subtotal.amount(), 3750);
+        assert_eq!(order.total.amount(), 3750);
+        assert_eq!(order.item_count, 3);
+        assert_eq!(stock(&pool, mug).await, 7);
+        assert_eq!(cart_item_count(&pool, cart_id).await, 0);
+
+        let items = get_order_items(&pool, order.id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].unit_price.amount(), 1250);
+        assert_eq!(items[0].quantity, 3);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_place_order_rejects_insufficient_stock(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let lamp = test_support::product(&pool, store_id, 5000, 1).await;
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 2), (lamp, 2)]).await;
+
+        let error = place_order(&pool, cart_id, &guest_order("ada@example.com"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code(), "insufficient_stock");
+        assert_eq!(stock(&pool, mug).await, 10);
+        assert_eq!(stock(&pool, lamp).await, 1);
+        assert_eq!(cart_item_count(&pool, cart_id).await, 2);
+        assert_eq!(order_count(&pool).await, 0);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_place_order_rechecks_quantity_rules(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        sqlx::query("UPDATE products SET max_per_customer = 3 WHERE id = $1")
+            .bind(mug)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let first = filled_cart(&pool, store_id, &[(mug, 2)]).await;
+        place_order(&pool, first, &guest_order("ada@example.com"))
+            .await
+            .unwrap();
+
+        // Past orders count towards the limit, matched on the email address
+        let second = filled_cart(&pool, store_id, &[(mug, 2)]).await;
+        let error = place_order(&pool, second, &guest_order("ADA@example.com"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "customer_limit_exceeded");
+
+        // The rule changed after the items went in the cart
+        sqlx::query("UPDATE products SET max_per_customer = NULL, max_quantity = 1 WHERE id = $1")
+            .bind(mug)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let error = place_order(&pool, second, &guest_order("bo@example.com"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "quantity_above_maximum");
+        assert_eq!(stock(&pool, mug).await, 8);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_gift_promotions_share_the_gift_stock(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let tote = test_support::product(&pool, store_id, 1200, 3).await;
+        for _ in 0..2 {
+            test_support::promotion(
+                &pool,
+                store_id,
+                serde_json::json!({ "type": "free_gift", "product_id": tote, "quantity": 2 }),
+            )
+            .await;
+        }
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 1)]).await;
+
+        // Only one promotion's totes fit in the three left
+        let order = place_order(&pool, cart_id, &guest_order("ada@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(order.item_count, 3);
+        assert_eq!(stock(&pool, tote).await, 1);
+
+        let items = get_order_items(&pool, order.id).await.unwrap();
+        let totes: i32 = items
+            .iter()
+            .filter(|item| item.product_id == Some(tote))
+            .map(|item| item.quantity)
+            .sum();
+        assert_eq!(totes, 2);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_concurrent_submits_place_one_order(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 4)]).await;
+        let new_order = guest_order("ada@example.com");
+
+        let (first, second) = tokio::join!(
+            place_order(&pool, cart_id, &new_order),
+            place_order(&pool, cart_id, &new_order)
+        );
+
+        // The second checkout waits for the cart lock, then finds it empty
+        assert_eq!([&first, &second].iter().filter(|r| r.is_ok()).count(), 1);
+        let error = first.err().or(second.err()).unwrap();
+        assert!(error.to_string().contains("Cart is empty"), "{error}");
+        assert_eq!(order_count(&pool).await, 1);
+        assert_eq!(stock(&pool, mug).await, 6);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_failed_checkout_rolls_back_everything(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 2)]).await;
+
+        // Fail the last write, after the order, its lines and the stock
+        // update have gone through
+        sqlx::query(
+            r#"
+            CREATE FUNCTION fail_order_events() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'order events are down';
+            END
+            $$ LANGUAGE plpgsql
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "CREATE TRIGGER fail_order_events BEFORE INSERT ON order_events
+             FOR EACH ROW EXECUTE FUNCTION fail_order_events()",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let error = place_order(&pool, cart_id, &guest_order("ada@example.com"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ApiError::Database(_)), "{error}");
+
+        assert_eq!(order_count(&pool).await, 0);
+        assert_eq!(stock(&pool, mug).await, 10);
+        assert_eq!(cart_item_count(&pool, cart_id).await, 1);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cancel_waits_for_the_payment_to_be_released(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let cart_id = filled_cart(&pool, store_id, &[(mug, 2)]).await;
+        let order = place_order(&pool, cart_id, &guest_order("ada@example.com"))
+            .await
+            .unwrap();
+        let cancel = || {
+            transition_order(
+                &pool,
+                order.id,
+                OrderAction::Cancel,
+                OrderActor::Admin,
+                None,
+                None,
+            )
+        };
+
+        let payment_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO payments (order_id, provider, status, currency, amount)
+             VALUES ($1, 'mock', 'authorized', 'USD', 2000) RETURNING id",
+        )
+        .bind(order.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            cancel().await.unwrap_err().code(),
+            "order_payment_authorized"
+        );
+
+        sqlx::query(
+            "UPDATE payments SET status = 'captured', amount_captured = 2000 WHERE id = $1",
+        )
+        .bind(payment_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        transition_order(
+            &pool,
+            order.id,
+            OrderAction::Pay,
+            OrderActor::System,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(cancel().await.unwrap_err().code(), "order_payment_captured");
+        assert_eq!(stock(&pool, mug).await, 8);
+
+        // Refunded through the provider, the order can go
+        sqlx::query("UPDATE payments SET amount_refunded = 2000 WHERE id = $1")
+            .bind(payment_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let cancelled = cancel().await.unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+        assert_eq!(cancelled.payment_status, PaymentStatus::Refunded);
+        assert_eq!(stock(&pool, mug).await, 10);
+    }
+}
@@ -0,0 +1,105 @@
+use goseli_core::{
+    models::{PaymentWebhookEvent, WebhookEventStatus},
+    Result,
+};
+use sqlx::PgPool;
+use time::Duration;
+use uuid::Uuid;
+
+/// Store a verified webhook event.
+///
+/// Returns false when the provider already delivered this event, so
+/// redeliveries are acknowledged without being applied twice.
+pub async fn record_event(
+    pool: &PgPool,
+    provider: &str,
+    event_id: &str,
+    event_type: &str,
+    payload: &str,
+) -> Result<bool> {
+    let inserted: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO payment_webhook_events (provider, event_id, event_type, payload)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (provider, event_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(provider)
+    .bind(event_id)
+    .bind(event_type)
+    .bind(payload)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(inserted.is_some())
+}
+
+/// Claim up to `limit` due events for processing.
+///
+/// Claimed events are pushed `lease` into the future, so another worker
+/// only picks one up again if this one dies before finishing it.
+pub async fn claim_pending(
+    pool: &PgPool,
+    limit: i64,
+    lease: Duration,
+) -> Result<Vec<PaymentWebhookEvent>> {
+    let events = sqlx::query_as::<_, PaymentWebhookEvent>(
+        r#"
+        UPDATE payment_webhook_events SET
+            attempts = attempts + 1,
+            process_after = NOW() + make_interval(secs => $2)
+        WHERE id IN (
+            SELECT id FROM payment_webhook_events
+            WHERE status = 'pending' AND process_after <= NOW()
+            ORDER BY received_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(limit)
+    .bind(lease.as_seconds_f64())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
+
+/// Mark an event as applied (or deliberately ignored)
+pub async fn mark_done(pool: &PgPool, id: Uuid, status: WebhookEventStatus) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE payment_webhook_events
+        SET status = $2, last_error = NULL, processed_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a processing failure; retried with backoff, given up after `max_attempts`
+pub async fn mark_failed(pool: &PgPool, id: Uuid, error: &str, max_attempts: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE payment_webhook_events SET
+            status = CASE WHEN attempts >= $3 THEN 'failed' ELSE 'pending' END,
+            last_error = $2,
+            process_after = NOW() + (attempts * INTERVAL '1 minute')
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(max_attempts)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,349 @@
+use goseli_core::{
+    models::{
+        Order, OrderAction, OrderActor, OrderStatus, Payment, PaymentAttempt, PaymentAttemptStatus,
+        PaymentOperation, PaymentState, PaymentStatus,
+    },
+    ApiError, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Provider state to copy onto a payment
+#[derive(Debug, Clone)]
+pub struct PaymentUpdate {
+    pub status: PaymentState,
+    pub provider_payment_id: Option<String>,
+    pub amount_captured: i64,
+    pub failure_reason: Option<String>,
+}
+
+/// Load an order and lock it for the rest of the transaction
+pub(crate) async fn lock_order(conn: &mut PgConnection, order_id: Uuid) -> Result<Order> {
+    sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+        .bind(order_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Order not found"))
+}
+
+/// Start paying an order through `provider`.
+///
+/// The amount and currency are taken from the order (its total, less what
+/// gift cards paid), never from the client. An order can only have one live payment at a time.
+pub async fn create_payment(pool: &PgPool, order_id: Uuid, provider: &str) -> Result<Payment> {
+    let mut tx = pool.begin().await?;
+
+    let order = lock_order(&mut tx, order_id).await?;
+    if order.status != OrderStatus::Pending || order.payment_status == PaymentStatus::Paid {
+        return Err(ApiError::rule(
+            "order_not_payable",
+            format!(
+                "Order {} is {} and cannot be paid",
+                order.order_number, order.status
+            ),
+        ));
+    }
+    // Gift cards may already cover part of the total
+    let due = order.amount_due()?;
+    if due.amount() <= 0 {
+        return Err(ApiError::rule(
+            "order_not_payable",
+            format!("Order {} has nothing to pay", order.order_number),
+        ));
+    }
+    let amount = due.amount();
+
+    let live: Option<PaymentState> = sqlx::query_scalar(
+        r#"
+        SELECT status FROM payments
+        WHERE order_id = $1 AND status IN ('pending', 'requires_action', 'authorized', 'captured')
+        "#,
+    )
+    .bind(order_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(status) = live {
+        return Err(ApiError::conflict(format!(
+            "This order already has a payment that is {}",
+            status
+        )));
+    }
+
+    let payment = sqlx::query_as::<_, Payment>(
+        r#"
+        INSERT INTO payments (id, order_id, provider, currency, amount)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(order_id)
+    .bind(provider)
+    .bind(order.currency)
+    .bind(amount)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(payment)
+}
+
+/// Find a payment by the provider's ID for it
+pub async fn find_by_provider_payment_id(
+    pool: &PgPool,
+    provider: &str,
+    provider_payment_id: &str,
+) -> Result<Option<Payment>> {
+    let payment = sqlx::query_as::<_, Payment>(
+        "SELECT * FROM payments WHERE provider = $1 AND provider_payment_id = $2",
+    )
+    .bind(provider)
+    .bind(provider_payment_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(payment)
+}
+
+/// Get a payment by ID
+pub async fn get_payment(pool: &PgPool, id: Uuid) -> Result<Payment> {
+    let payment = sqlx::query_as::<_, Payment>("SELECT * FROM payments WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Payment not found"))?;
+
+    Ok(payment)
+}
+
+/// Get an order's payments, oldest first
+pub async fn list_order_payments(pool: &PgPool, order_id: Uuid) -> Result<Vec<Payment>> {
+    let payments = sqlx::query_as::<_, Payment>(
+        "SELECT * FROM payments WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(payments)
+}
+
+/// Get the provider calls made for a payment, oldest first
+pub async fn list_attempts(pool: &PgPool, payment_id: Uuid) -> Result<Vec<PaymentAttempt>> {
+    let attempts = sqlx::query_as::<_, PaymentAttempt>(
+        "SELECT * FROM payment_attempts WHERE payment_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(payment_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attempts)
+}
+
+/// Record a provider call before it is made
+pub async fn start_attempt(
+    pool: &PgPool,
+    payment_id: Uuid,
+    operation: PaymentOperation,
+    amount: i64,
+) -> Result<PaymentAttempt> {
+    let attempt = sqlx::query_as::<_, PaymentAttempt>(
+        r#"
+        INSERT INTO payment_attempts (id, payment_id, operation, amount)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(payment_id)
+    .bind(operation)
+    .bind(amount)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(attempt)
+}
+
+/// Record the outcome of a provider call
+pub async fn finish_attempt(
+    pool: &PgPool,
+    id: Uuid,
+    status: PaymentAttemptStatus,
+    provider_reference: Option<&str>,
+    error: Option<&str>,
+) -> Result<PaymentAttempt> {
+    let attempt = sqlx::query_as::<_, PaymentAttempt>(
+        r#"
+        UPDATE payment_attempts
+        SET status = $2, provider_reference = $3, error = $4, completed_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(provider_reference)
+    .bind(error)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(attempt)
+}
+
+/// Copy the provider's view of a payment onto it and bring the order along.
+///
+/// Updates that would move the payment backwards (see
+/// [`PaymentState::can_become`]) are ignored, so late or replayed webhooks
+/// are harmless.
+///
+/// - authorized: the order's payment status becomes `authorized`
+/// - voided / failed: an authorized order goes back to `unpaid`
+/// - captured: the order is paid, but only if the captured amount matches
+///   what is due on the order in the order currency
+pub async fn record_provider_state(
+    pool: &PgPool,
+    payment_id: Uuid,
+    update: &PaymentUpdate,
+) -> Result<(Payment, Order)> {
+    let mut tx = pool.begin().await?;
+
+    let order_id: Uuid = sqlx::query_scalar("SELECT order_id FROM payments WHERE id = $1")
+        .bind(payment_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Payment not found"))?;
+    let mut order = lock_order(&mut tx, order_id).await?;
+
+    let current = sqlx::query_as::<_, Payment>("SELECT * FROM payments WHERE id = $1")
+        .bind(payment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if current.status != update.status && !current.status.can_become(update.status) {
+        tracing::debug!(
+            "Ignoring stale update of payment {} from {} to {}",
+            payment_id,
+            current.status,
+            update.status
+        );
+        tx.commit().await?;
+        return Ok((current, order));
+    }
+
+    let payment = sqlx::query_as::<_, Payment>(
+        r#"
+        UPDATE payments SET
+            status = $2,
+            provider_payment_id = COALESCE($3, provider_payment_id),
+            amount_captured = $4,
+            failure_reason = $5
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(payment_id)
+    .bind(update.status)
+    .bind(&update.provider_payment_id)
+    .bind(update.amount_captured)
+    .bind(&update.failure_reason)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let payment_status = match (payment.status, order.payment_status) {
+        (PaymentState::Authorized, PaymentStatus::Unpaid) => Some(PaymentStatus::Authorized),
+        (PaymentState::Voided | PaymentState::Failed, PaymentStatus::Authorized) => {
+            Some(PaymentStatus::Unpaid)
+        }
+        _ => None,
+    };
+    if let Some(payment_status) = payment_status {
+        order = sqlx::query_as::<_, Order>(
+            "UPDATE orders SET payment_status = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(order_id)
+        .bind(payment_status)
+        .fetch_one(&mut *tx)
+        .await?;
+    }
+
+    let reconciled = goseli_core::models::payment::reconcile(&order, payment.amount_captured);
+    if payment.status == PaymentState::Captured && order.status == OrderStatus::Pending {
+        match reconciled {
+            Ok(()) => {
+                order = crate::orders::apply_transition(
+                    &mut tx,
+                    order_id,
+                    OrderAction::Pay,
+                    OrderActor::System,
+                    None,
+                    Some("Payment captured"),
+                )
+                .await?;
+            }
+            Err(e) => tracing::error!(
+                "Payment {} captured but not reconciled, order {} left unpaid: {}",
+                payment.id,
+                order.id,
+                e
+            ),
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok((payment, order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{orders, refunds, test_support};
+    use goseli_core::dto::CreateRefundRequest;
+    use time::OffsetDateTime;
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_orders_above_i32_can_be_paid_and_refunded(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let press = test_support::product(&pool, store_id, 1_500_000_000, 10).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(&pool, cart_id, press, 2).await;
+        let order = orders::place_order(
+            &pool,
+            cart_id,
+            &orders::NewOrder {
+                email: "buyer@example.com".to_string(),
+                ..orders::NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let payment = create_payment(&pool, order.id, "mock").await.unwrap();
+        assert_eq!(payment.amount.amount(), 3_000_000_000);
+
+        sqlx::query(
+            "UPDATE payments SET status = 'captured', amount_captured = amount WHERE id = $1",
+        )
+        .bind(payment.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let (refund, items) = refunds::create_refund(
+            &pool,
+            order.id,
+            &CreateRefundRequest {
+                full: true,
+                items: vec![],
+                shipping: false,
+                amount: None,
+                restock: false,
+                reason: "Cancelled by the buyer".to_string(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(refund.amount.amount(), 3_000_000_000);
+        assert_eq!(items[0].amount, 3_000_000_000);
+    }
+}
@@ -1,9 +1,13 @@
 use goseli_core::{
-    dto::{CreateProductRequest, ProductListParams, UpdateProductRequest},
+    dto::{
+        CreateProductRequest, CreateVariantRequest, ProductListParams, UpdateProductRequest,
+        UpdateVariantRequest,
+    },
     models::{Product, ProductImage, ProductVariant},
-    Result,
+    ApiError, Result,
 };
 use sqlx::PgPool;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Generate a URL-safe slug from a string
@@ -36,7 +40,10 @@ pub async fn list_products(
     let search_pattern = filters.q.as_ref().map(|s| format!("%{}%", s));
     let status_str = filters.status.as_ref().map(|s| s.to_string());
 
-    let mut query = sqlx::QueryBuilder::new("SELECT * FROM products WHERE store_id = ");
+    let mut query = sqlx::QueryBuilder::new(
+        "SELECT *, (SELECT currency FROM stores WHERE stores.id = products.store_id) AS currency
+         FROM products WHERE store_id = ",
+    );
     query.push_bind(store_id);
 
     if let Some(category_id) = filters.category_id {
@@ -104,26 +111,69 @@ pub async fn count_products(
 
 /// Get product by ID
 pub async fn get_product_by_id(pool: &PgPool, id: Uuid) -> Result<Product> {
-    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
-        .bind(id)
-        .fetch_one(pool)
-        .await?;
+    let product = sqlx::query_as::<_, Product>(
+        "SELECT *, (SELECT currency FROM stores WHERE stores.id = products.store_id) AS currency
+         FROM products WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await?;
 
     Ok(product)
 }
 
 /// Get product by slug for a specific store
 pub async fn get_product_by_slug(pool: &PgPool, store_id: Uuid, slug: &str) -> Result<Product> {
-    let product =
-        sqlx::query_as::<_, Product>("SELECT * FROM products WHERE store_id = $1 AND slug = $2")
-            .bind(store_id)
-            .bind(slug)
-            .fetch_one(pool)
-            .await?;
+    let product = sqlx::query_as::<_, Product>(
+        "SELECT *, (SELECT currency FROM stores WHERE stores.id = products.store_id) AS currency
+         FROM products WHERE store_id = $1 AND slug = $2",
+    )
+    .bind(store_id)
+    .bind(slug)
+    .fetch_one(pool)
+    .await?;
 
     Ok(product)
 }
 
+/// Resolve SKUs to (product_id, variant_id) for a store.
+///
+/// Product SKUs resolve to the product itself; variant SKUs resolve to the
+/// variant and its parent product. Unknown SKUs are absent from the result.
+pub async fn resolve_skus(
+    pool: &PgPool,
+    store_id: Uuid,
+    skus: &[String],
+) -> Result<HashMap<String, (Uuid, Option<Uuid>)>> {
+    // A product SKU wins if a variant happens to share it
+    let rows = sqlx::query_as::<_, (String, Uuid, Option<Uuid>)>(
+        r#"
+        SELECT DISTINCT ON (sku) sku, product_id, variant_id FROM (
+            SELECT sku, id as product_id, NULL::UUID as variant_id, 0 as priority
+            FROM products
+            WHERE store_id = $1 AND sku = ANY($2)
+            UNION ALL
+            SELECT pv.sku, pv.product_id, pv.id, 1
+            FROM product_variants pv
+            INNER JOIN products p ON pv.product_id = p.id
+            WHERE p.store_id = $1 AND pv.sku = ANY($2)
+        ) matches
+        ORDER BY sku, priority, variant_id
+        "#,
+    )
+    .bind(store_id)
+    .bind(skus)
+    .fetch_all(pool)
+    .await?;
+
+    let resolved = rows
+        .into_iter()
+        .map(|(sku, product_id, variant_id)| (sku, (product_id, variant_id)))
+        .collect();
+
+    Ok(resolved)
+}
+
 /// Get product images
 pub async fn get_product_images(pool: &PgPool, product_id: Uuid) -> Result<Vec<ProductImage>> {
     let images = sqlx::query_as::<_, ProductImage>(
@@ -139,7 +189,13 @@ pub async fn get_product_images(pool: &PgPool, product_id: Uuid) -> Result<Vec<P
 /// Get product variants
 pub async fn get_product_variants(pool: &PgPool, product_id: Uuid) -> Result<Vec<ProductVariant>> {
     let variants = sqlx::query_as::<_, ProductVariant>(
-        "SELECT * FROM product_variants WHERE product_id = $1 ORDER BY sort_order",
+        r#"
+        SELECT *, (
+            SELECT s.currency FROM products p INNER JOIN stores s ON s.id = p.store_id
+            WHERE p.id = product_variants.product_id
+        ) AS currency
+        FROM product_variants WHERE product_id = $1 ORDER BY sort_order
+        "#,
     )
     .bind(product_id)
     .fetch_all(pool)
@@ -172,9 +228,15 @@ pub async fn create_product(
         INSERT INTO products (
             store_id, category_id, name, slug, description, short_description,
             price, compare_at_price, cost_price, sku, stock_quantity,
-            attributes, status, is_featured
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
-        RETURNING *
+            attributes, status, is_featured,
+            min_quantity, max_quantity, quantity_step, max_per_customer,
+            weight_grams, length_mm, width_mm, height_mm, tax_class,
+            is_gift_card, gift_card_validity_days
+        ) VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14,
+            $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25
+        )
+        RETURNING *, (SELECT currency FROM stores WHERE stores.id = products.store_id) AS currency
         "#,
     )
     .bind(store_id)
@@ -191,6 +253,17 @@ pub async fn create_product(
     .bind(&attributes)
     .bind(&status_str)
     .bind(is_featured)
+    .bind(req.min_quantity)
+    .bind(req.max_quantity)
+    .bind(req.quantity_step)
+    .bind(req.max_per_customer)
+    .bind(req.weight_grams)
+    .bind(req.length_mm)
+    .bind(req.width_mm)
+    .bind(req.height_mm)
+    .bind(req.tax_class.unwrap_or_default())
+    .bind(req.is_gift_card.unwrap_or(false))
+    .bind(req.gift_card_validity_days)
     .fetch_one(pool)
     .await?;
 
@@ -233,9 +306,20 @@ pub async fn update_product(
             attributes = COALESCE($12, attributes),
             status = $13,
             is_featured = COALESCE($14, is_featured),
+            min_quantity = CASE WHEN $26 THEN $15 ELSE min_quantity END,
+            max_quantity = CASE WHEN $27 THEN $16 ELSE max_quantity END,
+            quantity_step = CASE WHEN $28 THEN $17 ELSE quantity_step END,
+            max_per_customer = CASE WHEN $29 THEN $18 ELSE max_per_customer END,
+            weight_grams = COALESCE($19, weight_grams),
+            length_mm = COALESCE($20, length_mm),
+            width_mm = COALESCE($21, width_mm),
+            height_mm = COALESCE($22, height_mm),
+            tax_class = COALESCE($23, tax_class),
+            is_gift_card = COALESCE($24, is_gift_card),
+            gift_card_validity_days = COALESCE($25, gift_card_validity_days),
             updated_at = NOW()
         WHERE id = $1
-        RETURNING *
+        RETURNING *, (SELECT currency FROM stores WHERE stores.id = products.store_id) AS currency
         "#,
     )
     .bind(id)
@@ -252,12 +336,136 @@ pub async fn update_product(
     .bind(&req.attributes)
     .bind(&status_str)
     .bind(req.is_featured)
+    .bind(req.min_quantity.flatten())
+    .bind(req.max_quantity.flatten())
+    .bind(req.quantity_step.flatten())
+    .bind(req.max_per_customer.flatten())
+    .bind(req.weight_grams)
+    .bind(req.length_mm)
+    .bind(req.width_mm)
+    .bind(req.height_mm)
+    .bind(req.tax_class)
+    .bind(req.is_gift_card)
+    .bind(req.gift_card_validity_days)
+    .bind(req.min_quantity.is_some())
+    .bind(req.max_quantity.is_some())
+    .bind(req.quantity_step.is_some())
+    .bind(req.max_per_customer.is_some())
     .fetch_one(pool)
     .await?;
 
     Ok(product)
 }
 
+/// Create a variant of a product
+pub async fn create_variant(
+    pool: &PgPool,
+    product_id: Uuid,
+    req: &CreateVariantRequest,
+) -> Result<ProductVariant> {
+    let variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        INSERT INTO product_variants (
+            product_id, name, sku, price, compare_at_price, stock_quantity,
+            attributes, sort_order,
+            min_quantity, max_quantity, quantity_step, max_per_customer,
+            weight_grams, length_mm, width_mm, height_mm
+        ) VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16
+        )
+        RETURNING *, (
+            SELECT s.currency FROM products p INNER JOIN stores s ON s.id = p.store_id
+            WHERE p.id = product_variants.product_id
+        ) AS currency
+        "#,
+    )
+    .bind(product_id)
+    .bind(&req.name)
+    .bind(&req.sku)
+    .bind(req.price)
+    .bind(req.compare_at_price)
+    .bind(req.stock_quantity.unwrap_or(0))
+    .bind(
+        req.attributes
+            .clone()
+            .unwrap_or_else(|| serde_json::json!({})),
+    )
+    .bind(req.sort_order.unwrap_or(0))
+    .bind(req.min_quantity)
+    .bind(req.max_quantity)
+    .bind(req.quantity_step)
+    .bind(req.max_per_customer)
+    .bind(req.weight_grams)
+    .bind(req.length_mm)
+    .bind(req.width_mm)
+    .bind(req.height_mm)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(variant)
+}
+
+/// Update a variant of a product; quantity rules set to `null` fall back to
+/// the product's
+pub async fn update_variant(
+    pool: &PgPool,
+    product_id: Uuid,
+    id: Uuid,
+    req: &UpdateVariantRequest,
+) -> Result<ProductVariant> {
+    sqlx::query_as::<_, ProductVariant>(
+        r#"
+        UPDATE product_variants SET
+            name = COALESCE($3, name),
+            sku = COALESCE($4, sku),
+            price = COALESCE($5, price),
+            compare_at_price = COALESCE($6, compare_at_price),
+            stock_quantity = COALESCE($7, stock_quantity),
+            attributes = COALESCE($8, attributes),
+            sort_order = COALESCE($9, sort_order),
+            is_active = COALESCE($10, is_active),
+            min_quantity = CASE WHEN $11 THEN $12 ELSE min_quantity END,
+            max_quantity = CASE WHEN $13 THEN $14 ELSE max_quantity END,
+            quantity_step = CASE WHEN $15 THEN $16 ELSE quantity_step END,
+            max_per_customer = CASE WHEN $17 THEN $18 ELSE max_per_customer END,
+            weight_grams = COALESCE($19, weight_grams),
+            length_mm = COALESCE($20, length_mm),
+            width_mm = COALESCE($21, width_mm),
+            height_mm = COALESCE($22, height_mm)
+        WHERE id = $2 AND product_id = $1
+        RETURNING *, (
+            SELECT s.currency FROM products p INNER JOIN stores s ON s.id = p.store_id
+            WHERE p.id = product_variants.product_id
+        ) AS currency
+        "#,
+    )
+    .bind(product_id)
+    .bind(id)
+    .bind(&req.name)
+    .bind(&req.sku)
+    .bind(req.price)
+    .bind(req.compare_at_price)
+    .bind(req.stock_quantity)
+    .bind(&req.attributes)
+    .bind(req.sort_order)
+    .bind(req.is_active)
+    .bind(req.min_quantity.is_some())
+    .bind(req.min_quantity.flatten())
+    .bind(req.max_quantity.is_some())
+    .bind(req.max_quantity.flatten())
+    .bind(req.quantity_step.is_some())
+    .bind(req.quantity_step.flatten())
+    .bind(req.max_per_customer.is_some())
+    .bind(req.max_per_customer.flatten())
+    .bind(req.weight_grams)
+    .bind(req.length_mm)
+    .bind(req.width_mm)
+    .bind(req.height_mm)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Product variant not found"))
+}
+
 /// Soft delete a product (set status to archived)
 pub async fn delete_product(pool: &PgPool, id: Uuid) -> Result<()> {
     sqlx::query("UPDATE products SET status = 'archived', updated_at = NOW() WHERE id = $1")
@@ -267,3 +475,83 @@ pub async fn delete_product(pool: &PgPool, id: Uuid) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use goseli_core::{Currency, Money};
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_resolve_skus_prefers_product_skus(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let shirt = test_support::product(&pool, store_id, 2000, 10).await;
+        let mug = test_support::product(&pool, store_id, 1000, 10).await;
+        let large = test_support::variant(&pool, shirt, 2200, 5).await;
+        let clash = test_support::variant(&pool, shirt, 2200, 5).await;
+        test_support::set_sku(&pool, "products", mug, "MUG").await;
+        test_support::set_sku(&pool, "product_variants", large, "SHIRT-L").await;
+        test_support::set_sku(&pool, "product_variants", clash, "MUG").await;
+
+        let skus = ["MUG", "SHIRT-L", "NOPE"].map(String::from);
+        let resolved = resolve_skus(&pool, store_id, &skus).await.unwrap();
+
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved["MUG"], (mug, None));
+        assert_eq!(resolved["SHIRT-L"], (shirt, Some(large)));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_quantity_rules_can_be_set_and_cleared(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+
+        let update: UpdateProductRequest = serde_json::from_value(serde_json::json!({
+            "min_quantity": 2,
+            "quantity_step": 2,
+        }))
+        .unwrap();
+        let product = update_product(&pool, product_id, &update).await.unwrap();
+        assert_eq!(product.price, Money::new(1000, Currency::USD));
+        assert_eq!(product.min_quantity, Some(2));
+        assert_eq!(product.quantity_step, Some(2));
+
+        // Left out keeps the rule, null removes it
+        let update: UpdateProductRequest =
+            serde_json::from_value(serde_json::json!({ "min_quantity": null })).unwrap();
+        let product = update_product(&pool, product_id, &update).await.unwrap();
+        assert_eq!(product.min_quantity, None);
+        assert_eq!(product.quantity_step, Some(2));
+
+        let create: CreateVariantRequest = serde_json::from_value(serde_json::json!({
+            "name": "Large",
+            "price": 1200,
+            "max_quantity": 5,
+            "max_per_customer": 3,
+        }))
+        .unwrap();
+        let variant = create_variant(&pool, product_id, &create).await.unwrap();
+        assert_eq!(variant.price, Money::new(1200, Currency::USD));
+        assert_eq!(variant.max_quantity, Some(5));
+        assert_eq!(variant.max_per_customer, Some(3));
+
+        let update: UpdateVariantRequest = serde_json::from_value(serde_json::json!({
+            "max_quantity": null,
+            "min_quantity": 4,
+        }))
+        .unwrap();
+        let variant = update_variant(&pool, product_id, variant.id, &update)
+            .await
+            .unwrap();
+        assert_eq!(variant.max_quantity, None);
+        assert_eq!(variant.min_quantity, Some(4));
+        assert_eq!(variant.max_per_customer, Some(3));
+
+        // Variants are only updated through their own product
+        let other = test_support::product(&pool, store_id, 1000, 10).await;
+        let error = update_variant(&pool, other, variant.id, &update)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "not_found");
+    }
+}
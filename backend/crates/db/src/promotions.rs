@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use goseli_core::{
+    dto::PromotionRequest,
+    models::{DisplayPricing, GiftProduct, Promotion, TaxClass},
+    ApiError, Money, Result,
+};
+use sqlx::{Executor, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Products and categories a promotion looks at, and the gift it hands out,
+/// must belong to the store
+async fn validate_targets(pool: &PgPool, store_id: Uuid, req: &PromotionRequest) -> Result<()> {
+    let conditions = &req.conditions;
+    let products: i64 = sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT id) FROM products WHERE store_id = $1 AND id = ANY($2)",
+    )
+    .bind(store_id)
+    .bind(&conditions.product_ids)
+    .fetch_one(pool)
+    .await?;
+    let mut product_ids = conditions.product_ids.clone();
+    product_ids.sort();
+    product_ids.dedup();
+    if products != product_ids.len() as i64 {
+        return Err(ApiError::validation(
+            "product_ids contains unknown products",
+        ));
+    }
+
+    let categories: i64 = sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT id) FROM categories WHERE store_id = $1 AND id = ANY($2)",
+    )
+    .bind(store_id)
+    .bind(&conditions.category_ids)
+    .fetch_one(pool)
+    .await?;
+    let mut category_ids = conditions.category_ids.clone();
+    category_ids.sort();
+    category_ids.dedup();
+    if categories != category_ids.len() as i64 {
+        return Err(ApiError::validation(
+            "category_ids contains unknown categories",
+        ));
+    }
+
+    if let Some((product_id, variant_id)) = req.action.gift() {
+        let found: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM products p
+                LEFT JOIN product_variants pv ON pv.product_id = p.id AND pv.id = $3
+                WHERE p.store_id = $1 AND p.id = $2 AND ($3::UUID IS NULL OR pv.id IS NOT NULL)
+            )
+            "#,
+        )
+        .bind(store_id)
+        .bind(product_id)
+        .bind(variant_id)
+        .fetch_one(pool)
+        .await?;
+        if !found {
+            return Err(ApiError::validation("The gift product does not exist"));
+        }
+    }
+
+    Ok(())
+}
+
+/// List a store's promotions in the order they run
+pub async fn list_promotions(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Promotion>> {
+    let promotions = sqlx::query_as::<_, Promotion>(
+        r#"
+        SELECT * FROM promotions
+        WHERE store_id = $1
+        ORDER BY priority DESC, created_at ASC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(promotions)
+}
+
+/// Count a store's promotions
+pub async fn count_promotions(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM promotions WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Promotions that run at `now`, in the order they run
+pub async fn list_live_promotions<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    now: OffsetDateTime,
+) -> Result<Vec<Promotion>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let promotions = sqlx::query_as::<_, Promotion>(
+        r#"
+        SELECT * FROM promotions
+        WHERE store_id = $1 AND is_active
+          AND (starts_at IS NULL OR starts_at <= $2)
+          AND (ends_at IS NULL OR ends_at > $2)
+        ORDER BY priority DESC, created_at ASC
+        "#,
+    )
+    .bind(store_id)
+    .bind(now)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(promotions)
+}
+
+/// Get a promotion
+pub async fn get_promotion(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<Promotion> {
+    sqlx::query_as::<_, Promotion>("SELECT * FROM promotions WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Promotion not found"))
+}
+
+/// Create a promotion
+pub async fn create_promotion(
+    pool: &PgPool,
+    store_id: Uuid,
+    req: &PromotionRequest,
+) -> Result<Promotion> {
+    req.validate_rules()?;
+    validate_targets(pool, store_id, req).await?;
+
+    let promotion = sqlx::query_as::<_, Promotion>(
+        r#"
+        INSERT INTO promotions (
+            id, store_id, name, priority, exclusive, combines_with_coupons,
+            conditions, action, starts_at, ends_at, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(req.priority.unwrap_or(0))
+    .bind(req.exclusive.unwrap_or(false))
+    .bind(req.combines_with_coupons.unwrap_or(true))
+    .bind(sqlx::types::Json(&req.conditions))
+    .bind(sqlx::types::Json(&req.action))
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+/// Replace a promotion's rules
+pub async fn update_promotion(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    req: &PromotionRequest,
+) -> Result<Promotion> {
+    req.validate_rules()?;
+    get_promotion(pool, store_id, id).await?;
+    validate_targets(pool, store_id, req).await?;
+
+    let promotion = sqlx::query_as::<_, Promotion>(
+        r#"
+        UPDATE promotions SET
+            name = $3,
+            priority = COALESCE($4, priority),
+            exclusive = COALESCE($5, exclusive),
+            combines_with_coupons = COALESCE($6, combines_with_coupons),
+            conditions = $7,
+            action = $8,
+            starts_at = $9,
+            ends_at = $10,
+            is_active = COALESCE($11, is_active)
+        WHERE id = $1 AND store_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(req.priority)
+    .bind(req.exclusive)
+    .bind(req.combines_with_coupons)
+    .bind(sqlx::types::Json(&req.conditions))
+    .bind(sqlx::types::Json(&req.action))
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.is_active)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+/// Delete a promotion; orders keep their own record of what it took off
+pub async fn delete_promotion(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<()> {
+    let result = sqlx::query("DELETE FROM promotions WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Promotion not found"));
+    }
+
+    Ok(())
+}
+
+/// A product free gift promotions hand out, with what an order line needs
+#[derive(Debug, sqlx::FromRow)]
+pub struct GiftRow {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub product_name: String,
+    pub variant_name: Option<String>,
+    pub sku: Option<String>,
+    /// Minor units of the store currency
+    pub price: i64,
+    pub tax_class: TaxClass,
+    pub stock_quantity: i32,
+}
+
+impl GiftRow {
+    /// The gift as promotions see it. `in_cart` is how many of each
+    /// product+variant the cart holds; gifts come out of the stock left
+    pub fn gift(
+        &self,
+        pricing: &DisplayPricing,
+        in_cart: &HashMap<(Uuid, Option<Uuid>), i64>,
+    ) -> Result<GiftProduct> {
+        let (price, _) = pricing.catalog_price(
+            self.product_id,
+            self.variant_id,
+            Money::new(self.price, pricing.base()),
+            None,
+        )?;
+        let reserved = in_cart
+            .get(&(self.product_id, self.variant_id))
+            .copied()
+            .unwrap_or(0);
+        let available = (i64::from(self.stock_quantity) - reserved).max(0);
+        Ok(GiftProduct {
+            product_id: self.product_id,
+            variant_id: self.variant_id,
+            name: self.product_name.clone(),
+            price,
+            available: i32::try_from(available).unwrap_or(i32::MAX),
+        })
+    }
+}
+
+/// The products the promotions hand out as gifts, if they can still be sold
+pub async fn load_gifts<'e, E>(executor: E, promotions: &[Promotion]) -> Result<Vec<GiftRow>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let (product_ids, variant_ids): (Vec<Uuid>, Vec<Option<Uuid>>) = promotions
+        .iter()
+        .filter_map(|promotion| promotion.action.gift())
+        .unzip();
+    if product_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let gifts = sqlx::query_as::<_, GiftRow>(
+        r#"
+        SELECT
+            p.id as product_id,
+            pv.id as variant_id,
+            p.name as product_name,
+            pv.name as variant_name,
+            COALESCE(pv.sku, p.sku) as sku,
+            COALESCE(pv.price, p.price) as price,
+            p.tax_class,
+            COALESCE(pv.stock_quantity, p.stock_quantity) as stock_quantity
+        FROM UNNEST($1::UUID[], $2::UUID[]) AS g(product_id, variant_id)
+        INNER JOIN products p ON p.id = g.product_id
+        LEFT JOIN product_variants pv ON pv.id = g.variant_id AND pv.product_id = p.id
+        WHERE p.status = 'active'
+          AND (g.variant_id IS NULL OR (pv.id IS NOT NULL AND pv.is_active))
+        "#,
+    )
+    .bind(&product_ids)
+    .bind(&variant_ids)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(gifts)
+}
+
+/// The customer group promotions and group prices see for a customer
+pub async fn customer_group<'e, E>(executor: E, user_id: Option<Uuid>) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+    let group: Option<Option<String>> =
+        sqlx::query_scalar("SELECT customer_group FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(group.flatten())
+}
+
+/// Put a customer in a customer group, or take them out of it
+pub async fn set_customer_group(
+    pool: &PgPool,
+    store_id: Uuid,
+    user_id: Uuid,
+    group: Option<&str>,
+) -> Result<()> {
+    if let Some(group) = group {
+        if !crate::customer_groups::group_exists(pool, store_id, group).await? {
+            return Err(ApiError::not_found(format!(
+                "Customer group '{}' not found",
+                group
+            )));
+        }
+    }
+    let result =
+        sqlx::query("UPDATE users SET customer_group = $3 WHERE id = $1 AND store_id = $2")
+            .bind(user_id)
+            .bind(store_id)
+            .bind(group)
+            .execute(pool)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Customer not found"));
+    }
+
+    Ok(())
+}
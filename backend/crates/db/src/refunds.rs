@@ -0,0 +1,670 @@
+use std::collections::{HashMap, HashSet};
+
+use goseli_core::{
+    dto::CreateRefundRequest,
+    models::{
+        refund::{line_refund_amount, refunded_payment_status},
+        Order, OrderAction, OrderActor, OrderItem, Payment, Refund, RefundItem, RefundStatus,
+        ReturnStatus,
+    },
+    ApiError, Money, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::payments::lock_order;
+
+/// Refunds for an order that still count against what was captured
+#[derive(sqlx::FromRow)]
+struct RefundedSoFar {
+    amount: i64,
+    gift_card: i64,
+    shipping: i64,
+}
+
+/// Record a refund of an order's captured payment, before it is sent to the provider.
+///
+/// Line amounts are derived from the order lines. On an order paid partly
+/// with gift cards, the refund is split between the payment and the cards
+/// in the proportion each paid. Each part is checked against what that
+/// tender paid minus every refund that has not failed, so two refunds
+/// issued at once cannot return more than was collected. Lines returned for
+/// store credit count as refunded, and their orders can only be refunded
+/// line by line.
+pub async fn create_refund(
+    pool: &PgPool,
+    order_id: Uuid,
+    req: &CreateRefundRequest,
+    created_by: Option<Uuid>,
+) -> Result<(Refund, Vec<RefundItem>)> {
+    let mut tx = pool.begin().await?;
+    let created = insert_refund(&mut tx, order_id, req, created_by).await?;
+    tx.commit().await?;
+
+    Ok(created)
+}
+
+/// Refund the lines of a received return and attach the refund to it.
+///
+/// The return stays locked while the refund is recorded, so it cannot be
+/// closed another way or refunded twice; a refund that failed may be
+/// retried.
+pub async fn create_return_refund(
+    pool: &PgPool,
+    return_id: Uuid,
+    req: &CreateRefundRequest,
+    created_by: Option<Uuid>,
+) -> Result<(Refund, Vec<RefundItem>)> {
+    let mut tx = pool.begin().await?;
+
+    let ret = crate::returns::lock_return(&mut tx, return_id, ReturnStatus::Closed).await?;
+    if let Some(refund_id) = ret.refund_id {
+        let status: RefundStatus = sqlx::query_scalar("SELECT status FROM refunds WHERE id = $1")
+            .bind(refund_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if status != RefundStatus::Failed {
+            return Err(ApiError::rule(
+                "return_already_refunded",
+                format!("Return RMA-{} already has a refund", ret.rma_number),
+            ));
+        }
+    }
+    let (refund, items) = insert_refund(&mut tx, ret.order_id, req, created_by).await?;
+    sqlx::query("UPDATE returns SET refund_id = $2 WHERE id = $1")
+        .bind(return_id)
+        .bind(refund.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((refund, items))
+}
+
+async fn insert_refund(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    req: &CreateRefundRequest,
+    created_by: Option<Uuid>,
+) -> Result<(Refund, Vec<RefundItem>)> {
+    if req.full && (!req.items.is_empty() || req.shipping || req.amount.is_some()) {
+        return Err(ApiError::validation(
+            "A full refund cannot also list lines, shipping or an amount",
+        ));
+    }
+    if !req.full && req.items.is_empty() && !req.shipping && req.amount.is_none() {
+        return Err(ApiError::validation("Nothing to refund"));
+    }
+
+    let order = lock_order(&mut *conn, order_id).await?;
+    let payment = sqlx::query_as::<_, Payment>(
+        "SELECT * FROM payments WHERE order_id = $1 AND status = 'captured'",
+    )
+    .bind(order_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| {
+        ApiError::rule(
+            "order_not_refundable",
+            format!("Order {} has no captured payment", order.order_number),
+        )
+    })?;
+
+    let so_far = sqlx::query_as::<_, RefundedSoFar>(
+        r#"
+        SELECT COALESCE(SUM(amount), 0)::BIGINT as amount,
+               COALESCE(SUM(gift_card_amount), 0)::BIGINT as gift_card,
+               COALESCE(SUM(shipping_amount), 0)::BIGINT as shipping
+        FROM refunds
+        WHERE order_id = $1 AND status <> 'failed'
+        "#,
+    )
+    .bind(order_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    // Lines returned for store credit were paid back with a gift card
+    let refunded_quantities: HashMap<Uuid, i32> = sqlx::query_as::<_, (Uuid, i64)>(
+        r#"
+        SELECT order_item_id, SUM(quantity)::BIGINT
+        FROM (
+            SELECT ri.order_item_id, ri.quantity
+            FROM refund_items ri
+            JOIN refunds r ON r.id = ri.refund_id
+            WHERE r.order_id = $1 AND r.status <> 'failed'
+            UNION ALL
+            SELECT ri.order_item_id, ri.quantity
+            FROM return_items ri
+            JOIN returns rt ON rt.id = ri.return_id
+            WHERE rt.order_id = $1 AND rt.resolution = 'store_credit'
+        ) t
+        GROUP BY order_item_id
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|(id, quantity)| {
+        let quantity = i32::try_from(quantity).map_err(|_| {
+            ApiError::rule(
+                "invalid_quantity",
+                "More of an order line was refunded than can be counted",
+            )
+        })?;
+        Ok((id, quantity))
+    })
+    .collect::<Result<_>>()?;
+
+    let items = sqlx::query_as::<_, OrderItem>(
+        "SELECT * FROM order_items WHERE order_id = $1 ORDER BY id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let currency = payment.currency;
+    let left = payment
+        .amount_captured
+        .checked_sub(Money::new(so_far.amount, currency))?;
+    let gift_card_left = order
+        .gift_card_total
+        .checked_sub(Money::new(so_far.gift_card, currency))?;
+    let shipping_left = order
+        .shipping_charged()?
+        .checked_sub(Money::new(so_far.shipping, currency))?;
+    let line_amount = |item: &OrderItem, refunded: i32, quantity: i32| -> Result<Money> {
+        let amount = line_refund_amount(item, order.prices_include_tax, refunded, quantity)?;
+        Ok(Money::new(amount, currency))
+    };
+
+    // Lines to refund with their quantities and amounts
+    let mut lines: Vec<(Uuid, i32, Money)> = vec![];
+    let amount: Money;
+    let gift_card_amount: Money;
+    let shipping_amount: Money;
+    if req.full {
+        let credited: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM returns WHERE order_id = $1 AND resolution = 'store_credit')",
+        )
+        .bind(order_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if credited {
+            return Err(ApiError::rule(
+                "order_has_store_credit",
+                format!(
+                    "Part of order {} was returned for store credit; refund the other lines one by one",
+                    order.order_number
+                ),
+            ));
+        }
+        for item in &items {
+            let refunded = refunded_quantities.get(&item.id).copied().unwrap_or(0);
+            if refunded < item.quantity {
+                let quantity = item.quantity - refunded;
+                lines.push((item.id, quantity, line_amount(item, refunded, quantity)?));
+            }
+        }
+        shipping_amount = shipping_left.clamp_to_zero();
+        amount = left.clamp_to_zero();
+        gift_card_amount = gift_card_left.clamp_to_zero();
+    } else {
+        let mut seen = HashSet::new();
+        for line in &req.items {
+            if !seen.insert(line.order_item_id) {
+                return Err(ApiError::validation("An order line is listed twice"));
+            }
+            let item = items
+                .iter()
+                .find(|item| item.id == line.order_item_id)
+                .ok_or_else(|| ApiError::validation("Order line not found on this order"))?;
+            let refunded = refunded_quantities.get(&item.id).copied().unwrap_or(0);
+            lines.push((
+                item.id,
+                line.quantity,
+                line_amount(item, refunded, line.quantity)?,
+            ));
+        }
+        shipping_amount = if req.shipping {
+            if shipping_left.amount() <= 0 {
+                return Err(ApiError::rule(
+                    "shipping_already_refunded",
+                    "There is no shipping left to refund",
+                ));
+            }
+            shipping_left
+        } else {
+            Money::zero(currency)
+        };
+        let extra = Money::new(req.amount.unwrap_or(0), currency);
+        let total = Money::sum(
+            currency,
+            lines
+                .iter()
+                .map(|(_, _, amount)| *amount)
+                .chain([shipping_amount, extra]),
+        )?;
+        // Each tender gets back its share of what it paid
+        (amount, gift_card_amount) = if order.gift_card_total.is_zero() {
+            (total, Money::zero(currency))
+        } else {
+            let parts = total.allocate(&[
+                payment.amount_captured.amount(),
+                order.gift_card_total.amount(),
+            ])?;
+            (parts[0], parts[1])
+        };
+    }
+
+    let total = amount.checked_add(gift_card_amount)?;
+    if total.amount() <= 0 && left.amount() <= 0 && gift_card_left.amount() <= 0 {
+        return Err(ApiError::rule(
+            "refund_exceeds_captured",
+            format!("Order {} is already fully refunded", order.order_number),
+        ));
+    }
+    if total.amount() <= 0 {
+        return Err(ApiError::validation("Refund amount must be positive"));
+    }
+    if amount.amount() > left.amount() {
+        return Err(ApiError::rule(
+            "refund_exceeds_captured",
+            format!(
+                "Cannot refund {}: only {} of the {} captured is left",
+                amount, left, payment.amount_captured
+            ),
+        ));
+    }
+    if gift_card_amount.amount() > gift_card_left.amount() {
+        return Err(ApiError::rule(
+            "refund_exceeds_captured",
+            format!(
+                "Cannot give back {} to gift cards: only {} of the {} paid with them is left",
+                gift_card_amount, gift_card_left, order.gift_card_total
+            ),
+        ));
+    }
+
+    let refund = sqlx::query_as::<_, Refund>(
+        r#"
+        INSERT INTO refunds (
+            id, order_id, payment_id, currency, amount, gift_card_amount, shipping_amount,
+            reason, restock, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(order_id)
+    .bind(payment.id)
+    .bind(currency)
+    .bind(amount)
+    .bind(gift_card_amount)
+    .bind(shipping_amount)
+    .bind(&req.reason)
+    .bind(req.restock)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let mut refund_items = Vec::with_capacity(lines.len());
+    for (order_item_id, quantity, line_amount) in lines {
+        let item = sqlx::query_as::<_, RefundItem>(
+            r#"
+            INSERT INTO refund_items (id, refund_id, order_item_id, quantity, amount)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(refund.id)
+        .bind(order_item_id)
+        .bind(quantity)
+        .bind(line_amount)
+        .fetch_one(&mut *conn)
+        .await?;
+        refund_items.push(item);
+    }
+
+    Ok((refund, refund_items))
+}
+
+/// Record the provider's answer for a pending refund.
+///
+/// Refunds that are no longer pending are returned unchanged, so replayed
+/// webhooks are harmless. When a refund succeeds the payment's refunded
+/// amount grows, its gift card share goes back on the cards, refunded
+/// lines are restocked if asked, and the order
+/// becomes `partially_refunded`, or `refunded` once everything captured has
+/// gone back (which also moves a paid order to the refunded status).
+#[allow(clippy::too_many_arguments)]
+pub async fn record_refund_result(
+    pool: &PgPool,
+    refund_id: Uuid,
+    status: RefundStatus,
+    provider_refund_id: Option<&str>,
+    failure_reason: Option<&str>,
+    actor: OrderActor,
+    actor_id: Option<Uuid>,
+) -> Result<(Refund, Order)> {
+    let mut tx = pool.begin().await?;
+
+    let order_id: Uuid = sqlx::query_scalar("SELECT order_id FROM refunds WHERE id = $1")
+        .bind(refund_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Refund not found"))?;
+    let mut order = lock_order(&mut tx, order_id).await?;
+
+    let current = sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE id = $1")
+        .bind(refund_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if current.status != RefundStatus::Pending {
+        tx.commit().await?;
+        return Ok((current, order));
+    }
+
+    let refund = sqlx::query_as::<_, Refund>(
+        r#"
+        UPDATE refunds SET
+            status = $2,
+            provider_refund_id = COALESCE($3, provider_refund_id),
+            failure_reason = $4
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(refund_id)
+    .bind(status)
+    .bind(provider_refund_id)
+    .bind(failure_reason)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if refund.status == RefundStatus::Succeeded {
+        let payment = sqlx::query_as::<_, Payment>(
+            "UPDATE payments SET amount_refunded = amount_refunded + $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(refund.payment_id)
+        .bind(refund.amount)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !refund.gift_card_amount.is_zero() {
+            crate::gift_cards::release_for_refund(&mut tx, order_id, refund.gift_card_amount)
+                .await?;
+        }
+        if refund.restock {
+            restock_refund(&mut tx, refund.id).await?;
+        }
+
+        let payment_status =
+            refunded_payment_status(payment.amount_captured, payment.amount_refunded);
+        order = sqlx::query_as::<_, Order>(
+            "UPDATE orders SET payment_status = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(order_id)
+        .bind(payment_status)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if payment_status == goseli_core::models::PaymentStatus::Refunded
+            && order.status.next(OrderAction::Refund).is_some()
+        {
+            order = crate::orders::apply_transition(
+                &mut tx,
+                order_id,
+                OrderAction::Refund,
+                actor,
+                actor_id,
+                Some(&refund.reason),
+            )
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok((refund, order))
+}
+
+/// Return a refund's line quantities to product and variant stock
+async fn restock_refund(conn: &mut PgConnection, refund_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE product_variants pv SET stock_quantity = pv.stock_quantity + t.quantity
+        FROM (
+            SELECT oi.variant_id, SUM(ri.quantity)::INTEGER as quantity
+            FROM refund_items ri
+            JOIN order_items oi ON oi.id = ri.order_item_id
+            WHERE ri.refund_id = $1 AND oi.variant_id IS NOT NULL
+            GROUP BY oi.variant_id
+        ) t
+        WHERE pv.id = t.variant_id
+        "#,
+    )
+    .bind(refund_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE products p SET stock_quantity = p.stock_quantity + t.quantity
+        FROM (
+            SELECT oi.product_id, SUM(ri.quantity)::INTEGER as quantity
+            FROM refund_items ri
+            JOIN order_items oi ON oi.id = ri.order_item_id
+            WHERE ri.refund_id = $1 AND oi.variant_id IS NULL AND oi.product_id IS NOT NULL
+            GROUP BY oi.product_id
+        ) t
+        WHERE p.id = t.product_id
+        "#,
+    )
+    .bind(refund_id)
+    .execute(&mut *conn)
+    .await?;
+
+    // Units sold at a sale price count against its cap again
+    let lines: Vec<(Uuid, i32)> =
+        sqlx::query_as("SELECT order_item_id, quantity FROM refund_items WHERE refund_id = $1")
+            .bind(refund_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    crate::sales::release_sold(conn, &lines).await?;
+
+    Ok(())
+}
+
+/// Get an order's refunds, oldest first
+pub async fn list_order_refunds(pool: &PgPool, order_id: Uuid) -> Result<Vec<Refund>> {
+    let refunds = sqlx::query_as::<_, Refund>(
+        "SELECT * FROM refunds WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(refunds)
+}
+
+/// Get the refunded lines of an order's refunds
+pub async fn list_order_refund_items(pool: &PgPool, order_id: Uuid) -> Result<Vec<RefundItem>> {
+    let items = sqlx::query_as::<_, RefundItem>(
+        r#"
+        SELECT ri.* FROM refund_items ri
+        JOIN refunds r ON r.id = ri.refund_id
+        WHERE r.order_id = $1
+        ORDER BY ri.id ASC
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// Get a payment's refunds still waiting for the provider, oldest first
+pub async fn list_pending_for_payment(pool: &PgPool, payment_id: Uuid) -> Result<Vec<Refund>> {
+    let refunds = sqlx::query_as::<_, Refund>(
+        r#"
+        SELECT * FROM refunds
+        WHERE payment_id = $1 AND status = 'pending'
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(payment_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(refunds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{self, NewOrder};
+    use crate::test_support;
+    use goseli_core::dto::RefundLineRequest;
+    use goseli_core::models::PaymentStatus;
+    use time::OffsetDateTime;
+
+    fn lines(order_item_id: Uuid, quantity: i32) -> CreateRefundRequest {
+        CreateRefundRequest {
+            full: false,
+            items: vec![RefundLineRequest {
+                order_item_id,
+                quantity,
+            }],
+            shipping: false,
+            amount: None,
+            restock: false,
+            reason: "Damaged".into(),
+        }
+    }
+
+    async fn card_balance(pool: &PgPool, code: &str) -> i64 {
+        sqlx::query_scalar("SELECT balance FROM gift_cards WHERE code = $1")
+            .bind(code)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_partial_refund_splits_between_payment_and_gift_card(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let code = test_support::gift_card(&pool, store_id, 6000).await;
+        let cart_id = test_support::cart(&pool, store_id, None, OffsetDateTime::now_utc()).await;
+        test_support::cart_item(&pool, cart_id, product_id, 2).await;
+
+        let order = orders::place_order(
+            &pool,
+            cart_id,
+            &NewOrder {
+                email: "ada@example.com".into(),
+                gift_card_codes: vec![code.clone()],
+                ..NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(order.gift_card_total.amount(), 6000);
+        assert_eq!(card_balance(&pool, &code).await, 0);
+        test_support::captured_payment(&pool, order.id).await;
+        orders::transition_order(
+            &pool,
+            order.id,
+            OrderAction::Pay,
+            OrderActor::System,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let item_id = orders::get_order_items(&pool, order.id).await.unwrap()[0].id;
+
+        let (refund, _) = create_refund(&pool, order.id, &lines(item_id, 1), None)
+            .await
+            .unwrap();
+        assert_eq!(refund.amount.amount(), 2000);
+        assert_eq!(refund.gift_card_amount.amount(), 3000);
+        let (_, order_after) = record_refund_result(
+            &pool,
+            refund.id,
+            RefundStatus::Succeeded,
+            Some("re_1"),
+            None,
+            OrderActor::System,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(card_balance(&pool, &code).await, 3000);
+        assert_eq!(order_after.payment_status, PaymentStatus::PartiallyRefunded);
+
+        let mut over = lines(item_id, 1);
+        over.amount = Some(1);
+        let error = create_refund(&pool, order.id, &over, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "refund_exceeds_captured");
+
+        let (refund, _) = create_refund(&pool, order.id, &lines(item_id, 1), None)
+            .await
+            .unwrap();
+        assert_eq!(refund.amount.amount(), 2000);
+        assert_eq!(refund.gift_card_amount.amount(), 3000);
+        record_refund_result(
+            &pool,
+            refund.id,
+            RefundStatus::Succeeded,
+            Some("re_2"),
+            None,
+            OrderActor::System,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(card_balance(&pool, &code).await, 6000);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_full_refund_returns_whatever_is_left(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 2).await;
+        test_support::captured_payment(&pool, order.id).await;
+        let goodwill = CreateRefundRequest {
+            full: false,
+            items: vec![],
+            shipping: false,
+            amount: Some(500),
+            restock: false,
+            reason: "Late delivery".into(),
+        };
+        let (refund, _) = create_refund(&pool, order.id, &goodwill, None)
+            .await
+            .unwrap();
+        assert_eq!(refund.amount.amount(), 500);
+
+        let full = CreateRefundRequest {
+            full: true,
+            amount: None,
+            ..goodwill
+        };
+        let (refund, _) = create_refund(&pool, order.id, &full, None).await.unwrap();
+        assert_eq!(refund.amount.amount(), 9500);
+        assert!(refund.gift_card_amount.is_zero());
+
+        // Pending refunds already hold everything captured
+        let error = create_refund(&pool, order.id, &full, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "refund_exceeds_captured");
+    }
+}
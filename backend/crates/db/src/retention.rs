@@ -0,0 +1,208 @@
+use goseli_core::Result;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+/// Delete one batch of carts with no items and no activity since `idle_before`.
+/// Returns the number of rows deleted; callers loop until it drops below `batch_size`.
+pub async fn purge_empty_carts(
+    pool: &PgPool,
+    idle_before: OffsetDateTime,
+    batch_size: i64,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM carts WHERE id IN (
+            SELECT c.id FROM carts c
+            WHERE c.last_activity_at < $1
+              AND NOT EXISTS (SELECT 1 FROM cart_items ci WHERE ci.cart_id = c.id)
+            LIMIT $2
+        )
+        "#,
+    )
+    .bind(idle_before)
+    .bind(batch_size)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete one batch of guest carts (with or without items) idle since `idle_before`
+pub async fn purge_idle_guest_carts(
+    pool: &PgPool,
+    idle_before: OffsetDateTime,
+    batch_size: i64,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM carts WHERE id IN (
+            SELECT id FROM carts
+            WHERE user_id IS NULL AND last_activity_at < $1
+            LIMIT $2
+        )
+        "#,
+    )
+    .bind(idle_before)
+    .bind(batch_size)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete one batch of expired or revoked refresh tokens
+pub async fn purge_expired_refresh_tokens(pool: &PgPool, batch_size: i64) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM refresh_tokens WHERE id IN (
+            SELECT id FROM refresh_tokens
+            WHERE expires_at < NOW() OR revoked_at IS NOT NULL
+            LIMIT $1
+        )
+        "#,
+    )
+    .bind(batch_size)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete idempotency records past their TTL
+pub async fn purge_expired_idempotency_keys(pool: &PgPool, batch_size: i64) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM idempotency_keys WHERE id IN (
+            SELECT id FROM idempotency_keys
+            WHERE expires_at < NOW()
+            LIMIT $1
+        )
+        "#,
+    )
+    .bind(batch_size)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use time::Duration;
+    use uuid::Uuid;
+
+    async fn cart_exists(pool: &PgPool, cart_id: Uuid) -> bool {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM carts WHERE id = $1)")
+            .bind(cart_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_purge_empty_carts_keeps_recent_and_filled_carts(pool: PgPool) {
+        let now = OffsetDateTime::now_utc();
+        let store_id = test_support::store(&pool).await;
+        let user_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+
+        let idle_empty = test_support::cart(&pool, store_id, None, now - Duration::days(2)).await;
+        let idle_empty_user =
+            test_support::cart(&pool, store_id, Some(user_id), now - Duration::days(2)).await;
+        let recent_empty = test_support::cart(&pool, store_id, None, now).await;
+        let idle_filled = test_support::cart(&pool, store_id, None, now - Duration::days(2)).await;
+        test_support::cart_item(&pool, idle_filled, product_id, 1).await;
+
+        let deleted = purge_empty_carts(&pool, now - Duration::days(1), 100)
+            .await
+            .unwrap();
+
+        assert_eq!(deleted, 2);
+        assert!(!cart_exists(&pool, idle_empty).await);
+        assert!(!cart_exists(&pool, idle_empty_user).await);
+        assert!(cart_exists(&pool, recent_empty).await);
+        assert!(cart_exists(&pool, idle_filled).await);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_purge_idle_guest_carts_keeps_customer_carts(pool: PgPool) {
+        let now = OffsetDateTime::now_utc();
+        let store_id = test_support::store(&pool).await;
+        let user_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+
+        let idle_guest = test_support::cart(&pool, store_id, None, now - Duration::days(40)).await;
+        test_support::cart_item(&pool, idle_guest, product_id, 2).await;
+        let idle_customer =
+            test_support::cart(&pool, store_id, Some(user_id), now - Duration::days(40)).await;
+        test_support::cart_item(&pool, idle_customer, product_id, 2).await;
+        let recent_guest = test_support::cart(&pool, store_id, None, now - Duration::days(5)).await;
+        test_support::cart_item(&pool, recent_guest, product_id, 1).await;
+
+        let deleted = purge_idle_guest_carts(&pool, now - Duration::days(30), 100)
+            .await
+            .unwrap();
+
+        assert_eq!(deleted, 1);
+        assert!(!cart_exists(&pool, idle_guest).await);
+        assert!(cart_exists(&pool, idle_customer).await);
+        assert!(cart_exists(&pool, recent_guest).await);
+        // The items went with the cart
+        let items: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cart_items WHERE cart_id = $1")
+            .bind(idle_guest)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(items, 0);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_purge_works_in_batches(pool: PgPool) {
+        let now = OffsetDateTime::now_utc();
+        let store_id = test_support::store(&pool).await;
+        for _ in 0..3 {
+            test_support::cart(&pool, store_id, None, now - Duration::days(2)).await;
+        }
+
+        let idle_before = now - Duration::days(1);
+        assert_eq!(purge_empty_carts(&pool, idle_before, 2).await.unwrap(), 2);
+        assert_eq!(purge_empty_carts(&pool, idle_before, 2).await.unwrap(), 1);
+        assert_eq!(purge_empty_carts(&pool, idle_before, 2).await.unwrap(), 0);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_purge_expired_refresh_tokens(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let user_id = test_support::customer(&pool, store_id).await;
+        let now = OffsetDateTime::now_utc();
+
+        for (hash, expires_at, revoked_at) in [
+            ("expired", now - Duration::hours(1), None),
+            ("revoked", now + Duration::days(1), Some(now)),
+            ("valid", now + Duration::days(1), None),
+        ] {
+            sqlx::query(
+                r#"
+                INSERT INTO refresh_tokens (user_id, token_hash, expires_at, revoked_at)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(user_id)
+            .bind(hash)
+            .bind(expires_at)
+            .bind(revoked_at)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(purge_expired_refresh_tokens(&pool, 100).await.unwrap(), 2);
+        let left: Vec<String> = sqlx::query_scalar("SELECT token_hash FROM refresh_tokens")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, vec!["valid".to_string()]);
+    }
+}
@@ -0,0 +1,836 @@
+use std::collections::{HashMap, HashSet};
+
+use goseli_core::{
+    dto::CreateReturnRequest,
+    models::{
+        GiftCardSource, ItemCondition, OrderItem, OrderStatus, Return, ReturnItem, ReturnPhoto,
+        ReturnResolution, ReturnStatus,
+    },
+    ApiError, Currency, Money, Result,
+};
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::payments::lock_order;
+
+/// Open a return for lines of a delivered or paid order.
+///
+/// Each line can only be returned up to its ordered quantity across all
+/// returns that were not rejected. Orders older than `window` cannot be
+/// returned.
+pub async fn create_return(
+    pool: &PgPool,
+    order_id: Uuid,
+    user_id: Option<Uuid>,
+    req: &CreateReturnRequest,
+    window: Duration,
+) -> Result<(Return, Vec<ReturnItem>)> {
+    let mut tx = pool.begin().await?;
+
+    let order = lock_order(&mut tx, order_id).await?;
+    if !matches!(
+        order.status,
+        OrderStatus::Paid
+            | OrderStatus::PartiallyFulfilled
+            | OrderStatus::Fulfilled
+            | OrderStatus::Completed
+    ) {
+        return Err(ApiError::rule(
+            "order_not_returnable",
+            format!(
+                "Order {} is {} and cannot be returned",
+                order.order_number, order.status
+            ),
+        ));
+    }
+    if order.created_at + window < OffsetDateTime::now_utc() {
+        return Err(ApiError::rule(
+            "return_window_closed",
+            format!(
+                "Order {} is past the {}-day return window",
+                order.order_number,
+                window.whole_days()
+            ),
+        ));
+    }
+
+    let items = sqlx::query_as::<_, OrderItem>("SELECT * FROM order_items WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let returned: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(
+        r#"
+        SELECT ri.order_item_id, SUM(ri.quantity)::BIGINT
+        FROM return_items ri
+        JOIN returns r ON r.id = ri.return_id
+        WHERE r.order_id = $1 AND r.status <> 'rejected'
+        GROUP BY ri.order_item_id
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut seen = HashSet::new();
+    for line in &req.items {
+        if !seen.insert(line.order_item_id) {
+            return Err(ApiError::validation("An order line is listed twice"));
+        }
+        let item = items
+            .iter()
+            .find(|item| item.id == line.order_item_id)
+            .ok_or_else(|| ApiError::validation("Order line not found on this order"))?;
+        let remaining = i64::from(item.quantity) - returned.get(&item.id).copied().unwrap_or(0);
+        if i64::from(line.quantity) > remaining {
+            return Err(ApiError::rule(
+                "return_quantity_exceeded",
+                format!(
+                    "Only {} of {} can still be returned",
+                    remaining.max(0),
+                    item.product_name
+                ),
+            ));
+        }
+    }
+
+    let ret = sqlx::query_as::<_, Return>(
+        r#"
+        INSERT INTO returns (id, store_id, order_id, user_id, reason, comment)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(order.store_id)
+    .bind(order_id)
+    .bind(user_id)
+    .bind(req.reason)
+    .bind(&req.comment)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut return_items = Vec::with_capacity(req.items.len());
+    for line in &req.items {
+        let item = sqlx::query_as::<_, ReturnItem>(
+            r#"
+            INSERT INTO return_items (id, return_id, order_item_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(ret.id)
+        .bind(line.order_item_id)
+        .bind(line.quantity)
+        .fetch_one(&mut *tx)
+        .await?;
+        return_items.push(item);
+    }
+
+    tx.commit().await?;
+
+    Ok((ret, return_items))
+}
+
+/// Lock a return and check it may move to `next`
+pub(crate) async fn lock_return(
+    conn: &mut PgConnection,
+    id: Uuid,
+    next: ReturnStatus,
+) -> Result<Return> {
+    let ret = sqlx::query_as::<_, Return>("SELECT * FROM returns WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Return not found"))?;
+    ret.status.transition(next)?;
+    Ok(ret)
+}
+
+/// Approve or reject a requested return
+pub async fn decide_return(
+    pool: &PgPool,
+    id: Uuid,
+    approve: bool,
+    note: Option<&str>,
+    decided_by: Uuid,
+) -> Result<Return> {
+    let status = if approve {
+        ReturnStatus::Approved
+    } else {
+        ReturnStatus::Rejected
+    };
+
+    let mut tx = pool.begin().await?;
+    lock_return(&mut tx, id, status).await?;
+
+    let ret = sqlx::query_as::<_, Return>(
+        r#"
+        UPDATE returns SET status = $2, decision_note = $3, decided_by = $4, decided_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(status)
+    .bind(note)
+    .bind(decided_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(ret)
+}
+
+/// Record that an approved return arrived, and in what condition
+pub async fn receive_return(
+    pool: &PgPool,
+    id: Uuid,
+    condition: ItemCondition,
+    note: Option<&str>,
+) -> Result<Return> {
+    let mut tx = pool.begin().await?;
+    lock_return(&mut tx, id, ReturnStatus::Received).await?;
+
+    let ret = sqlx::query_as::<_, Return>(
+        r#"
+        UPDATE returns SET status = 'received', condition = $2, condition_note = $3, received_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(condition)
+    .bind(note)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(ret)
+}
+
+/// How a received return was resolved
+#[derive(Debug, Clone)]
+pub struct ReturnClosing<'a> {
+    pub resolution: ReturnResolution,
+    pub refund_id: Option<Uuid>,
+    pub credit_amount: Option<i64>,
+    /// Put the returned quantities back in stock (refunds restock on their own)
+    pub restock: bool,
+    pub note: Option<&'a str>,
+}
+
+/// Close a received return with its resolution; store credit is issued
+/// as a gift card.
+///
+/// Store credit is worth at most the value of the returned lines, and only
+/// for lines that were not refunded already. A return refunded through
+/// `refunds::create_return_refund` can only be closed with that refund.
+pub async fn close_return(pool: &PgPool, id: Uuid, closing: &ReturnClosing<'_>) -> Result<Return> {
+    let mut tx = pool.begin().await?;
+    let current = lock_return(&mut tx, id, ReturnStatus::Closed).await?;
+    // A refund attached by `create_return_refund` decides how the return closes
+    if let Some(refund_id) = current
+        .refund_id
+        .filter(|&refund_id| Some(refund_id) != closing.refund_id)
+    {
+        let failed: bool =
+            sqlx::query_scalar("SELECT status = 'failed' FROM refunds WHERE id = $1")
+                .bind(refund_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if !failed {
+            return Err(ApiError::rule(
+                "return_already_refunded",
+                format!("Return RMA-{} already has a refund", current.rma_number),
+            ));
+        }
+    }
+
+    if let (ReturnResolution::StoreCredit, Some(amount)) =
+        (closing.resolution, closing.credit_amount)
+    {
+        // Refunds of the order's lines wait for the order lock
+        lock_order(&mut tx, current.order_id).await?;
+        let value = return_value(&mut *tx, id).await?;
+        if amount > value {
+            return Err(ApiError::rule(
+                "credit_exceeds_return_value",
+                format!(
+                    "Store credit of {} is more than the {} the returned lines are worth",
+                    amount, value
+                ),
+            ));
+        }
+        let refunded: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM return_items ri
+                JOIN order_items oi ON oi.id = ri.order_item_id
+                CROSS JOIN LATERAL (
+                    SELECT COALESCE(SUM(fi.quantity), 0) as quantity
+                    FROM refund_items fi
+                    JOIN refunds r ON r.id = fi.refund_id
+                    WHERE fi.order_item_id = oi.id AND r.status <> 'failed'
+                ) refunded
+                WHERE ri.return_id = $1 AND ri.quantity + refunded.quantity > oi.quantity
+            )
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if refunded {
+            return Err(ApiError::rule(
+                "return_already_refunded",
+                "Some of the returned lines were already refunded",
+            ));
+        }
+    }
+
+    let ret = sqlx::query_as::<_, Return>(
+        r#"
+        UPDATE returns SET
+            status = 'closed',
+            resolution = $2,
+            refund_id = $3,
+            credit_amount = $4,
+            resolution_note = $5,
+            closed_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(closing.resolution)
+    .bind(closing.refund_id)
+    .bind(closing.credit_amount)
+    .bind(closing.note)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Store credit is a gift card in the customer's name
+    let ret = match (closing.resolution, closing.credit_amount) {
+        (ReturnResolution::StoreCredit, Some(amount)) => {
+            let (currency, email): (Currency, String) =
+                sqlx::query_as("SELECT currency, email FROM orders WHERE id = $1")
+                    .bind(ret.order_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            let note = format!("Store credit for return RMA-{}", ret.rma_number);
+            let card = crate::gift_cards::issue(
+                &mut tx,
+                &crate::gift_cards::NewGiftCard {
+                    store_id: ret.store_id,
+                    amount: Money::new(amount, currency),
+                    code: None,
+                    expires_at: None,
+                    source: GiftCardSource::StoreCredit,
+                    order_id: None,
+                    order_item_id: None,
+                    return_id: Some(ret.id),
+                    recipient_email: Some(&email),
+                    note: Some(&note),
+                    created_by: None,
+                },
+            )
+            .await?;
+            sqlx::query_as::<_, Return>(
+                "UPDATE returns SET gift_card_id = $2 WHERE id = $1 RETURNING *",
+            )
+            .bind(id)
+            .bind(card.id)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        _ => ret,
+    };
+
+    if closing.restock {
+        restock_return(&mut tx, id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(ret)
+}
+
+/// Return a return's quantities to product and variant stock
+async fn restock_return(conn: &mut PgConnection, return_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE product_variants pv SET stock_quantity = pv.stock_quantity + t.quantity
+        FROM (
+            SELECT oi.variant_id, SUM(ri.quantity)::INTEGER as quantity
+            FROM return_items ri
+            JOIN order_items oi ON oi.id = ri.order_item_id
+            WHERE ri.return_id = $1 AND oi.variant_id IS NOT NULL
+            GROUP BY oi.variant_id
+        ) t
+        WHERE pv.id = t.variant_id
+        "#,
+    )
+    .bind(return_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE products p SET stock_quantity = p.stock_quantity + t.quantity
+        FROM (
+            SELECT oi.product_id, SUM(ri.quantity)::INTEGER as quantity
+            FROM return_items ri
+            JOIN order_items oi ON oi.id = ri.order_item_id
+            WHERE ri.return_id = $1 AND oi.variant_id IS NULL AND oi.product_id IS NOT NULL
+            GROUP BY oi.product_id
+        ) t
+        WHERE p.id = t.product_id
+        "#,
+    )
+    .bind(return_id)
+    .execute(&mut *conn)
+    .await?;
+
+    // Units sold at a sale price count against its cap again
+    let lines: Vec<(Uuid, i32)> =
+        sqlx::query_as("SELECT order_item_id, quantity FROM return_items WHERE return_id = $1")
+            .bind(return_id)
+            .fetch_all(&mut *conn)
+            .await?;
+    crate::sales::release_sold(conn, &lines).await?;
+
+    Ok(())
+}
+
+/// Get a return by ID
+pub async fn get_return(pool: &PgPool, id: Uuid) -> Result<Return> {
+    let ret = sqlx::query_as::<_, Return>("SELECT * FROM returns WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Return not found"))?;
+
+    Ok(ret)
+}
+
+/// Get the lines of a return
+pub async fn get_return_items(pool: &PgPool, return_id: Uuid) -> Result<Vec<ReturnItem>> {
+    let items = sqlx::query_as::<_, ReturnItem>(
+        "SELECT * FROM return_items WHERE return_id = $1 ORDER BY id ASC",
+    )
+    .bind(return_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// Get the photos of a return, oldest first
+pub async fn get_return_photos(pool: &PgPool, return_id: Uuid) -> Result<Vec<ReturnPhoto>> {
+    let photos = sqlx::query_as::<_, ReturnPhoto>(
+        "SELECT * FROM return_photos WHERE return_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(return_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(photos)
+}
+
+/// Get one photo of a return
+pub async fn get_return_photo(pool: &PgPool, return_id: Uuid, id: Uuid) -> Result<ReturnPhoto> {
+    let photo = sqlx::query_as::<_, ReturnPhoto>(
+        "SELECT * FROM return_photos WHERE id = $1 AND return_id = $2",
+    )
+    .bind(id)
+    .bind(return_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Photo not found"))?;
+
+    Ok(photo)
+}
+
+/// Record a photo already written to storage
+pub async fn add_return_photo(
+    pool: &PgPool,
+    id: Uuid,
+    return_id: Uuid,
+    storage_key: &str,
+    content_type: &str,
+    size_bytes: i32,
+) -> Result<ReturnPhoto> {
+    let photo = sqlx::query_as::<_, ReturnPhoto>(
+        r#"
+        INSERT INTO return_photos (id, return_id, storage_key, content_type, size_bytes)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(return_id)
+    .bind(storage_key)
+    .bind(content_type)
+    .bind(size_bytes)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(photo)
+}
+
+/// Count the photos attached to a return
+pub async fn count_return_photos(pool: &PgPool, return_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM return_photos WHERE return_id = $1")
+        .bind(return_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Get an order's returns, oldest first
+pub async fn list_order_returns(pool: &PgPool, order_id: Uuid) -> Result<Vec<Return>> {
+    let returns = sqlx::query_as::<_, Return>(
+        "SELECT * FROM returns WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(returns)
+}
+
+/// List a store's returns (admin view), newest first
+pub async fn list_returns(
+    pool: &PgPool,
+    store_id: Uuid,
+    status: Option<ReturnStatus>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Return>> {
+    let returns = sqlx::query_as::<_, Return>(
+        r#"
+        SELECT * FROM returns
+        WHERE store_id = $1 AND ($2::VARCHAR IS NULL OR status = $2)
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(store_id)
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(returns)
+}
+
+/// Count a store's returns
+pub async fn count_returns(
+    pool: &PgPool,
+    store_id: Uuid,
+    status: Option<ReturnStatus>,
+) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM returns WHERE store_id = $1 AND ($2::VARCHAR IS NULL OR status = $2)",
+    )
+    .bind(store_id)
+    .bind(status)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Value of a return's lines, as their share of what was charged for the
+/// order lines, tax included
+pub async fn return_value<'e, E>(executor: E, return_id: Uuid) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let lines = sqlx::query_as::<_, (i32, i32, i64)>(
+        r#"
+        SELECT ri.quantity, oi.quantity,
+               oi.total + CASE WHEN o.prices_include_tax THEN 0 ELSE oi.tax_total END
+        FROM return_items ri
+        JOIN order_items oi ON oi.id = ri.order_item_id
+        JOIN orders o ON o.id = oi.order_id
+        WHERE ri.return_id = $1
+        "#,
+    )
+    .bind(return_id)
+    .fetch_all(executor)
+    .await?;
+
+    let value: i128 = lines
+        .into_iter()
+        .map(|(returned, ordered, total)| {
+            i128::from(total) * i128::from(returned) / i128::from(ordered)
+        })
+        .sum();
+
+    i64::try_from(value).map_err(|_| goseli_core::money::MoneyError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{refunds, test_support};
+    use goseli_core::dto::{CreateRefundRequest, RefundLineRequest, ReturnLineRequest};
+    use goseli_core::models::ReturnReason;
+
+    fn return_of(order_item_id: Uuid, quantity: i32) -> CreateReturnRequest {
+        CreateReturnRequest {
+            items: vec![ReturnLineRequest {
+                order_item_id,
+                quantity,
+            }],
+            reason: ReturnReason::Damaged,
+            comment: None,
+        }
+    }
+
+    async fn stock(pool: &PgPool, product_id: Uuid) -> i32 {
+        sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1")
+            .bind(product_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_store_credit_return_issues_card_and_restocks(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 2500, 10).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 2).await;
+        let item_id = crate::orders::get_order_items(&pool, order.id)
+            .await
+            .unwrap()[0]
+            .id;
+
+        let (ret, items) = create_return(
+            &pool,
+            order.id,
+            None,
+            &return_of(item_id, 1),
+            Duration::days(30),
+        )
+        .await
+        .unwrap();
+        assert_eq!(ret.status, ReturnStatus::Requested);
+        assert_eq!(items.len(), 1);
+        assert_eq!(return_value(&pool, ret.id).await.unwrap(), 2500);
+
+        decide_return(&pool, ret.id, true, None, staff_id)
+            .await
+            .unwrap();
+        receive_return(&pool, ret.id, ItemCondition::Unopened, None)
+            .await
+            .unwrap();
+        let closed = close_return(
+            &pool,
+            ret.id,
+            &ReturnClosing {
+                resolution: ReturnResolution::StoreCredit,
+                refund_id: None,
+                credit_amount: Some(2500),
+                restock: true,
+                note: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(closed.status, ReturnStatus::Closed);
+        let balance: i64 = sqlx::query_scalar("SELECT balance FROM gift_cards WHERE id = $1")
+            .bind(closed.gift_card_id.unwrap())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(balance, 2500);
+        assert_eq!(stock(&pool, product_id).await, 9);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_lines_cannot_be_returned_twice(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 2500, 10).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 2).await;
+        let item_id = crate::orders::get_order_items(&pool, order.id)
+            .await
+            .unwrap()[0]
+            .id;
+        let (first, _) = create_return(
+            &pool,
+            order.id,
+            None,
+            &return_of(item_id, 2),
+            Duration::days(30),
+        )
+        .await
+        .unwrap();
+
+        let error = create_return(
+            &pool,
+            order.id,
+            None,
+            &return_of(item_id, 1),
+            Duration::days(30),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), "return_quantity_exceeded");
+
+        // A rejected return frees its quantities again
+        decide_return(&pool, first.id, false, None, staff_id)
+            .await
+            .unwrap();
+        create_return(
+            &pool,
+            order.id,
+            None,
+            &return_of(item_id, 2),
+            Duration::days(30),
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn received_return(
+        pool: &PgPool,
+        store_id: Uuid,
+        staff_id: Uuid,
+        product_id: Uuid,
+    ) -> (Uuid, Uuid, Return) {
+        let order = test_support::paid_order(pool, store_id, product_id, 2).await;
+        test_support::captured_payment(pool, order.id).await;
+        let item_id = crate::orders::get_order_items(pool, order.id)
+            .await
+            .unwrap()[0]
+            .id;
+        let (ret, _) = create_return(
+            pool,
+            order.id,
+            None,
+            &return_of(item_id, 1),
+            Duration::days(30),
+        )
+        .await
+        .unwrap();
+        decide_return(pool, ret.id, true, None, staff_id)
+            .await
+            .unwrap();
+        let ret = receive_return(pool, ret.id, ItemCondition::Used, None)
+            .await
+            .unwrap();
+        (order.id, item_id, ret)
+    }
+
+    fn refund_of(order_item_id: Uuid, quantity: i32) -> CreateRefundRequest {
+        CreateRefundRequest {
+            full: false,
+            items: vec![RefundLineRequest {
+                order_item_id,
+                quantity,
+            }],
+            shipping: false,
+            amount: None,
+            restock: false,
+            reason: "Returned".into(),
+        }
+    }
+
+    fn store_credit(amount: i64) -> ReturnClosing<'static> {
+        ReturnClosing {
+            resolution: ReturnResolution::StoreCredit,
+            refund_id: None,
+            credit_amount: Some(amount),
+            restock: false,
+            note: None,
+        }
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_store_credit_is_not_paid_twice(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 2500, 10).await;
+        let (order_id, item_id, ret) = received_return(&pool, store_id, staff_id, product_id).await;
+
+        let error = close_return(&pool, ret.id, &store_credit(2501))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "credit_exceeds_return_value");
+        close_return(&pool, ret.id, &store_credit(2500))
+            .await
+            .unwrap();
+
+        // The credited unit cannot also be refunded
+        let error = refunds::create_refund(&pool, order_id, &refund_of(item_id, 2), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "refund_quantity_exceeded");
+        let full = CreateRefundRequest {
+            full: true,
+            items: vec![],
+            ..refund_of(item_id, 1)
+        };
+        let error = refunds::create_refund(&pool, order_id, &full, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "order_has_store_credit");
+        refunds::create_refund(&pool, order_id, &refund_of(item_id, 1), None)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_refunded_returns_close_with_their_refund(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let product_id = test_support::product(&pool, store_id, 2500, 10).await;
+        let (_, item_id, ret) = received_return(&pool, store_id, staff_id, product_id).await;
+
+        let (refund, _) =
+            refunds::create_return_refund(&pool, ret.id, &refund_of(item_id, 1), None)
+                .await
+                .unwrap();
+        assert_eq!(
+            get_return(&pool, ret.id).await.unwrap().refund_id,
+            Some(refund.id)
+        );
+
+        let error = refunds::create_return_refund(&pool, ret.id, &refund_of(item_id, 1), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "return_already_refunded");
+        let error = close_return(&pool, ret.id, &store_credit(2500))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "return_already_refunded");
+
+        let closed = close_return(
+            &pool,
+            ret.id,
+            &ReturnClosing {
+                resolution: ReturnResolution::Refund,
+                refund_id: Some(refund.id),
+                credit_amount: None,
+                restock: false,
+                note: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(closed.status, ReturnStatus::Closed);
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use goseli_core::{
+    dto::{SaleRequest, SaleResponse},
+    models::{Sale, SaleItem, SalePrice},
+    ApiError, Result,
+};
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Products and variants in a sale must belong to the store
+async fn validate_items(pool: &PgPool, store_id: Uuid, req: &SaleRequest) -> Result<()> {
+    let product_ids: Vec<Uuid> = req.items.iter().map(|item| item.product_id).collect();
+    let variant_ids: Vec<Option<Uuid>> = req.items.iter().map(|item| item.variant_id).collect();
+    let found: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*)
+        FROM UNNEST($2::UUID[], $3::UUID[]) AS i(product_id, variant_id)
+        INNER JOIN products p ON p.id = i.product_id AND p.store_id = $1
+        LEFT JOIN product_variants pv ON pv.id = i.variant_id AND pv.product_id = p.id
+        WHERE i.variant_id IS NULL OR pv.id IS NOT NULL
+        "#,
+    )
+    .bind(store_id)
+    .bind(&product_ids)
+    .bind(&variant_ids)
+    .fetch_one(pool)
+    .await?;
+    if found != req.items.len() as i64 {
+        return Err(ApiError::validation(
+            "items contains unknown products or variants",
+        ));
+    }
+
+    Ok(())
+}
+
+/// List a store's sales, latest start first
+pub async fn list_sales(
+    pool: &PgPool,
+    store_id: Uuid,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Sale>> {
+    let sales = sqlx::query_as::<_, Sale>(
+        r#"
+        SELECT * FROM sales
+        WHERE store_id = $1
+        ORDER BY starts_at DESC, created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(store_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sales)
+}
+
+/// Count a store's sales
+pub async fn count_sales(pool: &PgPool, store_id: Uuid) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sales WHERE store_id = $1")
+        .bind(store_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Get a sale with its items
+pub async fn get_sale(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<SaleResponse> {
+    let sale = sqlx::query_as::<_, Sale>("SELECT * FROM sales WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Sale not found"))?;
+    let items = list_items(pool, id).await?;
+
+    Ok(SaleResponse { sale, items })
+}
+
+async fn list_items<'e, E>(executor: E, sale_id: Uuid) -> Result<Vec<SaleItem>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let items = sqlx::query_as::<_, SaleItem>(
+        "SELECT * FROM sale_items WHERE sale_id = $1 ORDER BY created_at ASC, id ASC",
+    )
+    .bind(sale_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(items)
+}
+
+/// Write a sale's items. Items already in the sale keep the units sold at
+/// their price; items left out of the request are removed.
+async fn save_items(conn: &mut PgConnection, sale_id: Uuid, req: &SaleRequest) -> Result<()> {
+    let product_ids: Vec<Uuid> = req.items.iter().map(|item| item.product_id).collect();
+    let variant_ids: Vec<Option<Uuid>> = req.items.iter().map(|item| item.variant_id).collect();
+    let sale_prices: Vec<Option<i64>> = req.items.iter().map(|item| item.sale_price).collect();
+    let percentages: Vec<Option<i32>> = req.items.iter().map(|item| item.percentage_ppm).collect();
+    let limits: Vec<Option<i32>> = req.items.iter().map(|item| item.quantity_limit).collect();
+
+    sqlx::query(
+        r#"
+        DELETE FROM sale_items
+        WHERE sale_id = $1
+          AND (product_id, COALESCE(variant_id, product_id)) NOT IN (
+              SELECT product_id, COALESCE(variant_id, product_id)
+              FROM UNNEST($2::UUID[], $3::UUID[]) AS i(product_id, variant_id)
+          )
+        "#,
+    )
+    .bind(sale_id)
+    .bind(&product_ids)
+    .bind(&variant_ids)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO sale_items (
+            id, sale_id, product_id, variant_id, sale_price, percentage_ppm, quantity_limit
+        )
+        SELECT uuid_generate_v7(), $1, i.product_id, i.variant_id, i.sale_price,
+               i.percentage_ppm, i.quantity_limit
+        FROM UNNEST($2::UUID[], $3::UUID[], $4::BIGINT[], $5::INTEGER[], $6::INTEGER[])
+            AS i(product_id, variant_id, sale_price, percentage_ppm, quantity_limit)
+        ON CONFLICT (sale_id, product_id, variant_id) DO UPDATE SET
+            sale_price = EXCLUDED.sale_price,
+            percentage_ppm = EXCLUDED.percentage_ppm,
+            quantity_limit = EXCLUDED.quantity_limit
+        "#,
+    )
+    .bind(sale_id)
+    .bind(&product_ids)
+    .bind(&variant_ids)
+    .bind(&sale_prices)
+    .bind(&percentages)
+    .bind(&limits)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Create a sale with its items
+pub async fn create_sale(pool: &PgPool, store_id: Uuid, req: &SaleRequest) -> Result<SaleResponse> {
+    req.validate_terms()?;
+    validate_items(pool, store_id, req).await?;
+
+    let mut tx = pool.begin().await?;
+    let sale = sqlx::query_as::<_, Sale>(
+        r#"
+        INSERT INTO sales (id, store_id, name, percentage_ppm, starts_at, ends_at, is_active)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(req.percentage_ppm)
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(&mut *tx)
+    .await?;
+    save_items(&mut tx, sale.id, req).await?;
+    let items = list_items(&mut *tx, sale.id).await?;
+    tx.commit().await?;
+
+    Ok(SaleResponse { sale, items })
+}
+
+/// Replace a sale and its items
+pub async fn update_sale(
+    pool: &PgPool,
+    store_id: Uuid,
+    id: Uuid,
+    req: &SaleRequest,
+) -> Result<SaleResponse> {
+    req.validate_terms()?;
+    validate_items(pool, store_id, req).await?;
+
+    let mut tx = pool.begin().await?;
+    let sale = sqlx::query_as::<_, Sale>(
+        r#"
+        UPDATE sales SET
+            name = $3,
+            percentage_ppm = $4,
+            starts_at = $5,
+            ends_at = $6,
+            is_active = COALESCE($7, is_active)
+        WHERE id = $1 AND store_id = $2
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(req.percentage_ppm)
+    .bind(req.starts_at)
+    .bind(req.ends_at)
+    .bind(req.is_active)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Sale not found"))?;
+    save_items(&mut tx, sale.id, req).await?;
+    let items = list_items(&mut *tx, sale.id).await?;
+    tx.commit().await?;
+
+    Ok(SaleResponse { sale, items })
+}
+
+/// Delete a sale; orders keep the prices they were placed at
+pub async fn delete_sale(pool: &PgPool, store_id: Uuid, id: Uuid) -> Result<()> {
+    let result = sqlx::query("DELETE FROM sales WHERE id = $1 AND store_id = $2")
+        .bind(id)
+        .bind(store_id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("Sale not found"));
+    }
+
+    Ok(())
+}
+
+/// Sale prices running at `at` for the given products, latest started sale
+/// first. With `lock`, the sale items stay locked until the transaction
+/// ends, so their caps cannot be overrun by concurrent checkouts.
+pub async fn sale_prices<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    product_ids: &[Uuid],
+    at: OffsetDateTime,
+    lock: bool,
+) -> Result<Vec<SalePrice>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    if product_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut sql = String::from(
+        r#"
+        SELECT
+            si.id as sale_item_id,
+            s.id as sale_id,
+            s.name,
+            s.ends_at,
+            si.product_id,
+            si.variant_id,
+            si.sale_price,
+            COALESCE(si.percentage_ppm, s.percentage_ppm) as percentage_ppm,
+            si.quantity_limit - si.sold_quantity as remaining
+        FROM sale_items si
+        INNER JOIN sales s ON si.sale_id = s.id
+        WHERE s.store_id = $1 AND s.is_active
+          AND s.starts_at <= $2 AND s.ends_at > $2
+          AND si.product_id = ANY($3)
+        ORDER BY s.starts_at DESC, si.id
+        "#,
+    );
+    if lock {
+        sql.push_str(" FOR UPDATE OF si");
+    }
+    let sales = sqlx::query_as::<_, SalePrice>(&sql)
+        .bind(store_id)
+        .bind(at)
+        .bind(product_ids)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(sales)
+}
+
+/// Count units sold at sale prices against the items' caps
+pub async fn record_sold(conn: &mut PgConnection, sold: &HashMap<Uuid, i32>) -> Result<()> {
+    for (&sale_item_id, &quantity) in sold {
+        if quantity == 0 {
+            continue;
+        }
+        sqlx::query("UPDATE sale_items SET sold_quantity = sold_quantity + $2 WHERE id = $1")
+            .bind(sale_item_id)
+            .bind(quantity)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Give units of order lines (order item ID and quantity) that went back on
+/// the shelf back to the caps of the sale items they were sold under
+pub async fn release_sold(conn: &mut PgConnection, lines: &[(Uuid, i32)]) -> Result<()> {
+    let (order_item_ids, quantities): (Vec<Uuid>, Vec<i32>) = lines.iter().copied().unzip();
+    sqlx::query(
+        r#"
+        UPDATE sale_items si SET sold_quantity = GREATEST(si.sold_quantity - t.quantity, 0)
+        FROM (
+            SELECT oi.sale_item_id, SUM(l.quantity)::INTEGER as quantity
+            FROM UNNEST($1::UUID[], $2::INTEGER[]) AS l(order_item_id, quantity)
+            JOIN order_items oi ON oi.id = l.order_item_id
+            WHERE oi.sale_item_id IS NOT NULL
+            GROUP BY oi.sale_item_id
+        ) t
+        WHERE si.id = t.sale_item_id
+        "#,
+    )
+    .bind(&order_item_ids)
+    .bind(&quantities)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use goseli_core::models::{OrderAction, OrderActor};
+
+    async fn sold(pool: &PgPool, sale_item_id: Uuid) -> i32 {
+        sqlx::query_scalar("SELECT sold_quantity FROM sale_items WHERE id = $1")
+            .bind(sale_item_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_cancelled_orders_give_sale_units_back(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let product_id = test_support::product(&pool, store_id, 1000, 10).await;
+        let sale_item_id = test_support::sale_item(&pool, store_id, product_id, 800, Some(5)).await;
+        let order = test_support::paid_order(&pool, store_id, product_id, 3).await;
+        assert_eq!(sold(&pool, sale_item_id).await, 3);
+
+        crate::orders::transition_order(
+            &pool,
+            order.id,
+            OrderAction::Cancel,
+            OrderActor::System,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(sold(&pool, sale_item_id).await, 0);
+    }
+}
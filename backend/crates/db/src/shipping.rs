@@ -0,0 +1,333 @@
+use goseli_core::{
+    dto::{
+        CreateShippingMethodRequest, CreateShippingZoneRequest, UpdateShippingMethodRequest,
+        UpdateShippingZoneRequest,
+    },
+    models::{
+        shipping::validate_method_config, Parcel, ShippingMethod, ShippingMethodKind, ShippingZone,
+    },
+    ApiError, Result,
+};
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+/// Uppercase country codes and reject anything that is not two letters
+pub(crate) fn normalize_countries(countries: &[String]) -> Result<Vec<String>> {
+    countries
+        .iter()
+        .map(|code| {
+            let code = code.trim().to_uppercase();
+            if code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+                Ok(code)
+            } else {
+                Err(ApiError::validation(format!(
+                    "Invalid country code '{}'",
+                    code
+                )))
+            }
+        })
+        .collect()
+}
+
+/// Drop blank entries and surrounding whitespace
+pub(crate) fn clean(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// List a store's zones in matching order
+pub async fn list_zones(
+    pool: &PgPool,
+    store_id: Uuid,
+    active_only: bool,
+) -> Result<Vec<ShippingZone>> {
+    let zones = sqlx::query_as::<_, ShippingZone>(
+        r#"
+        SELECT * FROM shipping_zones
+        WHERE store_id = $1 AND (is_active OR NOT $2)
+        ORDER BY priority ASC, created_at ASC
+        "#,
+    )
+    .bind(store_id)
+    .bind(active_only)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(zones)
+}
+
+/// List the methods of all of a store's zones
+pub async fn list_methods(
+    pool: &PgPool,
+    store_id: Uuid,
+    active_only: bool,
+) -> Result<Vec<ShippingMethod>> {
+    let methods = sqlx::query_as::<_, ShippingMethod>(
+        r#"
+        SELECT m.* FROM shipping_methods m
+        JOIN shipping_zones z ON z.id = m.zone_id
+        WHERE z.store_id = $1 AND (m.is_active OR NOT $2)
+        ORDER BY m.sort_order ASC, m.created_at ASC
+        "#,
+    )
+    .bind(store_id)
+    .bind(active_only)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(methods)
+}
+
+/// Get a zone by ID
+pub async fn get_zone(pool: &PgPool, id: Uuid) -> Result<ShippingZone> {
+    let zone = sqlx::query_as::<_, ShippingZone>("SELECT * FROM shipping_zones WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Shipping zone not found"))?;
+
+    Ok(zone)
+}
+
+/// Create a zone
+pub async fn create_zone(
+    pool: &PgPool,
+    store_id: Uuid,
+    req: &CreateShippingZoneRequest,
+) -> Result<ShippingZone> {
+    let countries = normalize_countries(&req.countries)?;
+
+    let zone = sqlx::query_as::<_, ShippingZone>(
+        r#"
+        INSERT INTO shipping_zones (
+            id, store_id, name, countries, regions, postcode_patterns, priority, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(&countries)
+    .bind(clean(&req.regions))
+    .bind(clean(&req.postcode_patterns))
+    .bind(req.priority.unwrap_or(0))
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(zone)
+}
+
+/// Update a zone
+pub async fn update_zone(
+    pool: &PgPool,
+    id: Uuid,
+    req: &UpdateShippingZoneRequest,
+) -> Result<ShippingZone> {
+    let countries = req
+        .countries
+        .as_deref()
+        .map(normalize_countries)
+        .transpose()?;
+
+    let zone = sqlx::query_as::<_, ShippingZone>(
+        r#"
+        UPDATE shipping_zones SET
+            name = COALESCE($2, name),
+            countries = COALESCE($3, countries),
+            regions = COALESCE($4, regions),
+            postcode_patterns = COALESCE($5, postcode_patterns),
+            priority = COALESCE($6, priority),
+            is_active = COALESCE($7, is_active)
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&countries)
+    .bind(req.regions.as_deref().map(clean))
+    .bind(req.postcode_patterns.as_deref().map(clean))
+    .bind(req.priority)
+    .bind(req.is_active)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Shipping zone not found"))?;
+
+    Ok(zone)
+}
+
+/// Delete a zone and its methods
+pub async fn delete_zone(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM shipping_zones WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Add a method to a zone; codes are unique within a zone
+pub async fn create_method(
+    pool: &PgPool,
+    zone_id: Uuid,
+    req: &CreateShippingMethodRequest,
+) -> Result<ShippingMethod> {
+    validate_method_config(
+        req.kind,
+        &req.tiers,
+        req.min_subtotal,
+        req.carrier_code.as_deref(),
+        req.service_code.as_deref(),
+    )?;
+    get_zone(pool, zone_id).await?;
+
+    let method = sqlx::query_as::<_, ShippingMethod>(
+        r#"
+        INSERT INTO shipping_methods (
+            id, zone_id, code, name, description, kind, rate, tiers, min_subtotal,
+            sort_order, is_active, carrier_code, service_code
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (zone_id, code) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(zone_id)
+    .bind(req.code.trim())
+    .bind(&req.name)
+    .bind(&req.description)
+    .bind(req.kind)
+    .bind(req.rate.unwrap_or(0))
+    .bind(Json(&req.tiers))
+    .bind(req.min_subtotal)
+    .bind(req.sort_order.unwrap_or(0))
+    .bind(req.is_active.unwrap_or(true))
+    .bind(req.carrier_code.as_deref().map(str::to_lowercase))
+    .bind(&req.service_code)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| {
+        ApiError::conflict(format!(
+            "Shipping method '{}' already exists in this zone",
+            req.code.trim()
+        ))
+    })?;
+
+    Ok(method)
+}
+
+/// Get a method by ID
+pub async fn get_method(pool: &PgPool, id: Uuid) -> Result<ShippingMethod> {
+    let method =
+        sqlx::query_as::<_, ShippingMethod>("SELECT * FROM shipping_methods WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Shipping method not found"))?;
+
+    Ok(method)
+}
+
+/// Update a method; the resulting settings are checked against its kind
+pub async fn update_method(
+    pool: &PgPool,
+    id: Uuid,
+    req: &UpdateShippingMethodRequest,
+) -> Result<ShippingMethod> {
+    let current = get_method(pool, id).await?;
+    let kind = req.kind.unwrap_or(current.kind);
+    let tiers = req.tiers.as_ref().unwrap_or(&current.tiers);
+    // Moving away from a carrier kind drops the carrier settings
+    let keep = kind == ShippingMethodKind::Carrier;
+    let carrier_code = req
+        .carrier_code
+        .as_deref()
+        .map(str::to_lowercase)
+        .or(current.carrier_code.filter(|_| keep));
+    let service_code = req
+        .service_code
+        .as_ref()
+        .or(current.service_code.as_ref().filter(|_| keep));
+    validate_method_config(
+        kind,
+        tiers,
+        req.min_subtotal.or(current.min_subtotal),
+        carrier_code.as_deref(),
+        service_code.map(String::as_str),
+    )?;
+
+    let method = sqlx::query_as::<_, ShippingMethod>(
+        r#"
+        UPDATE shipping_methods SET
+            name = COALESCE($2, name),
+            description = COALESCE($3, description),
+            kind = $4,
+            rate = COALESCE($5, rate),
+            tiers = $6,
+            min_subtotal = COALESCE($7, min_subtotal),
+            sort_order = COALESCE($8, sort_order),
+            is_active = COALESCE($9, is_active),
+            carrier_code = $10,
+            service_code = $11
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&req.description)
+    .bind(kind)
+    .bind(req.rate)
+    .bind(Json(tiers))
+    .bind(req.min_subtotal)
+    .bind(req.sort_order)
+    .bind(req.is_active)
+    .bind(&carrier_code)
+    .bind(service_code)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(method)
+}
+
+/// Delete a method
+pub async fn delete_method(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM shipping_methods WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Value and weight of a cart's contents.
+///
+/// Lines without a known weight count as weightless.
+pub async fn cart_parcel(pool: &PgPool, cart_id: Uuid) -> Result<Parcel> {
+    let (subtotal, weight_grams): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM((COALESCE(pv.price, p.price) + ci.price_modifier)::BIGINT * ci.quantity), 0)::BIGINT,
+            COALESCE(SUM(COALESCE(pv.weight_grams, p.weight_grams, 0)::BIGINT * ci.quantity), 0)::BIGINT
+        FROM cart_items ci
+        INNER JOIN products p ON ci.product_id = p.id
+        LEFT JOIN product_variants pv ON ci.variant_id = pv.id
+        WHERE ci.cart_id = $1
+        "#,
+    )
+    .bind(cart_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Parcel {
+        subtotal,
+        weight_grams,
+    })
+}
@@ -0,0 +1,301 @@
+use goseli_core::{
+    models::{
+        Fulfillment, FulfillmentStatus, LabelFormat, ShippingLabel, ShippingLabelStatus,
+        TrackingStatus,
+    },
+    ApiError, Result,
+};
+use sqlx::{PgConnection, PgPool};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::fulfillments::tracking_details;
+
+/// What a carrier returned for a purchased label
+#[derive(Debug, Clone)]
+pub struct LabelPurchase {
+    pub carrier_shipment_id: String,
+    pub tracking_number: String,
+    pub tracking_url: Option<String>,
+    pub storage_key: String,
+    pub amount: i64,
+    pub currency: String,
+}
+
+async fn lock_fulfillment(conn: &mut PgConnection, id: Uuid) -> Result<Fulfillment> {
+    sqlx::query_as::<_, Fulfillment>("SELECT * FROM fulfillments WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Fulfillment not found"))
+}
+
+/// Weight of a parcel's contents from the catalog; unknown weights count as zero
+pub async fn fulfillment_weight(pool: &PgPool, fulfillment_id: Uuid) -> Result<i64> {
+    let (weight,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COALESCE(SUM(COALESCE(pv.weight_grams, p.weight_grams, 0)::BIGINT * fi.quantity), 0)::BIGINT
+        FROM fulfillment_items fi
+        JOIN order_items oi ON oi.id = fi.order_item_id
+        LEFT JOIN products p ON p.id = oi.product_id
+        LEFT JOIN product_variants pv ON pv.id = oi.variant_id
+        WHERE fi.fulfillment_id = $1
+        "#,
+    )
+    .bind(fulfillment_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(weight)
+}
+
+/// Record a label purchase before calling the carrier.
+///
+/// Only parcels that have not shipped can get a label, and only one at a
+/// time: an existing label must be voided first.
+pub async fn begin_label(
+    pool: &PgPool,
+    fulfillment_id: Uuid,
+    carrier: &str,
+    service_code: &str,
+    format: LabelFormat,
+    created_by: Uuid,
+) -> Result<ShippingLabel> {
+    let mut tx = pool.begin().await?;
+
+    let fulfillment = lock_fulfillment(&mut tx, fulfillment_id).await?;
+    if fulfillment.status != FulfillmentStatus::Pending {
+        return Err(ApiError::rule(
+            "fulfillment_not_pending",
+            format!(
+                "Labels can only be bought for parcels that have not shipped; this one is {}",
+                fulfillment.status
+            ),
+        ));
+    }
+    let live: Option<(ShippingLabelStatus,)> = sqlx::query_as(
+        "SELECT status FROM shipping_labels WHERE fulfillment_id = $1 AND status IN ('pending', 'purchased')",
+    )
+    .bind(fulfillment_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some((status,)) = live {
+        return Err(ApiError::rule(
+            "label_exists",
+            format!("This parcel already has a {} label; void it first", status),
+        ));
+    }
+
+    let label = sqlx::query_as::<_, ShippingLabel>(
+        r#"
+        INSERT INTO shipping_labels (id, fulfillment_id, carrier, service_code, format, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(fulfillment_id)
+    .bind(carrier)
+    .bind(service_code)
+    .bind(format)
+    .bind(created_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(label)
+}
+
+/// Record that the carrier refused or failed to sell a label
+pub async fn fail_label(pool: &PgPool, id: Uuid, error: &str) -> Result<ShippingLabel> {
+    let label = sqlx::query_as::<_, ShippingLabel>(
+        "UPDATE shipping_labels SET status = 'failed', error = $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(id)
+    .bind(error)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(label)
+}
+
+/// Store a purchased label and put its tracking details on the parcel
+pub async fn complete_label(
+    pool: &PgPool,
+    id: Uuid,
+    purchase: &LabelPurchase,
+) -> Result<(ShippingLabel, Fulfillment)> {
+    let mut tx = pool.begin().await?;
+
+    let label = sqlx::query_as::<_, ShippingLabel>(
+        r#"
+        UPDATE shipping_labels SET
+            status = 'purchased', carrier_shipment_id = $2, tracking_number = $3,
+            storage_key = $4, amount = $5, currency = $6
+        WHERE id = $1 AND status = 'pending'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&purchase.carrier_shipment_id)
+    .bind(&purchase.tracking_number)
+    .bind(&purchase.storage_key)
+    .bind(purchase.amount)
+    .bind(&purchase.currency)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::conflict("Label purchase is no longer pending"))?;
+
+    lock_fulfillment(&mut tx, label.fulfillment_id).await?;
+    let (store_id,): (Uuid,) = sqlx::query_as(
+        "SELECT o.store_id FROM orders o JOIN fulfillments f ON f.order_id = o.id WHERE f.id = $1",
+    )
+    .bind(label.fulfillment_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let (carrier, tracking_url) = tracking_details(
+        &mut tx,
+        store_id,
+        Some(&label.carrier),
+        Some(&purchase.tracking_number),
+        purchase.tracking_url.as_deref(),
+    )
+    .await?;
+
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        r#"
+        UPDATE fulfillments SET
+            carrier = $2, tracking_number = $3, tracking_url = $4,
+            tracking_status = 'pre_transit', tracking_checked_at = NULL
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(label.fulfillment_id)
+    .bind(&carrier)
+    .bind(&purchase.tracking_number)
+    .bind(&tracking_url)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((label, fulfillment))
+}
+
+/// The pending or purchased label of a parcel
+pub async fn live_label(pool: &PgPool, fulfillment_id: Uuid) -> Result<Option<ShippingLabel>> {
+    let label = sqlx::query_as::<_, ShippingLabel>(
+        "SELECT * FROM shipping_labels WHERE fulfillment_id = $1 AND status IN ('pending', 'purchased')",
+    )
+    .bind(fulfillment_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(label)
+}
+
+/// Pending or purchased labels of all of an order's parcels
+pub async fn list_order_labels(pool: &PgPool, order_id: Uuid) -> Result<Vec<ShippingLabel>> {
+    let labels = sqlx::query_as::<_, ShippingLabel>(
+        r#"
+        SELECT l.* FROM shipping_labels l
+        JOIN fulfillments f ON f.id = l.fulfillment_id
+        WHERE f.order_id = $1 AND l.status IN ('pending', 'purchased')
+        "#,
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(labels)
+}
+
+/// Record that a label was voided at the carrier.
+///
+/// The parcel loses the label's tracking details so a new label can be
+/// bought for it.
+pub async fn void_label(pool: &PgPool, id: Uuid) -> Result<(ShippingLabel, Fulfillment)> {
+    let mut tx = pool.begin().await?;
+
+    let label = sqlx::query_as::<_, ShippingLabel>(
+        r#"
+        UPDATE shipping_labels SET status = 'voided', voided_at = NOW()
+        WHERE id = $1 AND status = 'purchased'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::conflict("Label is not in use"))?;
+
+    let mut fulfillment = lock_fulfillment(&mut tx, label.fulfillment_id).await?;
+    // Unless staff already replaced them by hand
+    if fulfillment.tracking_number == label.tracking_number {
+        fulfillment = sqlx::query_as::<_, Fulfillment>(
+            r#"
+            UPDATE fulfillments SET
+                tracking_number = NULL, tracking_url = NULL,
+                tracking_status = NULL, tracking_checked_at = NULL
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(label.fulfillment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok((label, fulfillment))
+}
+
+/// Purchased labels of shipped parcels whose tracking was last checked
+/// before `checked_before`, least recently checked first
+pub async fn labels_to_track(
+    pool: &PgPool,
+    checked_before: OffsetDateTime,
+    limit: i64,
+) -> Result<Vec<ShippingLabel>> {
+    let labels = sqlx::query_as::<_, ShippingLabel>(
+        r#"
+        SELECT l.* FROM shipping_labels l
+        JOIN fulfillments f ON f.id = l.fulfillment_id
+        WHERE l.status = 'purchased'
+          AND f.status = 'shipped'
+          AND (f.tracking_checked_at IS NULL OR f.tracking_checked_at < $1)
+        ORDER BY f.tracking_checked_at ASC NULLS FIRST
+        LIMIT $2
+        "#,
+    )
+    .bind(checked_before)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(labels)
+}
+
+/// Store the status a carrier reported for a parcel
+pub async fn record_tracking(
+    pool: &PgPool,
+    fulfillment_id: Uuid,
+    status: TrackingStatus,
+) -> Result<Fulfillment> {
+    let fulfillment = sqlx::query_as::<_, Fulfillment>(
+        r#"
+        UPDATE fulfillments SET tracking_status = $2, tracking_checked_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(fulfillment_id)
+    .bind(status)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(fulfillment)
+}
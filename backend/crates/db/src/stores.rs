@@ -0,0 +1,24 @@
+use goseli_core::{models::Store, ApiError, Result};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// List all active stores (used by background jobs that run per store)
+pub async fn list_active_stores(pool: &PgPool) -> Result<Vec<Store>> {
+    let stores =
+        sqlx::query_as::<_, Store>("SELECT * FROM stores WHERE is_active = true ORDER BY id")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(stores)
+}
+
+/// Get a store by ID
+pub async fn get_store(pool: &PgPool, id: Uuid) -> Result<Store> {
+    let store = sqlx::query_as::<_, Store>("SELECT * FROM stores WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Store not found"))?;
+
+    Ok(store)
+}
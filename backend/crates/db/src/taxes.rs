@@ -0,0 +1,577 @@
+use goseli_core::{
+    dto::{CreateTaxRateRequest, ExemptSalesRow, TaxReport, TaxReportRow, UpdateTaxRateRequest},
+    models::{TaxClass, TaxExemption, TaxExemptionStatus, TaxRate},
+    ApiError, Result,
+};
+use sqlx::{Executor, PgConnection, PgPool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::shipping::{clean, normalize_countries};
+
+/// Exempt products are never taxed, so no rate can target them
+fn ensure_taxable_class(class: Option<TaxClass>) -> Result<()> {
+    if class == Some(TaxClass::Exempt) {
+        return Err(ApiError::validation(
+            "Rates can only be set for the standard and reduced tax classes",
+        ));
+    }
+    Ok(())
+}
+
+fn normalize_country(country: &str) -> Result<String> {
+    Ok(normalize_countries(&[country.to_string()])?.remove(0))
+}
+
+/// List a store's tax rates
+pub async fn list_rates<'e, E>(
+    executor: E,
+    store_id: Uuid,
+    active_only: bool,
+) -> Result<Vec<TaxRate>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rates = sqlx::query_as::<_, TaxRate>(
+        r#"
+        SELECT * FROM tax_rates
+        WHERE store_id = $1 AND (is_active OR NOT $2)
+        ORDER BY country ASC, priority ASC, created_at ASC
+        "#,
+    )
+    .bind(store_id)
+    .bind(active_only)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rates)
+}
+
+/// Create a tax rate
+pub async fn create_rate(
+    pool: &PgPool,
+    store_id: Uuid,
+    req: &CreateTaxRateRequest,
+) -> Result<TaxRate> {
+    ensure_taxable_class(req.tax_class)?;
+    let country = normalize_country(&req.country)?;
+
+    let rate = sqlx::query_as::<_, TaxRate>(
+        r#"
+        INSERT INTO tax_rates (
+            id, store_id, name, country, region, postcode_patterns, tax_class,
+            rate_ppm, priority, is_active
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::now_v7())
+    .bind(store_id)
+    .bind(&req.name)
+    .bind(&country)
+    .bind(req.region.as_deref().map(str::trim))
+    .bind(clean(&req.postcode_patterns))
+    .bind(req.tax_class.unwrap_or_default())
+    .bind(req.rate_ppm)
+    .bind(req.priority.unwrap_or(0))
+    .bind(req.is_active.unwrap_or(true))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rate)
+}
+
+/// Update a tax rate
+pub async fn update_rate(pool: &PgPool, id: Uuid, req: &UpdateTaxRateRequest) -> Result<TaxRate> {
+    ensure_taxable_class(req.tax_class)?;
+    let country = req.country.as_deref().map(normalize_country).transpose()?;
+    let region = req.region.as_deref().map(str::trim);
+
+    let rate = sqlx::query_as::<_, TaxRate>(
+        r#"
+        UPDATE tax_rates SET
+            name = COALESCE($2, name),
+            country = COALESCE($3, country),
+            region = CASE WHEN $4::VARCHAR IS NULL THEN region ELSE NULLIF($4, '') END,
+            postcode_patterns = COALESCE($5, postcode_patterns),
+            tax_class = COALESCE($6, tax_class),
+            rate_ppm = COALESCE($7, rate_ppm),
+            priority = COALESCE($8, priority),
+            is_active = COALESCE($9, is_active)
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(&req.name)
+    .bind(&country)
+    .bind(region)
+    .bind(req.postcode_patterns.as_deref().map(clean))
+    .bind(req.tax_class)
+    .bind(req.rate_ppm)
+    .bind(req.priority)
+    .bind(req.is_active)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| ApiError::not_found("Tax rate not found"))?;
+
+    Ok(rate)
+}
+
+/// Delete a tax rate; orders keep their snapshot of it
+pub async fn delete_rate(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM tax_rates WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// A certificate upload to record
+#[derive(Debug, Clone)]
+pub struct NewTaxExemption {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub user_id: Uuid,
+    pub certificate_number: Option<String>,
+    pub storage_key: String,
+    pub content_type: String,
+    pub size_bytes: i32,
+    pub expires_at: OffsetDateTime,
+}
+
+/// Mark a customer's approved exemptions as replaced by a newer one
+async fn revoke_approved(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    except: Uuid,
+    reviewer: Uuid,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE tax_exemptions SET
+            status = 'revoked', note = 'Replaced by a newer certificate',
+            reviewed_by = $3, reviewed_at = NOW()
+        WHERE user_id = $1 AND id <> $2 AND status = 'approved'
+        "#,
+    )
+    .bind(user_id)
+    .bind(except)
+    .bind(reviewer)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Record an uploaded certificate.
+///
+/// Customers' uploads wait for review, one at a time; staff uploading on a
+/// customer's behalf (`approved_by`) approve them right away.
+pub async fn create_exemption(
+    pool: &PgPool,
+    new: &NewTaxExemption,
+    approved_by: Option<Uuid>,
+) -> Result<TaxExemption> {
+    let mut tx = pool.begin().await?;
+
+    if approved_by.is_none() {
+        let pending: Option<(Uuid,)> = sqlx::query_as(
+            "SELECT id FROM tax_exemptions WHERE user_id = $1 AND status = 'pending' FOR UPDATE",
+        )
+        .bind(new.user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if pending.is_some() {
+            return Err(ApiError::rule(
+                "exemption_pending",
+                "A certificate is already waiting for review",
+            ));
+        }
+    }
+
+    let exemption = sqlx::query_as::<_, TaxExemption>(
+        r#"
+        INSERT INTO tax_exemptions (
+            id, store_id, user_id, status, certificate_number, storage_key, content_type,
+            size_bytes, expires_at, reviewed_by, reviewed_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, CASE WHEN $10::UUID IS NULL THEN NULL ELSE NOW() END)
+        RETURNING *
+        "#,
+    )
+    .bind(new.id)
+    .bind(new.store_id)
+    .bind(new.user_id)
+    .bind(if approved_by.is_some() {
+        TaxExemptionStatus::Approved
+    } else {
+        TaxExemptionStatus::Pending
+    })
+    .bind(&new.certificate_number)
+    .bind(&new.storage_key)
+    .bind(&new.content_type)
+    .bind(new.size_bytes)
+    .bind(new.expires_at)
+    .bind(approved_by)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if let Some(reviewer) = approved_by {
+        revoke_approved(&mut tx, new.user_id, exemption.id, reviewer).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(exemption)
+}
+
+/// Get an exemption by ID
+pub async fn get_exemption(pool: &PgPool, id: Uuid) -> Result<TaxExemption> {
+    let exemption = sqlx::query_as::<_, TaxExemption>("SELECT * FROM tax_exemptions WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Tax exemption not found"))?;
+
+    Ok(exemption)
+}
+
+/// A customer's exemptions, newest first
+pub async fn list_user_exemptions(pool: &PgPool, user_id: Uuid) -> Result<Vec<TaxExemption>> {
+    let exemptions = sqlx::query_as::<_, TaxExemption>(
+        "SELECT * FROM tax_exemptions WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(exemptions)
+}
+
+/// List a store's exemptions, oldest first so the review queue reads in order
+pub async fn list_exemptions(
+    pool: &PgPool,
+    store_id: Uuid,
+    status: Option<TaxExemptionStatus>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TaxExemption>> {
+    let exemptions = sqlx::query_as::<_, TaxExemption>(
+        r#"
+        SELECT * FROM tax_exemptions
+        WHERE store_id = $1 AND ($2::VARCHAR IS NULL OR status = $2)
+        ORDER BY created_at ASC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(store_id)
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(exemptions)
+}
+
+/// Count a store's exemptions
+pub async fn count_exemptions(
+    pool: &PgPool,
+    store_id: Uuid,
+    status: Option<TaxExemptionStatus>,
+) -> Result<i64> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM tax_exemptions WHERE store_id = $1 AND ($2::VARCHAR IS NULL OR status = $2)",
+    )
+    .bind(store_id)
+    .bind(status)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Approve, reject or revoke an exemption.
+///
+/// Approving one revokes the customer's older approved certificates.
+pub async fn decide_exemption(
+    pool: &PgPool,
+    id: Uuid,
+    next: TaxExemptionStatus,
+    expires_at: Option<OffsetDateTime>,
+    note: Option<&str>,
+    reviewer: Uuid,
+) -> Result<TaxExemption> {
+    let mut tx = pool.begin().await?;
+
+    let current =
+        sqlx::query_as::<_, TaxExemption>("SELECT * FROM tax_exemptions WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Tax exemption not found"))?;
+    if !current.status.can_become(next) {
+        return Err(ApiError::rule(
+            "invalid_exemption_transition",
+            format!(
+                "An exemption that is {} cannot become {}",
+                current.status, next
+            ),
+        ));
+    }
+
+    let exemption = sqlx::query_as::<_, TaxExemption>(
+        r#"
+        UPDATE tax_exemptions SET
+            status = $2, expires_at = COALESCE($3, expires_at), note = $4,
+            reviewed_by = $5, reviewed_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(next)
+    .bind(expires_at)
+    .bind(note)
+    .bind(reviewer)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if next == TaxExemptionStatus::Approved {
+        revoke_approved(&mut tx, exemption.user_id, exemption.id, reviewer).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(exemption)
+}
+
+/// The exemption a customer buys under right now, if any
+pub async fn effective_exemption<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    now: OffsetDateTime,
+) -> Result<Option<TaxExemption>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let exemption = sqlx::query_as::<_, TaxExemption>(
+        r#"
+        SELECT * FROM tax_exemptions
+        WHERE user_id = $1 AND status = 'approved' AND expires_at > $2
+        ORDER BY expires_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(now)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(exemption)
+}
+
+/// Orders a tax report covers: paid and neither cancelled nor fully refunded
+const REPORTED_ORDERS: &str = r#"
+    o.store_id = $1 AND o.created_at >= $2 AND o.created_at < $3
+    AND o.status IN ('paid', 'partially_fulfilled', 'fulfilled', 'completed')
+"#;
+
+/// Tax collected per currency, jurisdiction and rate over `[from, to)`.
+///
+/// Built from the snapshots taken when the orders were placed, so later rate
+/// changes do not alter past figures. Partial refunds are not deducted.
+pub async fn tax_report(
+    pool: &PgPool,
+    store_id: Uuid,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Result<TaxReport> {
+    let rows = sqlx::query_as::<_, TaxReportRow>(&format!(
+        r#"
+        SELECT
+            o.currency,
+            t.country,
+            t.region,
+            t.name,
+            t.rate_ppm,
+            COUNT(DISTINCT o.id) as order_count,
+            COALESCE(SUM(t.taxable_amount), 0)::BIGINT as taxable_amount,
+            COALESCE(SUM(t.amount), 0)::BIGINT as tax_amount
+        FROM orders o
+        CROSS JOIN LATERAL jsonb_to_recordset(o.tax_lines) AS t(
+            country TEXT, region TEXT, name TEXT, rate_ppm INTEGER,
+            taxable_amount BIGINT, amount BIGINT
+        )
+        WHERE {REPORTED_ORDERS}
+        GROUP BY o.currency, t.country, t.region, t.name, t.rate_ppm
+        ORDER BY o.currency, t.country, t.region NULLS FIRST, t.name, t.rate_ppm
+        "#
+    ))
+    .bind(store_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    let exempt_sales = sqlx::query_as::<_, ExemptSalesRow>(&format!(
+        r#"
+        SELECT
+            o.currency,
+            COUNT(*) as order_count,
+            COALESCE(SUM(o.total), 0)::BIGINT as amount
+        FROM orders o
+        WHERE {REPORTED_ORDERS} AND o.tax_exemption_id IS NOT NULL
+        GROUP BY o.currency
+        ORDER BY o.currency
+        "#
+    ))
+    .bind(store_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(TaxReport {
+        from,
+        to,
+        rows,
+        exempt_sales,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orders::{self, NewOrder};
+    use crate::test_support;
+    use goseli_core::models::{OrderAction, OrderActor};
+    use time::Duration;
+
+    fn certificate(store_id: Uuid, user_id: Uuid) -> NewTaxExemption {
+        NewTaxExemption {
+            id: Uuid::now_v7(),
+            store_id,
+            user_id,
+            certificate_number: Some("EX-1".into()),
+            storage_key: format!("exemptions/{}", Uuid::now_v7()),
+            content_type: "application/pdf".into(),
+            size_bytes: 1024,
+            expires_at: OffsetDateTime::now_utc() + Duration::days(365),
+        }
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_report_covers_taxed_and_exempt_sales(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let customer_id = test_support::customer(&pool, store_id).await;
+        test_support::tax_rate(&pool, store_id, "US", 100_000).await;
+        let product_id = test_support::product(&pool, store_id, 5000, 10).await;
+
+        let taxed = test_support::paid_order(&pool, store_id, product_id, 2).await;
+        assert_eq!(taxed.tax_total.amount(), 1000);
+
+        let exemption =
+            create_exemption(&pool, &certificate(store_id, customer_id), Some(staff_id))
+                .await
+                .unwrap();
+        assert_eq!(exemption.status, TaxExemptionStatus::Approved);
+        let cart_id = test_support::cart(
+            &pool,
+            store_id,
+            Some(customer_id),
+            OffsetDateTime::now_utc(),
+        )
+        .await;
+        test_support::cart_item(&pool, cart_id, product_id, 1).await;
+        let exempt = orders::place_order(
+            &pool,
+            cart_id,
+            &NewOrder {
+                user_id: Some(customer_id),
+                email: "ada@example.com".into(),
+                ..NewOrder::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(exempt.tax_total.is_zero());
+        orders::transition_order(
+            &pool,
+            exempt.id,
+            OrderAction::Pay,
+            OrderActor::System,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let now = OffsetDateTime::now_utc();
+        let report = tax_report(
+            &pool,
+            store_id,
+            now - Duration::hours(1),
+            now + Duration::hours(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].order_count, 1);
+        assert_eq!(report.rows[0].taxable_amount, 10_000);
+        assert_eq!(report.rows[0].tax_amount, 1000);
+        assert_eq!(report.exempt_sales.len(), 1);
+        assert_eq!(report.exempt_sales[0].amount, 5000);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn test_customers_wait_for_review_one_certificate_at_a_time(pool: PgPool) {
+        let store_id = test_support::store(&pool).await;
+        let staff_id = test_support::customer(&pool, store_id).await;
+        let customer_id = test_support::customer(&pool, store_id).await;
+        let pending = create_exemption(&pool, &certificate(store_id, customer_id), None)
+            .await
+            .unwrap();
+        assert_eq!(pending.status, TaxExemptionStatus::Pending);
+        let now = OffsetDateTime::now_utc();
+        assert!(effective_exemption(&pool, customer_id, now)
+            .await
+            .unwrap()
+            .is_none());
+
+        let error = create_exemption(&pool, &certificate(store_id, customer_id), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "exemption_pending");
+
+        decide_exemption(
+            &pool,
+            pending.id,
+            TaxExemptionStatus::Approved,
+            None,
+            None,
+            staff_id,
+        )
+        .await
+        .unwrap();
+        assert!(effective_exemption(&pool, customer_id, now)
+            .await
+            .unwrap()
+            .is_some());
+        let error = decide_exemption(
+            &pool,
+            pending.id,
+            TaxExemptionStatus::Rejected,
+            None,
+            None,
+            staff_id,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.code(), "invalid_exemption_transition");
+    }
+}
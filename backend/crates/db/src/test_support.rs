@@ -0,0 +1,245 @@
+//! Fixtures for the database tests.
+//!
+//! Each `#[sqlx::test]` gets a fresh database with every migration applied;
+//! these helpers insert the few rows a test needs, with defaults for the rest.
+
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub async fn store(pool: &PgPool) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO stores (slug, name) VALUES ('test-' || uuid_generate_v7(), 'Test') RETURNING id",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+pub async fn customer(pool: &PgPool, store_id: Uuid) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO users (store_id, email, password_hash)
+        VALUES ($1, uuid_generate_v7() || '@example.com', 'x')
+        RETURNING id
+        "#,
+    )
+    .bind(store_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// An active product with `stock` units on hand
+pub async fn product(pool: &PgPool, store_id: Uuid, price: i64, stock: i32) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO products (store_id, name, slug, price, stock_quantity, status)
+        VALUES ($1, 'Widget', 'widget-' || uuid_generate_v7(), $2, $3, 'active')
+        RETURNING id
+        "#,
+    )
+    .bind(store_id)
+    .bind(price)
+    .bind(stock)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// A cart last used at `last_activity_at`; guest carts get a session ID
+pub async fn cart(
+    pool: &PgPool,
+    store_id: Uuid,
+    user_id: Option<Uuid>,
+    last_activity_at: OffsetDateTime,
+) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO carts (store_id, user_id, session_id, last_activity_at)
+        VALUES ($1, $2, CASE WHEN $2 IS NULL THEN uuid_generate_v7()::TEXT END, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(store_id)
+    .bind(user_id)
+    .bind(last_activity_at)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+pub async fn cart_item(pool: &PgPool, cart_id: Uuid, product_id: Uuid, quantity: i32) {
+    sqlx::query("INSERT INTO cart_items (cart_id, product_id, quantity) VALUES ($1, $2, $3)")
+        .bind(cart_id)
+        .bind(product_id)
+        .bind(quantity)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// An active variant of `product_id`
+pub async fn variant(pool: &PgPool, product_id: Uuid, price: i64, stock: i32) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO product_variants (product_id, name, price, stock_quantity)
+        VALUES ($1, 'Variant', $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(product_id)
+    .bind(price)
+    .bind(stock)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+pub async fn set_sku(pool: &PgPool, table: &str, id: Uuid, sku: &str) {
+    sqlx::query(&format!("UPDATE {table} SET sku = $2 WHERE id = $1"))
+        .bind(id)
+        .bind(sku)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+/// A running sale selling `product_id` at `sale_price`, capped at
+/// `quantity_limit` units; returns the sale item
+pub async fn sale_item(
+    pool: &PgPool,
+    store_id: Uuid,
+    product_id: Uuid,
+    sale_price: i64,
+    quantity_limit: Option<i32>,
+) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        WITH sale AS (
+            INSERT INTO sales (store_id, name, starts_at, ends_at)
+            VALUES ($1, 'Sale', NOW() - INTERVAL '1 hour', NOW() + INTERVAL '1 hour')
+            RETURNING id
+        )
+        INSERT INTO sale_items (sale_id, product_id, sale_price, quantity_limit)
+        SELECT id, $2, $3, $4 FROM sale
+        RETURNING id
+        "#,
+    )
+    .bind(store_id)
+    .bind(product_id)
+    .bind(sale_price)
+    .bind(quantity_limit)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// Tax at `rate_ppm` on standard goods shipped from and to `country`
+pub async fn tax_rate(pool: &PgPool, store_id: Uuid, country: &str, rate_ppm: i32) {
+    sqlx::query(
+        r#"
+        UPDATE stores
+        SET config = config || jsonb_build_object('ship_from', jsonb_build_object('country', $2::TEXT))
+        WHERE id = $1
+        "#,
+    )
+    .bind(store_id)
+    .bind(country)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO tax_rates (store_id, name, country, rate_ppm) VALUES ($1, 'Tax', $2, $3)",
+    )
+    .bind(store_id)
+    .bind(country)
+    .bind(rate_ppm)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// A running promotion for every cart, doing `action` (a `PromotionAction`
+/// as JSON)
+pub async fn promotion(pool: &PgPool, store_id: Uuid, action: serde_json::Value) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO promotions (store_id, name, action) VALUES ($1, 'Promotion', $2) RETURNING id",
+    )
+    .bind(store_id)
+    .bind(action)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// An active gift card worth `amount` in USD; returns its code
+pub async fn gift_card(pool: &PgPool, store_id: Uuid, amount: i64) -> String {
+    let mut conn = pool.acquire().await.unwrap();
+    crate::gift_cards::issue(
+        &mut conn,
+        &crate::gift_cards::NewGiftCard {
+            store_id,
+            amount: goseli_core::Money::new(amount, goseli_core::Currency::USD),
+            code: None,
+            expires_at: None,
+            source: goseli_core::models::GiftCardSource::Admin,
+            order_id: None,
+            order_item_id: None,
+            return_id: None,
+            recipient_email: None,
+            note: None,
+            created_by: None,
+        },
+    )
+    .await
+    .unwrap()
+    .code
+}
+
+/// A payment that captured everything `order_id` left to pay
+pub async fn captured_payment(pool: &PgPool, order_id: Uuid) -> Uuid {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO payments (order_id, provider, status, currency, amount, amount_captured)
+        SELECT id, 'mock', 'captured', currency, total - gift_card_total, total - gift_card_total
+        FROM orders WHERE id = $1
+        RETURNING id
+        "#,
+    )
+    .bind(order_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+/// A paid guest order for `quantity` units of `product_id`
+pub async fn paid_order(
+    pool: &PgPool,
+    store_id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+) -> goseli_core::models::Order {
+    let cart_id = cart(pool, store_id, None, OffsetDateTime::now_utc()).await;
+    cart_item(pool, cart_id, product_id, quantity).await;
+    let order = crate::orders::place_order(
+        pool,
+        cart_id,
+        &crate::orders::NewOrder {
+            email: "ada@example.com".into(),
+            ..crate::orders::NewOrder::default()
+        },
+    )
+    .await
+    .unwrap();
+    crate::orders::transition_order(
+        pool,
+        order.id,
+        goseli_core::models::OrderAction::Pay,
+        goseli_core::models::OrderActor::System,
+        None,
+        None,
+    )
+    .await
+    .unwrap()
+}
@@ -0,0 +1,27 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PaymentError {
+    /// The customer's payment method was refused (card declined, insufficient funds)
+    #[error("Payment declined: {0}")]
+    Declined(String),
+
+    /// The provider rejected the request itself (unknown payment, amount too high)
+    #[error("Invalid payment request: {0}")]
+    InvalidRequest(String),
+
+    /// Network failure or provider-side error; safe to retry with the same idempotency key
+    #[error("Payment provider error: {0}")]
+    Provider(String),
+
+    /// A webhook whose signature is missing, wrong or too old
+    #[error("Invalid webhook signature: {0}")]
+    InvalidSignature(String),
+
+    /// A webhook payload that could not be parsed
+    #[error("Invalid webhook: {0}")]
+    InvalidWebhook(String),
+
+    #[error("Payment provider not configured: {0}")]
+    NotConfigured(String),
+}
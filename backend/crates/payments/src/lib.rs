@@ -0,0 +1,87 @@
+// Goseli Payments - Payment provider trait and implementations
+// Depends on: nothing internal (the API maps provider errors to ApiError)
+
+pub mod error;
+pub mod mock;
+pub mod provider;
+pub mod signature;
+pub mod stripe;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use error::PaymentError;
+pub use mock::MockPaymentProvider;
+pub use provider::{
+    AuthorizeRequest, PaymentProvider, ProviderPayment, ProviderPaymentStatus, ProviderRefund,
+    ProviderRefundStatus, WebhookEvent, WebhookEventKind,
+};
+pub use stripe::StripeProvider;
+
+/// Payment providers configured for this deployment, by name
+#[derive(Clone)]
+pub struct PaymentProviders {
+    default: &'static str,
+    providers: HashMap<&'static str, Arc<dyn PaymentProvider>>,
+}
+
+impl PaymentProviders {
+    /// Registry holding a single provider, which is also the default
+    pub fn single(provider: Arc<dyn PaymentProvider>) -> Self {
+        let default = provider.name();
+        Self {
+            default,
+            providers: HashMap::from([(default, provider)]),
+        }
+    }
+
+    /// Configure providers from the environment.
+    ///
+    /// `PAYMENT_PROVIDER` picks the provider new payments go through and must
+    /// be set. Stripe is registered whenever `STRIPE_SECRET_KEY` is set, so
+    /// webhooks for older Stripe payments keep working after a switch. The
+    /// mock provider takes any card, so it is only registered for development
+    /// and tests, when `PAYMENT_MOCK_ENABLED` is `true`.
+    pub fn from_env() -> Result<Self, PaymentError> {
+        let default = std::env::var("PAYMENT_PROVIDER")
+            .ok()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                PaymentError::NotConfigured("PAYMENT_PROVIDER must be set".to_string())
+            })?;
+
+        let mut providers: HashMap<&'static str, Arc<dyn PaymentProvider>> = HashMap::new();
+        if let Some(stripe) = StripeProvider::from_env() {
+            providers.insert(stripe::PROVIDER_NAME, Arc::new(stripe));
+        }
+        if std::env::var("PAYMENT_MOCK_ENABLED").is_ok_and(|v| v == "true") {
+            providers.insert(
+                mock::PROVIDER_NAME,
+                Arc::new(MockPaymentProvider::from_env()?),
+            );
+        }
+
+        let default = providers
+            .keys()
+            .copied()
+            .find(|name| *name == default)
+            .ok_or_else(|| {
+                PaymentError::NotConfigured(format!(
+                    "PAYMENT_PROVIDER is '{}' but that provider is not configured",
+                    default
+                ))
+            })?;
+
+        Ok(Self { default, providers })
+    }
+
+    /// Provider used for new payments
+    pub fn default_provider(&self) -> Arc<dyn PaymentProvider> {
+        self.providers[self.default].clone()
+    }
+
+    /// Provider by name, as stored on payments and used in webhook URLs
+    pub fn get(&self, name: &str) -> Option<Arc<dyn PaymentProvider>> {
+        self.providers.get(name).cloned()
+    }
+}
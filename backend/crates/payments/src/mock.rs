@@ -0,0 +1,342 @@
+//! In-process payment provider for tests and local development.
+//!
+//! It approves almost any payment, so deployments only register it when
+//! `PAYMENT_MOCK_ENABLED` is `true`.
+//!
+//! Nothing leaves the process and results depend only on the inputs:
+//! payment IDs are derived from the idempotency key, and the payment method
+//! token picks the outcome, using Stripe's test token names:
+//!
+//! - `pm_card_declined`: declined
+//! - `pm_card_authentication_required`: waits for customer action
+//! - no token: waits for the browser to confirm
+//! - anything else: authorized
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use time::{Duration, OffsetDateTime};
+
+use crate::error::PaymentError;
+use crate::provider::{
+    AuthorizeRequest, PaymentProvider, ProviderPayment, ProviderPaymentStatus, ProviderRefund,
+    ProviderRefundStatus, WebhookEvent,
+};
+use crate::signature;
+
+pub const PROVIDER_NAME: &str = "mock";
+
+pub const DECLINED_TOKEN: &str = "pm_card_declined";
+pub const AUTHENTICATION_REQUIRED_TOKEN: &str = "pm_card_authentication_required";
+
+#[derive(Default)]
+struct MockState {
+    payments: HashMap<String, ProviderPayment>,
+    /// Refunds by ID, with the payment they belong to
+    refunds: HashMap<String, (String, ProviderRefund)>,
+    /// Responses by idempotency key, replayed like a real provider would
+    replies: HashMap<String, ProviderPayment>,
+}
+
+pub struct MockPaymentProvider {
+    state: Mutex<MockState>,
+    webhook_secret: String,
+    webhook_tolerance: Duration,
+}
+
+impl MockPaymentProvider {
+    pub fn new(webhook_secret: impl Into<String>) -> Self {
+        Self {
+            state: Mutex::default(),
+            webhook_secret: webhook_secret.into(),
+            webhook_tolerance: signature::DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Configure the webhook secret from `MOCK_WEBHOOK_SECRET`, which must be set
+    pub fn from_env() -> Result<Self, PaymentError> {
+        let webhook_secret = std::env::var("MOCK_WEBHOOK_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| {
+                PaymentError::NotConfigured("MOCK_WEBHOOK_SECRET must be set".to_string())
+            })?;
+
+        Ok(Self {
+            webhook_tolerance: signature::tolerance_from_env(),
+            ..Self::new(webhook_secret)
+        })
+    }
+
+    /// Serialize and sign a webhook event, as the mock "provider" would send it.
+    ///
+    /// Returns the body and the signature header value.
+    pub fn sign_webhook(&self, event: &WebhookEvent, now: OffsetDateTime) -> (Vec<u8>, String) {
+        let payload = serde_json::to_vec(event).expect("webhook events serialize");
+        let header = signature::sign(&self.webhook_secret, now.unix_timestamp(), &payload);
+        (payload, header)
+    }
+
+    /// Simulate the customer completing a payment that requires action
+    pub fn confirm(&self, payment_id: &str) -> Result<ProviderPayment, PaymentError> {
+        let mut state = self.state.lock().expect("mock payment state poisoned");
+        let payment = find_payment(&mut state, payment_id)?;
+        if payment.status != ProviderPaymentStatus::RequiresAction {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Payment {} does not require action",
+                payment_id
+            )));
+        }
+        payment.status = ProviderPaymentStatus::Authorized;
+        Ok(payment.clone())
+    }
+}
+
+fn find_payment<'a>(
+    state: &'a mut MockState,
+    payment_id: &str,
+) -> Result<&'a mut ProviderPayment, PaymentError> {
+    state
+        .payments
+        .get_mut(payment_id)
+        .ok_or_else(|| PaymentError::InvalidRequest(format!("No such payment: {}", payment_id)))
+}
+
+#[async_trait]
+impl PaymentProvider for MockPaymentProvider {
+    fn name(&self) -> &'static str {
+        PROVIDER_NAME
+    }
+
+    async fn authorize(&self, req: &AuthorizeRequest) -> Result<ProviderPayment, PaymentError> {
+        if req.amount <= 0 {
+            return Err(PaymentError::InvalidRequest(
+                "Amount must be positive".to_string(),
+            ));
+        }
+
+        let id = format!("mock_pi_{}", req.idempotency_key);
+        let mut state = self.state.lock().expect("mock payment state poisoned");
+        if let Some(existing) = state.payments.get(&id) {
+            return Ok(existing.clone());
+        }
+
+        let status = match req.payment_method.as_deref() {
+            Some(DECLINED_TOKEN) => {
+                return Err(PaymentError::Declined("Your card was declined".to_string()))
+            }
+            Some(AUTHENTICATION_REQUIRED_TOKEN) | None => ProviderPaymentStatus::RequiresAction,
+            Some(_) => ProviderPaymentStatus::Authorized,
+        };
+
+        let payment = ProviderPayment {
+            client_secret: Some(format!("{}_secret", id)),
+            id: id.clone(),
+            status,
+            amount: req.amount,
+            amount_captured: 0,
+            currency: req.currency.clone(),
+            failure_reason: None,
+        };
+        state.payments.insert(id, payment.clone());
+
+        Ok(payment)
+    }
+
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError> {
+        let mut state = self.state.lock().expect("mock payment state poisoned");
+        if let Some(reply) = state.replies.get(idempotency_key) {
+            return Ok(reply.clone());
+        }
+
+        let payment = find_payment(&mut state, payment_id)?;
+        if payment.status != ProviderPaymentStatus::Authorized {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Payment {} is not authorized",
+                payment_id
+            )));
+        }
+        if amount <= 0 || amount > payment.amount {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Cannot capture {} of an authorization for {}",
+                amount, payment.amount
+            )));
+        }
+        payment.status = ProviderPaymentStatus::Captured;
+        payment.amount_captured = amount;
+
+        let payment = payment.clone();
+        state
+            .replies
+            .insert(idempotency_key.to_string(), payment.clone());
+        Ok(payment)
+    }
+
+    async fn void(
+        &self,
+        payment_id: &str,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError> {
+        let mut state = self.state.lock().expect("mock payment state poisoned");
+        if let Some(reply) = state.replies.get(idempotency_key) {
+            return Ok(reply.clone());
+        }
+
+        let payment = find_payment(&mut state, payment_id)?;
+        if !matches!(
+            payment.status,
+            ProviderPaymentStatus::RequiresAction | ProviderPaymentStatus::Authorized
+        ) {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Payment {} cannot be voided",
+                payment_id
+            )));
+        }
+        payment.status = ProviderPaymentStatus::Voided;
+
+        let payment = payment.clone();
+        state
+            .replies
+            .insert(idempotency_key.to_string(), payment.clone());
+        Ok(payment)
+    }
+
+    async fn refund(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderRefund, PaymentError> {
+        let id = format!("mock_re_{}", idempotency_key);
+        let mut state = self.state.lock().expect("mock payment state poisoned");
+        if let Some((_, existing)) = state.refunds.get(&id) {
+            return Ok(existing.clone());
+        }
+
+        let payment = find_payment(&mut state, payment_id)?;
+        if payment.status != ProviderPaymentStatus::Captured {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Payment {} has not been captured",
+                payment_id
+            )));
+        }
+        let captured = payment.amount_captured;
+        let refunded: i64 = state
+            .refunds
+            .values()
+            .filter(|(refunded_payment, _)| refunded_payment == payment_id)
+            .map(|(_, refund)| refund.amount)
+            .sum();
+        if amount <= 0 || refunded + amount > captured {
+            return Err(PaymentError::InvalidRequest(format!(
+                "Cannot refund {}: {} of {} is left",
+                amount,
+                captured - refunded,
+                captured
+            )));
+        }
+
+        let refund = ProviderRefund {
+            id: id.clone(),
+            status: ProviderRefundStatus::Succeeded,
+            amount,
+        };
+        state
+            .refunds
+            .insert(id, (payment_id.to_string(), refund.clone()));
+        Ok(refund)
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "mock-signature"
+    }
+
+    fn verify_webhook(
+        &self,
+        payload: &[u8],
+        signature: &str,
+        now: OffsetDateTime,
+    ) -> Result<(), PaymentError> {
+        signature::verify(
+            signature,
+            &self.webhook_secret,
+            payload,
+            now,
+            self.webhook_tolerance,
+        )
+    }
+
+    /// Mock webhook bodies are [`WebhookEvent`]s serialized as JSON
+    fn parse_webhook(&self, payload: &[u8]) -> Result<WebhookEvent, PaymentError> {
+        serde_json::from_slice(payload).map_err(|e| PaymentError::InvalidWebhook(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn request(payment_method: Option<&str>, key: &str) -> AuthorizeRequest {
+        AuthorizeRequest {
+            amount: 2500,
+            currency: "USD".to_string(),
+            order_id: Uuid::nil(),
+            payment_method: payment_method.map(str::to_string),
+            idempotency_key: key.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_payment_lifecycle() {
+        let mock = MockPaymentProvider::new("whsec_test");
+
+        let payment = mock
+            .authorize(&request(Some("pm_card_visa"), "a1"))
+            .await
+            .unwrap();
+        assert_eq!(payment.status, ProviderPaymentStatus::Authorized);
+        // Same idempotency key, same payment
+        assert_eq!(
+            mock.authorize(&request(Some("pm_card_visa"), "a1"))
+                .await
+                .unwrap()
+                .id,
+            payment.id
+        );
+
+        assert!(mock.capture(&payment.id, 3000, "c0").await.is_err());
+        let captured = mock.capture(&payment.id, 2500, "c1").await.unwrap();
+        assert_eq!(captured.status, ProviderPaymentStatus::Captured);
+        assert_eq!(captured.amount_captured, 2500);
+        assert!(mock.capture(&payment.id, 2500, "c1").await.is_ok());
+
+        mock.refund(&payment.id, 2000, "r1").await.unwrap();
+        assert!(mock.refund(&payment.id, 1000, "r2").await.is_err());
+        assert!(mock.void(&payment.id, "v1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_outcome_follows_payment_method() {
+        let mock = MockPaymentProvider::new("whsec_test");
+
+        assert!(matches!(
+            mock.authorize(&request(Some(DECLINED_TOKEN), "d1")).await,
+            Err(PaymentError::Declined(_))
+        ));
+
+        let pending = mock.authorize(&request(None, "p1")).await.unwrap();
+        assert_eq!(pending.status, ProviderPaymentStatus::RequiresAction);
+        assert!(pending.client_secret.is_some());
+        assert_eq!(
+            mock.confirm(&pending.id).unwrap().status,
+            ProviderPaymentStatus::Authorized
+        );
+    }
+}
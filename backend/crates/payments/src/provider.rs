@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::error::PaymentError;
+
+/// Authorize an amount for an order.
+///
+/// Funds are only held; nothing is collected until [`PaymentProvider::capture`].
+#[derive(Debug, Clone)]
+pub struct AuthorizeRequest {
+    /// Amount in minor units (cents)
+    pub amount: i64,
+    /// ISO 4217 code, e.g. `USD`
+    pub currency: String,
+    /// Sent to the provider as metadata so its dashboard links back to the order
+    pub order_id: Uuid,
+    /// Provider payment method token (e.g. `pm_card_visa`). Without one the
+    /// payment waits for the browser to confirm it with the client secret.
+    pub payment_method: Option<String>,
+    /// Passed to the provider so a retried request cannot charge twice
+    pub idempotency_key: String,
+}
+
+/// Where a payment stands at the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderPaymentStatus {
+    /// Waiting for the customer (payment method, 3-D Secure)
+    RequiresAction,
+    /// Funds are held and can be captured
+    Authorized,
+    Captured,
+    /// The authorization was released without capturing
+    Voided,
+    Failed,
+}
+
+/// A payment as reported by the provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderPayment {
+    /// Provider's ID (e.g. a Stripe PaymentIntent ID)
+    pub id: String,
+    pub status: ProviderPaymentStatus,
+    pub amount: i64,
+    pub amount_captured: i64,
+    pub currency: String,
+    /// Handed to the browser so it can finish confirming the payment
+    pub client_secret: Option<String>,
+    pub failure_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderRefundStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// A refund as reported by the provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderRefund {
+    pub id: String,
+    pub status: ProviderRefundStatus,
+    pub amount: i64,
+}
+
+/// What a webhook event reports, normalized across providers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    PaymentAuthorized,
+    PaymentCaptured,
+    PaymentFailed,
+    PaymentVoided,
+    PaymentRefunded,
+    /// An event type we do not act on
+    Other,
+}
+
+/// A provider webhook event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Provider's event ID, unique per provider
+    pub id: String,
+    pub kind: WebhookEventKind,
+    /// Provider's own name for the event type (e.g. `payment_intent.succeeded`)
+    pub event_type: String,
+    /// Provider payment the event is about
+    pub payment_id: Option<String>,
+    /// Amount the event reports in minor units: authorized, captured, or for
+    /// refunds the total refunded on the payment so far
+    pub amount: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A payment processor.
+///
+/// Amounts are in minor units. Every call that moves money takes an
+/// idempotency key, which adapters must forward to the provider.
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Name stored on payments and used in webhook URLs (e.g. `stripe`)
+    fn name(&self) -> &'static str;
+
+    /// Create a payment and authorize it if a payment method is given
+    async fn authorize(&self, req: &AuthorizeRequest) -> Result<ProviderPayment, PaymentError>;
+
+    /// Collect `amount` (at most the authorized amount) from an authorized payment
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError>;
+
+    /// Release an uncaptured authorization
+    async fn void(
+        &self,
+        payment_id: &str,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError>;
+
+    /// Return `amount` of a captured payment to the customer
+    async fn refund(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderRefund, PaymentError>;
+
+    /// Header carrying the webhook signature (e.g. `Stripe-Signature`)
+    fn signature_header(&self) -> &'static str;
+
+    /// Check a webhook's signature against the provider's signing secret and
+    /// reject deliveries whose timestamp is too far from `now`
+    fn verify_webhook(
+        &self,
+        payload: &[u8],
+        signature: &str,
+        now: OffsetDateTime,
+    ) -> Result<(), PaymentError>;
+
+    /// Parse a webhook body into a normalized event; only call after verifying it
+    fn parse_webhook(&self, payload: &[u8]) -> Result<WebhookEvent, PaymentError>;
+}
@@ -0,0 +1,134 @@
+//! Webhook signatures in Stripe's scheme, also used by the mock provider.
+//!
+//! The header looks like `t=1760000000,v1=<hex>`, where the signature is an
+//! HMAC-SHA256 of `"{t}.{payload}"` keyed with the endpoint secret. Several
+//! `v1` entries may be present while a secret is being rolled.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use time::{Duration, OffsetDateTime};
+
+use crate::error::PaymentError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How old a signed webhook may be, unless `PAYMENT_WEBHOOK_TOLERANCE_SECS` says otherwise
+pub const DEFAULT_TOLERANCE: Duration = Duration::minutes(5);
+
+/// Tolerance from `PAYMENT_WEBHOOK_TOLERANCE_SECS`
+pub fn tolerance_from_env() -> Duration {
+    std::env::var("PAYMENT_WEBHOOK_TOLERANCE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(Duration::seconds)
+        .unwrap_or(DEFAULT_TOLERANCE)
+}
+
+fn mac(secret: &str, timestamp: i64, payload: &[u8]) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    mac
+}
+
+/// Build the signature header for `payload` sent at `timestamp`
+pub fn sign(secret: &str, timestamp: i64, payload: &[u8]) -> String {
+    let signature = hex::encode(mac(secret, timestamp, payload).finalize().into_bytes());
+    format!("t={},v1={}", timestamp, signature)
+}
+
+/// Check a signature header against `payload`.
+///
+/// Fails if no `v1` signature matches, or if the signed timestamp is more
+/// than `tolerance` away from `now` (a replayed or badly delayed delivery).
+pub fn verify(
+    header: &str,
+    secret: &str,
+    payload: &[u8],
+    now: OffsetDateTime,
+    tolerance: Duration,
+) -> Result<(), PaymentError> {
+    let mut timestamp = None;
+    let mut signatures = vec![];
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+
+    let timestamp =
+        timestamp.ok_or_else(|| PaymentError::InvalidSignature("Missing timestamp".to_string()))?;
+    if signatures.is_empty() {
+        return Err(PaymentError::InvalidSignature(
+            "Missing v1 signature".to_string(),
+        ));
+    }
+
+    // The timestamp comes from the sender, so the distance must not overflow
+    let age = now.unix_timestamp().abs_diff(timestamp);
+    if age > tolerance.whole_seconds().unsigned_abs() {
+        return Err(PaymentError::InvalidSignature(format!(
+            "Timestamp is {}s away from now, outside the {}s tolerance",
+            age,
+            tolerance.whole_seconds()
+        )));
+    }
+
+    let expected = mac(secret, timestamp, payload);
+    let matches = signatures.iter().any(|signature| {
+        hex::decode(signature)
+            .map(|bytes| expected.clone().verify_slice(&bytes).is_ok())
+            .unwrap_or(false)
+    });
+    if !matches {
+        return Err(PaymentError::InvalidSignature(
+            "Signature does not match".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature_and_tolerance() {
+        let payload = br#"{"id":"evt_1"}"#;
+        let now = OffsetDateTime::from_unix_timestamp(1_760_000_000).unwrap();
+        let header = sign("whsec_test", now.unix_timestamp(), payload);
+
+        assert!(verify(&header, "whsec_test", payload, now, DEFAULT_TOLERANCE).is_ok());
+        // Secret rotation: an extra stale signature is ignored
+        let rotated = format!("{},v1=00ff", header);
+        assert!(verify(&rotated, "whsec_test", payload, now, DEFAULT_TOLERANCE).is_ok());
+
+        assert!(verify(&header, "whsec_other", payload, now, DEFAULT_TOLERANCE).is_err());
+        assert!(verify(&header, "whsec_test", b"{}", now, DEFAULT_TOLERANCE).is_err());
+        assert!(verify(
+            &header,
+            "whsec_test",
+            payload,
+            now + Duration::minutes(6),
+            DEFAULT_TOLERANCE
+        )
+        .is_err());
+        assert!(verify("v1=abc", "whsec_test", payload, now, DEFAULT_TOLERANCE).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_extreme_timestamps() {
+        let payload = br#"{"id":"evt_1"}"#;
+        let now = OffsetDateTime::from_unix_timestamp(1_760_000_000).unwrap();
+
+        for timestamp in [i64::MIN, i64::MAX] {
+            let header = sign("whsec_test", timestamp, payload);
+            assert!(verify(&header, "whsec_test", payload, now, DEFAULT_TOLERANCE).is_err());
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! Stripe adapter over the PaymentIntents REST API.
+//!
+//! Payments are created with `capture_method=manual`, so authorization and
+//! capture are separate calls like every other provider. Also works against
+//! Stripe-compatible servers such as `stripe-mock` via `STRIPE_API_BASE`.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+
+use crate::error::PaymentError;
+use crate::provider::{
+    AuthorizeRequest, PaymentProvider, ProviderPayment, ProviderPaymentStatus, ProviderRefund,
+    ProviderRefundStatus, WebhookEvent, WebhookEventKind,
+};
+use crate::signature;
+
+pub const PROVIDER_NAME: &str = "stripe";
+
+const DEFAULT_API_BASE: &str = "https://api.stripe.com";
+
+pub struct StripeProvider {
+    client: reqwest::Client,
+    api_base: String,
+    secret_key: String,
+    /// Endpoint signing secret (`whsec_...`); webhooks are refused without it
+    webhook_secret: Option<String>,
+    webhook_tolerance: Duration,
+}
+
+impl StripeProvider {
+    pub fn new(secret_key: impl Into<String>, api_base: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_base: api_base.into().trim_end_matches('/').to_string(),
+            secret_key: secret_key.into(),
+            webhook_secret: None,
+            webhook_tolerance: signature::DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Accept webhooks signed with `secret`, up to `tolerance` old
+    pub fn with_webhook_secret(mut self, secret: impl Into<String>, tolerance: Duration) -> Self {
+        self.webhook_secret = Some(secret.into());
+        self.webhook_tolerance = tolerance;
+        self
+    }
+
+    /// Configure from `STRIPE_SECRET_KEY`, `STRIPE_API_BASE` and
+    /// `STRIPE_WEBHOOK_SECRET`; None without a key
+    pub fn from_env() -> Option<Self> {
+        let secret_key = std::env::var("STRIPE_SECRET_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())?;
+        let api_base =
+            std::env::var("STRIPE_API_BASE").unwrap_or_else(|_| DEFAULT_API_BASE.to_string());
+        let mut provider = Self::new(secret_key, api_base);
+
+        match std::env::var("STRIPE_WEBHOOK_SECRET") {
+            Ok(secret) if !secret.is_empty() => {
+                provider = provider.with_webhook_secret(secret, signature::tolerance_from_env());
+            }
+            _ => {
+                tracing::warn!("STRIPE_WEBHOOK_SECRET is not set; Stripe webhooks will be refused")
+            }
+        }
+
+        Some(provider)
+    }
+
+    /// POST a form-encoded request and decode the response
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        form: &[(&str, String)],
+        idempotency_key: &str,
+    ) -> Result<T, PaymentError> {
+        let response = self
+            .client
+            .post(format!("{}/v1{}", self.api_base, path))
+            .bearer_auth(&self.secret_key)
+            .header("Idempotency-Key", idempotency_key)
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| PaymentError::Provider(e.to_string()))?;
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| PaymentError::Provider(e.to_string()))?;
+
+        if !status.is_success() {
+            let message = serde_json::from_slice::<StripeErrorBody>(&body)
+                .map(|b| b.error)
+                .unwrap_or_else(|_| StripeError {
+                    error_type: None,
+                    message: Some(format!("HTTP {}", status)),
+                });
+            let text = message.message.unwrap_or_else(|| status.to_string());
+            return Err(match (status.as_u16(), message.error_type.as_deref()) {
+                (_, Some("card_error")) | (402, _) => PaymentError::Declined(text),
+                (400..=499, _) => PaymentError::InvalidRequest(text),
+                _ => PaymentError::Provider(text),
+            });
+        }
+
+        serde_json::from_slice(&body)
+            .map_err(|e| PaymentError::Provider(format!("Unexpected Stripe response: {}", e)))
+    }
+}
+
+#[derive(Deserialize)]
+struct StripeErrorBody {
+    error: StripeError,
+}
+
+#[derive(Deserialize)]
+struct StripeError {
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PaymentIntent {
+    id: String,
+    status: String,
+    amount: i64,
+    #[serde(default)]
+    amount_received: i64,
+    currency: String,
+    client_secret: Option<String>,
+    last_payment_error: Option<StripeError>,
+}
+
+impl From<PaymentIntent> for ProviderPayment {
+    fn from(intent: PaymentIntent) -> Self {
+        let status = match intent.status.as_str() {
+            "requires_capture" => ProviderPaymentStatus::Authorized,
+            "succeeded" => ProviderPaymentStatus::Captured,
+            "canceled" => ProviderPaymentStatus::Voided,
+            // requires_payment_method after an attempt means the attempt failed
+            "requires_payment_method" if intent.last_payment_error.is_some() => {
+                ProviderPaymentStatus::Failed
+            }
+            _ => ProviderPaymentStatus::RequiresAction,
+        };
+
+        ProviderPayment {
+            id: intent.id,
+            status,
+            amount: intent.amount,
+            amount_captured: intent.amount_received,
+            currency: intent.currency.to_uppercase(),
+            client_secret: intent.client_secret,
+            failure_reason: intent.last_payment_error.and_then(|e| e.message),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Refund {
+    id: String,
+    status: String,
+    amount: i64,
+}
+
+impl From<Refund> for ProviderRefund {
+    fn from(refund: Refund) -> Self {
+        let status = match refund.status.as_str() {
+            "succeeded" => ProviderRefundStatus::Succeeded,
+            "failed" | "canceled" => ProviderRefundStatus::Failed,
+            _ => ProviderRefundStatus::Pending,
+        };
+        ProviderRefund {
+            id: refund.id,
+            status,
+            amount: refund.amount,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Event {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    created: i64,
+    data: EventData,
+}
+
+#[derive(Deserialize)]
+struct EventData {
+    object: serde_json::Value,
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        PROVIDER_NAME
+    }
+
+    async fn authorize(&self, req: &AuthorizeRequest) -> Result<ProviderPayment, PaymentError> {
+        let mut form = vec![
+            ("amount", req.amount.to_string()),
+            ("currency", req.currency.to_lowercase()),
+            ("capture_method", "manual".to_string()),
+            ("payment_method_types[]", "card".to_string()),
+            ("metadata[order_id]", req.order_id.to_string()),
+        ];
+        if let Some(payment_method) = &req.payment_method {
+            form.push(("payment_method", payment_method.clone()));
+            form.push(("confirm", "true".to_string()));
+        }
+
+        let intent: PaymentIntent = self
+            .post("/payment_intents", &form, &req.idempotency_key)
+            .await?;
+        Ok(intent.into())
+    }
+
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError> {
+        let intent: PaymentIntent = self
+            .post(
+                &format!("/payment_intents/{}/capture", payment_id),
+                &[("amount_to_capture", amount.to_string())],
+                idempotency_key,
+            )
+            .await?;
+        Ok(intent.into())
+    }
+
+    async fn void(
+        &self,
+        payment_id: &str,
+        idempotency_key: &str,
+    ) -> Result<ProviderPayment, PaymentError> {
+        let intent: PaymentIntent = self
+            .post(
+                &format!("/payment_intents/{}/cancel", payment_id),
+                &[],
+                idempotency_key,
+            )
+            .await?;
+        Ok(intent.into())
+    }
+
+    async fn refund(
+        &self,
+        payment_id: &str,
+        amount: i64,
+        idempotency_key: &str,
+    ) -> Result<ProviderRefund, PaymentError> {
+        let refund: Refund = self
+            .post(
+                "/refunds",
+                &[
+                    ("payment_intent", payment_id.to_string()),
+                    ("amount", amount.to_string()),
+                ],
+                idempotency_key,
+            )
+            .await?;
+        Ok(refund.into())
+    }
+
+    fn signature_header(&self) -> &'static str {
+        "stripe-signature"
+    }
+
+    fn verify_webhook(
+        &self,
+        payload: &[u8],
+        signature: &str,
+        now: OffsetDateTime,
+    ) -> Result<(), PaymentError> {
+        let secret = self.webhook_secret.as_deref().ok_or_else(|| {
+            PaymentError::NotConfigured("STRIPE_WEBHOOK_SECRET is not set".to_string())
+        })?;
+        signature::verify(signature, secret, payload, now, self.webhook_tolerance)
+    }
+
+    fn parse_webhook(&self, payload: &[u8]) -> Result<WebhookEvent, PaymentError> {
+        parse_event(payload)
+    }
+}
+
+/// Map a Stripe event to a normalized one
+fn parse_event(payload: &[u8]) -> Result<WebhookEvent, PaymentError> {
+    let event: Event =
+        serde_json::from_slice(payload).map_err(|e| PaymentError::InvalidWebhook(e.to_string()))?;
+    let object = &event.data.object;
+    // An amount that is there but cannot be read must not be reconciled as another
+    let amount_field = |name: &str| {
+        object
+            .get(name)
+            .map(|v| {
+                v.as_i64()
+                    .ok_or_else(|| PaymentError::InvalidWebhook(format!("Invalid {name}: {v}")))
+            })
+            .transpose()
+    };
+
+    let (kind, amount) = match event.event_type.as_str() {
+        "payment_intent.amount_capturable_updated" => (
+            WebhookEventKind::PaymentAuthorized,
+            amount_field("amount_capturable")?,
+        ),
+        "payment_intent.succeeded" => (
+            WebhookEventKind::PaymentCaptured,
+            amount_field("amount_received")?,
+        ),
+        "payment_intent.payment_failed" => (WebhookEventKind::PaymentFailed, None),
+        "payment_intent.canceled" => (WebhookEventKind::PaymentVoided, None),
+        "charge.refunded" => (
+            WebhookEventKind::PaymentRefunded,
+            amount_field("amount_refunded")?,
+        ),
+        _ => (WebhookEventKind::Other, None),
+    };
+
+    // Charge events point at their PaymentIntent; PaymentIntent events are it
+    let payment_id = match event.event_type.split('.').next() {
+        Some("charge") => object.get("payment_intent"),
+        _ => object.get("id"),
+    }
+    .and_then(|v| v.as_str())
+    .map(str::to_string);
+
+    let created_at = OffsetDateTime::from_unix_timestamp(event.created)
+        .map_err(|e| PaymentError::InvalidWebhook(e.to_string()))?;
+
+    Ok(WebhookEvent {
+        id: event.id,
+        kind,
+        event_type: event.event_type,
+        payment_id,
+        amount,
+        created_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stripe_events() {
+        let event = parse_event(
+            br#"{
+                "id": "evt_1", "type": "payment_intent.succeeded", "created": 1760000000,
+                "data": { "object": { "id": "pi_1", "amount": 1500, "amount_received": 1500 } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(event.kind, WebhookEventKind::PaymentCaptured);
+        assert_eq!(event.payment_id.as_deref(), Some("pi_1"));
+        assert_eq!(event.amount, Some(1500));
+
+        let event = parse_event(
+            br#"{
+                "id": "evt_2", "type": "charge.refunded", "created": 1760000000,
+                "data": { "object": { "id": "ch_1", "payment_intent": "pi_1", "amount_refunded": 500 } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(event.kind, WebhookEventKind::PaymentRefunded);
+        assert_eq!(event.payment_id.as_deref(), Some("pi_1"));
+        assert_eq!(event.amount, Some(500));
+
+        // Amounts that are not whole minor units are rejected, not rounded
+        let event = parse_event(
+            br#"{
+                "id": "evt_3", "type": "payment_intent.succeeded", "created": 1760000000,
+                "data": { "object": { "id": "pi_2", "amount_received": 4294967296.5 } }
+            }"#,
+        );
+        assert!(matches!(event, Err(PaymentError::InvalidWebhook(_))));
+
+        assert!(parse_event(b"not json").is_err());
+    }
+}
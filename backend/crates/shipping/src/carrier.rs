@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::CarrierError;
+
+/// A postal address as carriers see it.
+///
+/// Rate quotes only need the country and postcode; labels need the rest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Address {
+    pub name: Option<String>,
+    pub company: Option<String>,
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
+    pub phone: Option<String>,
+}
+
+/// A box to ship; unknown dimensions are left to the carrier's defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    pub weight_grams: i64,
+    pub length_mm: Option<i32>,
+    pub width_mm: Option<i32>,
+    pub height_mm: Option<i32>,
+}
+
+/// Ask for the services a carrier offers between two addresses
+#[derive(Debug, Clone, Serialize)]
+pub struct RateRequest {
+    pub from: Address,
+    pub to: Address,
+    pub package: Package,
+    /// ISO 4217 code quotes should be in
+    pub currency: String,
+}
+
+/// One service a carrier can ship a package with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CarrierRate {
+    /// Carrier's code for the service (e.g. `ground`), used to buy the label
+    pub service_code: String,
+    pub service_name: String,
+    /// Amount in minor units (cents)
+    pub amount: i64,
+    pub currency: String,
+    pub estimated_days: Option<i32>,
+}
+
+/// File format of a shipping label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelFormat {
+    /// For office printers
+    Pdf,
+    /// Zebra Programming Language, for thermal label printers
+    Zpl,
+}
+
+impl LabelFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            LabelFormat::Pdf => "application/pdf",
+            LabelFormat::Zpl => "application/zpl",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            LabelFormat::Pdf => "pdf",
+            LabelFormat::Zpl => "zpl",
+        }
+    }
+}
+
+/// Buy a label for a package
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelRequest {
+    pub from: Address,
+    pub to: Address,
+    pub package: Package,
+    pub service_code: String,
+    pub format: LabelFormat,
+    /// Printed on the label and shown in the carrier's dashboard (e.g. the order number)
+    pub reference: String,
+    /// Passed to the carrier so a retried request cannot buy two labels
+    #[serde(skip)]
+    pub idempotency_key: String,
+}
+
+/// A purchased label
+#[derive(Debug, Clone)]
+pub struct Label {
+    /// Carrier's ID for the shipment, used to void the label
+    pub shipment_id: String,
+    pub tracking_number: String,
+    /// Carrier's tracking page, if it has one per parcel
+    pub tracking_url: Option<String>,
+    pub service_code: String,
+    /// What the carrier charged, in minor units
+    pub amount: i64,
+    pub currency: String,
+    pub format: LabelFormat,
+    /// The printable file
+    pub data: Vec<u8>,
+}
+
+/// Where a parcel stands according to the carrier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingStatus {
+    /// Label printed but the carrier has not scanned the parcel yet
+    PreTransit,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    /// Delayed, damaged or undeliverable; usually needs attention
+    Exception,
+    ReturnedToSender,
+    Unknown,
+}
+
+/// Latest tracking information for a parcel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingInfo {
+    pub status: TrackingStatus,
+    /// Carrier's description of the latest event
+    pub description: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub delivered_at: Option<OffsetDateTime>,
+}
+
+/// A shipping carrier that quotes, sells labels and tracks parcels.
+///
+/// Amounts are in minor units. Label purchases take an idempotency key,
+/// which adapters must forward to the carrier.
+#[async_trait]
+pub trait ShippingCarrier: Send + Sync {
+    /// Code stored on shipping methods and fulfillments (e.g. `ups`); matches
+    /// a store's carrier of the same code for tracking links
+    fn code(&self) -> &str;
+
+    /// Services available for a package, cheapest first where the carrier says so
+    async fn rates(&self, req: &RateRequest) -> Result<Vec<CarrierRate>, CarrierError>;
+
+    /// Buy a label and download it in the requested format
+    async fn purchase_label(&self, req: &LabelRequest) -> Result<Label, CarrierError>;
+
+    /// Poll the current status of a parcel
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError>;
+
+    /// Cancel an unused label so it is not charged
+    async fn void_label(&self, shipment_id: &str) -> Result<(), CarrierError>;
+}
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CarrierError {
+    /// The carrier rejected the request itself (unknown service, bad address,
+    /// label already used)
+    #[error("Invalid carrier request: {0}")]
+    InvalidRequest(String),
+
+    /// Network failure or carrier-side error; safe to retry with the same idempotency key
+    #[error("Carrier error: {0}")]
+    Provider(String),
+
+    #[error("Carrier not configured: {0}")]
+    NotConfigured(String),
+}
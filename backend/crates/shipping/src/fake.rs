@@ -0,0 +1,353 @@
+//! In-process carrier for tests and local development.
+//!
+//! Nothing leaves the process and results depend only on the inputs:
+//!
+//! - `ground` and `express` are quoted from the package weight, with a
+//!   surcharge when crossing borders; packages over 30 kg get no rates
+//! - labels get sequential `FK` tracking numbers and a small PDF or ZPL file,
+//!   and are billed in USD at the quoted price
+//! - every tracking poll moves a parcel one step further, from pre-transit
+//!   to delivered
+//! - labels can be voided until the parcel has been scanned
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::carrier::{
+    CarrierRate, Label, LabelFormat, LabelRequest, RateRequest, ShippingCarrier, TrackingInfo,
+    TrackingStatus,
+};
+use crate::error::CarrierError;
+
+pub const CARRIER_CODE: &str = "fake";
+
+const MAX_WEIGHT_GRAMS: i64 = 30_000;
+
+/// Steps a parcel goes through, one per tracking poll
+const JOURNEY: [TrackingStatus; 4] = [
+    TrackingStatus::PreTransit,
+    TrackingStatus::InTransit,
+    TrackingStatus::OutForDelivery,
+    TrackingStatus::Delivered,
+];
+
+struct FakeShipment {
+    tracking_number: String,
+    step: usize,
+    voided: bool,
+    delivered_at: Option<OffsetDateTime>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    /// Shipments by ID
+    shipments: HashMap<String, FakeShipment>,
+    /// Labels by idempotency key, replayed like a real carrier would
+    labels: HashMap<String, Label>,
+    issued: u64,
+}
+
+#[derive(Default)]
+pub struct FakeCarrier {
+    state: Mutex<FakeState>,
+}
+
+impl FakeCarrier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Ground price: a base fee plus a fee per started kilogram
+fn ground_rate(weight_grams: i64, international: bool) -> i64 {
+    let kilos = (weight_grams.max(1) + 999) / 1000;
+    let base = 450 + 75 * kilos;
+    if international {
+        base + 1500
+    } else {
+        base
+    }
+}
+
+/// A one-page PDF with a line of text per entry
+fn pdf_label(lines: &[String]) -> Vec<u8> {
+    let mut text = String::from("BT /F1 14 Tf 36 360 Td 18 TL");
+    for line in lines {
+        let escaped = line
+            .replace('\\', "\\\\")
+            .replace('(', "\\(")
+            .replace(')', "\\)");
+        text.push_str(&format!(" ({}) Tj T*", escaped));
+    }
+    text.push_str(" ET");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        // 4 x 6 inch label
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 288 432] /Contents 4 0 R \
+         /Resources << /Font << /F1 5 0 R >> >> >>"
+            .to_string(),
+        format!("<< /Length {} >>\nstream\n{}\nendstream", text.len(), text),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+/// A ZPL label with the lines and a Code 128 barcode of the tracking number
+fn zpl_label(lines: &[String], tracking_number: &str) -> Vec<u8> {
+    let mut zpl = String::from("^XA\n");
+    for (i, line) in lines.iter().enumerate() {
+        zpl.push_str(&format!("^FO40,{}^A0N,30,30^FD{}^FS\n", 40 + i * 40, line));
+    }
+    zpl.push_str(&format!(
+        "^FO40,{}^BY3^BCN,120,Y,N,N^FD{}^FS\n^XZ\n",
+        60 + lines.len() * 40,
+        tracking_number
+    ));
+    zpl.into_bytes()
+}
+
+#[async_trait]
+impl ShippingCarrier for FakeCarrier {
+    fn code(&self) -> &str {
+        CARRIER_CODE
+    }
+
+    async fn rates(&self, req: &RateRequest) -> Result<Vec<CarrierRate>, CarrierError> {
+        if req.package.weight_grams > MAX_WEIGHT_GRAMS {
+            return Ok(vec![]);
+        }
+        let international = !req.from.country.eq_ignore_ascii_case(&req.to.country);
+        let ground = ground_rate(req.package.weight_grams, international);
+        let transit = if international { 2 } else { 0 };
+
+        Ok(vec![
+            CarrierRate {
+                service_code: "ground".to_string(),
+                service_name: "Fake Ground".to_string(),
+                amount: ground,
+                currency: req.currency.clone(),
+                estimated_days: Some(3 + transit),
+            },
+            CarrierRate {
+                service_code: "express".to_string(),
+                service_name: "Fake Express".to_string(),
+                amount: ground * 2,
+                currency: req.currency.clone(),
+                estimated_days: Some(1 + transit),
+            },
+        ])
+    }
+
+    async fn purchase_label(&self, req: &LabelRequest) -> Result<Label, CarrierError> {
+        let mut state = self.state.lock().expect("fake carrier state poisoned");
+        if let Some(label) = state.labels.get(&req.idempotency_key) {
+            return Ok(label.clone());
+        }
+
+        let international = !req.from.country.eq_ignore_ascii_case(&req.to.country);
+        let ground = ground_rate(req.package.weight_grams, international);
+        let amount = match req.service_code.as_str() {
+            "ground" => ground,
+            "express" => ground * 2,
+            other => {
+                return Err(CarrierError::InvalidRequest(format!(
+                    "Unknown service '{}'",
+                    other
+                )))
+            }
+        };
+        if req.package.weight_grams > MAX_WEIGHT_GRAMS {
+            return Err(CarrierError::InvalidRequest(
+                "Package is too heavy".to_string(),
+            ));
+        }
+
+        state.issued += 1;
+        let tracking_number = format!("FK{:010}", state.issued);
+        let shipment_id = format!("fake_shp_{}", req.idempotency_key);
+
+        let to = &req.to;
+        let lines = vec![
+            format!("FAKE {}", req.service_code.to_uppercase()),
+            to.name.clone().unwrap_or_default(),
+            to.line1.clone().unwrap_or_default(),
+            format!(
+                "{} {}",
+                to.postal_code.as_deref().unwrap_or_default(),
+                to.city.as_deref().unwrap_or_default()
+            ),
+            to.country.clone(),
+            format!("Ref {}", req.reference),
+        ];
+        let data = match req.format {
+            LabelFormat::Pdf => pdf_label(&[lines, vec![tracking_number.clone()]].concat()),
+            LabelFormat::Zpl => zpl_label(&lines, &tracking_number),
+        };
+
+        let label = Label {
+            shipment_id: shipment_id.clone(),
+            tracking_number: tracking_number.clone(),
+            tracking_url: None,
+            service_code: req.service_code.clone(),
+            amount,
+            currency: "USD".to_string(),
+            format: req.format,
+            data,
+        };
+        state.shipments.insert(
+            shipment_id,
+            FakeShipment {
+                tracking_number,
+                step: 0,
+                voided: false,
+                delivered_at: None,
+            },
+        );
+        state
+            .labels
+            .insert(req.idempotency_key.clone(), label.clone());
+
+        Ok(label)
+    }
+
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+        let mut state = self.state.lock().expect("fake carrier state poisoned");
+        let shipment = state
+            .shipments
+            .values_mut()
+            .find(|shipment| shipment.tracking_number == tracking_number && !shipment.voided)
+            .ok_or_else(|| {
+                CarrierError::InvalidRequest(format!("Unknown tracking number {}", tracking_number))
+            })?;
+
+        shipment.step = (shipment.step + 1).min(JOURNEY.len() - 1);
+        let status = JOURNEY[shipment.step];
+        if status == TrackingStatus::Delivered && shipment.delivered_at.is_none() {
+            shipment.delivered_at = Some(OffsetDateTime::now_utc());
+        }
+
+        Ok(TrackingInfo {
+            status,
+            description: Some(format!("Parcel is {:?}", status)),
+            delivered_at: shipment.delivered_at,
+        })
+    }
+
+    async fn void_label(&self, shipment_id: &str) -> Result<(), CarrierError> {
+        let mut state = self.state.lock().expect("fake carrier state poisoned");
+        let shipment = state.shipments.get_mut(shipment_id).ok_or_else(|| {
+            CarrierError::InvalidRequest(format!("No such shipment: {}", shipment_id))
+        })?;
+        if shipment.step > 0 {
+            return Err(CarrierError::InvalidRequest(
+                "The parcel has already been scanned".to_string(),
+            ));
+        }
+        shipment.voided = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::carrier::{Address, Package};
+
+    fn address(country: &str) -> Address {
+        Address {
+            name: Some("Ada Lovelace".into()),
+            line1: Some("1 Main St".into()),
+            city: Some("Springfield".into()),
+            postal_code: Some("12345".into()),
+            country: country.into(),
+            ..Address::default()
+        }
+    }
+
+    fn label_request(key: &str, format: LabelFormat) -> LabelRequest {
+        LabelRequest {
+            from: address("US"),
+            to: address("US"),
+            package: Package {
+                weight_grams: 1200,
+                ..Package::default()
+            },
+            service_code: "ground".into(),
+            format,
+            reference: "1001".into(),
+            idempotency_key: key.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fake_carrier_lifecycle() {
+        let fake = FakeCarrier::new();
+
+        let rates = fake
+            .rates(&RateRequest {
+                from: address("US"),
+                to: address("CA"),
+                package: Package {
+                    weight_grams: 1200,
+                    ..Package::default()
+                },
+                currency: "USD".into(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(rates[0].amount, 450 + 150 + 1500);
+        assert_eq!(rates[1].amount, rates[0].amount * 2);
+
+        let label = fake
+            .purchase_label(&label_request("l1", LabelFormat::Pdf))
+            .await
+            .unwrap();
+        assert!(label.data.starts_with(b"%PDF-1.4"));
+        // Same idempotency key, same label
+        let replay = fake
+            .purchase_label(&label_request("l1", LabelFormat::Pdf))
+            .await
+            .unwrap();
+        assert_eq!(replay.tracking_number, label.tracking_number);
+
+        let zpl = fake
+            .purchase_label(&label_request("l2", LabelFormat::Zpl))
+            .await
+            .unwrap();
+        assert!(zpl.data.starts_with(b"^XA"));
+        fake.void_label(&zpl.shipment_id).await.unwrap();
+        assert!(fake.track(&zpl.tracking_number).await.is_err());
+
+        let mut status = TrackingStatus::PreTransit;
+        for _ in 0..3 {
+            status = fake.track(&label.tracking_number).await.unwrap().status;
+        }
+        assert_eq!(status, TrackingStatus::Delivered);
+        assert!(fake.void_label(&label.shipment_id).await.is_err());
+    }
+}
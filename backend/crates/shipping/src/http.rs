@@ -0,0 +1,210 @@
+//! Adapter for carriers and shipping aggregators behind a simple JSON API.
+//!
+//! The API is expected to offer, under its base URL:
+//!
+//! - `POST /rates` with a [`RateRequest`], answering `{"rates": [CarrierRate]}`
+//! - `POST /labels` with a [`LabelRequest`] and an `Idempotency-Key` header,
+//!   answering the shipment with a `label_url` to download the file from
+//! - `GET /tracking/{tracking_number}`, answering a [`TrackingInfo`]
+//! - `POST /shipments/{shipment_id}/void`
+//!
+//! Requests are authenticated with a bearer token. Error responses may carry
+//! `{"message": "..."}`; 4xx answers are reported as invalid requests.
+
+use async_trait::async_trait;
+use reqwest::{Method, RequestBuilder, Url};
+use serde::Deserialize;
+
+use crate::carrier::{
+    CarrierRate, Label, LabelRequest, RateRequest, ShippingCarrier, TrackingInfo,
+};
+use crate::error::CarrierError;
+
+pub struct HttpCarrier {
+    client: reqwest::Client,
+    code: String,
+    api_base: Url,
+    api_key: String,
+}
+
+impl HttpCarrier {
+    pub fn new(
+        code: impl Into<String>,
+        api_base: &str,
+        api_key: impl Into<String>,
+    ) -> Result<Self, CarrierError> {
+        // A trailing slash makes relative paths join below the base, not beside it
+        let api_base = Url::parse(&format!("{}/", api_base.trim_end_matches('/')))
+            .map_err(|e| CarrierError::NotConfigured(format!("Invalid API base: {}", e)))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            code: code.into(),
+            api_base,
+            api_key: api_key.into(),
+        })
+    }
+
+    /// Configure from `CARRIER_HTTP_API_BASE`, `CARRIER_HTTP_API_KEY` and
+    /// `CARRIER_HTTP_CODE` (`http` by default); None without a base URL
+    pub fn from_env() -> Result<Option<Self>, CarrierError> {
+        let Some(api_base) = std::env::var("CARRIER_HTTP_API_BASE")
+            .ok()
+            .filter(|base| !base.is_empty())
+        else {
+            return Ok(None);
+        };
+        let code = std::env::var("CARRIER_HTTP_CODE")
+            .ok()
+            .filter(|code| !code.is_empty())
+            .unwrap_or_else(|| "http".to_string());
+        let api_key = std::env::var("CARRIER_HTTP_API_KEY").unwrap_or_default();
+
+        Self::new(code.to_lowercase(), &api_base, api_key).map(Some)
+    }
+
+    /// URL of `segments` below the API base, each segment percent-encoded
+    fn url(&self, segments: &[&str]) -> Result<Url, CarrierError> {
+        let mut url = self.api_base.clone();
+        url.path_segments_mut()
+            .map_err(|_| CarrierError::NotConfigured("API base cannot have paths".to_string()))?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        self.client.request(method, url).bearer_auth(&self.api_key)
+    }
+
+    /// Send a request and return the body of a successful response
+    async fn send(&self, request: RequestBuilder) -> Result<Vec<u8>, CarrierError> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| CarrierError::Provider(e.to_string()))?;
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| CarrierError::Provider(e.to_string()))?;
+
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ErrorBody>(&body)
+                .ok()
+                .and_then(|b| b.message)
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return Err(if status.is_client_error() {
+                CarrierError::InvalidRequest(message)
+            } else {
+                CarrierError::Provider(message)
+            });
+        }
+
+        Ok(body.to_vec())
+    }
+
+    async fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, CarrierError> {
+        let body = self.send(request).await?;
+        serde_json::from_slice(&body)
+            .map_err(|e| CarrierError::Provider(format!("Unexpected carrier response: {}", e)))
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    rates: Vec<CarrierRate>,
+}
+
+#[derive(Deserialize)]
+struct ShipmentResponse {
+    shipment_id: String,
+    tracking_number: String,
+    tracking_url: Option<String>,
+    service_code: String,
+    amount: i64,
+    currency: String,
+    /// Absolute, or relative to the API base
+    label_url: String,
+}
+
+#[async_trait]
+impl ShippingCarrier for HttpCarrier {
+    fn code(&self) -> &str {
+        &self.code
+    }
+
+    async fn rates(&self, req: &RateRequest) -> Result<Vec<CarrierRate>, CarrierError> {
+        let url = self.url(&["rates"])?;
+        let response: RatesResponse = self
+            .send_json(self.request(Method::POST, url).json(req))
+            .await?;
+        Ok(response.rates)
+    }
+
+    async fn purchase_label(&self, req: &LabelRequest) -> Result<Label, CarrierError> {
+        let url = self.url(&["labels"])?;
+        let shipment: ShipmentResponse = self
+            .send_json(
+                self.request(Method::POST, url)
+                    .header("Idempotency-Key", &req.idempotency_key)
+                    .json(req),
+            )
+            .await?;
+
+        let label_url = self.api_base.join(&shipment.label_url).map_err(|e| {
+            CarrierError::Provider(format!("Invalid label URL from carrier: {}", e))
+        })?;
+        let data = self.send(self.request(Method::GET, label_url)).await?;
+
+        Ok(Label {
+            shipment_id: shipment.shipment_id,
+            tracking_number: shipment.tracking_number,
+            tracking_url: shipment.tracking_url,
+            service_code: shipment.service_code,
+            amount: shipment.amount,
+            currency: shipment.currency,
+            format: req.format,
+            data,
+        })
+    }
+
+    async fn track(&self, tracking_number: &str) -> Result<TrackingInfo, CarrierError> {
+        let url = self.url(&["tracking", tracking_number])?;
+        self.send_json(self.request(Method::GET, url)).await
+    }
+
+    async fn void_label(&self, shipment_id: &str) -> Result<(), CarrierError> {
+        let url = self.url(&["shipments", shipment_id, "void"])?;
+        self.send(self.request(Method::POST, url)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_carrier_urls() {
+        let carrier = HttpCarrier::new("acme", "https://api.example.com/v2/", "key").unwrap();
+        assert_eq!(
+            carrier.url(&["tracking", "1Z 99/A"]).unwrap().as_str(),
+            "https://api.example.com/v2/tracking/1Z%2099%2FA"
+        );
+        assert_eq!(
+            carrier.api_base.join("labels/abc.pdf").unwrap().as_str(),
+            "https://api.example.com/v2/labels/abc.pdf"
+        );
+        assert!(HttpCarrier::new("acme", "not a url", "key").is_err());
+    }
+}
@@ -0,0 +1,63 @@
+// Goseli Shipping - Carrier adapter trait and implementations
+// Depends on: nothing internal (the API maps carrier errors to ApiError)
+
+pub mod carrier;
+pub mod error;
+pub mod fake;
+pub mod http;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use carrier::{
+    Address, CarrierRate, Label, LabelFormat, LabelRequest, Package, RateRequest, ShippingCarrier,
+    TrackingInfo, TrackingStatus,
+};
+pub use error::CarrierError;
+pub use fake::FakeCarrier;
+pub use http::HttpCarrier;
+
+/// Carrier adapters configured for this deployment, by code
+#[derive(Clone, Default)]
+pub struct ShippingCarriers {
+    carriers: HashMap<String, Arc<dyn ShippingCarrier>>,
+}
+
+impl ShippingCarriers {
+    pub fn new(carriers: Vec<Arc<dyn ShippingCarrier>>) -> Self {
+        Self {
+            carriers: carriers
+                .into_iter()
+                .map(|carrier| (carrier.code().to_string(), carrier))
+                .collect(),
+        }
+    }
+
+    /// Configure adapters from the environment.
+    ///
+    /// The HTTP adapter is registered when `CARRIER_HTTP_API_BASE` is set, and
+    /// the fake carrier when `CARRIER_FAKE_ENABLED` is `true`. Without either,
+    /// only table rates are available.
+    pub fn from_env() -> Result<Self, CarrierError> {
+        let mut carriers: Vec<Arc<dyn ShippingCarrier>> = Vec::new();
+        if let Some(http) = HttpCarrier::from_env()? {
+            carriers.push(Arc::new(http));
+        }
+        if std::env::var("CARRIER_FAKE_ENABLED").is_ok_and(|v| v == "true") {
+            carriers.push(Arc::new(FakeCarrier::new()));
+        }
+
+        let registry = Self::new(carriers.clone());
+        if registry.carriers.len() < carriers.len() {
+            return Err(CarrierError::NotConfigured(
+                "Two carriers share the same code".to_string(),
+            ));
+        }
+        Ok(registry)
+    }
+
+    /// Adapter by code, as stored on shipping methods and labels
+    pub fn get(&self, code: &str) -> Option<Arc<dyn ShippingCarrier>> {
+        self.carriers.get(code).cloned()
+    }
+}
@@ -0,0 +1,27 @@
+use goseli_core::ApiError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("File not found: {0}")]
+    NotFound(String),
+
+    /// Empty, absolute, or escaping the storage root (`..`)
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::NotFound(_) => ApiError::not_found("File not found"),
+            StorageError::InvalidKey(_) | StorageError::Io(_) => {
+                tracing::error!("{e}");
+                ApiError::internal(e.to_string())
+            }
+        }
+    }
+}
@@ -1,4 +1,60 @@
 // Goseli Storage - File storage backend trait and implementations
 // Depends on: goseli-core
 
-// Stub - modules will be implemented in subsequent tasks
+pub mod error;
+pub mod local;
+
+use async_trait::async_trait;
+
+pub use error::StorageError;
+pub use local::LocalStorage;
+
+/// Where uploaded and generated files (return photos, tax exemption
+/// certificates, shipping labels) live.
+///
+/// Keys are relative, `/`-separated paths such as `returns/<id>/<file>.jpg`.
+/// Content types are kept by the caller alongside the key.
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Store `data` under `key`, replacing any existing file
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError>;
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Remove a file; deleting a missing file is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Content type of an uploaded image, sniffed from its first bytes.
+///
+/// Only formats browsers display everywhere are recognized; the type the
+/// client claims is never trusted.
+pub fn image_content_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Content type of an uploaded document: a PDF or one of the images
+/// [`image_content_type`] recognizes
+pub fn document_content_type(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        _ => image_content_type(data),
+    }
+}
+
+/// File extension for a content type returned by [`image_content_type`] or
+/// [`document_content_type`]
+pub fn extension_for(content_type: &str) -> &'static str {
+    match content_type {
+        "application/pdf" => "pdf",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/webp" => "webp",
+        _ => "bin",
+    }
+}
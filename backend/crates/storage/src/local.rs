@@ -0,0 +1,98 @@
+//! Files on the local disk, under `UPLOAD_DIR`.
+
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{error::StorageError, StorageBackend};
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Store under `UPLOAD_DIR` (`./uploads` by default)
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("UPLOAD_DIR").unwrap_or_else(|_| "./uploads".to_string()))
+    }
+
+    /// Path of a key, refusing keys that would leave the root
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        let relative = Path::new(key);
+        let valid = !key.is_empty()
+            && relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !valid {
+            return Err(StorageError::InvalidKey(key.to_string()));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write then rename, so readers never see a half-written file
+        let tmp = path.with_extension(format!("{}.tmp", Uuid::now_v7()));
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let path = self.path(key)?;
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StorageError::NotFound(key.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_storage_roundtrip() {
+        let root = std::env::temp_dir().join(format!("goseli-storage-{}", Uuid::now_v7()));
+        let storage = LocalStorage::new(&root);
+
+        storage.put("returns/a/photo.jpg", b"jpeg").await.unwrap();
+        assert_eq!(storage.get("returns/a/photo.jpg").await.unwrap(), b"jpeg");
+        storage.delete("returns/a/photo.jpg").await.unwrap();
+        assert!(matches!(
+            storage.get("returns/a/photo.jpg").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        assert!(matches!(
+            storage.put("../escape", b"x").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        assert!(storage.put("/etc/passwd", b"x").await.is_err());
+
+        std::fs::remove_dir_all(root).ok();
+    }
+}
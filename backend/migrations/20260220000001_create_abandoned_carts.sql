@@ -0,0 +1,60 @@
+-- Track customer activity and contact details on carts
+ALTER TABLE carts
+    ADD COLUMN email            VARCHAR(255),
+    ADD COLUMN last_activity_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_carts_last_activity ON carts (store_id, last_activity_at);
+
+CREATE TABLE abandoned_carts (
+    id                UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id          UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    cart_id           UUID         REFERENCES carts(id) ON DELETE SET NULL,
+    user_id           UUID         REFERENCES users(id) ON DELETE SET NULL,
+    email             VARCHAR(255) NOT NULL,
+    cart_total        INTEGER      NOT NULL DEFAULT 0,
+    item_count        INTEGER      NOT NULL DEFAULT 0,
+    status            VARCHAR(20)  NOT NULL DEFAULT 'abandoned'
+                      CHECK (status IN ('abandoned', 'recovered', 'expired')),
+    messages_sent     INTEGER      NOT NULL DEFAULT 0,
+    last_message_at   TIMESTAMPTZ,
+    abandoned_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    recovered_at      TIMESTAMPTZ,
+    -- Cart the customer restored into (may differ from cart_id on another device)
+    recovered_cart_id UUID         REFERENCES carts(id) ON DELETE SET NULL,
+    recovered_revenue INTEGER,
+    created_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+-- A cart can only have one open abandonment at a time
+CREATE UNIQUE INDEX idx_abandoned_carts_open ON abandoned_carts (cart_id)
+    WHERE status = 'abandoned';
+
+CREATE INDEX idx_abandoned_carts_store_status ON abandoned_carts (store_id, status);
+CREATE INDEX idx_abandoned_carts_recovered_cart ON abandoned_carts (recovered_cart_id)
+    WHERE recovered_cart_id IS NOT NULL;
+
+CREATE TRIGGER set_abandoned_carts_updated_at
+    BEFORE UPDATE ON abandoned_carts
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Outgoing customer messages, delivered by the notification worker
+CREATE TABLE notifications (
+    id          UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id    UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    recipient   VARCHAR(255) NOT NULL,
+    template    VARCHAR(100) NOT NULL,
+    payload     JSONB        NOT NULL DEFAULT '{}',
+    status      VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                CHECK (status IN ('pending', 'sent', 'failed')),
+    attempts    INTEGER      NOT NULL DEFAULT 0,
+    last_error  TEXT,
+    send_after  TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    sent_at     TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notifications_pending ON notifications (send_after)
+    WHERE status = 'pending';
+CREATE INDEX idx_notifications_store ON notifications (store_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- Support batched retention purges that scan across all stores
+CREATE INDEX idx_carts_guest_activity ON carts (last_activity_at)
+    WHERE user_id IS NULL;
+
+CREATE INDEX idx_refresh_tokens_revoked ON refresh_tokens (revoked_at)
+    WHERE revoked_at IS NOT NULL;
@@ -0,0 +1,29 @@
+-- Per-line customization (engraving text, gift messages, zone layouts, ...)
+ALTER TABLE cart_items
+    ADD COLUMN properties      JSONB       NOT NULL DEFAULT '{}',
+    -- SHA-256 of the canonical properties JSON; empty when there are none
+    ADD COLUMN properties_hash VARCHAR(64) NOT NULL DEFAULT '',
+    -- Amount added to the unit price by the chosen customizations
+    ADD COLUMN price_modifier  INTEGER     NOT NULL DEFAULT 0;
+
+-- NULL variant_ids never conflicted under the old constraint, so a cart could
+-- hold duplicate lines for simple products. Fold them into the oldest line.
+UPDATE cart_items ci SET quantity = d.total
+FROM (
+    SELECT (array_agg(id ORDER BY created_at, id))[1] AS keep_id, SUM(quantity) AS total
+    FROM cart_items
+    GROUP BY cart_id, product_id, variant_id
+    HAVING COUNT(*) > 1
+) d
+WHERE ci.id = d.keep_id;
+
+DELETE FROM cart_items ci USING cart_items other
+WHERE ci.cart_id = other.cart_id
+  AND ci.product_id = other.product_id
+  AND ci.variant_id IS NOT DISTINCT FROM other.variant_id
+  AND (other.created_at, other.id) < (ci.created_at, ci.id);
+
+-- Differently customized copies of a product+variant are separate lines
+ALTER TABLE cart_items DROP CONSTRAINT cart_items_cart_id_product_id_variant_id_key;
+ALTER TABLE cart_items ADD CONSTRAINT cart_items_line_key
+    UNIQUE NULLS NOT DISTINCT (cart_id, product_id, variant_id, properties_hash);
@@ -0,0 +1,12 @@
+-- Purchase quantity rules; NULL means no limit. Variant values override the product's.
+ALTER TABLE products
+    ADD COLUMN min_quantity     INTEGER CHECK (min_quantity IS NULL OR min_quantity > 0),
+    ADD COLUMN max_quantity     INTEGER CHECK (max_quantity IS NULL OR max_quantity > 0),
+    ADD COLUMN quantity_step    INTEGER CHECK (quantity_step IS NULL OR quantity_step > 0),
+    ADD COLUMN max_per_customer INTEGER CHECK (max_per_customer IS NULL OR max_per_customer > 0);
+
+ALTER TABLE product_variants
+    ADD COLUMN min_quantity     INTEGER CHECK (min_quantity IS NULL OR min_quantity > 0),
+    ADD COLUMN max_quantity     INTEGER CHECK (max_quantity IS NULL OR max_quantity > 0),
+    ADD COLUMN quantity_step    INTEGER CHECK (quantity_step IS NULL OR quantity_step > 0),
+    ADD COLUMN max_per_customer INTEGER CHECK (max_per_customer IS NULL OR max_per_customer > 0);
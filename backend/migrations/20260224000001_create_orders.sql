@@ -0,0 +1,56 @@
+CREATE TABLE orders (
+    id               UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id         UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    user_id          UUID         REFERENCES users(id) ON DELETE SET NULL,
+    -- Cart the order was placed from; carts are short-lived, so no foreign key
+    cart_id          UUID,
+    -- Human-facing order number, shown in emails and the admin
+    order_number     BIGINT       GENERATED BY DEFAULT AS IDENTITY (START WITH 1001) UNIQUE,
+    status           VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                     CHECK (status IN ('pending', 'paid', 'fulfilled', 'completed', 'cancelled', 'refunded')),
+    payment_status   VARCHAR(20)  NOT NULL DEFAULT 'unpaid'
+                     CHECK (payment_status IN ('unpaid', 'paid')),
+    email            VARCHAR(255) NOT NULL,
+    -- Snapshot of stores.currency at the time of purchase
+    currency         VARCHAR(3)   NOT NULL,
+    subtotal         INTEGER      NOT NULL CHECK (subtotal >= 0),
+    total            INTEGER      NOT NULL CHECK (total >= 0),
+    item_count       INTEGER      NOT NULL CHECK (item_count > 0),
+    shipping_address JSONB,
+    billing_address  JSONB,
+    notes            TEXT,
+    created_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_orders_store_created ON orders (store_id, created_at DESC);
+CREATE INDEX idx_orders_store_status ON orders (store_id, status);
+CREATE INDEX idx_orders_user ON orders (user_id, created_at DESC)
+    WHERE user_id IS NOT NULL;
+CREATE INDEX idx_orders_email ON orders (store_id, lower(email));
+
+CREATE TRIGGER set_orders_updated_at
+    BEFORE UPDATE ON orders
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Line items are immutable snapshots: catalog edits never change past orders
+CREATE TABLE order_items (
+    id             UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    order_id       UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    product_id     UUID         REFERENCES products(id) ON DELETE SET NULL,
+    variant_id     UUID         REFERENCES product_variants(id) ON DELETE SET NULL,
+    product_name   VARCHAR(255) NOT NULL,
+    variant_name   VARCHAR(255),
+    sku            VARCHAR(100),
+    properties     JSONB        NOT NULL DEFAULT '{}',
+    -- Unit price including customization modifiers
+    unit_price     INTEGER      NOT NULL CHECK (unit_price >= 0),
+    price_modifier INTEGER      NOT NULL DEFAULT 0,
+    quantity       INTEGER      NOT NULL CHECK (quantity > 0),
+    total          INTEGER      NOT NULL CHECK (total >= 0),
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_order_items_order ON order_items (order_id);
+CREATE INDEX idx_order_items_product ON order_items (product_id, variant_id);
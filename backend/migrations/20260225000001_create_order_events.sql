@@ -0,0 +1,18 @@
+-- Status history for orders; every lifecycle transition appends one row
+CREATE TABLE order_events (
+    id          UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    order_id    UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    from_status VARCHAR(20),
+    to_status   VARCHAR(20)  NOT NULL,
+    action      VARCHAR(20)  NOT NULL,
+    actor       VARCHAR(20)  NOT NULL CHECK (actor IN ('customer', 'admin', 'system')),
+    actor_id    UUID         REFERENCES users(id) ON DELETE SET NULL,
+    reason      TEXT,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_order_events_order ON order_events (order_id, created_at);
+
+-- Orders placed before the history existed get a synthetic placement event
+INSERT INTO order_events (order_id, from_status, to_status, action, actor, actor_id, created_at)
+SELECT id, NULL, 'pending', 'place', 'customer', user_id, created_at FROM orders;
@@ -0,0 +1,33 @@
+CREATE TABLE checkout_sessions (
+    id               UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id         UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    cart_id          UUID         NOT NULL REFERENCES carts(id) ON DELETE CASCADE,
+    user_id          UUID         REFERENCES users(id) ON DELETE SET NULL,
+    status           VARCHAR(20)  NOT NULL DEFAULT 'open'
+                     CHECK (status IN ('open', 'completed')),
+    completed_steps  TEXT[]       NOT NULL DEFAULT '{}',
+    email            VARCHAR(255),
+    shipping_address JSONB,
+    billing_address  JSONB,
+    shipping_method  VARCHAR(64),
+    payment_method   VARCHAR(64),
+    custom_fields    JSONB        NOT NULL DEFAULT '{}',
+    order_id         UUID         REFERENCES orders(id) ON DELETE SET NULL,
+    expires_at       TIMESTAMPTZ  NOT NULL,
+    created_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+-- One open checkout per cart
+CREATE UNIQUE INDEX idx_checkout_sessions_open ON checkout_sessions (cart_id)
+    WHERE status = 'open';
+
+CREATE TRIGGER set_checkout_sessions_updated_at
+    BEFORE UPDATE ON checkout_sessions
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Checkout details carried onto the order
+ALTER TABLE orders
+    ADD COLUMN shipping_method VARCHAR(64),
+    ADD COLUMN custom_fields   JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,27 @@
+-- Responses to mutating requests sent with an Idempotency-Key header,
+-- replayed when a client retries the same request
+CREATE TABLE idempotency_keys (
+    id               UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    -- Who sent the key: "user:<id>", "session:<id>" or "anonymous"
+    scope            VARCHAR(255) NOT NULL,
+    key              VARCHAR(255) NOT NULL,
+    -- SHA-256 of method, path and body
+    fingerprint      VARCHAR(64)  NOT NULL,
+    status           VARCHAR(20)  NOT NULL DEFAULT 'in_progress'
+                     CHECK (status IN ('in_progress', 'completed')),
+    response_status  SMALLINT,
+    response_headers JSONB,
+    response_body    BYTEA,
+    expires_at       TIMESTAMPTZ  NOT NULL,
+    created_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+
+    UNIQUE (scope, key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires ON idempotency_keys (expires_at);
+
+CREATE TRIGGER set_idempotency_keys_updated_at
+    BEFORE UPDATE ON idempotency_keys
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
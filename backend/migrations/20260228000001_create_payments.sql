@@ -0,0 +1,52 @@
+-- An order can be authorized but not yet captured
+ALTER TABLE orders DROP CONSTRAINT orders_payment_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_payment_status_check
+    CHECK (payment_status IN ('unpaid', 'authorized', 'paid'));
+
+-- One payment per attempt to pay an order through a provider
+CREATE TABLE payments (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    order_id            UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    -- Provider name (stripe, mock) and its ID for the payment (e.g. a PaymentIntent)
+    provider            VARCHAR(32)  NOT NULL,
+    provider_payment_id VARCHAR(255),
+    status              VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                        CHECK (status IN ('pending', 'requires_action', 'authorized', 'captured', 'voided', 'failed')),
+    -- Always the order total in the order currency at the time of payment
+    currency            VARCHAR(3)   NOT NULL,
+    amount              INTEGER      NOT NULL CHECK (amount > 0),
+    amount_captured     INTEGER      NOT NULL DEFAULT 0 CHECK (amount_captured >= 0 AND amount_captured <= amount),
+    failure_reason      TEXT,
+    created_at          TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at          TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_payments_order ON payments (order_id, created_at);
+CREATE UNIQUE INDEX idx_payments_provider_id ON payments (provider, provider_payment_id)
+    WHERE provider_payment_id IS NOT NULL;
+-- An order has at most one payment in flight or collected
+CREATE UNIQUE INDEX idx_payments_one_live_per_order ON payments (order_id)
+    WHERE status IN ('pending', 'requires_action', 'authorized', 'captured');
+
+CREATE TRIGGER set_payments_updated_at
+    BEFORE UPDATE ON payments
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Every call made to the provider for a payment; the attempt ID is sent as
+-- the provider idempotency key
+CREATE TABLE payment_attempts (
+    id                 UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    payment_id         UUID         NOT NULL REFERENCES payments(id) ON DELETE CASCADE,
+    operation          VARCHAR(20)  NOT NULL CHECK (operation IN ('authorize', 'capture', 'void', 'refund')),
+    amount             INTEGER      NOT NULL CHECK (amount >= 0),
+    status             VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                       CHECK (status IN ('pending', 'succeeded', 'failed')),
+    -- Provider's ID for what the call created (e.g. a refund ID)
+    provider_reference VARCHAR(255),
+    error              TEXT,
+    created_at         TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    completed_at       TIMESTAMPTZ
+);
+
+CREATE INDEX idx_payment_attempts_payment ON payment_attempts (payment_id, created_at);
@@ -0,0 +1,22 @@
+-- Inbox of verified payment provider webhooks. Receiving only stores the
+-- event; a background worker applies it, retrying with backoff on failure.
+CREATE TABLE payment_webhook_events (
+    id            UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    provider      VARCHAR(32)  NOT NULL,
+    -- Provider's event ID; redeliveries of the same event are dropped
+    event_id      VARCHAR(255) NOT NULL,
+    event_type    VARCHAR(100) NOT NULL,
+    -- Body exactly as received, kept for audit
+    payload       TEXT         NOT NULL,
+    status        VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                  CHECK (status IN ('pending', 'processed', 'ignored', 'failed')),
+    attempts      INTEGER      NOT NULL DEFAULT 0,
+    last_error    TEXT,
+    process_after TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    processed_at  TIMESTAMPTZ,
+    received_at   TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, event_id)
+);
+
+CREATE INDEX idx_payment_webhook_events_pending ON payment_webhook_events (process_after)
+    WHERE status = 'pending';
@@ -0,0 +1,53 @@
+-- Orders can be refunded in part or in full
+ALTER TABLE orders DROP CONSTRAINT orders_payment_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_payment_status_check
+    CHECK (payment_status IN ('unpaid', 'authorized', 'paid', 'partially_refunded', 'refunded'));
+
+-- Shipping charged on the order, included in total
+ALTER TABLE orders ADD COLUMN shipping_total INTEGER NOT NULL DEFAULT 0 CHECK (shipping_total >= 0);
+
+-- Never more than was captured can go back to the customer
+ALTER TABLE payments ADD COLUMN amount_refunded INTEGER NOT NULL DEFAULT 0
+    CHECK (amount_refunded >= 0 AND amount_refunded <= amount_captured);
+
+-- Money returned to the customer through the payment provider
+CREATE TABLE refunds (
+    id                 UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    order_id           UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    payment_id         UUID         NOT NULL REFERENCES payments(id) ON DELETE CASCADE,
+    status             VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                       CHECK (status IN ('pending', 'succeeded', 'failed')),
+    currency           VARCHAR(3)   NOT NULL,
+    -- Lines + shipping + any extra amount
+    amount             INTEGER      NOT NULL CHECK (amount > 0),
+    shipping_amount    INTEGER      NOT NULL DEFAULT 0 CHECK (shipping_amount >= 0),
+    reason             TEXT         NOT NULL,
+    -- Put refunded line quantities back in stock once the refund succeeds
+    restock            BOOLEAN      NOT NULL DEFAULT FALSE,
+    provider_refund_id VARCHAR(255),
+    failure_reason     TEXT,
+    created_by         UUID         REFERENCES users(id) ON DELETE SET NULL,
+    created_at         TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_refunds_order ON refunds (order_id, created_at);
+CREATE INDEX idx_refunds_payment_pending ON refunds (payment_id, created_at)
+    WHERE status = 'pending';
+
+CREATE TRIGGER set_refunds_updated_at
+    BEFORE UPDATE ON refunds
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Order lines (and how many of each) a refund covers
+CREATE TABLE refund_items (
+    id            UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    refund_id     UUID        NOT NULL REFERENCES refunds(id) ON DELETE CASCADE,
+    order_item_id UUID        NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    quantity      INTEGER     NOT NULL CHECK (quantity > 0),
+    amount        INTEGER     NOT NULL CHECK (amount >= 0),
+    UNIQUE (refund_id, order_item_id)
+);
+
+CREATE INDEX idx_refund_items_order_item ON refund_items (order_item_id);
@@ -0,0 +1,63 @@
+-- Return merchandise authorizations: a customer asks to send order lines back
+CREATE TABLE returns (
+    id                UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id          UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    order_id          UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    user_id           UUID         REFERENCES users(id) ON DELETE SET NULL,
+    -- Human-facing RMA number, quoted on the parcel and in emails
+    rma_number        BIGINT       GENERATED BY DEFAULT AS IDENTITY (START WITH 1001) UNIQUE,
+    status            VARCHAR(20)  NOT NULL DEFAULT 'requested'
+                      CHECK (status IN ('requested', 'approved', 'rejected', 'received', 'closed')),
+    reason            VARCHAR(30)  NOT NULL
+                      CHECK (reason IN ('damaged', 'defective', 'missing_parts', 'wrong_item',
+                                        'not_as_described', 'changed_mind', 'other')),
+    comment           TEXT,
+    -- Staff decision (approve / reject)
+    decision_note     TEXT,
+    decided_by        UUID         REFERENCES users(id) ON DELETE SET NULL,
+    decided_at        TIMESTAMPTZ,
+    -- Arrival at the warehouse
+    received_at       TIMESTAMPTZ,
+    condition         VARCHAR(20)
+                      CHECK (condition IN ('unopened', 'like_new', 'used', 'damaged')),
+    condition_note    TEXT,
+    -- Outcome
+    resolution        VARCHAR(20)
+                      CHECK (resolution IN ('refund', 'exchange', 'store_credit')),
+    refund_id         UUID         REFERENCES refunds(id) ON DELETE SET NULL,
+    credit_amount     INTEGER      CHECK (credit_amount > 0),
+    resolution_note   TEXT,
+    closed_at         TIMESTAMPTZ,
+    created_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_returns_store_status ON returns (store_id, status, created_at DESC);
+CREATE INDEX idx_returns_order ON returns (order_id, created_at);
+
+CREATE TRIGGER set_returns_updated_at
+    BEFORE UPDATE ON returns
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+CREATE TABLE return_items (
+    id            UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    return_id     UUID        NOT NULL REFERENCES returns(id) ON DELETE CASCADE,
+    order_item_id UUID        NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    quantity      INTEGER     NOT NULL CHECK (quantity > 0),
+    UNIQUE (return_id, order_item_id)
+);
+
+CREATE INDEX idx_return_items_order_item ON return_items (order_item_id);
+
+-- Photos the customer attached, kept in goseli-storage
+CREATE TABLE return_photos (
+    id           UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    return_id    UUID         NOT NULL REFERENCES returns(id) ON DELETE CASCADE,
+    storage_key  VARCHAR(500) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes   INTEGER      NOT NULL CHECK (size_bytes > 0),
+    created_at   TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_return_photos_return ON return_photos (return_id, created_at);
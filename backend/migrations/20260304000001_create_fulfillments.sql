@@ -0,0 +1,59 @@
+-- Orders can ship in several parcels
+ALTER TABLE orders DROP CONSTRAINT orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check
+    CHECK (status IN ('pending', 'paid', 'partially_fulfilled', 'fulfilled', 'completed',
+                      'cancelled', 'refunded'));
+
+-- Carriers a store ships with, and how to link to their tracking pages
+CREATE TABLE carriers (
+    id                    UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id              UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Short code used on fulfillments, e.g. 'ups' or 'postnl'
+    code                  VARCHAR(50)  NOT NULL,
+    name                  VARCHAR(100) NOT NULL,
+    -- URL with a {tracking_number} placeholder
+    tracking_url_template VARCHAR(500),
+    is_active             BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at            TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at            TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, code)
+);
+
+CREATE TRIGGER set_carriers_updated_at
+    BEFORE UPDATE ON carriers
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- A parcel: some quantity of some of an order's lines
+CREATE TABLE fulfillments (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    order_id        UUID         NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    status          VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                    CHECK (status IN ('pending', 'shipped', 'delivered', 'cancelled')),
+    carrier         VARCHAR(50),
+    tracking_number VARCHAR(100),
+    -- Rendered from the carrier's template when the parcel is created or shipped
+    tracking_url    VARCHAR(1000),
+    shipped_at      TIMESTAMPTZ,
+    delivered_at    TIMESTAMPTZ,
+    created_by      UUID         REFERENCES users(id) ON DELETE SET NULL,
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_fulfillments_order ON fulfillments (order_id, created_at);
+
+CREATE TRIGGER set_fulfillments_updated_at
+    BEFORE UPDATE ON fulfillments
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+CREATE TABLE fulfillment_items (
+    id             UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    fulfillment_id UUID        NOT NULL REFERENCES fulfillments(id) ON DELETE CASCADE,
+    order_item_id  UUID        NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    quantity       INTEGER     NOT NULL CHECK (quantity > 0),
+    UNIQUE (fulfillment_id, order_item_id)
+);
+
+CREATE INDEX idx_fulfillment_items_order_item ON fulfillment_items (order_item_id);
@@ -0,0 +1,59 @@
+-- Physical size of what ships; NULL on a variant means the product's value
+ALTER TABLE products
+    ADD COLUMN weight_grams INTEGER CHECK (weight_grams IS NULL OR weight_grams >= 0),
+    ADD COLUMN length_mm    INTEGER CHECK (length_mm IS NULL OR length_mm > 0),
+    ADD COLUMN width_mm     INTEGER CHECK (width_mm IS NULL OR width_mm > 0),
+    ADD COLUMN height_mm    INTEGER CHECK (height_mm IS NULL OR height_mm > 0);
+
+ALTER TABLE product_variants
+    ADD COLUMN weight_grams INTEGER CHECK (weight_grams IS NULL OR weight_grams >= 0),
+    ADD COLUMN length_mm    INTEGER CHECK (length_mm IS NULL OR length_mm > 0),
+    ADD COLUMN width_mm     INTEGER CHECK (width_mm IS NULL OR width_mm > 0),
+    ADD COLUMN height_mm    INTEGER CHECK (height_mm IS NULL OR height_mm > 0);
+
+-- Where a store ships to; empty arrays match everything
+CREATE TABLE shipping_zones (
+    id                UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id          UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    name              VARCHAR(100) NOT NULL,
+    countries         TEXT[]       NOT NULL DEFAULT '{}',
+    regions           TEXT[]       NOT NULL DEFAULT '{}',
+    -- 'SW1*' wildcards or '1000-1999' numeric ranges
+    postcode_patterns TEXT[]       NOT NULL DEFAULT '{}',
+    -- The first matching zone (lowest priority) decides the methods offered
+    priority          INTEGER      NOT NULL DEFAULT 0,
+    is_active         BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_shipping_zones_store ON shipping_zones (store_id, priority, created_at);
+
+CREATE TRIGGER set_shipping_zones_updated_at
+    BEFORE UPDATE ON shipping_zones
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+CREATE TABLE shipping_methods (
+    id           UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    zone_id      UUID         NOT NULL REFERENCES shipping_zones(id) ON DELETE CASCADE,
+    code         VARCHAR(64)  NOT NULL,
+    name         VARCHAR(100) NOT NULL,
+    description  VARCHAR(500),
+    kind         VARCHAR(20)  NOT NULL
+                 CHECK (kind IN ('flat_rate', 'weight_tiers', 'price_tiers', 'free', 'local_pickup')),
+    rate         INTEGER      NOT NULL DEFAULT 0 CHECK (rate >= 0),
+    -- [{"up_to": 1000, "rate": 495}, {"up_to": null, "rate": 995}]
+    tiers        JSONB        NOT NULL DEFAULT '[]',
+    min_subtotal INTEGER      CHECK (min_subtotal IS NULL OR min_subtotal >= 0),
+    sort_order   INTEGER      NOT NULL DEFAULT 0,
+    is_active    BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at   TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at   TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (zone_id, code)
+);
+
+CREATE TRIGGER set_shipping_methods_updated_at
+    BEFORE UPDATE ON shipping_methods
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
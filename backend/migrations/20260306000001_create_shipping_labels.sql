@@ -0,0 +1,54 @@
+-- Methods priced live by a carrier adapter; `rate` is added as a handling fee
+ALTER TABLE shipping_methods DROP CONSTRAINT shipping_methods_kind_check;
+ALTER TABLE shipping_methods ADD CONSTRAINT shipping_methods_kind_check
+    CHECK (kind IN ('flat_rate', 'weight_tiers', 'price_tiers', 'free', 'local_pickup', 'carrier'));
+ALTER TABLE shipping_methods
+    ADD COLUMN carrier_code VARCHAR(64),
+    ADD COLUMN service_code VARCHAR(64),
+    ADD CONSTRAINT shipping_methods_carrier_check
+        CHECK (kind <> 'carrier' OR (carrier_code IS NOT NULL AND service_code IS NOT NULL));
+
+-- Last status reported by the carrier for parcels with a purchased label
+ALTER TABLE fulfillments
+    ADD COLUMN tracking_status     VARCHAR(30)
+        CHECK (tracking_status IN ('pre_transit', 'in_transit', 'out_for_delivery', 'delivered',
+                                   'exception', 'returned_to_sender', 'unknown')),
+    ADD COLUMN tracking_checked_at TIMESTAMPTZ;
+
+-- Every label bought for a parcel; the label ID is sent as the carrier
+-- idempotency key
+CREATE TABLE shipping_labels (
+    id                  UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    fulfillment_id      UUID         NOT NULL REFERENCES fulfillments(id) ON DELETE CASCADE,
+    -- Carrier adapter code and its IDs for the shipment
+    carrier             VARCHAR(64)  NOT NULL,
+    service_code        VARCHAR(64)  NOT NULL,
+    carrier_shipment_id VARCHAR(255),
+    tracking_number     VARCHAR(255),
+    status              VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                        CHECK (status IN ('pending', 'purchased', 'failed', 'voided')),
+    format              VARCHAR(10)  NOT NULL CHECK (format IN ('pdf', 'zpl')),
+    -- Key of the label file in storage
+    storage_key         VARCHAR(500),
+    -- What the carrier charged
+    amount              INTEGER      CHECK (amount IS NULL OR amount >= 0),
+    currency            VARCHAR(3),
+    error               TEXT,
+    created_by          UUID         REFERENCES users(id) ON DELETE SET NULL,
+    created_at          TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at          TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    voided_at           TIMESTAMPTZ
+);
+
+CREATE INDEX idx_shipping_labels_fulfillment ON shipping_labels (fulfillment_id, created_at);
+-- A parcel has at most one label being bought or in use
+CREATE UNIQUE INDEX idx_shipping_labels_one_live_per_fulfillment ON shipping_labels (fulfillment_id)
+    WHERE status IN ('pending', 'purchased');
+
+CREATE TRIGGER set_shipping_labels_updated_at
+    BEFORE UPDATE ON shipping_labels
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+CREATE INDEX idx_fulfillments_tracking_due ON fulfillments (tracking_checked_at NULLS FIRST)
+    WHERE status = 'shipped';
@@ -0,0 +1,47 @@
+-- Which tax rates apply to a product; variants share their product's class
+ALTER TABLE products
+    ADD COLUMN tax_class VARCHAR(20) NOT NULL DEFAULT 'standard'
+        CHECK (tax_class IN ('standard', 'reduced', 'exempt'));
+
+-- Taxes a store charges by country, region and postcode. Within a priority
+-- the most specific match applies; different priorities stack.
+CREATE TABLE tax_rates (
+    id                UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id          UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    name              VARCHAR(100) NOT NULL,
+    country           VARCHAR(2)   NOT NULL,
+    region            VARCHAR(100),
+    -- 'SW1*' wildcards or '1000-1999' numeric ranges; empty for the whole region
+    postcode_patterns TEXT[]       NOT NULL DEFAULT '{}',
+    tax_class         VARCHAR(20)  NOT NULL DEFAULT 'standard'
+                      CHECK (tax_class IN ('standard', 'reduced')),
+    -- Parts per million: 21% is 210000, 8.875% is 88750
+    rate_ppm          INTEGER      NOT NULL CHECK (rate_ppm >= 0 AND rate_ppm <= 1000000),
+    priority          INTEGER      NOT NULL DEFAULT 0,
+    is_active         BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_tax_rates_store ON tax_rates (store_id, country, priority, created_at);
+
+CREATE TRIGGER set_tax_rates_updated_at
+    BEFORE UPDATE ON tax_rates
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Tax snapshots: what was charged and by which rates, frozen at purchase.
+-- With tax-inclusive prices the tax is already part of the line and
+-- shipping amounts; otherwise it is added to the total.
+ALTER TABLE orders
+    ADD COLUMN prices_include_tax BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN tax_total          INTEGER NOT NULL DEFAULT 0 CHECK (tax_total >= 0),
+    ADD COLUMN shipping_tax       INTEGER NOT NULL DEFAULT 0 CHECK (shipping_tax >= 0),
+    -- Totals per rate, shipping included
+    ADD COLUMN tax_lines          JSONB   NOT NULL DEFAULT '[]';
+
+ALTER TABLE order_items
+    ADD COLUMN tax_class VARCHAR(20) NOT NULL DEFAULT 'standard'
+        CHECK (tax_class IN ('standard', 'reduced', 'exempt')),
+    ADD COLUMN tax_total INTEGER     NOT NULL DEFAULT 0 CHECK (tax_total >= 0),
+    ADD COLUMN tax_lines JSONB       NOT NULL DEFAULT '[]';
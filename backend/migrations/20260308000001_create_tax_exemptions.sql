@@ -0,0 +1,38 @@
+-- Customers who buy without tax (resellers, contractors, non-profits), on the
+-- strength of a certificate reviewed by staff
+CREATE TABLE tax_exemptions (
+    id                 UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id           UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    user_id            UUID         NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status             VARCHAR(20)  NOT NULL DEFAULT 'pending'
+                       CHECK (status IN ('pending', 'approved', 'rejected', 'revoked')),
+    certificate_number VARCHAR(100),
+    -- Key of the uploaded certificate in storage
+    storage_key        VARCHAR(500) NOT NULL,
+    content_type       VARCHAR(100) NOT NULL,
+    size_bytes         INTEGER      NOT NULL CHECK (size_bytes > 0),
+    -- The exemption stops applying at this moment
+    expires_at         TIMESTAMPTZ  NOT NULL,
+    -- Staff note on the decision, shown to the customer
+    note               TEXT,
+    reviewed_by        UUID         REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at        TIMESTAMPTZ,
+    created_at         TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_tax_exemptions_user ON tax_exemptions (user_id, created_at DESC);
+CREATE INDEX idx_tax_exemptions_store_status ON tax_exemptions (store_id, status, created_at);
+-- One certificate waiting for review per customer
+CREATE UNIQUE INDEX idx_tax_exemptions_one_pending ON tax_exemptions (user_id)
+    WHERE status = 'pending';
+
+CREATE TRIGGER set_tax_exemptions_updated_at
+    BEFORE UPDATE ON tax_exemptions
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Exemption an order was placed under; its tax snapshot is then empty
+ALTER TABLE orders
+    ADD COLUMN tax_exemption_id UUID REFERENCES tax_exemptions(id) ON DELETE SET NULL;
+
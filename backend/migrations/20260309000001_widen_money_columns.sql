@@ -0,0 +1,36 @@
+-- Amounts are whole minor units of a currency, up to i64. Widen catalog,
+-- cart and order amounts so large B2B orders cannot overflow.
+ALTER TABLE products
+    ALTER COLUMN price            TYPE BIGINT,
+    ALTER COLUMN compare_at_price TYPE BIGINT,
+    ALTER COLUMN cost_price       TYPE BIGINT;
+
+ALTER TABLE product_variants
+    ALTER COLUMN price            TYPE BIGINT,
+    ALTER COLUMN compare_at_price TYPE BIGINT;
+
+ALTER TABLE cart_items
+    ALTER COLUMN price_modifier TYPE BIGINT;
+
+ALTER TABLE abandoned_carts
+    ALTER COLUMN cart_total        TYPE BIGINT,
+    ALTER COLUMN recovered_revenue TYPE BIGINT;
+
+ALTER TABLE orders
+    ALTER COLUMN subtotal       TYPE BIGINT,
+    ALTER COLUMN shipping_total TYPE BIGINT,
+    ALTER COLUMN total          TYPE BIGINT,
+    ALTER COLUMN tax_total      TYPE BIGINT,
+    ALTER COLUMN shipping_tax   TYPE BIGINT;
+
+-- Lines carry their order's currency so they can be read on their own
+ALTER TABLE order_items
+    ALTER COLUMN unit_price     TYPE BIGINT,
+    ALTER COLUMN price_modifier TYPE BIGINT,
+    ALTER COLUMN total          TYPE BIGINT,
+    ALTER COLUMN tax_total      TYPE BIGINT,
+    ADD COLUMN currency VARCHAR(3);
+
+UPDATE order_items oi SET currency = o.currency FROM orders o WHERE o.id = oi.order_id;
+
+ALTER TABLE order_items ALTER COLUMN currency SET NOT NULL;
@@ -0,0 +1,62 @@
+-- Currencies a store shows prices in besides its own, and how converted
+-- prices are rounded (an increment of 100 with an ending of 99 gives .99
+-- prices). Orders are still placed in the store currency.
+CREATE TABLE store_currencies (
+    store_id           UUID        NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    currency           VARCHAR(3)  NOT NULL,
+    rounding_mode      VARCHAR(20) NOT NULL DEFAULT 'half_up'
+                       CHECK (rounding_mode IN ('half_up', 'half_even', 'down', 'up')),
+    -- Minor units
+    rounding_increment BIGINT      NOT NULL DEFAULT 1 CHECK (rounding_increment > 0),
+    price_ending       BIGINT      CHECK (price_ending >= 0 AND price_ending < rounding_increment),
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (store_id, currency)
+);
+
+CREATE TRIGGER set_store_currencies_updated_at
+    BEFORE UPDATE ON store_currencies
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Current rate per currency against the store currency, entered by hand or
+-- imported from a rates file
+CREATE TABLE exchange_rates (
+    store_id   UUID        NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    currency   VARCHAR(3)  NOT NULL,
+    -- Millionths of a unit per unit of the store currency: 0.92 is 920000
+    rate_ppm   BIGINT      NOT NULL CHECK (rate_ppm > 0),
+    source     VARCHAR(20) NOT NULL DEFAULT 'manual' CHECK (source IN ('manual', 'import')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (store_id, currency)
+);
+
+CREATE TRIGGER set_exchange_rates_updated_at
+    BEFORE UPDATE ON exchange_rates
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Fixed prices per currency that replace converted ones
+CREATE TABLE price_list_entries (
+    id               UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id         UUID        NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    currency         VARCHAR(3)  NOT NULL,
+    product_id       UUID        NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    -- NULL for the product's own price
+    variant_id       UUID        REFERENCES product_variants(id) ON DELETE CASCADE,
+    price            BIGINT      NOT NULL CHECK (price >= 0),
+    compare_at_price BIGINT      CHECK (compare_at_price >= 0),
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One price per product or variant and currency
+CREATE UNIQUE INDEX idx_price_list_entries_item ON price_list_entries (
+    store_id, currency, product_id, COALESCE(variant_id, '00000000-0000-0000-0000-000000000000')
+);
+
+CREATE TRIGGER set_price_list_entries_updated_at
+    BEFORE UPDATE ON price_list_entries
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
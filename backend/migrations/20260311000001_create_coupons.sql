@@ -0,0 +1,86 @@
+-- Discount codes. A coupon has one shared code, single-use generated codes
+-- (coupon_codes), or both. Amounts are in the store currency.
+CREATE TABLE coupons (
+    id                       UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id                 UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Shared code anyone can enter; NULL when only generated codes work
+    code                     VARCHAR(50),
+    -- Shown to customers next to the discount
+    description              VARCHAR(255),
+    kind                     VARCHAR(20)  NOT NULL
+                             CHECK (kind IN ('percentage', 'fixed_amount', 'free_shipping')),
+    -- Parts per million of the eligible lines (10% = 100000)
+    percentage_ppm           INTEGER      CHECK (percentage_ppm > 0 AND percentage_ppm <= 1000000),
+    -- Minor units taken off the eligible lines
+    amount                   BIGINT       CHECK (amount > 0),
+    -- Cart subtotal needed before the coupon applies
+    min_subtotal             BIGINT       CHECK (min_subtotal >= 0),
+    -- Lines the discount applies to; both empty for the whole cart
+    product_ids              UUID[]       NOT NULL DEFAULT '{}',
+    category_ids             UUID[]       NOT NULL DEFAULT '{}',
+    starts_at                TIMESTAMPTZ,
+    ends_at                  TIMESTAMPTZ,
+    -- Redemptions allowed in total and per customer; NULL for no limit
+    usage_limit              INTEGER      CHECK (usage_limit > 0),
+    usage_limit_per_customer INTEGER      CHECK (usage_limit_per_customer > 0),
+    times_used               INTEGER      NOT NULL DEFAULT 0 CHECK (times_used >= 0),
+    is_active                BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at               TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at               TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CHECK (kind <> 'percentage' OR percentage_ppm IS NOT NULL),
+    CHECK (kind <> 'fixed_amount' OR amount IS NOT NULL),
+    CHECK (ends_at IS NULL OR starts_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE UNIQUE INDEX idx_coupons_code ON coupons (store_id, upper(code)) WHERE code IS NOT NULL;
+CREATE INDEX idx_coupons_store ON coupons (store_id, created_at DESC);
+
+CREATE TRIGGER set_coupons_updated_at
+    BEFORE UPDATE ON coupons
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Bulk-generated codes, each redeemable once
+CREATE TABLE coupon_codes (
+    id         UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id   UUID        NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    coupon_id  UUID        NOT NULL REFERENCES coupons(id) ON DELETE CASCADE,
+    code       VARCHAR(50) NOT NULL,
+    used_at    TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_coupon_codes_code ON coupon_codes (store_id, upper(code));
+CREATE INDEX idx_coupon_codes_coupon ON coupon_codes (coupon_id, created_at);
+
+-- One row per order placed with a coupon, written with the order
+CREATE TABLE coupon_redemptions (
+    id             UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    coupon_id      UUID         NOT NULL REFERENCES coupons(id) ON DELETE CASCADE,
+    coupon_code_id UUID         REFERENCES coupon_codes(id) ON DELETE SET NULL,
+    order_id       UUID         NOT NULL UNIQUE REFERENCES orders(id) ON DELETE CASCADE,
+    user_id        UUID         REFERENCES users(id) ON DELETE SET NULL,
+    email          VARCHAR(255) NOT NULL,
+    code           VARCHAR(50)  NOT NULL,
+    -- Taken off the lines plus the shipping waived
+    amount         BIGINT       NOT NULL CHECK (amount >= 0),
+    currency       VARCHAR(3)   NOT NULL,
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_coupon_redemptions_coupon ON coupon_redemptions (coupon_id, created_at DESC);
+CREATE INDEX idx_coupon_redemptions_customer ON coupon_redemptions (coupon_id, user_id);
+CREATE INDEX idx_coupon_redemptions_email ON coupon_redemptions (coupon_id, lower(email));
+
+-- Code entered on the cart, checked again when the order is placed
+ALTER TABLE carts
+    ADD COLUMN coupon_code VARCHAR(50);
+
+-- Discount snapshots. Line totals are what was charged for the line, after
+-- its discount; the order subtotal is before discounts.
+ALTER TABLE orders
+    ADD COLUMN discount_total BIGINT NOT NULL DEFAULT 0 CHECK (discount_total >= 0),
+    ADD COLUMN coupon_code    VARCHAR(50);
+
+ALTER TABLE order_items
+    ADD COLUMN discount_total BIGINT NOT NULL DEFAULT 0 CHECK (discount_total >= 0);
@@ -0,0 +1,39 @@
+-- Automatic promotions: discounts that apply without a code when the cart
+-- meets their conditions. Amounts are in the store currency.
+CREATE TABLE promotions (
+    id                    UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id              UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Shown to customers next to the discount
+    name                  VARCHAR(255) NOT NULL,
+    -- Higher runs first; ties run oldest first
+    priority              INTEGER      NOT NULL DEFAULT 0,
+    -- Applies alone, never together with another promotion
+    exclusive             BOOLEAN      NOT NULL DEFAULT FALSE,
+    -- Still applies when the customer uses a coupon
+    combines_with_coupons BOOLEAN      NOT NULL DEFAULT TRUE,
+    -- Cart contents, customer groups, thresholds (PromotionConditions)
+    conditions            JSONB        NOT NULL DEFAULT '{}',
+    -- What the promotion does, tagged by "type" (PromotionAction)
+    action                JSONB        NOT NULL,
+    starts_at             TIMESTAMPTZ,
+    ends_at               TIMESTAMPTZ,
+    is_active             BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at            TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at            TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CHECK (ends_at IS NULL OR starts_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE INDEX idx_promotions_store ON promotions (store_id, priority DESC, created_at);
+
+CREATE TRIGGER set_promotions_updated_at
+    BEFORE UPDATE ON promotions
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Customer group promotions can be limited to, e.g. "wholesale"
+ALTER TABLE users
+    ADD COLUMN customer_group VARCHAR(50);
+
+-- Promotions applied to the order, with their explanations
+ALTER TABLE orders
+    ADD COLUMN promotions JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,43 @@
+-- Scheduled sales. Prices are not rewritten: catalog, cart and checkout
+-- look up the sales running at the time, so a sale starts and ends on the
+-- second and regular prices come back by themselves.
+CREATE TABLE sales (
+    id             UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id       UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Shown to customers, e.g. "Black Friday"
+    name           VARCHAR(255) NOT NULL,
+    -- Taken off items without a price or percentage of their own (10% = 100000)
+    percentage_ppm INTEGER      CHECK (percentage_ppm > 0 AND percentage_ppm <= 1000000),
+    starts_at      TIMESTAMPTZ  NOT NULL,
+    ends_at        TIMESTAMPTZ  NOT NULL,
+    is_active      BOOLEAN      NOT NULL DEFAULT TRUE,
+    created_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at     TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX idx_sales_store ON sales (store_id, starts_at, ends_at);
+
+CREATE TRIGGER set_sales_updated_at
+    BEFORE UPDATE ON sales
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Products, or single variants, in a sale
+CREATE TABLE sale_items (
+    id             UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    sale_id        UUID        NOT NULL REFERENCES sales(id) ON DELETE CASCADE,
+    product_id     UUID        NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    -- NULL for the product and all its variants
+    variant_id     UUID        REFERENCES product_variants(id) ON DELETE CASCADE,
+    -- Fixed sale price in minor units of the store currency
+    sale_price     BIGINT      CHECK (sale_price >= 0),
+    percentage_ppm INTEGER     CHECK (percentage_ppm > 0 AND percentage_ppm <= 1000000),
+    -- Units sold at the sale price, and how many may be; NULL for no cap
+    quantity_limit INTEGER     CHECK (quantity_limit > 0),
+    sold_quantity  INTEGER     NOT NULL DEFAULT 0 CHECK (sold_quantity >= 0),
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE NULLS NOT DISTINCT (sale_id, product_id, variant_id)
+);
+
+CREATE INDEX idx_sale_items_product ON sale_items (product_id);
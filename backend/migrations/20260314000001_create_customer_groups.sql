@@ -0,0 +1,56 @@
+-- Customer groups (retail, wholesale, VIP, ...) that customers are put in.
+-- users.customer_group holds the group's code, which promotions match on.
+CREATE TABLE customer_groups (
+    id          UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id    UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- E.g. "wholesale"; fixed once created
+    code        VARCHAR(50)  NOT NULL,
+    name        VARCHAR(255) NOT NULL,
+    description TEXT,
+    created_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at  TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, code)
+);
+
+CREATE TRIGGER set_customer_groups_updated_at
+    BEFORE UPDATE ON customer_groups
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Groups customers were already put in for promotions
+INSERT INTO customer_groups (store_id, code, name)
+SELECT DISTINCT store_id, customer_group, customer_group
+FROM users
+WHERE customer_group IS NOT NULL;
+
+-- Deleting a group takes its customers out of it
+ALTER TABLE users
+    ADD FOREIGN KEY (store_id, customer_group) REFERENCES customer_groups (store_id, code)
+        ON DELETE SET NULL (customer_group);
+
+-- Price rules per group: a fixed price or a percentage off the regular
+-- price, from a quantity up. Several rules with different minimum
+-- quantities for the same product make quantity breaks.
+CREATE TABLE group_prices (
+    id                UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id          UUID        NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    customer_group_id UUID        NOT NULL REFERENCES customer_groups(id) ON DELETE CASCADE,
+    product_id        UUID        NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    -- NULL for the product and all its variants
+    variant_id        UUID        REFERENCES product_variants(id) ON DELETE CASCADE,
+    min_quantity      INTEGER     NOT NULL DEFAULT 1 CHECK (min_quantity > 0),
+    -- Minor units of the store currency
+    price             BIGINT      CHECK (price >= 0),
+    percentage_ppm    INTEGER     CHECK (percentage_ppm > 0 AND percentage_ppm <= 1000000),
+    created_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at        TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((price IS NULL) <> (percentage_ppm IS NULL)),
+    UNIQUE NULLS NOT DISTINCT (customer_group_id, product_id, variant_id, min_quantity)
+);
+
+CREATE INDEX idx_group_prices_product ON group_prices (product_id);
+
+CREATE TRIGGER set_group_prices_updated_at
+    BEFORE UPDATE ON group_prices
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
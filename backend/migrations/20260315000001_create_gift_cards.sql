@@ -0,0 +1,75 @@
+-- Gift cards: a code with a balance in the store currency, spent at
+-- checkout. Cards are issued when a gift card product is paid for, by staff,
+-- or as store credit for a return.
+CREATE TABLE gift_cards (
+    id              UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    store_id        UUID         NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Stored upper case; customers may type it in any case
+    code            VARCHAR(50)  NOT NULL,
+    currency        VARCHAR(3)   NOT NULL,
+    initial_amount  BIGINT       NOT NULL CHECK (initial_amount >= 0),
+    -- Always the balance_after of the card's latest transaction
+    balance         BIGINT       NOT NULL CHECK (balance >= 0),
+    -- NULL for cards that never expire
+    expires_at      TIMESTAMPTZ,
+    is_active       BOOLEAN      NOT NULL DEFAULT TRUE,
+    source          VARCHAR(20)  NOT NULL
+                    CHECK (source IN ('purchase', 'admin', 'store_credit')),
+    -- Order line the card was bought with, or return it credits
+    order_id        UUID         REFERENCES orders(id) ON DELETE SET NULL,
+    order_item_id   UUID         REFERENCES order_items(id) ON DELETE SET NULL,
+    return_id       UUID         REFERENCES returns(id) ON DELETE SET NULL,
+    recipient_email VARCHAR(255),
+    note            TEXT,
+    created_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, code)
+);
+
+CREATE INDEX idx_gift_cards_store ON gift_cards (store_id, created_at DESC);
+CREATE INDEX idx_gift_cards_order ON gift_cards (order_id) WHERE order_id IS NOT NULL;
+CREATE INDEX idx_gift_cards_expiring ON gift_cards (expires_at)
+    WHERE expires_at IS NOT NULL AND balance > 0;
+
+CREATE TRIGGER set_gift_cards_updated_at
+    BEFORE UPDATE ON gift_cards
+    FOR EACH ROW
+    EXECUTE FUNCTION trigger_set_updated_at();
+
+-- Every change to a card's balance, written with the change
+CREATE TABLE gift_card_transactions (
+    id            UUID PRIMARY KEY DEFAULT uuid_generate_v7(),
+    gift_card_id  UUID        NOT NULL REFERENCES gift_cards(id) ON DELETE CASCADE,
+    kind          VARCHAR(20) NOT NULL
+                  CHECK (kind IN ('issue', 'redeem', 'release', 'adjust', 'expire')),
+    -- Signed: positive adds to the balance
+    amount        BIGINT      NOT NULL,
+    balance_after BIGINT      NOT NULL CHECK (balance_after >= 0),
+    -- Order the card was spent on or given back from
+    order_id      UUID        REFERENCES orders(id) ON DELETE SET NULL,
+    note          TEXT,
+    created_by    UUID        REFERENCES users(id) ON DELETE SET NULL,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_gift_card_transactions_card ON gift_card_transactions (gift_card_id, created_at);
+CREATE INDEX idx_gift_card_transactions_order ON gift_card_transactions (order_id)
+    WHERE order_id IS NOT NULL;
+
+-- Products sold as gift cards issue one card per unit, worth its price
+ALTER TABLE products
+    ADD COLUMN is_gift_card            BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN gift_card_validity_days INTEGER CHECK (gift_card_validity_days > 0);
+
+-- Part of the total paid with gift cards; the rest goes to the payment
+ALTER TABLE orders
+    ADD COLUMN gift_card_total BIGINT NOT NULL DEFAULT 0
+        CHECK (gift_card_total >= 0 AND gift_card_total <= total);
+
+-- Codes entered at the payment step, redeemed when the order is placed
+ALTER TABLE checkout_sessions
+    ADD COLUMN gift_card_codes TEXT[] NOT NULL DEFAULT '{}';
+
+-- Card issued for a store credit resolution
+ALTER TABLE returns
+    ADD COLUMN gift_card_id UUID REFERENCES gift_cards(id) ON DELETE SET NULL;
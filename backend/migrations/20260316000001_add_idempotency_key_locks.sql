@@ -0,0 +1,6 @@
+-- When the request holding an in-progress key started; a claim older than
+-- the lock timeout belongs to a request that died and can be taken over
+ALTER TABLE idempotency_keys ADD COLUMN locked_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Guests without a session no longer get idempotent replays
+DELETE FROM idempotency_keys WHERE scope = 'anonymous';
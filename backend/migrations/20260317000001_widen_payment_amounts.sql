@@ -0,0 +1,26 @@
+-- Payments, refunds, return credit and shipping charges are amounts of an
+-- order too, and must hold any order total
+ALTER TABLE payments
+    ALTER COLUMN amount          TYPE BIGINT,
+    ALTER COLUMN amount_captured TYPE BIGINT,
+    ALTER COLUMN amount_refunded TYPE BIGINT;
+
+ALTER TABLE payment_attempts
+    ALTER COLUMN amount TYPE BIGINT;
+
+ALTER TABLE refunds
+    ALTER COLUMN amount          TYPE BIGINT,
+    ALTER COLUMN shipping_amount TYPE BIGINT;
+
+ALTER TABLE refund_items
+    ALTER COLUMN amount TYPE BIGINT;
+
+ALTER TABLE returns
+    ALTER COLUMN credit_amount TYPE BIGINT;
+
+ALTER TABLE shipping_methods
+    ALTER COLUMN rate         TYPE BIGINT,
+    ALTER COLUMN min_subtotal TYPE BIGINT;
+
+ALTER TABLE shipping_labels
+    ALTER COLUMN amount TYPE BIGINT;
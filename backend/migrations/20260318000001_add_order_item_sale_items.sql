@@ -0,0 +1,4 @@
+-- The sale item a line was charged the sale price under, so its units count
+-- against the sale's cap again when the line goes back in stock
+ALTER TABLE order_items
+    ADD COLUMN sale_item_id UUID REFERENCES sale_items(id) ON DELETE SET NULL;
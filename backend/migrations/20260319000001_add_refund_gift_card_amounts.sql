@@ -0,0 +1,9 @@
+-- On orders paid partly with gift cards, a refund goes back to the card
+-- payment and to the gift cards in the proportion each paid. Only `amount`
+-- is sent to the payment provider; `gift_card_amount` goes back on the cards.
+ALTER TABLE refunds
+    ADD COLUMN gift_card_amount BIGINT NOT NULL DEFAULT 0 CHECK (gift_card_amount >= 0);
+
+ALTER TABLE refunds DROP CONSTRAINT refunds_amount_check;
+ALTER TABLE refunds ADD CONSTRAINT refunds_amount_check
+    CHECK (amount >= 0 AND (amount > 0 OR gift_card_amount > 0));
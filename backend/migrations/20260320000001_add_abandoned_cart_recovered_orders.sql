@@ -0,0 +1,4 @@
+-- The order a recovery is credited with; later orders from the same cart
+-- are not recovered revenue
+ALTER TABLE abandoned_carts
+    ADD COLUMN recovered_order_id UUID REFERENCES orders(id) ON DELETE SET NULL;
@@ -120,7 +120,10 @@ export default function CartPage() {
 
         <div className="lg:col-span-1">
           <CartSummary
-            subtotal={cart.total}
+            subtotal={cart.subtotal}
+            tax={cart.tax_total}
+            total={cart.total}
+            taxIncluded={cart.prices_include_tax}
             itemCount={cart.item_count}
             onCheckout={handleCheckout}
           />
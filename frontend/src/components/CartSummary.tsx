@@ -1,17 +1,20 @@
 'use client';
 
 import { formatPrice } from '@/lib/api';
+import type { Money } from '@/lib/types';
 
 interface CartSummaryProps {
-  subtotal: number;
+  subtotal: Money;
+  /** Estimated tax on the items */
+  tax: Money;
+  total: Money;
+  /** Whether the prices already include the tax */
+  taxIncluded: boolean;
   itemCount: number;
   onCheckout?: () => void;
 }
 
-export function CartSummary({ subtotal, itemCount, onCheckout }: CartSummaryProps) {
-  const shipping = 0;
-  const tax = 0;
-  const total = subtotal + shipping + tax;
+export function CartSummary({ subtotal, tax, total, taxIncluded, itemCount, onCheckout }: CartSummaryProps) {
 
   return (
     <div className="card sticky top-20">
@@ -29,14 +32,14 @@ export function CartSummary({ subtotal, itemCount, onCheckout }: CartSummaryProp
           <div className="flex items-center justify-between text-sm">
             <span className="text-neutral-600">Shipping</span>
             <span className="font-medium text-neutral-900">
-              {shipping === 0 ? 'FREE' : formatPrice(shipping)}
+              FREE
             </span>
           </div>
 
           <div className="flex items-center justify-between text-sm">
-            <span className="text-neutral-600">Tax</span>
+            <span className="text-neutral-600">{taxIncluded ? 'Includes tax' : 'Tax'}</span>
             <span className="font-medium text-neutral-900">
-              {tax === 0 ? 'Calculated at checkout' : formatPrice(tax)}
+              {tax.amount === 0 ? 'Calculated at checkout' : formatPrice(tax)}
             </span>
           </div>
         </div>
@@ -1,13 +1,14 @@
 import { formatPrice } from '@/lib/api';
+import type { Money } from '@/lib/types';
 
 interface PriceDisplayProps {
-  price: number;
-  compareAtPrice?: number | null;
+  price: Money;
+  compareAtPrice?: Money | null;
   className?: string;
 }
 
 export function PriceDisplay({ price, compareAtPrice, className }: PriceDisplayProps) {
-  const hasDiscount = compareAtPrice != null && compareAtPrice > price;
+  const hasDiscount = compareAtPrice != null && compareAtPrice.amount > price.amount;
 
   return (
     <div className={`flex items-center gap-2 ${className ?? ''}`}>
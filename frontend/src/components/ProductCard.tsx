@@ -44,9 +44,10 @@ export function ProductCard({ product }: ProductCardProps) {
             </svg>
           </div>
         )}
-        {product.compare_at_price != null && product.compare_at_price > product.price && (
-          <span className="badge badge-error absolute left-2 top-2">Sale</span>
-        )}
+        {product.compare_at_price != null &&
+          product.compare_at_price.amount > product.price.amount && (
+            <span className="badge badge-error absolute left-2 top-2">Sale</span>
+          )}
       </div>
       <div className="card-body">
         {product.category && (
@@ -5,7 +5,9 @@ import type {
   Category,
   CartResponse,
   AddToCartRequest,
+  Money,
   UpdateCartItemRequest,
+  CurrencyList,
 } from '@/lib/types';
 
 export class ApiError extends Error {
@@ -60,23 +62,32 @@ export async function getProducts(
   );
 }
 
-export async function getProduct(id: string): Promise<Product> {
-  return fetchApi<Product>(`${API_BASE}/api/v1/products/${id}`);
+export async function getProduct(id: string, currency?: string): Promise<Product> {
+  const qs = buildQueryString({ currency });
+  return fetchApi<Product>(`${API_BASE}/api/v1/products/${id}${qs}`);
+}
+
+export async function getCurrencies(): Promise<CurrencyList> {
+  return fetchApi<CurrencyList>(`${API_BASE}/api/v1/currencies`);
 }
 
 export async function getCategories(): Promise<Category[]> {
   return fetchApi<Category[]>(`${API_BASE}/api/v1/categories`);
 }
 
-export function formatPrice(cents: number): string {
-  return new Intl.NumberFormat('en-US', {
+export function formatPrice(money: Money): string {
+  const format = new Intl.NumberFormat('en-US', {
     style: 'currency',
-    currency: 'USD',
-  }).format(cents / 100);
+    currency: money.currency,
+  });
+  // Minor units per major unit: 100 for USD, 1 for JPY
+  const exponent = format.resolvedOptions().maximumFractionDigits ?? 2;
+  return format.format(money.amount / 10 ** exponent);
 }
 
-export async function getCart(): Promise<CartResponse> {
-  return fetchApi<CartResponse>(`${API_BASE}/api/v1/cart`, {
+export async function getCart(currency?: string): Promise<CartResponse> {
+  const qs = buildQueryString({ currency });
+  return fetchApi<CartResponse>(`${API_BASE}/api/v1/cart${qs}`, {
     credentials: 'include',
   });
 }
@@ -109,6 +120,26 @@ export async function removeCartItem(itemId: string): Promise<void> {
   });
 }
 
+export async function applyCoupon(
+  code: string,
+  currency?: string,
+): Promise<CartResponse> {
+  const qs = buildQueryString({ currency });
+  return fetchApi<CartResponse>(`${API_BASE}/api/v1/cart/coupon${qs}`, {
+    method: 'POST',
+    body: JSON.stringify({ code }),
+    credentials: 'include',
+  });
+}
+
+export async function removeCoupon(currency?: string): Promise<CartResponse> {
+  const qs = buildQueryString({ currency });
+  return fetchApi<CartResponse>(`${API_BASE}/api/v1/cart/coupon${qs}`, {
+    method: 'DELETE',
+    credentials: 'include',
+  });
+}
+
 export async function clearCart(): Promise<void> {
   await fetch(`${API_BASE}/api/v1/cart`, {
     method: 'DELETE',
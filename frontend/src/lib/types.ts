@@ -4,6 +4,52 @@ export type ProductStatus = 'draft' | 'active' | 'archived';
 
 export type ProductSort = 'price_asc' | 'price_desc' | 'created_at_desc' | 'name_asc';
 
+/** An amount in minor units (cents; yen for JPY) of an ISO 4217 currency */
+export interface Money {
+  amount: number;
+  currency: string;
+}
+
+export type RoundingMode = 'half_up' | 'half_even' | 'down' | 'up';
+
+/** Currencies the storefront can show prices in; orders use `base` */
+export interface CurrencyList {
+  base: string;
+  currencies: string[];
+}
+
+export interface StoreCurrency {
+  store_id: string;
+  currency: string;
+  rounding_mode: RoundingMode;
+  rounding_increment: number;
+  price_ending: number | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface ExchangeRate {
+  store_id: string;
+  currency: string;
+  /** Millionths of a unit per unit of the store currency */
+  rate_ppm: number;
+  source: 'manual' | 'import';
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PriceListEntry {
+  id: string;
+  store_id: string;
+  currency: string;
+  product_id: string;
+  variant_id: string | null;
+  price: number;
+  compare_at_price: number | null;
+  created_at: string;
+  updated_at: string;
+}
+
 export interface CategorySummary {
   id: string;
   name: string;
@@ -32,14 +78,29 @@ export interface ProductImage {
   created_at: string;
 }
 
+export interface QuantityRules {
+  min_quantity: number | null;
+  max_quantity: number | null;
+  quantity_step: number | null;
+  max_per_customer: number | null;
+}
+
 export interface ProductVariant {
   id: string;
   product_id: string;
   name: string;
   sku: string | null;
-  price: number;
-  compare_at_price: number | null;
+  price: Money;
+  compare_at_price: Money | null;
+  /** The sale behind a lowered price */
+  sale: SaleSummary | null;
+  /** The customer group's prices for this variant, or the product's */
+  price_tiers: PriceTier[];
   stock_quantity: number;
+  min_quantity: number | null;
+  max_quantity: number | null;
+  quantity_step: number | null;
+  max_per_customer: number | null;
   attributes: Record<string, unknown>;
   sort_order: number;
   is_active: boolean;
@@ -47,22 +108,104 @@ export interface ProductVariant {
   updated_at: string;
 }
 
+export interface PackageDimensions {
+  weight_grams: number | null;
+  length_mm: number | null;
+  width_mm: number | null;
+  height_mm: number | null;
+}
+
+export type TaxClass = 'standard' | 'reduced' | 'exempt';
+
+export interface TaxLine {
+  rate_id: string;
+  name: string;
+  country: string;
+  region: string | null;
+  /** Parts per million (21% = 210000) */
+  rate_ppm: number;
+  taxable_amount: number;
+  amount: number;
+}
+
+export interface TaxRate {
+  id: string;
+  store_id: string;
+  name: string;
+  country: string;
+  region: string | null;
+  postcode_patterns: string[];
+  tax_class: TaxClass;
+  rate_ppm: number;
+  priority: number;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export type TaxExemptionStatus = 'pending' | 'approved' | 'rejected' | 'revoked';
+
+export interface TaxExemption {
+  id: string;
+  store_id: string;
+  user_id: string;
+  status: TaxExemptionStatus;
+  certificate_number: string | null;
+  content_type: string;
+  size_bytes: number;
+  expires_at: string;
+  note: string | null;
+  reviewed_by: string | null;
+  reviewed_at: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface TaxReportRow {
+  currency: string;
+  country: string;
+  region: string | null;
+  name: string;
+  rate_ppm: number;
+  order_count: number;
+  taxable_amount: number;
+  tax_amount: number;
+}
+
+export interface TaxReport {
+  from: string;
+  to: string;
+  rows: TaxReportRow[];
+  exempt_sales: { currency: string; order_count: number; amount: number }[];
+}
+
 export interface Product {
   id: string;
   name: string;
   slug: string;
   description: string | null;
   short_description: string | null;
-  price: number;
-  compare_at_price: number | null;
+  price: Money;
+  compare_at_price: Money | null;
+  /** The sale behind a lowered price */
+  sale: SaleSummary | null;
+  /** Unit prices by quantity for the customer's group; empty without group prices */
+  price_tiers: PriceTier[];
   status: ProductStatus;
   is_featured: boolean;
   sku: string | null;
   stock_quantity: number;
+  quantity_rules: QuantityRules;
   attributes: Record<string, unknown>;
   category: CategorySummary | null;
   images: ProductImage[];
   variants: ProductVariant[];
+  dimensions: PackageDimensions;
+  tax_class: TaxClass;
+  /** Each unit bought issues a gift card worth its price */
+  is_gift_card: boolean;
+  /** Days a purchased gift card stays valid; null for no expiry */
+  gift_card_validity_days: number | null;
   created_at: string;
   updated_at: string;
 }
@@ -86,6 +229,8 @@ export interface ProductListParams {
   category_id?: string;
   sort?: ProductSort;
   q?: string;
+  /** Show prices in this currency instead of the store's */
+  currency?: string;
 }
 
 export interface CartItemResponse {
@@ -96,15 +241,62 @@ export interface CartItemResponse {
   product_slug: string;
   product_image_url: string | null;
   variant_name: string | null;
-  price: number;
+  properties: Record<string, unknown>;
+  price: Money;
+  /** Regular unit price while `price` is a sale price */
+  compare_at_price: Money | null;
+  /** Units at the sale price; the rest are at `compare_at_price` */
+  sale_quantity: number | null;
   quantity: number;
-  subtotal: number;
+  subtotal: Money;
+  discount_total: Money;
+  tax_total: Money;
+  tax_lines: TaxLine[];
+}
+
+export interface AppliedCoupon {
+  code: string;
+  description: string | null;
+  /** False when the coupon no longer applies; `message` says why */
+  applied: boolean;
+  message: string | null;
+  /** What the coupon does, e.g. "10% off your order" */
+  explanation: string | null;
+  discount_total: Money;
+  free_shipping: boolean;
+}
+
+export interface AppliedPromotion {
+  promotion_id: string;
+  name: string;
+  /** E.g. "Buy 2, get 1 free: 1 × Tube free" */
+  explanation: string;
+  discount_total: Money;
+}
+
+export interface FreeItem {
+  promotion_id: string;
+  product_id: string;
+  variant_id: string | null;
+  name: string;
+  quantity: number;
+  value: Money;
 }
 
 export interface CartResponse {
-  id: string;
+  id: string | null;
+  email: string | null;
   items: CartItemResponse[];
-  total: number;
+  subtotal: Money;
+  discount_total: Money;
+  promotions: AppliedPromotion[];
+  free_items: FreeItem[];
+  coupon: AppliedCoupon | null;
+  tax_total: Money;
+  tax_lines: TaxLine[];
+  prices_include_tax: boolean;
+  tax_exempt: boolean;
+  total: Money;
   item_count: number;
 }
 
@@ -112,8 +304,629 @@ export interface AddToCartRequest {
   product_id: string;
   variant_id?: string | null;
   quantity: number;
+  properties?: Record<string, unknown>;
 }
 
 export interface UpdateCartItemRequest {
   quantity: number;
 }
+
+export type OrderStatus =
+  | 'pending'
+  | 'paid'
+  | 'partially_fulfilled'
+  | 'fulfilled'
+  | 'completed'
+  | 'cancelled'
+  | 'refunded';
+
+export type OrderAction =
+  | 'place'
+  | 'pay'
+  | 'partially_fulfill'
+  | 'fulfill'
+  | 'complete'
+  | 'cancel'
+  | 'refund';
+
+export type OrderActor = 'customer' | 'admin' | 'system';
+
+export type PaymentStatus =
+  | 'unpaid'
+  | 'authorized'
+  | 'paid'
+  | 'partially_refunded'
+  | 'refunded';
+
+export interface Address {
+  name: string;
+  company?: string | null;
+  line1: string;
+  line2?: string | null;
+  city: string;
+  region?: string | null;
+  postal_code: string;
+  country: string;
+  phone?: string | null;
+}
+
+export interface OrderItem {
+  id: string;
+  order_id: string;
+  product_id: string | null;
+  variant_id: string | null;
+  product_name: string;
+  variant_name: string | null;
+  sku: string | null;
+  properties: Record<string, unknown>;
+  unit_price: Money;
+  price_modifier: Money;
+  quantity: number;
+  total: Money;
+  discount_total: Money;
+  tax_class: TaxClass;
+  tax_total: Money;
+  tax_lines: TaxLine[];
+  created_at: string;
+}
+
+export interface Order {
+  id: string;
+  store_id: string;
+  user_id: string | null;
+  cart_id: string | null;
+  order_number: number;
+  status: OrderStatus;
+  payment_status: PaymentStatus;
+  email: string;
+  currency: string;
+  subtotal: Money;
+  discount_total: Money;
+  coupon_code: string | null;
+  promotions: AppliedPromotion[];
+  shipping_total: Money;
+  total: Money;
+  /** Part of the total paid with gift cards; the rest is due from the payment */
+  gift_card_total: Money;
+  prices_include_tax: boolean;
+  tax_total: Money;
+  shipping_tax: Money;
+  tax_lines: TaxLine[];
+  tax_exemption_id: string | null;
+  item_count: number;
+  shipping_address: Address | null;
+  billing_address: Address | null;
+  notes: string | null;
+  shipping_method: string | null;
+  custom_fields: Record<string, unknown>;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface OrderResponse extends Order {
+  items: OrderItem[];
+  allowed_actions: OrderAction[];
+}
+
+export interface OrderEvent {
+  id: string;
+  order_id: string;
+  from_status: OrderStatus | null;
+  to_status: OrderStatus;
+  action: OrderAction;
+  actor: OrderActor;
+  actor_id: string | null;
+  reason: string | null;
+  created_at: string;
+}
+
+export type PaymentState =
+  | 'pending'
+  | 'requires_action'
+  | 'authorized'
+  | 'captured'
+  | 'voided'
+  | 'failed';
+
+export interface Payment {
+  id: string;
+  order_id: string;
+  provider: string;
+  provider_payment_id: string | null;
+  status: PaymentState;
+  currency: string;
+  amount: Money;
+  amount_captured: Money;
+  amount_refunded: Money;
+  failure_reason: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PaymentResponse extends Payment {
+  /** Only returned when the payment is created */
+  client_secret?: string;
+}
+
+export interface PaymentAttempt {
+  id: string;
+  payment_id: string;
+  operation: 'authorize' | 'capture' | 'void' | 'refund';
+  amount: number;
+  status: 'pending' | 'succeeded' | 'failed';
+  provider_reference: string | null;
+  error: string | null;
+  created_at: string;
+  completed_at: string | null;
+}
+
+export type RefundStatus = 'pending' | 'succeeded' | 'failed';
+
+export interface RefundItem {
+  id: string;
+  refund_id: string;
+  order_item_id: string;
+  quantity: number;
+  amount: number;
+}
+
+export interface Refund {
+  id: string;
+  order_id: string;
+  payment_id: string;
+  status: RefundStatus;
+  currency: string;
+  amount: Money;
+  shipping_amount: Money;
+  reason: string;
+  restock: boolean;
+  provider_refund_id: string | null;
+  failure_reason: string | null;
+  created_by: string | null;
+  created_at: string;
+  updated_at: string;
+  items: RefundItem[];
+}
+
+export type FulfillmentStatus = 'pending' | 'shipped' | 'delivered' | 'cancelled';
+
+export type TrackingStatus =
+  | 'pre_transit'
+  | 'in_transit'
+  | 'out_for_delivery'
+  | 'delivered'
+  | 'exception'
+  | 'returned_to_sender'
+  | 'unknown';
+
+export type LabelFormat = 'pdf' | 'zpl';
+
+export type ShippingLabelStatus = 'pending' | 'purchased' | 'failed' | 'voided';
+
+export interface ShippingLabel {
+  id: string;
+  fulfillment_id: string;
+  carrier: string;
+  service_code: string;
+  carrier_shipment_id: string | null;
+  tracking_number: string | null;
+  status: ShippingLabelStatus;
+  format: LabelFormat;
+  amount: number | null;
+  currency: string | null;
+  error: string | null;
+  created_by: string | null;
+  created_at: string;
+  updated_at: string;
+  voided_at: string | null;
+}
+
+export interface CarrierRate {
+  service_code: string;
+  service_name: string;
+  amount: number;
+  currency: string;
+  estimated_days: number | null;
+}
+
+export interface Carrier {
+  id: string;
+  store_id: string;
+  code: string;
+  name: string;
+  tracking_url_template: string | null;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface FulfillmentItem {
+  id: string;
+  fulfillment_id: string;
+  order_item_id: string;
+  quantity: number;
+}
+
+export interface Fulfillment {
+  id: string;
+  order_id: string;
+  status: FulfillmentStatus;
+  carrier: string | null;
+  tracking_number: string | null;
+  tracking_url: string | null;
+  shipped_at: string | null;
+  delivered_at: string | null;
+  created_by: string | null;
+  created_at: string;
+  updated_at: string;
+  tracking_status: TrackingStatus | null;
+  tracking_checked_at: string | null;
+  items: FulfillmentItem[];
+  label?: ShippingLabel;
+}
+
+export interface OrderTracking {
+  order_id: string;
+  order_number: number;
+  status: OrderStatus;
+  shipments: Fulfillment[];
+  updated_at: string;
+}
+
+export type ShippingMethodKind =
+  | 'flat_rate'
+  | 'weight_tiers'
+  | 'price_tiers'
+  | 'free'
+  | 'local_pickup'
+  | 'carrier';
+
+export interface RateTier {
+  up_to: number | null;
+  rate: number;
+}
+
+export interface ShippingZone {
+  id: string;
+  store_id: string;
+  name: string;
+  countries: string[];
+  regions: string[];
+  postcode_patterns: string[];
+  priority: number;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface ShippingMethod {
+  id: string;
+  zone_id: string;
+  code: string;
+  name: string;
+  description: string | null;
+  kind: ShippingMethodKind;
+  rate: number;
+  tiers: RateTier[];
+  min_subtotal: number | null;
+  sort_order: number;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+  carrier_code: string | null;
+  service_code: string | null;
+}
+
+export interface ShippingZoneWithMethods extends ShippingZone {
+  methods: ShippingMethod[];
+}
+
+export interface ShippingRate {
+  code: string;
+  name: string;
+  description: string | null;
+  kind: ShippingMethodKind;
+  amount: Money;
+  zone_id: string;
+}
+
+export type ReturnStatus = 'requested' | 'approved' | 'rejected' | 'received' | 'closed';
+
+export type ReturnReason =
+  | 'damaged'
+  | 'defective'
+  | 'missing_parts'
+  | 'wrong_item'
+  | 'not_as_described'
+  | 'changed_mind'
+  | 'other';
+
+export type ItemCondition = 'unopened' | 'like_new' | 'used' | 'damaged';
+
+export type ReturnResolution = 'refund' | 'exchange' | 'store_credit';
+
+export interface ReturnItem {
+  id: string;
+  return_id: string;
+  order_item_id: string;
+  quantity: number;
+}
+
+export interface ReturnPhoto {
+  id: string;
+  return_id: string;
+  content_type: string;
+  size_bytes: number;
+  created_at: string;
+}
+
+export interface Return {
+  id: string;
+  store_id: string;
+  order_id: string;
+  user_id: string | null;
+  rma_number: number;
+  status: ReturnStatus;
+  reason: ReturnReason;
+  comment: string | null;
+  decision_note: string | null;
+  decided_by: string | null;
+  decided_at: string | null;
+  received_at: string | null;
+  condition: ItemCondition | null;
+  condition_note: string | null;
+  resolution: ReturnResolution | null;
+  refund_id: string | null;
+  credit_amount: number | null;
+  /** Gift card the store credit was issued on */
+  gift_card_id: string | null;
+  resolution_note: string | null;
+  closed_at: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface ReturnResponse extends Return {
+  items: ReturnItem[];
+  photos: ReturnPhoto[];
+}
+
+export type CheckoutStep =
+  | 'cart'
+  | 'contact'
+  | 'shipping'
+  | 'shipping_method'
+  | 'payment'
+  | 'confirmation';
+
+export interface CheckoutField {
+  key: string;
+  label: string | null;
+  type: 'text' | 'number' | 'boolean' | 'enum' | 'date' | 'json';
+  required: boolean;
+  max_length: number | null;
+  min: number | null;
+  max: number | null;
+  options: { value: string; price_modifier: number }[];
+  price_modifier: number;
+}
+
+export interface CheckoutStepState {
+  step: CheckoutStep;
+  completed: boolean;
+  fields: CheckoutField[];
+}
+
+export interface CheckoutSessionResponse {
+  id: string;
+  store_id: string;
+  cart_id: string;
+  user_id: string | null;
+  status: 'open' | 'completed';
+  completed_steps: CheckoutStep[];
+  email: string | null;
+  shipping_address: Address | null;
+  billing_address: Address | null;
+  shipping_method: string | null;
+  payment_method: string | null;
+  /** Gift card codes to redeem when the order is placed */
+  gift_card_codes: string[];
+  custom_fields: Record<string, unknown>;
+  order_id: string | null;
+  expires_at: string;
+  created_at: string;
+  updated_at: string;
+  steps: CheckoutStepState[];
+  current_step: CheckoutStep;
+  cart: CartResponse;
+}
+
+export type CouponKind = 'percentage' | 'fixed_amount' | 'free_shipping';
+
+export interface Coupon {
+  id: string;
+  store_id: string;
+  code: string | null;
+  description: string | null;
+  kind: CouponKind;
+  percentage_ppm: number | null;
+  amount: number | null;
+  min_subtotal: number | null;
+  product_ids: string[];
+  category_ids: string[];
+  starts_at: string | null;
+  ends_at: string | null;
+  usage_limit: number | null;
+  usage_limit_per_customer: number | null;
+  times_used: number;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PromotionConditions {
+  product_ids: string[];
+  category_ids: string[];
+  min_subtotal: number | null;
+  min_quantity: number | null;
+  customer_groups: string[];
+}
+
+export interface SpendTier {
+  min_subtotal: number;
+  percentage_ppm: number | null;
+  amount: number | null;
+}
+
+export type PromotionAction =
+  | { type: 'percent_off'; percentage_ppm: number }
+  | { type: 'amount_off'; amount: number }
+  | { type: 'buy_x_get_y'; buy: number; get: number; percentage_ppm: number }
+  | { type: 'spend_tiers'; tiers: SpendTier[] }
+  | { type: 'free_gift'; product_id: string; variant_id: string | null; quantity: number };
+
+export interface Promotion {
+  id: string;
+  store_id: string;
+  name: string;
+  priority: number;
+  exclusive: boolean;
+  combines_with_coupons: boolean;
+  conditions: PromotionConditions;
+  action: PromotionAction;
+  starts_at: string | null;
+  ends_at: string | null;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CouponCode {
+  id: string;
+  store_id: string;
+  coupon_id: string;
+  code: string;
+  used_at: string | null;
+  created_at: string;
+}
+
+export interface SaleSummary {
+  sale_id: string;
+  name: string;
+  ends_at: string;
+  /** Units left at the sale price, when capped */
+  remaining: number | null;
+}
+
+export interface SaleItem {
+  id: string;
+  sale_id: string;
+  product_id: string;
+  /** Null for the product and all its variants */
+  variant_id: string | null;
+  sale_price: number | null;
+  percentage_ppm: number | null;
+  quantity_limit: number | null;
+  sold_quantity: number;
+  created_at: string;
+}
+
+export interface Sale {
+  id: string;
+  store_id: string;
+  name: string;
+  percentage_ppm: number | null;
+  starts_at: string;
+  ends_at: string;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface SaleResponse extends Sale {
+  items: SaleItem[];
+}
+
+export interface PriceTier {
+  min_quantity: number;
+  /** Null for no upper bound */
+  max_quantity: number | null;
+  price: Money;
+}
+
+export interface CustomerGroup {
+  id: string;
+  store_id: string;
+  /** What customers and promotion conditions refer to, e.g. "wholesale" */
+  code: string;
+  name: string;
+  description: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface GroupPrice {
+  id: string;
+  store_id: string;
+  customer_group_id: string;
+  product_id: string;
+  /** Null for the product and all its variants */
+  variant_id: string | null;
+  min_quantity: number;
+  price: number | null;
+  percentage_ppm: number | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export type GiftCardSource = 'purchase' | 'admin' | 'store_credit';
+
+export type GiftCardStatus = 'active' | 'disabled' | 'expired' | 'spent';
+
+export type GiftCardTransactionKind = 'issue' | 'redeem' | 'release' | 'adjust' | 'expire';
+
+export interface GiftCard {
+  id: string;
+  store_id: string;
+  code: string;
+  currency: string;
+  /** Minor units of the card currency */
+  initial_amount: number;
+  balance: number;
+  /** Null for cards that never expire */
+  expires_at: string | null;
+  is_active: boolean;
+  source: GiftCardSource;
+  order_id: string | null;
+  order_item_id: string | null;
+  return_id: string | null;
+  recipient_email: string | null;
+  note: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface GiftCardTransaction {
+  id: string;
+  gift_card_id: string;
+  kind: GiftCardTransactionKind;
+  /** Signed: positive adds to the balance */
+  amount: number;
+  balance_after: number;
+  order_id: string | null;
+  note: string | null;
+  created_by: string | null;
+  created_at: string;
+}
+
+export interface GiftCardResponse extends GiftCard {
+  status: GiftCardStatus;
+  transactions: GiftCardTransaction[];
+}
+
+export interface GiftCardBalance {
+  code: string;
+  balance: Money;
+  status: GiftCardStatus;
+  expires_at: string | null;
+}